@@ -0,0 +1,37 @@
+CREATE TABLE webhooks (
+    id           UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    url          TEXT        NOT NULL,
+    secret       TEXT        NOT NULL,
+    event_types  TEXT[]      NOT NULL,
+    description  TEXT,
+    active       BOOLEAN     NOT NULL DEFAULT TRUE,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+SELECT diesel_manage_updated_at('webhooks');
+
+CREATE TABLE webhook_deliveries (
+    id                UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    webhook_id        UUID        NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+    event_type        TEXT        NOT NULL,
+    payload           JSONB       NOT NULL,
+    status            TEXT        NOT NULL DEFAULT 'pending',
+    attempts          INTEGER     NOT NULL DEFAULT 0,
+    last_status_code  INTEGER,
+    last_error        TEXT,
+    next_attempt_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at      TIMESTAMPTZ,
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at        TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+SELECT diesel_manage_updated_at('webhook_deliveries');
+
+-- the dispatcher polls for due pending deliveries
+CREATE INDEX idx_webhook_deliveries_pending
+    ON webhook_deliveries (next_attempt_at)
+    WHERE status = 'pending';
+
+CREATE INDEX idx_webhook_deliveries_webhook_id
+    ON webhook_deliveries (webhook_id, created_at DESC);
@@ -0,0 +1,16 @@
+CREATE TABLE api_keys (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    name          TEXT        NOT NULL,
+    -- first characters of the key, safe to display for identification
+    key_prefix    TEXT        NOT NULL,
+    -- SHA-256 of the full key, the key itself is never stored
+    key_hash      TEXT        NOT NULL,
+    last_used_at  TIMESTAMPTZ,
+    revoked_at    TIMESTAMPTZ,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_api_keys_key_hash ON api_keys (key_hash);
+
+ALTER TABLE query_history
+    ADD COLUMN api_key_id UUID REFERENCES api_keys (id) ON DELETE SET NULL;
@@ -0,0 +1,5 @@
+-- Keys issued before roles existed could only query data, so they become
+-- read-only keys.
+ALTER TABLE api_keys
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'read'
+        CHECK (role IN ('read', 'ingest', 'admin'));
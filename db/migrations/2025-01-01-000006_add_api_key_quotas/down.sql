@@ -0,0 +1,3 @@
+ALTER TABLE api_keys
+    DROP COLUMN IF EXISTS daily_quota,
+    DROP COLUMN IF EXISTS monthly_quota;
@@ -0,0 +1,5 @@
+-- Request quotas per UTC day / calendar month, NULL means unlimited.
+-- Consumption is tracked in Redis.
+ALTER TABLE api_keys
+    ADD COLUMN daily_quota   BIGINT CHECK (daily_quota > 0),
+    ADD COLUMN monthly_quota BIGINT CHECK (monthly_quota > 0);
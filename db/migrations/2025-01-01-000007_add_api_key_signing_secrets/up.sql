@@ -0,0 +1,4 @@
+-- HMAC secret for signed requests to ingestion endpoints. Unlike the key
+-- itself it must be readable to verify signatures. Keys issued before this
+-- migration have none and cannot call signed endpoints.
+ALTER TABLE api_keys ADD COLUMN signing_secret TEXT;
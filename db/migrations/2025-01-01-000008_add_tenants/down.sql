@@ -0,0 +1,12 @@
+DROP INDEX IF EXISTS idx_query_history_tenant_created_at;
+CREATE INDEX idx_query_history_created_at
+    ON query_history (created_at DESC);
+
+DROP INDEX IF EXISTS idx_energy_readings_tenant_reading_time;
+DELETE FROM energy_readings WHERE tenant_id <> 'default';
+CREATE UNIQUE INDEX idx_energy_readings_reading_time
+    ON energy_readings (reading_time);
+
+ALTER TABLE query_history DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE energy_readings DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE api_keys DROP COLUMN IF EXISTS tenant_id;
@@ -0,0 +1,19 @@
+-- Column tenancy: every API key, reading and history entry belongs to a
+-- tenant. Existing rows and single-tenant deployments use 'default'.
+ALTER TABLE api_keys
+    ADD COLUMN tenant_id TEXT NOT NULL DEFAULT 'default';
+
+ALTER TABLE energy_readings
+    ADD COLUMN tenant_id TEXT NOT NULL DEFAULT 'default';
+
+ALTER TABLE query_history
+    ADD COLUMN tenant_id TEXT NOT NULL DEFAULT 'default';
+
+-- one reading per hour per tenant
+DROP INDEX idx_energy_readings_reading_time;
+CREATE UNIQUE INDEX idx_energy_readings_tenant_reading_time
+    ON energy_readings (tenant_id, reading_time);
+
+DROP INDEX idx_query_history_created_at;
+CREATE INDEX idx_query_history_tenant_created_at
+    ON query_history (tenant_id, created_at DESC);
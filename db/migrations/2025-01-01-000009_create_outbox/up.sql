@@ -0,0 +1,18 @@
+-- Events are written in the same transaction as the change they describe
+-- and published afterwards by the relay, so none are lost or sent for
+-- changes that were rolled back.
+CREATE TABLE outbox (
+    id            BIGSERIAL   PRIMARY KEY,
+    event_type    TEXT        NOT NULL,
+    tenant_id     TEXT        NOT NULL,
+    payload       JSONB       NOT NULL,
+    attempts      INTEGER     NOT NULL DEFAULT 0,
+    last_error    TEXT,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    published_at  TIMESTAMPTZ
+);
+
+-- the relay reads unpublished events in id order
+CREATE INDEX idx_outbox_unpublished
+    ON outbox (id)
+    WHERE published_at IS NULL;
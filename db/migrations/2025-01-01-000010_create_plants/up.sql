@@ -0,0 +1,17 @@
+CREATE TABLE plants (
+    id           UUID           PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id    TEXT           NOT NULL DEFAULT 'default',
+    name         TEXT           NOT NULL,
+    energy_type  TEXT           NOT NULL,
+    capacity_mw  NUMERIC(10, 3) NOT NULL CHECK (capacity_mw > 0),
+    location     TEXT,
+    status       TEXT           NOT NULL DEFAULT 'active',
+    created_at   TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    CHECK (energy_type IN ('solar', 'wind', 'hydro', 'battery', 'other')),
+    CHECK (status IN ('active', 'inactive', 'maintenance', 'decommissioned'))
+);
+
+SELECT diesel_manage_updated_at('plants');
+
+CREATE INDEX idx_plants_tenant_name ON plants (tenant_id, name);
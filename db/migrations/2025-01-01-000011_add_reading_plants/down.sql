@@ -0,0 +1,15 @@
+DROP INDEX IF EXISTS idx_energy_readings_plant_reading_time;
+DROP INDEX IF EXISTS idx_energy_readings_tenant_reading_time;
+DROP INDEX IF EXISTS idx_energy_readings_tenant_plant_reading_time;
+
+-- Keep one reading per hour per tenant, preferring unattributed ones
+DELETE FROM energy_readings r
+USING energy_readings other
+WHERE r.tenant_id = other.tenant_id
+  AND r.reading_time = other.reading_time
+  AND r.plant_id IS NOT NULL
+  AND (other.plant_id IS NULL OR other.id < r.id);
+CREATE UNIQUE INDEX idx_energy_readings_tenant_reading_time
+    ON energy_readings (tenant_id, reading_time);
+
+ALTER TABLE energy_readings DROP COLUMN IF EXISTS plant_id;
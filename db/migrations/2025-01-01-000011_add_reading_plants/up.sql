@@ -0,0 +1,14 @@
+-- Readings can be attributed to a plant. Unattributed readings keep a NULL
+-- plant_id, and a plant's deletion leaves its readings unattributed.
+ALTER TABLE energy_readings
+    ADD COLUMN plant_id UUID REFERENCES plants (id) ON DELETE SET NULL;
+
+-- one reading per hour per tenant and plant
+DROP INDEX idx_energy_readings_tenant_reading_time;
+CREATE UNIQUE INDEX idx_energy_readings_tenant_plant_reading_time
+    ON energy_readings (tenant_id, plant_id, reading_time) NULLS NOT DISTINCT;
+CREATE INDEX idx_energy_readings_tenant_reading_time
+    ON energy_readings (tenant_id, reading_time, id);
+CREATE INDEX idx_energy_readings_plant_reading_time
+    ON energy_readings (plant_id, reading_time)
+    WHERE plant_id IS NOT NULL;
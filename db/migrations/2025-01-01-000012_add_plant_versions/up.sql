@@ -0,0 +1,4 @@
+-- Incremented on every update; updates name the version they were based on
+-- so concurrent edits are detected instead of overwriting each other.
+ALTER TABLE plants
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
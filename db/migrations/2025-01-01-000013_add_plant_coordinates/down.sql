@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_plants_tenant_latitude;
+ALTER TABLE plants RENAME COLUMN address TO location;
+ALTER TABLE plants
+    DROP CONSTRAINT IF EXISTS plants_coordinates_check,
+    DROP COLUMN IF EXISTS longitude,
+    DROP COLUMN IF EXISTS latitude;
@@ -0,0 +1,15 @@
+-- Plants are located by WGS 84 coordinates; the former free-text location
+-- becomes the address.
+ALTER TABLE plants
+    ADD COLUMN latitude DOUBLE PRECISION
+        CHECK (latitude BETWEEN -90 AND 90),
+    ADD COLUMN longitude DOUBLE PRECISION
+        CHECK (longitude BETWEEN -180 AND 180),
+    ADD CONSTRAINT plants_coordinates_check
+        CHECK ((latitude IS NULL) = (longitude IS NULL));
+
+ALTER TABLE plants RENAME COLUMN location TO address;
+
+-- radius searches narrow the tenant's plants by latitude first
+CREATE INDEX idx_plants_tenant_latitude ON plants (tenant_id, latitude)
+    WHERE latitude IS NOT NULL;
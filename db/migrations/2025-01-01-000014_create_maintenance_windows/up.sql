@@ -0,0 +1,31 @@
+-- Planned maintenance of a plant. The scheduler keeps `status` in step with
+-- the clock: `scheduled` before `starts_at`, `active` until `ends_at`, then
+-- `completed`. While a window is active its plant's status is
+-- `maintenance`; `previous_plant_status` holds the status to restore.
+CREATE TABLE maintenance_windows (
+    id                     UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id              TEXT        NOT NULL,
+    plant_id               UUID        NOT NULL REFERENCES plants (id) ON DELETE CASCADE,
+    starts_at              TIMESTAMPTZ NOT NULL,
+    ends_at                TIMESTAMPTZ NOT NULL,
+    notes                  TEXT,
+    status                 TEXT        NOT NULL DEFAULT 'scheduled',
+    previous_plant_status  TEXT,
+    created_at             TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at             TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at),
+    CHECK (status IN ('scheduled', 'active', 'completed'))
+);
+
+SELECT diesel_manage_updated_at('maintenance_windows');
+
+CREATE INDEX idx_maintenance_windows_tenant_starts_at
+    ON maintenance_windows (tenant_id, starts_at);
+
+CREATE INDEX idx_maintenance_windows_plant_id
+    ON maintenance_windows (plant_id, status);
+
+-- the scheduler polls for windows to start or end
+CREATE INDEX idx_maintenance_windows_open
+    ON maintenance_windows (starts_at)
+    WHERE status <> 'completed';
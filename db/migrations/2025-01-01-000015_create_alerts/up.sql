@@ -0,0 +1,56 @@
+-- Threshold rules on a tenant's readings. The evaluator computes `metric`
+-- over the readings of the last `window_minutes` (of one plant when
+-- `plant_id` is set) and compares it with `threshold`. `firing` is set
+-- while the rule is breached, so each breach fires once.
+CREATE TABLE alert_rules (
+    id              UUID           PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id       TEXT           NOT NULL,
+    name            TEXT           NOT NULL,
+    plant_id        UUID           REFERENCES plants (id) ON DELETE CASCADE,
+    metric          TEXT           NOT NULL,
+    comparison      TEXT           NOT NULL,
+    threshold       NUMERIC(20, 4) NOT NULL,
+    window_minutes  INTEGER        NOT NULL,
+    channel         TEXT           NOT NULL,
+    email_to        TEXT,
+    enabled         BOOLEAN        NOT NULL DEFAULT TRUE,
+    firing          BOOLEAN        NOT NULL DEFAULT FALSE,
+    evaluated_at    TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    CHECK (metric IN ('total_kwh', 'average_kwh', 'max_kwh', 'min_kwh', 'reading_count')),
+    CHECK (comparison IN ('gt', 'gte', 'lt', 'lte')),
+    CHECK (window_minutes > 0),
+    CHECK (channel IN ('webhook', 'email')),
+    CHECK ((channel = 'email') = (email_to IS NOT NULL))
+);
+
+SELECT diesel_manage_updated_at('alert_rules');
+
+CREATE INDEX idx_alert_rules_tenant_name ON alert_rules (tenant_id, name);
+
+-- the evaluator polls enabled rules, least recently evaluated first
+CREATE INDEX idx_alert_rules_due
+    ON alert_rules (evaluated_at NULLS FIRST)
+    WHERE enabled;
+
+-- One row per breach of a rule, resolved when the value recovers.
+CREATE TABLE alerts (
+    id                  UUID           PRIMARY KEY DEFAULT gen_random_uuid(),
+    rule_id             UUID           NOT NULL REFERENCES alert_rules (id) ON DELETE CASCADE,
+    tenant_id           TEXT           NOT NULL,
+    value               NUMERIC(20, 4) NOT NULL,
+    threshold           NUMERIC(20, 4) NOT NULL,
+    window_start        TIMESTAMPTZ    NOT NULL,
+    window_end          TIMESTAMPTZ    NOT NULL,
+    fired_at            TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    resolved_at         TIMESTAMPTZ,
+    acknowledged_at     TIMESTAMPTZ,
+    acknowledged_by     TEXT,
+    notified_at         TIMESTAMPTZ,
+    notification_error  TEXT
+);
+
+CREATE INDEX idx_alerts_tenant_fired_at ON alerts (tenant_id, fired_at DESC);
+
+CREATE INDEX idx_alerts_rule_open ON alerts (rule_id) WHERE resolved_at IS NULL;
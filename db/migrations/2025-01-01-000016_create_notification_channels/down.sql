@@ -0,0 +1,23 @@
+ALTER TABLE alerts
+    DROP COLUMN notification_id,
+    ADD COLUMN notified_at TIMESTAMPTZ,
+    ADD COLUMN notification_error TEXT;
+
+ALTER TABLE alert_rules
+    ADD COLUMN channel TEXT NOT NULL DEFAULT 'webhook',
+    ADD COLUMN email_to TEXT;
+
+UPDATE alert_rules
+SET channel = 'email', email_to = notification_channels.target
+FROM notification_channels
+WHERE notification_channels.id = alert_rules.channel_id
+  AND notification_channels.kind = 'email';
+
+ALTER TABLE alert_rules
+    ALTER COLUMN channel DROP DEFAULT,
+    ADD CHECK (channel IN ('webhook', 'email')),
+    ADD CHECK ((channel = 'email') = (email_to IS NOT NULL)),
+    DROP COLUMN channel_id;
+
+DROP TABLE notifications;
+DROP TABLE notification_channels;
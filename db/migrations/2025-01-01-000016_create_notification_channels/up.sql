@@ -0,0 +1,76 @@
+-- Where notifications are delivered: a JSON webhook, a Slack-compatible
+-- incoming webhook, or email recipients. `events` lists the system events
+-- (`import_failed`, `health_degraded`) sent to the channel; alert rules
+-- name their channel themselves.
+CREATE TABLE notification_channels (
+    id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id   TEXT        NOT NULL,
+    name        TEXT        NOT NULL,
+    kind        TEXT        NOT NULL,
+    -- URL for webhook and slack, comma separated recipients for email
+    target      TEXT        NOT NULL,
+    events      TEXT[]      NOT NULL DEFAULT '{}',
+    enabled     BOOLEAN     NOT NULL DEFAULT TRUE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (kind IN ('webhook', 'slack', 'email'))
+);
+
+SELECT diesel_manage_updated_at('notification_channels');
+
+CREATE INDEX idx_notification_channels_tenant_name
+    ON notification_channels (tenant_id, name);
+
+-- Queue of notifications, sent and retried by the notification dispatcher.
+CREATE TABLE notifications (
+    id               UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    channel_id       UUID        NOT NULL REFERENCES notification_channels (id) ON DELETE CASCADE,
+    tenant_id        TEXT        NOT NULL,
+    event            TEXT        NOT NULL,
+    subject          TEXT        NOT NULL,
+    body             TEXT        NOT NULL,
+    data             JSONB       NOT NULL DEFAULT '{}',
+    status           TEXT        NOT NULL DEFAULT 'pending',
+    attempts         INTEGER     NOT NULL DEFAULT 0,
+    last_error       TEXT,
+    next_attempt_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at     TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (status IN ('pending', 'in_flight', 'delivered', 'failed'))
+);
+
+SELECT diesel_manage_updated_at('notifications');
+
+CREATE INDEX idx_notifications_channel_created_at
+    ON notifications (channel_id, created_at DESC);
+
+-- the dispatcher polls for due notifications
+CREATE INDEX idx_notifications_due
+    ON notifications (next_attempt_at)
+    WHERE status IN ('pending', 'in_flight');
+
+-- Alert rules notify a channel instead of choosing webhook or email; every
+-- firing still emits a `threshold_breached` event to subscribed webhooks.
+ALTER TABLE alert_rules
+    ADD COLUMN channel_id UUID REFERENCES notification_channels (id) ON DELETE SET NULL;
+
+INSERT INTO notification_channels (tenant_id, name, kind, target)
+SELECT DISTINCT tenant_id, 'Email ' || email_to, 'email', email_to
+FROM alert_rules
+WHERE channel = 'email';
+
+UPDATE alert_rules
+SET channel_id = notification_channels.id
+FROM notification_channels
+WHERE alert_rules.channel = 'email'
+  AND notification_channels.tenant_id = alert_rules.tenant_id
+  AND notification_channels.kind = 'email'
+  AND notification_channels.target = alert_rules.email_to;
+
+ALTER TABLE alert_rules DROP COLUMN channel, DROP COLUMN email_to;
+
+ALTER TABLE alerts
+    DROP COLUMN notified_at,
+    DROP COLUMN notification_error,
+    ADD COLUMN notification_id UUID REFERENCES notifications (id) ON DELETE SET NULL;
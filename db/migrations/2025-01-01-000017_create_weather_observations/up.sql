@@ -0,0 +1,19 @@
+-- Hourly weather at plants with coordinates, imported from a weather
+-- provider. Each import overwrites the hours it covers, so forecasts turn
+-- into observations once their hour has passed.
+CREATE TABLE weather_observations (
+    plant_id         UUID             NOT NULL REFERENCES plants (id) ON DELETE CASCADE,
+    observed_at      TIMESTAMPTZ      NOT NULL,
+    tenant_id        TEXT             NOT NULL,
+    temperature_c    DOUBLE PRECISION,
+    irradiance_w_m2  DOUBLE PRECISION,
+    wind_speed_m_s   DOUBLE PRECISION,
+    forecast         BOOLEAN          NOT NULL,
+    source           TEXT             NOT NULL,
+    fetched_at       TIMESTAMPTZ      NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (plant_id, observed_at)
+);
+
+-- aggregates correlate a tenant's readings with the weather by period
+CREATE INDEX idx_weather_observations_tenant_observed_at
+    ON weather_observations (tenant_id, observed_at);
@@ -0,0 +1,11 @@
+-- Day-ahead spot prices per bidding zone and delivery hour, imported from a
+-- price feed. Prices are public market data, shared by every tenant.
+CREATE TABLE market_prices (
+    zone           TEXT           NOT NULL,
+    starts_at      TIMESTAMPTZ    NOT NULL,
+    price_per_mwh  NUMERIC(12, 4) NOT NULL,
+    currency       TEXT           NOT NULL,
+    source         TEXT           NOT NULL,
+    fetched_at     TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (zone, starts_at)
+);
@@ -0,0 +1,22 @@
+-- Energy a tenant, or one of its plants, aims at over a period, e.g. a
+-- monthly consumption budget or a yearly generation goal.
+CREATE TABLE energy_targets (
+    id            UUID           PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id     TEXT           NOT NULL,
+    plant_id      UUID           REFERENCES plants (id) ON DELETE CASCADE,
+    name          TEXT           NOT NULL,
+    period_start  TIMESTAMPTZ    NOT NULL,
+    period_end    TIMESTAMPTZ    NOT NULL,
+    target_kwh    NUMERIC(18, 4) NOT NULL,
+    created_at    TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    updated_at    TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    CHECK (period_end > period_start),
+    CHECK (target_kwh >= 0)
+);
+
+SELECT diesel_manage_updated_at('energy_targets');
+
+CREATE INDEX idx_energy_targets_tenant_period
+    ON energy_targets (tenant_id, period_start);
+
+CREATE INDEX idx_energy_targets_plant_id ON energy_targets (plant_id);
@@ -0,0 +1,17 @@
+-- One import of a readings file, so deliveries made of several files can be
+-- told apart and files already imported skipped.
+CREATE TABLE import_runs (
+    id           UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id    TEXT         NOT NULL,
+    file         TEXT         NOT NULL,
+    status       TEXT         NOT NULL DEFAULT 'running',
+    inserted     INTEGER,
+    total        INTEGER,
+    error        TEXT,
+    started_at   TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    finished_at  TIMESTAMPTZ,
+    CHECK (status IN ('running', 'completed', 'failed'))
+);
+
+CREATE INDEX idx_import_runs_tenant_file
+    ON import_runs (tenant_id, file, started_at DESC);
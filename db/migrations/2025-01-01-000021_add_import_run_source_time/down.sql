@@ -0,0 +1,4 @@
+ALTER TABLE import_runs
+    DROP COLUMN source_timezone,
+    DROP COLUMN dst_policy,
+    DROP COLUMN dst_adjusted;
@@ -0,0 +1,7 @@
+-- How the local times of a file were read: the timezone its times are in,
+-- the policy for times a DST change repeats or skips, and how many readings
+-- the policy decided.
+ALTER TABLE import_runs
+    ADD COLUMN source_timezone TEXT    NOT NULL DEFAULT 'UTC',
+    ADD COLUMN dst_policy      TEXT    NOT NULL DEFAULT 'earlier',
+    ADD COLUMN dst_adjusted    INTEGER;
@@ -0,0 +1,6 @@
+ALTER TABLE import_runs
+    DROP COLUMN rejected,
+    DROP COLUMN clamped,
+    DROP COLUMN flagged;
+
+ALTER TABLE energy_readings DROP COLUMN quality_code;
@@ -0,0 +1,9 @@
+-- Problem found with a stored quantity, e.g. `negative` or `outlier`, for
+-- readings kept under the `flag` quantity policy.
+ALTER TABLE energy_readings ADD COLUMN quality_code TEXT;
+
+-- Readings of an import run each quantity policy action affected.
+ALTER TABLE import_runs
+    ADD COLUMN rejected INTEGER,
+    ADD COLUMN clamped  INTEGER,
+    ADD COLUMN flagged  INTEGER;
@@ -0,0 +1,18 @@
+-- A readings file uploaded through `/admin/files`, in chunks that can be
+-- resumed from `received` bytes, then checked and imported.
+CREATE TABLE file_uploads (
+    id             UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id      TEXT         NOT NULL,
+    file_name      TEXT         NOT NULL,
+    size           BIGINT       NOT NULL,
+    sha256         TEXT         NOT NULL,
+    received       BIGINT       NOT NULL DEFAULT 0,
+    status         TEXT         NOT NULL DEFAULT 'uploading',
+    location       TEXT,
+    import_run_id  UUID         REFERENCES import_runs (id) ON DELETE SET NULL,
+    error          TEXT,
+    created_at     TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at     TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    CHECK (status IN ('uploading', 'importing', 'imported', 'failed')),
+    CHECK (received BETWEEN 0 AND size)
+);
@@ -0,0 +1 @@
+ALTER TABLE energy_readings DROP COLUMN source;
@@ -0,0 +1,7 @@
+-- Where a reading came from: a file import, a push to the ingest endpoint
+-- by a webhook, a Kafka bridge or a manual correction. Readings stored
+-- before sources were tracked all came from file imports or pushes; they
+-- are attributed to file imports.
+ALTER TABLE energy_readings
+    ADD COLUMN source TEXT NOT NULL DEFAULT 'file'
+    CHECK (source IN ('file', 'webhook', 'kafka', 'manual'));
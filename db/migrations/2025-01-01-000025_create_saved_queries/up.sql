@@ -0,0 +1,18 @@
+-- A named aggregation of a tenant, run again by id instead of each client
+-- repeating its configuration.
+CREATE TABLE saved_queries (
+    id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id   TEXT        NOT NULL,
+    name        TEXT        NOT NULL,
+    -- Body of `POST /energy/aggregate`
+    request     JSONB       NOT NULL,
+    -- Query parameters of `POST /energy/aggregate`
+    params      JSONB       NOT NULL DEFAULT '{}',
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+SELECT diesel_manage_updated_at('saved_queries');
+
+CREATE UNIQUE INDEX idx_saved_queries_tenant_name
+    ON saved_queries (tenant_id, name);
@@ -0,0 +1,15 @@
+-- Defaults of the requests made with an API key, applied when a request
+-- leaves them out.
+CREATE TABLE api_key_preferences (
+    api_key_id        UUID        PRIMARY KEY
+                                  REFERENCES api_keys (id) ON DELETE CASCADE,
+    -- IANA time zone, e.g. Europe/Berlin
+    timezone          TEXT,
+    -- Unit of energy, e.g. kwh
+    unit              TEXT,
+    decimals          INTEGER     CHECK (decimals BETWEEN 0 AND 4),
+    aggregation_type  TEXT,
+    updated_at        TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+SELECT diesel_manage_updated_at('api_key_preferences');
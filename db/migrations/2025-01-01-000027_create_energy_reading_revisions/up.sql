@@ -0,0 +1,22 @@
+-- Changes made to readings, each keeping the reading as it was before, so
+-- removed readings can be audited and restored. A `delete` revision is the
+-- tombstone of a reading that no longer exists.
+CREATE TABLE energy_reading_revisions (
+    id            UUID           PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Not a foreign key: the reading of a tombstone is gone
+    reading_id    UUID           NOT NULL,
+    tenant_id     TEXT           NOT NULL,
+    reading_time  TIMESTAMPTZ    NOT NULL,
+    quantity_kwh  NUMERIC(12, 4) NOT NULL,
+    plant_id      UUID,
+    quality_code  TEXT,
+    source        TEXT           NOT NULL,
+    operation     TEXT           NOT NULL,
+    -- The request making the change
+    request_id    UUID,
+    created_at    TIMESTAMPTZ    NOT NULL DEFAULT NOW(),
+    CHECK (operation IN ('delete'))
+);
+
+CREATE INDEX idx_energy_reading_revisions_tenant_time
+    ON energy_reading_revisions (tenant_id, reading_time);
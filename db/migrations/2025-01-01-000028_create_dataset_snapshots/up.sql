@@ -0,0 +1,22 @@
+-- A snapshot of a tenant's readings taken through `/admin/snapshots`: Parquet
+-- files and a manifest listing them, restored on request into a scratch
+-- schema of its own.
+CREATE TABLE dataset_snapshots (
+    id               UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id        TEXT         NOT NULL,
+    name             TEXT         NOT NULL,
+    status           TEXT         NOT NULL DEFAULT 'taking',
+    -- Where the manifest was stored, once completed
+    location         TEXT,
+    readings         BIGINT,
+    error            TEXT,
+    restore_status   TEXT,
+    -- Schema the snapshot was last restored into
+    restored_schema  TEXT,
+    restore_error    TEXT,
+    created_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (tenant_id, name),
+    CHECK (status IN ('taking', 'completed', 'failed')),
+    CHECK (restore_status IN ('restoring', 'restored', 'failed'))
+);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_webhooks_tenant_created_at;
+
+ALTER TABLE webhooks DROP COLUMN IF EXISTS tenant_id;
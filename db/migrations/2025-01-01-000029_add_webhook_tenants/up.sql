@@ -0,0 +1,7 @@
+-- Webhooks belong to a tenant, and only receive its events. Existing
+-- webhooks and single-tenant deployments use 'default'.
+ALTER TABLE webhooks
+    ADD COLUMN tenant_id TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX idx_webhooks_tenant_created_at
+    ON webhooks (tenant_id, created_at DESC);
@@ -8,6 +8,9 @@ pub enum ExcelDataReaderError {
     #[error("Calamine (xlsx reader) error: {0}")]
     Xlsx(#[from] XlsxError),
 
+    #[error("Xlsx writer error: {0}")]
+    Write(#[from] rust_xlsxwriter::XlsxError),
+
     #[error("Sheet is empty, no header row found")]
     EmptySheet,
 
@@ -1,6 +1,7 @@
 pub mod client;
 pub mod error;
 pub mod models;
+pub mod table;
 
 #[cfg(test)]
 mod tests;
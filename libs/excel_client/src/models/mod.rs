@@ -1,3 +1,5 @@
 pub mod record;
+pub mod table;
 
 pub use record::*;
+pub use table::*;
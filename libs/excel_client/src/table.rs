@@ -0,0 +1,77 @@
+use std::io::Cursor;
+
+use calamine::{Data, Reader, Xlsx};
+use rust_xlsxwriter::Workbook;
+
+use crate::error::{ExcelDataReaderClientResult, ExcelDataReaderError};
+use crate::models::{Cell, Table};
+
+/// Read the first worksheet of an in-memory xlsx file. The first row holds
+/// the headers; rows are padded to the number of headers and kept in sheet
+/// order, empty ones included, so callers can report row numbers.
+pub fn read_table(bytes: Vec<u8>) -> ExcelDataReaderClientResult<Table> {
+    let mut workbook = Xlsx::new(Cursor::new(bytes))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or(ExcelDataReaderError::EmptySheet)??;
+
+    let mut rows = range.rows();
+    let headers = rows
+        .next()
+        .ok_or(ExcelDataReaderError::EmptySheet)?
+        .iter()
+        .map(|cell| cell.to_string())
+        .collect::<Vec<_>>();
+
+    let rows = rows
+        .map(|row| {
+            let mut cells = row.iter().map(to_cell).collect::<Vec<_>>();
+            cells.resize(headers.len(), Cell::Empty);
+            cells
+        })
+        .collect();
+
+    Ok(Table { headers, rows })
+}
+
+fn to_cell(data: &Data) -> Cell {
+    match data {
+        Data::Empty => Cell::Empty,
+        Data::Int(number) => Cell::Number(*number as f64),
+        Data::Float(number) => Cell::Number(*number),
+        Data::String(text) => Cell::Text(text.clone()),
+        other => Cell::Text(other.to_string()),
+    }
+}
+
+/// Write a table to a single worksheet of a new xlsx file, with a bold
+/// header row.
+pub fn write_table(
+    sheet_name: &str,
+    table: &Table,
+) -> ExcelDataReaderClientResult<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(sheet_name)?;
+
+    let bold = rust_xlsxwriter::Format::new().set_bold();
+    for (col, header) in table.headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, header, &bold)?;
+    }
+    for (index, row) in table.rows.iter().enumerate() {
+        let row_num = index as u32 + 1;
+        for (col, cell) in row.iter().enumerate() {
+            match cell {
+                Cell::Empty => {}
+                Cell::Text(text) => {
+                    sheet.write_string(row_num, col as u16, text)?;
+                }
+                Cell::Number(number) => {
+                    sheet.write_number(row_num, col as u16, *number)?;
+                }
+            }
+        }
+    }
+
+    Ok(workbook.save_to_buffer()?)
+}
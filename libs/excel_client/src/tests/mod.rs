@@ -1 +1,2 @@
 pub mod client_tests;
+pub mod table_tests;
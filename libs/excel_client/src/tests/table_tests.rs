@@ -0,0 +1,29 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::{Cell, Table};
+    use crate::table::{read_table, write_table};
+
+    #[test]
+    fn test_table_round_trip() {
+        let table = Table {
+            headers: vec!["name".to_string(), "capacity_mw".to_string()],
+            rows: vec![
+                vec![Cell::Text("Sofia Solar".to_string()), Cell::Number(12.5)],
+                vec![Cell::Text("Varna Wind".to_string()), Cell::Empty],
+            ],
+        };
+
+        let bytes = write_table("Plants", &table).unwrap();
+        let read = read_table(bytes).unwrap();
+
+        assert_eq!(read, table);
+        assert_eq!(read.column("Capacity_MW "), Some(1));
+        assert_eq!(read.rows[1][1].as_number(), None);
+        assert_eq!(read.rows[0][1].as_text().as_deref(), Some("12.5"));
+    }
+
+    #[test]
+    fn test_read_table_rejects_garbage() {
+        assert!(read_table(b"name,capacity_mw\n".to_vec()).is_err());
+    }
+}
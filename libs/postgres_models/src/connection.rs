@@ -1,14 +1,22 @@
+use diesel::connection::{DebugQuery, Instrumentation, InstrumentationEvent};
 use diesel::pg::Pg;
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::pooled_connection::bb8;
+use diesel_async::pooled_connection::{
+    AsyncDieselConnectionManager, ManagerConfig,
+};
 use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness};
+use futures::FutureExt;
 use serde::Deserialize;
+use std::collections::HashSet;
 use std::error::Error;
-use std::sync::Arc;
-use std::time::Duration;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::task;
+pub use tokio_postgres::CancelToken;
 use tokio_postgres::Client as TokioPgClient;
 use tracing::Instrument;
 use tracing::{info, instrument, warn};
@@ -19,6 +27,112 @@ pub type PooledConnection = bb8::PooledConnection<'static, AsyncPgConnection>;
 pub const MAX_POOL_SIZE: u32 = 300;
 pub const MIN_RESERVED_CONNECTIONS: u32 = 10;
 
+/// Lifetime of the connections of a pool.
+///
+/// A connection keeps the prepared statement of every cacheable query it ran
+/// until it is closed, so these settings also bound how long a statement is
+/// reused before Postgres has to parse and plan it again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolSettings {
+    /// Age at which a connection is closed, `None` to keep it
+    pub max_lifetime: Option<Duration>,
+    /// Idle time after which a connection is closed, `None` to keep it
+    pub idle_timeout: Option<Duration>,
+    /// Idle connections kept open, `None` for none
+    pub min_idle: Option<u32>,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_lifetime: Some(Duration::from_secs(3600)),
+            idle_timeout: Some(Duration::from_secs(180)),
+            min_idle: None,
+        }
+    }
+}
+
+/// How a query used the prepared statement cache of its connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementCacheUse {
+    /// Ran the statement prepared by an earlier query
+    Hit,
+    /// Prepared its statement and cached it
+    Miss,
+    /// Not cacheable, so parsed and planned on every call
+    Uncached,
+}
+
+impl StatementCacheUse {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatementCacheUse::Hit => "hit",
+            StatementCacheUse::Miss => "miss",
+            StatementCacheUse::Uncached => "uncached",
+        }
+    }
+}
+
+/// Called after every query of a connection with its use of the cache.
+pub type StatementListener = Arc<dyn Fn(StatementCacheUse) + Send + Sync>;
+
+/// Reports the [`StatementCacheUse`] of each query of one connection.
+///
+/// Diesel only reports the statements it caches, so this tracks the SQL of
+/// those: a query that is not cached on start and caches nothing was not
+/// cacheable.
+struct StatementCacheInstrumentation {
+    listener: StatementListener,
+    /// SQL of the statements cached by the connection
+    cached: HashSet<String>,
+    /// SQL of the running query, until it caches its statement
+    running: Option<String>,
+}
+
+impl Instrumentation for StatementCacheInstrumentation {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                self.running = Some(query_sql(query));
+            }
+            InstrumentationEvent::CacheQuery { sql, .. } => {
+                self.running = None;
+                self.cached.insert(sql.to_string());
+                (self.listener)(StatementCacheUse::Miss);
+            }
+            InstrumentationEvent::FinishQuery { .. } => {
+                if let Some(sql) = self.running.take() {
+                    (self.listener)(if self.cached.contains(&sql) {
+                        StatementCacheUse::Hit
+                    } else {
+                        StatementCacheUse::Uncached
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The SQL of `query`, without formatting its binds.
+///
+/// Diesel writes the SQL of a query first and in one piece, followed by its
+/// binds, so formatting stops after the first write.
+fn query_sql(query: &dyn DebugQuery) -> String {
+    struct FirstWrite(String);
+
+    impl Write for FirstWrite {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.0.push_str(s);
+            Err(std::fmt::Error)
+        }
+    }
+
+    let mut sql = FirstWrite(String::new());
+    let _ = write!(sql, "{query}");
+    sql.0
+}
+
 #[derive(Deserialize)]
 pub struct Credentials {
     pub username: String,
@@ -73,27 +187,60 @@ fn calculate_optimal_pool_size(
     per_instance.min(MAX_POOL_SIZE)
 }
 
-pub async fn establish_connection(
-    db_url: String,
-) -> Result<Pool, anyhow::Error> {
-    let client = create_tokio_pg_client(&db_url).await.map_err(|e| {
+/// The `max_connections` of the database at `db_url` and the size of a pool
+/// connecting to it, leaving [`MIN_RESERVED_CONNECTIONS`] for admins.
+pub async fn optimal_pool_size(
+    db_url: &str,
+) -> Result<(i32, u32), anyhow::Error> {
+    let client = create_tokio_pg_client(db_url).await.map_err(|e| {
         anyhow::anyhow!("Failed to create PostgreSQL tokio client: {}", e)
     })?;
 
     let max_conn = get_max_connections(&client).await?;
-    info!("PostgreSQL max_connections: {}", max_conn);
-
     let max_pool_size =
         calculate_optimal_pool_size(max_conn, 1, MIN_RESERVED_CONNECTIONS);
+    Ok((max_conn, max_pool_size))
+}
+
+/// Connect a pool to `db_url`, reporting the statement cache use of every
+/// query to `on_statement` when given.
+pub async fn establish_connection(
+    db_url: String,
+    settings: &PoolSettings,
+    on_statement: Option<StatementListener>,
+) -> Result<Pool, anyhow::Error> {
+    let (max_conn, max_pool_size) = optimal_pool_size(&db_url).await?;
+    info!("PostgreSQL max_connections: {}", max_conn);
     info!("PostgreSQL max_pool_size: {}", max_pool_size);
 
-    let config = AsyncDieselConnectionManager::<AsyncPgConnection>::new(db_url);
+    let mut manager_config = ManagerConfig::default();
+    if let Some(listener) = on_statement {
+        manager_config.custom_setup = Box::new(move |url| {
+            let listener = listener.clone();
+            async move {
+                let mut conn = AsyncPgConnection::establish(url).await?;
+                conn.set_instrumentation(StatementCacheInstrumentation {
+                    listener,
+                    cached: HashSet::new(),
+                    running: None,
+                });
+                Ok(conn)
+            }
+            .boxed()
+        });
+    }
+    let config =
+        AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(
+            db_url,
+            manager_config,
+        );
     let pool = bb8::Pool::builder()
         .max_size(max_pool_size)
+        .min_idle(settings.min_idle)
         .connection_timeout(Duration::from_secs(10))
-        .idle_timeout(Some(Duration::from_secs(180)))
+        .idle_timeout(settings.idle_timeout)
         .retry_connection(true)
-        .max_lifetime(Some(Duration::from_secs(3600)))
+        .max_lifetime(settings.max_lifetime)
         .build(config)
         .await?;
 
@@ -204,6 +351,16 @@ where
 /// - `holding_db_connection` span: Shows time the connection is held and used
 /// - Debug log: Connection returned to pool
 ///
+/// Waits for a connection are also reported to the listener installed with
+/// [`set_acquire_listener`], if any.
+///
+/// # Deadlines
+///
+/// Within [`with_deadline`], waiting for a connection stops at the deadline
+/// with a pool timeout, and a query still running then is cancelled, so the
+/// operation fails soon after instead of holding the connection for a
+/// caller that gave up.
+///
 /// # Performance Considerations
 ///
 /// While this pattern requires acquiring a connection for each database operation
@@ -224,6 +381,7 @@ where
     F: FnOnce(PooledConnection) -> Fut,
     Fut: std::future::Future<Output = Result<T, E>>,
 {
+    let deadline = deadline();
     let pool_state_before = pool.state();
     let acquire_span = tracing::info_span!(
         "acquiring_pooled_connection",
@@ -231,19 +389,48 @@ where
         pool.idle_connections = pool_state_before.idle_connections,
     );
 
-    let conn =
-        async { pool.get_owned().await.map_err(WithConnectionError::Pool) }
-            .instrument(acquire_span)
-            .await?;
+    let conn = async {
+        let mut waiting = Waiting::start();
+        let conn = match deadline {
+            Some(deadline) => {
+                tokio::time::timeout_at(deadline, pool.get_owned())
+                    .await
+                    .unwrap_or(Err(bb8::RunError::TimedOut))
+            }
+            None => pool.get_owned().await,
+        };
+        waiting.outcome = Some(match &conn {
+            Ok(_) => AcquireOutcome::Acquired,
+            Err(e) if is_pool_exhausted(e) => AcquireOutcome::TimedOut,
+            Err(_) => AcquireOutcome::Failed,
+        });
+        conn.map_err(WithConnectionError::Pool)
+    }
+    .instrument(acquire_span)
+    .await?;
 
     let hold_span = tracing::info_span!("holding_db_connection");
     let result = async {
-        operation(conn)
-            .await
-            .map_err(WithConnectionError::Operation)
+        let Some(deadline) = deadline else {
+            return operation(conn).await;
+        };
+        let cancel = conn.cancel_token();
+        let operation = operation(conn);
+        tokio::pin!(operation);
+        match tokio::time::timeout_at(deadline, &mut operation).await {
+            Ok(result) => result,
+            Err(_) => {
+                // The connection stays ours until the query is cancelled,
+                // so the cancel cannot reach a later borrower's query
+                info!("Query ran past the deadline, cancelling it");
+                cancel_query(&cancel).await;
+                operation.await
+            }
+        }
     }
     .instrument(hold_span)
-    .await;
+    .await
+    .map_err(WithConnectionError::Operation);
 
     let pool_state_after = pool.state();
     tracing::debug!(
@@ -255,6 +442,114 @@ where
     result
 }
 
+/// Cancel the query running on the connection of `cancel`, taken with
+/// `conn.cancel_token()`, logging when it cannot be cancelled.
+///
+/// Keep the connection until its query has returned, otherwise the cancel
+/// may reach the query of its next borrower.
+pub async fn cancel_query(cancel: &CancelToken) {
+    if let Err(e) = cancel.cancel_query(tokio_postgres::NoTls).await {
+        warn!("Failed to cancel query: {e}");
+    }
+}
+
+tokio::task_local! {
+    static DEADLINE: tokio::time::Instant;
+}
+
+/// Run `future` with the database work of its [`with_connection`] calls
+/// bounded by `deadline`.
+pub async fn with_deadline<F: std::future::Future>(
+    deadline: tokio::time::Instant,
+    future: F,
+) -> F::Output {
+    DEADLINE.scope(deadline, future).await
+}
+
+/// Deadline of the current [`with_deadline`], if any.
+pub fn deadline() -> Option<tokio::time::Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// How a wait for a connection in [`with_connection`] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcquireOutcome {
+    Acquired,
+    /// No connection freed up within the pool's connection timeout
+    TimedOut,
+    /// A connection could not be opened
+    Failed,
+    /// The caller stopped waiting, like a request that was cancelled
+    Cancelled,
+}
+
+impl AcquireOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AcquireOutcome::Acquired => "acquired",
+            AcquireOutcome::TimedOut => "timed_out",
+            AcquireOutcome::Failed => "failed",
+            AcquireOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Observes the waits for a connection in [`with_connection`], across pools.
+pub trait AcquireListener: Send + Sync {
+    /// The number of callers waiting for a connection changed.
+    fn waiters(&self, waiters: usize);
+    /// A wait ended after `waited`.
+    fn acquired(&self, outcome: AcquireOutcome, waited: Duration);
+}
+
+static ACQUIRE_LISTENER: OnceLock<Box<dyn AcquireListener>> = OnceLock::new();
+static WAITERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Install the listener of every wait for a connection, returning `false`
+/// if one was already installed.
+pub fn set_acquire_listener(listener: Box<dyn AcquireListener>) -> bool {
+    ACQUIRE_LISTENER.set(listener).is_ok()
+}
+
+/// Whether the pool ran out of connections, as opposed to failing to open
+/// one.
+pub fn is_pool_exhausted(e: &bb8::RunError) -> bool {
+    matches!(e, bb8::RunError::TimedOut)
+}
+
+/// A caller waiting for a connection, reported to the listener when the
+/// wait ends, including when the future waiting is dropped.
+struct Waiting {
+    started: Instant,
+    outcome: Option<AcquireOutcome>,
+}
+
+impl Waiting {
+    fn start() -> Self {
+        let waiters = WAITERS.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(listener) = ACQUIRE_LISTENER.get() {
+            listener.waiters(waiters);
+        }
+        Self {
+            started: Instant::now(),
+            outcome: None,
+        }
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        let waiters = WAITERS.fetch_sub(1, Ordering::Relaxed) - 1;
+        if let Some(listener) = ACQUIRE_LISTENER.get() {
+            listener.waiters(waiters);
+            listener.acquired(
+                self.outcome.unwrap_or(AcquireOutcome::Cancelled),
+                self.started.elapsed(),
+            );
+        }
+    }
+}
+
 /// Error type for with_connection that distinguishes between pool and operation errors
 #[derive(Debug)]
 pub enum WithConnectionError<E> {
@@ -288,6 +583,13 @@ impl<E: std::error::Error + 'static> std::error::Error
     }
 }
 
+impl<E> WithConnectionError<E> {
+    /// Whether the pool ran out of connections, see [`is_pool_exhausted`].
+    pub fn is_pool_exhausted(&self) -> bool {
+        matches!(self, WithConnectionError::Pool(e) if is_pool_exhausted(e))
+    }
+}
+
 /// Helper to convert WithConnectionError<diesel::result::Error> to diesel::result::Error
 ///
 /// This is useful when you want to use the `?` operator directly with with_connection results
@@ -390,3 +692,93 @@ where
         .await
         .map_err(connection_error_to_diesel)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use diesel::debug_query;
+    use diesel::sql_types::Text;
+
+    use super::*;
+
+    #[test]
+    fn test_tells_exhausted_pools_from_failed_connections() {
+        assert!(is_pool_exhausted(&bb8::RunError::TimedOut));
+
+        let failed = bb8::RunError::User(
+            diesel_async::pooled_connection::PoolError::ConnectionError(
+                diesel::ConnectionError::BadConnection("refused".to_string()),
+            ),
+        );
+        assert!(!is_pool_exhausted(&failed));
+        assert!(
+            !WithConnectionError::<diesel::result::Error>::Pool(failed)
+                .is_pool_exhausted()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stops_waiting_for_a_connection_at_the_deadline() {
+        let pool = Pool::builder()
+            .connection_timeout(Duration::from_secs(30))
+            .build_unchecked(AsyncDieselConnectionManager::new(
+                "postgres://127.0.0.1:1/unused",
+            ));
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(100);
+
+        let result = with_deadline(deadline, async {
+            assert_eq!(super::deadline(), Some(deadline));
+            with_connection(&pool, |_| async {
+                Ok::<_, diesel::result::Error>(())
+            })
+            .await
+        })
+        .await;
+
+        assert!(result.unwrap_err().is_pool_exhausted());
+        assert!(
+            tokio::time::Instant::now() < deadline + Duration::from_secs(5)
+        );
+        assert_eq!(super::deadline(), None);
+    }
+
+    #[test]
+    fn test_reports_statement_cache_use() {
+        let uses = Arc::new(Mutex::new(Vec::new()));
+        let mut instrumentation = StatementCacheInstrumentation {
+            listener: Arc::new({
+                let uses = uses.clone();
+                move |cache_use| uses.lock().unwrap().push(cache_use)
+            }),
+            cached: HashSet::new(),
+            running: None,
+        };
+        let query = diesel::sql_query("SELECT $1").bind::<Text, _>("x");
+        let query = debug_query::<Pg, _>(&query);
+        let mut run = |cached: bool| {
+            instrumentation
+                .on_connection_event(InstrumentationEvent::start_query(&query));
+            if cached {
+                instrumentation.on_connection_event(
+                    InstrumentationEvent::cache_query("SELECT $1"),
+                );
+            }
+            instrumentation.on_connection_event(
+                InstrumentationEvent::finish_query(&query, None),
+            );
+        };
+
+        run(false);
+        run(true);
+        run(false);
+        assert_eq!(
+            *uses.lock().unwrap(),
+            [
+                StatementCacheUse::Uncached,
+                StatementCacheUse::Miss,
+                StatementCacheUse::Hit
+            ]
+        );
+    }
+}
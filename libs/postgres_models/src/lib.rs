@@ -1,5 +1,7 @@
 pub mod connection;
 pub mod models;
+pub mod periods;
+pub mod prepared;
 pub mod schema;
 
 // Useful cheatsheet: https://kotiri.com/2018/01/31/postgresql-diesel-rust-types.html
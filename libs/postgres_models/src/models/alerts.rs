@@ -0,0 +1,390 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use super::Keyset;
+
+/// Values of [`AlertRule::metric`].
+pub mod metric {
+    pub const TOTAL_KWH: &str = "total_kwh";
+    pub const AVERAGE_KWH: &str = "average_kwh";
+    pub const MAX_KWH: &str = "max_kwh";
+    pub const MIN_KWH: &str = "min_kwh";
+    pub const READING_COUNT: &str = "reading_count";
+}
+
+/// Values of [`AlertRule::comparison`].
+pub mod comparison {
+    pub const GT: &str = "gt";
+    pub const GTE: &str = "gte";
+    pub const LT: &str = "lt";
+    pub const LTE: &str = "lte";
+}
+
+/// A threshold on a metric of a tenant's recent readings.
+#[derive(
+    Queryable, QueryableByName, Selectable, Debug, Clone, serde::Serialize,
+)]
+#[diesel(table_name = crate::schema::alert_rules)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AlertRule {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub name: String,
+    /// Only readings of this plant count, all of the tenant's when `None`
+    pub plant_id: Option<Uuid>,
+    pub metric: String,
+    pub comparison: String,
+    pub threshold: BigDecimal,
+    /// Length of the window of readings, ending at evaluation time
+    pub window_minutes: i32,
+    pub enabled: bool,
+    /// Set while the rule is breached
+    pub firing: bool,
+    pub evaluated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Notification channel of the rule's alerts, `None` to only emit the
+    /// `threshold_breached` webhook event
+    pub channel_id: Option<Uuid>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::alert_rules)]
+pub struct NewAlertRule {
+    pub tenant_id: String,
+    pub name: String,
+    pub plant_id: Option<Uuid>,
+    pub metric: String,
+    pub comparison: String,
+    pub threshold: BigDecimal,
+    pub window_minutes: i32,
+    pub enabled: bool,
+    pub channel_id: Option<Uuid>,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::alert_rules)]
+pub struct UpdateAlertRule {
+    pub name: Option<String>,
+    pub plant_id: Option<Option<Uuid>>,
+    pub metric: Option<String>,
+    pub comparison: Option<String>,
+    pub threshold: Option<BigDecimal>,
+    pub window_minutes: Option<i32>,
+    pub enabled: Option<bool>,
+    pub firing: Option<bool>,
+    pub channel_id: Option<Option<Uuid>>,
+}
+
+/// A breach of an [`AlertRule`].
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::alerts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Alert {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub tenant_id: String,
+    /// The metric's value when the rule fired
+    pub value: BigDecimal,
+    /// The rule's threshold when it fired
+    pub threshold: BigDecimal,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub fired_at: DateTime<Utc>,
+    /// Set when the metric no longer breaches the threshold
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// Name of the API key that acknowledged the alert, `None` for the
+    /// admin token
+    pub acknowledged_by: Option<String>,
+    /// Notification queued for the rule's channel when the alert fired
+    pub notification_id: Option<Uuid>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::alerts)]
+pub struct NewAlert {
+    pub rule_id: Uuid,
+    pub tenant_id: String,
+    pub value: BigDecimal,
+    pub threshold: BigDecimal,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub fired_at: DateTime<Utc>,
+}
+
+impl AlertRule {
+    pub async fn create(
+        entry: NewAlertRule,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::alert_rules::dsl::*;
+
+        diesel::insert_into(alert_rules)
+            .values(&entry)
+            .returning(AlertRule::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// A rule of the tenant, `None` when it does not exist or belongs to
+    /// another tenant.
+    pub async fn find(
+        tenant: &str,
+        rule_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::alert_rules::dsl::*;
+
+        alert_rules
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(rule_id))
+            .select(AlertRule::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// A tenant's rules ordered by name.
+    pub async fn list(
+        tenant: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::alert_rules::dsl::*;
+
+        alert_rules
+            .filter(tenant_id.eq(tenant))
+            .order((name, id))
+            .select(AlertRule::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Lock up to `limit` enabled rules last evaluated before `due_before`,
+    /// least recently evaluated first.
+    ///
+    /// Uses `FOR UPDATE SKIP LOCKED` so concurrent evaluators never claim the
+    /// same rule; the caller should record the evaluation in the same
+    /// transaction.
+    pub async fn claim_due(
+        due_before: DateTime<Utc>,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        diesel::sql_query(
+            "SELECT * FROM alert_rules \
+             WHERE enabled AND (evaluated_at IS NULL OR evaluated_at < $1) \
+             ORDER BY evaluated_at NULLS FIRST, id \
+             LIMIT $2 \
+             FOR UPDATE SKIP LOCKED",
+        )
+        .bind::<Timestamptz, _>(due_before)
+        .bind::<BigInt, _>(limit)
+        .load(conn)
+        .await
+    }
+
+    /// Record that a rule was evaluated at `at` and whether it is breached.
+    pub async fn record_evaluation(
+        rule_id: Uuid,
+        is_firing: bool,
+        at: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::alert_rules::dsl::*;
+
+        diesel::update(alert_rules.find(rule_id))
+            .set((firing.eq(is_firing), evaluated_at.eq(at)))
+            .execute(conn)
+            .await
+    }
+
+    /// Apply `changes` to a rule of the tenant. `None` when it does not
+    /// exist.
+    pub async fn update(
+        tenant: &str,
+        rule_id: Uuid,
+        changes: UpdateAlertRule,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::alert_rules::dsl::*;
+
+        diesel::update(
+            alert_rules
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(rule_id)),
+        )
+        .set(&changes)
+        .returning(AlertRule::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Delete a rule of the tenant and its alerts. Returns the number of
+    /// deleted rules.
+    pub async fn delete(
+        tenant: &str,
+        rule_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::alert_rules::dsl::*;
+
+        diesel::delete(
+            alert_rules
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(rule_id)),
+        )
+        .execute(conn)
+        .await
+    }
+}
+
+impl Alert {
+    pub async fn create(
+        entry: NewAlert,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::alerts::dsl::*;
+
+        diesel::insert_into(alerts)
+            .values(&entry)
+            .returning(Alert::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// An alert of the tenant, `None` when it does not exist or belongs to
+    /// another tenant.
+    pub async fn find(
+        tenant: &str,
+        alert_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::alerts::dsl::*;
+
+        alerts
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(alert_id))
+            .select(Alert::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// A tenant's latest alerts, of one rule if given and only unresolved
+    /// ones if `open`, newest first from `keyset`.
+    pub async fn list(
+        tenant: &str,
+        rule: Option<Uuid>,
+        open: bool,
+        keyset: Keyset,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::alerts::dsl::*;
+
+        let mut query = alerts
+            .filter(tenant_id.eq(tenant))
+            .select(Alert::as_select())
+            .into_boxed();
+        if let Some(rule) = rule {
+            query = query.filter(rule_id.eq(rule));
+        }
+        if open {
+            query = query.filter(resolved_at.is_null());
+        }
+        // Alerts can fire at the same time
+        match keyset {
+            Keyset::First => {
+                query = query.order((fired_at.desc(), id.desc()));
+            }
+            Keyset::After(time, after) => {
+                query = query
+                    .filter(
+                        fired_at
+                            .lt(time)
+                            .or(fired_at.eq(time).and(id.lt(after))),
+                    )
+                    .order((fired_at.desc(), id.desc()));
+            }
+            // Read towards newer alerts, the nearest first
+            Keyset::Before(time, before) => {
+                query = query
+                    .filter(
+                        fired_at
+                            .gt(time)
+                            .or(fired_at.eq(time).and(id.gt(before))),
+                    )
+                    .order((fired_at.asc(), id.asc()));
+            }
+        }
+
+        let mut page = query.limit(limit).load(conn).await?;
+        if let Keyset::Before(..) = keyset {
+            page.reverse();
+        }
+        Ok(page)
+    }
+
+    /// Resolve the open alerts of a rule. Returns the number of resolved
+    /// alerts.
+    pub async fn resolve_open(
+        rule: Uuid,
+        at: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::alerts::dsl::*;
+
+        diesel::update(
+            alerts
+                .filter(rule_id.eq(rule))
+                .filter(resolved_at.is_null()),
+        )
+        .set(resolved_at.eq(at))
+        .execute(conn)
+        .await
+    }
+
+    /// Link an alert to the notification queued for it.
+    pub async fn set_notification(
+        alert_id: Uuid,
+        notification: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::alerts::dsl::*;
+
+        diesel::update(alerts.find(alert_id))
+            .set(notification_id.eq(notification))
+            .execute(conn)
+            .await
+    }
+
+    /// Acknowledge an alert of the tenant that is not acknowledged yet.
+    /// `None` when there is no such alert.
+    pub async fn acknowledge(
+        tenant: &str,
+        alert_id: Uuid,
+        by: Option<&str>,
+        at: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::alerts::dsl::*;
+
+        diesel::update(
+            alerts
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(alert_id))
+                .filter(acknowledged_at.is_null()),
+        )
+        .set((acknowledged_at.eq(at), acknowledged_by.eq(by)))
+        .returning(Alert::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Defaults of the requests made with an API key.
+#[derive(
+    Queryable,
+    Selectable,
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[diesel(table_name = crate::schema::api_key_preferences)]
+#[diesel(primary_key(api_key_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKeyPreferences {
+    pub api_key_id: Uuid,
+    /// IANA time zone, e.g. `Europe/Berlin`
+    pub timezone: Option<String>,
+    /// Unit of energy, e.g. `kwh`
+    pub unit: Option<String>,
+    /// Decimal places of totals, 0 to 4
+    pub decimals: Option<i32>,
+    /// Aggregation type of aggregations leaving it out
+    pub aggregation_type: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::api_key_preferences)]
+pub struct NewApiKeyPreferences {
+    pub api_key_id: Uuid,
+    pub timezone: Option<String>,
+    pub unit: Option<String>,
+    pub decimals: Option<i32>,
+    pub aggregation_type: Option<String>,
+}
+
+impl ApiKeyPreferences {
+    /// The preferences of an API key, `None` when none are set.
+    pub async fn find(
+        key_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::api_key_preferences::dsl::*;
+
+        api_key_preferences
+            .filter(api_key_id.eq(key_id))
+            .select(ApiKeyPreferences::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Set the preferences of an API key, replacing those already stored.
+    pub async fn upsert(
+        entry: NewApiKeyPreferences,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::api_key_preferences::dsl::*;
+        use diesel::upsert::excluded;
+
+        diesel::insert_into(api_key_preferences)
+            .values(&entry)
+            .on_conflict(api_key_id)
+            .do_update()
+            .set((
+                timezone.eq(excluded(timezone)),
+                unit.eq(excluded(unit)),
+                decimals.eq(excluded(decimals)),
+                aggregation_type.eq(excluded(aggregation_type)),
+            ))
+            .returning(ApiKeyPreferences::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Delete the preferences of an API key. Returns the number of deleted
+    /// rows.
+    pub async fn delete(
+        key_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::api_key_preferences::dsl::*;
+
+        diesel::delete(api_key_preferences.filter(api_key_id.eq(key_id)))
+            .execute(conn)
+            .await
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    #[serde(skip)]
+    pub key_hash: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// One of `read`, `ingest` or `admin`
+    pub role: String,
+    /// Requests allowed per UTC day, `None` for unlimited
+    pub daily_quota: Option<i64>,
+    /// Requests allowed per calendar month, `None` for unlimited
+    pub monthly_quota: Option<i64>,
+    /// HMAC secret for signed requests
+    #[serde(skip)]
+    pub signing_secret: Option<String>,
+    /// Tenant whose data the key can access
+    pub tenant_id: String,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::api_keys)]
+pub struct NewApiKey {
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub role: String,
+    pub daily_quota: Option<i64>,
+    pub monthly_quota: Option<i64>,
+    pub signing_secret: Option<String>,
+    pub tenant_id: String,
+}
+
+impl ApiKey {
+    pub async fn create(
+        entry: NewApiKey,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::api_keys::dsl::*;
+
+        diesel::insert_into(api_keys)
+            .values(&entry)
+            .returning(ApiKey::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Look up a non-revoked key by the SHA-256 hash of its secret.
+    pub async fn find_active_by_hash(
+        hash: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::api_keys::dsl::*;
+
+        api_keys
+            .filter(key_hash.eq(hash))
+            .filter(revoked_at.is_null())
+            .select(ApiKey::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Look up a non-revoked key by id.
+    pub async fn find_active(
+        key_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::api_keys::dsl::*;
+
+        api_keys
+            .find(key_id)
+            .filter(revoked_at.is_null())
+            .select(ApiKey::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Look up a tenant's keys by id, revoked ones included, in one query.
+    pub async fn find_many(
+        tenant: &str,
+        key_ids: &[Uuid],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::api_keys::dsl::*;
+
+        api_keys
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq_any(key_ids))
+            .select(ApiKey::as_select())
+            .load(conn)
+            .await
+    }
+
+    pub async fn list(
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::api_keys::dsl::*;
+
+        api_keys
+            .order(created_at.desc())
+            .select(ApiKey::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Revoke a key. Returns the number of updated rows (0 if the key does
+    /// not exist or was already revoked).
+    pub async fn revoke(
+        key_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::api_keys::dsl::*;
+
+        diesel::update(api_keys.find(key_id).filter(revoked_at.is_null()))
+            .set(revoked_at.eq(Utc::now()))
+            .execute(conn)
+            .await
+    }
+
+    pub async fn touch_last_used(
+        key_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::api_keys::dsl::*;
+
+        diesel::update(api_keys.find(key_id))
+            .set(last_used_at.eq(Utc::now()))
+            .execute(conn)
+            .await
+    }
+}
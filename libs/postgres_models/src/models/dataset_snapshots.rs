@@ -0,0 +1,273 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Nullable, Numeric, Text, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use super::energy_readings::EnergyReading;
+
+/// Lifecycle states of a [`DatasetSnapshot`].
+pub mod snapshot_status {
+    pub const TAKING: &str = "taking";
+    pub const COMPLETED: &str = "completed";
+    pub const FAILED: &str = "failed";
+}
+
+/// States of the last restore of a [`DatasetSnapshot`].
+pub mod restore_state {
+    pub const RESTORING: &str = "restoring";
+    pub const RESTORED: &str = "restored";
+    pub const FAILED: &str = "failed";
+}
+
+/// A snapshot of a tenant's readings.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::dataset_snapshots)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DatasetSnapshot {
+    pub id: Uuid,
+    pub tenant_id: String,
+    /// Unique among the tenant's snapshots
+    pub name: String,
+    /// See [`snapshot_status`]
+    pub status: String,
+    /// Where the manifest was stored, once completed
+    pub location: Option<String>,
+    /// Readings in the snapshot, once completed
+    pub readings: Option<i64>,
+    /// Why taking the snapshot failed
+    pub error: Option<String>,
+    /// See [`restore_state`], `None` until first restored
+    pub restore_status: Option<String>,
+    /// Schema the snapshot was last restored into
+    pub restored_schema: Option<String>,
+    /// Why the last restore failed
+    pub restore_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::dataset_snapshots)]
+pub struct NewDatasetSnapshot {
+    pub tenant_id: String,
+    pub name: String,
+}
+
+/// Scratch schema snapshot `id` is restored into, a valid identifier.
+pub fn scratch_schema(id: Uuid) -> String {
+    format!("snapshot_{}", id.simple())
+}
+
+impl DatasetSnapshot {
+    pub async fn create(
+        snapshot: NewDatasetSnapshot,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::dataset_snapshots::dsl::*;
+
+        diesel::insert_into(dataset_snapshots)
+            .values(snapshot)
+            .returning(DatasetSnapshot::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    pub async fn find_by_name(
+        tenant: &str,
+        snapshot_name: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::dataset_snapshots::dsl::*;
+
+        dataset_snapshots
+            .filter(tenant_id.eq(tenant))
+            .filter(name.eq(snapshot_name))
+            .select(DatasetSnapshot::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// The tenant's snapshots, newest first.
+    pub async fn list(
+        tenant: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::dataset_snapshots::dsl::*;
+
+        dataset_snapshots
+            .filter(tenant_id.eq(tenant))
+            .order(created_at.desc())
+            .select(DatasetSnapshot::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Record that the snapshot's manifest was stored at `stored_at`.
+    pub async fn complete(
+        snapshot_id: Uuid,
+        stored_at: &str,
+        count: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::dataset_snapshots::dsl::*;
+
+        diesel::update(dataset_snapshots.filter(id.eq(snapshot_id)))
+            .set((
+                status.eq(snapshot_status::COMPLETED),
+                location.eq(stored_at),
+                readings.eq(count),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Record that taking the snapshot failed with `reason`.
+    pub async fn fail(
+        snapshot_id: Uuid,
+        reason: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::dataset_snapshots::dsl::*;
+
+        diesel::update(dataset_snapshots.filter(id.eq(snapshot_id)))
+            .set((
+                status.eq(snapshot_status::FAILED),
+                error.eq(reason),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Mark a completed snapshot as being restored; `None` unless it is
+    /// completed and not being restored already.
+    pub async fn restoring(
+        snapshot_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::dataset_snapshots::dsl::*;
+
+        diesel::update(
+            dataset_snapshots
+                .filter(id.eq(snapshot_id))
+                .filter(status.eq(snapshot_status::COMPLETED))
+                .filter(
+                    restore_status.is_distinct_from(restore_state::RESTORING),
+                ),
+        )
+        .set((
+            restore_status.eq(restore_state::RESTORING),
+            restore_error.eq(None::<String>),
+            updated_at.eq(diesel::dsl::now),
+        ))
+        .returning(DatasetSnapshot::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Record the outcome of a restore into `schema`, and why it failed if
+    /// it did.
+    pub async fn restored(
+        snapshot_id: Uuid,
+        schema: &str,
+        reason: Option<&str>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::dataset_snapshots::dsl::*;
+
+        let outcome = match reason {
+            None => restore_state::RESTORED,
+            Some(_) => restore_state::FAILED,
+        };
+        diesel::update(dataset_snapshots.filter(id.eq(snapshot_id)))
+            .set((
+                restore_status.eq(outcome),
+                restored_schema.eq(reason.is_none().then_some(schema)),
+                restore_error.eq(reason),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// (Re)create `schema` with an empty `energy_readings` table like the
+    /// live one, without its foreign keys. `schema` must be an identifier,
+    /// see [`scratch_schema`].
+    pub async fn create_scratch(
+        schema: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), diesel::result::Error> {
+        Self::drop_scratch(schema, conn).await?;
+        diesel::sql_query(format!("CREATE SCHEMA {schema}"))
+            .execute(conn)
+            .await?;
+        diesel::sql_query(format!(
+            "CREATE TABLE {schema}.energy_readings (LIKE energy_readings \
+             INCLUDING DEFAULTS INCLUDING CONSTRAINTS INCLUDING INDEXES)"
+        ))
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Drop `schema` and its tables, if it exists.
+    pub async fn drop_scratch(
+        schema: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), diesel::result::Error> {
+        diesel::sql_query(format!("DROP SCHEMA IF EXISTS {schema} CASCADE"))
+            .execute(conn)
+            .await
+            .map(drop)
+    }
+
+    /// Insert `readings` as they are, ids and timestamps included, into
+    /// the `energy_readings` table of `schema`.
+    pub async fn insert_scratch(
+        schema: &str,
+        readings: &[EnergyReading],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        let mut ids = Vec::with_capacity(readings.len());
+        let mut times = Vec::with_capacity(readings.len());
+        let mut quantities = Vec::with_capacity(readings.len());
+        let mut created = Vec::with_capacity(readings.len());
+        let mut updated = Vec::with_capacity(readings.len());
+        let mut tenants = Vec::with_capacity(readings.len());
+        let mut plants = Vec::with_capacity(readings.len());
+        let mut quality_codes = Vec::with_capacity(readings.len());
+        let mut sources = Vec::with_capacity(readings.len());
+        for reading in readings {
+            ids.push(reading.id);
+            times.push(reading.reading_time);
+            quantities.push(&reading.quantity_kwh);
+            created.push(reading.created_at);
+            updated.push(reading.updated_at);
+            tenants.push(reading.tenant_id.as_str());
+            plants.push(reading.plant_id);
+            quality_codes.push(reading.quality_code.as_deref());
+            sources.push(reading.source.as_str());
+        }
+        diesel::sql_query(format!(
+            "INSERT INTO {schema}.energy_readings (id, reading_time, \
+                 quantity_kwh, created_at, updated_at, tenant_id, plant_id, \
+                 quality_code, source) \
+             SELECT * FROM UNNEST($1, $2, $3, $4, $5, $6, $7, $8, $9)"
+        ))
+        .bind::<Array<diesel::sql_types::Uuid>, _>(ids)
+        .bind::<Array<Timestamptz>, _>(times)
+        .bind::<Array<Numeric>, _>(quantities)
+        .bind::<Array<Timestamptz>, _>(created)
+        .bind::<Array<Timestamptz>, _>(updated)
+        .bind::<Array<Text>, _>(tenants)
+        .bind::<Array<Nullable<diesel::sql_types::Uuid>>, _>(plants)
+        .bind::<Array<Nullable<Text>>, _>(quality_codes)
+        .bind::<Array<Text>, _>(sources)
+        .execute(conn)
+        .await
+    }
+}
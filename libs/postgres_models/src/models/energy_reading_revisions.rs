@@ -0,0 +1,53 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Changes recorded by an [`EnergyReadingRevision`].
+pub mod revision_operation {
+    /// The reading was deleted; the revision is its tombstone
+    pub const DELETE: &str = "delete";
+}
+
+/// A change made to a reading, with the reading as it was before.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::energy_reading_revisions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EnergyReadingRevision {
+    pub id: Uuid,
+    pub reading_id: Uuid,
+    pub tenant_id: String,
+    pub reading_time: DateTime<Utc>,
+    pub quantity_kwh: BigDecimal,
+    pub plant_id: Option<Uuid>,
+    pub quality_code: Option<String>,
+    pub source: String,
+    /// See [`revision_operation`]
+    pub operation: String,
+    /// The request making the change
+    pub request_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EnergyReadingRevision {
+    /// The tenant's revisions of readings in `[date_from, date_to)`, in
+    /// reading time order.
+    pub async fn list(
+        tenant: &str,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::energy_reading_revisions::dsl;
+
+        dsl::energy_reading_revisions
+            .filter(dsl::tenant_id.eq(tenant))
+            .filter(dsl::reading_time.ge(date_from))
+            .filter(dsl::reading_time.lt(date_to))
+            .order((dsl::reading_time.asc(), dsl::created_at.asc()))
+            .select(Self::as_select())
+            .load(conn)
+            .await
+    }
+}
@@ -1,10 +1,29 @@
 use bigdecimal::BigDecimal;
 use chrono::{DateTime, Utc};
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use diesel::sql_types::{Numeric, Timestamptz};
+use diesel::query_builder::{BoxedSqlQuery, SqlQuery};
+use diesel::sql_types::{BigInt, Json, Nullable, Numeric, Timestamptz};
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use uuid::Uuid;
 
+use super::Keyset;
+use super::energy_reading_revisions::revision_operation;
+use crate::periods;
+use crate::prepared::Prepared;
+
+/// Where a reading came from.
+pub mod reading_source {
+    /// An imported readings file
+    pub const FILE: &str = "file";
+    /// A push to the ingest endpoint
+    pub const WEBHOOK: &str = "webhook";
+    /// A push to the ingest endpoint relayed from Kafka
+    pub const KAFKA: &str = "kafka";
+    /// A manual correction
+    pub const MANUAL: &str = "manual";
+}
+
 #[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
 #[diesel(table_name = crate::schema::energy_readings)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -14,6 +33,13 @@ pub struct EnergyReading {
     pub quantity_kwh: BigDecimal,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub tenant_id: String,
+    /// The plant the reading is attributed to
+    pub plant_id: Option<Uuid>,
+    /// Problem found with the quantity when it was stored, e.g. `negative`
+    pub quality_code: Option<String>,
+    /// Where the reading came from, see [`reading_source`]
+    pub source: String,
 }
 
 #[derive(Insertable, Debug, Clone)]
@@ -21,18 +47,191 @@ pub struct EnergyReading {
 pub struct NewEnergyReading {
     pub reading_time: DateTime<Utc>,
     pub quantity_kwh: BigDecimal,
+    pub tenant_id: String,
+    pub plant_id: Option<Uuid>,
+    pub quality_code: Option<String>,
+    pub source: String,
 }
 
-#[derive(QueryableByName, Debug, Clone, serde::Serialize)]
+#[derive(QueryableByName, Debug, Clone, Default, serde::Serialize)]
 pub struct AggregatedReading {
     #[diesel(sql_type = Timestamptz)]
     pub period: DateTime<Utc>,
     #[diesel(sql_type = Numeric)]
     pub total_kwh: BigDecimal,
+    /// Readings of the period, also with a running `total_kwh`
+    #[diesel(sql_type = BigInt)]
+    pub reading_count: i64,
+    /// Feeds (plants, and readings without a plant) with readings in the
+    /// period
+    #[diesel(sql_type = BigInt)]
+    pub feed_count: i64,
+    /// Smallest reading of the period, `None` when it has none. These stay
+    /// per period with a running `total_kwh`.
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub min_kwh: Option<BigDecimal>,
+    /// Largest reading of the period
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub max_kwh: Option<BigDecimal>,
+    /// Mean reading of the period, to the 4 decimal places readings have
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub avg_kwh: Option<BigDecimal>,
+}
+
+/// How [`EnergyReading::aggregate`] returns the periods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AggregateOptions {
+    /// Also return the periods without readings, with a total of 0, from
+    /// the period of the start bound, or of the first reading, to that of
+    /// the end bound, or of the last reading
+    pub fill_gaps: bool,
+    /// Return running totals, each period's plus those of the periods
+    /// before it
+    pub cumulative: bool,
+}
+
+/// The output of `EXPLAIN (FORMAT JSON)`.
+#[derive(QueryableByName)]
+struct QueryPlan {
+    #[diesel(sql_type = Json)]
+    #[diesel(column_name = "QUERY PLAN")]
+    plan: serde_json::Value,
+}
+
+/// Summary of the readings in a time window.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct ReadingStats {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub reading_count: i64,
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub total_kwh: Option<BigDecimal>,
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub average_kwh: Option<BigDecimal>,
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub max_kwh: Option<BigDecimal>,
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub min_kwh: Option<BigDecimal>,
+}
+
+/// Which of a tenant's readings [`EnergyReading::page`] lists, all of
+/// them by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadingFilter<'a> {
+    /// Only those attributed to the plant
+    pub plant: Option<Uuid>,
+    /// Only those at or after the time
+    pub date_from: Option<DateTime<Utc>>,
+    /// Only those before the time
+    pub date_to: Option<DateTime<Utc>>,
+    /// Only those from the source, a [`reading_source`]
+    pub source: Option<&'a str>,
+}
+
+/// Readings to delete, see [`EnergyReading::delete`].
+#[derive(Debug, Clone, Copy)]
+pub enum ReadingSelection<'a> {
+    /// The readings in `[from, to)`
+    Range {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    },
+    /// The readings at these times
+    Times(&'a [DateTime<Utc>]),
+}
+
+/// Readings deleted at one reading time, see [`EnergyReading::delete`].
+#[derive(QueryableByName, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeletedReadings {
+    #[diesel(sql_type = Timestamptz)]
+    pub reading_time: DateTime<Utc>,
+    #[diesel(sql_type = BigInt)]
+    pub deleted: i64,
+}
+
+/// Quality counts of a tenant's readings on one day, see
+/// [`EnergyReading::daily_quality`].
+#[derive(QueryableByName, Debug, Clone)]
+pub struct DailyQuality {
+    #[diesel(sql_type = Timestamptz)]
+    pub day: DateTime<Utc>,
+    #[diesel(sql_type = BigInt)]
+    pub reading_count: i64,
+    /// Interval slots of a feed holding at least one reading
+    #[diesel(sql_type = BigInt)]
+    pub slot_count: i64,
+    /// Negative readings and readings above the plant's capacity over an
+    /// interval
+    #[diesel(sql_type = BigInt)]
+    pub out_of_range_count: i64,
+}
+
+/// A tenant's energy at one reading time, summed over its plants.
+#[derive(QueryableByName, Debug, Clone, Copy, PartialEq)]
+pub struct SeriesPoint {
+    #[diesel(sql_type = Timestamptz)]
+    pub reading_time: DateTime<Utc>,
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    pub kwh: f64,
+}
+
+#[derive(QueryableByName)]
+struct PeriodCount {
+    #[diesel(sql_type = BigInt)]
+    periods: i64,
+}
+
+/// What running an aggregation would take, see
+/// [`EnergyReading::estimate_aggregate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregateEstimate {
+    /// Periods of the aggregation with its gaps filled
+    pub periods: i64,
+    /// Readings the planner expects the aggregation to scan
+    pub scanned_rows: i64,
+}
+
+#[derive(QueryableByName)]
+struct FeedCount {
+    #[diesel(sql_type = BigInt)]
+    feeds: i64,
+}
+
+/// Rows the nodes of `plan`, an `EXPLAIN (FORMAT JSON)` output or one of
+/// its nodes, are expected to read from `relation`. Parallel nodes
+/// estimate the rows of one of `workers` processes.
+fn planned_rows(plan: &serde_json::Value, relation: &str, workers: f64) -> f64 {
+    match plan {
+        serde_json::Value::Array(plans) => plans
+            .iter()
+            .map(|plan| planned_rows(plan, relation, workers))
+            .sum(),
+        serde_json::Value::Object(object) => {
+            // The output wraps its root node in `Plan`
+            let node = object.get("Plan").unwrap_or(plan);
+            let workers = match node["Workers Planned"].as_f64() {
+                // The leader takes part too
+                Some(planned) => planned + 1.0,
+                None => workers,
+            };
+            let rows = if node["Relation Name"] == relation {
+                node["Plan Rows"].as_f64().unwrap_or_default()
+                    * if node["Parallel Aware"] == true {
+                        workers
+                    } else {
+                        1.0
+                    }
+            } else {
+                0.0
+            };
+            rows + planned_rows(&node["Plans"], relation, workers)
+        }
+        _ => 0.0,
+    }
 }
 
 impl EnergyReading {
-    /// Bulk insert energy readings - skipping conflicts on reading_time (upsert).
+    /// Bulk insert energy readings - skipping conflicts on the tenant's
+    /// plant and reading_time (upsert).
     pub async fn bulk_insert(
         readings: Vec<NewEnergyReading>,
         conn: &mut AsyncPgConnection,
@@ -41,75 +240,608 @@ impl EnergyReading {
 
         diesel::insert_into(energy_readings)
             .values(&readings)
-            .on_conflict(reading_time)
+            .on_conflict((tenant_id, plant_id, reading_time))
             .do_nothing()
             .execute(conn)
             .await
     }
 
-    /// Count total rows in the table.
+    /// Count the readings of a tenant.
     pub async fn count(
+        tenant: &str,
         conn: &mut AsyncPgConnection,
     ) -> Result<i64, diesel::result::Error> {
         use crate::schema::energy_readings::dsl::*;
 
-        energy_readings.count().get_result(conn).await
+        energy_readings
+            .filter(tenant_id.eq(tenant))
+            .count()
+            .get_result(conn)
+            .await
+    }
+
+    /// Where the [`EnergyReading::page`] after the reading starts.
+    pub fn cursor(&self) -> Keyset {
+        Keyset::After(self.reading_time, self.id)
+    }
+
+    /// A page of a tenant's readings in time order from `keyset`, limited
+    /// to those `filter` selects.
+    pub async fn page(
+        tenant: &str,
+        keyset: Keyset,
+        filter: ReadingFilter<'_>,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<EnergyReading>, diesel::result::Error> {
+        use crate::schema::energy_readings::dsl::*;
+
+        let mut query = energy_readings
+            .filter(tenant_id.eq(tenant))
+            .select(EnergyReading::as_select())
+            .into_boxed();
+        if let Some(plant) = filter.plant {
+            query = query.filter(plant_id.eq(plant));
+        }
+        if let Some(from) = filter.date_from {
+            query = query.filter(reading_time.ge(from));
+        }
+        if let Some(to) = filter.date_to {
+            query = query.filter(reading_time.lt(to));
+        }
+        if let Some(origin) = filter.source {
+            query = query.filter(source.eq(origin));
+        }
+        // Readings of several plants can share a time
+        match keyset {
+            Keyset::First => {
+                query = query.order((reading_time.asc(), id.asc()));
+            }
+            Keyset::After(time, after) => {
+                query = query
+                    .filter(
+                        reading_time
+                            .gt(time)
+                            .or(reading_time.eq(time).and(id.gt(after))),
+                    )
+                    .order((reading_time.asc(), id.asc()));
+            }
+            // Read towards older readings, the nearest first
+            Keyset::Before(time, before) => {
+                query = query
+                    .filter(
+                        reading_time
+                            .lt(time)
+                            .or(reading_time.eq(time).and(id.lt(before))),
+                    )
+                    .order((reading_time.desc(), id.desc()));
+            }
+        }
+
+        let mut page = query.limit(limit).load(conn).await?;
+        if let Keyset::Before(..) = keyset {
+            page.reverse();
+        }
+        Ok(page)
+    }
+
+    /// Delete a tenant's readings in `selection`, optionally only those
+    /// attributed to `plant`, writing a tombstone of each to
+    /// `energy_reading_revisions` in the same statement. Returns how many
+    /// were deleted at each reading time that had any, in time order.
+    pub async fn delete(
+        tenant: &str,
+        plant: Option<Uuid>,
+        selection: ReadingSelection<'_>,
+        request_id: Option<Uuid>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<DeletedReadings>, diesel::result::Error> {
+        let selected = match selection {
+            ReadingSelection::Range { .. } => {
+                "reading_time >= $4 AND reading_time < $5"
+            }
+            ReadingSelection::Times(_) => "reading_time = ANY($4)",
+        };
+        let query = diesel::sql_query(format!(
+            "WITH deleted AS (\
+                 DELETE FROM energy_readings WHERE tenant_id = $1 \
+                 AND ($2::uuid IS NULL OR plant_id = $2) AND {selected} \
+                 RETURNING id, tenant_id, reading_time, quantity_kwh, \
+                     plant_id, quality_code, source\
+             ), tombstones AS (\
+                 INSERT INTO energy_reading_revisions (reading_id, \
+                     tenant_id, reading_time, quantity_kwh, plant_id, \
+                     quality_code, source, operation, request_id) \
+                 SELECT id, tenant_id, reading_time, quantity_kwh, \
+                     plant_id, quality_code, source, '{}', $3 \
+                 FROM deleted RETURNING reading_time\
+             ) \
+             SELECT reading_time, COUNT(*) AS deleted FROM tombstones \
+             GROUP BY reading_time ORDER BY reading_time",
+            revision_operation::DELETE,
+        ))
+        .into_boxed()
+        .bind::<diesel::sql_types::Text, _>(tenant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(request_id);
+        let query = match selection {
+            ReadingSelection::Range { from, to } => query
+                .bind::<Timestamptz, _>(from)
+                .bind::<Timestamptz, _>(to),
+            ReadingSelection::Times(times) => {
+                query.bind::<diesel::sql_types::Array<Timestamptz>, _>(times)
+            }
+        };
+        query.load(conn).await
+    }
+
+    /// Summarize a tenant's readings in `[date_from, date_to)`, optionally
+    /// only those attributed to `plant`. Sums and extremes are `None` when
+    /// there are no readings.
+    pub async fn window_stats(
+        tenant: &str,
+        plant: Option<Uuid>,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<ReadingStats, diesel::result::Error> {
+        diesel::sql_query(
+            "SELECT COUNT(*) AS reading_count, \
+             SUM(quantity_kwh) AS total_kwh, \
+             AVG(quantity_kwh) AS average_kwh, \
+             MAX(quantity_kwh) AS max_kwh, \
+             MIN(quantity_kwh) AS min_kwh \
+             FROM energy_readings WHERE tenant_id = $1 \
+             AND ($2::uuid IS NULL OR plant_id = $2) \
+             AND reading_time >= $3 AND reading_time < $4",
+        )
+        .bind::<diesel::sql_types::Text, _>(tenant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant)
+        .bind::<Timestamptz, _>(date_from)
+        .bind::<Timestamptz, _>(date_to)
+        .get_result(conn)
+        .await
     }
 
-    /// Aggregate energy readings by the given truncation level (hour, day, month).
+    /// The query of [`EnergyReading::aggregate`], with `prefix` prepended
+    /// to its SQL. Each combination of prefix, week start, bounds and
+    /// options is prepared once per connection.
+    fn aggregate_query<'a>(
+        prefix: &str,
+        tenant: &'a str,
+        plant: Option<Uuid>,
+        trunc_level: &'a str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        options: AggregateOptions,
+    ) -> Prepared<BoxedSqlQuery<'a, Pg, SqlQuery>> {
+        let period = periods::period_sql(trunc_level, "reading_time");
+        let mut totals = format!(
+            "SELECT {period} AS period, \
+             SUM(quantity_kwh) AS total_kwh, \
+             COUNT(*) AS reading_count, \
+             COUNT(DISTINCT plant_id) \
+                 + CASE WHEN bool_or(plant_id IS NULL) THEN 1 ELSE 0 END \
+                 AS feed_count, \
+             MIN(quantity_kwh) AS min_kwh, \
+             MAX(quantity_kwh) AS max_kwh, \
+             ROUND(AVG(quantity_kwh), 4) AS avg_kwh \
+             FROM energy_readings WHERE tenant_id = $2 \
+             AND ($3::uuid IS NULL OR plant_id = $3)",
+        );
+
+        let mut param_idx = 4;
+        // Periods of the bounds, or of the first and last readings
+        let mut first = "(SELECT MIN(period) FROM totals)".to_string();
+        let mut last = "(SELECT MAX(period) FROM totals)".to_string();
+
+        if date_from.is_some() {
+            totals.push_str(&format!(" AND reading_time >= ${param_idx}"));
+            first = periods::period_sql(
+                trunc_level,
+                &format!("${param_idx}::timestamptz"),
+            );
+            param_idx += 1;
+        }
+        if date_to.is_some() {
+            totals.push_str(&format!(" AND reading_time < ${param_idx}"));
+            last = periods::period_sql(
+                trunc_level,
+                &format!(
+                    "(${param_idx}::timestamptz - interval '1 microsecond')"
+                ),
+            );
+        }
+
+        totals.push_str(" GROUP BY period");
+        // Gaps at the scale of the column, `0.0000` rather than `0`
+        let total = "COALESCE(total_kwh, 0::numeric(12, 4))";
+        let total = if options.cumulative {
+            format!("SUM({total}) OVER (ORDER BY period)")
+        } else {
+            total.to_string()
+        };
+        let step = periods::step(trunc_level).filter(|_| options.fill_gaps);
+        let query = match step {
+            // Every period from the first to the last, 0 without readings
+            Some(step) => format!(
+                "{prefix}WITH totals AS ({totals}) \
+                 SELECT period, {total} AS total_kwh, \
+                     COALESCE(reading_count, 0) AS reading_count, \
+                     COALESCE(feed_count, 0) AS feed_count, \
+                     min_kwh, max_kwh, avg_kwh \
+                 FROM generate_series({first}, {last}, interval '{step}') \
+                     AS series(period) \
+                 LEFT JOIN totals USING (period) ORDER BY period"
+            ),
+            None if options.cumulative => format!(
+                "{prefix}WITH totals AS ({totals}) \
+                 SELECT period, {total} AS total_kwh, reading_count, \
+                     feed_count, min_kwh, max_kwh, avg_kwh \
+                 FROM totals ORDER BY period"
+            ),
+            None => format!("{prefix}{totals} ORDER BY period"),
+        };
+
+        let mut query = diesel::sql_query(query)
+            .into_boxed()
+            .bind::<diesel::sql_types::Text, _>(periods::field(trunc_level))
+            .bind::<diesel::sql_types::Text, _>(tenant)
+            .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant);
+        if let Some(from) = date_from {
+            query = query.bind::<Timestamptz, _>(from);
+        }
+        if let Some(to) = date_to {
+            query = query.bind::<Timestamptz, _>(to);
+        }
+        Prepared::new(query)
+    }
+
+    /// Aggregate a tenant's energy readings by the given truncation level,
+    /// see [`crate::periods`], optionally only those attributed to `plant`.
+    /// The periods are returned as `options` say.
     pub async fn aggregate(
+        tenant: &str,
+        plant: Option<Uuid>,
         trunc_level: &str,
         date_from: Option<DateTime<Utc>>,
         date_to: Option<DateTime<Utc>>,
+        options: AggregateOptions,
         conn: &mut AsyncPgConnection,
     ) -> Result<Vec<AggregatedReading>, diesel::result::Error> {
-        let mut query = String::from(
-            "SELECT date_trunc($1, reading_time) AS period, \
-             SUM(quantity_kwh) AS total_kwh \
-             FROM energy_readings WHERE 1=1",
-        );
+        Self::aggregate_query(
+            "",
+            tenant,
+            plant,
+            trunc_level,
+            date_from,
+            date_to,
+            options,
+        )
+        .load(conn)
+        .await
+    }
 
-        let mut param_idx = 2;
+    /// Run the query of [`EnergyReading::aggregate`] under
+    /// `EXPLAIN (ANALYZE, BUFFERS)` and return its plan as JSON. The query
+    /// is executed, so this costs as much as the aggregation itself.
+    pub async fn explain_aggregate(
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        options: AggregateOptions,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<serde_json::Value, diesel::result::Error> {
+        Self::aggregate_query(
+            "EXPLAIN (ANALYZE, BUFFERS, FORMAT JSON) ",
+            tenant,
+            plant,
+            trunc_level,
+            date_from,
+            date_to,
+            options,
+        )
+        .get_result::<QueryPlan>(conn)
+        .await
+        .map(|row| row.plan)
+    }
 
+    /// Estimate the aggregation of [`EnergyReading::aggregate`] without
+    /// running it: its periods from the start bound, or the first reading,
+    /// to the end bound, or the last reading, counted from the bounds and
+    /// the index on reading times, and the readings it would scan, from the
+    /// planner's estimates under `EXPLAIN`.
+    pub async fn estimate_aggregate(
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<AggregateEstimate, diesel::result::Error> {
+        let step = periods::step(trunc_level).ok_or_else(|| {
+            diesel::result::Error::QueryBuilderError(
+                format!("Unknown truncation level {trunc_level}").into(),
+            )
+        })?;
+        let mut readings = "SELECT MIN(reading_time) AS first, \
+             MAX(reading_time) AS last \
+             FROM energy_readings WHERE tenant_id = $2 \
+             AND ($3::uuid IS NULL OR plant_id = $3)"
+            .to_string();
+
+        let mut param_idx = 4;
+        let mut first = periods::period_sql(trunc_level, "first");
+        let mut last = periods::period_sql(trunc_level, "last");
         if date_from.is_some() {
-            query.push_str(&format!(" AND reading_time >= ${param_idx}"));
+            readings.push_str(&format!(" AND reading_time >= ${param_idx}"));
+            first = periods::period_sql(
+                trunc_level,
+                &format!("${param_idx}::timestamptz"),
+            );
             param_idx += 1;
         }
         if date_to.is_some() {
-            query.push_str(&format!(" AND reading_time < ${param_idx}"));
+            readings.push_str(&format!(" AND reading_time < ${param_idx}"));
+            last = periods::period_sql(
+                trunc_level,
+                &format!(
+                    "(${param_idx}::timestamptz - interval '1 microsecond')"
+                ),
+            );
         }
 
-        query.push_str(" GROUP BY period ORDER BY period");
+        let mut query = diesel::sql_query(format!(
+            "WITH readings AS ({readings}) \
+             SELECT COUNT(*) AS periods FROM readings, \
+             generate_series({first}, {last}, interval '{step}')"
+        ))
+        .into_boxed()
+        .bind::<diesel::sql_types::Text, _>(periods::field(trunc_level))
+        .bind::<diesel::sql_types::Text, _>(tenant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant);
+        if let Some(from) = date_from {
+            query = query.bind::<Timestamptz, _>(from);
+        }
+        if let Some(to) = date_to {
+            query = query.bind::<Timestamptz, _>(to);
+        }
+        let periods = Prepared::new(query)
+            .get_result::<PeriodCount>(conn)
+            .await?
+            .periods;
 
-        match (date_from, date_to) {
-            (Some(from), Some(to)) => {
-                diesel::sql_query(&query)
-                    .bind::<diesel::sql_types::Text, _>(trunc_level)
-                    .bind::<Timestamptz, _>(from)
-                    .bind::<Timestamptz, _>(to)
-                    .load::<AggregatedReading>(conn)
-                    .await
-            }
-            (Some(from), None) => {
-                diesel::sql_query(&query)
-                    .bind::<diesel::sql_types::Text, _>(trunc_level)
-                    .bind::<Timestamptz, _>(from)
-                    .load::<AggregatedReading>(conn)
-                    .await
-            }
-            (None, Some(to)) => {
-                diesel::sql_query(&query)
-                    .bind::<diesel::sql_types::Text, _>(trunc_level)
-                    .bind::<Timestamptz, _>(to)
-                    .load::<AggregatedReading>(conn)
-                    .await
-            }
-            (None, None) => {
-                diesel::sql_query(&query)
-                    .bind::<diesel::sql_types::Text, _>(trunc_level)
-                    .load::<AggregatedReading>(conn)
-                    .await
-            }
+        let plan = Self::aggregate_query(
+            "EXPLAIN (FORMAT JSON) ",
+            tenant,
+            plant,
+            trunc_level,
+            date_from,
+            date_to,
+            AggregateOptions::default(),
+        )
+        .get_result::<QueryPlan>(conn)
+        .await?
+        .plan;
+
+        Ok(AggregateEstimate {
+            periods,
+            scanned_rows: planned_rows(&plan, "energy_readings", 1.0).round()
+                as i64,
+        })
+    }
+
+    /// Count, by day, a tenant's readings in `[date_from, date_to)`,
+    /// optionally only those attributed to `plant` or from `origin`, the
+    /// `interval_secs` slots of each feed (plant, or no plant) they fall in
+    /// and those out of range. Days without readings are left out.
+    pub async fn daily_quality(
+        tenant: &str,
+        plant: Option<Uuid>,
+        origin: Option<&str>,
+        interval_secs: i32,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<DailyQuality>, diesel::result::Error> {
+        diesel::sql_query(
+            "SELECT date_trunc('day', r.reading_time, 'UTC') AS day, \
+             COUNT(*) AS reading_count, \
+             COUNT(DISTINCT (r.plant_id, \
+                 floor(extract(epoch FROM r.reading_time) / $3))) \
+                 AS slot_count, \
+             COUNT(*) FILTER (WHERE r.quantity_kwh < 0 \
+                 OR r.quantity_kwh > p.capacity_mw * $3 / 3.6) \
+                 AS out_of_range_count \
+             FROM energy_readings r \
+             LEFT JOIN plants p ON p.id = r.plant_id \
+             WHERE r.tenant_id = $1 \
+             AND ($2::uuid IS NULL OR r.plant_id = $2) \
+             AND r.reading_time >= $4 AND r.reading_time < $5 \
+             AND ($6::text IS NULL OR r.source = $6) \
+             GROUP BY day ORDER BY day",
+        )
+        .bind::<diesel::sql_types::Text, _>(tenant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant)
+        .bind::<diesel::sql_types::Integer, _>(interval_secs)
+        .bind::<Timestamptz, _>(date_from)
+        .bind::<Timestamptz, _>(date_to)
+        .bind::<Nullable<diesel::sql_types::Text>, _>(origin)
+        .load(conn)
+        .await
+    }
+
+    /// Number of feeds, plants and readings without a plant, with readings
+    /// of a tenant in `[date_from, date_to)`, optionally only readings from
+    /// `origin`.
+    pub async fn feed_count(
+        tenant: &str,
+        origin: Option<&str>,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<i64, diesel::result::Error> {
+        diesel::sql_query(
+            "SELECT COUNT(DISTINCT plant_id) \
+             + CASE WHEN bool_or(plant_id IS NULL) THEN 1 ELSE 0 END \
+             AS feeds \
+             FROM energy_readings WHERE tenant_id = $1 \
+             AND reading_time >= $2 AND reading_time < $3 \
+             AND ($4::text IS NULL OR source = $4)",
+        )
+        .bind::<diesel::sql_types::Text, _>(tenant)
+        .bind::<Timestamptz, _>(date_from)
+        .bind::<Timestamptz, _>(date_to)
+        .bind::<Nullable<diesel::sql_types::Text>, _>(origin)
+        .get_result::<FeedCount>(conn)
+        .await
+        .map(|count| count.feeds)
+    }
+
+    /// A tenant's readings in `[date_from, date_to)`, optionally only those
+    /// attributed to `plant`, summed by reading time, in time order.
+    pub async fn series(
+        tenant: &str,
+        plant: Option<Uuid>,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<SeriesPoint>, diesel::result::Error> {
+        diesel::sql_query(
+            "SELECT reading_time, SUM(quantity_kwh)::float8 AS kwh \
+             FROM energy_readings WHERE tenant_id = $1 \
+             AND ($2::uuid IS NULL OR plant_id = $2) \
+             AND ($3::timestamptz IS NULL OR reading_time >= $3) \
+             AND ($4::timestamptz IS NULL OR reading_time < $4) \
+             GROUP BY reading_time ORDER BY reading_time",
+        )
+        .bind::<diesel::sql_types::Text, _>(tenant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant)
+        .bind::<Nullable<Timestamptz>, _>(date_from)
+        .bind::<Nullable<Timestamptz>, _>(date_to)
+        .load(conn)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone};
+    use diesel::debug_query;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// An instant between 2000 and 2100, as written in a random UTC offset.
+    fn instant() -> impl Strategy<Value = DateTime<Utc>> {
+        (946_684_800..4_102_444_800_i64, -48..=56_i32).prop_map(
+            |(secs, quarters)| {
+                let offset = FixedOffset::east_opt(quarters * 900).unwrap();
+                offset.timestamp_opt(secs, 0).unwrap().with_timezone(&Utc)
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn test_aggregate_query_binds_every_placeholder(
+            trunc_level in prop::sample::select(
+                vec!["hour", "day", "week_sunday", "month"],
+            ),
+            plant in prop::option::of(any::<u128>().prop_map(Uuid::from_u128)),
+            date_from in prop::option::of(instant()),
+            date_to in prop::option::of(instant()),
+            fill_gaps in any::<bool>(),
+            cumulative in any::<bool>(),
+        ) {
+            let options = AggregateOptions { fill_gaps, cumulative };
+            let query = EnergyReading::aggregate_query(
+                "", "tenant", plant, trunc_level, date_from, date_to, options,
+            );
+            let debug = debug_query::<Pg, _>(&query).to_string();
+            let (sql, binds) = debug.split_once(" -- binds: ").unwrap();
+
+            let binds_count = 3
+                + usize::from(date_from.is_some())
+                + usize::from(date_to.is_some());
+            let mut placeholders = sql
+                .split('$')
+                .skip(1)
+                .map(|rest| {
+                    let digits = rest
+                        .chars()
+                        .take_while(char::is_ascii_digit)
+                        .collect::<String>();
+                    digits.parse::<usize>().unwrap()
+                })
+                .collect::<Vec<_>>();
+            placeholders.sort_unstable();
+            placeholders.dedup();
+            prop_assert_eq!(
+                placeholders,
+                (1..=binds_count).collect::<Vec<_>>()
+            );
+
+            prop_assert_eq!(
+                sql.contains("reading_time >= $4"),
+                date_from.is_some()
+            );
+            let to_placeholder = if date_from.is_some() { 5 } else { 4 };
+            prop_assert_eq!(
+                sql.contains(&format!("reading_time < ${to_placeholder}")),
+                date_to.is_some()
+            );
+            prop_assert_eq!(sql.contains("generate_series"), fill_gaps);
+            prop_assert_eq!(sql.contains(" OVER "), cumulative);
+
+            // Binds are listed in placeholder order
+            let mut expected = vec![
+                format!("{:?}", periods::field(trunc_level)),
+                format!("{:?}", "tenant"),
+                format!("{plant:?}"),
+            ];
+            expected.extend(
+                date_from.iter().chain(&date_to).map(|d| format!("{d:?}")),
+            );
+            let positions = expected
+                .iter()
+                .map(|value| binds.find(value.as_str()).unwrap())
+                .collect::<Vec<_>>();
+            prop_assert!(positions.is_sorted());
         }
     }
+
+    #[test]
+    fn test_planned_rows() {
+        let plan = serde_json::json!([{"Plan": {
+            "Node Type": "Aggregate",
+            "Plan Rows": 60,
+            "Plans": [{
+                "Node Type": "Gather",
+                "Workers Planned": 2,
+                "Plan Rows": 1000,
+                "Plans": [{
+                    "Node Type": "Parallel Seq Scan",
+                    "Relation Name": "energy_readings",
+                    "Parallel Aware": true,
+                    "Plan Rows": 400,
+                }, {
+                    "Node Type": "Seq Scan",
+                    "Relation Name": "plants",
+                    "Parallel Aware": false,
+                    "Plan Rows": 3,
+                }],
+            }, {
+                "Node Type": "Index Scan",
+                "Relation Name": "energy_readings",
+                "Parallel Aware": false,
+                "Plan Rows": 10,
+            }],
+        }}]);
+        assert_eq!(planned_rows(&plan, "energy_readings", 1.0), 1210.0);
+        assert_eq!(planned_rows(&plan, "plants", 1.0), 3.0);
+        assert_eq!(planned_rows(&plan, "weather", 1.0), 0.0);
+    }
 }
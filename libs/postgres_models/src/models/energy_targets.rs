@@ -0,0 +1,179 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Numeric, Text, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Energy a tenant, or one of its plants, aims at over a period.
+#[derive(
+    Queryable, QueryableByName, Selectable, Debug, Clone, serde::Serialize,
+)]
+#[diesel(table_name = crate::schema::energy_targets)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EnergyTarget {
+    pub id: Uuid,
+    pub tenant_id: String,
+    /// The plant whose readings count, all of the tenant's when `None`
+    pub plant_id: Option<Uuid>,
+    pub name: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub target_kwh: BigDecimal,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::energy_targets)]
+pub struct NewEnergyTarget {
+    pub tenant_id: String,
+    pub plant_id: Option<Uuid>,
+    pub name: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub target_kwh: BigDecimal,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::energy_targets)]
+pub struct UpdateEnergyTarget {
+    pub name: Option<String>,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
+    pub target_kwh: Option<BigDecimal>,
+}
+
+/// A target with the energy read in its period so far.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct TargetActual {
+    #[diesel(embed)]
+    pub target: EnergyTarget,
+    /// Energy read from the start of the period to its end or, when
+    /// earlier, the time of the query
+    #[diesel(sql_type = Numeric)]
+    pub actual_kwh: BigDecimal,
+}
+
+impl EnergyTarget {
+    pub async fn create(
+        entry: NewEnergyTarget,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::energy_targets::dsl::*;
+
+        diesel::insert_into(energy_targets)
+            .values(&entry)
+            .returning(EnergyTarget::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// A target of the tenant, `None` when it does not exist or belongs to
+    /// another tenant.
+    pub async fn find(
+        tenant: &str,
+        target_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::energy_targets::dsl::*;
+
+        energy_targets
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(target_id))
+            .select(EnergyTarget::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// A tenant's targets, of one plant if given, ordered by period start.
+    pub async fn list(
+        tenant: &str,
+        plant: Option<Uuid>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::energy_targets::dsl::*;
+
+        let mut query = energy_targets
+            .filter(tenant_id.eq(tenant))
+            .select(EnergyTarget::as_select())
+            .into_boxed();
+        if let Some(plant) = plant {
+            query = query.filter(plant_id.eq(plant));
+        }
+        query.order((period_start, id)).load(conn).await
+    }
+
+    /// Apply `changes` to a target of the tenant. `None` when it does not
+    /// exist.
+    pub async fn update(
+        tenant: &str,
+        target_id: Uuid,
+        changes: UpdateEnergyTarget,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::energy_targets::dsl::*;
+
+        diesel::update(
+            energy_targets
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(target_id)),
+        )
+        .set(&changes)
+        .returning(EnergyTarget::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Delete a target of the tenant. Returns the number of deleted rows.
+    pub async fn delete(
+        tenant: &str,
+        target_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::energy_targets::dsl::*;
+
+        diesel::delete(
+            energy_targets
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(target_id)),
+        )
+        .execute(conn)
+        .await
+    }
+
+    /// A tenant's targets, of one plant if given, whose period overlaps
+    /// `[date_from, date_to)`, each with the energy read in its period up
+    /// to `at`.
+    pub async fn actuals(
+        tenant: &str,
+        plant: Option<Uuid>,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        at: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<TargetActual>, diesel::result::Error> {
+        diesel::sql_query(
+            "SELECT t.*, COALESCE(SUM(r.quantity_kwh), 0) AS actual_kwh \
+             FROM energy_targets t \
+             LEFT JOIN energy_readings r ON r.tenant_id = t.tenant_id \
+                 AND (t.plant_id IS NULL OR r.plant_id = t.plant_id) \
+                 AND r.reading_time >= t.period_start \
+                 AND r.reading_time < LEAST(t.period_end, $5) \
+             WHERE t.tenant_id = $1 \
+             AND ($2::uuid IS NULL OR t.plant_id = $2) \
+             AND ($3::timestamptz IS NULL OR t.period_end > $3) \
+             AND ($4::timestamptz IS NULL OR t.period_start < $4) \
+             GROUP BY t.id ORDER BY t.period_start, t.id",
+        )
+        .bind::<Text, _>(tenant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant)
+        .bind::<Nullable<Timestamptz>, _>(date_from)
+        .bind::<Nullable<Timestamptz>, _>(date_to)
+        .bind::<Timestamptz, _>(at)
+        .load(conn)
+        .await
+    }
+}
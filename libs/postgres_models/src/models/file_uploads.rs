@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Lifecycle states of a [`FileUpload`].
+pub mod upload_status {
+    pub const UPLOADING: &str = "uploading";
+    pub const IMPORTING: &str = "importing";
+    pub const IMPORTED: &str = "imported";
+    pub const FAILED: &str = "failed";
+}
+
+/// A readings file uploaded in chunks.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::file_uploads)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct FileUpload {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub file_name: String,
+    /// Bytes of the whole file
+    pub size: i64,
+    /// Hex SHA-256 digest the file must have
+    pub sha256: String,
+    /// Bytes received so far, where the next chunk starts
+    pub received: i64,
+    /// See [`upload_status`]
+    pub status: String,
+    /// Where the file was stored, once complete
+    pub location: Option<String>,
+    /// Import run of the file, once imported
+    pub import_run_id: Option<Uuid>,
+    /// Why the upload or its import failed
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::file_uploads)]
+pub struct NewFileUpload {
+    pub tenant_id: String,
+    pub file_name: String,
+    pub size: i64,
+    pub sha256: String,
+}
+
+impl FileUpload {
+    pub async fn create(
+        upload: NewFileUpload,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::file_uploads::dsl::*;
+
+        diesel::insert_into(file_uploads)
+            .values(upload)
+            .returning(FileUpload::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    pub async fn find(
+        upload_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::file_uploads::dsl::*;
+
+        file_uploads
+            .find(upload_id)
+            .select(FileUpload::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Record that the bytes up to `to` were received, if the upload is
+    /// still at `from`; `None` when another chunk got there first.
+    pub async fn advance(
+        upload_id: Uuid,
+        from: i64,
+        to: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::file_uploads::dsl::*;
+
+        diesel::update(
+            file_uploads
+                .filter(id.eq(upload_id))
+                .filter(status.eq(upload_status::UPLOADING))
+                .filter(received.eq(from)),
+        )
+        .set((received.eq(to), updated_at.eq(diesel::dsl::now)))
+        .returning(FileUpload::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Record that the complete file was stored at `stored_at` and is
+    /// being imported.
+    pub async fn importing(
+        upload_id: Uuid,
+        stored_at: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::file_uploads::dsl::*;
+
+        diesel::update(file_uploads.filter(id.eq(upload_id)))
+            .set((
+                status.eq(upload_status::IMPORTING),
+                location.eq(stored_at),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(FileUpload::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Record the import run of the file, and why it failed if it did.
+    pub async fn imported(
+        upload_id: Uuid,
+        run_id: Uuid,
+        reason: Option<&str>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::file_uploads::dsl::*;
+
+        let outcome = match reason {
+            None => upload_status::IMPORTED,
+            Some(_) => upload_status::FAILED,
+        };
+        diesel::update(file_uploads.filter(id.eq(upload_id)))
+            .set((
+                status.eq(outcome),
+                import_run_id.eq(run_id),
+                error.eq(reason),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Record that the upload failed with `reason`.
+    pub async fn fail(
+        upload_id: Uuid,
+        reason: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::file_uploads::dsl::*;
+
+        diesel::update(file_uploads.filter(id.eq(upload_id)))
+            .set((
+                status.eq(upload_status::FAILED),
+                error.eq(reason),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .returning(FileUpload::as_returning())
+            .get_result(conn)
+            .await
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Lifecycle states of an [`ImportRun`].
+pub mod run_status {
+    pub const RUNNING: &str = "running";
+    pub const COMPLETED: &str = "completed";
+    pub const FAILED: &str = "failed";
+}
+
+/// One import of a readings file.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::import_runs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ImportRun {
+    pub id: Uuid,
+    pub tenant_id: String,
+    /// Path of the file as given to the import
+    pub file: String,
+    /// See [`run_status`]
+    pub status: String,
+    /// Readings stored, once completed
+    pub inserted: Option<i32>,
+    /// Readings in the file, once completed
+    pub total: Option<i32>,
+    /// Why the import failed
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Timezone the local times of the file were read in
+    pub source_timezone: String,
+    /// How local times repeated or skipped by a DST change were read
+    pub dst_policy: String,
+    /// Readings whose time the DST policy decided, once completed
+    pub dst_adjusted: Option<i32>,
+    /// Readings dropped by the quantity policy, once completed
+    pub rejected: Option<i32>,
+    /// Readings whose quantity the policy clamped, once completed
+    pub clamped: Option<i32>,
+    /// Readings stored with a quality code, once completed
+    pub flagged: Option<i32>,
+}
+
+/// Counts of a completed [`ImportRun`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunCounts {
+    /// Readings stored
+    pub inserted: usize,
+    /// Readings in the file
+    pub total: usize,
+    pub dst_adjusted: usize,
+    pub rejected: usize,
+    pub clamped: usize,
+    pub flagged: usize,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::import_runs)]
+pub struct NewImportRun<'a> {
+    pub tenant_id: &'a str,
+    pub file: &'a str,
+    pub source_timezone: &'a str,
+    pub dst_policy: &'a str,
+}
+
+impl ImportRun {
+    /// Record the start of an import.
+    pub async fn start(
+        run: NewImportRun<'_>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::import_runs::dsl::*;
+
+        diesel::insert_into(import_runs)
+            .values(run)
+            .returning(ImportRun::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Record that a run completed.
+    pub async fn complete(
+        run_id: Uuid,
+        counts: RunCounts,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::import_runs::dsl::*;
+
+        let count = |n: usize| i32::try_from(n).unwrap_or(i32::MAX);
+        diesel::update(import_runs.filter(id.eq(run_id)))
+            .set((
+                status.eq(run_status::COMPLETED),
+                inserted.eq(count(counts.inserted)),
+                total.eq(count(counts.total)),
+                dst_adjusted.eq(count(counts.dst_adjusted)),
+                rejected.eq(count(counts.rejected)),
+                clamped.eq(count(counts.clamped)),
+                flagged.eq(count(counts.flagged)),
+                finished_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Record that a run failed with `reason`.
+    pub async fn fail(
+        run_id: Uuid,
+        reason: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::import_runs::dsl::*;
+
+        diesel::update(import_runs.filter(id.eq(run_id)))
+            .set((
+                status.eq(run_status::FAILED),
+                error.eq(reason),
+                finished_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Files among `paths` the tenant has completed an import of.
+    pub async fn completed_files(
+        tenant: &str,
+        paths: &[String],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<String>, diesel::result::Error> {
+        use crate::schema::import_runs::dsl::*;
+
+        import_runs
+            .filter(tenant_id.eq(tenant))
+            .filter(status.eq(run_status::COMPLETED))
+            .filter(file.eq_any(paths))
+            .select(file)
+            .distinct()
+            .load(conn)
+            .await
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Values of [`MaintenanceWindow::status`].
+pub mod window_status {
+    pub const SCHEDULED: &str = "scheduled";
+    pub const ACTIVE: &str = "active";
+    pub const COMPLETED: &str = "completed";
+}
+
+/// Planned maintenance of a plant.
+#[derive(
+    Queryable, QueryableByName, Selectable, Debug, Clone, serde::Serialize,
+)]
+#[diesel(table_name = crate::schema::maintenance_windows)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MaintenanceWindow {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub plant_id: Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub notes: Option<String>,
+    /// Set by the scheduler from the window's times
+    pub status: String,
+    /// Plant status to restore when the window ends, set while it is active
+    pub previous_plant_status: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::maintenance_windows)]
+pub struct NewMaintenanceWindow {
+    pub tenant_id: String,
+    pub plant_id: Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub notes: Option<String>,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::maintenance_windows)]
+pub struct UpdateMaintenanceWindow {
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub notes: Option<Option<String>>,
+    pub status: Option<String>,
+}
+
+impl MaintenanceWindow {
+    /// The status the window should have at `now`.
+    pub fn status_at(&self, now: DateTime<Utc>) -> &'static str {
+        if now < self.starts_at {
+            window_status::SCHEDULED
+        } else if now < self.ends_at {
+            window_status::ACTIVE
+        } else {
+            window_status::COMPLETED
+        }
+    }
+
+    pub async fn create(
+        entry: NewMaintenanceWindow,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        diesel::insert_into(maintenance_windows)
+            .values(&entry)
+            .returning(MaintenanceWindow::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// A window of the tenant, `None` when it does not exist or belongs to
+    /// another tenant.
+    pub async fn find(
+        tenant: &str,
+        window_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        maintenance_windows
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(window_id))
+            .select(MaintenanceWindow::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// Like [`MaintenanceWindow::find`], locking the window until the
+    /// transaction ends so the scheduler cannot move it meanwhile.
+    pub async fn find_for_update(
+        tenant: &str,
+        window_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        maintenance_windows
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(window_id))
+            .select(MaintenanceWindow::as_select())
+            .for_update()
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// A tenant's windows, of one plant if given, ordered by start.
+    pub async fn list(
+        tenant: &str,
+        plant: Option<Uuid>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        let mut query = maintenance_windows
+            .filter(tenant_id.eq(tenant))
+            .select(MaintenanceWindow::as_select())
+            .into_boxed();
+        if let Some(plant) = plant {
+            query = query.filter(plant_id.eq(plant));
+        }
+        query.order((starts_at, id)).load(conn).await
+    }
+
+    /// The active windows of a plant.
+    pub async fn active_for_plant(
+        plant: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        maintenance_windows
+            .filter(plant_id.eq(plant))
+            .filter(status.eq(window_status::ACTIVE))
+            .order((starts_at, id))
+            .select(MaintenanceWindow::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Lock up to `limit` windows whose status is behind `now`, windows to
+    /// end first.
+    ///
+    /// Uses `FOR UPDATE SKIP LOCKED` so concurrent schedulers never claim the
+    /// same window; the caller should update them in the same transaction.
+    pub async fn claim_due(
+        now: DateTime<Utc>,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        diesel::sql_query(
+            "SELECT * FROM maintenance_windows \
+             WHERE (status = 'scheduled' AND starts_at <= $1) \
+                OR (status = 'active' AND (ends_at <= $1 OR starts_at > $1)) \
+             ORDER BY status = 'active' DESC, starts_at, id \
+             LIMIT $2 \
+             FOR UPDATE SKIP LOCKED",
+        )
+        .bind::<Timestamptz, _>(now)
+        .bind::<BigInt, _>(limit)
+        .load(conn)
+        .await
+    }
+
+    /// Set a window's status and the plant status it restores.
+    pub async fn set_status(
+        window_id: Uuid,
+        new_status: &str,
+        plant_status: Option<String>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        diesel::update(maintenance_windows.find(window_id))
+            .set((
+                status.eq(new_status),
+                previous_plant_status.eq(plant_status),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Apply `changes` to a window of the tenant. `None` when it does not
+    /// exist.
+    pub async fn update(
+        tenant: &str,
+        window_id: Uuid,
+        changes: UpdateMaintenanceWindow,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        diesel::update(
+            maintenance_windows
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(window_id)),
+        )
+        .set(&changes)
+        .returning(MaintenanceWindow::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Delete a window. Returns the number of deleted rows.
+    pub async fn delete(
+        window_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::maintenance_windows::dsl::*;
+
+        diesel::delete(maintenance_windows.find(window_id))
+            .execute(conn)
+            .await
+    }
+}
@@ -0,0 +1,132 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Numeric, Text, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::periods;
+
+/// The day-ahead price of a bidding zone for one delivery hour.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::market_prices)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MarketPrice {
+    /// Bidding zone, e.g. `SE3`
+    pub zone: String,
+    /// Start of the delivery hour
+    pub starts_at: DateTime<Utc>,
+    pub price_per_mwh: BigDecimal,
+    pub currency: String,
+    /// Feed the price comes from
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::market_prices)]
+pub struct NewMarketPrice {
+    pub zone: String,
+    pub starts_at: DateTime<Utc>,
+    pub price_per_mwh: BigDecimal,
+    pub currency: String,
+    pub source: String,
+}
+
+/// A tenant's energy in a period, valued at the market prices of a zone.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct PricedPeriod {
+    #[diesel(sql_type = Timestamptz)]
+    pub period: DateTime<Utc>,
+    #[diesel(sql_type = Numeric)]
+    pub total_kwh: BigDecimal,
+    /// Value of the readings with a price, `None` when none has one
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub market_cost: Option<BigDecimal>,
+    /// Energy of the readings without a price for their hour
+    #[diesel(sql_type = Numeric)]
+    pub unpriced_kwh: BigDecimal,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub currency: Option<String>,
+}
+
+impl MarketPrice {
+    /// Insert prices, replacing those already stored for the same zone and
+    /// hour.
+    pub async fn upsert(
+        prices: Vec<NewMarketPrice>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::market_prices::dsl::*;
+        use diesel::upsert::excluded;
+
+        diesel::insert_into(market_prices)
+            .values(&prices)
+            .on_conflict((zone, starts_at))
+            .do_update()
+            .set((
+                price_per_mwh.eq(excluded(price_per_mwh)),
+                currency.eq(excluded(currency)),
+                source.eq(excluded(source)),
+                fetched_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Whether any price of `market_zone` is stored.
+    pub async fn zone_exists(
+        market_zone: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<bool, diesel::result::Error> {
+        use crate::schema::market_prices::dsl::*;
+
+        diesel::select(diesel::dsl::exists(
+            market_prices.filter(zone.eq(market_zone)),
+        ))
+        .get_result(conn)
+        .await
+    }
+
+    /// Sum a tenant's readings by the given truncation level, see
+    /// [`crate::periods`], optionally only those attributed to `plant`, and value each
+    /// reading at the price of `market_zone` for its hour. Costs are
+    /// rounded to 4 decimals.
+    pub async fn price_readings(
+        tenant: &str,
+        plant: Option<Uuid>,
+        market_zone: &str,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<PricedPeriod>, diesel::result::Error> {
+        diesel::sql_query(format!(
+            "SELECT {} AS period, \
+             SUM(r.quantity_kwh) AS total_kwh, \
+             ROUND(SUM(r.quantity_kwh * p.price_per_mwh / 1000), 4) \
+                 AS market_cost, \
+             ROUND(COALESCE(SUM(r.quantity_kwh) \
+                 FILTER (WHERE p.price_per_mwh IS NULL), 0), 4) \
+                 AS unpriced_kwh, \
+             MIN(p.currency) AS currency \
+             FROM energy_readings r \
+             LEFT JOIN market_prices p ON p.zone = $3 \
+                 AND p.starts_at = date_trunc('hour', r.reading_time) \
+             WHERE r.tenant_id = $2 \
+             AND ($4::uuid IS NULL OR r.plant_id = $4) \
+             AND ($5::timestamptz IS NULL OR r.reading_time >= $5) \
+             AND ($6::timestamptz IS NULL OR r.reading_time < $6) \
+             GROUP BY period ORDER BY period",
+            periods::period_sql(trunc_level, "r.reading_time"),
+        ))
+        .bind::<Text, _>(periods::field(trunc_level))
+        .bind::<Text, _>(tenant)
+        .bind::<Text, _>(market_zone)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant)
+        .bind::<Nullable<Timestamptz>, _>(date_from)
+        .bind::<Nullable<Timestamptz>, _>(date_to)
+        .load(conn)
+        .await
+    }
+}
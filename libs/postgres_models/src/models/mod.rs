@@ -1,2 +1,34 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Tenant of rows created before tenancy and of single-tenant deployments.
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Where a page of a list paginated by `(time, id)` starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keyset {
+    First,
+    /// The items listed after this one
+    After(DateTime<Utc>, Uuid),
+    /// The items listed before this one
+    Before(DateTime<Utc>, Uuid),
+}
+
+pub mod alerts;
+pub mod api_key_preferences;
+pub mod api_keys;
+pub mod dataset_snapshots;
+pub mod energy_reading_revisions;
 pub mod energy_readings;
+pub mod energy_targets;
+pub mod file_uploads;
+pub mod import_runs;
+pub mod maintenance_windows;
+pub mod market_prices;
+pub mod notifications;
+pub mod outbox;
+pub mod plants;
 pub mod query_history;
+pub mod saved_queries;
+pub mod weather;
+pub mod webhooks;
@@ -0,0 +1,296 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Interval};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use super::webhooks::{DeliveryAttempt, delivery_status};
+
+/// Values of [`NotificationChannel::kind`].
+pub mod channel_kind {
+    pub const WEBHOOK: &str = "webhook";
+    pub const SLACK: &str = "slack";
+    pub const EMAIL: &str = "email";
+}
+
+/// A destination for a tenant's notifications.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::notification_channels)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NotificationChannel {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub name: String,
+    pub kind: String,
+    /// URL of `webhook` and `slack` channels, comma separated recipients of
+    /// `email` channels
+    pub target: String,
+    /// System events sent to the channel
+    pub events: Vec<Option<String>>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::notification_channels)]
+pub struct NewNotificationChannel {
+    pub tenant_id: String,
+    pub name: String,
+    pub kind: String,
+    pub target: String,
+    pub events: Vec<Option<String>>,
+    pub enabled: bool,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::notification_channels)]
+pub struct UpdateNotificationChannel {
+    pub name: Option<String>,
+    pub target: Option<String>,
+    pub events: Option<Vec<Option<String>>>,
+    pub enabled: Option<bool>,
+}
+
+/// A notification queued for, or sent to, a channel. Statuses are those of
+/// webhook deliveries, see [`delivery_status`].
+#[derive(
+    Queryable, QueryableByName, Selectable, Debug, Clone, serde::Serialize,
+)]
+#[diesel(table_name = crate::schema::notifications)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Notification {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub tenant_id: String,
+    /// `alert_fired` or a system event
+    pub event: String,
+    pub subject: String,
+    pub body: String,
+    pub data: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::notifications)]
+pub struct NewNotification {
+    pub channel_id: Uuid,
+    pub tenant_id: String,
+    pub event: String,
+    pub subject: String,
+    pub body: String,
+    pub data: serde_json::Value,
+}
+
+impl NotificationChannel {
+    /// System events the channel is subscribed to, ignoring NULL items.
+    pub fn subscribed_events(&self) -> Vec<String> {
+        self.events.iter().flatten().cloned().collect()
+    }
+
+    pub async fn create(
+        entry: NewNotificationChannel,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::notification_channels::dsl::*;
+
+        diesel::insert_into(notification_channels)
+            .values(&entry)
+            .returning(NotificationChannel::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// A channel of the tenant, `None` when it does not exist or belongs to
+    /// another tenant.
+    pub async fn find(
+        tenant: &str,
+        channel_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::notification_channels::dsl::*;
+
+        notification_channels
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(channel_id))
+            .select(NotificationChannel::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// A tenant's channels ordered by name.
+    pub async fn list(
+        tenant: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::notification_channels::dsl::*;
+
+        notification_channels
+            .filter(tenant_id.eq(tenant))
+            .order((name, id))
+            .select(NotificationChannel::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Enabled channels subscribed to a system event, of one tenant or of
+    /// every tenant when `tenant` is `None`.
+    pub async fn subscribed_to(
+        event: &str,
+        tenant: Option<&str>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::notification_channels::dsl::*;
+        use diesel::PgArrayExpressionMethods;
+
+        let mut query = notification_channels
+            .filter(enabled.eq(true))
+            .filter(events.contains(vec![Some(event.to_string())]))
+            .select(NotificationChannel::as_select())
+            .into_boxed();
+        if let Some(tenant) = tenant {
+            query = query.filter(tenant_id.eq(tenant));
+        }
+        query.load(conn).await
+    }
+
+    /// Apply `changes` to a channel of the tenant. `None` when it does not
+    /// exist.
+    pub async fn update(
+        tenant: &str,
+        channel_id: Uuid,
+        changes: UpdateNotificationChannel,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::notification_channels::dsl::*;
+
+        diesel::update(
+            notification_channels
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(channel_id)),
+        )
+        .set(&changes)
+        .returning(NotificationChannel::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Delete a channel of the tenant and its notifications. Returns the
+    /// number of deleted channels.
+    pub async fn delete(
+        tenant: &str,
+        channel_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::notification_channels::dsl::*;
+
+        diesel::delete(
+            notification_channels
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(channel_id)),
+        )
+        .execute(conn)
+        .await
+    }
+}
+
+impl Notification {
+    /// Queue notifications for dispatch.
+    pub async fn enqueue(
+        entries: Vec<NewNotification>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::notifications::dsl::*;
+
+        diesel::insert_into(notifications)
+            .values(&entries)
+            .returning(Notification::as_returning())
+            .get_results(conn)
+            .await
+    }
+
+    /// The last `limit` notifications of a channel, most recent first.
+    pub async fn latest_for_channel(
+        for_channel_id: Uuid,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::notifications::dsl::*;
+
+        notifications
+            .filter(channel_id.eq(for_channel_id))
+            .order(created_at.desc())
+            .limit(limit)
+            .select(Notification::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Atomically claim up to `limit` due notifications for this
+    /// dispatcher.
+    ///
+    /// Uses `FOR UPDATE SKIP LOCKED` so concurrent replicas never claim the
+    /// same row. Rows stuck `in_flight` for longer than `stale_after` are
+    /// reclaimed, giving at-least-once delivery.
+    pub async fn claim_due(
+        limit: i32,
+        stale_after: std::time::Duration,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        let stale_after = diesel::data_types::PgInterval::from_microseconds(
+            stale_after.as_micros() as i64,
+        );
+
+        diesel::sql_query(
+            "UPDATE notifications SET status = 'in_flight', \
+             attempts = attempts + 1 \
+             WHERE id IN ( \
+                 SELECT id FROM notifications \
+                 WHERE (status = 'pending' AND next_attempt_at <= NOW()) \
+                    OR (status = 'in_flight' AND updated_at < NOW() - $2) \
+                 ORDER BY next_attempt_at \
+                 LIMIT $1 \
+                 FOR UPDATE SKIP LOCKED \
+             ) \
+             RETURNING *",
+        )
+        .bind::<Integer, _>(limit)
+        .bind::<Interval, _>(stale_after)
+        .load::<Notification>(conn)
+        .await
+    }
+
+    /// Record the outcome of an attempt: delivered, rescheduled or failed.
+    pub async fn record_attempt(
+        notification_id: Uuid,
+        attempt: DeliveryAttempt,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::notifications::dsl::*;
+
+        let succeeded = attempt.error.is_none();
+        let new_status = match (succeeded, attempt.retry_at) {
+            (true, _) => delivery_status::DELIVERED,
+            (false, Some(_)) => delivery_status::PENDING,
+            (false, None) => delivery_status::FAILED,
+        };
+
+        diesel::update(notifications.find(notification_id))
+            .set((
+                status.eq(new_status),
+                last_error.eq(attempt.error),
+                next_attempt_at.eq(attempt.retry_at.unwrap_or_else(Utc::now)),
+                delivered_at.eq(succeeded.then(Utc::now)),
+            ))
+            .execute(conn)
+            .await
+    }
+}
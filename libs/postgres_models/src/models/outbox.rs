@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// An event recorded with the change it describes, published by the relay.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::outbox)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub tenant_id: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::outbox)]
+pub struct NewOutboxEvent {
+    pub event_type: String,
+    pub tenant_id: String,
+    pub payload: serde_json::Value,
+}
+
+impl OutboxEvent {
+    /// Record an event. Call it in the transaction making the change, so the
+    /// event is stored if and only if the change is committed.
+    pub async fn append(
+        entry: NewOutboxEvent,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::outbox::dsl::*;
+
+        diesel::insert_into(outbox)
+            .values(&entry)
+            .returning(OutboxEvent::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Lock up to `limit` unpublished events, oldest first.
+    ///
+    /// Must run in a transaction; the rows stay locked until it ends and
+    /// `SKIP LOCKED` keeps concurrent relays from claiming the same events.
+    pub async fn claim_unpublished(
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::outbox::dsl::*;
+
+        outbox
+            .filter(published_at.is_null())
+            .order(id)
+            .limit(limit)
+            .for_update()
+            .skip_locked()
+            .select(OutboxEvent::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Mark events as published. Returns the number of updated rows.
+    pub async fn mark_published(
+        event_ids: &[i64],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::outbox::dsl::*;
+
+        diesel::update(outbox.filter(id.eq_any(event_ids)))
+            .set((
+                published_at.eq(Utc::now()),
+                attempts.eq(attempts + 1),
+                last_error.eq(None::<String>),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Record a failed publish attempt; the event is retried later.
+    pub async fn record_failure(
+        event_id: i64,
+        error: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::outbox::dsl::*;
+
+        diesel::update(outbox.find(event_id))
+            .set((attempts.eq(attempts + 1), last_error.eq(error)))
+            .execute(conn)
+            .await
+    }
+}
@@ -0,0 +1,294 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Values of [`Plant::energy_type`].
+pub mod energy_type {
+    pub const SOLAR: &str = "solar";
+    pub const WIND: &str = "wind";
+    pub const HYDRO: &str = "hydro";
+    pub const BATTERY: &str = "battery";
+    pub const OTHER: &str = "other";
+}
+
+/// Values of [`Plant::status`].
+pub mod plant_status {
+    pub const ACTIVE: &str = "active";
+    pub const INACTIVE: &str = "inactive";
+    pub const MAINTENANCE: &str = "maintenance";
+    pub const DECOMMISSIONED: &str = "decommissioned";
+}
+
+/// Mean Earth radius used for distances between plants.
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// A generation or storage site of a tenant.
+#[derive(
+    Queryable, QueryableByName, Selectable, Debug, Clone, serde::Serialize,
+)]
+#[diesel(table_name = crate::schema::plants)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Plant {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub name: String,
+    pub energy_type: String,
+    /// Nameplate capacity in MW
+    pub capacity_mw: BigDecimal,
+    pub address: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Incremented by every [`Plant::update`]
+    pub version: i32,
+    /// WGS 84 coordinates in degrees, both set or both `None`
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// A plant and its distance from the point of a radius search.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct NearbyPlant {
+    #[diesel(embed)]
+    pub plant: Plant,
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    pub distance_km: f64,
+}
+
+/// The number and total capacity of a tenant's plants of an energy type and
+/// status.
+#[derive(Queryable, Debug, Clone)]
+pub struct PlantCapacityGroup {
+    pub energy_type: String,
+    pub status: String,
+    pub plant_count: i64,
+    pub total_capacity_mw: BigDecimal,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::plants)]
+pub struct NewPlant {
+    pub tenant_id: String,
+    pub name: String,
+    pub energy_type: String,
+    pub capacity_mw: BigDecimal,
+    pub address: Option<String>,
+    pub status: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::plants)]
+pub struct UpdatePlant {
+    pub name: Option<String>,
+    pub energy_type: Option<String>,
+    pub capacity_mw: Option<BigDecimal>,
+    pub address: Option<Option<String>>,
+    pub status: Option<String>,
+    pub latitude: Option<Option<f64>>,
+    pub longitude: Option<Option<f64>>,
+}
+
+impl Plant {
+    pub async fn create(
+        entry: NewPlant,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        diesel::insert_into(plants)
+            .values(&entry)
+            .returning(Plant::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Insert several plants in one statement.
+    pub async fn create_many(
+        entries: Vec<NewPlant>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        diesel::insert_into(plants)
+            .values(&entries)
+            .returning(Plant::as_returning())
+            .get_results(conn)
+            .await
+    }
+
+    /// A plant of the tenant, `None` when it does not exist or belongs to
+    /// another tenant.
+    pub async fn find(
+        tenant: &str,
+        plant_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        plants
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(plant_id))
+            .select(Plant::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// A tenant's plants ordered by name.
+    pub async fn list(
+        tenant: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        plants
+            .filter(tenant_id.eq(tenant))
+            .order((name, id))
+            .select(Plant::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Plants of every tenant that have coordinates and are not
+    /// decommissioned, ordered by id.
+    pub async fn located(
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        plants
+            .filter(latitude.is_not_null())
+            .filter(status.ne(plant_status::DECOMMISSIONED))
+            .order(id)
+            .select(Plant::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// A tenant's plants grouped by energy type and status, ordered by both.
+    pub async fn capacity_groups(
+        tenant: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<PlantCapacityGroup>, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+        use diesel::dsl::{count_star, sum};
+
+        plants
+            .filter(tenant_id.eq(tenant))
+            .group_by((energy_type, status))
+            .select((
+                energy_type,
+                status,
+                count_star(),
+                // Groups are never empty
+                sum(capacity_mw).assume_not_null(),
+            ))
+            .order((energy_type, status))
+            .load(conn)
+            .await
+    }
+
+    /// A tenant's plants within `radius_km` of a point, nearest first.
+    /// Distances are great-circle distances on a spherical Earth.
+    pub async fn near(
+        tenant: &str,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<NearbyPlant>, diesel::result::Error> {
+        use diesel::sql_types::{Double, Text};
+
+        // A degree of latitude is the same distance everywhere, so the
+        // radius bounds the latitude before distances are computed
+        let latitude_delta = radius_km / EARTH_RADIUS_KM.to_radians();
+        diesel::sql_query(
+            "SELECT * FROM ( \
+                 SELECT *, $5 * 2 * asin(least(1, sqrt( \
+                     power(sin(radians(latitude - $2) / 2), 2) \
+                     + cos(radians($2)) * cos(radians(latitude)) \
+                     * power(sin(radians(longitude - $3) / 2), 2) \
+                 ))) AS distance_km \
+                 FROM plants \
+                 WHERE tenant_id = $1 \
+                 AND latitude BETWEEN $2 - $6 AND $2 + $6 \
+             ) nearby \
+             WHERE distance_km <= $4 \
+             ORDER BY distance_km, name, id",
+        )
+        .bind::<Text, _>(tenant)
+        .bind::<Double, _>(latitude)
+        .bind::<Double, _>(longitude)
+        .bind::<Double, _>(radius_km)
+        .bind::<Double, _>(EARTH_RADIUS_KM)
+        .bind::<Double, _>(latitude_delta)
+        .load(conn)
+        .await
+    }
+
+    /// Apply `changes` to a plant of the tenant if it is still at
+    /// `expected_version`, and increment the version. `None` when the plant
+    /// does not exist or was updated since.
+    pub async fn update(
+        tenant: &str,
+        plant_id: Uuid,
+        expected_version: i32,
+        changes: UpdatePlant,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        diesel::update(
+            plants
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(plant_id))
+                .filter(version.eq(expected_version)),
+        )
+        .set((&changes, version.eq(version + 1)))
+        .returning(Plant::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Set the status of a plant and increment the version, regardless of
+    /// the version it is at. `None` when the plant does not exist.
+    pub async fn set_status(
+        plant_id: Uuid,
+        new_status: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        diesel::update(plants.find(plant_id))
+            .set((status.eq(new_status), version.eq(version + 1)))
+            .returning(Plant::as_returning())
+            .get_result(conn)
+            .await
+            .optional()
+    }
+
+    /// Delete a plant of the tenant, if given only while it is still at
+    /// `expected_version`. Returns the number of deleted rows.
+    pub async fn delete(
+        tenant: &str,
+        plant_id: Uuid,
+        expected_version: Option<i32>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::plants::dsl::*;
+
+        let mut query = diesel::delete(
+            plants.filter(tenant_id.eq(tenant)).filter(id.eq(plant_id)),
+        )
+        .into_boxed();
+        if let Some(expected_version) = expected_version {
+            query = query.filter(version.eq(expected_version));
+        }
+        query.execute(conn).await
+    }
+}
@@ -2,7 +2,11 @@ use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use uuid::Uuid;
 
-#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+use super::Keyset;
+
+#[derive(
+    Queryable, Selectable, Debug, Clone, serde::Serialize, serde::Deserialize,
+)]
 #[diesel(table_name = crate::schema::query_history)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct QueryHistory {
@@ -11,6 +15,17 @@ pub struct QueryHistory {
     pub date_from: Option<chrono::DateTime<chrono::Utc>>,
     pub date_to: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    pub api_key_id: Option<Uuid>,
+    pub tenant_id: String,
+}
+
+/// A query of the history and how often it was run.
+#[derive(Queryable, Debug, Clone, PartialEq)]
+pub struct FrequentQuery {
+    pub aggregation_type: String,
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+    pub count: i64,
 }
 
 #[derive(Insertable, Debug, Clone)]
@@ -19,6 +34,10 @@ pub struct NewQueryHistory {
     pub aggregation_type: String,
     pub date_from: Option<chrono::DateTime<chrono::Utc>>,
     pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+    pub api_key_id: Option<Uuid>,
+    pub tenant_id: String,
+    /// When the query ran, `None` for the time of the insert
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl QueryHistory {
@@ -35,17 +54,119 @@ impl QueryHistory {
             .await
     }
 
-    /// Get the last N query history entries ordered by most recent first.
+    /// Insert `entries` in one statement, returning how many were inserted.
+    pub async fn create_many(
+        entries: &[NewQueryHistory],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::query_history::dsl::*;
+
+        diesel::insert_into(query_history)
+            .values(entries)
+            .execute(conn)
+            .await
+    }
+
+    /// Get the last N query history entries of a tenant ordered by most
+    /// recent first.
     pub async fn get_latest(
+        tenant: &str,
         limit: i64,
         conn: &mut AsyncPgConnection,
     ) -> Result<Vec<Self>, diesel::result::Error> {
         use crate::schema::query_history::dsl::*;
 
         query_history
+            .filter(tenant_id.eq(tenant))
             .order(created_at.desc())
             .limit(limit)
             .load(conn)
             .await
     }
+
+    /// A page of a tenant's queries, most recent first from `keyset`.
+    pub async fn page(
+        tenant: &str,
+        keyset: Keyset,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::query_history::dsl::*;
+
+        let mut query = query_history
+            .filter(tenant_id.eq(tenant))
+            .select(QueryHistory::as_select())
+            .into_boxed();
+        // Batched entries share their insert time
+        match keyset {
+            Keyset::First => {
+                query = query.order((created_at.desc(), id.desc()));
+            }
+            Keyset::After(time, after) => {
+                query = query
+                    .filter(
+                        created_at
+                            .lt(time)
+                            .or(created_at.eq(time).and(id.lt(after))),
+                    )
+                    .order((created_at.desc(), id.desc()));
+            }
+            // Read towards newer queries, the nearest first
+            Keyset::Before(time, before) => {
+                query = query
+                    .filter(
+                        created_at
+                            .gt(time)
+                            .or(created_at.eq(time).and(id.gt(before))),
+                    )
+                    .order((created_at.asc(), id.asc()));
+            }
+        }
+
+        let mut page = query.limit(limit).load(conn).await?;
+        if let Keyset::Before(..) = keyset {
+            page.reverse();
+        }
+        Ok(page)
+    }
+
+    /// A tenant's `limit` most frequent queries since `since`, by
+    /// aggregation type and date range, most frequent first and, at the
+    /// same frequency, most recently run first.
+    pub async fn most_frequent(
+        tenant: &str,
+        since: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<FrequentQuery>, diesel::result::Error> {
+        use crate::schema::query_history::dsl::*;
+        use diesel::dsl::{count_star, max};
+
+        query_history
+            .filter(tenant_id.eq(tenant))
+            .filter(created_at.ge(since))
+            .group_by((aggregation_type, date_from, date_to))
+            .select((aggregation_type, date_from, date_to, count_star()))
+            .order((count_star().desc(), max(created_at).desc()))
+            .limit(limit)
+            .load(conn)
+            .await
+    }
+
+    /// Up to `limit` entries of any tenant created since `since`, picked at
+    /// random.
+    pub async fn sample(
+        since: chrono::DateTime<chrono::Utc>,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::query_history::dsl::*;
+
+        query_history
+            .filter(created_at.ge(since))
+            .order(diesel::dsl::sql::<diesel::sql_types::Double>("random()"))
+            .limit(limit)
+            .load(conn)
+            .await
+    }
 }
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// A named aggregation of a tenant.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::saved_queries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SavedQuery {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub name: String,
+    /// Body of the aggregation request
+    pub request: serde_json::Value,
+    /// Query parameters of the aggregation request
+    pub params: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::saved_queries)]
+pub struct NewSavedQuery {
+    pub tenant_id: String,
+    pub name: String,
+    pub request: serde_json::Value,
+    pub params: serde_json::Value,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::saved_queries)]
+pub struct UpdateSavedQuery {
+    pub name: Option<String>,
+    pub request: Option<serde_json::Value>,
+    pub params: Option<serde_json::Value>,
+}
+
+impl SavedQuery {
+    /// Save a query. Fails with a unique violation when the tenant already
+    /// has a query of that name.
+    pub async fn create(
+        entry: NewSavedQuery,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::saved_queries::dsl::*;
+
+        diesel::insert_into(saved_queries)
+            .values(&entry)
+            .returning(SavedQuery::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// A saved query of the tenant, `None` when it does not exist or
+    /// belongs to another tenant.
+    pub async fn find(
+        tenant: &str,
+        query_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::saved_queries::dsl::*;
+
+        saved_queries
+            .filter(tenant_id.eq(tenant))
+            .filter(id.eq(query_id))
+            .select(SavedQuery::as_select())
+            .first(conn)
+            .await
+            .optional()
+    }
+
+    /// A tenant's saved queries ordered by name.
+    pub async fn list(
+        tenant: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::saved_queries::dsl::*;
+
+        saved_queries
+            .filter(tenant_id.eq(tenant))
+            .select(SavedQuery::as_select())
+            .order((name, id))
+            .load(conn)
+            .await
+    }
+
+    /// Apply `changes` to a saved query of the tenant. `None` when it does
+    /// not exist.
+    pub async fn update(
+        tenant: &str,
+        query_id: Uuid,
+        changes: UpdateSavedQuery,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Self>, diesel::result::Error> {
+        use crate::schema::saved_queries::dsl::*;
+
+        diesel::update(
+            saved_queries
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(query_id)),
+        )
+        .set(&changes)
+        .returning(SavedQuery::as_returning())
+        .get_result(conn)
+        .await
+        .optional()
+    }
+
+    /// Delete a saved query of the tenant. Returns the number of deleted
+    /// rows.
+    pub async fn delete(
+        tenant: &str,
+        query_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::saved_queries::dsl::*;
+
+        diesel::delete(
+            saved_queries
+                .filter(tenant_id.eq(tenant))
+                .filter(id.eq(query_id)),
+        )
+        .execute(conn)
+        .await
+    }
+}
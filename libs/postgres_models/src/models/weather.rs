@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Nullable, Text, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use crate::periods;
+use crate::prepared::Prepared;
+
+/// The weather at a plant in one hour.
+#[derive(Queryable, Selectable, Debug, Clone, serde::Serialize)]
+#[diesel(table_name = crate::schema::weather_observations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WeatherObservation {
+    pub plant_id: Uuid,
+    /// Start of the hour
+    pub observed_at: DateTime<Utc>,
+    pub tenant_id: String,
+    /// Air temperature 2 m above ground
+    pub temperature_c: Option<f64>,
+    /// Global horizontal irradiance, averaged over the hour
+    pub irradiance_w_m2: Option<f64>,
+    /// Wind speed 10 m above ground
+    pub wind_speed_m_s: Option<f64>,
+    /// Whether the hour was still ahead when it was fetched
+    pub forecast: bool,
+    /// Provider the values come from
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::weather_observations)]
+pub struct NewWeatherObservation {
+    pub plant_id: Uuid,
+    pub observed_at: DateTime<Utc>,
+    pub tenant_id: String,
+    pub temperature_c: Option<f64>,
+    pub irradiance_w_m2: Option<f64>,
+    pub wind_speed_m_s: Option<f64>,
+    pub forecast: bool,
+    pub source: String,
+}
+
+/// The weather averaged over a period and a tenant's plants.
+#[derive(QueryableByName, Debug, Clone, PartialEq)]
+pub struct AggregatedWeather {
+    #[diesel(sql_type = Timestamptz)]
+    pub period: DateTime<Utc>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub temperature_c: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub irradiance_w_m2: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub wind_speed_m_s: Option<f64>,
+}
+
+impl WeatherObservation {
+    /// Insert observations, replacing those already stored for the same
+    /// plant and hour.
+    pub async fn upsert(
+        observations: Vec<NewWeatherObservation>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::weather_observations::dsl::*;
+        use diesel::upsert::excluded;
+
+        diesel::insert_into(weather_observations)
+            .values(&observations)
+            .on_conflict((plant_id, observed_at))
+            .do_update()
+            .set((
+                temperature_c.eq(excluded(temperature_c)),
+                irradiance_w_m2.eq(excluded(irradiance_w_m2)),
+                wind_speed_m_s.eq(excluded(wind_speed_m_s)),
+                forecast.eq(excluded(forecast)),
+                source.eq(excluded(source)),
+                fetched_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    /// Average a tenant's weather by the given truncation level, see
+    /// [`crate::periods`], over all its plants or only `plant`, in
+    /// `[date_from, date_to)`.
+    pub async fn aggregate(
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<AggregatedWeather>, diesel::result::Error> {
+        let query = diesel::sql_query(format!(
+            "SELECT {} AS period, \
+             AVG(temperature_c) AS temperature_c, \
+             AVG(irradiance_w_m2) AS irradiance_w_m2, \
+             AVG(wind_speed_m_s) AS wind_speed_m_s \
+             FROM weather_observations WHERE tenant_id = $2 \
+             AND ($3::uuid IS NULL OR plant_id = $3) \
+             AND ($4::timestamptz IS NULL OR observed_at >= $4) \
+             AND ($5::timestamptz IS NULL OR observed_at < $5) \
+             GROUP BY period ORDER BY period",
+            periods::period_sql(trunc_level, "observed_at"),
+        ))
+        .bind::<Text, _>(periods::field(trunc_level))
+        .bind::<Text, _>(tenant)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(plant)
+        .bind::<Nullable<Timestamptz>, _>(date_from)
+        .bind::<Nullable<Timestamptz>, _>(date_to);
+        Prepared::new(query).load(conn).await
+    }
+}
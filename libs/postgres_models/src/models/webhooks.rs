@@ -0,0 +1,296 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Interval};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Lifecycle states of a [`WebhookDelivery`].
+pub mod delivery_status {
+    pub const PENDING: &str = "pending";
+    pub const IN_FLIGHT: &str = "in_flight";
+    pub const DELIVERED: &str = "delivered";
+    pub const FAILED: &str = "failed";
+}
+
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = crate::schema::webhooks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Webhook {
+    pub id: Uuid,
+    pub tenant_id: String,
+    pub url: String,
+    /// HMAC key signing the deliveries, which is why the struct is not
+    /// `Serialize`
+    pub secret: String,
+    pub event_types: Vec<Option<String>>,
+    pub description: Option<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::webhooks)]
+pub struct NewWebhook {
+    pub tenant_id: String,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<Option<String>>,
+    pub description: Option<String>,
+    pub active: bool,
+}
+
+#[derive(AsChangeset, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::webhooks)]
+pub struct UpdateWebhook {
+    pub url: Option<String>,
+    pub event_types: Option<Vec<Option<String>>>,
+    pub description: Option<Option<String>>,
+    pub active: Option<bool>,
+}
+
+#[derive(
+    Queryable, QueryableByName, Selectable, Debug, Clone, serde::Serialize,
+)]
+#[diesel(table_name = crate::schema::webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::webhook_deliveries)]
+pub struct NewWebhookDelivery {
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+}
+
+/// Outcome of a single delivery attempt, recorded on the delivery row.
+#[derive(Debug, Clone)]
+pub struct DeliveryAttempt {
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    /// `None` when the attempt succeeded or retries are exhausted.
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+impl Webhook {
+    /// Event types this webhook is subscribed to, ignoring NULL array items.
+    pub fn subscribed_events(&self) -> Vec<String> {
+        self.event_types.iter().flatten().cloned().collect()
+    }
+
+    pub async fn create(
+        entry: NewWebhook,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        diesel::insert_into(webhooks)
+            .values(&entry)
+            .returning(Webhook::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// A webhook of the tenant, `NotFound` when it belongs to another.
+    pub async fn find(
+        tenant: &str,
+        webhook_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        webhooks
+            .find(webhook_id)
+            .filter(tenant_id.eq(tenant))
+            .select(Webhook::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// The webhook `delivery` goes to, of whichever tenant.
+    pub async fn of_delivery(
+        delivery: &WebhookDelivery,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        webhooks
+            .find(delivery.webhook_id)
+            .select(Webhook::as_select())
+            .first(conn)
+            .await
+    }
+
+    /// A tenant's webhooks, newest first.
+    pub async fn list(
+        tenant: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        webhooks
+            .filter(tenant_id.eq(tenant))
+            .order(created_at.desc())
+            .select(Webhook::as_select())
+            .load(conn)
+            .await
+    }
+
+    pub async fn update(
+        tenant: &str,
+        webhook_id: Uuid,
+        changes: UpdateWebhook,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Self, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        diesel::update(webhooks.find(webhook_id).filter(tenant_id.eq(tenant)))
+            .set(&changes)
+            .returning(Webhook::as_returning())
+            .get_result(conn)
+            .await
+    }
+
+    /// Delete a webhook of the tenant and (via cascade) its delivery
+    /// records. Returns the number of deleted rows.
+    pub async fn delete(
+        tenant: &str,
+        webhook_id: Uuid,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+
+        diesel::delete(webhooks.find(webhook_id).filter(tenant_id.eq(tenant)))
+            .execute(conn)
+            .await
+    }
+
+    /// Active webhooks of the tenant subscribed to the given event type.
+    pub async fn subscribed_to(
+        tenant: &str,
+        event: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::webhooks::dsl::*;
+        use diesel::PgArrayExpressionMethods;
+
+        webhooks
+            .filter(tenant_id.eq(tenant))
+            .filter(active.eq(true))
+            .filter(event_types.contains(vec![Some(event.to_string())]))
+            .select(Webhook::as_select())
+            .load(conn)
+            .await
+    }
+}
+
+impl WebhookDelivery {
+    /// Queue deliveries for dispatch. Returns the number of queued rows.
+    pub async fn enqueue(
+        entries: Vec<NewWebhookDelivery>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        diesel::insert_into(webhook_deliveries)
+            .values(&entries)
+            .execute(conn)
+            .await
+    }
+
+    /// Get the last N deliveries for a webhook of the tenant, most recent
+    /// first.
+    pub async fn latest_for_webhook(
+        tenant: &str,
+        for_webhook_id: Uuid,
+        limit: i64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        use crate::schema::webhook_deliveries::dsl::*;
+        use crate::schema::webhooks;
+
+        webhook_deliveries
+            .inner_join(webhooks::table)
+            .filter(webhook_id.eq(for_webhook_id))
+            .filter(webhooks::tenant_id.eq(tenant))
+            .order(created_at.desc())
+            .limit(limit)
+            .select(WebhookDelivery::as_select())
+            .load(conn)
+            .await
+    }
+
+    /// Atomically claim up to `limit` due deliveries for this dispatcher.
+    ///
+    /// Uses `FOR UPDATE SKIP LOCKED` so concurrent replicas never claim the
+    /// same row. Rows stuck `in_flight` for longer than `stale_after` (e.g. a
+    /// crashed dispatcher) are reclaimed, giving at-least-once delivery.
+    pub async fn claim_due(
+        limit: i32,
+        stale_after: std::time::Duration,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Self>, diesel::result::Error> {
+        let stale_after = diesel::data_types::PgInterval::from_microseconds(
+            stale_after.as_micros() as i64,
+        );
+
+        diesel::sql_query(
+            "UPDATE webhook_deliveries SET status = 'in_flight', \
+             attempts = attempts + 1 \
+             WHERE id IN ( \
+                 SELECT id FROM webhook_deliveries \
+                 WHERE (status = 'pending' AND next_attempt_at <= NOW()) \
+                    OR (status = 'in_flight' AND updated_at < NOW() - $2) \
+                 ORDER BY next_attempt_at \
+                 LIMIT $1 \
+                 FOR UPDATE SKIP LOCKED \
+             ) \
+             RETURNING *",
+        )
+        .bind::<Integer, _>(limit)
+        .bind::<Interval, _>(stale_after)
+        .load::<WebhookDelivery>(conn)
+        .await
+    }
+
+    /// Record the outcome of an attempt: delivered, rescheduled or failed.
+    pub async fn record_attempt(
+        delivery_id: Uuid,
+        attempt: DeliveryAttempt,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        let succeeded = attempt.error.is_none();
+        let new_status = match (succeeded, attempt.retry_at) {
+            (true, _) => delivery_status::DELIVERED,
+            (false, Some(_)) => delivery_status::PENDING,
+            (false, None) => delivery_status::FAILED,
+        };
+
+        diesel::update(webhook_deliveries.find(delivery_id))
+            .set((
+                status.eq(new_status),
+                last_status_code.eq(attempt.status_code),
+                last_error.eq(attempt.error),
+                next_attempt_at.eq(attempt.retry_at.unwrap_or_else(Utc::now)),
+                delivered_at.eq(succeeded.then(Utc::now)),
+            ))
+            .execute(conn)
+            .await
+    }
+}
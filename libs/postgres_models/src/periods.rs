@@ -0,0 +1,88 @@
+//! Periods that aggregations group rows by.
+//!
+//! A truncation level is a `date_trunc` field, `hour`, `day`, `week`
+//! (starting on Monday), `month`, `quarter` or `year`, or `week_<day>` for
+//! weeks starting on another day, e.g. `week_sunday`. Postgres only
+//! truncates to Monday weeks, so other weeks are truncated shifted by the
+//! days from their start to the next Monday.
+
+/// Days of the week from Monday, as in the `week_<day>` levels.
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Days from the start of the weeks of `trunc_level` to the next Monday,
+/// `None` for levels other than `week_<day>`.
+fn week_shift(trunc_level: &str) -> Option<usize> {
+    let day = trunc_level.strip_prefix("week_")?;
+    let from_monday = WEEKDAYS.iter().position(|weekday| *weekday == day)?;
+    Some((7 - from_monday) % 7)
+}
+
+/// The `date_trunc` field of `trunc_level`, to bind to `$1` of
+/// [`period_sql`].
+pub fn field(trunc_level: &str) -> &str {
+    match week_shift(trunc_level) {
+        Some(_) => "week",
+        None => trunc_level,
+    }
+}
+
+/// SQL of the start of the `trunc_level` period of the timestamp `column`,
+/// with `$1` bound to [`field`] of the level. One of a fixed set of
+/// fragments, so queries built with it can be [`crate::prepared::Prepared`].
+pub fn period_sql(trunc_level: &str, column: &str) -> String {
+    match week_shift(trunc_level) {
+        Some(days) if days > 0 => format!(
+            "(date_trunc($1, {column} + interval '{days} days') \
+             - interval '{days} days')"
+        ),
+        _ => format!("date_trunc($1, {column})"),
+    }
+}
+
+/// SQL interval between the starts of consecutive `trunc_level` periods,
+/// `None` for unknown levels.
+pub fn step(trunc_level: &str) -> Option<&'static str> {
+    match field(trunc_level) {
+        "hour" => Some("1 hour"),
+        "day" => Some("1 day"),
+        "week" => Some("1 week"),
+        "month" => Some("1 month"),
+        "quarter" => Some("3 months"),
+        "year" => Some("1 year"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shifts_weeks() {
+        assert_eq!(field("month"), "month");
+        assert_eq!(period_sql("month", "t"), "date_trunc($1, t)");
+        assert_eq!(field("week_monday"), "week");
+        assert_eq!(period_sql("week_monday", "t"), "date_trunc($1, t)");
+        assert_eq!(field("week_sunday"), "week");
+        assert_eq!(
+            period_sql("week_sunday", "t"),
+            "(date_trunc($1, t + interval '1 days') - interval '1 days')"
+        );
+        assert_eq!(
+            period_sql("week_tuesday", "t"),
+            "(date_trunc($1, t + interval '6 days') - interval '6 days')"
+        );
+        assert_eq!(field("week_someday"), "week_someday");
+        assert_eq!(step("week_sunday"), Some("1 week"));
+        assert_eq!(step("quarter"), Some("3 months"));
+        assert_eq!(step("week_someday"), None);
+    }
+}
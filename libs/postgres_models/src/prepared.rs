@@ -0,0 +1,61 @@
+//! Raw SQL kept in the prepared statement cache of a connection.
+//!
+//! Diesel never caches the statements of `sql_query`, whose SQL may embed
+//! values and so grow the cache without bound, so Postgres parses and plans
+//! them on every call. Wrapped in [`Prepared`], a raw query is cached by its
+//! SQL and bind types like the queries of the DSL; only wrap queries built
+//! from a fixed set of SQL fragments, with every value bound.
+use diesel::QueryResult;
+use diesel::pg::Pg;
+use diesel::query_builder::{AstPass, Query, QueryFragment, QueryId};
+
+/// A raw query cached as a prepared statement, see the module docs.
+pub struct Prepared<Q>(Q);
+
+impl<Q> Prepared<Q> {
+    pub fn new(query: Q) -> Self {
+        Self(query)
+    }
+}
+
+impl<Q: Query> Query for Prepared<Q> {
+    type SqlType = Q::SqlType;
+}
+
+impl<Q> QueryId for Prepared<Q> {
+    type QueryId = ();
+
+    // Cached by SQL, as one type may build several statements
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<Q: QueryFragment<Pg>> QueryFragment<Pg> for Prepared<Q> {
+    fn walk_ast<'b>(&'b self, pass: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        self.0.walk_ast(pass)
+    }
+
+    fn is_safe_to_cache_prepared(&self, _backend: &Pg) -> QueryResult<bool> {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::debug_query;
+    use diesel::sql_types::Text;
+
+    use super::*;
+
+    #[test]
+    fn test_caches_raw_sql() {
+        let query = || diesel::sql_query("SELECT $1").bind::<Text, _>("x");
+        assert!(!query().is_safe_to_cache_prepared(&Pg).unwrap());
+
+        let prepared = Prepared::new(query());
+        assert!(prepared.is_safe_to_cache_prepared(&Pg).unwrap());
+        assert_eq!(
+            debug_query::<Pg, _>(&prepared).to_string(),
+            debug_query::<Pg, _>(&query()).to_string()
+        );
+    }
+}
@@ -1,5 +1,102 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    alert_rules (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        name -> Text,
+        plant_id -> Nullable<Uuid>,
+        metric -> Text,
+        comparison -> Text,
+        threshold -> Numeric,
+        window_minutes -> Int4,
+        enabled -> Bool,
+        firing -> Bool,
+        evaluated_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        channel_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    alerts (id) {
+        id -> Uuid,
+        rule_id -> Uuid,
+        tenant_id -> Text,
+        value -> Numeric,
+        threshold -> Numeric,
+        window_start -> Timestamptz,
+        window_end -> Timestamptz,
+        fired_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+        acknowledged_at -> Nullable<Timestamptz>,
+        acknowledged_by -> Nullable<Text>,
+        notification_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    api_key_preferences (api_key_id) {
+        api_key_id -> Uuid,
+        timezone -> Nullable<Text>,
+        unit -> Nullable<Text>,
+        decimals -> Nullable<Int4>,
+        aggregation_type -> Nullable<Text>,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    api_keys (id) {
+        id -> Uuid,
+        name -> Text,
+        key_prefix -> Text,
+        key_hash -> Text,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        role -> Text,
+        daily_quota -> Nullable<Int8>,
+        monthly_quota -> Nullable<Int8>,
+        signing_secret -> Nullable<Text>,
+        tenant_id -> Text,
+    }
+}
+
+diesel::table! {
+    dataset_snapshots (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        name -> Text,
+        status -> Text,
+        location -> Nullable<Text>,
+        readings -> Nullable<Int8>,
+        error -> Nullable<Text>,
+        restore_status -> Nullable<Text>,
+        restored_schema -> Nullable<Text>,
+        restore_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    energy_reading_revisions (id) {
+        id -> Uuid,
+        reading_id -> Uuid,
+        tenant_id -> Text,
+        reading_time -> Timestamptz,
+        quantity_kwh -> Numeric,
+        plant_id -> Nullable<Uuid>,
+        quality_code -> Nullable<Text>,
+        source -> Text,
+        operation -> Text,
+        request_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     energy_readings (id) {
         id -> Uuid,
@@ -7,6 +104,150 @@ diesel::table! {
         quantity_kwh -> Numeric,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        tenant_id -> Text,
+        plant_id -> Nullable<Uuid>,
+        quality_code -> Nullable<Text>,
+        source -> Text,
+    }
+}
+
+diesel::table! {
+    energy_targets (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        plant_id -> Nullable<Uuid>,
+        name -> Text,
+        period_start -> Timestamptz,
+        period_end -> Timestamptz,
+        target_kwh -> Numeric,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    file_uploads (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        file_name -> Text,
+        size -> Int8,
+        sha256 -> Text,
+        received -> Int8,
+        status -> Text,
+        location -> Nullable<Text>,
+        import_run_id -> Nullable<Uuid>,
+        error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    import_runs (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        file -> Text,
+        status -> Text,
+        inserted -> Nullable<Int4>,
+        total -> Nullable<Int4>,
+        error -> Nullable<Text>,
+        started_at -> Timestamptz,
+        finished_at -> Nullable<Timestamptz>,
+        source_timezone -> Text,
+        dst_policy -> Text,
+        dst_adjusted -> Nullable<Int4>,
+        rejected -> Nullable<Int4>,
+        clamped -> Nullable<Int4>,
+        flagged -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    maintenance_windows (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        plant_id -> Uuid,
+        starts_at -> Timestamptz,
+        ends_at -> Timestamptz,
+        notes -> Nullable<Text>,
+        status -> Text,
+        previous_plant_status -> Nullable<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    market_prices (zone, starts_at) {
+        zone -> Text,
+        starts_at -> Timestamptz,
+        price_per_mwh -> Numeric,
+        currency -> Text,
+        source -> Text,
+        fetched_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    notification_channels (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        name -> Text,
+        kind -> Text,
+        target -> Text,
+        events -> Array<Nullable<Text>>,
+        enabled -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Uuid,
+        channel_id -> Uuid,
+        tenant_id -> Text,
+        event -> Text,
+        subject -> Text,
+        body -> Text,
+        data -> Jsonb,
+        status -> Text,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Timestamptz,
+        delivered_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    outbox (id) {
+        id -> Int8,
+        event_type -> Text,
+        tenant_id -> Text,
+        payload -> Jsonb,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        published_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    plants (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        name -> Text,
+        energy_type -> Text,
+        capacity_mw -> Numeric,
+        address -> Nullable<Text>,
+        status -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        version -> Int4,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
     }
 }
 
@@ -17,7 +258,102 @@ diesel::table! {
         date_from -> Nullable<Timestamptz>,
         date_to -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
+        api_key_id -> Nullable<Uuid>,
+        tenant_id -> Text,
+    }
+}
+
+diesel::table! {
+    saved_queries (id) {
+        id -> Uuid,
+        tenant_id -> Text,
+        name -> Text,
+        request -> Jsonb,
+        params -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        event_type -> Text,
+        payload -> Jsonb,
+        status -> Text,
+        attempts -> Int4,
+        last_status_code -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Timestamptz,
+        delivered_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    weather_observations (plant_id, observed_at) {
+        plant_id -> Uuid,
+        observed_at -> Timestamptz,
+        tenant_id -> Text,
+        temperature_c -> Nullable<Float8>,
+        irradiance_w_m2 -> Nullable<Float8>,
+        wind_speed_m_s -> Nullable<Float8>,
+        forecast -> Bool,
+        source -> Text,
+        fetched_at -> Timestamptz,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(energy_readings, query_history,);
+diesel::table! {
+    webhooks (id) {
+        id -> Uuid,
+        url -> Text,
+        secret -> Text,
+        event_types -> Array<Nullable<Text>>,
+        description -> Nullable<Text>,
+        active -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        tenant_id -> Text,
+    }
+}
+
+diesel::joinable!(alert_rules -> notification_channels (channel_id));
+diesel::joinable!(alert_rules -> plants (plant_id));
+diesel::joinable!(alerts -> alert_rules (rule_id));
+diesel::joinable!(alerts -> notifications (notification_id));
+diesel::joinable!(api_key_preferences -> api_keys (api_key_id));
+diesel::joinable!(energy_readings -> plants (plant_id));
+diesel::joinable!(energy_targets -> plants (plant_id));
+diesel::joinable!(file_uploads -> import_runs (import_run_id));
+diesel::joinable!(maintenance_windows -> plants (plant_id));
+diesel::joinable!(notifications -> notification_channels (channel_id));
+diesel::joinable!(query_history -> api_keys (api_key_id));
+diesel::joinable!(weather_observations -> plants (plant_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    alert_rules,
+    alerts,
+    api_key_preferences,
+    api_keys,
+    dataset_snapshots,
+    energy_reading_revisions,
+    energy_readings,
+    energy_targets,
+    file_uploads,
+    import_runs,
+    maintenance_windows,
+    market_prices,
+    notification_channels,
+    notifications,
+    outbox,
+    plants,
+    query_history,
+    saved_queries,
+    weather_observations,
+    webhook_deliveries,
+    webhooks,
+);
@@ -1,6 +1,6 @@
 use std::error::Error;
 
-use crate::secrets::get_secret;
+use crate::secrets::{get_secret, get_secret_as};
 
 pub struct DatabaseConnections {
     pub postgres: postgres_models::connection::Pool,
@@ -12,9 +12,13 @@ pub async fn establish_connections()
     let db_rw_url = get_database_url().await?;
     let redis_url = get_secret("REDIS_URL").await?;
 
-    let postgres = postgres_models::connection::establish_connection(db_rw_url)
-        .await
-        .expect("failed to connect to Postgres");
+    let postgres = postgres_models::connection::establish_connection(
+        db_rw_url,
+        &Default::default(),
+        None,
+    )
+    .await
+    .expect("failed to connect to Postgres");
 
     let redis = redis_cache::connection::establish_connection(redis_url)
         .await
@@ -37,14 +41,10 @@ async fn get_database_url() -> Result<String, Box<dyn Error>> {
     match get_secret("DATABASE_URL").await {
         Ok(url) => Ok(url),
         Err(_) => {
-            let database_credentials_string =
-                get_secret("DATABASE_CREDENTIALS").await?;
-            let database_credentials = serde_json::from_str::<
+            let database_credentials = get_secret_as::<
                 postgres_models::connection::Credentials,
-            >(
-                database_credentials_string.as_str()
-            )
-            .expect("DATABASE_CREDENTIALS must be valid");
+            >("DATABASE_CREDENTIALS")
+            .await?;
 
             let db_username = database_credentials.username;
             let db_password = database_credentials.password;
@@ -0,0 +1,281 @@
+//! Process-wide cache for Secrets Manager values.
+//!
+//! Secrets are cached per secret id for a TTL and refreshed in the
+//! background before they expire, so rotation is picked up without calling
+//! Secrets Manager on every lookup. A failed refresh keeps serving the last
+//! known value.
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::sync::{OnceCell, RwLock};
+
+pub type SourceError = Box<dyn Error + Send + Sync>;
+
+const DEFAULT_TTL_SECS: u64 = 300;
+
+/// Where cached secrets are loaded from.
+#[async_trait]
+pub trait SecretSource: Send + Sync + 'static {
+    async fn fetch(&self, secret_id: &str) -> Result<String, SourceError>;
+}
+
+/// AWS Secrets Manager, with a client created on first use and reused.
+#[derive(Default)]
+pub struct SecretsManagerSource {
+    client: OnceCell<aws_sdk_secretsmanager::Client>,
+}
+
+#[async_trait]
+impl SecretSource for SecretsManagerSource {
+    async fn fetch(&self, secret_id: &str) -> Result<String, SourceError> {
+        let client =
+            self.client.get_or_init(super::create_secrets_client).await;
+        let response = client
+            .get_secret_value()
+            .secret_id(secret_id)
+            .send()
+            .await?;
+
+        response.secret_string().map(str::to_string).ok_or_else(|| {
+            format!("Secret {secret_id} has no string value").into()
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheSettings {
+    /// How long a fetched value is served without refetching
+    pub ttl: Duration,
+    /// How often cached values are refetched in the background, `None` to
+    /// only refetch on lookup once expired
+    pub refresh_interval: Option<Duration>,
+}
+
+impl CacheSettings {
+    /// Settings from `SECRETS_CACHE_TTL_SECS` (default 300) and
+    /// `SECRETS_REFRESH_INTERVAL_SECS` (default half the TTL, 0 disables
+    /// background refresh).
+    pub fn from_env() -> Self {
+        let secs = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+        let ttl = Duration::from_secs(
+            secs("SECRETS_CACHE_TTL_SECS").unwrap_or(DEFAULT_TTL_SECS),
+        );
+        let refresh_interval = match secs("SECRETS_REFRESH_INTERVAL_SECS") {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => Some(ttl / 2).filter(|d| !d.is_zero()),
+        };
+
+        Self {
+            ttl,
+            refresh_interval,
+        }
+    }
+}
+
+struct Entry {
+    value: String,
+    fetched_at: Instant,
+}
+
+pub struct SecretsCache<S: SecretSource = SecretsManagerSource> {
+    source: S,
+    ttl: Duration,
+    entries: RwLock<HashMap<String, Entry>>,
+}
+
+impl<S: SecretSource> SecretsCache<S> {
+    pub fn new(source: S, ttl: Duration) -> Self {
+        Self {
+            source,
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The value of `secret_id`, fetched from the source unless a cached
+    /// value is younger than the TTL.
+    pub async fn get(&self, secret_id: &str) -> Result<String, SourceError> {
+        if let Some(entry) = self.entries.read().await.get(secret_id)
+            && entry.fetched_at.elapsed() < self.ttl
+        {
+            return Ok(entry.value.clone());
+        }
+
+        match self.fetch(secret_id).await {
+            Ok(value) => Ok(value),
+            Err(e) => match self.entries.read().await.get(secret_id) {
+                Some(entry) => {
+                    tracing::warn!(
+                        secret_id,
+                        "Failed to refresh secret, serving cached value: {e}"
+                    );
+                    Ok(entry.value.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Drop the cached value of `secret_id`, so the next lookup refetches it.
+    pub async fn invalidate(&self, secret_id: &str) {
+        self.entries.write().await.remove(secret_id);
+    }
+
+    pub async fn invalidate_all(&self) {
+        self.entries.write().await.clear();
+    }
+
+    /// Refetch every cached secret, keeping the old value of those that
+    /// fail.
+    pub async fn refresh(&self) {
+        let secret_ids: Vec<String> =
+            self.entries.read().await.keys().cloned().collect();
+
+        for secret_id in secret_ids {
+            if let Err(e) = self.fetch(&secret_id).await {
+                tracing::warn!(secret_id, "Failed to refresh secret: {e}");
+            }
+        }
+    }
+
+    /// Refresh the cache every `interval` until the cache is dropped.
+    pub fn spawn_refresh(
+        self: &Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let cache = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                cache.refresh().await;
+            }
+        })
+    }
+
+    async fn fetch(&self, secret_id: &str) -> Result<String, SourceError> {
+        let value = self.source.fetch(secret_id).await?;
+        self.entries.write().await.insert(
+            secret_id.to_string(),
+            Entry {
+                value: value.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+        Ok(value)
+    }
+}
+
+static GLOBAL: OnceLock<Arc<SecretsCache>> = OnceLock::new();
+
+/// The process-wide cache used by [`super::get_secret`], configured by
+/// [`CacheSettings::from_env`]. Background refresh starts on first use, which
+/// must happen inside a Tokio runtime.
+pub fn global() -> &'static Arc<SecretsCache> {
+    GLOBAL.get_or_init(|| {
+        let settings = CacheSettings::from_env();
+        let cache = Arc::new(SecretsCache::new(
+            SecretsManagerSource::default(),
+            settings.ttl,
+        ));
+        if let Some(interval) = settings.refresh_interval {
+            cache.spawn_refresh(interval);
+        }
+        cache
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingSource {
+        calls: AtomicUsize,
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl SecretSource for Arc<CountingSource> {
+        async fn fetch(&self, secret_id: &str) -> Result<String, SourceError> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err("unavailable".into());
+            }
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(format!("{secret_id}-v{call}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_values_are_cached_for_the_ttl() {
+        let source = Arc::new(CountingSource::default());
+        let cache =
+            SecretsCache::new(source.clone(), Duration::from_millis(100));
+
+        assert_eq!(cache.get("db").await.unwrap(), "db-v1");
+        assert_eq!(cache.get("db").await.unwrap(), "db-v1");
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(cache.get("db").await.unwrap(), "db-v2");
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_forces_a_refetch() {
+        let source = Arc::new(CountingSource::default());
+        let cache = SecretsCache::new(source.clone(), Duration::from_secs(60));
+
+        cache.get("db").await.unwrap();
+        cache.invalidate("db").await;
+        assert_eq!(cache.get("db").await.unwrap(), "db-v2");
+
+        cache.invalidate_all().await;
+        assert_eq!(cache.get("db").await.unwrap(), "db-v3");
+    }
+
+    #[tokio::test]
+    async fn test_stale_value_served_when_source_fails() {
+        let source = Arc::new(CountingSource::default());
+        let cache = SecretsCache::new(source.clone(), Duration::ZERO);
+
+        assert_eq!(cache.get("db").await.unwrap(), "db-v1");
+        source.failing.store(true, Ordering::SeqCst);
+        assert_eq!(cache.get("db").await.unwrap(), "db-v1");
+        assert!(cache.get("other").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_background_refresh() {
+        let source = Arc::new(CountingSource::default());
+        let cache = Arc::new(SecretsCache::new(
+            source.clone(),
+            Duration::from_secs(60),
+        ));
+
+        cache.get("db").await.unwrap();
+        let handle = cache.spawn_refresh(Duration::from_millis(20));
+        tokio::time::sleep(Duration::from_millis(70)).await;
+
+        assert!(source.calls.load(Ordering::SeqCst) >= 3);
+        assert_ne!(cache.get("db").await.unwrap(), "db-v1");
+
+        drop(cache);
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("refresh task stops once the cache is dropped")
+            .unwrap();
+    }
+}
@@ -1,6 +1,10 @@
+pub mod cache;
+
 use std::error::Error;
 use std::fmt;
 
+use serde::de::DeserializeOwned;
+
 #[derive(Debug)]
 pub enum SecretLoadError {
     EnvVarNotSet {
@@ -11,6 +15,14 @@ pub enum SecretLoadError {
         arn: String,
         aws_error: String,
     },
+    InvalidJson {
+        name: String,
+        error: String,
+    },
+    FieldNotFound {
+        name: String,
+        pointer: String,
+    },
 }
 
 impl fmt::Display for SecretLoadError {
@@ -53,13 +65,38 @@ impl fmt::Display for SecretLoadError {
                     name, arn, aws_error
                 )
             }
+            SecretLoadError::InvalidJson { name, error } => {
+                write!(
+                    f,
+                    "Secret '{}' does not have the expected JSON format.\n\
+                     \n\
+                     Error: {}\n\
+                     \n\
+                     Troubleshooting:\n\
+                     1. Check that the secret value is a JSON object, not plain text\n\
+                     2. Verify the field names and types match what the service expects",
+                    name, error
+                )
+            }
+            SecretLoadError::FieldNotFound { name, pointer } => {
+                write!(
+                    f,
+                    "Secret '{}' has no field at '{}'.\n\
+                     \n\
+                     Troubleshooting:\n\
+                     1. Check the field name in the secret value, it is case-sensitive\n\
+                     2. JSON pointers start with '/' and separate nested fields with '/'",
+                    name, pointer
+                )
+            }
         }
     }
 }
 
 impl Error for SecretLoadError {}
 
-fn is_secrets_manager_arn(value: &str) -> bool {
+/// Whether `value` is a Secrets Manager ARN rather than a literal value.
+pub fn is_secrets_manager_arn(value: &str) -> bool {
     value.starts_with("arn:aws:secretsmanager:")
 }
 
@@ -69,6 +106,9 @@ pub async fn create_secrets_client() -> aws_sdk_secretsmanager::Client {
     aws_sdk_secretsmanager::Client::new(&config)
 }
 
+/// Value of the environment variable `name`, or of the Secrets Manager secret
+/// it points to when it holds an ARN. Secrets are served from the
+/// process-wide [`cache`].
 pub async fn get_secret(name: &str) -> Result<String, Box<dyn Error>> {
     if name == "LOCAL_REDIS_URL" {
         return Ok("redis://localhost:6379".to_string());
@@ -83,57 +123,100 @@ pub async fn get_secret(name: &str) -> Result<String, Box<dyn Error>> {
         }
     };
 
-    if is_secrets_manager_arn(&env_value) {
-        tracing::info!(
-            "Environment variable '{}' contains Secrets Manager ARN, fetching actual secret",
-            name
-        );
+    Ok(resolve(name, env_value).await?)
+}
+
+/// `value` of the setting `name`, or the Secrets Manager secret it points to
+/// when it is an ARN. Secrets are served from the process-wide [`cache`].
+pub async fn resolve(
+    name: &str,
+    value: String,
+) -> Result<String, SecretLoadError> {
+    if !is_secrets_manager_arn(&value) {
+        tracing::debug!("Using direct value for '{}'", name);
+        return Ok(value);
+    }
 
-        let secrets_client = create_secrets_client().await;
+    tracing::info!(
+        "'{}' contains Secrets Manager ARN, fetching actual secret",
+        name
+    );
 
-        match load_secret(&secrets_client, &env_value).await {
-            Ok(actual_value) => {
-                tracing::info!("Successfully loaded secret for '{}'", name);
-                Ok(actual_value)
-            }
-            Err(e) => {
-                tracing::error!(
-                    "Failed to fetch secret from Secrets Manager for '{}': {}",
-                    name,
-                    e
-                );
-
-                Err(Box::new(SecretLoadError::ArnFetchFailed {
-                    name: name.to_string(),
-                    arn: env_value,
-                    aws_error: e.to_string(),
-                }))
-            }
+    match cache::global().get(&value).await {
+        Ok(actual_value) => {
+            tracing::info!("Successfully loaded secret for '{}'", name);
+            Ok(actual_value)
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to fetch secret from Secrets Manager for '{}': {}",
+                name,
+                e
+            );
+
+            Err(SecretLoadError::ArnFetchFailed {
+                name: name.to_string(),
+                arn: value,
+                aws_error: e.to_string(),
+            })
         }
-    } else {
-        tracing::debug!(
-            "Using direct value for environment variable '{}'",
-            name
-        );
-        Ok(env_value)
     }
 }
 
-async fn load_secret(
-    secrets_client: &aws_sdk_secretsmanager::Client,
-    secret_id: &str,
+/// A single field of a JSON secret, addressed by a JSON pointer such as
+/// `/password` or `/db/host`. String fields are returned without quotes,
+/// other values as JSON.
+pub async fn get_secret_field(
+    name: &str,
+    json_pointer: &str,
 ) -> Result<String, Box<dyn Error>> {
-    let secret_response = secrets_client
-        .get_secret_value()
-        .secret_id(secret_id)
-        .send()
-        .await?;
+    let secret = get_secret(name).await?;
+    Ok(extract_field(name, &secret, json_pointer)?)
+}
+
+/// A JSON secret deserialized into `T`.
+pub async fn get_secret_as<T: DeserializeOwned>(
+    name: &str,
+) -> Result<T, Box<dyn Error>> {
+    let secret = get_secret(name).await?;
+    Ok(parse_secret(name, &secret)?)
+}
+
+fn parse_secret<T: DeserializeOwned>(
+    name: &str,
+    secret: &str,
+) -> Result<T, SecretLoadError> {
+    serde_json::from_str(secret).map_err(|e| SecretLoadError::InvalidJson {
+        name: name.to_string(),
+        error: e.to_string(),
+    })
+}
 
-    let secret_string = secret_response
-        .secret_string()
-        .expect("Secret must have string value");
+fn extract_field(
+    name: &str,
+    secret: &str,
+    json_pointer: &str,
+) -> Result<String, SecretLoadError> {
+    let value: serde_json::Value = parse_secret(name, secret)?;
+
+    match value.pointer(json_pointer) {
+        Some(serde_json::Value::String(field)) => Ok(field.clone()),
+        Some(field) => Ok(field.to_string()),
+        None => Err(SecretLoadError::FieldNotFound {
+            name: name.to_string(),
+            pointer: json_pointer.to_string(),
+        }),
+    }
+}
 
-    Ok(secret_string.to_string())
+/// Drop the cached secret behind the environment variable `name`, so the
+/// next [`get_secret`] refetches it, e.g. after a rotation.
+pub async fn invalidate(name: &str) {
+    if let Ok(env_value) = std::env::var(name)
+        && is_secrets_manager_arn(&env_value)
+    {
+        cache::global().invalidate(&env_value).await;
+    }
 }
 
 #[cfg(test)]
@@ -212,6 +295,41 @@ mod tests {
         assert!(msg.contains("5. Verify the secret is in the same region"));
     }
 
+    #[test]
+    fn test_extract_field() {
+        let secret =
+            r#"{"username": "wire", "port": 5432, "db": {"host": "pg"}}"#;
+
+        assert_eq!(extract_field("DB", secret, "/username").unwrap(), "wire");
+        assert_eq!(extract_field("DB", secret, "/port").unwrap(), "5432");
+        assert_eq!(extract_field("DB", secret, "/db/host").unwrap(), "pg");
+        assert!(matches!(
+            extract_field("DB", secret, "/password"),
+            Err(SecretLoadError::FieldNotFound { .. })
+        ));
+        assert!(matches!(
+            extract_field("DB", "plain-text", "/username"),
+            Err(SecretLoadError::InvalidJson { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_secret_as_type() {
+        let credentials: postgres_models::connection::Credentials =
+            parse_secret("DB", r#"{"username": "wire", "password": "pw"}"#)
+                .unwrap();
+        assert_eq!(credentials.username, "wire");
+        assert_eq!(credentials.password, "pw");
+
+        let Err(err) = parse_secret::<postgres_models::connection::Credentials>(
+            "DB",
+            r#"{"username": "wire"}"#,
+        ) else {
+            panic!("credentials without a password must not parse");
+        };
+        assert!(err.to_string().contains("missing field `password`"));
+    }
+
     #[test]
     fn test_print_example_error_messages() {
         println!("\n========================================");
@@ -0,0 +1,147 @@
+//! Benchmarks of the request and import hot paths.
+//!
+//! Run with `make bench` or `cargo bench -p wire-api`; criterion compares
+//! each run with the previous one saved under `target/criterion`.
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use chrono::{Duration, TimeZone, Utc};
+use criterion::{Criterion, criterion_group, criterion_main};
+use uuid::Uuid;
+use validator::Validate;
+use wire_api::auth::TenantContext;
+use wire_api::bench::{
+    AggregateDataPoint, AggregateParams, AggregateRequest, AggregateResponse,
+    AggregationType, DEFAULT_INTERVAL_MINUTES, cache_key,
+};
+use wire_api::data_loader::kwh_decimal;
+use wire_api::shared::extractors::validations::{
+    Error as ValidationError, validation_errors_to_strings,
+};
+use wire_api::shared::json::DecimalFormat;
+
+/// Hours in a year, the size of a yearly hourly aggregation or import.
+const HOURS: usize = 8760;
+
+fn excel_parsing(c: &mut Criterion) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../../Test January2025-December2025-hourly-example.xlsx");
+    let mut group = c.benchmark_group("excel");
+    group.sample_size(10);
+    group.bench_function("read_worksheet_data", |b| {
+        b.iter(|| {
+            let mut client =
+                excel_client::ExcelDataReaderClient::new(path.clone()).unwrap();
+            client
+                .read_worksheet_data("Sheet1", &["Time (UTC)", "Quantity kWh"])
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn decimal_conversion(c: &mut Criterion) {
+    let quantities = (0..HOURS).map(|i| i as f64 * 0.137).collect::<Vec<_>>();
+    c.bench_function("kwh_decimal/8760", |b| {
+        b.iter(|| {
+            for quantity in &quantities {
+                black_box(kwh_decimal(black_box(*quantity)).unwrap());
+            }
+        })
+    });
+}
+
+fn cache_keys(c: &mut Criterion) {
+    let tenant = TenantContext::new("acme");
+    let request = AggregateRequest {
+        aggregation_type: AggregationType::Hourly,
+        date_from: Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()),
+        date_to: Some(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()),
+        week_start: None,
+        interval_minutes: DEFAULT_INTERVAL_MINUTES,
+        metrics: Vec::new(),
+    };
+    let params = AggregateParams::default();
+    let format = DecimalFormat::default();
+    c.bench_function("cache_key", |b| {
+        b.iter(|| cache_key(black_box(&tenant), &request, &params, &format))
+    });
+}
+
+fn response_serialization(c: &mut Criterion) {
+    let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let response = AggregateResponse {
+        aggregation_type: AggregationType::Hourly,
+        date_from: Some(start),
+        date_to: None,
+        data: (0..HOURS)
+            .map(|hour| AggregateDataPoint {
+                period: start + Duration::hours(hour as i64),
+                total_kwh: kwh_decimal(hour as f64 * 0.137).unwrap().into(),
+                reading_count: 1,
+                coverage: Some(1.0),
+                min_kwh: None,
+                max_kwh: None,
+                avg_kwh: None,
+                weather: None,
+            })
+            .collect(),
+        weather_correlation: None,
+        plan: None,
+    };
+    c.bench_function("aggregate_response_json/8760", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&response)).unwrap())
+    });
+}
+
+#[derive(Validate)]
+struct Reading {
+    #[validate(range(min = 0.0))]
+    quantity_kwh: f64,
+    #[validate(length(min = 1, max = 8))]
+    source: String,
+}
+
+#[derive(Validate)]
+struct Batch {
+    #[validate(length(min = 1, max = 64))]
+    name: String,
+    #[validate(nested)]
+    readings: Vec<Reading>,
+}
+
+fn validation_errors(c: &mut Criterion) {
+    let batch = Batch {
+        name: String::new(),
+        readings: (0..100)
+            .map(|_| Reading {
+                quantity_kwh: -1.0,
+                source: "a source name too long".to_string(),
+            })
+            .collect(),
+    };
+    let errors = batch.validate().unwrap_err();
+    let request_id = Uuid::new_v4();
+
+    let mut group = c.benchmark_group("validation_errors");
+    group.bench_function("strings", |b| {
+        b.iter(|| validation_errors_to_strings(black_box(&errors)))
+    });
+    group.bench_function("wire_v1", |b| {
+        b.iter(|| {
+            ValidationError::Validation(errors.clone())
+                .to_wire_v1_error(&request_id)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    excel_parsing,
+    decimal_conversion,
+    cache_keys,
+    response_serialization,
+    validation_errors
+);
+criterion_main!(benches);
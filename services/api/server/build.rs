@@ -0,0 +1,102 @@
+//! Build metadata for the `/version` endpoint, see `src/build_info.rs`, and
+//! the gRPC service code, see `src/grpc`.
+//!
+//! The git SHA comes from `GIT_SHA` when set (Docker builds have no `.git`),
+//! otherwise from `git rev-parse`. `SOURCE_DATE_EPOCH` pins the build time
+//! for reproducible builds. Protos are compiled with protox, so building
+//! needs no `protoc`.
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && !stdout.trim().is_empty())
+        .then(|| stdout.trim().to_string())
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Rebuild when the checked out commit changes.
+fn watch_git_head() {
+    let git_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../.git");
+    let head = git_dir.join("HEAD");
+    if !head.exists() {
+        return;
+    }
+    println!("cargo:rerun-if-changed={}", head.display());
+
+    if let Ok(content) = std::fs::read_to_string(&head)
+        && let Some(reference) = content.trim().strip_prefix("ref: ")
+    {
+        let reference = git_dir.join(reference);
+        if reference.exists() {
+            println!("cargo:rerun-if-changed={}", reference.display());
+        }
+    }
+}
+
+/// Generate the gRPC server code from the protos under `proto/`.
+fn compile_protos() {
+    let descriptors = protox::compile(["wire/v1/energy.proto"], ["proto"])
+        .unwrap_or_else(|e| panic!("Failed to compile protos: {e:?}"));
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)
+        .unwrap_or_else(|e| panic!("Failed to generate gRPC code: {e}"));
+}
+
+fn main() {
+    compile_protos();
+
+    let git_sha = env_var("GIT_SHA")
+        .or_else(|| command_output("git", &["rev-parse", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let built_at = env_var("SOURCE_DATE_EPOCH")
+        .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"])
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut features = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_ascii_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+
+    println!("cargo:rustc-env=WIRE_GIT_SHA={git_sha}");
+    println!("cargo:rustc-env=WIRE_BUILT_AT={built_at}");
+    println!("cargo:rustc-env=WIRE_RUSTC_VERSION={rustc_version}");
+    println!("cargo:rustc-env=WIRE_FEATURES={}", features.join(","));
+    println!(
+        "cargo:rustc-env=WIRE_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=WIRE_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-env-changed=VERSION");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=proto");
+    watch_git_head();
+}
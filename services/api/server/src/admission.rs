@@ -0,0 +1,300 @@
+//! Admission control of expensive aggregations.
+//!
+//! Every aggregation has an estimated cost, see [`aggregation_cost`]. While
+//! the pool serving reads has at least `ADMISSION_BUSY_CONNECTIONS`
+//! connections in use, which it always has with a mark of 0, aggregations costing more than `ADMISSION_BUDGET` wait for the
+//! pool to calm down, at most `ADMISSION_QUEUE_TIMEOUT_SECS`, before they
+//! reach the database; at most `ADMISSION_QUEUE_SIZE` of them wait at once.
+//! Aggregations within budget, and any while the pool is not busy, run at
+//! once. A budget of 0 admits everything.
+use std::time::Duration;
+
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, TimeDelta, Utc};
+use postgres_models::connection::Pool;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+use crate::wire_api::error_recorder::{ErrorRecorder, IntoWireV1Error};
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+/// How often a waiting aggregation checks the pool.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Range assumed for aggregations without a start, in years.
+const OPEN_RANGE_YEARS: i64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdmissionSettings {
+    /// Largest cost admitted while the pool is busy; 0 admits everything
+    pub budget: u64,
+    /// Connections of the pool serving reads in use from which it is busy
+    pub busy_connections: u32,
+    /// Aggregations over budget waiting at once
+    pub queue_size: u32,
+    /// How long an aggregation over budget waits for the pool
+    pub queue_timeout: Duration,
+}
+
+/// Why an aggregation over budget was not admitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// `ADMISSION_QUEUE_SIZE` aggregations were already waiting
+    QueueFull,
+    /// The pool stayed busy for `ADMISSION_QUEUE_TIMEOUT_SECS`
+    TimedOut,
+}
+
+/// An aggregation over budget that was not admitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "Aggregation of cost {cost} over the budget of {budget} while the \
+     database is busy"
+)]
+pub struct Rejection {
+    pub reason: Reason,
+    pub cost: u64,
+    pub budget: u64,
+    /// When the client may retry
+    pub retry_after: Duration,
+}
+
+impl Rejection {
+    /// Error code of the rejection, for metrics and responses.
+    pub fn code(&self) -> &'static str {
+        match self.reason {
+            Reason::QueueFull => "admission_queue_full",
+            Reason::TimedOut => "database_busy",
+        }
+    }
+
+    fn suggestion(&self) -> String {
+        format!(
+            "Narrow the date range or use a coarser aggregation type to stay \
+             within a cost of {}, or retry after {} seconds",
+            self.budget,
+            self.retry_after.as_secs()
+        )
+    }
+
+    /// `429 Too Many Requests` when the queue is full, `503 Service
+    /// Unavailable` when the wait timed out, with `Retry-After`.
+    pub(crate) fn into_response(self, recorder: &ErrorRecorder) -> Response {
+        let retry_after = self.retry_after.as_secs();
+        let mut response = recorder.record(self.code(), self).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, retry_after.into());
+        response
+    }
+}
+
+impl IntoWireV1Error for Rejection {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        let details = vec![WireV1Detail {
+            field: None,
+            code: self.code().to_string(),
+            message: self.to_string(),
+            suggestion: self.suggestion(),
+            documentation: String::new(),
+        }];
+        match self.reason {
+            Reason::QueueFull => WireV1Error::too_many_requests(
+                "Too many expensive aggregations".to_string(),
+                details,
+                request_id.to_string(),
+            ),
+            Reason::TimedOut => WireV1Error::service_unavailable(
+                "Database busy".to_string(),
+                details,
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl From<Rejection> for tonic::Status {
+    fn from(rejection: Rejection) -> Self {
+        let message = format!("{rejection}. {}", rejection.suggestion());
+        match rejection.reason {
+            Reason::QueueFull => tonic::Status::resource_exhausted(message),
+            Reason::TimedOut => tonic::Status::unavailable(message),
+        }
+    }
+}
+
+/// Estimated cost of an aggregation: the hours in its range times 4 for
+/// hourly, 2 for daily and 1 for weekly and longer periods, as finer
+/// periods take longer to group and return. A missing end is now and a missing start
+/// [`OPEN_RANGE_YEARS`] before the end; a year of hourly periods costs
+/// 35,040.
+pub fn aggregation_cost(
+    aggregation_type: AggregationType,
+    date_from: Option<DateTime<Utc>>,
+    date_to: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> u64 {
+    let weight = match aggregation_type {
+        AggregationType::Hourly => 4,
+        AggregationType::DayOfMonth => 2,
+        AggregationType::Weekly
+        | AggregationType::Monthly
+        | AggregationType::Quarterly
+        | AggregationType::Yearly => 1,
+    };
+    let date_to = date_to.unwrap_or(now);
+    let date_from =
+        date_from.unwrap_or(date_to - TimeDelta::days(365 * OPEN_RANGE_YEARS));
+    let hours = (date_to - date_from).num_hours().max(0) as u64;
+    hours.saturating_mul(weight)
+}
+
+pub struct AdmissionControl {
+    settings: AdmissionSettings,
+    /// Places for aggregations waiting for the pool
+    queue: Semaphore,
+}
+
+impl AdmissionControl {
+    pub fn new(settings: AdmissionSettings) -> Self {
+        Self {
+            queue: Semaphore::new(settings.queue_size as usize),
+            settings,
+        }
+    }
+
+    /// Whether an aggregation of `cost` waits for the pool while it is
+    /// busy.
+    pub fn over_budget(&self, cost: u64) -> bool {
+        self.settings.budget > 0 && cost > self.settings.budget
+    }
+
+    /// Whether `pool` is busy, see [`AdmissionControl::is_busy_with`].
+    pub fn is_busy(&self, pool: &Pool) -> bool {
+        let state = pool.state();
+        self.is_busy_with(state.connections - state.idle_connections)
+    }
+
+    /// Whether a pool with `in_use` connections checked out is busy, from
+    /// `busy_connections` on, so always with a mark of 0.
+    pub fn is_busy_with(&self, in_use: u32) -> bool {
+        in_use >= self.settings.busy_connections
+    }
+
+    /// Admit an aggregation of `cost` to run on `pool`, the one serving
+    /// reads, waiting for the pool when it is busy and the cost over budget.
+    pub async fn admit(&self, cost: u64, pool: &Pool) -> Result<(), Rejection> {
+        self.admit_when(cost, || self.is_busy(pool)).await
+    }
+
+    async fn admit_when(
+        &self,
+        cost: u64,
+        busy: impl Fn() -> bool,
+    ) -> Result<(), Rejection> {
+        if !self.over_budget(cost) || !busy() {
+            return Ok(());
+        }
+        let rejection = |reason| Rejection {
+            reason,
+            cost,
+            budget: self.settings.budget,
+            retry_after: self
+                .settings
+                .queue_timeout
+                .max(Duration::from_secs(1)),
+        };
+        let Ok(_place) = self.queue.try_acquire() else {
+            return Err(rejection(Reason::QueueFull));
+        };
+
+        tracing::info!(cost, "Aggregation over budget waiting for the pool");
+        let deadline = Instant::now() + self.settings.queue_timeout;
+        while busy() {
+            if Instant::now() >= deadline {
+                return Err(rejection(Reason::TimedOut));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_aggregation_cost() {
+        let at = |year| Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).unwrap();
+        let cost = |aggregation_type, date_from| {
+            aggregation_cost(
+                aggregation_type,
+                date_from,
+                Some(at(2025)),
+                at(2030),
+            )
+        };
+
+        assert_eq!(cost(AggregationType::Hourly, Some(at(2024))), 35_136);
+        assert_eq!(cost(AggregationType::Monthly, Some(at(2024))), 8_784);
+        assert_eq!(cost(AggregationType::Yearly, Some(at(2024))), 8_784);
+        assert_eq!(cost(AggregationType::Monthly, Some(at(2026))), 0);
+        assert_eq!(
+            cost(AggregationType::DayOfMonth, None),
+            365 * 24 * 2 * OPEN_RANGE_YEARS as u64
+        );
+        assert_eq!(
+            aggregation_cost(
+                AggregationType::Hourly,
+                Some(at(2029)),
+                None,
+                at(2030)
+            ),
+            8_760 * 4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_waits_over_budget_while_busy() {
+        let control = Arc::new(AdmissionControl::new(AdmissionSettings {
+            budget: 100,
+            busy_connections: 1,
+            queue_size: 1,
+            queue_timeout: Duration::from_millis(200),
+        }));
+        let busy = Arc::new(AtomicBool::new(true));
+
+        let reason = |result: Result<(), Rejection>| result.unwrap_err().reason;
+
+        assert_eq!(control.admit_when(100, || true).await, Ok(()));
+        assert_eq!(control.admit_when(101, || false).await, Ok(()));
+        let timed_out = control.admit_when(101, || true).await.unwrap_err();
+        assert_eq!(timed_out.reason, Reason::TimedOut);
+        assert_eq!(timed_out.retry_after, Duration::from_secs(1));
+
+        let waiting = tokio::spawn({
+            let control = control.clone();
+            let busy = busy.clone();
+            async move {
+                control
+                    .admit_when(101, || busy.load(Ordering::Relaxed))
+                    .await
+            }
+        });
+        tokio::time::sleep(POLL_INTERVAL).await;
+        assert_eq!(
+            reason(control.admit_when(101, || true).await),
+            Reason::QueueFull
+        );
+        busy.store(false, Ordering::Relaxed);
+        assert_eq!(waiting.await.unwrap(), Ok(()));
+    }
+}
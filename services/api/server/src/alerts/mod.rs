@@ -0,0 +1,357 @@
+//! Alert rules on energy readings.
+//!
+//! The [`AlertEvaluator`] computes the metric of every enabled rule over the
+//! readings of its window. A rule fires when its metric starts breaching the
+//! threshold: an alert is recorded, a `threshold_breached` event goes
+//! through the outbox to subscribed webhooks and, when the rule has a
+//! channel, a notification is queued for it (see [`crate::notifications`]).
+//! The alert is resolved once the metric no longer breaches the threshold.
+//! Without readings in the window, only the `total_kwh` and `reading_count`
+//! metrics have a value (zero), so other rules stay as they are.
+use std::sync::Arc;
+use std::time::Duration;
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use diesel_async::AsyncConnection;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use postgres_models::models::alerts::{
+    Alert, AlertRule, NewAlert, comparison, metric,
+};
+use postgres_models::models::energy_readings::{EnergyReading, ReadingStats};
+use telemetry::metrics::Telemetry;
+
+use crate::metrics::ServerMetrics;
+use crate::notifications::{self, Message};
+use crate::outbox;
+use crate::shutdown::ShutdownCoordinator;
+use crate::webhooks::WebhookEvent;
+
+/// Rules evaluated per evaluator transaction.
+const BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Clone)]
+pub struct EvaluatorSettings {
+    pub poll_interval: Duration,
+}
+
+/// The value of `rule_metric` in `stats`, `None` when it has none.
+pub fn metric_value(
+    rule_metric: &str,
+    stats: &ReadingStats,
+) -> Option<BigDecimal> {
+    match rule_metric {
+        metric::TOTAL_KWH => Some(stats.total_kwh.clone().unwrap_or_default()),
+        metric::AVERAGE_KWH => stats.average_kwh.clone(),
+        metric::MAX_KWH => stats.max_kwh.clone(),
+        metric::MIN_KWH => stats.min_kwh.clone(),
+        metric::READING_COUNT => Some(BigDecimal::from(stats.reading_count)),
+        _ => None,
+    }
+}
+
+/// Whether `value` breaches `threshold` under `rule_comparison`.
+pub fn breaches(
+    rule_comparison: &str,
+    value: &BigDecimal,
+    threshold: &BigDecimal,
+) -> bool {
+    match rule_comparison {
+        comparison::GT => value > threshold,
+        comparison::GTE => value >= threshold,
+        comparison::LT => value < threshold,
+        comparison::LTE => value <= threshold,
+        _ => false,
+    }
+}
+
+fn operator(rule_comparison: &str) -> &'static str {
+    match rule_comparison {
+        comparison::GT => ">",
+        comparison::GTE => ">=",
+        comparison::LT => "<",
+        comparison::LTE => "<=",
+        _ => "?",
+    }
+}
+
+/// Data of the `threshold_breached` event of `alert`.
+fn event_data(rule: &AlertRule, alert: &Alert) -> serde_json::Value {
+    serde_json::json!({
+        "alertId": alert.id,
+        "ruleId": rule.id,
+        "ruleName": rule.name,
+        "plantId": rule.plant_id,
+        "metric": rule.metric,
+        "comparison": rule.comparison,
+        "value": alert.value.to_string(),
+        "threshold": alert.threshold.to_string(),
+        "windowStart": alert.window_start,
+        "windowEnd": alert.window_end,
+        "firedAt": alert.fired_at,
+    })
+}
+
+/// Notification of `alert` to the rule's channel.
+fn alert_message(rule: &AlertRule, alert: &Alert) -> Message {
+    let subject = format!(
+        "Alert: {} ({} {} {})",
+        rule.name,
+        rule.metric,
+        operator(&rule.comparison),
+        alert.threshold
+    );
+    let plant = rule
+        .plant_id
+        .map(|plant| plant.to_string())
+        .unwrap_or_else(|| "all plants".to_string());
+    let body = format!(
+        "Alert rule \"{}\" fired at {}.\n\n\
+         Metric:    {} over readings of {}\n\
+         Value:     {}\n\
+         Threshold: {} {}\n\
+         Window:    {} to {}\n\
+         Alert id:  {}\n",
+        rule.name,
+        alert.fired_at.to_rfc3339(),
+        rule.metric,
+        plant,
+        alert.value,
+        operator(&rule.comparison),
+        alert.threshold,
+        alert.window_start.to_rfc3339(),
+        alert.window_end.to_rfc3339(),
+        alert.id,
+    );
+    Message {
+        subject,
+        body,
+        data: event_data(rule, alert),
+    }
+}
+
+/// Background worker that evaluates alert rules.
+pub struct AlertEvaluator {
+    pool: postgres_models::connection::Pool,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    settings: EvaluatorSettings,
+}
+
+impl AlertEvaluator {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        settings: EvaluatorSettings,
+    ) -> Self {
+        Self {
+            pool,
+            telemetry,
+            settings,
+        }
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            poll_interval = ?self.settings.poll_interval,
+            "Starting alert evaluator"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.poll_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            // Keep going while full batches come back, to catch up quickly
+            loop {
+                match self.evaluate_batch().await {
+                    Ok(evaluated) if evaluated == BATCH_SIZE as usize => {}
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::warn!("Alert evaluation cycle failed: {e:#}");
+                        break;
+                    }
+                }
+            }
+        }
+
+        tracing::info!("Alert evaluator stopped");
+    }
+
+    /// Evaluate the next batch of due rules. Returns the number of rules
+    /// evaluated.
+    async fn evaluate_batch(&self) -> anyhow::Result<usize> {
+        let now = Utc::now();
+        // Rules are due once per interval, even with several evaluators
+        let due_before = now - self.settings.poll_interval / 2;
+        let mut conn = self.pool.get().await?;
+        let evaluated = conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                async move {
+                    let rules =
+                        AlertRule::claim_due(due_before, BATCH_SIZE, conn)
+                            .await?;
+
+                    for rule in &rules {
+                        let window_start = now
+                            - chrono::Duration::minutes(
+                                rule.window_minutes.into(),
+                            );
+                        let stats = EnergyReading::window_stats(
+                            &rule.tenant_id,
+                            rule.plant_id,
+                            window_start,
+                            now,
+                            conn,
+                        )
+                        .await?;
+                        let breached =
+                            metric_value(&rule.metric, &stats).map(|value| {
+                                let breached = breaches(
+                                    &rule.comparison,
+                                    &value,
+                                    &rule.threshold,
+                                );
+                                (breached, value)
+                            });
+
+                        let firing = match breached {
+                            Some((true, value)) if !rule.firing => {
+                                let alert = Alert::create(
+                                    NewAlert {
+                                        rule_id: rule.id,
+                                        tenant_id: rule.tenant_id.clone(),
+                                        value,
+                                        threshold: rule.threshold.clone(),
+                                        window_start,
+                                        window_end: now,
+                                        fired_at: now,
+                                    },
+                                    conn,
+                                )
+                                .await?;
+                                tracing::info!(
+                                    rule_id = %rule.id,
+                                    alert_id = %alert.id,
+                                    value = %alert.value,
+                                    "Alert rule fired"
+                                );
+                                self.record_event("fired");
+                                outbox::record(
+                                    WebhookEvent::ThresholdBreached,
+                                    &rule.tenant_id,
+                                    event_data(rule, &alert),
+                                    conn,
+                                )
+                                .await?;
+                                if let Some(channel_id) = rule.channel_id
+                                    && let Some(notification) =
+                                        notifications::notify_channel(
+                                            channel_id,
+                                            &rule.tenant_id,
+                                            notifications::ALERT_FIRED,
+                                            &alert_message(rule, &alert),
+                                            conn,
+                                        )
+                                        .await?
+                                {
+                                    Alert::set_notification(
+                                        alert.id,
+                                        notification.id,
+                                        conn,
+                                    )
+                                    .await?;
+                                    self.record_event("notification_queued");
+                                }
+                                true
+                            }
+                            Some((false, _)) if rule.firing => {
+                                Alert::resolve_open(rule.id, now, conn).await?;
+                                tracing::info!(
+                                    rule_id = %rule.id,
+                                    "Alert rule resolved"
+                                );
+                                self.record_event("resolved");
+                                false
+                            }
+                            _ => rule.firing,
+                        };
+                        AlertRule::record_evaluation(
+                            rule.id, firing, now, conn,
+                        )
+                        .await?;
+                    }
+                    Ok(rules.len())
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(evaluated)
+    }
+
+    fn record_event(&self, outcome: &str) {
+        self.telemetry
+            .maybe_use_metrics(|m| m.record_alert_event(outcome));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(readings: &[i64]) -> ReadingStats {
+        let values = readings.iter().copied().map(BigDecimal::from);
+        ReadingStats {
+            reading_count: readings.len() as i64,
+            total_kwh: (!readings.is_empty())
+                .then(|| values.clone().sum::<BigDecimal>()),
+            average_kwh: (!readings.is_empty()).then(|| {
+                values.clone().sum::<BigDecimal>()
+                    / BigDecimal::from(readings.len() as i64)
+            }),
+            max_kwh: values.clone().max(),
+            min_kwh: values.min(),
+        }
+    }
+
+    #[test]
+    fn metrics_of_empty_windows() {
+        let empty = stats(&[]);
+        assert_eq!(
+            metric_value(metric::TOTAL_KWH, &empty),
+            Some(BigDecimal::from(0))
+        );
+        assert_eq!(
+            metric_value(metric::READING_COUNT, &empty),
+            Some(BigDecimal::from(0))
+        );
+        assert_eq!(metric_value(metric::AVERAGE_KWH, &empty), None);
+        assert_eq!(metric_value(metric::MIN_KWH, &empty), None);
+
+        let readings = stats(&[4, 10, 1]);
+        assert_eq!(
+            metric_value(metric::TOTAL_KWH, &readings),
+            Some(BigDecimal::from(15))
+        );
+        assert_eq!(
+            metric_value(metric::MAX_KWH, &readings),
+            Some(BigDecimal::from(10))
+        );
+    }
+
+    #[test]
+    fn compares_with_threshold() {
+        let threshold = BigDecimal::from(10);
+        let at = BigDecimal::from(10);
+        assert!(!breaches(comparison::GT, &at, &threshold));
+        assert!(breaches(comparison::GTE, &at, &threshold));
+        assert!(!breaches(comparison::LT, &at, &threshold));
+        assert!(breaches(comparison::LTE, &at, &threshold));
+        assert!(breaches(comparison::LT, &BigDecimal::from(9), &threshold));
+    }
+}
@@ -0,0 +1,62 @@
+use sha2::{Digest, Sha256};
+
+pub const KEY_PREFIX: &str = "wk_";
+/// Number of leading characters stored in clear for identification.
+const DISPLAY_PREFIX_LEN: usize = KEY_PREFIX.len() + 8;
+
+/// A freshly generated API key. `secret` and `signing_secret` are shown to
+/// the caller exactly once.
+pub struct GeneratedKey {
+    pub secret: String,
+    pub prefix: String,
+    pub hash: String,
+    pub signing_secret: String,
+}
+
+pub fn generate() -> GeneratedKey {
+    let bytes: [u8; 32] = rand::random();
+    let secret = format!("{KEY_PREFIX}{}", hex::encode(bytes));
+
+    let signing_bytes: [u8; 32] = rand::random();
+
+    GeneratedKey {
+        prefix: secret[..DISPLAY_PREFIX_LEN].to_string(),
+        hash: hash(&secret),
+        secret,
+        signing_secret: format!("wss_{}", hex::encode(signing_bytes)),
+    }
+}
+
+/// SHA-256 hex digest of a key. Keys are 256-bit random values, so a fast
+/// unsalted hash is sufficient to make the stored value useless if leaked.
+pub fn hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Whether a bearer token looks like one of our API keys rather than a JWT.
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(KEY_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_key_shape() {
+        let key = generate();
+
+        assert!(key.secret.starts_with("wk_"));
+        assert_eq!(key.secret.len(), 3 + 64);
+        assert!(key.secret.starts_with(&key.prefix));
+        assert_eq!(key.hash, hash(&key.secret));
+        assert!(key.signing_secret.starts_with("wss_"));
+        assert_ne!(key.signing_secret[4..], key.secret[3..]);
+    }
+
+    #[test]
+    fn test_hash_differs_per_key() {
+        assert_ne!(generate().hash, generate().hash);
+        assert_ne!(hash("wk_a"), hash("wk_b"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Session-less CSRF protection for browser-originated requests.
+//!
+//! Browsers send `Sec-Fetch-Site` and `Origin` with state-changing requests,
+//! which tells whether a page on another site triggered them. Requests
+//! without either header come from non-browser clients and are allowed.
+use axum::http::{HeaderMap, Method, header};
+
+const SEC_FETCH_SITE_HEADER: &str = "sec-fetch-site";
+
+/// Route groups that can be protected independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    /// `/api/wire/v1`
+    Wire,
+    /// `/api/wire/v1/admin`
+    Admin,
+}
+
+impl RouteGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteGroup::Wire => "wire",
+            RouteGroup::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for RouteGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wire" => Ok(RouteGroup::Wire),
+            "admin" => Ok(RouteGroup::Admin),
+            other => Err(format!("Unknown route group `{other}`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CsrfPolicy {
+    /// Route groups whose state-changing requests are checked
+    pub groups: Vec<RouteGroup>,
+    /// Origins besides the API's own allowed to send state-changing requests,
+    /// e.g. `https://dashboard.example.com`
+    pub trusted_origins: Vec<String>,
+}
+
+/// Why a request was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub origin: Option<String>,
+    pub fetch_site: Option<String>,
+}
+
+/// Parse a comma-separated list of route groups.
+pub fn parse_groups(list: &str) -> Result<Vec<RouteGroup>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Parse a comma-separated list of origins.
+pub fn parse_origins(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(normalize_origin)
+        .filter(|origin| !origin.is_empty())
+        .collect()
+}
+
+fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+fn is_state_changing(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+impl CsrfPolicy {
+    pub fn protects(&self, group: RouteGroup) -> bool {
+        self.groups.contains(&group)
+    }
+
+    /// Check a request to a protected route group.
+    pub fn check(
+        &self,
+        method: &Method,
+        headers: &HeaderMap,
+    ) -> Result<(), Rejection> {
+        if !is_state_changing(method) {
+            return Ok(());
+        }
+
+        let value = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        let fetch_site = value(SEC_FETCH_SITE_HEADER);
+        let origin = value(header::ORIGIN.as_str());
+
+        // Same-origin pages and user-initiated navigation
+        if matches!(fetch_site, Some("same-origin" | "none")) {
+            return Ok(());
+        }
+
+        let allowed = match origin {
+            Some(origin) => {
+                let origin = normalize_origin(origin);
+                self.trusted_origins.contains(&origin)
+                    || (fetch_site.is_none()
+                        && is_own_origin(&origin, value(header::HOST.as_str())))
+            }
+            // Browsers always send Origin with cross-site requests
+            None => fetch_site.is_none(),
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(Rejection {
+                origin: origin.map(str::to_string),
+                fetch_site: fetch_site.map(str::to_string),
+            })
+        }
+    }
+}
+
+/// Whether `origin` points at the host the request was sent to.
+fn is_own_origin(origin: &str, host: Option<&str>) -> bool {
+    let Some(host) = host else {
+        return false;
+    };
+    origin
+        .split_once("://")
+        .is_some_and(|(_, authority)| authority.eq_ignore_ascii_case(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn policy() -> CsrfPolicy {
+        CsrfPolicy {
+            groups: vec![RouteGroup::Admin],
+            trusted_origins: parse_origins("https://Dashboard.example.com/"),
+        }
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        assert_eq!(
+            parse_groups("wire, admin,").unwrap(),
+            vec![RouteGroup::Wire, RouteGroup::Admin]
+        );
+        assert!(parse_groups("wire,public").is_err());
+        assert!(policy().protects(RouteGroup::Admin));
+        assert!(!policy().protects(RouteGroup::Wire));
+    }
+
+    #[test]
+    fn test_non_browser_and_safe_requests_pass() {
+        let cross_site = headers(&[
+            ("sec-fetch-site", "cross-site"),
+            ("origin", "https://evil.example"),
+        ]);
+
+        assert!(policy().check(&Method::POST, &HeaderMap::new()).is_ok());
+        assert!(policy().check(&Method::GET, &cross_site).is_ok());
+        assert!(policy().check(&Method::POST, &cross_site).is_err());
+    }
+
+    #[test]
+    fn test_fetch_metadata() {
+        let check = |site: &str, origin: &str| {
+            policy().check(
+                &Method::DELETE,
+                &headers(&[("sec-fetch-site", site), ("origin", origin)]),
+            )
+        };
+
+        assert!(check("same-origin", "https://api.example.com").is_ok());
+        assert!(check("same-site", "https://dashboard.example.com").is_ok());
+        assert!(check("same-site", "https://blog.example.com").is_err());
+        assert!(check("cross-site", "null").is_err());
+    }
+
+    #[test]
+    fn test_origin_without_fetch_metadata() {
+        let check = |origin: &str| {
+            policy().check(
+                &Method::POST,
+                &headers(&[("origin", origin), ("host", "api.example.com")]),
+            )
+        };
+
+        assert!(check("https://api.example.com").is_ok());
+        assert!(check("https://dashboard.example.com").is_ok());
+        assert!(check("https://evil.example").is_err());
+        assert!(check("null").is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use uuid::Uuid;
+
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Missing credentials")]
+    MissingCredentials,
+
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+
+    #[error("Identity provider unavailable: {0}")]
+    IdentityProviderUnavailable(String),
+
+    #[error("Role {0} required")]
+    InsufficientRole(super::roles::Role),
+
+    #[error("{} quota of {limit} requests exhausted", window.as_str())]
+    QuotaExceeded {
+        window: super::quota::Window,
+        limit: i64,
+        resets_at: chrono::DateTime<chrono::Utc>,
+    },
+
+    #[error("Admin API is disabled")]
+    AdminDisabled,
+
+    #[error("Cross-origin request from {0:?} rejected")]
+    CrossOriginRequest(Option<String>),
+
+    #[error("Client address {0} is not allowed")]
+    IpNotAllowed(std::net::IpAddr),
+
+    #[error("Missing or malformed {0} header")]
+    MissingSignature(&'static str),
+
+    #[error("Invalid request signature")]
+    InvalidSignature,
+
+    #[error("Request signature expired")]
+    SignatureExpired { max_age_secs: u64 },
+
+    #[error("Request signature already used")]
+    ReplayedSignature,
+
+    #[error("Request signing is not enabled for this caller")]
+    SigningNotEnabled,
+
+    #[error("Failed to read request body: {0}")]
+    UnreadableBody(String),
+
+    #[error("Failed to get database connection: {0}")]
+    Pool(String),
+
+    #[error("No database connection available: {0}")]
+    PoolExhausted(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] diesel::result::Error),
+}
+
+impl From<super::jwt::JwtError> for Error {
+    fn from(e: super::jwt::JwtError) -> Self {
+        match e {
+            super::jwt::JwtError::JwksUnavailable(e) => {
+                Error::IdentityProviderUnavailable(e)
+            }
+            e => Error::InvalidToken(e.to_string()),
+        }
+    }
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::MissingCredentials => WireV1Error::unauthorized(
+                "Authentication required".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Authorization".to_string()),
+                    code: "missing_credentials".to_string(),
+                    message: "The Authorization header is missing".to_string(),
+                    suggestion: "Send `Authorization: Bearer <api key or JWT>`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidCredentials => WireV1Error::unauthorized(
+                "Authentication failed".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Authorization".to_string()),
+                    code: "invalid_credentials".to_string(),
+                    message: "The supplied credentials are invalid or revoked"
+                        .to_string(),
+                    suggestion: "Check the API key or request a new one"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidToken(e) => WireV1Error::unauthorized(
+                "Authentication failed".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Authorization".to_string()),
+                    code: "invalid_token".to_string(),
+                    message: e,
+                    suggestion: "Obtain a new token from the issuer"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::IdentityProviderUnavailable(e) => {
+                WireV1Error::service_unavailable(
+                    "Service temporarily unavailable".to_string(),
+                    vec![WireV1Detail {
+                        field: None,
+                        code: "identity_provider_unavailable".to_string(),
+                        message: e,
+                        suggestion: "Please try again later".to_string(),
+                        documentation: String::new(),
+                    }],
+                    request_id.to_string(),
+                )
+            }
+            Error::InsufficientRole(role) => WireV1Error::forbidden(
+                "Insufficient permissions".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "insufficient_permissions".to_string(),
+                    message: format!(
+                        "This endpoint requires the `{role}` role"
+                    ),
+                    suggestion: format!(
+                        "Use an API key with the `{role}` role or a token \
+                         with the `wire:{role}` scope"
+                    ),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::QuotaExceeded {
+                window,
+                limit,
+                resets_at,
+            } => WireV1Error::too_many_requests(
+                "Quota exceeded".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "quota_exceeded".to_string(),
+                    message: format!(
+                        "The {} quota of {limit} requests is exhausted",
+                        window.as_str()
+                    ),
+                    suggestion: format!(
+                        "Retry after {}, or ask for a higher quota",
+                        resets_at.to_rfc3339()
+                    ),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::AdminDisabled => WireV1Error::forbidden(
+                "Admin API is disabled".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "admin_disabled".to_string(),
+                    message: "No admin token is configured on this deployment"
+                        .to_string(),
+                    suggestion: "Set ADMIN_API_TOKEN to enable admin endpoints"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::CrossOriginRequest(origin) => WireV1Error::forbidden(
+                "Cross-origin request rejected".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Origin".to_string()),
+                    code: "cross_origin_request".to_string(),
+                    message: match origin {
+                        Some(origin) => format!(
+                            "State-changing requests from {origin} are not \
+                             allowed"
+                        ),
+                        None => "State-changing requests from other sites are \
+                                 not allowed"
+                            .to_string(),
+                    },
+                    suggestion: "Add the page's origin to CSRF_TRUSTED_ORIGINS"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::IpNotAllowed(ip) => WireV1Error::forbidden(
+                "Access denied".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "ip_not_allowed".to_string(),
+                    message: format!(
+                        "Admin endpoints are not reachable from {ip}"
+                    ),
+                    suggestion: "Call the admin API from an allowed network"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::MissingSignature(header) => WireV1Error::unauthorized(
+                "Request signature required".to_string(),
+                vec![WireV1Detail {
+                    field: Some(header.to_string()),
+                    code: "missing_signature".to_string(),
+                    message: format!(
+                        "The {header} header is missing or malformed"
+                    ),
+                    suggestion: "Sign the request with the API key's signing \
+                                 secret"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidSignature => WireV1Error::unauthorized(
+                "Invalid request signature".to_string(),
+                vec![WireV1Detail {
+                    field: Some(super::signature::SIGNATURE_HEADER.to_string()),
+                    code: "invalid_signature".to_string(),
+                    message: "The signature does not match the request body"
+                        .to_string(),
+                    suggestion: "Sign `{timestamp}.{body}` with HMAC-SHA256 \
+                                 and send it as `sha256=<hex>`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::SignatureExpired { max_age_secs } => {
+                WireV1Error::unauthorized(
+                    "Request signature expired".to_string(),
+                    vec![WireV1Detail {
+                        field: Some(
+                            super::signature::TIMESTAMP_HEADER.to_string(),
+                        ),
+                        code: "signature_expired".to_string(),
+                        message: format!(
+                            "The timestamp is more than {max_age_secs} seconds \
+                             from the server time"
+                        ),
+                        suggestion: "Sign the request with the current time \
+                                     and check the client clock"
+                            .to_string(),
+                        documentation: String::new(),
+                    }],
+                    request_id.to_string(),
+                )
+            }
+            Error::ReplayedSignature => WireV1Error::unauthorized(
+                "Request already processed".to_string(),
+                vec![WireV1Detail {
+                    field: Some(super::signature::SIGNATURE_HEADER.to_string()),
+                    code: "replayed_request".to_string(),
+                    message: "This signed request was already received"
+                        .to_string(),
+                    suggestion: "Sign every request with a fresh timestamp"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::SigningNotEnabled => WireV1Error::forbidden(
+                "Request signing is not enabled".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "signing_not_enabled".to_string(),
+                    message: "This endpoint requires an API key with a \
+                              signing secret"
+                        .to_string(),
+                    suggestion: "Issue a new API key and keep its signing \
+                                 secret"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::UnreadableBody(e) => WireV1Error::bad_request(
+                "Invalid request body".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "unreadable_body".to_string(),
+                    message: format!("Failed to read request body: {e}"),
+                    suggestion: format!(
+                        "Send at most {} bytes per request",
+                        super::signature::MAX_SIGNED_BODY_BYTES
+                    ),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::Pool(e) => WireV1Error::service_unavailable(
+                "Service temporarily unavailable".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "pool_error".to_string(),
+                    message: format!("Failed to get database connection: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::PoolExhausted(e) => WireV1Error::service_unavailable(
+                "Service temporarily unavailable".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "pool_exhausted".to_string(),
+                    message: format!("No database connection available: {e}"),
+                    suggestion: "Retry with backoff".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::Database(e) => WireV1Error::internal_server_error(
+                "Authentication failed".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "database_error".to_string(),
+                    message: format!("Database error: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! CIDR allow/deny lists for admin routes.
+//!
+//! The client address is the TCP peer, unless the peer is a trusted proxy:
+//! then `X-Forwarded-For` is walked from the right and the first address that
+//! is not a trusted proxy is used. Headers from untrusted peers are ignored,
+//! so clients cannot spoof their address.
+use std::net::IpAddr;
+
+use axum::http::HeaderMap;
+use ipnet::IpNet;
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    /// When non-empty, only these networks are allowed
+    pub allow: Vec<IpNet>,
+    /// Always rejected, even when also allowed
+    pub deny: Vec<IpNet>,
+    /// Proxies whose `X-Forwarded-For` header is honored
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+/// Outcome of [`IpFilter::check`], with the rule that decided it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Matched an allow rule
+    Allowed(IpNet),
+    /// Matched a deny rule
+    Denied(IpNet),
+    /// Not on the allow list
+    NotAllowed,
+    /// No rule applies and there is no allow list
+    Unmatched,
+}
+
+impl Decision {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Decision::Allowed(_) | Decision::Unmatched)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Decision::Allowed(_) => "allowed",
+            Decision::Denied(_) => "denied",
+            Decision::NotAllowed => "not_allowed",
+            Decision::Unmatched => "unmatched",
+        }
+    }
+
+    /// The matching rule, for logs and metric labels.
+    pub fn rule(&self) -> Option<&IpNet> {
+        match self {
+            Decision::Allowed(rule) | Decision::Denied(rule) => Some(rule),
+            Decision::NotAllowed | Decision::Unmatched => None,
+        }
+    }
+}
+
+/// Parse a comma-separated list of CIDRs or single addresses.
+pub fn parse_networks(list: &str) -> Result<Vec<IpNet>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("Invalid network `{entry}`"))
+        })
+        .collect()
+}
+
+impl IpFilter {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Address of the client that sent a request received from `peer`.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !contains(&self.trusted_proxies, peer) {
+            return peer;
+        }
+
+        let forwarded = headers
+            .get_all(FORWARDED_FOR_HEADER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+
+        let mut client = peer;
+        for hop in forwarded.into_iter().rev() {
+            let Ok(ip) = hop.parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !contains(&self.trusted_proxies, ip) {
+                break;
+            }
+        }
+        client
+    }
+
+    pub fn check(&self, ip: IpAddr) -> Decision {
+        if let Some(rule) = matching(&self.deny, ip) {
+            return Decision::Denied(*rule);
+        }
+        if let Some(rule) = matching(&self.allow, ip) {
+            return Decision::Allowed(*rule);
+        }
+        if self.allow.is_empty() {
+            Decision::Unmatched
+        } else {
+            Decision::NotAllowed
+        }
+    }
+}
+
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+fn matching(networks: &[IpNet], ip: IpAddr) -> Option<&IpNet> {
+    let ip = canonical(ip);
+    networks.iter().find(|net| net.contains(&ip))
+}
+
+fn contains(networks: &[IpNet], ip: IpAddr) -> bool {
+    matching(networks, ip).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn filter(allow: &str, deny: &str, proxies: &str) -> IpFilter {
+        IpFilter {
+            allow: parse_networks(allow).unwrap(),
+            deny: parse_networks(deny).unwrap(),
+            trusted_proxies: parse_networks(proxies).unwrap(),
+        }
+    }
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            FORWARDED_FOR_HEADER,
+            HeaderValue::from_str(value).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_parse_networks() {
+        let networks =
+            parse_networks(" 10.0.0.0/8, 192.168.1.7 ,,::1").unwrap();
+        assert_eq!(
+            networks,
+            vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "192.168.1.7/32".parse().unwrap(),
+                "::1/128".parse().unwrap(),
+            ]
+        );
+        assert!(parse_networks("10.0.0.0/33").is_err());
+        assert!(parse_networks("not-an-ip").is_err());
+    }
+
+    #[test]
+    fn test_deny_takes_precedence() {
+        let filter = filter("10.0.0.0/8", "10.1.0.0/16", "");
+
+        assert_eq!(
+            filter.check(ip("10.2.0.1")),
+            Decision::Allowed("10.0.0.0/8".parse().unwrap())
+        );
+        assert_eq!(
+            filter.check(ip("10.1.2.3")),
+            Decision::Denied("10.1.0.0/16".parse().unwrap())
+        );
+        assert_eq!(filter.check(ip("192.168.0.1")), Decision::NotAllowed);
+        assert_eq!(
+            filter.check(ip("::ffff:10.2.0.1")),
+            Decision::Allowed("10.0.0.0/8".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_deny_only_allows_the_rest() {
+        let filter = filter("", "203.0.113.0/24", "");
+
+        assert!(filter.check(ip("198.51.100.1")).is_allowed());
+        assert!(!filter.check(ip("203.0.113.9")).is_allowed());
+    }
+
+    #[test]
+    fn test_forwarded_for_only_from_trusted_proxies() {
+        let filter = filter("", "", "10.0.0.0/8");
+        let headers = forwarded_for("1.1.1.1, 203.0.113.5, 10.0.0.2");
+
+        assert_eq!(
+            filter.client_ip(ip("10.0.0.1"), &headers),
+            ip("203.0.113.5")
+        );
+        assert_eq!(
+            filter.client_ip(ip("198.51.100.1"), &headers),
+            ip("198.51.100.1")
+        );
+        assert_eq!(
+            filter.client_ip(ip("10.0.0.1"), &HeaderMap::new()),
+            ip("10.0.0.1")
+        );
+        assert_eq!(
+            filter.client_ip(ip("10.0.0.1"), &forwarded_for("garbage")),
+            ip("10.0.0.1")
+        );
+    }
+}
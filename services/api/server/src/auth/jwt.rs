@@ -0,0 +1,341 @@
+use std::time::{Duration, Instant};
+
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum::http::request::Parts;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{DecodingKey, Validation};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use super::errors;
+use super::roles::Role;
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::IntoWireV1Error;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+/// Minimum time between two JWKS fetches triggered by an unknown `kid`, so
+/// tokens with made-up key ids cannot hammer the identity provider.
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+/// Clock skew tolerated when checking `exp` and `nbf`.
+const LEEWAY_SECS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct JwtSettings {
+    pub issuer: String,
+    pub audience: String,
+    pub jwks_url: String,
+    pub jwks_refresh_interval: Duration,
+    pub request_timeout: Duration,
+}
+
+/// Verified claims of a JWT bearer token, attached to the request by
+/// [`super::middleware::authenticate`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Claims {
+    /// Subject, the caller identity assigned by the issuer
+    pub sub: String,
+    pub iss: String,
+    pub exp: i64,
+    #[serde(default)]
+    pub iat: Option<i64>,
+    /// Space separated OAuth scopes
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Tenant the caller belongs to, the default tenant when absent
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+impl Claims {
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.as_deref().unwrap_or_default().split_whitespace()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes().any(|s| s == scope)
+    }
+
+    /// Role granted by the `wire:<role>` scopes, if any.
+    pub fn role(&self) -> Option<Role> {
+        Role::from_scopes(self.scopes())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    #[error("Malformed token: {0}")]
+    Malformed(jsonwebtoken::errors::Error),
+
+    #[error("Token has no key id")]
+    MissingKeyId,
+
+    #[error("Unknown signing key {0}")]
+    UnknownKey(String),
+
+    #[error("Token rejected: {0}")]
+    Rejected(jsonwebtoken::errors::Error),
+
+    #[error("Failed to fetch JWKS: {0}")]
+    JwksUnavailable(String),
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Validates JWTs against the issuer's JWKS.
+///
+/// The key set is cached for `jwks_refresh_interval` and refetched early when
+/// a token references a key id we have not seen yet (key rotation). If a
+/// refresh fails the previously cached keys keep being used.
+pub struct JwtVerifier {
+    settings: JwtSettings,
+    client: reqwest::Client,
+    jwks: RwLock<Option<CachedJwks>>,
+}
+
+impl JwtVerifier {
+    pub fn new(settings: JwtSettings) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(settings.request_timeout)
+            .build()?;
+
+        Ok(Self {
+            settings,
+            client,
+            jwks: RwLock::new(None),
+        })
+    }
+
+    /// Verify the token signature, issuer, audience and expiry.
+    pub async fn verify(&self, token: &str) -> Result<Claims, JwtError> {
+        let header =
+            jsonwebtoken::decode_header(token).map_err(JwtError::Malformed)?;
+        let kid = header.kid.ok_or(JwtError::MissingKeyId)?;
+        let key = self.decoding_key(&kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = LEEWAY_SECS;
+        validation.set_issuer(&[&self.settings.issuer]);
+        validation.set_audience(&[&self.settings.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        jsonwebtoken::decode::<Claims>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(JwtError::Rejected)
+    }
+
+    async fn decoding_key(&self, kid: &str) -> Result<DecodingKey, JwtError> {
+        let (key, fresh, refetch_allowed) = {
+            let cached = self.jwks.read().await;
+            match cached.as_ref() {
+                Some(cached) => {
+                    let age = cached.fetched_at.elapsed();
+                    (
+                        find_key(&cached.keys, kid),
+                        age < self.settings.jwks_refresh_interval,
+                        age >= MIN_REFETCH_INTERVAL,
+                    )
+                }
+                None => (None, false, true),
+            }
+        };
+
+        match key {
+            Some(key) if fresh => return key,
+            None if !refetch_allowed => {
+                return Err(JwtError::UnknownKey(kid.to_string()));
+            }
+            _ => {}
+        }
+
+        match self.refresh().await {
+            Ok(keys) => find_key(&keys, kid)
+                .unwrap_or_else(|| Err(JwtError::UnknownKey(kid.to_string()))),
+            // Keep serving the stale key set while the issuer is unreachable
+            Err(e) => key.unwrap_or(Err(e)),
+        }
+    }
+
+    async fn refresh(&self) -> Result<JwkSet, JwtError> {
+        let mut cached = self.jwks.write().await;
+
+        // Another request may have refreshed while we waited for the lock
+        if let Some(cached) = cached.as_ref()
+            && cached.fetched_at.elapsed() < MIN_REFETCH_INTERVAL
+        {
+            return Ok(cached.keys.clone());
+        }
+
+        let keys = self.fetch().await.inspect_err(|e| {
+            tracing::warn!(url = %self.settings.jwks_url, "{e}");
+        })?;
+        tracing::debug!(keys = keys.keys.len(), "Refreshed JWKS");
+
+        *cached = Some(CachedJwks {
+            keys: keys.clone(),
+            fetched_at: Instant::now(),
+        });
+
+        Ok(keys)
+    }
+
+    async fn fetch(&self) -> Result<JwkSet, JwtError> {
+        self.client
+            .get(&self.settings.jwks_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| JwtError::JwksUnavailable(e.to_string()))?
+            .json::<JwkSet>()
+            .await
+            .map_err(|e| JwtError::JwksUnavailable(e.to_string()))
+    }
+}
+
+fn find_key(keys: &JwkSet, kid: &str) -> Option<Result<DecodingKey, JwtError>> {
+    keys.find(kid)
+        .map(|jwk| DecodingKey::from_jwk(jwk).map_err(JwtError::Rejected))
+}
+
+impl<S> FromRequestParts<S> for Claims
+where
+    S: Send + Sync,
+{
+    type Rejection = WireV1Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Claims>() {
+            Some(claims) => Ok(claims.clone()),
+            None => {
+                let Ok(RequestId(request_id)) =
+                    RequestId::from_request_parts(parts, state).await;
+                Err(errors::Error::MissingCredentials
+                    .into_wire_v1_error(&request_id))
+            }
+        }
+    }
+}
+
+impl<S> OptionalFromRequestParts<S> for Claims
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Claims>().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    const SECRET: &[u8] = b"test-signing-secret";
+
+    fn verifier() -> JwtVerifier {
+        let keys: JwkSet = serde_json::from_value(serde_json::json!({
+            "keys": [{
+                "kty": "oct",
+                "kid": "test-key",
+                "alg": "HS256",
+                "k": "dGVzdC1zaWduaW5nLXNlY3JldA",
+            }]
+        }))
+        .unwrap();
+
+        let verifier = JwtVerifier::new(JwtSettings {
+            issuer: "https://issuer.example.com/".to_string(),
+            audience: "wire-api".to_string(),
+            jwks_url: "http://127.0.0.1:9/jwks.json".to_string(),
+            jwks_refresh_interval: Duration::from_secs(3600),
+            request_timeout: Duration::from_secs(1),
+        })
+        .unwrap();
+        verifier.jwks.try_write().unwrap().replace(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+        });
+        verifier
+    }
+
+    fn token(claims: serde_json::Value, kid: Option<&str>) -> String {
+        let mut header = Header::new(jsonwebtoken::Algorithm::HS256);
+        header.kid = kid.map(str::to_string);
+        jsonwebtoken::encode(
+            &header,
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    fn claims(aud: &str, exp_offset: i64) -> serde_json::Value {
+        serde_json::json!({
+            "sub": "user-1",
+            "iss": "https://issuer.example.com/",
+            "aud": aud,
+            "exp": chrono::Utc::now().timestamp() + exp_offset,
+            "scope": "openid wire:ingest",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_verify_valid_token() {
+        let claims = verifier()
+            .verify(&token(claims("wire-api", 300), Some("test-key")))
+            .await
+            .unwrap();
+
+        assert_eq!(claims.sub, "user-1");
+        assert!(claims.has_scope("wire:ingest"));
+        assert!(!claims.has_scope("wire"));
+        assert_eq!(claims.role(), Some(Role::Ingest));
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_wrong_audience_and_expired() {
+        let verifier = verifier();
+
+        let wrong_aud = token(claims("other-api", 300), Some("test-key"));
+        assert!(matches!(
+            verifier.verify(&wrong_aud).await,
+            Err(JwtError::Rejected(_))
+        ));
+
+        let expired = token(claims("wire-api", -300), Some("test-key"));
+        assert!(matches!(
+            verifier.verify(&expired).await,
+            Err(JwtError::Rejected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_verify_requires_known_key_id() {
+        let verifier = verifier();
+
+        let no_kid = token(claims("wire-api", 300), None);
+        assert!(matches!(
+            verifier.verify(&no_kid).await,
+            Err(JwtError::MissingKeyId)
+        ));
+
+        // Cache is fresh, so an unknown kid does not trigger a refetch
+        let unknown = token(claims("wire-api", 300), Some("rotated"));
+        assert!(matches!(
+            verifier.verify(&unknown).await,
+            Err(JwtError::UnknownKey(_))
+        ));
+    }
+}
@@ -0,0 +1,558 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderMap, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use postgres_models::connection::{
+    WithConnectionError, is_pool_exhausted, with_connection,
+};
+use postgres_models::models::api_keys::ApiKey;
+use subtle::ConstantTimeEq;
+use tracing::Instrument;
+
+use super::csrf::RouteGroup;
+use super::quota::{self, Quota};
+use super::tenant::{self, TenantContext};
+use super::{Caller, Role, api_key, errors, signature};
+use crate::AppState;
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+const HANDLER_NAME: &str = "auth";
+/// Skip the `last_used_at` write if the key was used more recently than this.
+const LAST_USED_RESOLUTION: chrono::TimeDelta = chrono::TimeDelta::minutes(1);
+
+/// Extract the token from an `Authorization: Bearer <token>` header.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers.get(name)?.to_str().ok().map(str::to_string)
+}
+
+fn record_db_error(
+    recorder: &ErrorRecorder<'_>,
+    e: WithConnectionError<diesel::result::Error>,
+) -> WireV1Error {
+    match e {
+        WithConnectionError::Pool(e) if is_pool_exhausted(&e) => recorder
+            .record(
+                "pool_exhausted",
+                errors::Error::PoolExhausted(e.to_string()),
+            ),
+        WithConnectionError::Pool(e) => {
+            recorder.record("pool_error", errors::Error::Pool(e.to_string()))
+        }
+        WithConnectionError::Operation(e) => {
+            recorder.record("database_error", errors::Error::Database(e))
+        }
+    }
+}
+
+fn auth_enabled(state: &AppState) -> bool {
+    state.config.require_api_key || state.jwt.is_some()
+}
+
+/// Authenticate wire v1 requests.
+///
+/// Authentication is enforced when `REQUIRE_API_KEY` is set or a JWT issuer
+/// is configured; otherwise every request is treated as an admin. The caller's
+/// [`Role`] is stored in the request extensions for [`super::RequirePermission`]
+/// and the rest of the request runs inside an `authenticated_request` span
+/// carrying the caller identity.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    RequestId(request_id): RequestId,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, WireV1Error> {
+    if !auth_enabled(&state) {
+        req.extensions_mut().insert(Role::Admin);
+        req.extensions_mut().insert(TenantContext::default());
+        return Ok(next.run(req).await);
+    }
+
+    let recorder =
+        ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+
+    let token =
+        bearer_token(req.headers())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                recorder.record(
+                    "missing_credentials",
+                    errors::Error::MissingCredentials,
+                )
+            })?;
+
+    let span = authenticate_token(&state, &recorder, &token, &mut req).await?;
+
+    Ok(next.run(req).instrument(span).await)
+}
+
+/// Guard admin routes.
+///
+/// The static `ADMIN_API_TOKEN` grants the admin role; otherwise the request
+/// is authenticated like any wire v1 request and the routes check the role.
+/// Admin routes are unreachable when neither the token nor authentication is
+/// configured.
+pub async fn authenticate_admin(
+    State(state): State<AppState>,
+    RequestId(request_id): RequestId,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, WireV1Error> {
+    let recorder =
+        ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+
+    let admin_token = state
+        .config
+        .admin_api_token
+        .as_deref()
+        .filter(|t| !t.is_empty());
+    if admin_token.is_none() && !auth_enabled(&state) {
+        return Err(
+            recorder.record("admin_disabled", errors::Error::AdminDisabled)
+        );
+    }
+
+    let token =
+        bearer_token(req.headers())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                recorder.record(
+                    "missing_credentials",
+                    errors::Error::MissingCredentials,
+                )
+            })?;
+
+    if let Some(expected) = admin_token
+        && bool::from(token.as_bytes().ct_eq(expected.as_bytes()))
+    {
+        req.extensions_mut().insert(Role::Admin);
+        req.extensions_mut().insert(TenantContext::default());
+        return Ok(next.run(req).await);
+    }
+
+    if !auth_enabled(&state) {
+        return Err(recorder
+            .record("invalid_credentials", errors::Error::InvalidCredentials));
+    }
+
+    let span = authenticate_token(&state, &recorder, &token, &mut req).await?;
+
+    Ok(next.run(req).instrument(span).await)
+}
+
+/// Reject state-changing requests that a browser sent from another site, for
+/// route groups listed in `CSRF_PROTECTED_ROUTES`. See [`super::csrf`].
+pub async fn protect_csrf(
+    State((state, group)): State<(AppState, RouteGroup)>,
+    RequestId(request_id): RequestId,
+    req: Request,
+    next: Next,
+) -> Result<Response, WireV1Error> {
+    if !state.config.csrf.protects(group) {
+        return Ok(next.run(req).await);
+    }
+
+    if let Err(rejection) = state.config.csrf.check(req.method(), req.headers())
+    {
+        tracing::warn!(
+            group = group.as_str(),
+            method = %req.method(),
+            path = %req.uri().path(),
+            origin = rejection.origin.as_deref(),
+            sec_fetch_site = rejection.fetch_site.as_deref(),
+            "Rejected cross-origin request"
+        );
+        let recorder =
+            ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+        return Err(recorder.record(
+            "cross_origin_request",
+            errors::Error::CrossOriginRequest(rejection.origin),
+        ));
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Apply the admin IP allow/deny lists.
+///
+/// Every rule match is logged and counted in the `ip_filter_decisions`
+/// metric. Requests are rejected with `403 ip_not_allowed` when the client
+/// matches a deny rule, or when an allow list is configured and the client is
+/// not on it. Runs before authentication, so rejected clients never reach the
+/// key lookup.
+pub async fn filter_admin_ip(
+    State(state): State<AppState>,
+    RequestId(request_id): RequestId,
+    req: Request,
+    next: Next,
+) -> Result<Response, WireV1Error> {
+    let Some(filter) = state.config.admin_ip_filter.as_ref() else {
+        return Ok(next.run(req).await);
+    };
+
+    let recorder =
+        ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+
+    // Without the peer address the lists cannot be applied; fail closed
+    let Some(ConnectInfo(peer)) =
+        req.extensions().get::<ConnectInfo<SocketAddr>>().copied()
+    else {
+        tracing::error!("Admin IP filter has no peer address, rejecting");
+        return Err(recorder.record(
+            "ip_not_allowed",
+            errors::Error::IpNotAllowed(std::net::Ipv4Addr::UNSPECIFIED.into()),
+        ));
+    };
+
+    let client_ip = filter.client_ip(peer.ip(), req.headers());
+    let decision = filter.check(client_ip);
+    let rule = decision.rule().map(ToString::to_string);
+    state.telemetry.maybe_use_metrics(|m| {
+        m.record_ip_filter_decision(
+            decision.as_str(),
+            rule.as_deref().unwrap_or(""),
+        );
+    });
+
+    if !decision.is_allowed() {
+        tracing::warn!(
+            %client_ip,
+            peer = %peer.ip(),
+            decision = decision.as_str(),
+            rule = rule.as_deref(),
+            "Admin request rejected by IP filter"
+        );
+        return Err(recorder
+            .record("ip_not_allowed", errors::Error::IpNotAllowed(client_ip)));
+    }
+
+    if let Some(rule) = &rule {
+        tracing::debug!(%client_ip, %rule, "Admin request allowed by IP filter");
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Count requests made with an API key against its daily and monthly quotas,
+/// rejecting them with `429` once a quota is exhausted.
+///
+/// Must run after [`authenticate`]; requests without a [`Caller`] (JWTs,
+/// authentication disabled) are not metered. Quotas are not enforced while
+/// Redis is unavailable.
+pub async fn enforce_quota(
+    State(state): State<AppState>,
+    RequestId(request_id): RequestId,
+    req: Request,
+    next: Next,
+) -> Result<Response, WireV1Error> {
+    let Some(caller) = req.extensions().get::<Caller>() else {
+        return Ok(next.run(req).await);
+    };
+
+    let consumption = match state.cache_pool.get().await {
+        Ok(mut conn) => {
+            quota::consume(
+                &mut conn,
+                caller.api_key_id,
+                &caller.quota,
+                chrono::Utc::now(),
+            )
+            .await
+        }
+        Err(e) => {
+            tracing::warn!("Quota not enforced, Redis unavailable: {e}");
+            return Ok(next.run(req).await);
+        }
+    };
+
+    match consumption {
+        Ok(consumption) => {
+            if let Some(exhausted) = consumption.exhausted() {
+                let recorder = ErrorRecorder::new(
+                    &state.telemetry,
+                    HANDLER_NAME,
+                    &request_id,
+                );
+                let retry_after =
+                    (exhausted.resets_at - chrono::Utc::now()).num_seconds();
+                let error = recorder.record(
+                    "quota_exceeded",
+                    errors::Error::QuotaExceeded {
+                        window: exhausted.window,
+                        limit: exhausted.limit.unwrap_or_default(),
+                        resets_at: exhausted.resets_at,
+                    },
+                );
+
+                let mut response = error.into_response();
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, retry_after.max(1).into());
+                return Ok(response);
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Quota not enforced, Redis error: {e}");
+        }
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Verify the HMAC signature of requests to high-trust ingestion routes.
+///
+/// The caller must authenticate with an API key that has a signing secret and
+/// send `X-Wire-Timestamp` and `X-Wire-Signature` headers (see
+/// [`signature`]). Timestamps further than `SIGNATURE_MAX_AGE_SECS` from the
+/// server time are rejected, and each signature is accepted once within that
+/// window; replays are not detected while Redis is unavailable.
+///
+/// Must run after [`authenticate`].
+pub async fn verify_signature(
+    State(state): State<AppState>,
+    RequestId(request_id): RequestId,
+    req: Request,
+    next: Next,
+) -> Result<Response, WireV1Error> {
+    let recorder =
+        ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+
+    let Some(key_id) = req.extensions().get::<Caller>().map(|c| c.api_key_id)
+    else {
+        return Err(recorder
+            .record("signing_not_enabled", errors::Error::SigningNotEnabled));
+    };
+
+    let timestamp = header_value(req.headers(), signature::TIMESTAMP_HEADER)
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| {
+            recorder.record(
+                "missing_signature",
+                errors::Error::MissingSignature(signature::TIMESTAMP_HEADER),
+            )
+        })?;
+    let provided = header_value(req.headers(), signature::SIGNATURE_HEADER)
+        .ok_or_else(|| {
+            recorder.record(
+                "missing_signature",
+                errors::Error::MissingSignature(signature::SIGNATURE_HEADER),
+            )
+        })?;
+
+    let max_age = state.config.signature_max_age;
+    if !signature::is_fresh(timestamp, chrono::Utc::now().timestamp(), max_age)
+    {
+        return Err(recorder.record(
+            "signature_expired",
+            errors::Error::SignatureExpired {
+                max_age_secs: max_age.as_secs(),
+            },
+        ));
+    }
+
+    let secret = state
+        .reads
+        .with_connection(|mut conn| async move {
+            ApiKey::find_active(key_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| record_db_error(&recorder, e))?
+        .and_then(|key| key.signing_secret)
+        .ok_or_else(|| {
+            recorder
+                .record("signing_not_enabled", errors::Error::SigningNotEnabled)
+        })?;
+
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, signature::MAX_SIGNED_BODY_BYTES)
+        .await
+        .map_err(|e| {
+            recorder.record(
+                "unreadable_body",
+                errors::Error::UnreadableBody(e.to_string()),
+            )
+        })?;
+
+    if !signature::verify(&secret, timestamp, &body, &provided) {
+        return Err(recorder
+            .record("invalid_signature", errors::Error::InvalidSignature));
+    }
+
+    match state.cache_pool.get().await {
+        Ok(mut conn) => {
+            let first_use =
+                signature::remember(&mut conn, key_id, &provided, max_age * 2)
+                    .await;
+            match first_use {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err(recorder.record(
+                        "replayed_request",
+                        errors::Error::ReplayedSignature,
+                    ));
+                }
+                Err(e) => {
+                    tracing::warn!("Replay check skipped, Redis error: {e}");
+                }
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Replay check skipped, Redis unavailable: {e}");
+        }
+    }
+
+    let req = Request::from_parts(parts, axum::body::Body::from(body));
+    Ok(next.run(req).await)
+}
+
+/// Resolve a bearer token to a caller and attach its identity and role to the
+/// request. API keys (`wk_...`) are looked up in `api_keys` and attach a
+/// [`Caller`]; any other token is verified as a JWT and attaches its
+/// [`super::Claims`].
+async fn authenticate_token(
+    state: &AppState,
+    recorder: &ErrorRecorder<'_>,
+    token: &str,
+    req: &mut Request,
+) -> Result<tracing::Span, WireV1Error> {
+    if let Some(verifier) = state.jwt.as_deref()
+        && !api_key::is_api_key(token)
+    {
+        let claims = verifier.verify(token).await.map_err(|e| {
+            let e = errors::Error::from(e);
+            let code = match e {
+                errors::Error::IdentityProviderUnavailable(_) => {
+                    "identity_provider_unavailable"
+                }
+                _ => "invalid_token",
+            };
+            recorder.record(code, e)
+        })?;
+
+        let tenant = match claims.tenant.as_deref() {
+            Some(tenant) if !tenant::is_valid_tenant_id(tenant) => {
+                return Err(recorder.record(
+                    "invalid_token",
+                    errors::Error::InvalidToken(format!(
+                        "Invalid tenant claim `{tenant}`"
+                    )),
+                ));
+            }
+            Some(tenant) => TenantContext::new(tenant),
+            None => TenantContext::default(),
+        };
+
+        let role = claims.role();
+        let span = tracing::info_span!(
+            "authenticated_request",
+            caller.sub = %claims.sub,
+            caller.role = role.map(|r| r.as_str()),
+            caller.tenant = %tenant.tenant_id,
+        );
+        if let Some(role) = role {
+            req.extensions_mut().insert(role);
+        }
+        req.extensions_mut().insert(tenant);
+        req.extensions_mut().insert(claims);
+
+        return Ok(span);
+    }
+
+    let hash = api_key::hash(token);
+
+    let key = state
+        .reads
+        .with_connection(|mut conn| async move {
+            ApiKey::find_active_by_hash(&hash, &mut conn).await
+        })
+        .await
+        .map_err(|e| record_db_error(recorder, e))?
+        .ok_or_else(|| {
+            recorder.record(
+                "invalid_credentials",
+                errors::Error::InvalidCredentials,
+            )
+        })?;
+
+    let stale = key
+        .last_used_at
+        .is_none_or(|t| chrono::Utc::now() - t > LAST_USED_RESOLUTION);
+    if stale {
+        let pool = state.pool.clone();
+        let key_id = key.id;
+        tokio::spawn(async move {
+            let result = with_connection(&pool, |mut conn| async move {
+                ApiKey::touch_last_used(key_id, &mut conn).await
+            })
+            .await;
+            if let Err(e) = result {
+                tracing::warn!(%key_id, "Failed to update key last_used_at: {e}");
+            }
+        });
+    }
+
+    let role = key.role.parse().unwrap_or_else(|e| {
+        tracing::warn!(api_key_id = %key.id, "{e}, treating key as read-only");
+        Role::Read
+    });
+    let caller = Caller {
+        api_key_id: key.id,
+        name: key.name,
+        role,
+        quota: Quota {
+            daily: key.daily_quota,
+            monthly: key.monthly_quota,
+        },
+    };
+    let span = tracing::info_span!(
+        "authenticated_request",
+        caller.api_key_id = %caller.api_key_id,
+        caller.name = %caller.name,
+        caller.role = caller.role.as_str(),
+        caller.tenant = %key.tenant_id,
+    );
+    req.extensions_mut().insert(role);
+    req.extensions_mut()
+        .insert(TenantContext::new(key.tenant_id));
+    req.extensions_mut().insert(caller);
+
+    Ok(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(value).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_bearer_token_parsing() {
+        assert_eq!(bearer_token(&headers("Bearer wk_abc")), Some("wk_abc"));
+        assert_eq!(bearer_token(&headers("bearer  wk_abc ")), Some("wk_abc"));
+        assert_eq!(bearer_token(&headers("Basic dXNlcjpwYXNz")), None);
+        assert_eq!(bearer_token(&headers("Bearer ")), None);
+        assert_eq!(bearer_token(&headers("wk_abc")), None);
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! Caller authentication for the wire API.
+//!
+//! Wire v1 routes accept API keys (`Authorization: Bearer wk_...`) validated
+//! against the hashed keys in `api_keys`, and JWTs validated against the
+//! configured issuer's JWKS; admin routes are guarded by a static token from
+//! the config.
+//!
+//! Every authenticated request carries a [`Role`] (read, ingest or admin)
+//! which routes check with the [`RequirePermission`] extractor, and a
+//! [`TenantContext`] scoping the data it can reach.
+pub mod api_key;
+pub mod csrf;
+pub mod errors;
+pub mod ip_filter;
+pub mod jwt;
+pub mod middleware;
+pub mod quota;
+pub mod roles;
+pub mod signature;
+pub mod tenant;
+
+pub use jwt::Claims;
+pub use roles::{RequirePermission, Role, permission};
+pub use tenant::TenantContext;
+
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum::http::request::Parts;
+use uuid::Uuid;
+
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::IntoWireV1Error;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+/// Identity of an authenticated caller, attached to the request by
+/// [`middleware::authenticate`].
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub api_key_id: Uuid,
+    pub name: String,
+    pub role: Role,
+    pub quota: quota::Quota,
+}
+
+impl<S> FromRequestParts<S> for Caller
+where
+    S: Send + Sync,
+{
+    type Rejection = WireV1Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Caller>() {
+            Some(caller) => Ok(caller.clone()),
+            None => {
+                let Ok(RequestId(request_id)) =
+                    RequestId::from_request_parts(parts, state).await;
+                Err(errors::Error::MissingCredentials
+                    .into_wire_v1_error(&request_id))
+            }
+        }
+    }
+}
+
+impl<S> OptionalFromRequestParts<S> for Caller
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Caller>().cloned())
+    }
+}
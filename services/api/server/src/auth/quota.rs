@@ -0,0 +1,270 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use deadpool_redis::redis::{self, RedisError};
+use redis_cache::connection::PooledConnection;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Counters outlive their window by this much so late reads still see them.
+const EXPIRY_SLACK_SECS: i64 = 3600;
+
+/// Checks every window before counting the request, so a rejected request
+/// does not consume quota. `ARGV[i]` is the limit of `KEYS[i]` (-1 for
+/// unlimited) and `ARGV[n + i]` its expiry in seconds.
+///
+/// Returns `{allowed, used_1, ..., used_n}`.
+const CONSUME_SCRIPT: &str = r"
+local n = #KEYS
+local used = {}
+for i = 1, n do
+    used[i] = tonumber(redis.call('GET', KEYS[i]) or '0')
+end
+for i = 1, n do
+    local limit = tonumber(ARGV[i])
+    if limit >= 0 and used[i] >= limit then
+        return {0, unpack(used)}
+    end
+end
+for i = 1, n do
+    used[i] = redis.call('INCR', KEYS[i])
+    if used[i] == 1 then
+        redis.call('EXPIRE', KEYS[i], ARGV[n + i])
+    end
+end
+return {1, unpack(used)}
+";
+
+/// Request quotas of an API key; `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quota {
+    pub daily: Option<i64>,
+    pub monthly: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Window {
+    Daily,
+    Monthly,
+}
+
+impl Window {
+    const ALL: [Window; 2] = [Window::Daily, Window::Monthly];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Window::Daily => "daily",
+            Window::Monthly => "monthly",
+        }
+    }
+
+    /// Start of the window containing `now` and the start of the next one.
+    fn bounds(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let today = now.date_naive();
+        let (start, end) = match self {
+            Window::Daily => (today, today + Duration::days(1)),
+            Window::Monthly => {
+                let start = today.with_day(1).unwrap_or(today);
+                let end = if start.month() == 12 {
+                    NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+                }
+                .unwrap_or(start);
+                (start, end)
+            }
+        };
+
+        let midnight = |d: NaiveDate| {
+            Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0).unwrap_or_default())
+        };
+        (midnight(start), midnight(end))
+    }
+
+    fn counter_key(&self, key_id: Uuid, now: DateTime<Utc>) -> String {
+        let (start, _) = self.bounds(now);
+        match self {
+            Window::Daily => {
+                format!("quota:{key_id}:day:{}", start.format("%Y%m%d"))
+            }
+            Window::Monthly => {
+                format!("quota:{key_id}:month:{}", start.format("%Y%m"))
+            }
+        }
+    }
+
+    fn limit(&self, quota: &Quota) -> Option<i64> {
+        match self {
+            Window::Daily => quota.daily,
+            Window::Monthly => quota.monthly,
+        }
+    }
+}
+
+/// Consumption of one quota window.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowUsage {
+    pub window: Window,
+    pub used: i64,
+    /// `None` when the window is unlimited
+    pub limit: Option<i64>,
+    pub remaining: Option<i64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl WindowUsage {
+    fn new(
+        window: Window,
+        used: i64,
+        quota: &Quota,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let limit = window.limit(quota);
+        Self {
+            window,
+            used,
+            limit,
+            remaining: limit.map(|l| (l - used).max(0)),
+            resets_at: window.bounds(now).1,
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+}
+
+/// Result of [`consume`]: whether the request is allowed and the usage of
+/// every window after counting it.
+#[derive(Debug, Clone)]
+pub struct Consumption {
+    pub allowed: bool,
+    pub windows: Vec<WindowUsage>,
+}
+
+impl Consumption {
+    /// The window that rejected the request, if any.
+    pub fn exhausted(&self) -> Option<&WindowUsage> {
+        if self.allowed {
+            return None;
+        }
+        self.windows.iter().find(|w| w.is_exhausted())
+    }
+}
+
+/// Count one request against the key's quotas, unless a window is already
+/// exhausted. Keys without any quota are counted too, so `/usage` reports
+/// their consumption.
+pub async fn consume(
+    conn: &mut PooledConnection,
+    key_id: Uuid,
+    quota: &Quota,
+    now: DateTime<Utc>,
+) -> Result<Consumption, RedisError> {
+    let script = redis::Script::new(CONSUME_SCRIPT);
+    let mut invocation = script.prepare_invoke();
+    for window in Window::ALL {
+        invocation.key(window.counter_key(key_id, now));
+    }
+    for window in Window::ALL {
+        invocation.arg(window.limit(quota).unwrap_or(-1));
+    }
+    for window in Window::ALL {
+        let (_, end) = window.bounds(now);
+        invocation.arg((end - now).num_seconds() + EXPIRY_SLACK_SECS);
+    }
+
+    let result: Vec<i64> = invocation.invoke_async(conn).await?;
+    let (allowed, used) = result.split_first().unwrap_or((&0, &[]));
+
+    Ok(Consumption {
+        allowed: *allowed == 1,
+        windows: Window::ALL
+            .iter()
+            .zip(used.iter().chain(std::iter::repeat(&0)))
+            .map(|(window, used)| WindowUsage::new(*window, *used, quota, now))
+            .collect(),
+    })
+}
+
+/// Current usage of every window, without counting a request.
+pub async fn usage(
+    conn: &mut PooledConnection,
+    key_id: Uuid,
+    quota: &Quota,
+    now: DateTime<Utc>,
+) -> Result<Vec<WindowUsage>, RedisError> {
+    let keys: Vec<String> = Window::ALL
+        .iter()
+        .map(|w| w.counter_key(key_id, now))
+        .collect();
+    let used: Vec<Option<i64>> =
+        redis::cmd("MGET").arg(&keys).query_async(conn).await?;
+
+    Ok(Window::ALL
+        .iter()
+        .zip(used.into_iter().chain(std::iter::repeat(None)))
+        .map(|(window, used)| {
+            WindowUsage::new(*window, used.unwrap_or(0), quota, now)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_window_bounds() {
+        let now = at("2025-12-31T18:30:00Z");
+
+        assert_eq!(
+            Window::Daily.bounds(now),
+            (at("2025-12-31T00:00:00Z"), at("2026-01-01T00:00:00Z"))
+        );
+        assert_eq!(
+            Window::Monthly.bounds(now),
+            (at("2025-12-01T00:00:00Z"), at("2026-01-01T00:00:00Z"))
+        );
+        assert_eq!(
+            Window::Monthly.bounds(at("2025-02-10T00:00:00Z")).1,
+            at("2025-03-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_counter_keys_roll_over_with_the_window() {
+        let id = Uuid::nil();
+
+        assert_eq!(
+            Window::Daily.counter_key(id, at("2025-03-09T23:59:59Z")),
+            format!("quota:{id}:day:20250309")
+        );
+        assert_eq!(
+            Window::Monthly.counter_key(id, at("2025-03-09T23:59:59Z")),
+            format!("quota:{id}:month:202503")
+        );
+    }
+
+    #[test]
+    fn test_window_usage_remaining() {
+        let quota = Quota {
+            daily: Some(10),
+            monthly: None,
+        };
+        let now = at("2025-03-09T12:00:00Z");
+
+        let daily = WindowUsage::new(Window::Daily, 10, &quota, now);
+        assert_eq!(daily.remaining, Some(0));
+        assert!(daily.is_exhausted());
+
+        let monthly = WindowUsage::new(Window::Monthly, 250, &quota, now);
+        assert_eq!(monthly.remaining, None);
+        assert!(!monthly.is_exhausted());
+    }
+}
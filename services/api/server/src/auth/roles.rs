@@ -0,0 +1,170 @@
+use std::marker::PhantomData;
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::errors;
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::IntoWireV1Error;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+/// Prefix of the JWT scopes mapped to roles, e.g. `wire:ingest`.
+const SCOPE_PREFIX: &str = "wire:";
+
+/// Access level of a caller. Roles are ordered: each one grants everything
+/// the previous ones do.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Deserialize,
+    Serialize,
+    ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Query energy data and history
+    Read,
+    /// Load energy readings
+    Ingest,
+    /// Manage API keys, webhooks, plants and operational settings
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Read => "read",
+            Role::Ingest => "ingest",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn grants(&self, required: Role) -> bool {
+        *self >= required
+    }
+
+    /// Highest role granted by a set of `wire:<role>` OAuth scopes.
+    pub fn from_scopes<'a>(
+        scopes: impl IntoIterator<Item = &'a str>,
+    ) -> Option<Role> {
+        scopes
+            .into_iter()
+            .filter_map(|scope| scope.strip_prefix(SCOPE_PREFIX))
+            .filter_map(|role| role.parse().ok())
+            .max()
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Role::Read),
+            "ingest" => Ok(Role::Ingest),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("Unknown role: {other}")),
+        }
+    }
+}
+
+/// Type-level role used by [`RequirePermission`].
+pub trait Permission: Send + Sync + 'static {
+    const ROLE: Role;
+}
+
+pub mod permission {
+    use super::{Permission, Role};
+
+    pub struct Read;
+    pub struct Ingest;
+    pub struct Admin;
+
+    impl Permission for Read {
+        const ROLE: Role = Role::Read;
+    }
+
+    impl Permission for Ingest {
+        const ROLE: Role = Role::Ingest;
+    }
+
+    impl Permission for Admin {
+        const ROLE: Role = Role::Admin;
+    }
+}
+
+/// Rejects the request unless the authenticated caller's [`Role`] grants
+/// `P`. Use as a handler argument or as a route layer:
+///
+/// ```ignore
+/// router.route_layer(axum::middleware::from_extractor::<
+///     RequirePermission<permission::Admin>,
+/// >())
+/// ```
+///
+/// The role is attached to the request by the authentication middleware.
+pub struct RequirePermission<P>(PhantomData<P>);
+
+impl<S, P> FromRequestParts<S> for RequirePermission<P>
+where
+    S: Send + Sync,
+    P: Permission,
+{
+    type Rejection = WireV1Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let granted = parts
+            .extensions
+            .get::<Role>()
+            .is_some_and(|role| role.grants(P::ROLE));
+        if granted {
+            return Ok(Self(PhantomData));
+        }
+
+        let Ok(RequestId(request_id)) =
+            RequestId::from_request_parts(parts, state).await;
+        Err(errors::Error::InsufficientRole(P::ROLE)
+            .into_wire_v1_error(&request_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_are_ordered() {
+        assert!(Role::Admin.grants(Role::Read));
+        assert!(Role::Ingest.grants(Role::Read));
+        assert!(Role::Ingest.grants(Role::Ingest));
+        assert!(!Role::Read.grants(Role::Ingest));
+        assert!(!Role::Ingest.grants(Role::Admin));
+    }
+
+    #[test]
+    fn test_role_from_scopes() {
+        assert_eq!(
+            Role::from_scopes(["openid", "wire:read", "wire:ingest"]),
+            Some(Role::Ingest)
+        );
+        assert_eq!(Role::from_scopes(["wire:admin"]), Some(Role::Admin));
+        assert_eq!(Role::from_scopes(["openid", "wire:unknown"]), None);
+        assert_eq!(Role::from_scopes(["admin"]), None);
+    }
+}
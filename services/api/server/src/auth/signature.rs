@@ -0,0 +1,84 @@
+//! Signed requests for ingestion endpoints.
+//!
+//! Clients sign `"{timestamp}.{body}"` with their API key's signing secret,
+//! the same scheme the service uses for outgoing webhooks (see
+//! [`crate::webhooks::signing`]), and send it in the `X-Wire-Timestamp` and
+//! `X-Wire-Signature` headers.
+use std::time::Duration;
+
+use deadpool_redis::redis::{self, RedisError};
+use redis_cache::connection::PooledConnection;
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::webhooks::signing;
+
+pub use crate::webhooks::signing::{SIGNATURE_HEADER, TIMESTAMP_HEADER};
+
+/// Largest body buffered for signature verification.
+pub const MAX_SIGNED_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Whether a signature timestamp is within `max_age` of `now`, in either
+/// direction to tolerate clock skew.
+pub fn is_fresh(timestamp: i64, now: i64, max_age: Duration) -> bool {
+    let max_age = i64::try_from(max_age.as_secs()).unwrap_or(i64::MAX);
+    now.abs_diff(timestamp) <= max_age.unsigned_abs()
+}
+
+/// Check `signature` against the expected one in constant time.
+pub fn verify(
+    secret: &str,
+    timestamp: i64,
+    body: &[u8],
+    signature: &str,
+) -> bool {
+    let expected = signing::sign(secret, timestamp, body);
+    expected.as_bytes().ct_eq(signature.as_bytes()).into()
+}
+
+/// Record a verified signature for `ttl`, returning `false` if it was already
+/// seen.
+pub async fn remember(
+    conn: &mut PooledConnection,
+    key_id: Uuid,
+    signature: &str,
+    ttl: Duration,
+) -> Result<bool, RedisError> {
+    let stored: Option<String> = redis::cmd("SET")
+        .arg(format!("signature:{key_id}:{signature}"))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl.as_secs().max(1))
+        .query_async(conn)
+        .await?;
+
+    Ok(stored.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature() {
+        let signature = signing::sign("wss_secret", 1_700_000_000, b"{}");
+
+        assert!(verify("wss_secret", 1_700_000_000, b"{}", &signature));
+        assert!(!verify("wss_other", 1_700_000_000, b"{}", &signature));
+        assert!(!verify("wss_secret", 1_700_000_001, b"{}", &signature));
+        assert!(!verify("wss_secret", 1_700_000_000, b"{ }", &signature));
+        assert!(!verify("wss_secret", 1_700_000_000, b"{}", "sha256=00"));
+    }
+
+    #[test]
+    fn test_timestamp_window() {
+        let window = Duration::from_secs(300);
+
+        assert!(is_fresh(1_000, 1_000, window));
+        assert!(is_fresh(700, 1_000, window));
+        assert!(is_fresh(1_300, 1_000, window));
+        assert!(!is_fresh(699, 1_000, window));
+        assert!(!is_fresh(1_301, 1_000, window));
+    }
+}
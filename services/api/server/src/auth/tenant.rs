@@ -0,0 +1,106 @@
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+use super::errors;
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::IntoWireV1Error;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+pub use postgres_models::models::DEFAULT_TENANT;
+
+/// Longest accepted tenant id.
+pub const MAX_TENANT_LEN: usize = 64;
+
+/// Tenant the caller acts for, attached to the request by
+/// [`super::middleware::authenticate`]. Handlers pass it to every query and
+/// key cached data with [`CacheKey`], so one deployment can serve several
+/// customers without their data mixing.
+///
+/// API keys carry the tenant they were issued for; JWTs name it in the
+/// `tenant` claim. Tokens without one, and deployments without
+/// authentication, use [`DEFAULT_TENANT`].
+///
+/// [`CacheKey`]: crate::shared::cache_key::CacheKey
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantContext {
+    pub tenant_id: String,
+}
+
+impl TenantContext {
+    pub fn new(tenant_id: impl Into<String>) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+        }
+    }
+
+    /// Scope a cache key to the tenant.
+    pub fn cache_key(&self, key: &str) -> String {
+        format!("tenant:{}:{key}", self.tenant_id)
+    }
+}
+
+impl Default for TenantContext {
+    fn default() -> Self {
+        Self::new(DEFAULT_TENANT)
+    }
+}
+
+/// Whether `tenant_id` is a valid tenant id: 1 to [`MAX_TENANT_LEN`]
+/// lowercase ASCII letters, digits, `-` or `_`.
+pub fn is_valid_tenant_id(tenant_id: &str) -> bool {
+    (1..=MAX_TENANT_LEN).contains(&tenant_id.len())
+        && tenant_id.bytes().all(|b| {
+            b.is_ascii_lowercase()
+                || b.is_ascii_digit()
+                || b == b'-'
+                || b == b'_'
+        })
+}
+
+impl<S> FromRequestParts<S> for TenantContext
+where
+    S: Send + Sync,
+{
+    type Rejection = WireV1Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<TenantContext>() {
+            Some(tenant) => Ok(tenant.clone()),
+            None => {
+                let Ok(RequestId(request_id)) =
+                    RequestId::from_request_parts(parts, state).await;
+                Err(errors::Error::MissingCredentials
+                    .into_wire_v1_error(&request_id))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_keys_are_scoped() {
+        let acme = TenantContext::new("acme");
+
+        assert_eq!(acme.cache_key("energy:x"), "tenant:acme:energy:x");
+        assert_ne!(
+            acme.cache_key("energy:x"),
+            TenantContext::default().cache_key("energy:x")
+        );
+    }
+
+    #[test]
+    fn test_tenant_id_validation() {
+        assert!(is_valid_tenant_id("default"));
+        assert!(is_valid_tenant_id("acme-energy_2"));
+        assert!(!is_valid_tenant_id(""));
+        assert!(!is_valid_tenant_id("Acme"));
+        assert!(!is_valid_tenant_id("acme:prod"));
+        assert!(!is_valid_tenant_id(&"a".repeat(MAX_TENANT_LEN + 1)));
+    }
+}
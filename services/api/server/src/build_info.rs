@@ -0,0 +1,60 @@
+//! Build metadata served by `/version`, recorded by `build.rs`.
+use serde::Serialize;
+
+/// Release label given at build time, e.g. a deploy tag or `local`.
+const RELEASE: Option<&str> = option_env!("VERSION");
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    /// Semantic version of the crate
+    pub version: &'static str,
+    /// Release label from the `VERSION` build variable
+    pub release: Option<&'static str>,
+    pub git_sha: &'static str,
+    /// RFC 3339 time the binary was built
+    pub built_at: String,
+    pub rustc_version: &'static str,
+    /// `debug` or `release`
+    pub profile: &'static str,
+    pub target: &'static str,
+    pub features: Vec<&'static str>,
+}
+
+pub fn build_info() -> BuildInfo {
+    let built_at = env!("WIRE_BUILT_AT")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .unwrap_or_default();
+
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        release: RELEASE.filter(|release| !release.is_empty()),
+        git_sha: env!("WIRE_GIT_SHA"),
+        built_at,
+        rustc_version: env!("WIRE_RUSTC_VERSION"),
+        profile: env!("WIRE_PROFILE"),
+        target: env!("WIRE_TARGET"),
+        features: env!("WIRE_FEATURES")
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+
+        assert_eq!(info.version.split('.').count(), 3);
+        assert!(info.rustc_version.starts_with("rustc "));
+        assert!(chrono::DateTime::parse_from_rfc3339(&info.built_at).is_ok());
+        assert!(!info.git_sha.is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+//! Pre-computed aggregates after an import.
+//!
+//! An import changes the readings behind every cached aggregate, and the
+//! first dashboard load afterwards would compute its aggregations from
+//! scratch. The [`CacheWarmer`] computes the tenant's
+//! [`WarmerSettings::queries`] most frequent aggregations of the query
+//! history of the last [`WarmerSettings::lookback`] into the aggregate
+//! cache instead, as `POST /energy/aggregate` without weather would. It
+//! runs after the startup import and `wire-api import`, unless the
+//! `aggregate_cache` flag is off.
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use redis_cache::connection::Pool;
+
+use crate::auth::TenantContext;
+use crate::flags::{FeatureFlags, Flag};
+use crate::repository::{EnergyReadingRepository, QueryHistoryRepository};
+use crate::wire_api::core::v1::energy::aggregate::handler::warm_cache;
+use crate::wire_api::core::v1::energy::aggregate::models::{
+    AggregateRequest, DEFAULT_INTERVAL_MINUTES,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarmerSettings {
+    /// Most frequent aggregations computed; 0 disables warming
+    pub queries: i64,
+    /// How far back the query history is counted
+    pub lookback: Duration,
+}
+
+pub struct CacheWarmer {
+    readings: Arc<dyn EnergyReadingRepository>,
+    query_history: Arc<dyn QueryHistoryRepository>,
+    cache_pool: Pool,
+    flags: Arc<FeatureFlags>,
+    settings: WarmerSettings,
+}
+
+impl CacheWarmer {
+    pub fn new(
+        readings: Arc<dyn EnergyReadingRepository>,
+        query_history: Arc<dyn QueryHistoryRepository>,
+        cache_pool: Pool,
+        flags: Arc<FeatureFlags>,
+        settings: WarmerSettings,
+    ) -> Self {
+        Self {
+            readings,
+            query_history,
+            cache_pool,
+            flags,
+            settings,
+        }
+    }
+
+    /// Compute the tenant's most frequent aggregations into the cache,
+    /// returning how many were cached. Aggregations too large to cache are
+    /// skipped.
+    pub async fn warm(&self, tenant: &TenantContext) -> anyhow::Result<usize> {
+        if self.settings.queries == 0
+            || !self
+                .flags
+                .is_enabled(Flag::AggregateCache, &self.cache_pool)
+                .await
+        {
+            return Ok(0);
+        }
+
+        let since = Utc::now() - self.settings.lookback;
+        let queries = self
+            .query_history
+            .most_frequent(&tenant.tenant_id, since, self.settings.queries)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to count queries: {e}"))?;
+
+        let mut cached = 0;
+        for query in queries {
+            let Ok(aggregation_type) = query.aggregation_type.parse() else {
+                continue;
+            };
+            let request = AggregateRequest {
+                aggregation_type,
+                date_from: query.date_from,
+                date_to: query.date_to,
+                // The history doesn't keep week starts, so weeks are warmed
+                // from Monday
+                week_start: None,
+                interval_minutes: DEFAULT_INTERVAL_MINUTES,
+                metrics: Vec::new(),
+            };
+            let stored = warm_cache(
+                self.readings.as_ref(),
+                &self.cache_pool,
+                tenant,
+                &request,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to aggregate: {e}"))?;
+            cached += usize::from(stored);
+        }
+        Ok(cached)
+    }
+
+    /// [`CacheWarmer::warm`] in the background, logging the outcome.
+    pub fn spawn(self: Arc<Self>, tenant: TenantContext) {
+        tokio::spawn(async move {
+            match self.warm(&tenant).await {
+                Ok(cached) => tracing::info!(
+                    tenant = tenant.tenant_id,
+                    cached,
+                    "Warmed the aggregate cache"
+                ),
+                Err(e) => tracing::warn!(
+                    tenant = tenant.tenant_id,
+                    "Failed to warm the aggregate cache: {e:#}"
+                ),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use deadpool_redis::redis::AsyncCommands;
+    use serde_json::json;
+
+    use super::*;
+    use crate::shared::json::DecimalFormat;
+    use crate::shared::response_cache;
+    use crate::wire_api::core::v1::energy::aggregate::handler::cache_key;
+    use crate::wire_api::core::v1::energy::aggregate::models::{
+        AggregateParams, AggregateResponse, AggregationType,
+    };
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[tokio::test]
+    async fn test_warms_the_most_frequent_aggregations() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let at = |month| Utc.with_ymd_and_hms(2025, month, 1, 0, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(1), "1.5"), (at(2), "2")],
+        )
+        .await;
+        for aggregation_type in ["monthly", "monthly", "hourly"] {
+            app.server
+                .post("/api/wire/v1/energy/aggregate")
+                .json(&json!({
+                    "aggregationType": aggregation_type,
+                    "dateFrom": at(1),
+                }))
+                .await
+                .assert_status_ok();
+        }
+
+        let state = &app.state;
+        let warmer = |queries| {
+            CacheWarmer::new(
+                state.readings.clone(),
+                state.query_history.clone(),
+                state.cache_pool.clone(),
+                // The test apps don't cache aggregates
+                Arc::new(FeatureFlags::new(Default::default())),
+                WarmerSettings {
+                    queries,
+                    lookback: Duration::from_secs(3600),
+                },
+            )
+        };
+        let tenant = TenantContext::default();
+        let request = AggregateRequest {
+            aggregation_type: AggregationType::Monthly,
+            date_from: Some(at(1)),
+            date_to: None,
+            week_start: None,
+            interval_minutes: DEFAULT_INTERVAL_MINUTES,
+            metrics: Vec::new(),
+        };
+        let key = cache_key(
+            &tenant,
+            &request,
+            &AggregateParams::default(),
+            &DecimalFormat::default(),
+        );
+        // Left by earlier runs on the same Redis
+        let mut conn = state.cache_pool.get().await.unwrap();
+        let _: () = conn.del(&key).await.unwrap();
+
+        assert_eq!(warmer(0).warm(&tenant).await.unwrap(), 0);
+        assert_eq!(warmer(1).warm(&tenant).await.unwrap(), 1);
+        let cached = response_cache::get(&state.cache_pool, &key)
+            .await
+            .expect("the monthly aggregation to be cached");
+        let cached =
+            serde_json::from_str::<AggregateResponse>(&cached).unwrap();
+        let totals = cached
+            .data
+            .iter()
+            .map(|point| point.total_kwh.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(totals, ["1.5000", "2.0000"]);
+    }
+}
@@ -0,0 +1,410 @@
+//! Circuit breakers around the database and cache calls of endpoints.
+//!
+//! Each endpoint has a breaker per [`Dependency`]. A closed breaker lets
+//! every call through and remembers the outcomes of the last
+//! `CIRCUIT_BREAKER_WINDOW`; once that many calls failed at a rate of at
+//! least `CIRCUIT_BREAKER_FAILURE_RATE` percent, it opens. An open breaker
+//! turns calls away at once for `CIRCUIT_BREAKER_OPEN_SECS`, then half-opens
+//! and lets up to `CIRCUIT_BREAKER_HALF_OPEN_PROBES` calls through: a failed
+//! probe opens it again, as many successful ones close it. A failure rate of
+//! 0 never opens a breaker.
+//!
+//! Callers ask for a [`Permit`] before the call and hand it its result, so
+//! calls that never reach the dependency, like aggregations held back by
+//! admission control, count for nothing.
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::wire_api::error_recorder::{ErrorRecorder, IntoWireV1Error};
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakerSettings {
+    /// Percentage of failed calls in the window opening a breaker; 0 never
+    /// opens one
+    pub failure_rate: u8,
+    /// Calls whose outcomes a closed breaker remembers
+    pub window: u32,
+    /// How long an open breaker turns calls away
+    pub open_for: Duration,
+    /// Calls let through, and successes needed, while half-open
+    pub half_open_probes: u32,
+}
+
+/// What a breaker guards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dependency {
+    Postgres,
+    Redis,
+}
+
+impl Dependency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dependency::Postgres => "postgres",
+            Dependency::Redis => "redis",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    /// Letting probes through
+    HalfOpen,
+    Open,
+}
+
+impl BreakerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::HalfOpen => "half_open",
+            BreakerState::Open => "open",
+        }
+    }
+
+    /// Value of the state in the `circuit_breaker_state` gauge.
+    pub fn as_gauge(&self) -> i64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::HalfOpen => 1,
+            BreakerState::Open => 2,
+        }
+    }
+}
+
+/// Called with the endpoint, dependency and new state of a breaker whenever
+/// it changes state.
+pub type StateListener =
+    Arc<dyn Fn(&str, Dependency, BreakerState) + Send + Sync>;
+
+/// A call turned away by an open breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Calls to {} are failing, circuit open", dependency.as_str())]
+pub struct Open {
+    pub dependency: Dependency,
+    /// When the breaker lets calls through again
+    pub retry_after: Duration,
+}
+
+impl Open {
+    pub const CODE: &'static str = "circuit_open";
+
+    /// `503 Service Unavailable` with `Retry-After`.
+    pub(crate) fn into_response(self, recorder: &ErrorRecorder) -> Response {
+        let retry_after = self.retry_after.as_secs();
+        let mut response = recorder.record(Self::CODE, self).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, retry_after.into());
+        response
+    }
+}
+
+impl IntoWireV1Error for Open {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        WireV1Error::service_unavailable(
+            "Service temporarily unavailable".to_string(),
+            vec![WireV1Detail {
+                field: None,
+                code: Self::CODE.to_string(),
+                message: self.to_string(),
+                suggestion: format!(
+                    "Retry after {} seconds",
+                    self.retry_after.as_secs()
+                ),
+                documentation: String::new(),
+            }],
+            request_id.to_string(),
+        )
+    }
+}
+
+struct Inner {
+    state: BreakerState,
+    /// Outcomes of the last calls while closed, `true` for failures
+    outcomes: VecDeque<bool>,
+    /// When the breaker last opened
+    opened_at: Instant,
+    /// Probes let through while half-open
+    probes: u32,
+    /// Probes that succeeded while half-open
+    successes: u32,
+}
+
+/// The breaker of one dependency of one endpoint, see the module docs.
+pub struct CircuitBreaker {
+    endpoint: &'static str,
+    dependency: Dependency,
+    settings: BreakerSettings,
+    listener: Option<StateListener>,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    fn new(
+        endpoint: &'static str,
+        dependency: Dependency,
+        settings: BreakerSettings,
+        listener: Option<StateListener>,
+    ) -> Self {
+        Self {
+            endpoint,
+            dependency,
+            settings,
+            listener,
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                outcomes: VecDeque::new(),
+                opened_at: Instant::now(),
+                probes: 0,
+                successes: 0,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.lock().state
+    }
+
+    /// Let a call through, unless the breaker is open or half-open with all
+    /// its probes out.
+    pub fn acquire(self: &Arc<Self>) -> Result<Permit, Open> {
+        let mut inner = self.lock();
+        if inner.state == BreakerState::Open {
+            let elapsed = inner.opened_at.elapsed();
+            if elapsed < self.settings.open_for {
+                return Err(self.open(self.settings.open_for - elapsed));
+            }
+            inner.probes = 0;
+            inner.successes = 0;
+            self.transition(&mut inner, BreakerState::HalfOpen);
+        }
+        let probe = inner.state == BreakerState::HalfOpen;
+        if probe {
+            if inner.probes >= self.settings.half_open_probes {
+                return Err(self.open(Duration::ZERO));
+            }
+            inner.probes += 1;
+        }
+        Ok(Permit {
+            breaker: self.clone(),
+            probe,
+        })
+    }
+
+    fn open(&self, retry_after: Duration) -> Open {
+        Open {
+            dependency: self.dependency,
+            retry_after: retry_after.max(Duration::from_secs(1)),
+        }
+    }
+
+    fn record(&self, probe: bool, failed: bool) {
+        let mut inner = self.lock();
+        match inner.state {
+            BreakerState::HalfOpen if probe => {
+                if failed {
+                    inner.opened_at = Instant::now();
+                    self.transition(&mut inner, BreakerState::Open);
+                    return;
+                }
+                inner.successes += 1;
+                if inner.successes >= self.settings.half_open_probes {
+                    inner.outcomes.clear();
+                    self.transition(&mut inner, BreakerState::Closed);
+                }
+            }
+            BreakerState::Closed => {
+                let window = self.settings.window as usize;
+                inner.outcomes.push_back(failed);
+                while inner.outcomes.len() > window {
+                    inner.outcomes.pop_front();
+                }
+                let failures =
+                    inner.outcomes.iter().filter(|&&failed| failed).count();
+                if self.settings.failure_rate > 0
+                    && inner.outcomes.len() >= window
+                    && failures * 100
+                        >= window * self.settings.failure_rate as usize
+                {
+                    tracing::warn!(
+                        endpoint = self.endpoint,
+                        dependency = self.dependency.as_str(),
+                        failures,
+                        "Circuit breaker opened"
+                    );
+                    inner.opened_at = Instant::now();
+                    self.transition(&mut inner, BreakerState::Open);
+                }
+            }
+            // Outcomes of calls let through before the breaker opened, or
+            // while it was in a previous half-open period
+            _ => {}
+        }
+    }
+
+    /// Give back the place of a probe that never reached the dependency.
+    fn release(&self) {
+        let mut inner = self.lock();
+        if inner.state == BreakerState::HalfOpen {
+            inner.probes = inner.probes.saturating_sub(1);
+        }
+    }
+
+    fn transition(&self, inner: &mut Inner, state: BreakerState) {
+        inner.state = state;
+        if let Some(listener) = &self.listener {
+            listener(self.endpoint, self.dependency, state);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("circuit breaker lock poisoned")
+    }
+}
+
+/// A call let through by a breaker, to hand the call's result to.
+pub struct Permit {
+    breaker: Arc<CircuitBreaker>,
+    probe: bool,
+}
+
+impl Permit {
+    /// Record the outcome of the call.
+    pub fn record<T, E>(mut self, result: &Result<T, E>) {
+        self.breaker.record(self.probe, result.is_err());
+        // Recorded, so its place is not given back
+        self.probe = false;
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.release();
+        }
+    }
+}
+
+/// The breakers of every endpoint, created on first use.
+pub struct CircuitBreakers {
+    settings: BreakerSettings,
+    listener: Option<StateListener>,
+    breakers: Mutex<HashMap<(&'static str, Dependency), Arc<CircuitBreaker>>>,
+}
+
+impl CircuitBreakers {
+    pub fn new(
+        settings: BreakerSettings,
+        listener: Option<StateListener>,
+    ) -> Self {
+        Self {
+            settings,
+            listener,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The breaker of `dependency` for `endpoint`.
+    pub fn get(
+        &self,
+        endpoint: &'static str,
+        dependency: Dependency,
+    ) -> Arc<CircuitBreaker> {
+        let mut breakers = self
+            .breakers
+            .lock()
+            .expect("circuit breakers lock poisoned");
+        breakers
+            .entry((endpoint, dependency))
+            .or_insert_with(|| {
+                Arc::new(CircuitBreaker::new(
+                    endpoint,
+                    dependency,
+                    self.settings.clone(),
+                    self.listener.clone(),
+                ))
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(open_for: Duration) -> Arc<CircuitBreaker> {
+        CircuitBreakers::new(
+            BreakerSettings {
+                failure_rate: 50,
+                window: 4,
+                open_for,
+                half_open_probes: 2,
+            },
+            None,
+        )
+        .get("endpoint", Dependency::Postgres)
+    }
+
+    fn call(breaker: &Arc<CircuitBreaker>, failed: bool) {
+        let result = if failed { Err(()) } else { Ok(()) };
+        breaker.acquire().expect("breaker closed").record(&result);
+    }
+
+    #[test]
+    fn test_opens_at_the_failure_rate() {
+        let breaker = breaker(Duration::from_secs(30));
+
+        // Not before the window is full
+        call(&breaker, true);
+        call(&breaker, true);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        let breaker = self::breaker(Duration::from_secs(30));
+        // One failure in the last 4 calls
+        call(&breaker, false);
+        call(&breaker, false);
+        call(&breaker, false);
+        call(&breaker, true);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        call(&breaker, true);
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        let open = breaker.acquire().err().unwrap();
+        assert_eq!(open.dependency, Dependency::Postgres);
+        assert!(open.retry_after > Duration::from_secs(29));
+    }
+
+    #[tokio::test]
+    async fn test_probes_while_half_open() {
+        let breaker = breaker(Duration::from_millis(20));
+        for _ in 0..4 {
+            call(&breaker, true);
+        }
+        assert!(breaker.acquire().is_err());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // A probe that fails opens the breaker again
+        call(&breaker, true);
+        assert_eq!(breaker.state(), BreakerState::Open);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        let first = breaker.acquire().unwrap();
+        let second = breaker.acquire().unwrap();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.acquire().is_err());
+        // A probe that never ran gives its place back
+        drop(second);
+        first.record(&Ok::<_, ()>(()));
+        call(&breaker, false);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
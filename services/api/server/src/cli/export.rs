@@ -0,0 +1,86 @@
+//! `wire-api export`: readings as CSV, in time order.
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::Args;
+use postgres_models::models::Keyset;
+use postgres_models::models::energy_readings::{EnergyReading, ReadingFilter};
+
+use crate::Config;
+use crate::auth::tenant::DEFAULT_TENANT;
+
+/// Readings fetched per query.
+const PAGE_SIZE: i64 = 10_000;
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Tenant whose readings are exported
+    #[arg(long, default_value = DEFAULT_TENANT, value_parser = super::parse_tenant)]
+    tenant: String,
+    /// Earliest reading time, inclusive (RFC 3339)
+    #[arg(long)]
+    from: Option<DateTime<Utc>>,
+    /// Latest reading time, exclusive (RFC 3339)
+    #[arg(long)]
+    to: Option<DateTime<Utc>>,
+    /// File to write, stdout when omitted
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub async fn run(args: ExportArgs, config: &Config) -> anyhow::Result<()> {
+    let pool =
+        super::connect_database(config, &config.database_ro_endpoint, None)
+            .await?;
+    let mut conn = pool
+        .get()
+        .await
+        .context("Failed to get a database connection")?;
+
+    let mut out = super::open_output(args.output.as_deref())?;
+    writeln!(out, "reading_time,quantity_kwh")?;
+
+    let mut keyset = Keyset::First;
+    let mut exported = 0;
+    loop {
+        let filter = ReadingFilter {
+            date_from: args.from,
+            date_to: args.to,
+            ..Default::default()
+        };
+        let page = EnergyReading::page(
+            &args.tenant,
+            keyset,
+            filter,
+            PAGE_SIZE,
+            &mut conn,
+        )
+        .await
+        .context("Failed to read energy readings")?;
+
+        for reading in &page {
+            writeln!(
+                out,
+                "{},{}",
+                reading
+                    .reading_time
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+                reading.quantity_kwh
+            )?;
+        }
+        exported += page.len();
+
+        match page.last() {
+            Some(last) if page.len() as i64 == PAGE_SIZE => {
+                keyset = last.cursor();
+            }
+            _ => break,
+        }
+    }
+    out.flush()?;
+
+    tracing::info!(tenant = %args.tenant, exported, "Exported energy readings");
+    Ok(())
+}
@@ -0,0 +1,356 @@
+//! `wire-api loadgen`: steady traffic against a running server, reported as
+//! latency percentiles per endpoint.
+//!
+//! Requests start at `--rps` whether or not earlier ones have finished, so a
+//! saturated server shows up as latency and errors rather than as a lower
+//! rate. Requests that would exceed `--concurrency` in flight are skipped
+//! and counted. The mix of endpoints is deterministic, so runs with the same
+//! arguments send the same requests.
+//!
+//! `readings` requests store one reading each, an hour apart from
+//! 2000-01-01, in the tenant of the API key; later runs store nothing new.
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use chrono::{TimeZone, Utc};
+use clap::Args;
+use serde_json::json;
+use tokio::sync::{Semaphore, mpsc};
+use url::Url;
+
+use crate::webhooks::signing;
+
+#[derive(Debug, Args)]
+pub struct LoadgenArgs {
+    /// Base URL of the server
+    #[arg(long, default_value = "http://localhost:50051")]
+    url: Url,
+    /// Requests started per second
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+    rps: u32,
+    /// How long to send requests for, in seconds
+    #[arg(long, default_value_t = 30)]
+    duration: u64,
+    /// Relative weights of the endpoints
+    #[arg(long, default_value = "aggregate=6,history=3,readings=1")]
+    mix: Mix,
+    /// Most requests in flight
+    #[arg(long, default_value_t = 256)]
+    concurrency: usize,
+    /// API key sent as a bearer token; `readings` needs the ingest scope
+    #[arg(long)]
+    api_key: Option<String>,
+    /// Signing secret of the API key, to sign `readings` requests
+    #[arg(long, requires = "api_key")]
+    signing_secret: Option<String>,
+    /// Seconds before a request counts as failed
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Endpoint {
+    /// `POST /energy/aggregate`, cycling through the aggregation types
+    Aggregate,
+    /// `GET /energy/history`
+    History,
+    /// `POST /energy/readings` with one reading
+    Readings,
+}
+
+impl Endpoint {
+    fn name(self) -> &'static str {
+        match self {
+            Endpoint::Aggregate => "aggregate",
+            Endpoint::History => "history",
+            Endpoint::Readings => "readings",
+        }
+    }
+}
+
+/// Weights of the endpoints, e.g. `aggregate=6,history=3,readings=1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mix(Vec<(Endpoint, u32)>);
+
+impl FromStr for Mix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Vec::new();
+        for part in s.split(',') {
+            let (name, weight) = part.split_once('=').ok_or_else(|| {
+                format!("expected endpoint=weight, got `{part}`")
+            })?;
+            let endpoint = match name.trim() {
+                "aggregate" => Endpoint::Aggregate,
+                "history" => Endpoint::History,
+                "readings" => Endpoint::Readings,
+                other => {
+                    return Err(format!(
+                        "unknown endpoint `{other}`, expected aggregate, \
+                         history or readings"
+                    ));
+                }
+            };
+            let weight = weight
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("invalid weight of {name}: {e}"))?;
+            if weight > 0 {
+                weights.push((endpoint, weight));
+            }
+        }
+        if weights.is_empty() {
+            return Err("at least one endpoint needs a weight".to_string());
+        }
+        Ok(Self(weights))
+    }
+}
+
+impl Mix {
+    /// Endpoint of the `n`th request.
+    fn pick(&self, n: u64) -> Endpoint {
+        let total = self.0.iter().map(|(_, w)| u64::from(*w)).sum::<u64>();
+        let mut slot = n % total;
+        for (endpoint, weight) in &self.0 {
+            if slot < u64::from(*weight) {
+                return *endpoint;
+            }
+            slot -= u64::from(*weight);
+        }
+        unreachable!("slot is below the total weight")
+    }
+}
+
+/// How a request ended.
+enum Outcome {
+    Status(reqwest::StatusCode),
+    /// No response: connection error or timeout
+    Failed,
+}
+
+#[derive(Default)]
+struct EndpointStats {
+    /// Latencies of requests with a response
+    latencies: Vec<Duration>,
+    ok: u64,
+    not_ok: u64,
+    failed: u64,
+    skipped: u64,
+}
+
+impl EndpointStats {
+    fn merge(&mut self, other: &EndpointStats) {
+        self.latencies.extend(&other.latencies);
+        self.ok += other.ok;
+        self.not_ok += other.not_ok;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+    }
+
+    fn row(&mut self, name: &str) -> String {
+        self.latencies.sort_unstable();
+        let ms = |q| {
+            percentile(&self.latencies, q)
+                .map_or("-".to_string(), |d| format!("{:.1}", millis(d)))
+        };
+        format!(
+            "{name:<10} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            self.ok + self.not_ok + self.failed,
+            self.ok,
+            self.not_ok,
+            self.failed,
+            self.skipped,
+            ms(0.5),
+            ms(0.9),
+            ms(0.99),
+            ms(1.0),
+        )
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Nearest-rank percentile `q` (0 to 1) of sorted latencies.
+fn percentile(sorted: &[Duration], q: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+pub async fn run(args: LoadgenArgs) -> anyhow::Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(args.timeout))
+        .build()
+        .context("Failed to create HTTP client")?;
+    let base = format!(
+        "{}/api/wire/v1/energy",
+        args.url.as_str().trim_end_matches('/')
+    );
+    let total = u64::from(args.rps) * args.duration;
+    eprintln!(
+        "Sending {total} requests to {base} at {} req/s for {}s",
+        args.rps, args.duration
+    );
+
+    let in_flight = Arc::new(Semaphore::new(args.concurrency));
+    let (samples_tx, mut samples) = mpsc::unbounded_channel();
+    let mut stats = BTreeMap::<Endpoint, EndpointStats>::new();
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(
+        1.0 / f64::from(args.rps),
+    ));
+    let started = Instant::now();
+
+    for n in 0..total {
+        interval.tick().await;
+        let endpoint = args.mix.pick(n);
+        let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+            stats.entry(endpoint).or_default().skipped += 1;
+            continue;
+        };
+        let mut request = match endpoint {
+            Endpoint::Aggregate => {
+                let aggregation_type =
+                    ["hourly", "day_of_month", "monthly"][(n % 3) as usize];
+                client
+                    .post(format!("{base}/aggregate"))
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(
+                        json!({"aggregationType": aggregation_type})
+                            .to_string(),
+                    )
+            }
+            Endpoint::History => client.get(format!("{base}/history")),
+            Endpoint::Readings => {
+                let reading_time =
+                    Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap()
+                        + chrono::Duration::hours(n as i64);
+                let body = json!({"readings": [{
+                    "readingTime": reading_time,
+                    "quantityKwh": 1.0,
+                }]})
+                .to_string();
+                let mut request = client
+                    .post(format!("{base}/readings"))
+                    .header(reqwest::header::CONTENT_TYPE, "application/json");
+                if let Some(secret) = &args.signing_secret {
+                    let timestamp = Utc::now().timestamp();
+                    request = request
+                        .header(signing::TIMESTAMP_HEADER, timestamp)
+                        .header(
+                            signing::SIGNATURE_HEADER,
+                            signing::sign(secret, timestamp, body.as_bytes()),
+                        );
+                }
+                request.body(body)
+            }
+        };
+        if let Some(api_key) = &args.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let samples_tx = samples_tx.clone();
+        tokio::spawn(async move {
+            let sent = Instant::now();
+            // The latency includes reading the body
+            let outcome = match request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    match response.bytes().await {
+                        Ok(_) => Outcome::Status(status),
+                        Err(_) => Outcome::Failed,
+                    }
+                }
+                Err(_) => Outcome::Failed,
+            };
+            let _ = samples_tx.send((endpoint, sent.elapsed(), outcome));
+            drop(permit);
+        });
+    }
+    let sending = started.elapsed();
+    drop(samples_tx);
+
+    while let Some((endpoint, latency, outcome)) = samples.recv().await {
+        let stats = stats.entry(endpoint).or_default();
+        match outcome {
+            Outcome::Status(status) => {
+                stats.latencies.push(latency);
+                if status.is_success() {
+                    stats.ok += 1;
+                } else {
+                    stats.not_ok += 1;
+                }
+            }
+            Outcome::Failed => stats.failed += 1,
+        }
+    }
+
+    println!(
+        "{:<10} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "endpoint",
+        "sent",
+        "2xx",
+        "other",
+        "failed",
+        "skipped",
+        "p50 ms",
+        "p90 ms",
+        "p99 ms",
+        "max ms",
+    );
+    let mut all = EndpointStats::default();
+    for (endpoint, stats) in &mut stats {
+        all.merge(stats);
+        println!("{}", stats.row(endpoint.name()));
+    }
+    println!("{}", all.row("all"));
+    println!(
+        "Started {} requests in {:.1}s ({:.1} req/s)",
+        total - all.skipped,
+        sending.as_secs_f64(),
+        (total - all.skipped) as f64 / sending.as_secs_f64().max(f64::EPSILON),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mix() {
+        let mix = "aggregate=2, history=1,readings=0".parse::<Mix>().unwrap();
+        let picks = (0..6).map(|n| mix.pick(n)).collect::<Vec<_>>();
+        assert_eq!(
+            picks,
+            [
+                Endpoint::Aggregate,
+                Endpoint::Aggregate,
+                Endpoint::History,
+                Endpoint::Aggregate,
+                Endpoint::Aggregate,
+                Endpoint::History,
+            ]
+        );
+        assert!("aggregate".parse::<Mix>().is_err());
+        assert!("plants=1".parse::<Mix>().is_err());
+        assert!("history=0".parse::<Mix>().is_err());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let sorted = (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&sorted, 0.5), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&sorted, 0.99), Some(Duration::from_millis(99)));
+        assert_eq!(percentile(&sorted, 1.0), Some(Duration::from_millis(100)));
+        assert_eq!(percentile(&sorted, 0.0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 0.5), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! `wire-api migrate`: the embedded database migrations.
+//!
+//! Applying them holds a Postgres advisory lock, so replicas starting
+//! together, or a replica and `wire-api migrate up`, apply them one at a
+//! time: the others wait, then find nothing pending.
+use std::collections::HashSet;
+
+use anyhow::Context;
+use clap::Subcommand;
+use diesel::RunQueryDsl;
+use diesel::migration::MigrationSource;
+use diesel::pg::Pg;
+use diesel::sql_types::BigInt;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness};
+use postgres_models::connection::{Pool, PooledConnection};
+
+/// Migrations in `db/migrations`, embedded at build time.
+pub const MIGRATIONS: EmbeddedMigrations =
+    diesel_migrations::embed_migrations!("./../../../db/migrations");
+
+/// Key of the advisory lock held while applying migrations.
+const MIGRATION_LOCK: i64 = 0x7769_7265_6d69_6772;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply pending migrations
+    Up,
+    /// Revert the most recently applied migrations
+    Down {
+        /// Number of migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// List migrations and whether they are applied
+    Status,
+}
+
+type Connection = AsyncConnectionWrapper<PooledConnection>;
+
+pub async fn run(command: MigrateCommand, pool: &Pool) -> anyhow::Result<()> {
+    let mut conn = connection(pool).await?;
+
+    // The migration harness is synchronous
+    tokio::task::spawn_blocking(move || match command {
+        MigrateCommand::Up => up(&mut conn),
+        MigrateCommand::Down { steps } => down(&mut conn, steps),
+        MigrateCommand::Status => status(&mut conn),
+    })
+    .await
+    .context("Migration task failed")?
+}
+
+/// Apply pending migrations under the advisory lock, returning their
+/// versions.
+pub async fn apply(pool: &Pool) -> anyhow::Result<Vec<String>> {
+    let mut conn = connection(pool).await?;
+    tokio::task::spawn_blocking(move || apply_locked(&mut conn))
+        .await
+        .context("Migration task failed")?
+}
+
+/// Versions of the migrations not applied yet.
+pub async fn pending(pool: &Pool) -> anyhow::Result<Vec<String>> {
+    let mut conn = connection(pool).await?;
+    tokio::task::spawn_blocking(move || {
+        let pending = conn
+            .pending_migrations(MIGRATIONS)
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("Failed to read pending migrations")?;
+        Ok(pending
+            .iter()
+            .map(|migration| migration.name().version().to_string())
+            .collect())
+    })
+    .await
+    .context("Migration task failed")?
+}
+
+async fn connection(pool: &Pool) -> anyhow::Result<Connection> {
+    let conn = pool
+        .get_owned()
+        .await
+        .context("Failed to get a database connection")?;
+    Ok(Connection::from(conn))
+}
+
+fn apply_locked(conn: &mut Connection) -> anyhow::Result<Vec<String>> {
+    // Session locks outlive transactions, so it is released explicitly
+    // before the connection goes back to the pool
+    diesel::sql_query("SELECT pg_advisory_lock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK)
+        .execute(conn)
+        .context("Failed to take the migration lock")?;
+    let applied = conn
+        .run_pending_migrations(MIGRATIONS)
+        .map(|applied| applied.iter().map(ToString::to_string).collect())
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Failed to apply migrations");
+    diesel::sql_query("SELECT pg_advisory_unlock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK)
+        .execute(conn)
+        .context("Failed to release the migration lock")?;
+    applied
+}
+
+fn up(conn: &mut Connection) -> anyhow::Result<()> {
+    let applied = apply_locked(conn)?;
+
+    if applied.is_empty() {
+        println!("No pending migrations");
+    }
+    for version in applied {
+        println!("Applied {version}");
+    }
+    Ok(())
+}
+
+fn down(conn: &mut Connection, steps: usize) -> anyhow::Result<()> {
+    for _ in 0..steps {
+        let applied = conn
+            .applied_migrations()
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("Failed to read applied migrations")?;
+        if applied.is_empty() {
+            println!("No applied migrations left to revert");
+            break;
+        }
+
+        let version = conn
+            .revert_last_migration(MIGRATIONS)
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("Failed to revert migration")?;
+        println!("Reverted {version}");
+    }
+    Ok(())
+}
+
+fn status(conn: &mut Connection) -> anyhow::Result<()> {
+    let applied = conn
+        .applied_migrations()
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Failed to read applied migrations")?
+        .into_iter()
+        .map(|version| version.to_string())
+        .collect::<HashSet<_>>();
+    let migrations = MigrationSource::<Pg>::migrations(&MIGRATIONS)
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    for migration in migrations {
+        let name = migration.name();
+        let state = if applied.contains(&name.version().to_string()) {
+            "applied"
+        } else {
+            "pending"
+        };
+        println!("{state:<8} {name}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wire_api::testing::TestApp;
+
+    #[tokio::test]
+    async fn test_replicas_migrate_one_at_a_time() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let pool = &app.state.pool;
+
+        let (first, second) =
+            tokio::join!(super::apply(pool), super::apply(pool));
+        assert!(first.unwrap().is_empty());
+        assert!(second.unwrap().is_empty());
+        assert!(super::pending(pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+//! Command line interface of the `wire-api` binary.
+//!
+//! `serve`, the default, runs the HTTP server. The other commands run one
+//! operational task with the same config and database, then exit, so they
+//! don't need a running server; `loadgen` instead drives one.
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand, ValueEnum};
+use postgres_models::connection::{Pool, StatementListener};
+use utoipa::OpenApi;
+
+use crate::Config;
+use crate::auth::TenantContext;
+use crate::auth::tenant::{DEFAULT_TENANT, is_valid_tenant_id};
+use crate::cache_warmer::CacheWarmer;
+use crate::data_loader::{DstPolicy, ImportSummary, SourceTime};
+use crate::flags::FeatureFlags;
+use crate::openapi::{WireV1ApiDoc, WireV2ApiDoc};
+use crate::read_fallback::ReadPools;
+use crate::repository::{DieselEnergyReadings, DieselQueryHistory};
+
+pub mod export;
+pub mod loadgen;
+pub mod migrate;
+
+#[derive(Debug, Parser)]
+#[command(name = "wire-api", version, about = "Energy readings API")]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Cli {
+    /// The command to run, `serve` when none is given.
+    pub fn command(self) -> Command {
+        self.command.unwrap_or(Command::Serve)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (the default)
+    Serve,
+    /// Apply, revert or list database migrations
+    Migrate {
+        #[command(subcommand)]
+        command: migrate::MigrateCommand,
+    },
+    /// Import readings from Excel or CSV files, skipping ones already stored
+    Import {
+        /// Excel or CSV file with `Time (UTC)` and `Quantity kWh` columns, a
+        /// directory of them or a pattern like `readings-*.xlsx`
+        file: PathBuf,
+        /// Tenant the readings belong to
+        #[arg(long, default_value = DEFAULT_TENANT, value_parser = parse_tenant)]
+        tenant: String,
+        /// Timezone the times are in, e.g. `Europe/Berlin`; defaults to
+        /// `IMPORT_SOURCE_TIMEZONE`
+        #[arg(long)]
+        timezone: Option<Tz>,
+        /// `earlier`, `later` or `reject` for times a DST change repeats or
+        /// skips; defaults to `IMPORT_DST_POLICY`
+        #[arg(long)]
+        dst: Option<DstPolicy>,
+    },
+    /// Export readings as CSV
+    Export(export::ExportArgs),
+    /// Print the OpenAPI spec, or write both versions to a directory; needs
+    /// no configuration or database
+    GenerateOpenapi {
+        /// OpenAPI version of the spec
+        #[arg(long, value_enum, default_value_t = SpecVersion::V3_1)]
+        spec: SpecVersion,
+        /// Version of the API the spec describes
+        #[arg(long, value_enum, default_value_t = ApiVersion::V1)]
+        api: ApiVersion,
+        /// File to write, stdout when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Directory to write `openapi.json` (3.0) and `openapi-3.1.json`
+        /// of both API versions to, named as served under `/api-docs`
+        #[arg(long, conflicts_with_all = ["spec", "api", "output"])]
+        out_dir: Option<PathBuf>,
+    },
+    /// Validate the configuration, including secrets, and exit
+    CheckConfig,
+    /// Send aggregate, history and readings requests to a running server
+    /// at a steady rate and report latency percentiles; needs no
+    /// configuration or database
+    Loadgen(loadgen::LoadgenArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SpecVersion {
+    /// As served at `/api-docs/openapi.json`, with nullable fields
+    #[value(name = "3.0")]
+    V3_0,
+    /// As served at `/api-docs/openapi-3.1.json`
+    #[value(name = "3.1")]
+    V3_1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ApiVersion {
+    /// Served under `/api/wire/v1`
+    V1,
+    /// Served under `/api/wire/v2`
+    V2,
+}
+
+impl ApiVersion {
+    /// Directory of the specs of the version under `/api-docs`.
+    pub fn docs_dir(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "",
+            ApiVersion::V2 => "v2",
+        }
+    }
+}
+
+impl SpecVersion {
+    /// File name of the spec under `/api-docs`.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            SpecVersion::V3_0 => "openapi.json",
+            SpecVersion::V3_1 => "openapi-3.1.json",
+        }
+    }
+}
+
+fn parse_tenant(tenant: &str) -> Result<String, String> {
+    if is_valid_tenant_id(tenant) {
+        Ok(tenant.to_string())
+    } else {
+        Err("expected lowercase letters, digits, `-` or `_`".to_string())
+    }
+}
+
+/// Writer for `path`, or stdout when there is none.
+fn open_output(path: Option<&Path>) -> anyhow::Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).with_context(|| {
+                format!("Failed to create {}", path.display())
+            })?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    })
+}
+
+/// Connect to the database on `endpoint`, one of the config's read-write or
+/// read-only endpoints, reporting the statement cache use of its queries to
+/// `on_statement` when given.
+pub async fn connect_database(
+    config: &Config,
+    endpoint: &str,
+    on_statement: Option<StatementListener>,
+) -> anyhow::Result<Pool> {
+    postgres_models::connection::establish_connection(
+        config.database_url(endpoint),
+        &config.database_pool,
+        on_statement,
+    )
+    .await
+    .with_context(|| format!("Failed to connect to Postgres at {endpoint}"))
+}
+
+/// `wire-api import`
+pub async fn import(
+    file: &Path,
+    tenant: &str,
+    source_time: SourceTime,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let pool =
+        connect_database(config, &config.database_rw_endpoint, None).await?;
+    let report = crate::data_loader::import_energy_readings(
+        file,
+        tenant,
+        source_time,
+        config.quantity_policy,
+        &pool,
+    )
+    .await
+    .with_context(|| format!("Failed to import {}", file.display()))?;
+
+    for import in &report.files {
+        match &import.result {
+            Ok(summary) => println!(
+                "{}: imported {} of {} readings{}",
+                import.file.display(),
+                summary.inserted,
+                summary.total,
+                notes(summary)
+            ),
+            Err(e) => println!("{}: failed: {e}", import.file.display()),
+        }
+    }
+    let summary = report.summary();
+    println!(
+        "Imported {} of {} readings for tenant {tenant}",
+        summary.inserted, summary.total
+    );
+    if summary.inserted > 0 {
+        match warm_cache(tenant, pool, config).await {
+            Ok(cached) => println!("Cached {cached} frequent aggregations"),
+            Err(e) => eprintln!("Failed to warm the aggregate cache: {e:#}"),
+        }
+    }
+    report.into_result().map(drop)
+}
+
+/// What the DST and quantity policies did to the readings of a file.
+fn notes(summary: &ImportSummary) -> String {
+    let quantities = summary.quantities;
+    [
+        (summary.dst_adjusted, "at times repeated or skipped by DST"),
+        (quantities.rejected, "rejected"),
+        (quantities.clamped, "clamped"),
+        (quantities.flagged, "flagged"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, note)| format!(", {count} {note}"))
+    .collect()
+}
+
+/// Warm the aggregate cache after an import; failing to does not fail it.
+async fn warm_cache(
+    tenant: &str,
+    pool: Pool,
+    config: &Config,
+) -> anyhow::Result<usize> {
+    let reads = ReadPools::new(
+        connect_database(config, &config.database_ro_endpoint, None).await?,
+    );
+    let cache_pool = redis_cache::connection::establish_connection(
+        config.redis_url.to_string(),
+    )
+    .await
+    .context("Failed to connect to Redis")?;
+    let warmer = CacheWarmer::new(
+        Arc::new(DieselEnergyReadings::new(reads.clone())),
+        Arc::new(DieselQueryHistory::new(pool, reads)),
+        cache_pool,
+        Arc::new(FeatureFlags::new(config.feature_flags.clone())),
+        config.cache_warmer.clone(),
+    );
+    warmer.warm(&TenantContext::new(tenant)).await
+}
+
+/// `wire-api generate-openapi --spec`, writing one spec to `output`.
+pub fn generate_openapi(
+    api: ApiVersion,
+    spec: SpecVersion,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let json = match (api, spec) {
+        (ApiVersion::V1, SpecVersion::V3_0) => WireV1ApiDoc::openapi_json(),
+        (ApiVersion::V1, SpecVersion::V3_1) => {
+            serde_json::to_value(WireV1ApiDoc::openapi())?
+        }
+        (ApiVersion::V2, SpecVersion::V3_0) => WireV2ApiDoc::openapi_json(),
+        (ApiVersion::V2, SpecVersion::V3_1) => {
+            serde_json::to_value(WireV2ApiDoc::openapi())?
+        }
+    };
+
+    let mut out = open_output(output)?;
+    serde_json::to_writer_pretty(&mut out, &json)?;
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// `wire-api generate-openapi --out-dir`, writing both specs of both API
+/// versions to `dir`.
+pub fn generate_openapi_files(dir: &Path) -> anyhow::Result<()> {
+    for api in [ApiVersion::V1, ApiVersion::V2] {
+        let dir = dir.join(api.docs_dir());
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        for spec in [SpecVersion::V3_0, SpecVersion::V3_1] {
+            let path = dir.join(spec.file_name());
+            generate_openapi(api, spec, Some(&path))?;
+            eprintln!("Wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// `wire-api check-config`, run once the config has loaded.
+pub fn check_config(config: &Config) {
+    println!("Configuration is valid");
+    println!("  port: {}", config.api_service_port);
+    println!(
+        "  database: {} (read-write), {} (read-only)",
+        config.database_rw_endpoint, config.database_ro_endpoint
+    );
+    println!(
+        "  redis: {}",
+        config.redis_url.host_str().unwrap_or_default()
+    );
+    println!(
+        "  tls: {}",
+        if config.tls.is_some() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    let disabled = config
+        .endpoints
+        .disabled()
+        .map(|group| group.as_str())
+        .collect::<Vec<_>>();
+    if !disabled.is_empty() {
+        println!("  disabled endpoints: {}", disabled.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        let command = |args: &[&str]| {
+            Cli::try_parse_from([&["wire-api"], args].concat())
+                .map(Cli::command)
+        };
+
+        assert!(matches!(command(&[]), Ok(Command::Serve)));
+        assert!(matches!(
+            command(&["migrate", "down", "--steps", "2"]),
+            Ok(Command::Migrate {
+                command: migrate::MigrateCommand::Down { steps: 2 }
+            })
+        ));
+        assert!(matches!(
+            command(&["import", "readings.xlsx"]),
+            Ok(Command::Import { tenant, .. }) if tenant == DEFAULT_TENANT
+        ));
+        assert!(matches!(
+            command(&["generate-openapi", "--spec", "3.0"]),
+            Ok(Command::GenerateOpenapi {
+                spec: SpecVersion::V3_0,
+                api: ApiVersion::V1,
+                output: None,
+                out_dir: None
+            })
+        ));
+        assert!(matches!(
+            command(&["generate-openapi", "--api", "v2"]),
+            Ok(Command::GenerateOpenapi {
+                api: ApiVersion::V2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            command(&["generate-openapi", "--out-dir", "docs"]),
+            Ok(Command::GenerateOpenapi {
+                out_dir: Some(_),
+                ..
+            })
+        ));
+        assert!(
+            command(&["generate-openapi", "--out-dir", "docs", "-o", "a.json"])
+                .is_err()
+        );
+        assert!(
+            command(&["generate-openapi", "--out-dir", "docs", "--api", "v2"])
+                .is_err()
+        );
+        assert!(matches!(
+            command(&["loadgen", "--rps", "200", "--mix", "history=1"]),
+            Ok(Command::Loadgen(_))
+        ));
+        assert!(command(&["loadgen", "--rps", "0"]).is_err());
+        assert!(command(&["import", "a.xlsx", "--tenant", "Acme"]).is_err());
+        assert!(command(&["export", "--from", "yesterday"]).is_err());
+    }
+}
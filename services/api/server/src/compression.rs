@@ -0,0 +1,184 @@
+//! Response compression.
+//!
+//! Responses are compressed with the best algorithm the client accepts
+//! among the enabled ones (`COMPRESSION_ALGORITHMS`), unless they are
+//! smaller than `COMPRESSION_MIN_BYTES` or of a content type that is
+//! already compressed or streamed to the client as it happens, see
+//! [`ALWAYS_EXCLUDED`]. `COMPRESSION_EXCLUDED_CONTENT_TYPES` excludes more.
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::body::HttpBody;
+use axum::http::{Response, header};
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::{And, Predicate, SizeAbove};
+
+/// Content types never compressed: gRPC frames its own messages, images
+/// and the export formats are compressed already, and compressing server
+/// sent events would hold them back in the encoder.
+pub const ALWAYS_EXCLUDED: &[&str] = &[
+    "application/grpc",
+    "image/",
+    "text/event-stream",
+    "application/vnd.apache.parquet",
+    "application/vnd.apache.arrow.file",
+    "application/zip",
+    "application/gzip",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Brotli,
+    Zstd,
+    Gzip,
+    Deflate,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "br" | "brotli" => Ok(Algorithm::Brotli),
+            "zstd" => Ok(Algorithm::Zstd),
+            "gzip" => Ok(Algorithm::Gzip),
+            "deflate" => Ok(Algorithm::Deflate),
+            other => Err(format!(
+                "Unknown compression algorithm `{other}`, expected br, zstd, \
+                 gzip or deflate"
+            )),
+        }
+    }
+}
+
+/// Parse a comma-separated list of algorithms, or `none` for none.
+pub fn parse_algorithms(list: &str) -> Result<Vec<Algorithm>, String> {
+    if list.trim() == "none" {
+        return Ok(Vec::new());
+    }
+    let algorithms = list
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if algorithms.is_empty() {
+        return Err("expected at least one algorithm, or `none`".to_string());
+    }
+    Ok(algorithms)
+}
+
+/// Parse a comma-separated list of content types or prefixes of them, like
+/// `text/` or `application/pdf`.
+pub fn parse_content_types(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|content_type| content_type.trim().to_ascii_lowercase())
+        .filter(|content_type| !content_type.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionSettings {
+    /// Smallest response compressed, in bytes; responses of unknown size,
+    /// like streamed ones, are always compressed
+    pub min_size: u16,
+    /// Enabled algorithms; empty to disable compression
+    pub algorithms: Vec<Algorithm>,
+    /// Content types not compressed, on top of [`ALWAYS_EXCLUDED`]
+    pub excluded_content_types: Vec<String>,
+}
+
+impl CompressionSettings {
+    pub fn layer(&self) -> CompressionLayer<And<SizeAbove, ContentTypes>> {
+        let enabled = |algorithm| self.algorithms.contains(&algorithm);
+        CompressionLayer::new()
+            .br(enabled(Algorithm::Brotli))
+            .zstd(enabled(Algorithm::Zstd))
+            .gzip(enabled(Algorithm::Gzip))
+            .deflate(enabled(Algorithm::Deflate))
+            .compress_when(self.predicate())
+    }
+
+    fn predicate(&self) -> And<SizeAbove, ContentTypes> {
+        SizeAbove::new(self.min_size).and(ContentTypes {
+            excluded: ALWAYS_EXCLUDED
+                .iter()
+                .map(|content_type| content_type.to_string())
+                .chain(self.excluded_content_types.iter().cloned())
+                .collect(),
+        })
+    }
+}
+
+/// Compresses responses unless their content type starts with an excluded
+/// one.
+#[derive(Debug, Clone)]
+pub struct ContentTypes {
+    excluded: Arc<[String]>,
+}
+
+impl Predicate for ContentTypes {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: HttpBody,
+    {
+        let Some(content_type) = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return true;
+        };
+        let content_type = content_type.trim().to_ascii_lowercase();
+        !self
+            .excluded
+            .iter()
+            .any(|excluded| content_type.starts_with(excluded.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_algorithms() {
+        assert_eq!(
+            parse_algorithms("br, gzip").unwrap(),
+            [Algorithm::Brotli, Algorithm::Gzip]
+        );
+        assert_eq!(parse_algorithms("none").unwrap(), []);
+        assert!(parse_algorithms("").is_err());
+        assert!(parse_algorithms("gzip,lz4").is_err());
+    }
+
+    #[test]
+    fn test_compresses_large_responses_of_other_content_types() {
+        let settings = CompressionSettings {
+            min_size: 1024,
+            algorithms: vec![Algorithm::Gzip],
+            excluded_content_types: parse_content_types(" Text/CSV ,"),
+        };
+        let predicate = settings.predicate();
+        let response = |content_type: &str, size: usize| {
+            Response::builder()
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::from(vec![b' '; size]))
+                .unwrap()
+        };
+
+        assert!(predicate.should_compress(&response("application/json", 1024)));
+        assert!(!predicate.should_compress(&response("application/json", 100)));
+        assert!(
+            !predicate
+                .should_compress(&response("text/csv; charset=utf-8", 4096))
+        );
+        assert!(!predicate.should_compress(&response(
+            "application/vnd.apache.parquet",
+            4096
+        )));
+        assert!(!predicate.should_compress(&response("image/png", 4096)));
+    }
+}
@@ -0,0 +1,1899 @@
+//! Service configuration.
+//!
+//! Settings are layered, later sources overriding earlier ones: built-in
+//! defaults, then the TOML file named by `CONFIG_FILE` (if set), then
+//! environment variables, including those from a `.env` file. File keys are
+//! the lowercase environment variable names, e.g. `api_service_port = 50051`.
+//!
+//! Any setting can hold a Secrets Manager ARN instead of its value, which
+//! [`Config::load_async`] resolves before parsing.
+//!
+//! Every setting is parsed into its final type when the service starts, and
+//! all invalid or missing settings are reported together.
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono_tz::Tz;
+use figment::providers::{Data, Format, Serialized, Toml};
+use figment::value::{Dict, Map, Value};
+use figment::{Figment, Metadata, Profile, Provider};
+use lettre::message::Mailbox;
+use postgres_models::connection::{Credentials, PoolSettings};
+use url::Url;
+
+use crate::admission::AdmissionSettings;
+use crate::alerts::EvaluatorSettings;
+use crate::auth::csrf::{self, CsrfPolicy};
+use crate::auth::ip_filter::{self, IpFilter};
+use crate::auth::jwt::JwtSettings;
+use crate::cache_warmer::WarmerSettings;
+use crate::circuit_breaker::BreakerSettings;
+use crate::compression::{self, CompressionSettings};
+use crate::data_loader::SourceTime;
+use crate::endpoints::{self, Endpoints};
+use crate::flags::{self, Flag};
+use crate::history_writer::{Overflow, WriterSettings};
+use crate::listener::{self, HttpSettings, ListenAddr};
+use crate::maintenance::SchedulerSettings;
+use crate::market_prices::ImporterSettings as PriceImporterSettings;
+use crate::notifications::dispatcher::DispatcherSettings as NotificationSettings;
+use crate::notifications::email::SmtpSettings;
+use crate::notifications::health::MonitorSettings;
+use crate::outbox::RelaySettings;
+use crate::pool_sizing::{DriftAction, PoolSizingSettings};
+use crate::quantity_policy::QuantityPolicy;
+use crate::read_fallback::FallbackSettings;
+use crate::repository::HedgeSettings;
+use crate::shadow::ShadowSettings;
+use crate::snapshots::SnapshotSettings;
+use crate::startup::RetrySettings;
+use crate::tls::TlsSettings;
+use crate::uploads::UploadSettings;
+use crate::weather::ImporterSettings;
+use crate::webhooks::dispatcher::DispatcherSettings;
+
+/// Environment variable naming the optional TOML config file.
+pub const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+
+/// Every setting read by [`Config::from_figment`].
+const SETTINGS: &[&str] = &[
+    "api_service_port",
+    "listen_addrs",
+    "internal_listen_addrs",
+    "grpc_listen_addrs",
+    "http2_enabled",
+    "http1_keep_alive",
+    "http2_keep_alive_interval_secs",
+    "http2_keep_alive_timeout_secs",
+    "http2_max_concurrent_streams",
+    "http_max_header_bytes",
+    "request_timeout_secs",
+    "poll_timeout_secs",
+    "load_poll_interval_secs",
+    "compression_min_bytes",
+    "compression_algorithms",
+    "compression_excluded_content_types",
+    "rust_log",
+    "log_format",
+    "database_credentials",
+    "database_rw_endpoint",
+    "database_ro_endpoint",
+    "database_max_lifetime_secs",
+    "database_idle_timeout_secs",
+    "database_min_idle",
+    "auto_migrate",
+    "database_pool_check_interval_secs",
+    "database_pool_resize",
+    "hedged_read_delay_ms",
+    "hedged_read_concurrency",
+    "read_fallback_concurrency",
+    "read_replica_check_interval_secs",
+    "redis_url",
+    "startup_retry_initial_backoff_ms",
+    "startup_retry_max_backoff_secs",
+    "startup_retry_max_wait_secs",
+    "energy_readings_xls_file_path",
+    "import_source_timezone",
+    "import_dst_policy",
+    "quantity_negative_policy",
+    "quantity_max_kwh",
+    "quantity_outlier_policy",
+    "upload_dir",
+    "upload_storage",
+    "upload_max_bytes",
+    "snapshot_dir",
+    "snapshot_storage",
+    "cache_warm_queries",
+    "cache_warm_lookback_secs",
+    "admission_budget",
+    "admission_busy_connections",
+    "admission_queue_size",
+    "admission_queue_timeout_secs",
+    "circuit_breaker_failure_rate",
+    "circuit_breaker_window",
+    "circuit_breaker_open_secs",
+    "circuit_breaker_half_open_probes",
+    "query_history_strict",
+    "query_history_buffer_size",
+    "query_history_batch_size",
+    "query_history_flush_interval_secs",
+    "query_history_overflow",
+    "webhook_poll_interval_secs",
+    "webhook_request_timeout_secs",
+    "webhook_max_attempts",
+    "outbox_poll_interval_secs",
+    "outbox_redis_channel",
+    "maintenance_poll_interval_secs",
+    "alert_evaluation_interval_secs",
+    "smtp_url",
+    "alert_email_from",
+    "notification_poll_interval_secs",
+    "notification_request_timeout_secs",
+    "notification_max_attempts",
+    "health_check_interval_secs",
+    "weather_api_url",
+    "weather_poll_interval_secs",
+    "weather_request_timeout_secs",
+    "weather_past_days",
+    "weather_forecast_days",
+    "market_price_api_url",
+    "market_price_zones",
+    "market_price_currency",
+    "market_price_poll_interval_secs",
+    "market_price_request_timeout_secs",
+    "market_price_past_days",
+    "shadow_database_endpoint",
+    "shadow_interval_secs",
+    "shadow_sample_size",
+    "shadow_lookback_secs",
+    "require_api_key",
+    "admin_api_token",
+    "jwt_issuer",
+    "jwt_audience",
+    "jwt_jwks_url",
+    "jwt_jwks_refresh_secs",
+    "signature_max_age_secs",
+    "admin_ip_allowlist",
+    "admin_ip_denylist",
+    "trusted_proxies",
+    "csrf_protected_routes",
+    "csrf_trusted_origins",
+    "tls_cert_path",
+    "tls_key_path",
+    "tls_client_ca_path",
+    "feature_flags",
+    "disabled_endpoints",
+];
+
+/// Smallest `HTTP_MAX_HEADER_BYTES`, hyper's minimum read buffer.
+const MIN_HEADER_BYTES: u32 = 8192;
+
+/// Most days of weather imported before and after today, the range of the
+/// Open-Meteo forecast API.
+const MAX_WEATHER_PAST_DAYS: u32 = 92;
+const MAX_WEATHER_FORECAST_DAYS: u32 = 16;
+
+/// Most delivery days of prices re-imported on every poll.
+const MAX_MARKET_PRICE_PAST_DAYS: u32 = 31;
+
+/// Timeout of JWKS requests to the identity provider.
+const JWKS_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Json,
+    Pretty,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(LogFormat::Json),
+            "pretty" => Ok(LogFormat::Pretty),
+            other => Err(format!(
+                "Unknown log format `{other}`, expected `json` or `pretty`"
+            )),
+        }
+    }
+}
+
+pub struct Config {
+    // Service port
+    pub api_service_port: u16,
+    /// Listeners serving the API, by default every interface on
+    /// `api_service_port`
+    pub listen_addrs: Vec<ListenAddr>,
+    /// Listeners that take over the admin and metrics routes from the
+    /// public ones; empty to serve everything publicly
+    pub internal_listen_addrs: Vec<ListenAddr>,
+    /// Listeners serving the gRPC API; empty to disable it
+    pub grpc_listen_addrs: Vec<ListenAddr>,
+    /// HTTP/1 and HTTP/2 connection settings of every listener
+    pub http: HttpSettings,
+    /// Compression of HTTP responses
+    pub compression: CompressionSettings,
+    /// Longest time a `/v1` request may take, `None` for no limit
+    pub request_timeout: Option<Duration>,
+    /// Longest wait of `GET /energy/readings/poll` for new readings
+    pub poll_timeout: Duration,
+    /// Interval between polls suggested to clients at low load, see
+    /// [`crate::load`]
+    pub load_poll_interval: Duration,
+
+    // Loggers
+    pub rust_log: String,
+    pub log_format: LogFormat,
+
+    // Db configs
+    pub database_credentials: Credentials,
+    pub database_rw_endpoint: String,
+    pub database_ro_endpoint: String,
+    /// Lifetime of the connections of every pool, and so of their prepared
+    /// statements
+    pub database_pool: PoolSettings,
+    /// Whether pending migrations are applied at startup
+    pub auto_migrate: bool,
+    /// Re-checks of the pool sizes, `None` when disabled
+    pub database_pool_sizing: Option<PoolSizingSettings>,
+    /// Aggregations hedged on the read-write pool when the read-only one
+    /// is slow, `None` not to hedge them
+    pub hedged_reads: Option<HedgeSettings>,
+    /// Reads on the read-write pool while the read replica is down, `None`
+    /// never to fall back
+    pub read_fallback: Option<FallbackSettings>,
+
+    // Redis configs
+    pub redis_url: Url,
+
+    // Retries of the connections to Postgres and Redis at startup
+    pub startup_retry: RetrySettings,
+
+    // Energy readings Excel file, directory of them or file name pattern
+    pub energy_readings_xls_file_path: PathBuf,
+    /// Timezone of the times in imported files, and DST handling
+    pub import_source_time: SourceTime,
+    /// Handling of negative and outlier quantities of imported and
+    /// ingested readings
+    pub quantity_policy: QuantityPolicy,
+    /// Readings files uploaded through `/admin/files`
+    pub uploads: UploadSettings,
+    /// Snapshots of readings taken through `/admin/snapshots`
+    pub snapshots: SnapshotSettings,
+
+    // Aggregate cache warming after imports
+    pub cache_warmer: WarmerSettings,
+
+    // Admission control of expensive aggregations
+    pub admission: AdmissionSettings,
+
+    // Circuit breakers around the database and cache calls of endpoints
+    pub circuit_breaker: BreakerSettings,
+
+    // Whether aggregations wait for their query history to be written
+    pub query_history_strict: bool,
+
+    // Write-behind batching of the query history otherwise
+    pub query_history_writer: WriterSettings,
+
+    // Webhook dispatcher
+    pub webhook_dispatcher: DispatcherSettings,
+
+    // Outbox relay
+    pub outbox_relay: RelaySettings,
+
+    // Maintenance window scheduler
+    pub maintenance_scheduler: SchedulerSettings,
+
+    // Alert rule evaluator
+    pub alert_evaluator: EvaluatorSettings,
+
+    // Notification dispatcher
+    pub notification_dispatcher: NotificationSettings,
+    /// SMTP relay of email channels, present when `SMTP_URL` and
+    /// `ALERT_EMAIL_FROM` are set
+    pub smtp: Option<SmtpSettings>,
+    pub health_monitor: MonitorSettings,
+
+    /// Weather importer, present when `WEATHER_API_URL` is set
+    pub weather: Option<ImporterSettings>,
+
+    /// Market price importer, present when `MARKET_PRICE_API_URL` is set
+    pub market_prices: Option<PriceImporterSettings>,
+
+    /// Shadow aggregate comparisons, present when `SHADOW_DATABASE_ENDPOINT`
+    /// is set
+    pub shadow: Option<ShadowSettings>,
+
+    // Auth
+    pub require_api_key: bool,
+    pub admin_api_token: Option<String>,
+    /// JWT validation, present when issuer, audience and JWKS URL are set
+    pub jwt: Option<JwtSettings>,
+    /// Accepted age of request signature timestamps
+    pub signature_max_age: Duration,
+    /// Allow/deny lists for admin routes, present when any list is set
+    pub admin_ip_filter: Option<IpFilter>,
+    /// CSRF checks; no route group is protected unless configured
+    pub csrf: CsrfPolicy,
+
+    // TLS termination, `None` to serve plain HTTP
+    pub tls: Option<TlsSettings>,
+
+    /// Feature flag defaults, see [`crate::flags`]
+    pub feature_flags: HashMap<Flag, bool>,
+    /// Endpoint groups left out of the routers and the served spec
+    pub endpoints: Endpoints,
+}
+
+/// Every invalid or missing setting found while loading [`Config`].
+#[derive(Debug, thiserror::Error)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid configuration ({} problems):",
+            self.problems.len()
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Defaults {
+    rust_log: &'static str,
+    log_format: &'static str,
+    http2_enabled: bool,
+    http1_keep_alive: bool,
+    http2_keep_alive_timeout_secs: u64,
+    http2_max_concurrent_streams: u32,
+    request_timeout_secs: u64,
+    poll_timeout_secs: u64,
+    load_poll_interval_secs: u64,
+    compression_min_bytes: u16,
+    compression_algorithms: &'static str,
+    database_max_lifetime_secs: u64,
+    database_idle_timeout_secs: u64,
+    database_min_idle: u32,
+    auto_migrate: bool,
+    database_pool_check_interval_secs: u64,
+    database_pool_resize: &'static str,
+    hedged_read_concurrency: u32,
+    read_fallback_concurrency: u32,
+    read_replica_check_interval_secs: u64,
+    startup_retry_initial_backoff_ms: u64,
+    startup_retry_max_backoff_secs: u64,
+    startup_retry_max_wait_secs: u64,
+    import_source_timezone: &'static str,
+    import_dst_policy: &'static str,
+    quantity_negative_policy: &'static str,
+    quantity_max_kwh: f64,
+    quantity_outlier_policy: &'static str,
+    upload_dir: &'static str,
+    upload_storage: &'static str,
+    upload_max_bytes: u64,
+    snapshot_dir: &'static str,
+    snapshot_storage: &'static str,
+    cache_warm_queries: i32,
+    cache_warm_lookback_secs: u64,
+    admission_budget: u64,
+    admission_busy_connections: u32,
+    admission_queue_size: u32,
+    admission_queue_timeout_secs: u64,
+    circuit_breaker_failure_rate: u8,
+    circuit_breaker_window: u32,
+    circuit_breaker_open_secs: u64,
+    circuit_breaker_half_open_probes: u32,
+    query_history_strict: bool,
+    query_history_buffer_size: u32,
+    query_history_batch_size: u32,
+    query_history_flush_interval_secs: u64,
+    query_history_overflow: &'static str,
+    webhook_poll_interval_secs: u64,
+    webhook_request_timeout_secs: u64,
+    webhook_max_attempts: i32,
+    outbox_poll_interval_secs: u64,
+    maintenance_poll_interval_secs: u64,
+    alert_evaluation_interval_secs: u64,
+    notification_poll_interval_secs: u64,
+    notification_request_timeout_secs: u64,
+    notification_max_attempts: i32,
+    health_check_interval_secs: u64,
+    weather_poll_interval_secs: u64,
+    weather_request_timeout_secs: u64,
+    weather_past_days: u32,
+    weather_forecast_days: u32,
+    market_price_currency: &'static str,
+    market_price_poll_interval_secs: u64,
+    market_price_request_timeout_secs: u64,
+    market_price_past_days: u32,
+    shadow_interval_secs: u64,
+    shadow_sample_size: i32,
+    shadow_lookback_secs: u64,
+    require_api_key: bool,
+    jwt_jwks_refresh_secs: u64,
+    signature_max_age_secs: u64,
+}
+
+fn defaults() -> Serialized<Defaults> {
+    Serialized::defaults(Defaults {
+        rust_log: "info",
+        log_format: "json",
+        http2_enabled: true,
+        http1_keep_alive: true,
+        http2_keep_alive_timeout_secs: 20,
+        http2_max_concurrent_streams: 200,
+        request_timeout_secs: 30,
+        poll_timeout_secs: 25,
+        load_poll_interval_secs: 15,
+        compression_min_bytes: 1024,
+        compression_algorithms: "br,zstd,gzip,deflate",
+        database_max_lifetime_secs: 3600,
+        database_idle_timeout_secs: 180,
+        database_min_idle: 0,
+        auto_migrate: true,
+        database_pool_check_interval_secs: 300,
+        database_pool_resize: "log",
+        hedged_read_concurrency: 4,
+        read_fallback_concurrency: 8,
+        read_replica_check_interval_secs: 5,
+        startup_retry_initial_backoff_ms: 500,
+        startup_retry_max_backoff_secs: 10,
+        startup_retry_max_wait_secs: 120,
+        import_source_timezone: "UTC",
+        import_dst_policy: "earlier",
+        quantity_negative_policy: "allow",
+        quantity_max_kwh: 0.0,
+        quantity_outlier_policy: "flag",
+        upload_dir: "uploads",
+        upload_storage: "disk",
+        upload_max_bytes: 1024 * 1024 * 1024,
+        snapshot_dir: "snapshots",
+        snapshot_storage: "disk",
+        cache_warm_queries: 10,
+        cache_warm_lookback_secs: 7 * 86400,
+        admission_budget: 35_040,
+        admission_busy_connections: 16,
+        admission_queue_size: 8,
+        admission_queue_timeout_secs: 5,
+        circuit_breaker_failure_rate: 50,
+        circuit_breaker_window: 20,
+        circuit_breaker_open_secs: 30,
+        circuit_breaker_half_open_probes: 3,
+        query_history_strict: false,
+        query_history_buffer_size: 10_000,
+        query_history_batch_size: 500,
+        query_history_flush_interval_secs: 1,
+        query_history_overflow: "drop",
+        webhook_poll_interval_secs: 5,
+        webhook_request_timeout_secs: 10,
+        webhook_max_attempts: 8,
+        outbox_poll_interval_secs: 1,
+        maintenance_poll_interval_secs: 30,
+        alert_evaluation_interval_secs: 60,
+        notification_poll_interval_secs: 5,
+        notification_request_timeout_secs: 10,
+        notification_max_attempts: 8,
+        health_check_interval_secs: 30,
+        weather_poll_interval_secs: 3600,
+        weather_request_timeout_secs: 30,
+        weather_past_days: 2,
+        weather_forecast_days: 2,
+        market_price_currency: "EUR",
+        market_price_poll_interval_secs: 3600,
+        market_price_request_timeout_secs: 30,
+        market_price_past_days: 2,
+        shadow_interval_secs: 300,
+        shadow_sample_size: 10,
+        shadow_lookback_secs: 86400,
+        require_api_key: false,
+        jwt_jwks_refresh_secs: 300,
+        signature_max_age_secs: 300,
+    })
+}
+
+/// Environment variables as lowercase keys with their raw string values.
+///
+/// Unlike figment's `Env` provider, values are never reinterpreted, so a
+/// token like `007` stays a string. Empty values count as unset.
+struct EnvVars(Vec<(String, String)>);
+
+impl EnvVars {
+    fn from_process() -> Self {
+        Self(std::env::vars().collect())
+    }
+}
+
+impl Provider for EnvVars {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("environment")
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        let dict = self
+            .0
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| {
+                (key.to_ascii_lowercase(), Value::from(value.clone()))
+            })
+            .collect();
+        Ok(Profile::Default.collect(dict))
+    }
+}
+
+/// Settings resolved from Secrets Manager, layered over all other sources.
+struct ResolvedSecrets(Dict);
+
+impl Provider for ResolvedSecrets {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("Secrets Manager")
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        Ok(Profile::Default.collect(self.0.clone()))
+    }
+}
+
+impl Config {
+    /// Load the config without resolving secrets; settings holding a
+    /// Secrets Manager ARN are reported as problems.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_figment(&Self::sources()?)
+    }
+
+    /// Load the config, fetching settings that hold a Secrets Manager ARN
+    /// (through the [`utils::secrets::cache`]).
+    pub async fn load_async() -> Result<Self, ConfigError> {
+        let figment = Self::sources()?;
+        let (secrets, failed) =
+            resolve_secrets(&figment, |name, arn| async move {
+                utils::secrets::resolve(&name, arn)
+                    .await
+                    .map_err(|e| match e {
+                        utils::secrets::SecretLoadError::ArnFetchFailed {
+                            aws_error,
+                            ..
+                        } => aws_error,
+                        e => e.to_string(),
+                    })
+            })
+            .await;
+
+        Self::read(&figment.merge(secrets), failed)
+    }
+
+    /// Connection URL of the `wire` database on `endpoint`, one of the
+    /// read-write or read-only endpoints.
+    pub fn database_url(&self, endpoint: &str) -> String {
+        let Credentials { username, password } = &self.database_credentials;
+        format!("postgresql://{username}:{password}@{endpoint}:5432/wire")
+    }
+
+    fn sources() -> Result<Figment, ConfigError> {
+        // Load .env file if present (useful when running outside docker-compose)
+        match dotenv::dotenv() {
+            Ok(path) => eprintln!("Loaded .env from: {}", path.display()),
+            Err(e) => eprintln!("dotenv warning: {e}"),
+        }
+
+        let mut figment = Figment::from(defaults());
+        if let Some(path) =
+            std::env::var_os(CONFIG_FILE_ENV).filter(|path| !path.is_empty())
+        {
+            figment = figment.merge(config_file(Path::new(&path))?);
+        }
+
+        Ok(figment.merge(EnvVars::from_process()))
+    }
+
+    /// Parse and validate every setting, collecting all problems.
+    pub fn from_figment(figment: &Figment) -> Result<Self, ConfigError> {
+        Self::read(figment, Vec::new())
+    }
+
+    /// Like [`Config::from_figment`], also reporting the secrets in `failed`
+    /// that could not be fetched.
+    fn read(
+        figment: &Figment,
+        failed: Vec<(String, String)>,
+    ) -> Result<Self, ConfigError> {
+        if let Err(e) = figment.data() {
+            return Err(ConfigError {
+                problems: e.into_iter().map(|e| e.to_string()).collect(),
+            });
+        }
+
+        let mut r = Reader {
+            figment,
+            problems: failed
+                .iter()
+                .map(|(key, error)| {
+                    format!(
+                        "{}: failed to fetch secret: {error}",
+                        key.to_ascii_uppercase()
+                    )
+                })
+                .collect(),
+            unresolved: failed.into_iter().map(|(key, _)| key).collect(),
+        };
+
+        let api_service_port = r.required("api_service_port");
+        let (listen_addrs, internal_listen_addrs, grpc_listen_addrs) =
+            r.listeners(api_service_port);
+        let http = r.http();
+        let compression = r.compression();
+        let rust_log = r.required("rust_log");
+        let log_format = r.required("log_format");
+
+        let database_credentials = r.credentials("database_credentials");
+        let database_rw_endpoint = r.required("database_rw_endpoint");
+        let database_ro_endpoint = r.required("database_ro_endpoint");
+        let database_pool = PoolSettings {
+            max_lifetime: r.secs_or_unlimited("database_max_lifetime_secs"),
+            idle_timeout: r.secs_or_unlimited("database_idle_timeout_secs"),
+            min_idle: r
+                .required("database_min_idle")
+                .filter(|&connections| connections > 0),
+        };
+        let request_timeout = r.secs_or_unlimited("request_timeout_secs");
+        let poll_timeout = r.secs("poll_timeout_secs");
+        let load_poll_interval = r.secs("load_poll_interval_secs");
+        let auto_migrate = r.required("auto_migrate");
+        let pool_check_interval =
+            r.secs_or_unlimited("database_pool_check_interval_secs");
+        let on_drift = r
+            .required("database_pool_resize")
+            .unwrap_or(DriftAction::Log);
+        let database_pool_sizing =
+            pool_check_interval.map(|check_interval| PoolSizingSettings {
+                check_interval,
+                on_drift,
+            });
+        let hedged_read_concurrency = r
+            .required::<u32>("hedged_read_concurrency")
+            .unwrap_or_default();
+        let hedged_reads = r
+            .optional::<u64>("hedged_read_delay_ms")
+            .filter(|&ms| ms > 0 && hedged_read_concurrency > 0)
+            .map(|ms| HedgeSettings {
+                delay: Duration::from_millis(ms),
+                concurrency: hedged_read_concurrency,
+            });
+        let replica_check_interval = r.secs("read_replica_check_interval_secs");
+        let read_fallback = r
+            .required::<u32>("read_fallback_concurrency")
+            .filter(|&concurrency| concurrency > 0)
+            .map(|concurrency| FallbackSettings {
+                concurrency,
+                check_interval: replica_check_interval,
+            });
+        let redis_url = r.required::<Url>("redis_url");
+        let startup_retry = RetrySettings {
+            initial_backoff: Duration::from_millis(
+                r.required("startup_retry_initial_backoff_ms")
+                    .unwrap_or_default(),
+            ),
+            max_backoff: r.secs("startup_retry_max_backoff_secs"),
+            max_wait: Duration::from_secs(
+                r.required("startup_retry_max_wait_secs")
+                    .unwrap_or_default(),
+            ),
+        };
+        let energy_readings_xls_file_path =
+            r.required("energy_readings_xls_file_path");
+        let import_source_time = SourceTime {
+            timezone: r
+                .required::<Tz>("import_source_timezone")
+                .unwrap_or_default(),
+            dst: r.required("import_dst_policy").unwrap_or_default(),
+        };
+        let quantity_policy = r.quantity_policy();
+        let uploads = r.uploads();
+        let snapshots = SnapshotSettings {
+            dir: r.required("snapshot_dir").unwrap_or_default(),
+            storage: r.required("snapshot_storage").unwrap_or_default(),
+        };
+        let cache_warmer = WarmerSettings {
+            queries: r.at_least("cache_warm_queries", 0).into(),
+            lookback: r.secs("cache_warm_lookback_secs"),
+        };
+        let admission = AdmissionSettings {
+            budget: r.required("admission_budget").unwrap_or_default(),
+            busy_connections: r
+                .required("admission_busy_connections")
+                .unwrap_or_default(),
+            queue_size: r.required("admission_queue_size").unwrap_or_default(),
+            queue_timeout: r.secs("admission_queue_timeout_secs"),
+        };
+        let circuit_breaker = r.circuit_breaker();
+        let query_history_strict = r.required("query_history_strict");
+        let query_history_writer = WriterSettings {
+            buffer_size: r.at_least("query_history_buffer_size", 1) as u32,
+            batch_size: r.at_least("query_history_batch_size", 1) as u32,
+            flush_interval: r.secs("query_history_flush_interval_secs"),
+            overflow: r
+                .required("query_history_overflow")
+                .unwrap_or(Overflow::Drop),
+        };
+
+        let webhook_dispatcher = DispatcherSettings {
+            poll_interval: r.secs("webhook_poll_interval_secs"),
+            request_timeout: r.secs("webhook_request_timeout_secs"),
+            max_attempts: r.at_least("webhook_max_attempts", 1),
+        };
+        let outbox_relay = RelaySettings {
+            poll_interval: r.secs("outbox_poll_interval_secs"),
+            redis_channel: r.optional("outbox_redis_channel"),
+        };
+        let maintenance_scheduler = SchedulerSettings {
+            poll_interval: r.secs("maintenance_poll_interval_secs"),
+        };
+        let alert_evaluator = EvaluatorSettings {
+            poll_interval: r.secs("alert_evaluation_interval_secs"),
+        };
+        let notification_dispatcher = NotificationSettings {
+            poll_interval: r.secs("notification_poll_interval_secs"),
+            request_timeout: r.secs("notification_request_timeout_secs"),
+            max_attempts: r.at_least("notification_max_attempts", 1),
+        };
+        let smtp = r.smtp();
+        let health_monitor = MonitorSettings {
+            interval: r.secs("health_check_interval_secs"),
+        };
+        let weather = r.weather();
+        let market_prices = r.market_prices();
+        let shadow = r.shadow();
+
+        let require_api_key = r.required("require_api_key");
+        let admin_api_token = r.optional("admin_api_token");
+        let jwt = r.jwt();
+        let signature_max_age = r.secs("signature_max_age_secs");
+        let admin_ip_filter = r.ip_filter();
+        let csrf = r.csrf();
+        let tls = r.tls();
+        let feature_flags = r.feature_flags();
+        let endpoints = r.endpoints();
+
+        match (database_credentials, redis_url) {
+            (Some(database_credentials), Some(redis_url))
+                if r.problems.is_empty() =>
+            {
+                Ok(Self {
+                    api_service_port: api_service_port.unwrap_or_default(),
+                    listen_addrs,
+                    internal_listen_addrs,
+                    grpc_listen_addrs,
+                    http,
+                    compression,
+                    request_timeout,
+                    poll_timeout,
+                    load_poll_interval,
+                    rust_log: rust_log.unwrap_or_default(),
+                    log_format: log_format.unwrap_or_default(),
+                    database_credentials,
+                    database_rw_endpoint: database_rw_endpoint
+                        .unwrap_or_default(),
+                    database_ro_endpoint: database_ro_endpoint
+                        .unwrap_or_default(),
+                    database_pool,
+                    auto_migrate: auto_migrate.unwrap_or_default(),
+                    database_pool_sizing,
+                    hedged_reads,
+                    read_fallback,
+                    redis_url,
+                    startup_retry,
+                    energy_readings_xls_file_path:
+                        energy_readings_xls_file_path.unwrap_or_default(),
+                    import_source_time,
+                    quantity_policy,
+                    uploads,
+                    snapshots,
+                    cache_warmer,
+                    admission,
+                    circuit_breaker,
+                    query_history_strict: query_history_strict
+                        .unwrap_or_default(),
+                    query_history_writer,
+                    webhook_dispatcher,
+                    outbox_relay,
+                    maintenance_scheduler,
+                    alert_evaluator,
+                    notification_dispatcher,
+                    smtp,
+                    health_monitor,
+                    weather,
+                    market_prices,
+                    shadow,
+                    require_api_key: require_api_key.unwrap_or_default(),
+                    admin_api_token,
+                    jwt,
+                    signature_max_age,
+                    admin_ip_filter,
+                    csrf,
+                    tls,
+                    feature_flags,
+                    endpoints,
+                })
+            }
+            _ => Err(ConfigError {
+                problems: r.problems,
+            }),
+        }
+    }
+}
+
+/// Fetch every setting holding a Secrets Manager ARN with `fetch`, returning
+/// the fetched values and the `(key, error)` of those that failed.
+async fn resolve_secrets<F, Fut>(
+    figment: &Figment,
+    fetch: F,
+) -> (ResolvedSecrets, Vec<(String, String)>)
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let mut resolved = Dict::new();
+    let mut failed = Vec::new();
+
+    for key in SETTINGS {
+        let Ok(Value::String(_, value)) = figment.find_value(key) else {
+            continue;
+        };
+        if !utils::secrets::is_secrets_manager_arn(value.trim()) {
+            continue;
+        }
+        match fetch(key.to_ascii_uppercase(), value.trim().to_string()).await {
+            Ok(secret) => {
+                resolved.insert(key.to_string(), Value::from(secret));
+            }
+            Err(e) => failed.push((key.to_string(), e)),
+        }
+    }
+
+    (ResolvedSecrets(resolved), failed)
+}
+
+/// The TOML config file, checked up front so read and syntax errors name it.
+fn config_file(path: &Path) -> Result<Data<Toml>, ConfigError> {
+    let file = Toml::file_exact(path);
+    match file.data() {
+        Ok(_) => Ok(file),
+        Err(e) => Err(ConfigError {
+            problems: vec![format!(
+                "{CONFIG_FILE_ENV} {}: {}",
+                path.display(),
+                e.to_string().trim_end()
+            )],
+        }),
+    }
+}
+
+/// Name of a config source, e.g. `TOML file /etc/wire/config.toml`.
+fn origin(metadata: &Metadata) -> String {
+    match &metadata.source {
+        Some(source) => format!("{} {source}", metadata.name),
+        None => metadata.name.to_string(),
+    }
+}
+
+/// Reads settings from a figment, recording problems instead of stopping at
+/// the first one. Values that fail to parse come back as `None` or a default.
+struct Reader<'a> {
+    figment: &'a Figment,
+    problems: Vec<String>,
+    /// Settings whose secret could not be fetched, already reported
+    unresolved: Vec<String>,
+}
+
+impl Reader<'_> {
+    fn invalid(&mut self, key: &str, reason: impl Display) {
+        let origin = self
+            .figment
+            .find_metadata(key)
+            .map(|metadata| format!(" (from {})", origin(metadata)))
+            .unwrap_or_default();
+        self.problems
+            .push(format!("{}: {reason}{origin}", key.to_ascii_uppercase()));
+    }
+
+    /// The setting as a string, `None` when unset or empty.
+    fn string(&mut self, key: &str) -> Option<String> {
+        let value = self.figment.find_value(key).ok()?;
+        let string = match value {
+            Value::String(_, s) => s,
+            Value::Char(_, c) => c.to_string(),
+            Value::Bool(_, b) => b.to_string(),
+            Value::Num(_, num) => match (num.to_u128(), num.to_i128()) {
+                (Some(n), _) => n.to_string(),
+                (None, Some(n)) => n.to_string(),
+                (None, None) => num.to_f64().unwrap_or_default().to_string(),
+            },
+            Value::Empty(..) => return None,
+            Value::Dict(..) | Value::Array(..) => {
+                self.invalid(key, "expected a single value");
+                return None;
+            }
+        };
+        let string = string.trim().to_string();
+        if self.unresolved.iter().any(|unresolved| unresolved == key) {
+            return None;
+        }
+        if utils::secrets::is_secrets_manager_arn(&string) {
+            self.invalid(
+                key,
+                "holds a Secrets Manager ARN, which only \
+                 `Config::load_async` resolves",
+            );
+            return None;
+        }
+        if string.starts_with("arn:aws:ssm:") {
+            self.invalid(
+                key,
+                "SSM parameters are not supported, use a Secrets Manager ARN",
+            );
+            return None;
+        }
+        (!string.is_empty()).then_some(string)
+    }
+
+    fn optional<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.string(key)?;
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.invalid(key, e);
+                None
+            }
+        }
+    }
+
+    fn required<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if self.figment.find_value(key).is_err() {
+            self.problems
+                .push(format!("{} is required", key.to_ascii_uppercase()));
+            return None;
+        }
+        self.optional(key)
+    }
+
+    /// A positive number of seconds.
+    fn secs(&mut self, key: &str) -> Duration {
+        match self.required::<u64>(key) {
+            Some(0) => {
+                self.invalid(key, "must be greater than 0");
+                Duration::ZERO
+            }
+            secs => Duration::from_secs(secs.unwrap_or_default()),
+        }
+    }
+
+    /// Seconds of a limit, `None` for 0.
+    fn secs_or_unlimited(&mut self, key: &str) -> Option<Duration> {
+        self.required::<u64>(key)
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    fn at_least(&mut self, key: &str, min: i32) -> i32 {
+        let value = self.required::<i32>(key).unwrap_or(min);
+        if value < min {
+            self.invalid(key, format!("must be at least {min}"));
+        }
+        value
+    }
+
+    /// Database credentials, a JSON object string or a table in the file.
+    fn credentials(&mut self, key: &str) -> Option<Credentials> {
+        let Ok(value) = self.figment.find_value(key) else {
+            self.problems
+                .push(format!("{} is required", key.to_ascii_uppercase()));
+            return None;
+        };
+        let parsed = match &value {
+            Value::String(..) => {
+                let json = self.string(key)?;
+                serde_json::from_str(&json).map_err(|e| e.to_string())
+            }
+            _ => value.deserialize().map_err(|e| e.to_string()),
+        };
+        match parsed {
+            Ok(credentials) => Some(credentials),
+            Err(e) => {
+                self.invalid(
+                    key,
+                    format!("expected {{\"username\", \"password\"}}: {e}"),
+                );
+                None
+            }
+        }
+    }
+
+    fn jwt(&mut self) -> Option<JwtSettings> {
+        const KEYS: [&str; 3] = ["jwt_issuer", "jwt_audience", "jwt_jwks_url"];
+
+        let refresh = self.secs("jwt_jwks_refresh_secs");
+        let issuer = self.optional::<String>("jwt_issuer");
+        let audience = self.optional::<String>("jwt_audience");
+        let jwks_url = self.optional::<Url>("jwt_jwks_url");
+
+        let set = KEYS
+            .iter()
+            .filter(|key| self.figment.find_value(key).is_ok())
+            .count();
+        if set != 0 && set != KEYS.len() {
+            self.problems.push(
+                "JWT_ISSUER, JWT_AUDIENCE and JWT_JWKS_URL must be set together"
+                    .to_string(),
+            );
+        }
+
+        Some(JwtSettings {
+            issuer: issuer?,
+            audience: audience?,
+            jwks_url: jwks_url?.to_string(),
+            jwks_refresh_interval: refresh,
+            request_timeout: JWKS_REQUEST_TIMEOUT,
+        })
+    }
+
+    fn smtp(&mut self) -> Option<SmtpSettings> {
+        let url = self.optional::<Url>("smtp_url");
+        let from = self.optional::<Mailbox>("alert_email_from");
+        if let Some(url) = &url
+            && !matches!(url.scheme(), "smtp" | "smtps")
+        {
+            self.invalid("smtp_url", "expected an smtp:// or smtps:// URL");
+            return None;
+        }
+
+        match (url, from) {
+            (Some(url), Some(from)) => Some(SmtpSettings {
+                url: url.to_string(),
+                from,
+            }),
+            (None, None) => None,
+            _ => {
+                self.problems.push(
+                    "SMTP_URL and ALERT_EMAIL_FROM must be set together"
+                        .to_string(),
+                );
+                None
+            }
+        }
+    }
+
+    fn days(&mut self, key: &str, max: u32) -> u32 {
+        let days = self.required::<u32>(key).unwrap_or_default();
+        if days > max {
+            self.invalid(key, format!("must be at most {max}"));
+        }
+        days
+    }
+
+    fn weather(&mut self) -> Option<ImporterSettings> {
+        let poll_interval = self.secs("weather_poll_interval_secs");
+        let request_timeout = self.secs("weather_request_timeout_secs");
+        let past_days = self.days("weather_past_days", MAX_WEATHER_PAST_DAYS);
+        let forecast_days =
+            self.days("weather_forecast_days", MAX_WEATHER_FORECAST_DAYS);
+
+        let api_url = self.optional::<Url>("weather_api_url")?;
+        if !matches!(api_url.scheme(), "http" | "https") {
+            self.invalid("weather_api_url", "expected an http(s) URL");
+            return None;
+        }
+
+        Some(ImporterSettings {
+            api_url,
+            poll_interval,
+            request_timeout,
+            past_days,
+            forecast_days,
+        })
+    }
+
+    fn market_prices(&mut self) -> Option<PriceImporterSettings> {
+        let currency = self
+            .required::<String>("market_price_currency")
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let poll_interval = self.secs("market_price_poll_interval_secs");
+        let request_timeout = self.secs("market_price_request_timeout_secs");
+        let past_days =
+            self.days("market_price_past_days", MAX_MARKET_PRICE_PAST_DAYS);
+        let zones = self
+            .string("market_price_zones")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|zone| !zone.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let Some(api_url) = self.optional::<Url>("market_price_api_url") else {
+            if !zones.is_empty() {
+                self.problems.push(
+                    "MARKET_PRICE_ZONES needs MARKET_PRICE_API_URL".to_string(),
+                );
+            }
+            return None;
+        };
+        if !matches!(api_url.scheme(), "http" | "https") {
+            self.invalid("market_price_api_url", "expected an http(s) URL");
+            return None;
+        }
+        if zones.is_empty() {
+            self.problems.push(
+                "MARKET_PRICE_API_URL needs MARKET_PRICE_ZONES".to_string(),
+            );
+            return None;
+        }
+
+        Some(PriceImporterSettings {
+            api_url,
+            zones,
+            currency,
+            poll_interval,
+            request_timeout,
+            past_days,
+        })
+    }
+
+    fn circuit_breaker(&mut self) -> BreakerSettings {
+        let failure_rate = self
+            .required::<u8>("circuit_breaker_failure_rate")
+            .unwrap_or_default();
+        if failure_rate > 100 {
+            self.invalid("circuit_breaker_failure_rate", "must be at most 100");
+        }
+
+        BreakerSettings {
+            failure_rate,
+            window: self.at_least("circuit_breaker_window", 1) as u32,
+            open_for: self.secs("circuit_breaker_open_secs"),
+            half_open_probes: self
+                .at_least("circuit_breaker_half_open_probes", 1)
+                as u32,
+        }
+    }
+
+    fn shadow(&mut self) -> Option<ShadowSettings> {
+        let interval = self.secs("shadow_interval_secs");
+        let sample_size = self.at_least("shadow_sample_size", 1);
+        let lookback = self.secs("shadow_lookback_secs");
+
+        Some(ShadowSettings {
+            database_endpoint: self.optional("shadow_database_endpoint")?,
+            interval,
+            sample_size,
+            lookback,
+        })
+    }
+
+    fn networks(&mut self, key: &str) -> Vec<ipnet::IpNet> {
+        let list = self.string(key).unwrap_or_default();
+        ip_filter::parse_networks(&list).unwrap_or_else(|e| {
+            self.invalid(key, e);
+            Vec::new()
+        })
+    }
+
+    fn ip_filter(&mut self) -> Option<IpFilter> {
+        let filter = IpFilter {
+            allow: self.networks("admin_ip_allowlist"),
+            deny: self.networks("admin_ip_denylist"),
+            trusted_proxies: self.networks("trusted_proxies"),
+        };
+
+        (!filter.is_empty()).then_some(filter)
+    }
+
+    fn csrf(&mut self) -> CsrfPolicy {
+        let groups = self.string("csrf_protected_routes").unwrap_or_default();
+        let origins = self.string("csrf_trusted_origins").unwrap_or_default();
+
+        CsrfPolicy {
+            groups: csrf::parse_groups(&groups).unwrap_or_else(|e| {
+                self.invalid("csrf_protected_routes", e);
+                Vec::new()
+            }),
+            trusted_origins: csrf::parse_origins(&origins),
+        }
+    }
+
+    fn listen_addrs(&mut self, key: &str) -> Vec<ListenAddr> {
+        let list = self.string(key).unwrap_or_default();
+        listener::parse_addrs(&list).unwrap_or_else(|e| {
+            self.invalid(key, e);
+            Vec::new()
+        })
+    }
+
+    /// Public, internal and gRPC listeners; the public ones default to every
+    /// interface on `port`.
+    fn listeners(
+        &mut self,
+        port: Option<u16>,
+    ) -> (Vec<ListenAddr>, Vec<ListenAddr>, Vec<ListenAddr>) {
+        let mut public = self.listen_addrs("listen_addrs");
+        if public.is_empty()
+            && let Some(port) = port
+        {
+            public.push(ListenAddr::Tcp((Ipv4Addr::UNSPECIFIED, port).into()));
+        }
+
+        let internal = self.listen_addrs("internal_listen_addrs");
+        if let Some(addr) = internal.iter().find(|addr| public.contains(addr)) {
+            self.invalid(
+                "internal_listen_addrs",
+                format!("{addr} is also a public listener"),
+            );
+        }
+
+        let grpc = self.listen_addrs("grpc_listen_addrs");
+        if let Some(addr) = grpc
+            .iter()
+            .find(|addr| public.contains(addr) || internal.contains(addr))
+        {
+            self.invalid(
+                "grpc_listen_addrs",
+                format!("{addr} is also an HTTP listener"),
+            );
+        }
+
+        (public, internal, grpc)
+    }
+
+    fn http(&mut self) -> HttpSettings {
+        let keep_alive_interval =
+            match self.optional::<u64>("http2_keep_alive_interval_secs") {
+                Some(0) => {
+                    self.invalid(
+                        "http2_keep_alive_interval_secs",
+                        "must be greater than 0, or unset to disable pings",
+                    );
+                    None
+                }
+                secs => secs.map(Duration::from_secs),
+            };
+        let max_header_bytes = self.optional::<u32>("http_max_header_bytes");
+        if max_header_bytes.is_some_and(|max| max < MIN_HEADER_BYTES) {
+            self.invalid(
+                "http_max_header_bytes",
+                format!("must be at least {MIN_HEADER_BYTES}"),
+            );
+        }
+        let max_concurrent_streams =
+            self.required::<u32>("http2_max_concurrent_streams");
+        if max_concurrent_streams == Some(0) {
+            self.invalid("http2_max_concurrent_streams", "must be at least 1");
+        }
+
+        HttpSettings {
+            http2: self.required("http2_enabled").unwrap_or(true),
+            http1_keep_alive: self.required("http1_keep_alive").unwrap_or(true),
+            http2_keep_alive_interval: keep_alive_interval,
+            http2_keep_alive_timeout: self
+                .secs("http2_keep_alive_timeout_secs"),
+            http2_max_concurrent_streams: max_concurrent_streams
+                .unwrap_or_default(),
+            max_header_bytes,
+        }
+    }
+
+    fn compression(&mut self) -> CompressionSettings {
+        let min_size = self.required("compression_min_bytes");
+        let algorithms =
+            self.string("compression_algorithms").unwrap_or_default();
+        let excluded = self
+            .string("compression_excluded_content_types")
+            .unwrap_or_default();
+
+        CompressionSettings {
+            min_size: min_size.unwrap_or_default(),
+            algorithms: compression::parse_algorithms(&algorithms)
+                .unwrap_or_else(|e| {
+                    self.invalid("compression_algorithms", e);
+                    Vec::new()
+                }),
+            excluded_content_types: compression::parse_content_types(&excluded),
+        }
+    }
+
+    fn quantity_policy(&mut self) -> QuantityPolicy {
+        let max_kwh = self.required::<f64>("quantity_max_kwh").unwrap_or(0.0);
+        if !(max_kwh >= 0.0 && max_kwh.is_finite()) {
+            self.invalid("quantity_max_kwh", "must be a number of at least 0");
+        }
+        QuantityPolicy {
+            negative: self
+                .required("quantity_negative_policy")
+                .unwrap_or_default(),
+            max_kwh: Some(max_kwh).filter(|&max| max > 0.0),
+            outlier: self
+                .required("quantity_outlier_policy")
+                .unwrap_or_default(),
+        }
+    }
+
+    fn uploads(&mut self) -> UploadSettings {
+        let max_bytes = self.required::<u64>("upload_max_bytes");
+        if max_bytes == Some(0) {
+            self.invalid("upload_max_bytes", "must be at least 1");
+        }
+        UploadSettings {
+            dir: self.required("upload_dir").unwrap_or_default(),
+            storage: self.required("upload_storage").unwrap_or_default(),
+            max_bytes: max_bytes.unwrap_or_default(),
+        }
+    }
+
+    fn endpoints(&mut self) -> Endpoints {
+        let list = self.string("disabled_endpoints").unwrap_or_default();
+        endpoints::parse_disabled(&list).unwrap_or_else(|e| {
+            self.invalid("disabled_endpoints", e);
+            Endpoints::default()
+        })
+    }
+
+    fn feature_flags(&mut self) -> HashMap<Flag, bool> {
+        let list = self.string("feature_flags").unwrap_or_default();
+        flags::parse_defaults(&list).unwrap_or_else(|e| {
+            self.invalid("feature_flags", e);
+            HashMap::new()
+        })
+    }
+
+    fn tls(&mut self) -> Option<TlsSettings> {
+        let cert = self.optional::<PathBuf>("tls_cert_path");
+        let key = self.optional::<PathBuf>("tls_key_path");
+        let client_ca = self.optional::<PathBuf>("tls_client_ca_path");
+
+        match (cert, key) {
+            (Some(cert_path), Some(key_path)) => Some(TlsSettings {
+                cert_path,
+                key_path,
+                client_ca_path: client_ca,
+            }),
+            (None, None) => {
+                if client_ca.is_some() {
+                    self.problems.push(
+                        "TLS_CLIENT_CA_PATH requires TLS_CERT_PATH and \
+                         TLS_KEY_PATH"
+                            .to_string(),
+                    );
+                }
+                None
+            }
+            _ => {
+                self.problems.push(
+                    "TLS_CERT_PATH and TLS_KEY_PATH must be set together"
+                        .to_string(),
+                );
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    /// The defaults with `vars` set as environment variables, for tests.
+    pub(crate) fn from_vars(
+        vars: &[(&str, &str)],
+    ) -> Result<Self, ConfigError> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Self::from_figment(&Figment::from(defaults()).merge(EnvVars(vars)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_loader::DstPolicy;
+    use crate::quantity_policy::QuantityAction;
+    use crate::uploads::Storage;
+
+    const REQUIRED: &[(&str, &str)] = &[
+        ("API_SERVICE_PORT", "50051"),
+        (
+            "DATABASE_CREDENTIALS",
+            r#"{"username": "wire", "password": "secret"}"#,
+        ),
+        ("DATABASE_RW_ENDPOINT", "postgresql-db"),
+        ("DATABASE_RO_ENDPOINT", "postgresql-db"),
+        ("REDIS_URL", "redis://redis:6379"),
+        ("ENERGY_READINGS_XLS_FILE_PATH", "/data/readings.xlsx"),
+    ];
+
+    fn env(vars: &[(&str, &str)]) -> EnvVars {
+        EnvVars(
+            REQUIRED
+                .iter()
+                .chain(vars)
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    fn load(file: &str, vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let figment = Figment::from(defaults())
+            .merge(Toml::string(file))
+            .merge(env(vars));
+        Config::from_figment(&figment)
+    }
+
+    fn problems(figment: &Figment) -> Vec<String> {
+        match Config::from_figment(figment) {
+            Ok(_) => panic!("expected configuration problems"),
+            Err(e) => e.problems,
+        }
+    }
+
+    #[test]
+    fn test_defaults_and_typed_values() {
+        let config = load("", &[("ADMIN_API_TOKEN", "007")]).unwrap();
+
+        assert_eq!(config.api_service_port, 50051);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.redis_url.host_str(), Some("redis"));
+        assert_eq!(config.database_credentials.username, "wire");
+        assert_eq!(config.database_pool, PoolSettings::default());
+        assert!(config.auto_migrate);
+        assert_eq!(config.request_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(
+            config.webhook_dispatcher.poll_interval,
+            Duration::from_secs(5)
+        );
+        assert_eq!(config.outbox_relay.poll_interval, Duration::from_secs(1));
+        assert!(config.outbox_relay.redis_channel.is_none());
+        assert_eq!(config.signature_max_age, Duration::from_secs(300));
+        assert_eq!(config.load_poll_interval, Duration::from_secs(15));
+        assert_eq!(config.admin_api_token.as_deref(), Some("007"));
+        assert!(config.jwt.is_none());
+        assert!(config.admin_ip_filter.is_none());
+        assert!(config.tls.is_none());
+        assert!(config.smtp.is_none());
+        assert_eq!(config.notification_dispatcher.max_attempts, 8);
+        assert_eq!(config.health_monitor.interval, Duration::from_secs(30));
+        assert!(config.weather.is_none());
+        assert!(config.market_prices.is_none());
+        assert!(config.shadow.is_none());
+        assert_eq!(config.cache_warmer.queries, 10);
+        assert_eq!(config.admission.budget, 35_040);
+        assert_eq!(config.circuit_breaker.failure_rate, 50);
+        assert_eq!(config.circuit_breaker.open_for, Duration::from_secs(30));
+        assert!(config.hedged_reads.is_none());
+        assert_eq!(
+            config.read_fallback,
+            Some(FallbackSettings {
+                concurrency: 8,
+                check_interval: Duration::from_secs(5),
+            })
+        );
+        assert_eq!(config.startup_retry.max_wait, Duration::from_secs(120));
+        assert!(!config.query_history_strict);
+        assert_eq!(config.query_history_writer.overflow, Overflow::Drop);
+        assert_eq!(config.endpoints, Endpoints::default());
+        assert_eq!(config.import_source_time, SourceTime::default());
+        assert_eq!(config.quantity_policy.negative, QuantityAction::Allow);
+        assert!(config.quantity_policy.max_kwh.is_none());
+        assert_eq!(
+            config.uploads,
+            UploadSettings {
+                dir: PathBuf::from("uploads"),
+                storage: Storage::Disk,
+                max_bytes: 1 << 30,
+            }
+        );
+        assert_eq!(
+            config.snapshots,
+            SnapshotSettings {
+                dir: PathBuf::from("snapshots"),
+                storage: Storage::Disk,
+            }
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_file_overrides_defaults() {
+        let file = r#"
+            api_service_port = 8080
+            log_format = "pretty"
+            webhook_max_attempts = 3
+            admin_ip_denylist = "203.0.113.0/24"
+            database_max_lifetime_secs = 0
+        "#;
+        let config = load(
+            file,
+            &[
+                ("WEBHOOK_MAX_ATTEMPTS", "5"),
+                ("DATABASE_MIN_IDLE", "4"),
+                ("DATABASE_POOL_RESIZE", "restart"),
+                ("IMPORT_SOURCE_TIMEZONE", "Europe/Sofia"),
+                ("IMPORT_DST_POLICY", "reject"),
+                ("QUANTITY_NEGATIVE_POLICY", "clamp"),
+                ("QUANTITY_MAX_KWH", "500"),
+                ("UPLOAD_STORAGE", "s3://readings/uploads"),
+                ("SNAPSHOT_DIR", "/var/lib/wire/snapshots"),
+                ("HEDGED_READ_DELAY_MS", "200"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(config.api_service_port, 50051);
+        assert_eq!(config.log_format, LogFormat::Pretty);
+        assert_eq!(config.webhook_dispatcher.max_attempts, 5);
+        assert_eq!(config.admin_ip_filter.unwrap().deny.len(), 1);
+        assert!(config.database_pool.max_lifetime.is_none());
+        assert_eq!(config.database_pool.min_idle, Some(4));
+        assert_eq!(
+            config.hedged_reads,
+            Some(HedgeSettings {
+                delay: Duration::from_millis(200),
+                concurrency: 4,
+            })
+        );
+        assert_eq!(
+            config.database_pool_sizing,
+            Some(PoolSizingSettings {
+                check_interval: Duration::from_secs(300),
+                on_drift: DriftAction::Restart,
+            })
+        );
+        assert_eq!(
+            config.import_source_time,
+            SourceTime {
+                timezone: Tz::Europe__Sofia,
+                dst: DstPolicy::Reject,
+            }
+        );
+        assert_eq!(
+            config.quantity_policy,
+            QuantityPolicy {
+                negative: QuantityAction::Clamp,
+                max_kwh: Some(500.0),
+                outlier: QuantityAction::Flag,
+            }
+        );
+        assert_eq!(
+            config.uploads.storage,
+            Storage::S3 {
+                bucket: "readings".to_string(),
+                prefix: "uploads".to_string(),
+            }
+        );
+        assert_eq!(
+            config.snapshots.dir,
+            PathBuf::from("/var/lib/wire/snapshots")
+        );
+    }
+
+    #[test]
+    fn test_listeners() {
+        let config = load("", &[]).unwrap();
+        assert_eq!(
+            config.listen_addrs,
+            vec![ListenAddr::Tcp("0.0.0.0:50051".parse().unwrap())]
+        );
+        assert!(config.internal_listen_addrs.is_empty());
+        assert!(config.grpc_listen_addrs.is_empty());
+        assert!(config.http.http2);
+        assert_eq!(config.http.http2_max_concurrent_streams, 200);
+        assert!(config.http.http2_keep_alive_interval.is_none());
+
+        let config = load(
+            "",
+            &[
+                ("LISTEN_ADDRS", "0.0.0.0:8080,unix:/run/wire/api.sock"),
+                ("INTERNAL_LISTEN_ADDRS", "127.0.0.1:9090"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.listen_addrs.len(), 2);
+        assert_eq!(config.internal_listen_addrs.len(), 1);
+
+        let figment = Figment::from(defaults()).merge(env(&[
+            ("LISTEN_ADDRS", "localhost:8080"),
+            ("INTERNAL_LISTEN_ADDRS", "0.0.0.0:50051"),
+        ]));
+        let problems = problems(&figment);
+        assert_eq!(problems.len(), 2, "{problems:#?}");
+        assert!(problems[0].starts_with("LISTEN_ADDRS:"));
+        assert!(problems[1].contains("is also a public listener"));
+    }
+
+    #[test]
+    fn test_grpc_listeners() {
+        let config =
+            load("", &[("GRPC_LISTEN_ADDRS", "127.0.0.1:50052")]).unwrap();
+        assert_eq!(
+            config.grpc_listen_addrs,
+            vec![ListenAddr::Tcp("127.0.0.1:50052".parse().unwrap())]
+        );
+
+        let figment = Figment::from(defaults())
+            .merge(env(&[("GRPC_LISTEN_ADDRS", "0.0.0.0:50051")]));
+        let problems = problems(&figment);
+        assert_eq!(problems.len(), 1, "{problems:#?}");
+        assert!(problems[0].contains("is also an HTTP listener"));
+    }
+
+    #[test]
+    fn test_http_settings() {
+        let config = load(
+            "",
+            &[
+                ("HTTP2_ENABLED", "false"),
+                ("HTTP2_KEEP_ALIVE_INTERVAL_SECS", "30"),
+                ("HTTP_MAX_HEADER_BYTES", "65536"),
+            ],
+        )
+        .unwrap();
+        assert!(!config.http.http2);
+        assert_eq!(
+            config.http.http2_keep_alive_interval,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(config.http.max_header_bytes, Some(65536));
+
+        let figment = Figment::from(defaults()).merge(env(&[
+            ("HTTP2_KEEP_ALIVE_INTERVAL_SECS", "0"),
+            ("HTTP2_MAX_CONCURRENT_STREAMS", "0"),
+            ("HTTP_MAX_HEADER_BYTES", "1024"),
+        ]));
+        assert_eq!(problems(&figment).len(), 3);
+    }
+
+    #[test]
+    fn test_compression_settings() {
+        let config = load("", &[]).unwrap();
+        assert_eq!(config.compression.min_size, 1024);
+        assert_eq!(config.compression.algorithms.len(), 4);
+        assert!(config.compression.excluded_content_types.is_empty());
+
+        let config = load(
+            "",
+            &[
+                ("COMPRESSION_MIN_BYTES", "0"),
+                ("COMPRESSION_ALGORITHMS", "none"),
+                ("COMPRESSION_EXCLUDED_CONTENT_TYPES", "text/csv,font/"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.compression.min_size, 0);
+        assert!(config.compression.algorithms.is_empty());
+        assert_eq!(
+            config.compression.excluded_content_types,
+            ["text/csv", "font/"]
+        );
+
+        let figment = Figment::from(defaults()).merge(env(&[
+            ("COMPRESSION_MIN_BYTES", "100000"),
+            ("COMPRESSION_ALGORITHMS", "gzip,lzma"),
+        ]));
+        assert_eq!(problems(&figment).len(), 2);
+    }
+
+    #[test]
+    fn test_weather_settings() {
+        let config = load(
+            "",
+            &[
+                ("WEATHER_API_URL", "https://api.open-meteo.com/v1/forecast"),
+                ("WEATHER_FORECAST_DAYS", "7"),
+            ],
+        )
+        .unwrap();
+        let weather = config.weather.unwrap();
+        assert_eq!(weather.api_url.host_str(), Some("api.open-meteo.com"));
+        assert_eq!(weather.poll_interval, Duration::from_secs(3600));
+        assert_eq!(weather.past_days, 2);
+        assert_eq!(weather.forecast_days, 7);
+
+        let figment = Figment::from(defaults()).merge(env(&[
+            ("WEATHER_API_URL", "ftp://weather.example.com"),
+            ("WEATHER_PAST_DAYS", "365"),
+        ]));
+        assert_eq!(problems(&figment).len(), 2);
+    }
+
+    #[test]
+    fn test_market_price_settings() {
+        let config = load(
+            "",
+            &[
+                ("MARKET_PRICE_API_URL", "https://prices.example.com/api"),
+                ("MARKET_PRICE_ZONES", "SE3, NO1,"),
+            ],
+        )
+        .unwrap();
+        let prices = config.market_prices.unwrap();
+        assert_eq!(prices.zones, ["SE3", "NO1"]);
+        assert_eq!(prices.currency, "EUR");
+        assert_eq!(prices.past_days, 2);
+
+        let figment = Figment::from(defaults()).merge(env(&[(
+            "MARKET_PRICE_API_URL",
+            "https://prices.example.com/api",
+        )]));
+        assert_eq!(
+            problems(&figment),
+            ["MARKET_PRICE_API_URL needs MARKET_PRICE_ZONES"]
+        );
+    }
+
+    #[test]
+    fn test_shadow_settings() {
+        let config = load(
+            "",
+            &[
+                ("SHADOW_DATABASE_ENDPOINT", "replica.internal"),
+                ("SHADOW_SAMPLE_SIZE", "25"),
+            ],
+        )
+        .unwrap();
+        let shadow = config.shadow.unwrap();
+        assert_eq!(shadow.database_endpoint, "replica.internal");
+        assert_eq!(shadow.sample_size, 25);
+        assert_eq!(shadow.interval, Duration::from_secs(300));
+
+        let figment = Figment::from(defaults())
+            .merge(env(&[("SHADOW_SAMPLE_SIZE", "0")]));
+        assert_eq!(
+            problems(&figment),
+            ["SHADOW_SAMPLE_SIZE: must be at least 1 (from environment)"]
+        );
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let figment = Figment::from(defaults()).merge(EnvVars(
+            [
+                ("API_SERVICE_PORT", "70000"),
+                ("LOG_FORMAT", "text"),
+                ("DATABASE_CREDENTIALS", "username=wire"),
+                ("REDIS_URL", "not a url"),
+                ("WEBHOOK_POLL_INTERVAL_SECS", "0"),
+                ("JWT_ISSUER", "https://auth.example.com/"),
+                ("ADMIN_IP_ALLOWLIST", "10.0.0.0/33"),
+                ("TLS_KEY_PATH", "/tls/server.key"),
+                ("ALERT_EMAIL_FROM", "alerts@example.com"),
+                ("DISABLED_ENDPOINTS", "ingestion,reports"),
+                ("IMPORT_SOURCE_TIMEZONE", "Europe/Sofiya"),
+                ("QUANTITY_OUTLIER_POLICY", "drop"),
+                ("UPLOAD_STORAGE", "ftp://readings"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        ));
+        let problems = problems(&figment);
+
+        let keys = [
+            "API_SERVICE_PORT:",
+            "LOG_FORMAT:",
+            "DATABASE_CREDENTIALS:",
+            "DATABASE_RW_ENDPOINT is required",
+            "DATABASE_RO_ENDPOINT is required",
+            "REDIS_URL:",
+            "ENERGY_READINGS_XLS_FILE_PATH is required",
+            "WEBHOOK_POLL_INTERVAL_SECS:",
+            "JWT_ISSUER, JWT_AUDIENCE and JWT_JWKS_URL",
+            "ADMIN_IP_ALLOWLIST:",
+            "TLS_CERT_PATH and TLS_KEY_PATH",
+            "SMTP_URL and ALERT_EMAIL_FROM",
+            "DISABLED_ENDPOINTS:",
+            "IMPORT_SOURCE_TIMEZONE:",
+            "QUANTITY_OUTLIER_POLICY:",
+            "UPLOAD_STORAGE:",
+        ];
+        for key in keys {
+            assert!(
+                problems.iter().any(|p| p.starts_with(key)),
+                "missing {key} in {problems:#?}"
+            );
+        }
+        assert_eq!(problems.len(), keys.len());
+        assert!(problems[0].ends_with("(from environment)"));
+    }
+
+    #[test]
+    fn test_unreadable_file_is_reported() {
+        let Err(e) = config_file(Path::new("/nonexistent/wire.toml")) else {
+            panic!("expected the missing file to be reported");
+        };
+        assert_eq!(e.problems.len(), 1);
+        assert!(
+            e.problems[0].starts_with("CONFIG_FILE /nonexistent/wire.toml:"),
+            "{:?}",
+            e.problems
+        );
+
+        let figment = Figment::from(defaults())
+            .merge(Toml::string("api_service_port = "))
+            .merge(env(&[]));
+        assert_eq!(problems(&figment).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_secret_arns_are_resolved() {
+        const ARN: &str = "arn:aws:secretsmanager:eu-west-1:123:secret:wire";
+        let figment = Figment::from(defaults()).merge(env(&[
+            ("DATABASE_CREDENTIALS", ARN),
+            (
+                "ADMIN_API_TOKEN",
+                "arn:aws:secretsmanager:eu-west-1:123:secret:x",
+            ),
+            (
+                "REDIS_URL",
+                "arn:aws:secretsmanager:eu-west-1:123:secret:redis",
+            ),
+        ]));
+
+        let unresolved = problems(&figment);
+        assert_eq!(unresolved.len(), 3);
+        assert!(unresolved[0].contains("Config::load_async"));
+
+        let (secrets, failed) =
+            resolve_secrets(&figment, |name, arn| async move {
+                match name.as_str() {
+                    "DATABASE_CREDENTIALS" if arn == ARN => {
+                        Ok(r#"{"username": "wire", "password": "rotated"}"#
+                            .into())
+                    }
+                    "REDIS_URL" => Ok("redis://cache:6379".into()),
+                    _ => Err("AccessDeniedException".into()),
+                }
+            })
+            .await;
+        assert_eq!(failed.len(), 1);
+
+        let figment = figment.merge(secrets);
+        let Err(e) = Config::read(&figment, failed.clone()) else {
+            panic!("expected the failed secret to be reported");
+        };
+        assert_eq!(
+            e.problems,
+            vec![
+                "ADMIN_API_TOKEN: failed to fetch secret: AccessDeniedException"
+            ]
+        );
+
+        let figment = figment.merge(EnvVars(vec![(
+            "ADMIN_API_TOKEN".to_string(),
+            "literal".to_string(),
+        )]));
+        let config = Config::from_figment(&figment).unwrap_or_else(|e| {
+            panic!("{e}");
+        });
+        assert_eq!(config.database_credentials.password, "rotated");
+        assert_eq!(config.redis_url.host_str(), Some("cache"));
+    }
+}
@@ -1,69 +1,781 @@
-use bigdecimal::BigDecimal;
-use chrono::{TimeZone, Utc};
+//! Imports of readings from Excel and CSV files.
+//!
+//! A source is a file, a directory of `.xlsx` and `.csv` files or a file name
+//! pattern, for customers delivering a file per month. Each file is
+//! imported in its own transaction and recorded as an import run, which
+//! startup uses to skip the files already imported.
+use anyhow::Context;
+use bigdecimal::{BigDecimal, ParseBigDecimalError};
+use chrono::{
+    DateTime, LocalResult, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use diesel_async::AsyncConnection;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use excel_client::models::Record;
+use postgres_models::models::DEFAULT_TENANT;
 use postgres_models::models::energy_readings::{
-    EnergyReading, NewEnergyReading,
+    EnergyReading, NewEnergyReading, reading_source,
+};
+use postgres_models::models::import_runs::{
+    ImportRun, NewImportRun, RunCounts,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::notifications;
+use crate::outbox;
+use crate::quantity_policy::{QuantityCounts, QuantityPolicy};
+use crate::webhooks::WebhookEvent;
 
 const SHEET_NAME: &str = "Sheet1";
 const HEADERS: &[&str] = &["Time (UTC)", "Quantity kWh"];
 const BATCH_SIZE: usize = 1000;
 
+/// Outcome of an import; readings already stored are skipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    pub total: usize,
+    /// Readings whose time the [`DstPolicy`] decided
+    pub dst_adjusted: usize,
+    /// Readings the [`QuantityPolicy`] dropped, clamped or flagged
+    pub quantities: QuantityCounts,
+}
+
+/// How local times repeated or skipped by a DST change are read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DstPolicy {
+    /// The first of repeated times; skipped times with the offset in effect
+    /// before the change, as if the clock had not moved yet
+    #[default]
+    Earlier,
+    /// The second of repeated times; skipped times with the offset in
+    /// effect after the change
+    Later,
+    /// Fail the file
+    Reject,
+}
+
+impl DstPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DstPolicy::Earlier => "earlier",
+            DstPolicy::Later => "later",
+            DstPolicy::Reject => "reject",
+        }
+    }
+}
+
+impl FromStr for DstPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "earlier" => Ok(DstPolicy::Earlier),
+            "later" => Ok(DstPolicy::Later),
+            "reject" => Ok(DstPolicy::Reject),
+            other => {
+                Err(format!("expected earlier, later or reject, got `{other}`"))
+            }
+        }
+    }
+}
+
+/// Timezone the times of source files are in, despite their `Time (UTC)`
+/// header, and how DST changes are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceTime {
+    pub timezone: Tz,
+    pub dst: DstPolicy,
+}
+
+impl Default for SourceTime {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            dst: DstPolicy::default(),
+        }
+    }
+}
+
+impl SourceTime {
+    /// The instant of the local time `local`, and whether the DST policy
+    /// decided it.
+    pub fn to_utc(
+        &self,
+        local: NaiveDateTime,
+    ) -> Result<(DateTime<Utc>, bool), String> {
+        let offset = match self.timezone.offset_from_local_datetime(&local) {
+            LocalResult::Single(offset) => {
+                return Ok((utc(local, offset), false));
+            }
+            LocalResult::Ambiguous(first, second) => match self.dst {
+                DstPolicy::Earlier => first,
+                DstPolicy::Later => second,
+                DstPolicy::Reject => {
+                    return Err(format!(
+                        "{local} occurs twice in {}",
+                        self.timezone
+                    ));
+                }
+            },
+            // DST changes are far more than a day apart
+            LocalResult::None => match self.dst {
+                DstPolicy::Earlier => self
+                    .timezone
+                    .offset_from_utc_datetime(&(local - TimeDelta::days(1))),
+                DstPolicy::Later => self
+                    .timezone
+                    .offset_from_utc_datetime(&(local + TimeDelta::days(1))),
+                DstPolicy::Reject => {
+                    return Err(format!(
+                        "{local} does not exist in {}",
+                        self.timezone
+                    ));
+                }
+            },
+        };
+        Ok((utc(local, offset), true))
+    }
+}
+
+fn utc(local: NaiveDateTime, offset: impl Offset) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&(local - offset.fix()))
+}
+
+/// A quantity in kWh as stored, with the 4 decimal places of
+/// `energy_readings.quantity_kwh`.
+pub fn kwh_decimal(kwh: f64) -> Result<BigDecimal, ParseBigDecimalError> {
+    BigDecimal::from_str(&format!("{kwh:.4}"))
+}
+
+/// Outcome of importing each file of a source, in the order imported.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub files: Vec<FileImport>,
+}
+
+/// Outcome of importing one file, recorded as an import run.
+#[derive(Debug)]
+pub struct FileImport {
+    pub file: PathBuf,
+    pub run_id: Uuid,
+    /// The summary, or why the import failed
+    pub result: Result<ImportSummary, String>,
+}
+
+impl ImportReport {
+    /// Readings of the files imported successfully.
+    pub fn summary(&self) -> ImportSummary {
+        self.files
+            .iter()
+            .filter_map(|file| file.result.as_ref().ok())
+            .fold(ImportSummary::default(), |sum, summary| ImportSummary {
+                inserted: sum.inserted + summary.inserted,
+                total: sum.total + summary.total,
+                dst_adjusted: sum.dst_adjusted + summary.dst_adjusted,
+                quantities: sum.quantities + summary.quantities,
+            })
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &FileImport> {
+        self.files.iter().filter(|file| file.result.is_err())
+    }
+
+    /// The report, or an error naming the files that failed.
+    pub fn into_result(self) -> anyhow::Result<Self> {
+        let failed = self
+            .failed()
+            .map(|file| {
+                let error = file.result.as_ref().err().map(String::as_str);
+                format!(
+                    "{}: {}",
+                    file.file.display(),
+                    error.unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>();
+        if failed.is_empty() {
+            Ok(self)
+        } else {
+            anyhow::bail!("Failed to import {}", failed.join("; "))
+        }
+    }
+}
+
+/// Files of `source`: the file itself, the readings files of a directory,
+/// or the files matching a pattern like `deliveries/readings-*.xlsx`, with
+/// `*` and `?` in the file name only. Sorted by name.
+pub fn resolve_files(source: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if source.is_file() {
+        return Ok(vec![source.to_path_buf()]);
+    }
+    let (dir, pattern) = if source.is_dir() {
+        (source, None)
+    } else {
+        let pattern = source
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| name.contains(['*', '?']))
+            .with_context(|| format!("{} does not exist", source.display()))?;
+        let dir = source
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        (dir, Some(pattern))
+    };
+
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let selected = match pattern {
+            Some(pattern) => wildcard_match(pattern, name),
+            None => !name.starts_with(['.', '~']) && is_readings_file(&path),
+        };
+        if selected && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    anyhow::ensure!(
+        !files.is_empty(),
+        "No readings files found at {}",
+        source.display()
+    );
+    Ok(files)
+}
+
+/// Whether `path` names an Excel workbook or a CSV file.
+pub fn is_readings_file(path: &Path) -> bool {
+    is_workbook(path) || is_csv(path)
+}
+
+fn has_extension(path: &Path, wanted: &str) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(wanted))
+}
+
+fn is_workbook(path: &Path) -> bool {
+    has_extension(path, "xlsx")
+}
+
+fn is_csv(path: &Path) -> bool {
+    has_extension(path, "csv")
+}
+
+/// Whether `name` matches `pattern`, `*` standing for any characters and
+/// `?` for one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    // Where the last `*` was in the pattern, and the name it matched up to
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` match one more character
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Load the default tenant's readings at startup from the files of
+/// `source`, skipping those already imported.
 pub async fn load_energy_readings(
-    file_path: &str,
+    source: &Path,
+    source_time: SourceTime,
+    quantities: QuantityPolicy,
     pool: &postgres_models::connection::Pool,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<ImportReport> {
+    let files = resolve_files(source)?;
+    let names = files
+        .iter()
+        .map(|file| file.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
     let mut conn = pool.get().await.map_err(|e| {
         anyhow::anyhow!("Failed to get DB connection for data loading: {e}")
     })?;
+    let completed =
+        ImportRun::completed_files(DEFAULT_TENANT, &names, &mut conn).await?;
+    drop(conn);
 
-    let existing_count = EnergyReading::count(&mut conn).await?;
-    if existing_count > 0 {
+    let pending = files
+        .into_iter()
+        .filter(|file| {
+            !completed.contains(&file.to_string_lossy().into_owned())
+        })
+        .collect::<Vec<_>>();
+    if pending.is_empty() {
         tracing::info!(
-            count = existing_count,
+            files = completed.len(),
             "Energy readings already loaded, skipping import"
         );
-        return Ok(());
+        return Ok(ImportReport::default());
+    }
+    import_files(pending, DEFAULT_TENANT, source_time, quantities, pool)
+        .await?
+        .into_result()
+}
+
+/// Import every reading of the files of `source` for `tenant`, see
+/// [`import_files`].
+pub async fn import_energy_readings(
+    source: &Path,
+    tenant: &str,
+    source_time: SourceTime,
+    quantities: QuantityPolicy,
+    pool: &postgres_models::connection::Pool,
+) -> anyhow::Result<ImportReport> {
+    let files = resolve_files(source)?;
+    import_files(files, tenant, source_time, quantities, pool).await
+}
+
+/// Import the readings of `files` for `tenant` in chronological order,
+/// that of their earliest readings, each in its own transaction and
+/// import run. A file failing does not stop the others; the tenant's
+/// channels subscribed to `import_failed` are notified of it. Times are
+/// read as local times of `source_time`, and quantities checked against
+/// `quantities`.
+pub async fn import_files(
+    files: Vec<PathBuf>,
+    tenant: &str,
+    source_time: SourceTime,
+    quantities: QuantityPolicy,
+    pool: &postgres_models::connection::Pool,
+) -> anyhow::Result<ImportReport> {
+    let mut parsed = files
+        .into_iter()
+        .map(|file| {
+            let readings =
+                read_readings(&file, tenant, source_time, quantities);
+            (file, readings)
+        })
+        .collect::<Vec<_>>();
+    // Unreadable files first, failing before any other is imported
+    parsed.sort_by_cached_key(|(file, readings)| {
+        let earliest = readings.as_ref().ok().and_then(|(readings, _)| {
+            readings.iter().map(|reading| reading.reading_time).min()
+        });
+        (earliest, file.clone())
+    });
+
+    let mut report = ImportReport::default();
+    for (file, readings) in parsed {
+        let path = file.to_string_lossy().into_owned();
+        let mut conn = pool.get().await.map_err(|e| {
+            anyhow::anyhow!("Failed to get DB connection for data loading: {e}")
+        })?;
+        let run = ImportRun::start(
+            NewImportRun {
+                tenant_id: tenant,
+                file: &path,
+                source_timezone: source_time.timezone.name(),
+                dst_policy: source_time.dst.as_str(),
+            },
+            &mut conn,
+        )
+        .await?;
+        drop(conn);
+
+        let result = match readings {
+            Ok((readings, summary)) => {
+                import(&path, readings, summary, tenant, pool).await
+            }
+            Err(e) => Err(e),
+        };
+        let mut conn = pool.get().await.map_err(|e| {
+            anyhow::anyhow!("Failed to get DB connection for data loading: {e}")
+        })?;
+        let result = match result {
+            Ok(summary) => {
+                let counts = RunCounts {
+                    inserted: summary.inserted,
+                    total: summary.total,
+                    dst_adjusted: summary.dst_adjusted,
+                    rejected: summary.quantities.rejected,
+                    clamped: summary.quantities.clamped,
+                    flagged: summary.quantities.flagged,
+                };
+                ImportRun::complete(run.id, counts, &mut conn).await?;
+                Ok(summary)
+            }
+            Err(e) => {
+                let error = format!("{e:#}");
+                tracing::error!(file = %path, tenant, "Import failed: {error}");
+                ImportRun::fail(run.id, &error, &mut conn).await?;
+                drop(conn);
+                notifications::notify_import_failed(
+                    pool, tenant, &path, &error,
+                )
+                .await;
+                Err(error)
+            }
+        };
+        report.files.push(FileImport {
+            file,
+            run_id: run.id,
+            result,
+        });
     }
 
-    tracing::info!(file = %file_path, "Loading energy readings from Excel");
+    let summary = report.summary();
+    tracing::info!(
+        files = report.files.len(),
+        failed = report.failed().count(),
+        inserted = summary.inserted,
+        total = summary.total,
+        dst_adjusted = summary.dst_adjusted,
+        rejected = summary.quantities.rejected,
+        clamped = summary.quantities.clamped,
+        flagged = summary.quantities.flagged,
+        "Energy readings files imported"
+    );
+    Ok(report)
+}
 
-    let path = PathBuf::from(file_path);
-    let mut client = excel_client::ExcelDataReaderClient::new(path)?;
-    let records = client.read_worksheet_data(SHEET_NAME, HEADERS)?;
+/// The readings of an Excel or CSV file to store for `tenant`, and the
+/// summary of those read, none inserted yet.
+fn read_readings(
+    file: &Path,
+    tenant: &str,
+    source_time: SourceTime,
+    quantities: QuantityPolicy,
+) -> anyhow::Result<(Vec<NewEnergyReading>, ImportSummary)> {
+    tracing::info!(
+        file = %file.display(),
+        tenant,
+        timezone = %source_time.timezone,
+        "Loading energy readings"
+    );
+
+    let records = if is_csv(file) {
+        read_csv(file)?
+    } else {
+        let mut client = excel_client::ExcelDataReaderClient::new(file.into())?;
+        client.read_worksheet_data(SHEET_NAME, HEADERS)?
+    };
 
-    tracing::info!(records = records.len(), "Parsed records from Excel");
+    tracing::info!(records = records.len(), "Parsed records");
 
     let mut new_readings = Vec::with_capacity(records.len());
+    let mut summary = ImportSummary {
+        total: records.len(),
+        ..ImportSummary::default()
+    };
     for record in &records {
-        let reading_time = Utc.from_utc_datetime(&record.time);
-        let quantity_kwh = BigDecimal::from_str(&format!(
-            "{:.4}",
-            record.quantity
-        ))
-        .map_err(|e| {
+        let (reading_time, adjusted) = source_time
+            .to_utc(record.time)
+            .map_err(|e| anyhow::anyhow!("Invalid time: {e}"))?;
+        summary.dst_adjusted += usize::from(adjusted);
+        let Some(checked) =
+            quantities.check(record.quantity, &mut summary.quantities)
+        else {
+            continue;
+        };
+        let quantity_kwh = kwh_decimal(checked.kwh).map_err(|e| {
             anyhow::anyhow!("Invalid quantity '{}': {e}", record.quantity)
         })?;
 
         new_readings.push(NewEnergyReading {
             reading_time,
             quantity_kwh,
+            tenant_id: tenant.to_string(),
+            plant_id: None,
+            quality_code: checked.quality_code.map(str::to_string),
+            source: reading_source::FILE.to_string(),
         });
     }
+    Ok((new_readings, summary))
+}
+
+/// Records of a CSV file with the columns of the Excel sheet, times as
+/// `2025-01-01 00:00:00` or `2025-01-01T00:00:00`.
+fn read_csv(file: &Path) -> anyhow::Result<Vec<Record>> {
+    let mut reader = csv::Reader::from_path(file)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.trim() == name)
+            .with_context(|| format!("Missing header: {name}"))
+    };
+    let (time_col, qty_col) = (column(HEADERS[0])?, column(HEADERS[1])?);
 
-    let mut total_inserted = 0usize;
-    for chunk in new_readings.chunks(BATCH_SIZE) {
-        let inserted =
-            EnergyReading::bulk_insert(chunk.to_vec(), &mut conn).await?;
-        total_inserted += inserted;
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row?;
+        let time = row.get(time_col).unwrap_or_default().trim();
+        let time = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(time, format).ok())
+            .with_context(|| format!("Invalid date: {time:?}"))?;
+        let quantity = row.get(qty_col).unwrap_or_default().trim();
+        let quantity = quantity
+            .parse()
+            .with_context(|| format!("Invalid float: {quantity:?}"))?;
+        records.push(Record { time, quantity });
     }
+    Ok(records)
+}
+
+/// Store `new_readings`, read as `summary` tells.
+async fn import(
+    file_path: &str,
+    new_readings: Vec<NewEnergyReading>,
+    summary: ImportSummary,
+    tenant: &str,
+    pool: &postgres_models::connection::Pool,
+) -> anyhow::Result<ImportSummary> {
+    let mut conn = pool.get().await.map_err(|e| {
+        anyhow::anyhow!("Failed to get DB connection for data loading: {e}")
+    })?;
+
+    let total = summary.total;
+    let total_inserted = conn
+        .transaction::<_, diesel::result::Error, _>(move |conn| {
+            async move {
+                let mut total_inserted = 0usize;
+                for chunk in new_readings.chunks(BATCH_SIZE) {
+                    total_inserted +=
+                        EnergyReading::bulk_insert(chunk.to_vec(), conn)
+                            .await?;
+                }
+                let event_data = serde_json::json!({
+                    "file": file_path,
+                    "tenant": tenant,
+                    "inserted": total_inserted,
+                    "total": total,
+                });
+                outbox::record(
+                    WebhookEvent::ImportCompleted,
+                    tenant,
+                    event_data,
+                    conn,
+                )
+                .await?;
+                Ok(total_inserted)
+            }
+            .scope_boxed()
+        })
+        .await?;
 
     tracing::info!(
+        file = %file_path,
         inserted = total_inserted,
-        total = new_readings.len(),
+        total,
         "Energy readings loaded into database"
     );
 
-    Ok(())
+    Ok(ImportSummary {
+        inserted: total_inserted,
+        ..summary
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_wildcards() {
+        assert!(wildcard_match("*.xlsx", "readings-2025-01.xlsx"));
+        assert!(wildcard_match(
+            "readings-2025-0?.xlsx",
+            "readings-2025-01.xlsx"
+        ));
+        assert!(wildcard_match("r*-*-*.xlsx", "readings-2025-01.xlsx"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("*.xlsx", "readings.csv"));
+        assert!(!wildcard_match("readings-?.xlsx", "readings-10.xlsx"));
+        assert!(!wildcard_match("readings", "readings.xlsx"));
+    }
+
+    #[test]
+    fn test_resolves_directories_and_patterns() {
+        let dir = std::env::temp_dir()
+            .join(format!("wire-import-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("archive")).unwrap();
+        for name in [
+            "readings-2025-02.xlsx",
+            "readings-2025-01.xlsx",
+            "notes.txt",
+            "~$readings-2025-01.xlsx",
+        ] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let names = |files: Vec<PathBuf>| {
+            files
+                .iter()
+                .map(|file| file.file_name().unwrap().to_string_lossy().into())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            names(resolve_files(&dir).unwrap()),
+            ["readings-2025-01.xlsx", "readings-2025-02.xlsx"]
+        );
+        assert_eq!(
+            names(resolve_files(&dir.join("*-02.xlsx")).unwrap()),
+            ["readings-2025-02.xlsx"]
+        );
+        assert_eq!(
+            names(resolve_files(&dir.join("notes.txt")).unwrap()),
+            ["notes.txt"]
+        );
+        assert!(resolve_files(&dir.join("*.csv")).is_err());
+        assert!(resolve_files(&dir.join("missing.xlsx")).is_err());
+        assert!(resolve_files(&dir.join("archive")).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_reads_csv_files() {
+        let file = std::env::temp_dir()
+            .join(format!("wire-import-{}.csv", Uuid::new_v4()));
+        std::fs::write(
+            &file,
+            "Time (UTC),Quantity kWh\n\
+             2025-01-01 00:00:00,12.5\n\
+             2025-01-01T01:00:00,-1\n",
+        )
+        .unwrap();
+
+        let (readings, summary) = read_readings(
+            &file,
+            "acme",
+            SourceTime::default(),
+            QuantityPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(summary.total, 2);
+        assert_eq!(
+            readings
+                .iter()
+                .map(|reading| (
+                    reading.reading_time.to_rfc3339(),
+                    reading.quantity_kwh.to_string()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "2025-01-01T00:00:00+00:00".to_string(),
+                    "12.5000".to_string()
+                ),
+                (
+                    "2025-01-01T01:00:00+00:00".to_string(),
+                    "-1.0000".to_string()
+                ),
+            ]
+        );
+
+        std::fs::write(&file, "Time,Quantity kWh\n").unwrap();
+        assert!(
+            read_readings(
+                &file,
+                "acme",
+                SourceTime::default(),
+                QuantityPolicy::default()
+            )
+            .is_err()
+        );
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_reads_local_times_across_dst_changes() {
+        let local = |s: &str| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+        };
+        let at = |s: &str| local(s).and_utc();
+        let berlin = |dst| SourceTime {
+            timezone: Tz::Europe__Berlin,
+            dst,
+        };
+
+        // Winter and summer time
+        assert_eq!(
+            berlin(DstPolicy::Reject).to_utc(local("2025-01-15 12:00")),
+            Ok((at("2025-01-15 11:00"), false))
+        );
+        assert_eq!(
+            berlin(DstPolicy::Reject).to_utc(local("2025-07-15 12:00")),
+            Ok((at("2025-07-15 10:00"), false))
+        );
+        // 02:30 is repeated when clocks go back on 26 October
+        assert_eq!(
+            berlin(DstPolicy::Earlier).to_utc(local("2025-10-26 02:30")),
+            Ok((at("2025-10-26 00:30"), true))
+        );
+        assert_eq!(
+            berlin(DstPolicy::Later).to_utc(local("2025-10-26 02:30")),
+            Ok((at("2025-10-26 01:30"), true))
+        );
+        // and skipped when they go forward on 30 March
+        assert_eq!(
+            berlin(DstPolicy::Earlier).to_utc(local("2025-03-30 02:30")),
+            Ok((at("2025-03-30 01:30"), true))
+        );
+        assert_eq!(
+            berlin(DstPolicy::Later).to_utc(local("2025-03-30 02:30")),
+            Ok((at("2025-03-30 00:30"), true))
+        );
+        for time in ["2025-10-26 02:30", "2025-03-30 02:30"] {
+            assert!(berlin(DstPolicy::Reject).to_utc(local(time)).is_err());
+        }
+        assert_eq!(
+            SourceTime::default().to_utc(local("2025-03-30 02:30")),
+            Ok((at("2025-03-30 02:30"), false))
+        );
+    }
+
+    #[test]
+    fn test_sums_successful_files() {
+        let file = |name: &str, result| FileImport {
+            file: PathBuf::from(name),
+            run_id: Uuid::nil(),
+            result,
+        };
+        let summary = |inserted, total| ImportSummary {
+            inserted,
+            total,
+            ..ImportSummary::default()
+        };
+        let report = ImportReport {
+            files: vec![
+                file("a.xlsx", Ok(summary(10, 12))),
+                file("b.xlsx", Err("Invalid date".to_string())),
+                file("c.xlsx", Ok(summary(5, 5))),
+            ],
+        };
+
+        assert_eq!(report.summary(), summary(15, 17));
+        assert_eq!(report.failed().count(), 1);
+        let error = report.into_result().unwrap_err().to_string();
+        assert_eq!(error, "Failed to import b.xlsx: Invalid date");
+    }
 }
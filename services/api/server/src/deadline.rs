@@ -0,0 +1,191 @@
+//! Request deadlines propagated to database queries.
+//!
+//! Every `/v1` request gets a deadline `REQUEST_TIMEOUT_SECS` after it
+//! starts, or sooner when the client sends `X-Request-Timeout` with fewer
+//! seconds (decimals allowed); a timeout of 0 sets none unless the client
+//! asks for one. Within the deadline, [`with_connection`] stops waiting for
+//! a connection and cancels queries still running once it passes, so a
+//! client that gave up no longer holds a pool slot. The request then fails
+//! with `504 request_timeout`, at the latest [`CANCEL_GRACE`] after the
+//! deadline.
+//!
+//! [`with_connection`]: postgres_models::connection::with_connection
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use postgres_models::connection::with_deadline;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::shared::extractors::header::{CustomHeader, Header};
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::{ErrorRecorder, IntoWireV1Error};
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+/// Header clients set to give up on a request sooner.
+pub const REQUEST_TIMEOUT_HEADER: &str = "x-request-timeout";
+/// How long a request may take past its deadline to unwind its cancelled
+/// queries before it is dropped.
+pub const CANCEL_GRACE: Duration = Duration::from_secs(1);
+
+const HANDLER_NAME: &str = "deadline";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid X-Request-Timeout header: {0}")]
+    InvalidTimeout(String),
+    #[error("Request did not complete within {0:?}")]
+    TimedOut(Duration),
+}
+
+impl IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::InvalidTimeout(_) => WireV1Error::bad_request(
+                "Invalid request timeout".to_string(),
+                vec![WireV1Detail {
+                    field: Some(REQUEST_TIMEOUT_HEADER.to_string()),
+                    code: "invalid_request_timeout".to_string(),
+                    message: self.to_string(),
+                    suggestion: "Send a positive number of seconds".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::TimedOut(_) => WireV1Error::gateway_timeout(
+                "Request timed out".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "request_timeout".to_string(),
+                    message: self.to_string(),
+                    suggestion: "Narrow the request or allow it more time"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+/// Timeout a client asks for with `X-Request-Timeout`.
+struct RequestTimeout(Duration);
+
+impl CustomHeader for RequestTimeout {
+    const NAME: &'static str = REQUEST_TIMEOUT_HEADER;
+    const EXPECTED: &'static str = "a positive number of seconds";
+
+    fn parse(value: &str) -> Option<Self> {
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|&secs| secs > 0.0)
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .map(Self)
+    }
+}
+
+/// Timeout asked for with `X-Request-Timeout`, if any.
+fn requested_timeout(headers: &HeaderMap) -> Result<Option<Duration>, Error> {
+    Header::<RequestTimeout>::parse(headers)
+        .map(|timeout| timeout.map(|RequestTimeout(timeout)| timeout))
+        .map_err(Error::InvalidTimeout)
+}
+
+/// The shorter of the configured and the requested timeouts.
+fn effective_timeout(
+    configured: Option<Duration>,
+    requested: Option<Duration>,
+) -> Option<Duration> {
+    match (configured, requested) {
+        (Some(configured), Some(requested)) => Some(configured.min(requested)),
+        (timeout, None) | (None, timeout) => timeout,
+    }
+}
+
+/// Bound the request and its database work by its deadline.
+pub async fn enforce_deadline(
+    State(state): State<AppState>,
+    RequestId(request_id): RequestId,
+    req: Request,
+    next: Next,
+) -> Response {
+    let recorder =
+        ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+    let requested = match requested_timeout(req.headers()) {
+        Ok(requested) => requested,
+        Err(e) => {
+            return recorder
+                .record("invalid_request_timeout", e)
+                .into_response();
+        }
+    };
+    let Some(timeout) =
+        effective_timeout(state.config.request_timeout, requested)
+    else {
+        return next.run(req).await;
+    };
+
+    let deadline = Instant::now() + timeout;
+    let response = with_deadline(
+        deadline,
+        tokio::time::timeout_at(deadline + CANCEL_GRACE, next.run(req)),
+    )
+    .await;
+    match response {
+        // A server error past the deadline is the cancelled work failing
+        Ok(response)
+            if !(response.status().is_server_error()
+                && Instant::now() >= deadline) =>
+        {
+            response
+        }
+        _ => recorder
+            .record("request_timeout", Error::TimedOut(timeout))
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn requested(value: &str) -> Result<Option<Duration>, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            REQUEST_TIMEOUT_HEADER,
+            HeaderValue::from_str(value).unwrap(),
+        );
+        requested_timeout(&headers)
+    }
+
+    #[test]
+    fn test_parses_requested_timeouts() {
+        assert_eq!(requested_timeout(&HeaderMap::new()).unwrap(), None);
+        assert_eq!(requested("2").unwrap(), Some(Duration::from_secs(2)));
+        assert_eq!(
+            requested(" 0.25 ").unwrap(),
+            Some(Duration::from_millis(250))
+        );
+        for invalid in ["0", "-1", "soon", "NaN", "inf", "1e400"] {
+            assert!(requested(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_takes_the_shorter_timeout() {
+        let secs = |secs| Some(Duration::from_secs(secs));
+
+        assert_eq!(effective_timeout(secs(30), secs(5)), secs(5));
+        assert_eq!(effective_timeout(secs(30), secs(60)), secs(30));
+        assert_eq!(effective_timeout(None, secs(60)), secs(60));
+        assert_eq!(effective_timeout(secs(30), None), secs(30));
+        assert_eq!(effective_timeout(None, None), None);
+    }
+}
@@ -0,0 +1,244 @@
+//! Endpoint groups switched off by configuration.
+//!
+//! `DISABLED_ENDPOINTS` names groups of `/api/wire/v1` routes left out when
+//! the routers are built, e.g. `ingestion` for a read-only deployment or
+//! `admin` on an instance exposed without an internal listener. Disabled
+//! routes answer `404` like any unknown path, and are left out of the
+//! OpenAPI spec served under `/api-docs`; `generate-openapi` needs no
+//! configuration and always writes the full spec.
+use std::collections::{BTreeSet, HashSet};
+
+use utoipa::openapi::OpenApi;
+use utoipa::openapi::path::{HttpMethod, Operation, PathItem};
+
+/// Routes enabled or disabled together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EndpointGroup {
+    /// `/admin`
+    Admin,
+    /// `/alert-rules` and `/alerts`
+    Alerts,
+    /// `/energy`, except ingestion
+    Energy,
+    /// `/graphql`
+    Graphql,
+    /// `POST /energy/readings`
+    Ingestion,
+    /// `/maintenance-windows`
+    Maintenance,
+    /// `/notification-channels`
+    Notifications,
+    /// `/plants`
+    Plants,
+    /// `/preferences`
+    Preferences,
+    /// `/usage`
+    Usage,
+    /// `/webhooks`
+    Webhooks,
+}
+
+impl EndpointGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndpointGroup::Admin => "admin",
+            EndpointGroup::Alerts => "alerts",
+            EndpointGroup::Energy => "energy",
+            EndpointGroup::Graphql => "graphql",
+            EndpointGroup::Ingestion => "ingestion",
+            EndpointGroup::Maintenance => "maintenance",
+            EndpointGroup::Notifications => "notifications",
+            EndpointGroup::Plants => "plants",
+            EndpointGroup::Preferences => "preferences",
+            EndpointGroup::Usage => "usage",
+            EndpointGroup::Webhooks => "webhooks",
+        }
+    }
+
+    /// Group of the `method` operation on `path`, relative to
+    /// `/api/wire/v1`.
+    pub fn of_operation(method: &HttpMethod, path: &str) -> Option<Self> {
+        if path == "/energy/readings" && *method == HttpMethod::Post {
+            return Some(EndpointGroup::Ingestion);
+        }
+        let segment = path.trim_start_matches('/').split('/').next()?;
+        match segment {
+            "admin" => Some(EndpointGroup::Admin),
+            "alert-rules" | "alerts" => Some(EndpointGroup::Alerts),
+            "energy" => Some(EndpointGroup::Energy),
+            "graphql" => Some(EndpointGroup::Graphql),
+            "maintenance-windows" => Some(EndpointGroup::Maintenance),
+            "notification-channels" => Some(EndpointGroup::Notifications),
+            "plants" => Some(EndpointGroup::Plants),
+            "preferences" => Some(EndpointGroup::Preferences),
+            "usage" => Some(EndpointGroup::Usage),
+            "webhooks" => Some(EndpointGroup::Webhooks),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for EndpointGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(EndpointGroup::Admin),
+            "alerts" => Ok(EndpointGroup::Alerts),
+            "energy" => Ok(EndpointGroup::Energy),
+            "graphql" => Ok(EndpointGroup::Graphql),
+            "ingestion" => Ok(EndpointGroup::Ingestion),
+            "maintenance" => Ok(EndpointGroup::Maintenance),
+            "notifications" => Ok(EndpointGroup::Notifications),
+            "plants" => Ok(EndpointGroup::Plants),
+            "preferences" => Ok(EndpointGroup::Preferences),
+            "usage" => Ok(EndpointGroup::Usage),
+            "webhooks" => Ok(EndpointGroup::Webhooks),
+            other => Err(format!("Unknown endpoint group `{other}`")),
+        }
+    }
+}
+
+/// Endpoint groups disabled by configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Endpoints {
+    disabled: BTreeSet<EndpointGroup>,
+}
+
+impl Endpoints {
+    pub fn disabling(groups: impl IntoIterator<Item = EndpointGroup>) -> Self {
+        Self {
+            disabled: groups.into_iter().collect(),
+        }
+    }
+
+    pub fn is_enabled(&self, group: EndpointGroup) -> bool {
+        !self.disabled.contains(&group)
+    }
+
+    pub fn disabled(&self) -> impl Iterator<Item = EndpointGroup> + '_ {
+        self.disabled.iter().copied()
+    }
+
+    /// `openapi` without the operations of disabled groups, the paths left
+    /// without operations, nor the tags no remaining operation uses.
+    pub fn filter_spec(&self, mut openapi: OpenApi) -> OpenApi {
+        if self.disabled.is_empty() {
+            return openapi;
+        }
+        for (path, item) in &mut openapi.paths.paths {
+            for (method, operation) in operations(item) {
+                if EndpointGroup::of_operation(&method, path)
+                    .is_some_and(|group| !self.is_enabled(group))
+                {
+                    *operation = None;
+                }
+            }
+        }
+        openapi
+            .paths
+            .paths
+            .retain(|_, item| operations(item).any(|(_, op)| op.is_some()));
+
+        let used = openapi
+            .paths
+            .paths
+            .values_mut()
+            .flat_map(operations)
+            .filter_map(|(_, operation)| operation.as_ref())
+            .flat_map(|operation| operation.tags.iter().flatten())
+            .cloned()
+            .collect::<HashSet<_>>();
+        if let Some(tags) = &mut openapi.tags {
+            tags.retain(|tag| used.contains(&tag.name));
+        }
+        openapi
+    }
+}
+
+/// The operations of `item` by method, absent ones as `None`.
+fn operations(
+    item: &mut PathItem,
+) -> impl Iterator<Item = (HttpMethod, &mut Option<Operation>)> {
+    [
+        (HttpMethod::Get, &mut item.get),
+        (HttpMethod::Put, &mut item.put),
+        (HttpMethod::Post, &mut item.post),
+        (HttpMethod::Delete, &mut item.delete),
+        (HttpMethod::Options, &mut item.options),
+        (HttpMethod::Head, &mut item.head),
+        (HttpMethod::Patch, &mut item.patch),
+        (HttpMethod::Trace, &mut item.trace),
+    ]
+    .into_iter()
+}
+
+/// Parse a comma-separated list of endpoint groups to disable.
+pub fn parse_disabled(list: &str) -> Result<Endpoints, String> {
+    let groups = list
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Endpoints::disabling(groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::WireV1ApiDoc;
+
+    #[test]
+    fn test_parses_disabled_groups() {
+        let endpoints = parse_disabled(" ingestion, admin ,").unwrap();
+
+        assert!(!endpoints.is_enabled(EndpointGroup::Ingestion));
+        assert!(!endpoints.is_enabled(EndpointGroup::Admin));
+        assert!(endpoints.is_enabled(EndpointGroup::Energy));
+        assert_eq!(parse_disabled("").unwrap(), Endpoints::default());
+        assert!(parse_disabled("ingestion,reports").is_err());
+    }
+
+    #[test]
+    fn test_leaves_disabled_groups_out_of_the_spec() {
+        let full = WireV1ApiDoc::openapi();
+        let endpoints = Endpoints::disabling([
+            EndpointGroup::Ingestion,
+            EndpointGroup::Admin,
+            EndpointGroup::Graphql,
+        ]);
+        let spec = endpoints.filter_spec(full.clone());
+
+        let paths = &spec.paths.paths;
+        // Listing readings is not ingestion
+        let readings = &paths["/energy/readings"];
+        assert!(readings.post.is_none() && readings.get.is_some());
+        assert!(!paths.keys().any(|path| path.starts_with("/admin")));
+        assert!(!paths.keys().any(|path| path.starts_with("/graphql")));
+        assert!(paths.contains_key("/energy/aggregate"));
+        assert!(paths.contains_key("/plants/{id}/energy/aggregate"));
+
+        let tags = spec.tags.unwrap();
+        let tag = |name| tags.iter().any(|tag| tag.name == name);
+        assert!(!tag("admin") && !tag("graphql"));
+        assert!(tag("energy") && tag("plants"));
+
+        let spec = Endpoints::disabling([EndpointGroup::Energy])
+            .filter_spec(full.clone());
+        let readings = &spec.paths.paths["/energy/readings"];
+        assert!(readings.post.is_some() && readings.get.is_none());
+        assert!(!spec.paths.paths.contains_key("/energy/aggregate"));
+
+        // Every operation of the spec belongs to a group
+        let mut full_paths = full.paths.paths.clone();
+        assert!(full_paths.iter_mut().all(|(path, item)| {
+            operations(item)
+                .filter(|(_, operation)| operation.is_some())
+                .all(|(method, _)| {
+                    EndpointGroup::of_operation(&method, path).is_some()
+                })
+        }));
+        assert!(Endpoints::default().filter_spec(full.clone()) == full);
+    }
+}
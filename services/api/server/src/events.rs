@@ -0,0 +1,275 @@
+//! In-process domain events.
+//!
+//! Handlers publish a change once it is committed, and every subscriber,
+//! such as the `GET /plants/changes` feed, receives the events published
+//! after it subscribed. Events are not persisted nor shared between
+//! instances; subscribers that fall more than the channel capacity behind
+//! miss events and are told how many.
+//!
+//! Besides plant changes, handlers publish [`DomainEvent`]s for what they
+//! served rather than recording it themselves: the [`EventMetrics`] and
+//! [`AuditLog`] workers observe them, and the outbox relay and webhook
+//! dispatcher wake up on them instead of waiting for their next poll.
+use std::sync::Arc;
+
+use postgres_models::models::plants::Plant;
+use telemetry::metrics::Telemetry;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+
+/// Events buffered for slow subscribers.
+const CAPACITY: usize = 1024;
+
+/// A committed change to a plant
+#[derive(Debug, Clone)]
+pub enum PlantEvent {
+    Created(Plant),
+    Updated(Plant),
+    Deleted { tenant_id: String, id: Uuid },
+}
+
+impl PlantEvent {
+    pub fn tenant_id(&self) -> &str {
+        match self {
+            PlantEvent::Created(plant) | PlantEvent::Updated(plant) => {
+                &plant.tenant_id
+            }
+            PlantEvent::Deleted { tenant_id, .. } => tenant_id,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlantEvent::Created(_) => "plant_created",
+            PlantEvent::Updated(_) => "plant_updated",
+            PlantEvent::Deleted { .. } => "plant_deleted",
+        }
+    }
+}
+
+/// Where an aggregation was answered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateSource {
+    Cache,
+    /// Computed for this request
+    Computed,
+    /// Computed for an identical request in flight
+    Shared,
+}
+
+impl AggregateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AggregateSource::Cache => "cache",
+            AggregateSource::Computed => "computed",
+            AggregateSource::Shared => "shared",
+        }
+    }
+}
+
+/// Something that happened while serving a request or in a worker
+#[derive(Debug, Clone)]
+pub enum DomainEvent {
+    AggregateServed {
+        tenant_id: String,
+        source: AggregateSource,
+    },
+    /// A cached response, of the named cache, was not found
+    CacheMiss { cache: &'static str },
+    /// Readings were stored
+    ImportCompleted {
+        tenant_id: String,
+        /// API key that sent them
+        api_key_id: Uuid,
+        inserted: usize,
+        total: usize,
+    },
+    /// The outbox relay queued webhook deliveries
+    DeliveriesQueued { count: usize },
+}
+
+impl DomainEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DomainEvent::AggregateServed { .. } => "aggregate_served",
+            DomainEvent::CacheMiss { .. } => "cache_miss",
+            DomainEvent::ImportCompleted { .. } => "import_completed",
+            DomainEvent::DeliveriesQueued { .. } => "deliveries_queued",
+        }
+    }
+}
+
+/// Broadcast channel of events.
+#[derive(Debug, Clone)]
+pub struct EventBus<E> {
+    sender: broadcast::Sender<Arc<E>>,
+}
+
+impl<E> Default for EventBus<E> {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+        }
+    }
+}
+
+impl<E> EventBus<E> {
+    /// Send `event` to the current subscribers, if any.
+    pub fn publish(&self, event: E) {
+        let _ = self.sender.send(Arc::new(event));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<E>> {
+        self.sender.subscribe()
+    }
+}
+
+/// Broadcast channel of [`PlantEvent`]s.
+pub type PlantEvents = EventBus<PlantEvent>;
+
+/// Broadcast channel of [`DomainEvent`]s.
+pub type DomainEvents = EventBus<DomainEvent>;
+
+/// The next event of `receiver`, `None` once shutdown begins. Missed
+/// events are logged as `subscriber` falling behind.
+pub async fn next_event<E>(
+    subscriber: &str,
+    receiver: &mut broadcast::Receiver<Arc<E>>,
+    shutdown: &ShutdownCoordinator,
+) -> Option<Arc<E>> {
+    loop {
+        let received = tokio::select! {
+            received = receiver.recv() => received,
+            _ = shutdown.wait_for_shutdown() => return None,
+        };
+        match received {
+            Ok(event) => return Some(event),
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!(subscriber, missed, "Missed domain events");
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                shutdown.wait_for_shutdown().await;
+                return None;
+            }
+        }
+    }
+}
+
+/// Background worker counting [`DomainEvent`]s in metrics.
+pub struct EventMetrics {
+    events: broadcast::Receiver<Arc<DomainEvent>>,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+}
+
+impl EventMetrics {
+    pub fn new(
+        events: &DomainEvents,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+    ) -> Self {
+        Self {
+            events: events.subscribe(),
+            telemetry,
+        }
+    }
+
+    pub async fn run(mut self, shutdown: Arc<ShutdownCoordinator>) {
+        while let Some(event) =
+            next_event("metrics", &mut self.events, &shutdown).await
+        {
+            self.record(&event);
+        }
+    }
+
+    fn record(&self, event: &DomainEvent) {
+        self.telemetry.maybe_use_metrics(|m| {
+            m.record_domain_event(event.as_str());
+            if let DomainEvent::AggregateServed { source, .. } = event
+                && *source != AggregateSource::Cache
+            {
+                m.record_aggregate_computation(source.as_str());
+            }
+        });
+    }
+}
+
+/// Background worker logging imports on the `audit` target.
+pub struct AuditLog {
+    events: broadcast::Receiver<Arc<DomainEvent>>,
+}
+
+impl AuditLog {
+    pub fn new(events: &DomainEvents) -> Self {
+        Self {
+            events: events.subscribe(),
+        }
+    }
+
+    pub async fn run(mut self, shutdown: Arc<ShutdownCoordinator>) {
+        while let Some(event) =
+            next_event("audit", &mut self.events, &shutdown).await
+        {
+            if let DomainEvent::ImportCompleted {
+                tenant_id,
+                api_key_id,
+                inserted,
+                total,
+            } = &*event
+            {
+                tracing::info!(
+                    target: "audit",
+                    event = event.as_str(),
+                    tenant = %tenant_id,
+                    api_key_id = %api_key_id,
+                    inserted,
+                    total,
+                    "Readings imported"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_counts_events_in_metrics() {
+        let metrics = ServerMetrics::new_with_random_prefix().unwrap();
+        let telemetry = Telemetry::new(Some(metrics.clone())).await.unwrap();
+        let events = DomainEvents::default();
+        let mut subscriber = EventMetrics::new(&events, telemetry);
+
+        for source in [
+            AggregateSource::Cache,
+            AggregateSource::Computed,
+            AggregateSource::Shared,
+        ] {
+            events.publish(DomainEvent::AggregateServed {
+                tenant_id: "default".to_string(),
+                source,
+            });
+        }
+        events.publish(DomainEvent::CacheMiss { cache: "aggregate" });
+        while let Ok(event) = subscriber.events.try_recv() {
+            subscriber.record(&event);
+        }
+
+        let served =
+            |event| metrics.domain_events.with_label_values(&[event]).get();
+        assert_eq!(served("aggregate_served"), 3);
+        assert_eq!(served("cache_miss"), 1);
+        let computations = |outcome| {
+            metrics
+                .aggregate_computations
+                .with_label_values(&[outcome])
+                .get()
+        };
+        assert_eq!(computations("computed"), 1);
+        assert_eq!(computations("shared"), 1);
+        assert_eq!(computations("cache"), 0);
+    }
+}
@@ -0,0 +1,315 @@
+//! Feature flags for rolling out risky features gradually.
+//!
+//! Every flag has a default, from the `FEATURE_FLAGS` setting or built in,
+//! which admins can override at runtime (`PUT /admin/flags/{flag}`).
+//! Overrides are stored in Redis so they apply to every instance, and each
+//! instance re-reads them at most every [`REFRESH_INTERVAL`].
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use deadpool_redis::redis::{AsyncCommands, RedisError};
+use redis_cache::connection::{Pool, PooledConnection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+/// Redis hash holding the overrides, flag name to `1` or `0`.
+const OVERRIDES_KEY: &str = "feature_flags";
+/// How long overrides read from Redis are reused.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    /// Serve `POST /energy/aggregate` responses from the Redis cache
+    AggregateCache,
+    /// Accept readings on `POST /energy/readings`
+    ReadingIngestion,
+    /// Require `If-Match` on `DELETE /plants/{id}`
+    PlantDeleteIfMatch,
+    /// Serve GET responses from the Redis cache, see
+    /// [`crate::shared::response_cache`]
+    ResponseCache,
+    /// Accept `numbers=true` on `POST /energy/aggregate`
+    DecimalNumbers,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 5] = [
+        Flag::AggregateCache,
+        Flag::ReadingIngestion,
+        Flag::PlantDeleteIfMatch,
+        Flag::ResponseCache,
+        Flag::DecimalNumbers,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Flag::AggregateCache => "aggregate_cache",
+            Flag::ReadingIngestion => "reading_ingestion",
+            Flag::PlantDeleteIfMatch => "plant_delete_if_match",
+            Flag::ResponseCache => "response_cache",
+            Flag::DecimalNumbers => "decimal_numbers",
+        }
+    }
+
+    /// Value used when neither the config nor an override sets the flag.
+    pub fn built_in_default(&self) -> bool {
+        match self {
+            Flag::AggregateCache
+            | Flag::ReadingIngestion
+            | Flag::ResponseCache => true,
+            // Breaks clients that delete without a version
+            Flag::PlantDeleteIfMatch => false,
+            // Until clients are known to parse them
+            Flag::DecimalNumbers => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Flag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Flag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Flag::ALL
+            .into_iter()
+            .find(|flag| flag.as_str() == s)
+            .ok_or_else(|| format!("Unknown feature flag `{s}`"))
+    }
+}
+
+/// Parse a comma-separated list of flag defaults, each `flag=true`,
+/// `flag=false` or a bare `flag` to enable it.
+pub fn parse_defaults(list: &str) -> Result<HashMap<Flag, bool>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, value) =
+                entry.split_once('=').unwrap_or((entry, "true"));
+            let enabled = value.trim().parse::<bool>().map_err(|_| {
+                format!("Invalid value for feature flag `{}`", name.trim())
+            })?;
+            Ok((name.trim().parse()?, enabled))
+        })
+        .collect()
+}
+
+/// Current value of a flag and where it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagState {
+    pub flag: Flag,
+    pub enabled: bool,
+    pub default: bool,
+    pub overridden: bool,
+}
+
+#[derive(Default)]
+struct Overrides {
+    values: HashMap<Flag, bool>,
+    fetched_at: Option<Instant>,
+}
+
+impl Overrides {
+    fn is_fresh(&self) -> bool {
+        self.fetched_at
+            .is_some_and(|fetched_at| fetched_at.elapsed() < REFRESH_INTERVAL)
+    }
+}
+
+pub struct FeatureFlags {
+    defaults: HashMap<Flag, bool>,
+    overrides: RwLock<Overrides>,
+}
+
+impl FeatureFlags {
+    /// Flags with the given defaults; flags not listed use their built-in
+    /// default.
+    pub fn new(defaults: HashMap<Flag, bool>) -> Self {
+        Self {
+            defaults,
+            overrides: RwLock::new(Overrides::default()),
+        }
+    }
+
+    pub fn default_value(&self, flag: Flag) -> bool {
+        self.defaults
+            .get(&flag)
+            .copied()
+            .unwrap_or_else(|| flag.built_in_default())
+    }
+
+    /// Whether `flag` is on. Overrides are refreshed from Redis when stale;
+    /// if Redis is unavailable the last known overrides are used.
+    pub async fn is_enabled(&self, flag: Flag, cache: &Pool) -> bool {
+        if !self.overrides.read().await.is_fresh() {
+            self.refresh(cache).await;
+        }
+
+        let overrides = self.overrides.read().await;
+        overrides
+            .values
+            .get(&flag)
+            .copied()
+            .unwrap_or_else(|| self.default_value(flag))
+    }
+
+    /// The state of every flag, with overrides read from Redis.
+    pub async fn states(
+        &self,
+        conn: &mut PooledConnection,
+    ) -> Result<Vec<FlagState>, RedisError> {
+        let overrides = self.load(conn).await?;
+
+        Ok(Flag::ALL
+            .into_iter()
+            .map(|flag| self.state_with(flag, &overrides))
+            .collect())
+    }
+
+    /// The state of `flag`, with overrides read from Redis.
+    pub async fn state(
+        &self,
+        flag: Flag,
+        conn: &mut PooledConnection,
+    ) -> Result<FlagState, RedisError> {
+        let overrides = self.load(conn).await?;
+        Ok(self.state_with(flag, &overrides))
+    }
+
+    fn state_with(
+        &self,
+        flag: Flag,
+        overrides: &HashMap<Flag, bool>,
+    ) -> FlagState {
+        let default = self.default_value(flag);
+        let value = overrides.get(&flag).copied();
+        FlagState {
+            flag,
+            enabled: value.unwrap_or(default),
+            default,
+            overridden: value.is_some(),
+        }
+    }
+
+    /// Override `flag` on every instance, returning its new state.
+    pub async fn set_override(
+        &self,
+        flag: Flag,
+        enabled: bool,
+        conn: &mut PooledConnection,
+    ) -> Result<FlagState, RedisError> {
+        let _: () = conn
+            .hset(
+                OVERRIDES_KEY,
+                flag.as_str(),
+                if enabled { "1" } else { "0" },
+            )
+            .await?;
+        self.state(flag, conn).await
+    }
+
+    /// Remove the override of `flag`, returning it to its default.
+    pub async fn clear_override(
+        &self,
+        flag: Flag,
+        conn: &mut PooledConnection,
+    ) -> Result<FlagState, RedisError> {
+        let _: () = conn.hdel(OVERRIDES_KEY, flag.as_str()).await?;
+        self.state(flag, conn).await
+    }
+
+    async fn refresh(&self, cache: &Pool) {
+        let result = match cache.get().await {
+            Ok(mut conn) => self
+                .load(&mut conn)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to refresh feature flag overrides: {e}");
+            // Retry after the refresh interval instead of on every check
+            self.overrides.write().await.fetched_at = Some(Instant::now());
+        }
+    }
+
+    /// Read the overrides from Redis and remember them.
+    async fn load(
+        &self,
+        conn: &mut PooledConnection,
+    ) -> Result<HashMap<Flag, bool>, RedisError> {
+        let raw: HashMap<String, String> = conn.hgetall(OVERRIDES_KEY).await?;
+        let values = parse_overrides(raw);
+
+        *self.overrides.write().await = Overrides {
+            values: values.clone(),
+            fetched_at: Some(Instant::now()),
+        };
+        Ok(values)
+    }
+}
+
+/// Overrides stored in Redis, ignoring flags that no longer exist.
+fn parse_overrides(raw: HashMap<String, String>) -> HashMap<Flag, bool> {
+    raw.into_iter()
+        .filter_map(|(name, value)| {
+            Some((name.parse().ok()?, matches!(value.as_str(), "1" | "true")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_defaults() {
+        let defaults =
+            parse_defaults(" aggregate_cache=false, reading_ingestion ,")
+                .unwrap();
+
+        assert_eq!(defaults.get(&Flag::AggregateCache), Some(&false));
+        assert_eq!(defaults.get(&Flag::ReadingIngestion), Some(&true));
+        assert!(parse_defaults("aggregate_cache=maybe").is_err());
+        assert!(parse_defaults("new_thing").is_err());
+    }
+
+    #[test]
+    fn test_defaults_fall_back_to_built_in() {
+        let flags = FeatureFlags::new(
+            parse_defaults("reading_ingestion=false").unwrap(),
+        );
+
+        assert!(!flags.default_value(Flag::ReadingIngestion));
+        assert!(flags.default_value(Flag::AggregateCache));
+        assert!(!flags.default_value(Flag::PlantDeleteIfMatch));
+    }
+
+    #[test]
+    fn test_parse_overrides() {
+        let raw = HashMap::from([
+            ("aggregate_cache".to_string(), "0".to_string()),
+            ("reading_ingestion".to_string(), "1".to_string()),
+            ("removed_flag".to_string(), "1".to_string()),
+        ]);
+
+        assert_eq!(
+            parse_overrides(raw),
+            HashMap::from([
+                (Flag::AggregateCache, false),
+                (Flag::ReadingIngestion, true),
+            ])
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! `wire.v1.EnergyService`: aggregates, query history and reading streams
+//! from the same models as the `/energy` routes.
+use std::pin::Pin;
+
+use chrono::{DateTime, Utc};
+use postgres_models::connection::WithConnectionError;
+use postgres_models::models::Keyset;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, EnergyReading, ReadingFilter,
+};
+use postgres_models::models::query_history::NewQueryHistory;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use super::proto;
+use crate::AppState;
+use crate::admission::aggregation_cost;
+use crate::auth::{Caller, TenantContext};
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+const HISTORY_LIMIT: i64 = 10;
+/// Readings fetched per query while streaming.
+const STREAM_PAGE_SIZE: i64 = 1000;
+
+pub struct EnergyService {
+    state: AppState,
+}
+
+impl EnergyService {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+/// Log a failed query and count it like HTTP handler errors.
+fn database_error(
+    state: &AppState,
+    method: &str,
+    e: WithConnectionError<diesel::result::Error>,
+) -> Status {
+    let error_code = match e {
+        _ if e.is_pool_exhausted() => "pool_exhausted",
+        WithConnectionError::Pool(_) => "pool_error",
+        WithConnectionError::Operation(_) => "database_error",
+    };
+    tracing::error!(method, error_code, "gRPC call failed: {e}");
+    state.telemetry.maybe_use_metrics(|m| {
+        m.record_error(&format!("grpc_{method}"), error_code);
+    });
+    Status::internal("Internal server error")
+}
+
+/// Tenant attached by [`crate::auth::middleware::authenticate`].
+fn tenant<T>(request: &Request<T>) -> Result<TenantContext, Status> {
+    request
+        .extensions()
+        .get::<TenantContext>()
+        .cloned()
+        .ok_or_else(|| Status::unauthenticated("Missing credentials"))
+}
+
+fn to_timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn from_timestamp(
+    field: &str,
+    timestamp: Option<prost_types::Timestamp>,
+) -> Result<Option<DateTime<Utc>>, Status> {
+    timestamp
+        .map(|ts| {
+            u32::try_from(ts.nanos)
+                .ok()
+                .and_then(|nanos| DateTime::from_timestamp(ts.seconds, nanos))
+                .ok_or_else(|| {
+                    Status::invalid_argument(format!("Invalid `{field}`"))
+                })
+        })
+        .transpose()
+}
+
+fn from_proto_aggregation(
+    aggregation_type: proto::AggregationType,
+) -> Result<AggregationType, Status> {
+    match aggregation_type {
+        proto::AggregationType::Hourly => Ok(AggregationType::Hourly),
+        proto::AggregationType::DayOfMonth => Ok(AggregationType::DayOfMonth),
+        proto::AggregationType::Weekly => Ok(AggregationType::Weekly),
+        proto::AggregationType::Monthly => Ok(AggregationType::Monthly),
+        proto::AggregationType::Quarterly => Ok(AggregationType::Quarterly),
+        proto::AggregationType::Yearly => Ok(AggregationType::Yearly),
+        proto::AggregationType::Unspecified => {
+            Err(Status::invalid_argument("`aggregation_type` is required"))
+        }
+    }
+}
+
+fn to_proto_aggregation(
+    aggregation_type: &AggregationType,
+) -> proto::AggregationType {
+    match aggregation_type {
+        AggregationType::Hourly => proto::AggregationType::Hourly,
+        AggregationType::DayOfMonth => proto::AggregationType::DayOfMonth,
+        AggregationType::Weekly => proto::AggregationType::Weekly,
+        AggregationType::Monthly => proto::AggregationType::Monthly,
+        AggregationType::Quarterly => proto::AggregationType::Quarterly,
+        AggregationType::Yearly => proto::AggregationType::Yearly,
+    }
+}
+
+#[tonic::async_trait]
+impl proto::energy_service_server::EnergyService for EnergyService {
+    type StreamReadingsStream =
+        Pin<Box<dyn Stream<Item = Result<proto::Reading, Status>> + Send>>;
+
+    #[tracing::instrument(skip_all, name = "grpc_aggregate")]
+    async fn aggregate(
+        &self,
+        request: Request<proto::AggregateRequest>,
+    ) -> Result<Response<proto::AggregateResponse>, Status> {
+        let tenant = tenant(&request)?;
+        let api_key_id = request
+            .extensions()
+            .get::<Caller>()
+            .map(|caller| caller.api_key_id);
+        let request = request.into_inner();
+        let aggregation_type =
+            from_proto_aggregation(request.aggregation_type())?;
+        let date_from = from_timestamp("date_from", request.date_from)?;
+        let date_to = from_timestamp("date_to", request.date_to)?;
+
+        let new_entry = NewQueryHistory {
+            aggregation_type: aggregation_type.to_string(),
+            date_from,
+            date_to,
+            api_key_id,
+            tenant_id: tenant.tenant_id.clone(),
+            created_at: None,
+        };
+        self.state
+            .record_query(new_entry)
+            .await
+            .map_err(|e| database_error(&self.state, "aggregate", e))?;
+
+        let cost =
+            aggregation_cost(aggregation_type, date_from, date_to, Utc::now());
+        self.state
+            .admission
+            .admit(cost, self.state.reads.serving())
+            .await
+            .map_err(|rejection| {
+                self.state.telemetry.maybe_use_metrics(|m| {
+                    m.record_error("grpc_aggregate", rejection.code());
+                });
+                Status::from(rejection)
+            })?;
+
+        let trunc_level = aggregation_type.to_trunc_level();
+        let rows = self
+            .state
+            .readings
+            .aggregate(
+                &tenant.tenant_id,
+                None,
+                trunc_level,
+                date_from,
+                date_to,
+                AggregateOptions::default(),
+            )
+            .await
+            .map_err(|e| database_error(&self.state, "aggregate", e))?;
+
+        Ok(Response::new(proto::AggregateResponse {
+            aggregation_type: to_proto_aggregation(&aggregation_type).into(),
+            date_from: date_from.map(to_timestamp),
+            date_to: date_to.map(to_timestamp),
+            data: rows
+                .into_iter()
+                .map(|row| proto::AggregateDataPoint {
+                    period: Some(to_timestamp(row.period)),
+                    total_kwh: row.total_kwh.to_string(),
+                })
+                .collect(),
+        }))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_history")]
+    async fn history(
+        &self,
+        request: Request<proto::HistoryRequest>,
+    ) -> Result<Response<proto::HistoryResponse>, Status> {
+        let tenant = tenant(&request)?;
+
+        let entries = self
+            .state
+            .query_history
+            .latest(&tenant.tenant_id, HISTORY_LIMIT)
+            .await
+            .map_err(|e| database_error(&self.state, "history", e))?;
+
+        Ok(Response::new(proto::HistoryResponse {
+            queries: entries
+                .into_iter()
+                .map(|entry| proto::QueryHistoryEntry {
+                    id: entry.id.to_string(),
+                    aggregation_type: entry.aggregation_type,
+                    date_from: entry.date_from.map(to_timestamp),
+                    date_to: entry.date_to.map(to_timestamp),
+                    api_key_id: entry.api_key_id.map(|id| id.to_string()),
+                    created_at: Some(to_timestamp(entry.created_at)),
+                })
+                .collect(),
+        }))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_stream_readings")]
+    async fn stream_readings(
+        &self,
+        request: Request<proto::StreamReadingsRequest>,
+    ) -> Result<Response<Self::StreamReadingsStream>, Status> {
+        let tenant = tenant(&request)?;
+        let request = request.into_inner();
+        let date_from = from_timestamp("date_from", request.date_from)?;
+        let date_to = from_timestamp("date_to", request.date_to)?;
+
+        // One page is buffered ahead of the client
+        let (tx, rx) = mpsc::channel(STREAM_PAGE_SIZE as usize);
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            let tenant_id = &tenant.tenant_id;
+            let mut keyset = Keyset::First;
+            loop {
+                let page = state
+                    .reads
+                    .with_connection(|mut conn| async move {
+                        let filter = ReadingFilter {
+                            date_from,
+                            date_to,
+                            ..Default::default()
+                        };
+                        EnergyReading::page(
+                            tenant_id,
+                            keyset,
+                            filter,
+                            STREAM_PAGE_SIZE,
+                            &mut conn,
+                        )
+                        .await
+                    })
+                    .await;
+                let page = match page {
+                    Ok(page) => page,
+                    Err(e) => {
+                        let status =
+                            database_error(&state, "stream_readings", e);
+                        let _ = tx.send(Err(status)).await;
+                        return;
+                    }
+                };
+
+                let full = page.len() as i64 == STREAM_PAGE_SIZE;
+                keyset = page.last().map_or(keyset, EnergyReading::cursor);
+                for reading in page {
+                    let reading = proto::Reading {
+                        reading_time: Some(to_timestamp(reading.reading_time)),
+                        quantity_kwh: reading.quantity_kwh.to_string(),
+                    };
+                    // The client went away
+                    if tx.send(Ok(reading)).await.is_err() {
+                        return;
+                    }
+                }
+                if !full {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        let time = "2025-03-01T12:30:00.250Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            from_timestamp("date_from", Some(to_timestamp(time))).unwrap(),
+            Some(time)
+        );
+        assert_eq!(from_timestamp("date_from", None).unwrap(), None);
+
+        let invalid = prost_types::Timestamp {
+            seconds: 0,
+            nanos: -1,
+        };
+        let status = from_timestamp("date_from", Some(invalid)).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_aggregation_types() {
+        for aggregation_type in [
+            AggregationType::Hourly,
+            AggregationType::DayOfMonth,
+            AggregationType::Weekly,
+            AggregationType::Monthly,
+            AggregationType::Quarterly,
+            AggregationType::Yearly,
+        ] {
+            assert_eq!(
+                from_proto_aggregation(to_proto_aggregation(&aggregation_type))
+                    .unwrap(),
+                aggregation_type
+            );
+        }
+        assert_eq!(
+            from_proto_aggregation(proto::AggregationType::Unspecified)
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+}
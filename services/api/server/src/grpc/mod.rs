@@ -0,0 +1,114 @@
+//! gRPC API for internal services, served on `GRPC_LISTEN_ADDRS`.
+//!
+//! The tonic services are turned into an axum router, so calls pass through
+//! the same authentication, quota and role checks as wire v1 requests, and
+//! are served by [`crate::listener`] like the HTTP listeners, always with
+//! HTTP/2. Rejections from those layers become gRPC statuses, such as
+//! `UNAUTHENTICATED` or `RESOURCE_EXHAUSTED`.
+use axum::Router;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::middleware::{Next, from_extractor, from_fn, from_fn_with_state};
+use axum::response::Response;
+use tonic::{Code, Status};
+
+use crate::AppState;
+use crate::auth::{RequirePermission, permission};
+
+pub mod energy;
+
+/// Code generated from `proto/wire/v1/energy.proto`.
+pub mod proto {
+    tonic::include_proto!("wire.v1");
+}
+
+pub fn get_routes(state: &AppState) -> Router {
+    tonic::service::Routes::new(
+        proto::energy_service_server::EnergyServiceServer::new(
+            energy::EnergyService::new(state.clone()),
+        ),
+    )
+    .into_axum_router()
+    .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+    .layer(from_fn_with_state(
+        state.clone(),
+        crate::auth::middleware::enforce_quota,
+    ))
+    .layer(from_fn_with_state(
+        state.clone(),
+        crate::auth::middleware::authenticate,
+    ))
+    .layer(from_fn(rejections_as_status))
+}
+
+/// Largest rejection body read for its message.
+const MAX_REJECTION_BYTES: usize = 64 * 1024;
+
+/// Turn the JSON error responses of the HTTP layers into gRPC statuses,
+/// which is all gRPC clients understand. The services themselves always
+/// answer `200 OK`.
+async fn rejections_as_status(req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+    let status = response.status();
+    if status == StatusCode::OK {
+        return response;
+    }
+
+    let message =
+        axum::body::to_bytes(response.into_body(), MAX_REJECTION_BYTES)
+            .await
+            .ok()
+            .and_then(|body| {
+                serde_json::from_slice::<serde_json::Value>(&body).ok()
+            })
+            .and_then(|body| body["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+
+    Status::new(status_code(status), message).into_http()
+}
+
+fn status_code(status: StatusCode) -> Code {
+    match status {
+        StatusCode::BAD_REQUEST => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        _ => Code::Internal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rejections_as_status() {
+        let app = Router::new()
+            .route(
+                "/",
+                axum::routing::post(|| async {
+                    (
+                        StatusCode::UNAUTHORIZED,
+                        axum::Json(serde_json::json!({
+                            "message": "Missing credentials"
+                        })),
+                    )
+                }),
+            )
+            .layer(from_fn(rejections_as_status));
+
+        let response = tower::ServiceExt::oneshot(
+            app,
+            Request::post("/").body(axum::body::Body::empty()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["grpc-status"], "16");
+        // Percent-encoded, as the gRPC spec requires
+        assert_eq!(response.headers()["grpc-message"], "Missing%20credentials");
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::time::{Duration, Instant};
 
 use axum::Json;
@@ -20,6 +21,16 @@ pub enum HealthStatus {
     Unhealthy,
 }
 
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct ComponentHealth {
     pub status: HealthStatus,
@@ -36,20 +47,77 @@ pub struct HealthResponse {
 }
 
 pub async fn handler(state: AppState) -> (StatusCode, Json<HealthResponse>) {
+    let mut response = check(
+        &state.pool,
+        &state.read_only_pool,
+        &state.cache_pool,
+        state.shutdown.is_shutting_down(),
+    )
+    .await;
+    // Pending migrations degrade the service; schema changes are rolled out
+    // before the code relying on them, so it keeps serving meanwhile
+    let migrations = check_migrations(&state.pool).await;
+    if migrations.status == HealthStatus::Unhealthy
+        && response.status == HealthStatus::Healthy
+    {
+        response.status = HealthStatus::Degraded;
+    }
+    response
+        .components
+        .insert("migrations".to_string(), migrations);
+
+    // Components of the API modules, see [`crate::modules::HealthCheck`]
+    let mut checks = tokio::task::JoinSet::new();
+    for check in crate::api_modules().health_checks(&state.config.endpoints) {
+        let state = state.clone();
+        checks.spawn(async move { (check, (check.check)(state).await) });
+    }
+    while let Some(checked) = checks.join_next().await {
+        let Ok((check, component)) = checked else {
+            continue;
+        };
+        if component.status == HealthStatus::Unhealthy {
+            if check.critical {
+                response.status = HealthStatus::Unhealthy;
+            } else if response.status == HealthStatus::Healthy {
+                response.status = HealthStatus::Degraded;
+            }
+        }
+        response
+            .components
+            .insert(check.component.to_string(), component);
+    }
+
+    let status_code = if response.status == HealthStatus::Unhealthy {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+
+    (status_code, Json(response))
+}
+
+/// Check every component. The service is unhealthy while shutting down or
+/// without the read-write database or Redis, and degraded when any other
+/// component is unhealthy.
+pub async fn check(
+    pool: &postgres_models::connection::Pool,
+    read_only_pool: &postgres_models::connection::Pool,
+    cache_pool: &redis_cache::connection::Pool,
+    is_shutting_down: bool,
+) -> HealthResponse {
     let mut components = HashMap::new();
 
     let (pg_rw, pg_ro, redis_main) = tokio::join!(
-        check_postgres(&state.pool),
-        check_postgres(&state.read_only_pool),
-        check_redis(&state.cache_pool),
+        check_postgres(pool),
+        check_postgres(read_only_pool),
+        check_redis(cache_pool),
     );
 
     components.insert("postgres_rw".to_string(), pg_rw);
     components.insert("postgres_ro".to_string(), pg_ro);
     components.insert("redis_main".to_string(), redis_main);
 
-    let is_shutting_down = state.shutdown.is_shutting_down();
-
     let critical_unhealthy = is_shutting_down
         || components
             .get("postgres_rw")
@@ -70,22 +138,39 @@ pub async fn handler(state: AppState) -> (StatusCode, Json<HealthResponse>) {
         HealthStatus::Healthy
     };
 
-    let status_code = if overall == HealthStatus::Unhealthy {
-        StatusCode::SERVICE_UNAVAILABLE
-    } else {
-        StatusCode::OK
-    };
+    HealthResponse {
+        status: overall,
+        components,
+    }
+}
 
-    (
-        status_code,
-        Json(HealthResponse {
-            status: overall,
-            components,
-        }),
-    )
+/// Health of a component from `check`, unhealthy when it fails or takes
+/// longer than `timeout`.
+pub async fn timed(
+    timeout: Duration,
+    check: impl Future<Output = Result<(), String>>,
+) -> ComponentHealth {
+    let start = Instant::now();
+    let result = tokio::time::timeout(timeout, check).await;
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e),
+        Err(_) => Some("timeout".to_string()),
+    };
+    ComponentHealth {
+        status: if error.is_none() {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy
+        },
+        latency_ms: Some(latency_ms),
+        error,
+    }
 }
 
-async fn check_postgres(
+pub(crate) async fn check_postgres(
     pool: &postgres_models::connection::Pool,
 ) -> ComponentHealth {
     let start = Instant::now();
@@ -120,6 +205,42 @@ async fn check_postgres(
     }
 }
 
+async fn check_migrations(
+    pool: &postgres_models::connection::Pool,
+) -> ComponentHealth {
+    let start = Instant::now();
+    let result = tokio::time::timeout(
+        POSTGRES_TIMEOUT,
+        crate::cli::migrate::pending(pool),
+    )
+    .await;
+
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(Ok(pending)) if pending.is_empty() => ComponentHealth {
+            status: HealthStatus::Healthy,
+            latency_ms: Some(latency_ms),
+            error: None,
+        },
+        Ok(Ok(pending)) => ComponentHealth {
+            status: HealthStatus::Unhealthy,
+            latency_ms: Some(latency_ms),
+            error: Some(format!("pending migrations: {}", pending.join(", "))),
+        },
+        Ok(Err(e)) => ComponentHealth {
+            status: HealthStatus::Unhealthy,
+            latency_ms: Some(latency_ms),
+            error: Some(format!("{e:#}")),
+        },
+        Err(_) => ComponentHealth {
+            status: HealthStatus::Unhealthy,
+            latency_ms: Some(latency_ms),
+            error: Some("timeout".to_string()),
+        },
+    }
+}
+
 async fn check_redis(pool: &redis_cache::connection::Pool) -> ComponentHealth {
     let start = Instant::now();
     let result = tokio::time::timeout(REDIS_TIMEOUT, async {
@@ -0,0 +1,259 @@
+//! Write-behind batching of the query history.
+//!
+//! Unless `QUERY_HISTORY_STRICT` is set, aggregations hand their query to
+//! the [`HistoryWriter`] instead of inserting it, and the [`HistoryFlusher`]
+//! inserts what was buffered every `QUERY_HISTORY_FLUSH_INTERVAL_SECS`, up
+//! to `QUERY_HISTORY_BATCH_SIZE` rows per statement. The buffer holds
+//! `QUERY_HISTORY_BUFFER_SIZE` entries; with `QUERY_HISTORY_OVERFLOW=drop`
+//! queries that find it full are dropped, with `wait` they wait for room.
+//! The flusher writes what is left on shutdown, before the pools close.
+//!
+//! Entries are lost when their batch fails to insert or the process dies
+//! before a flush, so the history may miss up to a flush interval of
+//! queries; strict mode loses none but costs every aggregation an insert.
+//! Entries are counted in the `query_history_entries` metric by outcome.
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use postgres_models::models::query_history::NewQueryHistory;
+use telemetry::metrics::Telemetry;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::metrics::ServerMetrics;
+use crate::repository::QueryHistoryRepository;
+use crate::shutdown::ShutdownCoordinator;
+
+/// What happens to queries recorded while the buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the query, so aggregations never wait for the history
+    Drop,
+    /// Wait for room in the buffer
+    Wait,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(Overflow::Drop),
+            "wait" => Ok(Overflow::Wait),
+            other => Err(format!(
+                "Unknown overflow `{other}`, expected `drop` or `wait`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriterSettings {
+    /// Entries buffered before [`WriterSettings::overflow`] applies
+    pub buffer_size: u32,
+    /// Most entries inserted per statement
+    pub batch_size: u32,
+    pub flush_interval: Duration,
+    pub overflow: Overflow,
+}
+
+/// Sends queries to the [`HistoryFlusher`].
+#[derive(Clone)]
+pub struct HistoryWriter {
+    sender: mpsc::Sender<NewQueryHistory>,
+    overflow: Overflow,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+}
+
+impl HistoryWriter {
+    /// A writer and the flusher to run in the background for it.
+    pub fn new(
+        query_history: Arc<dyn QueryHistoryRepository>,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        settings: WriterSettings,
+    ) -> (Self, HistoryFlusher) {
+        let (sender, receiver) = mpsc::channel(settings.buffer_size as usize);
+        let writer = Self {
+            sender,
+            overflow: settings.overflow,
+            telemetry: telemetry.clone(),
+        };
+        let flusher = HistoryFlusher {
+            receiver,
+            query_history,
+            telemetry,
+            batch_size: settings.batch_size as usize,
+            flush_interval: settings.flush_interval,
+        };
+        (writer, flusher)
+    }
+
+    /// Buffer `entry` for the next flush, stamped with the current time so
+    /// the entries of a batch keep their order.
+    pub async fn send(&self, mut entry: NewQueryHistory) {
+        entry.created_at.get_or_insert_with(Utc::now);
+        let sent = match self.overflow {
+            Overflow::Drop => match self.sender.try_send(entry) {
+                Err(TrySendError::Full(_)) => {
+                    tracing::warn!("Query history buffer full, dropping query");
+                    Err("dropped")
+                }
+                other => other.map_err(|_| "closed"),
+            },
+            Overflow::Wait => {
+                self.sender.send(entry).await.map_err(|_| "closed")
+            }
+        };
+        if let Err(outcome) = sent {
+            self.telemetry.maybe_use_metrics(|m| {
+                m.record_query_history_entries(outcome, 1);
+            });
+        }
+    }
+}
+
+/// Background worker inserting buffered queries in batches.
+pub struct HistoryFlusher {
+    receiver: mpsc::Receiver<NewQueryHistory>,
+    query_history: Arc<dyn QueryHistoryRepository>,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    batch_size: usize,
+    flush_interval: Duration,
+}
+
+impl HistoryFlusher {
+    pub async fn run(mut self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            flush_interval = ?self.flush_interval,
+            batch_size = self.batch_size,
+            "Starting query history flusher"
+        );
+
+        let mut interval = tokio::time::interval(self.flush_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+            self.flush().await;
+        }
+        self.flush().await;
+
+        tracing::info!("Query history flusher stopped");
+    }
+
+    /// Insert every buffered entry, a batch at a time.
+    async fn flush(&mut self) {
+        loop {
+            let mut batch = Vec::with_capacity(self.batch_size);
+            while batch.len() < self.batch_size
+                && let Ok(entry) = self.receiver.try_recv()
+            {
+                batch.push(entry);
+            }
+            if batch.is_empty() {
+                return;
+            }
+
+            let count = batch.len() as u64;
+            let outcome = match self.query_history.create_many(batch).await {
+                Ok(_) => "written",
+                Err(e) => {
+                    tracing::warn!(
+                        count,
+                        "Failed to write query history batch: {e}"
+                    );
+                    "failed"
+                }
+            };
+            self.telemetry.maybe_use_metrics(|m| {
+                m.record_query_history_entries(outcome, count);
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::InMemoryQueryHistory;
+
+    fn entry(aggregation_type: &str) -> NewQueryHistory {
+        NewQueryHistory {
+            aggregation_type: aggregation_type.to_string(),
+            date_from: None,
+            date_to: None,
+            api_key_id: None,
+            tenant_id: "acme".to_string(),
+            created_at: None,
+        }
+    }
+
+    async fn writer(
+        overflow: Overflow,
+    ) -> (HistoryWriter, HistoryFlusher, Arc<InMemoryQueryHistory>) {
+        let history = Arc::new(InMemoryQueryHistory::default());
+        let telemetry = Telemetry::new(Some(
+            ServerMetrics::new_with_random_prefix().unwrap(),
+        ))
+        .await
+        .unwrap();
+        let (writer, flusher) = HistoryWriter::new(
+            history.clone(),
+            telemetry,
+            WriterSettings {
+                buffer_size: 3,
+                batch_size: 2,
+                flush_interval: Duration::from_secs(60),
+                overflow,
+            },
+        );
+        (writer, flusher, history)
+    }
+
+    #[tokio::test]
+    async fn test_flushes_in_batches_and_drops_when_full() {
+        let (writer, mut flusher, history) = writer(Overflow::Drop).await;
+        for aggregation_type in ["hourly", "monthly", "day_of_month", "lost"] {
+            writer.send(entry(aggregation_type)).await;
+        }
+        assert!(history.entries().is_empty());
+
+        flusher.flush().await;
+        let types = history
+            .entries()
+            .into_iter()
+            .map(|entry| entry.aggregation_type)
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["hourly", "monthly", "day_of_month"]);
+    }
+
+    #[tokio::test]
+    async fn test_flushes_on_shutdown() {
+        let (writer, flusher, history) = writer(Overflow::Wait).await;
+        let shutdown = Arc::new(ShutdownCoordinator::new(
+            postgres_models::connection::Pool::builder().build_unchecked(
+                diesel_async::pooled_connection::AsyncDieselConnectionManager::new(
+                    "postgres://127.0.0.1:1/unused",
+                ),
+            ),
+            Arc::new(
+                deadpool_redis::Config::from_url("redis://127.0.0.1:1")
+                    .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+                    .unwrap(),
+            ),
+        ));
+        shutdown
+            .drain_before_close(tokio::spawn(flusher.run(shutdown.clone())))
+            .await;
+        writer.send(entry("monthly")).await;
+
+        shutdown.shutdown().await;
+        assert_eq!(history.entries().len(), 1);
+    }
+}
@@ -5,10 +5,47 @@ use crate::shutdown::ShutdownCoordinator;
 use std::sync::Arc;
 use telemetry::metrics::Telemetry;
 // Private API modules - internal implementation details
+pub mod admission;
+pub mod alerts;
+pub mod auth;
+pub mod build_info;
+pub mod cache_warmer;
+pub mod circuit_breaker;
+pub mod cli;
+pub mod compression;
+pub mod config;
 pub mod data_loader;
+pub mod deadline;
+pub mod endpoints;
+pub mod events;
+pub mod flags;
+pub mod grpc;
+pub mod history_writer;
+pub mod listener;
+pub mod load;
+pub mod logging;
+pub mod maintenance;
+pub mod market_prices;
+pub mod modules;
+pub mod notifications;
+pub mod outbox;
+pub mod pool_sizing;
+pub mod quantity_policy;
+pub mod read_fallback;
+pub mod repository;
+pub mod shadow;
 pub mod shutdown;
+pub mod single_flight;
+pub mod snapshots;
+pub mod startup;
+pub mod tls;
+pub mod uploads;
+pub mod weather;
+pub mod webhooks;
 mod wire_api;
 
+pub use config::Config;
+
 // OpenAPI documentation module
 pub mod openapi;
 
@@ -22,32 +59,91 @@ pub mod shared;
 // This provides a clean API boundary where external code can only access
 // the route registration functions without depending on internal module structure
 
-pub use wire_api::core::v1::get_routes as get_wire_api_v1_routes;
+/// Feature areas of the API, see [`modules`]. Adding one is a registration
+/// here; routes, the OpenAPI spec, `/health` and shutdown pick it up.
+pub fn api_modules() -> modules::ApiModules {
+    use wire_api::core::v1;
+
+    modules::ApiModules::default()
+        .register(v1::energy::Module)
+        .register(v1::graphql::Module)
+        .register(v1::plants::Module)
+        .register(v1::maintenance::Module)
+        .register(v1::alerts::Module)
+        .register(v1::notification_channels::Module)
+        .register(v1::preferences::Module)
+        .register(v1::usage::Module)
+        .register(v1::webhooks::Module)
+        .register(v1::admin::Module)
+}
+
+/// Routes served under `/api/wire/v1` on the public listeners.
+pub fn get_wire_api_v1_routes(state: AppState) -> axum::Router {
+    wire_api::core::v1::get_routes(state, &api_modules())
+}
+
+/// Routes served under `/api/wire/v2` on the public listeners.
+pub fn get_wire_api_v2_routes(state: AppState) -> axum::Router {
+    wire_api::core::v2::get_routes(state)
+}
+
+/// Routes served under `/api/wire/v1` on the internal listeners.
+pub fn get_internal_routes(state: AppState) -> axum::Router {
+    api_modules().routes(&state, modules::Mount::Internal)
+}
+
+/// Internals measured by the benchmarks in `benches/`; not part of the API.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::wire_api::core::v1::energy::aggregate::handler::cache_key;
+    pub use crate::wire_api::core::v1::energy::aggregate::models::{
+        AggregateDataPoint, AggregateParams, AggregateRequest,
+        AggregateResponse, AggregationType, DEFAULT_INTERVAL_MINUTES,
+    };
+}
 
-/// Returns the OpenAPI documentation routes for Wire v1 API
+/// Returns the OpenAPI documentation routes for Wire v1 API, and v2's under
+/// `/api-docs/v2`
 /// Includes Swagger UI and OpenAPI JSON spec with OpenAPI 3.0 compatibility fixes
-pub fn get_openapi_routes() -> axum::Router {
+/// Paths of the endpoint groups disabled in `endpoints` are left out
+pub fn get_openapi_routes(endpoints: &endpoints::Endpoints) -> axum::Router {
     use axum::Json;
     use axum::routing::get;
+    use utoipa::OpenApi;
     use utoipa_swagger_ui::SwaggerUi;
 
-    // Custom handler that serves OpenAPI 3.0 compatible JSON (for Mintlify)
+    let spec = endpoints.filter_spec(openapi::WireV1ApiDoc::openapi());
+    // OpenAPI 3.0 compatible JSON (for Mintlify)
     // Converts type: ["array", "null"] -> type: "array", nullable: true
-    async fn openapi_3_0_handler() -> Json<serde_json::Value> {
-        Json(openapi::WireV1ApiDoc::openapi_json())
-    }
+    let spec_3_0 = Json(openapi::WireV1ApiDoc::to_3_0_json(spec.clone()));
+    let spec_v2 = endpoints.filter_spec(openapi::WireV2ApiDoc::openapi());
+    let spec_v2_3_0 = Json(openapi::WireV1ApiDoc::to_3_0_json(spec_v2.clone()));
 
     axum::Router::new()
         .without_v07_checks()
         // OpenAPI 3.0 format with nullable array fixes
         // Keep as /api-docs/openapi.json for backward compatibility
-        .route("/api-docs/openapi.json", get(openapi_3_0_handler))
+        .route(
+            "/api-docs/openapi.json",
+            get(move || {
+                let spec = spec_3_0.clone();
+                async move { spec }
+            }),
+        )
+        .route(
+            "/api-docs/v2/openapi.json",
+            get(move || {
+                let spec = spec_v2_3_0.clone();
+                async move { spec }
+            }),
+        )
         // SwaggerUI: Creates /api-docs/openapi-3.1.json serving native OpenAPI 3.1 spec
         // SwaggerUI handles type: ["array", "null"] correctly, so no conversion needed
-        .merge(SwaggerUi::new("/swagger-ui").url(
-            "/api-docs/openapi-3.1.json",
-            openapi::WireV1ApiDoc::openapi(),
-        ))
+        .merge(
+            SwaggerUi::new("/swagger-ui")
+                .url("/api-docs/openapi-3.1.json", spec)
+                .url("/api-docs/v2/openapi-3.1.json", spec_v2),
+        )
 }
 
 #[derive(Clone)]
@@ -55,58 +151,70 @@ pub struct AppState {
     pub telemetry: Arc<Telemetry<ServerMetrics>>,
     pub pool: postgres_models::connection::Pool,
     pub read_only_pool: postgres_models::connection::Pool,
+    /// Pools reads are served from, falling back to `pool` while the read
+    /// replica is down, see [`read_fallback`]
+    pub reads: read_fallback::ReadPools,
     pub cache_pool: redis_cache::connection::Pool,
     pub config: Arc<Config>,
     pub shutdown: Arc<ShutdownCoordinator>,
+    /// JWT verifier, present when a JWT issuer is configured
+    pub jwt: Option<Arc<auth::jwt::JwtVerifier>>,
+    pub flags: Arc<flags::FeatureFlags>,
+    /// Reload handle of the log filter
+    pub log_filter: logging::LogFilter,
+    pub plant_events: events::PlantEvents,
+    /// What handlers served, for metrics, auditing and workers, see
+    /// [`events`]
+    pub domain_events: events::DomainEvents,
+    /// Aggregated readings, see [`repository`]
+    pub readings: Arc<dyn repository::EnergyReadingRepository>,
+    pub query_history: Arc<dyn repository::QueryHistoryRepository>,
+    /// Admission control of expensive aggregations, see [`admission`]
+    pub admission: Arc<admission::AdmissionControl>,
+    /// Buffered query history writes, see [`history_writer`]
+    pub history_writer: history_writer::HistoryWriter,
+    /// Aggregations in flight, shared by identical requests, see
+    /// [`single_flight`]
+    pub aggregations: Arc<single_flight::SingleFlight<Arc<Aggregation>>>,
+    /// Circuit breakers around database and cache calls, see
+    /// [`circuit_breaker`]
+    pub breakers: Arc<circuit_breaker::CircuitBreakers>,
+    /// Uploads of readings files in progress, see [`uploads`]
+    pub uploads: Arc<uploads::Uploads>,
+    /// Snapshots being taken or restored, see [`snapshots`]
+    pub snapshots: Arc<snapshots::Snapshots>,
 }
 
-impl AppState {}
+/// The readings of an aggregation, with the weather when requested.
+pub type Aggregation = (
+    Vec<postgres_models::models::energy_readings::AggregatedReading>,
+    Option<Vec<postgres_models::models::weather::AggregatedWeather>>,
+);
 
-impl axum::extract::FromRef<AppState> for postgres_models::connection::Pool {
-    fn from_ref(state: &AppState) -> Self {
-        state.pool.clone()
+impl AppState {
+    /// Whether a feature flag is on, see [`flags::FeatureFlags::is_enabled`].
+    pub async fn flag_enabled(&self, flag: flags::Flag) -> bool {
+        self.flags.is_enabled(flag, &self.cache_pool).await
     }
-}
-
-#[derive(serde::Deserialize)]
-pub struct Config {
-    // Service port
-    pub api_service_port: String,
-
-    // Loggers
-    pub rust_log: String,
-    #[serde(default)]
-    pub log_format: String,
-
-    // Db configs
-    pub database_credentials: String,
-    pub database_rw_endpoint: String,
-    pub database_ro_endpoint: String,
-
-    // Redis configs
-    pub redis_url: String,
-
-    // Energy readings Excel file path
-    pub energy_readings_xls_file_path: String,
-}
 
-impl Config {
-    pub fn load() -> Result<Self, envy::Error> {
-        // Load .env file if present (useful when running outside docker-compose)
-        match dotenv::dotenv() {
-            Ok(path) => eprintln!("Loaded .env from: {}", path.display()),
-            Err(e) => eprintln!("dotenv warning: {e}"),
+    /// Record an aggregation in the query history. Unless
+    /// `QUERY_HISTORY_STRICT` is set, the entry is buffered and written in a
+    /// batch later, see [`history_writer`], so the request waits for its
+    /// read alone.
+    pub async fn record_query(
+        &self,
+        entry: postgres_models::models::query_history::NewQueryHistory,
+    ) -> repository::RepositoryResult<()> {
+        if self.config.query_history_strict {
+            return self.query_history.create(entry).await.map(drop);
         }
-
-        envy::from_env::<Config>()
+        self.history_writer.send(entry).await;
+        Ok(())
     }
+}
 
-    pub fn database_credentials(
-        &self,
-    ) -> postgres_models::connection::Credentials {
-        serde_json::from_str::<postgres_models::connection::Credentials>(
-            self.database_credentials.as_str(),
-        )
-        .expect("creds must be valid")
+impl axum::extract::FromRef<AppState> for postgres_models::connection::Pool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
     }
 }
@@ -0,0 +1,353 @@
+//! Addresses the server listens on, and how connections are served.
+//!
+//! Public listeners (`LISTEN_ADDRS`, by default every interface on
+//! `API_SERVICE_PORT`) serve the API. When internal listeners are configured
+//! (`INTERNAL_LISTEN_ADDRS`), the admin and metrics routes move to them, so
+//! they can be kept off the public network. gRPC listeners
+//! (`GRPC_LISTEN_ADDRS`) serve [`crate::grpc`]. Each address is a TCP socket
+//! address or a Unix domain socket, `unix:/run/wire/api.sock`.
+//!
+//! Connections are served by hyper with the [`HttpSettings`] from the config.
+//! HTTP/2 is negotiated through ALPN over TLS, and accepted with prior
+//! knowledge (h2c) over plain TCP and Unix sockets, as Envoy and other
+//! proxies send it upstream.
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::Router;
+use axum::extract::{ConnectInfo, Request};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+
+use crate::shutdown::ShutdownCoordinator;
+
+/// How long in-flight connections may finish after shutdown starts.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a client may take to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    /// Unix domain socket; always plain HTTP, TLS only applies to TCP
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("Unix socket address needs a path".to_string());
+            }
+            return Ok(ListenAddr::Unix(PathBuf::from(path)));
+        }
+        s.parse().map(ListenAddr::Tcp).map_err(|_| {
+            format!(
+                "Invalid listen address `{s}`, expected `host:port` or \
+                 `unix:/path`"
+            )
+        })
+    }
+}
+
+impl Display for ListenAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => addr.fmt(f),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Parse a comma-separated list of listen addresses.
+pub fn parse_addrs(list: &str) -> Result<Vec<ListenAddr>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// HTTP protocol settings of every listener.
+#[derive(Debug, Clone)]
+pub struct HttpSettings {
+    /// Accept HTTP/2 besides HTTP/1.1
+    pub http2: bool,
+    /// Reuse HTTP/1.1 connections for several requests
+    pub http1_keep_alive: bool,
+    /// Interval of HTTP/2 keep-alive pings, `None` to send none
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// How long an HTTP/2 ping may go unacknowledged before the connection
+    /// is closed
+    pub http2_keep_alive_timeout: Duration,
+    /// Streams a client may have open at once on one HTTP/2 connection
+    pub http2_max_concurrent_streams: u32,
+    /// Largest accepted request head: the HTTP/1 read buffer and the HTTP/2
+    /// header list size. `None` keeps hyper's defaults
+    pub max_header_bytes: Option<u32>,
+}
+
+/// Serves the connections of one listener.
+struct Server {
+    settings: HttpSettings,
+    auto: auto::Builder<TokioExecutor>,
+    http1: http1::Builder,
+    tls: Option<TlsAcceptor>,
+}
+
+impl Server {
+    fn new(
+        settings: &HttpSettings,
+        tls: Option<Arc<rustls::ServerConfig>>,
+    ) -> Self {
+        let mut auto = auto::Builder::new(TokioExecutor::new());
+        let mut http1 = http1::Builder::new();
+        auto.http1()
+            .timer(TokioTimer::new())
+            .keep_alive(settings.http1_keep_alive);
+        http1
+            .timer(TokioTimer::new())
+            .keep_alive(settings.http1_keep_alive);
+        auto.http2()
+            .timer(TokioTimer::new())
+            .keep_alive_interval(settings.http2_keep_alive_interval)
+            .keep_alive_timeout(settings.http2_keep_alive_timeout)
+            .max_concurrent_streams(settings.http2_max_concurrent_streams);
+        if let Some(max) = settings.max_header_bytes {
+            auto.http1().max_buf_size(max as usize);
+            http1.max_buf_size(max as usize);
+            auto.http2().max_header_list_size(max);
+        }
+
+        let tls = tls.map(|config| {
+            if settings.http2 {
+                TlsAcceptor::from(config)
+            } else {
+                let mut config = (*config).clone();
+                config.alpn_protocols = vec![b"http/1.1".to_vec()];
+                TlsAcceptor::from(Arc::new(config))
+            }
+        });
+
+        Self {
+            settings: settings.clone(),
+            auto,
+            http1,
+            tls,
+        }
+    }
+
+    async fn serve_tcp(
+        self: Arc<Self>,
+        stream: tokio::net::TcpStream,
+        peer: SocketAddr,
+        app: Router,
+        shutdown: watch::Receiver<bool>,
+    ) {
+        let _ = stream.set_nodelay(true);
+        let Some(tls) = &self.tls else {
+            return self.serve_io(stream, Some(peer), app, shutdown).await;
+        };
+
+        match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(stream))
+            .await
+        {
+            Ok(Ok(stream)) => {
+                self.serve_io(stream, Some(peer), app, shutdown).await
+            }
+            Ok(Err(e)) => {
+                tracing::debug!(%peer, "TLS handshake failed: {e}");
+            }
+            Err(_) => tracing::debug!(%peer, "TLS handshake timed out"),
+        }
+    }
+
+    async fn serve_io<I>(
+        &self,
+        io: I,
+        peer: Option<SocketAddr>,
+        app: Router,
+        shutdown: watch::Receiver<bool>,
+    ) where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let service =
+            hyper::service::service_fn(move |mut req: Request<Incoming>| {
+                if let Some(peer) = peer {
+                    req.extensions_mut().insert(ConnectInfo(peer));
+                }
+                app.clone().call(req)
+            });
+        let io = TokioIo::new(io);
+
+        if self.settings.http2 {
+            let conn = self.auto.serve_connection_with_upgrades(io, service);
+            drive(conn, shutdown, |conn| conn.graceful_shutdown()).await;
+        } else {
+            let conn = self.http1.serve_connection(io, service).with_upgrades();
+            drive(conn, shutdown, |conn| conn.graceful_shutdown()).await;
+        }
+    }
+}
+
+/// Run a connection until it closes or, once shutdown starts, until its
+/// in-flight requests finish.
+async fn drive<C, E>(
+    conn: C,
+    mut shutdown: watch::Receiver<bool>,
+    graceful_shutdown: impl FnOnce(Pin<&mut C>),
+) where
+    C: Future<Output = Result<(), E>>,
+    E: Display,
+{
+    tokio::pin!(conn);
+    let result = tokio::select! {
+        result = conn.as_mut() => result,
+        // The watch borrow is dropped before the connection drains
+        _ = async { shutdown.wait_for(|stopping| *stopping).await.is_ok() } => {
+            graceful_shutdown(conn.as_mut());
+            match tokio::time::timeout(DRAIN_TIMEOUT, conn).await {
+                Ok(result) => result,
+                Err(_) => return,
+            }
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::debug!("Connection closed with error: {e}");
+    }
+}
+
+/// Serve `app` on `addr` until shutdown, over TLS when `tls` is given and
+/// the address is TCP. Returns once in-flight connections have finished.
+pub async fn serve(
+    addr: ListenAddr,
+    app: Router,
+    http: &HttpSettings,
+    tls: Option<Arc<rustls::ServerConfig>>,
+    shutdown: Arc<ShutdownCoordinator>,
+) -> anyhow::Result<()> {
+    let server = Arc::new(Server::new(http, tls));
+    // Shutdown fans out to the connections through a watch channel, which
+    // also reaches connections that start waiting after it was sent
+    let (stop, stopping) = watch::channel(false);
+    let mut connections = JoinSet::new();
+    // Created once: the coordinator only wakes futures that already exist
+    let shutting_down = shutdown.wait_for_shutdown();
+    tokio::pin!(shutting_down);
+
+    match &addr {
+        ListenAddr::Tcp(socket_addr) => {
+            let listener = tokio::net::TcpListener::bind(socket_addr)
+                .await
+                .with_context(|| format!("Failed to bind to {addr}"))?;
+            tracing::info!(%addr, tls = server.tls.is_some(), "Listening");
+
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = &mut shutting_down => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, peer)) => {
+                            connections.spawn(server.clone().serve_tcp(
+                                stream,
+                                peer,
+                                app.clone(),
+                                stopping.clone(),
+                            ));
+                        }
+                        Err(e) => accept_failed(&addr, e).await,
+                    },
+                }
+                while connections.try_join_next().is_some() {}
+            }
+        }
+        ListenAddr::Unix(path) => {
+            // A socket left behind by an earlier run would fail the bind
+            if std::fs::symlink_metadata(path)
+                .is_ok_and(|metadata| metadata.file_type().is_socket())
+            {
+                std::fs::remove_file(path).with_context(|| {
+                    format!("Failed to remove stale socket {}", path.display())
+                })?;
+            }
+            let listener = tokio::net::UnixListener::bind(path)
+                .with_context(|| format!("Failed to bind to {addr}"))?;
+            tracing::info!(%addr, "Listening");
+
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = &mut shutting_down => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            let server = server.clone();
+                            let app = app.clone();
+                            let stopping = stopping.clone();
+                            connections.spawn(async move {
+                                server
+                                    .serve_io(stream, None, app, stopping)
+                                    .await
+                            });
+                        }
+                        Err(e) => accept_failed(&addr, e).await,
+                    },
+                }
+                while connections.try_join_next().is_some() {}
+            }
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    let _ = stop.send(true);
+    while connections.join_next().await.is_some() {}
+    Ok(())
+}
+
+/// Back off briefly when accepting fails, e.g. on running out of file
+/// descriptors, rather than spinning.
+async fn accept_failed(addr: &ListenAddr, e: std::io::Error) {
+    tracing::warn!(%addr, "Failed to accept connection: {e}");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addrs() {
+        let addrs =
+            parse_addrs("0.0.0.0:50051, unix:/run/wire/api.sock,[::1]:9090,")
+                .unwrap();
+
+        assert_eq!(
+            addrs,
+            vec![
+                ListenAddr::Tcp("0.0.0.0:50051".parse().unwrap()),
+                ListenAddr::Unix(PathBuf::from("/run/wire/api.sock")),
+                ListenAddr::Tcp("[::1]:9090".parse().unwrap()),
+            ]
+        );
+        assert_eq!(addrs[1].to_string(), "unix:/run/wire/api.sock");
+        assert!(parse_addrs("localhost").is_err());
+        assert!(parse_addrs("unix:").is_err());
+    }
+}
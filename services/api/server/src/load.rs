@@ -0,0 +1,120 @@
+//! Load guidance for clients.
+//!
+//! Every `/v1` response carries `X-Server-Load`, the [`LoadClass`] of the
+//! pool serving reads, the read-write one while reads fall back to it, and
+//! `X-Poll-Interval`, the seconds a client polling the API should wait
+//! before its next request, so dashboards back off while the database is
+//! busy. Load is high while admission control finds the pool busy and
+//! queues expensive aggregations, see [`AdmissionControl::is_busy`], and
+//! normal from half of `ADMISSION_BUSY_CONNECTIONS` in use. The interval is `LOAD_POLL_INTERVAL_SECS` at low
+//! load, and doubles with each class above it. Nothing is enforced;
+//! clients ignoring the headers are served as before.
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::AppState;
+use crate::admission::AdmissionControl;
+
+pub const LOAD_HEADER: &str = "x-server-load";
+pub const POLL_INTERVAL_HEADER: &str = "x-poll-interval";
+
+/// How busy the pool serving reads is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LoadClass {
+    /// Less than half the busy mark in use
+    Low,
+    /// At least half the busy mark in use
+    Normal,
+    /// The busy mark reached; expensive aggregations wait
+    High,
+}
+
+impl LoadClass {
+    /// The class of a pool with `in_use` connections checked out, high
+    /// when `admission` finds it busy.
+    pub fn of(in_use: u32, admission: &AdmissionControl) -> Self {
+        if admission.is_busy_with(in_use) {
+            LoadClass::High
+        } else if admission.is_busy_with(in_use * 2) {
+            LoadClass::Normal
+        } else {
+            LoadClass::Low
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadClass::Low => "low",
+            LoadClass::Normal => "normal",
+            LoadClass::High => "high",
+        }
+    }
+
+    /// How long clients should wait between polls, given the interval at
+    /// low load.
+    pub fn poll_interval(&self, base: Duration) -> Duration {
+        match self {
+            LoadClass::Low => base,
+            LoadClass::Normal => base * 2,
+            LoadClass::High => base * 4,
+        }
+    }
+}
+
+/// Set `X-Server-Load` and `X-Poll-Interval` on responses, from the load
+/// of the pool serving reads once the request is served.
+pub async fn advise(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+
+    let pool = state.reads.serving().state();
+    let class = LoadClass::of(
+        pool.connections - pool.idle_connections,
+        &state.admission,
+    );
+    let interval = class.poll_interval(state.config.load_poll_interval);
+    let headers = response.headers_mut();
+    headers.insert(LOAD_HEADER, HeaderValue::from_static(class.as_str()));
+    headers.insert(POLL_INTERVAL_HEADER, interval.as_secs().into());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admission::AdmissionSettings;
+
+    #[test]
+    fn test_classifies_pool_load() {
+        let admission = |busy_connections| {
+            AdmissionControl::new(AdmissionSettings {
+                budget: 35_040,
+                busy_connections,
+                queue_size: 8,
+                queue_timeout: Duration::from_secs(5),
+            })
+        };
+        let busy_from_16 = admission(16);
+        assert_eq!(LoadClass::of(0, &busy_from_16), LoadClass::Low);
+        assert_eq!(LoadClass::of(7, &busy_from_16), LoadClass::Low);
+        assert_eq!(LoadClass::of(8, &busy_from_16), LoadClass::Normal);
+        assert_eq!(LoadClass::of(16, &busy_from_16), LoadClass::High);
+        assert_eq!(LoadClass::of(20, &busy_from_16), LoadClass::High);
+        // Admission finds the pool always busy without a mark
+        assert_eq!(LoadClass::of(0, &admission(0)), LoadClass::High);
+
+        let base = Duration::from_secs(15);
+        assert_eq!(LoadClass::Low.poll_interval(base), base);
+        assert_eq!(
+            LoadClass::High.poll_interval(base),
+            Duration::from_secs(60)
+        );
+    }
+}
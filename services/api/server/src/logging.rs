@@ -0,0 +1,68 @@
+//! Runtime control of the log filter.
+//!
+//! The tracing `EnvFilter` is installed behind a reload layer, so admins can
+//! change log levels (`PUT /admin/log-level`) without a redeploy. Changes are
+//! per instance and last until the next restart, which goes back to
+//! `RUST_LOG`.
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+pub type FilterLayer = reload::Layer<EnvFilter, Registry>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogFilterError {
+    #[error("Invalid filter directives: {0}")]
+    Invalid(String),
+
+    #[error("Failed to reload the log filter: {0}")]
+    Reload(#[from] reload::Error),
+}
+
+/// Handle to the installed log filter.
+#[derive(Clone)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogFilter {
+    /// Wrap `filter` in a reloadable layer, to be installed directly on the
+    /// registry.
+    pub fn new(filter: EnvFilter) -> (FilterLayer, Self) {
+        let (layer, handle) = reload::Layer::new(filter);
+        (layer, Self { handle })
+    }
+
+    /// Directives of the active filter, e.g. `info,wire_api=debug`.
+    pub fn current(&self) -> Result<String, LogFilterError> {
+        Ok(self.handle.with_current(|filter| filter.to_string())?)
+    }
+
+    /// Replace the active filter with `directives` (`RUST_LOG` syntax,
+    /// including per-target directives), returning the new filter.
+    pub fn set(&self, directives: &str) -> Result<String, LogFilterError> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|e| LogFilterError::Invalid(e.to_string()))?;
+        self.handle.reload(filter)?;
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_filter() {
+        let (_layer, filter) = LogFilter::new(EnvFilter::new("info"));
+        assert_eq!(filter.current().unwrap(), "info");
+
+        assert_eq!(
+            filter.set("warn,wire_api::auth=debug").unwrap(),
+            "wire_api::auth=debug,warn"
+        );
+        assert!(matches!(
+            filter.set("wire_api=loud"),
+            Err(LogFilterError::Invalid(_))
+        ));
+        assert_eq!(filter.current().unwrap(), "wire_api::auth=debug,warn");
+    }
+}
@@ -3,20 +3,108 @@ use axum::{http::StatusCode, response::Json};
 use serde_json::json;
 use std::sync::Arc;
 use telemetry::metrics::Telemetry;
-use tower_http::{
-    catch_panic::CatchPanicLayer, compression::CompressionLayer,
-    trace::TraceLayer,
-};
-use wire_api::metrics::ServerMetrics;
+use tower_http::{catch_panic::CatchPanicLayer, trace::TraceLayer};
+use wire_api::cli::{Cli, Command};
+use wire_api::config::LogFormat;
+use wire_api::listener;
+use wire_api::logging::LogFilter;
+use wire_api::metrics::{AcquireMetrics, ServerMetrics, statement_listener};
 use wire_api::shutdown::{ShutdownCoordinator, listen_for_shutdown_signals};
+use wire_api::startup::{StartupStatus, StatusServer, retry};
 
 use tracing_subscriber::filter::EnvFilter;
 use tracing_subscriber::fmt::time::UtcTime;
 use tracing_subscriber::prelude::*;
 
-const VERSION: Option<&'static str> = option_env!("VERSION");
-const MIGRATIONS: diesel_migrations::EmbeddedMigrations =
-    diesel_migrations::embed_migrations!("./../../../db/migrations");
+/// Route groups served by a listener.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Routes {
+    /// The API and its docs
+    Public,
+    /// Admin and metrics
+    Internal,
+    All,
+}
+
+fn router(
+    app_state: &wire_api::AppState,
+    startup: &Arc<StartupStatus>,
+    routes: Routes,
+) -> axum::Router {
+    let mut app = axum::Router::new()
+        .without_v07_checks()
+        .route("/health", {
+            let state = app_state.clone();
+            axum::routing::get(move || {
+                let state = state.clone();
+                async move { wire_api::health::handler(state).await }
+            })
+        })
+        .route("/startup", {
+            let startup = startup.clone();
+            axum::routing::get(move || {
+                wire_api::startup::handler(startup.clone())
+            })
+        })
+        .route(
+            "/version",
+            axum::routing::get(|| async {
+                Json(wire_api::build_info::build_info())
+            }),
+        );
+
+    if routes != Routes::Public {
+        app = app.route("/metrics", {
+            let telemetry = app_state.telemetry.clone();
+            axum::routing::get(move || {
+                let telemetry = telemetry.clone();
+                async move {
+                    (
+                        axum::http::StatusCode::OK,
+                        [(
+                            axum::http::header::CONTENT_TYPE,
+                            "text/plain; charset=utf-8",
+                        )],
+                        telemetry.get_metrics().await,
+                    )
+                }
+            })
+        });
+    }
+    let mut api = axum::Router::new();
+    if routes != Routes::Public {
+        api = api.merge(wire_api::get_internal_routes(app_state.clone()));
+    }
+    if routes != Routes::Internal {
+        api = api.merge(wire_api::get_wire_api_v1_routes(app_state.clone()));
+    }
+    app = app.nest("/api/wire/v1", api);
+    if routes != Routes::Internal {
+        app = app.nest(
+            "/api/wire/v2",
+            wire_api::get_wire_api_v2_routes(app_state.clone()),
+        );
+    }
+
+    let app = app
+        .fallback(fallback_handler)
+        .layer(tower_http::cors::CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+        .layer(app_state.config.compression.layer())
+        .layer(CatchPanicLayer::new());
+
+    if routes == Routes::Internal {
+        app
+    } else {
+        app.merge(wire_api::get_openapi_routes(&app_state.config.endpoints))
+    }
+}
+
+fn grpc_router(app_state: &wire_api::AppState) -> axum::Router {
+    wire_api::grpc::get_routes(app_state)
+        .layer(TraceLayer::new_for_grpc())
+        .layer(CatchPanicLayer::new())
+}
 
 async fn fallback_handler() -> (StatusCode, Json<serde_json::Value>) {
     (
@@ -30,33 +118,120 @@ async fn fallback_handler() -> (StatusCode, Json<serde_json::Value>) {
 }
 
 fn main() {
-    let version = VERSION.unwrap_or("unknown").to_string();
-    let config = wire_api::Config::load().expect("Failed to load config");
+    let command = <Cli as clap::Parser>::parse().command();
 
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .expect("Failed to build tokio runtime")
         .block_on(async {
-            if let Err(e) = setup(config, version).await {
-                tracing::error!("Fatal error during setup: {e:#}");
+            if let Command::GenerateOpenapi {
+                spec,
+                api,
+                output,
+                out_dir,
+            } = &command
+            {
+                let result = match out_dir {
+                    Some(dir) => wire_api::cli::generate_openapi_files(dir),
+                    None => wire_api::cli::generate_openapi(
+                        *api,
+                        *spec,
+                        output.as_deref(),
+                    ),
+                };
+                if let Err(e) = result {
+                    eprintln!("Error: {e:#}");
+                    std::process::exit(1);
+                }
+                return;
+            }
+
+            if let Command::Loadgen(args) = command {
+                if let Err(e) = wire_api::cli::loadgen::run(args).await {
+                    eprintln!("Error: {e:#}");
+                    std::process::exit(1);
+                }
+                return;
+            }
+
+            let config = match wire_api::Config::load_async().await {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            };
+
+            if let Command::Serve = command {
+                if let Err(e) = serve(config).await {
+                    tracing::error!("Fatal error during setup: {e:#}");
+                    std::process::exit(1);
+                }
+                return;
+            }
+
+            // Logs go to stderr, leaving stdout to the command's output
+            tracing_subscriber::fmt()
+                .with_env_filter(
+                    EnvFilter::try_from_default_env()
+                        .unwrap_or_else(|_| EnvFilter::new("info")),
+                )
+                .with_writer(std::io::stderr)
+                .init();
+            if let Err(e) = run(command, config).await {
+                eprintln!("Error: {e:#}");
                 std::process::exit(1);
             }
         });
 }
 
-async fn setup(
-    config: wire_api::Config,
-    _version: String,
-) -> anyhow::Result<()> {
-    let filter_layer = EnvFilter::try_from_default_env()
+/// Run an operational command, everything but `serve`.
+async fn run(command: Command, config: wire_api::Config) -> anyhow::Result<()> {
+    match command {
+        Command::Migrate { command } => {
+            let pool = wire_api::cli::connect_database(
+                &config,
+                &config.database_rw_endpoint,
+                None,
+            )
+            .await?;
+            wire_api::cli::migrate::run(command, &pool).await
+        }
+        Command::Import {
+            file,
+            tenant,
+            timezone,
+            dst,
+        } => {
+            let mut source_time = config.import_source_time;
+            source_time.timezone = timezone.unwrap_or(source_time.timezone);
+            source_time.dst = dst.unwrap_or(source_time.dst);
+            wire_api::cli::import(&file, &tenant, source_time, &config).await
+        }
+        Command::Export(args) => {
+            wire_api::cli::export::run(args, &config).await
+        }
+        Command::CheckConfig => {
+            wire_api::cli::check_config(&config);
+            Ok(())
+        }
+        Command::Serve
+        | Command::GenerateOpenapi { .. }
+        | Command::Loadgen(_) => {
+            unreachable!("handled before loading the config")
+        }
+    }
+}
+
+async fn serve(config: wire_api::Config) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("info"))
         .map_err(|e| anyhow::anyhow!(e))
         .context("Failed to initialize tracing filter")?;
+    let (filter_layer, log_filter) = LogFilter::new(filter);
 
-    let use_json = config.log_format != "pretty";
-
-    if use_json {
+    if config.log_format == LogFormat::Json {
         let fmt_layer = tracing_subscriber::fmt::layer()
             .with_timer(UtcTime::rfc_3339())
             .with_target(true)
@@ -78,133 +253,441 @@ async fn setup(
             .init();
     };
 
-    let addr: String = format!("0.0.0.0:{}", config.api_service_port);
-    tracing::info!("Starting wire-api service at: {addr}");
-
-    let db_creds = config.database_credentials();
-    let db_username = db_creds.username;
-    let db_password = db_creds.password;
-    let db_rw_endpoint = config.database_rw_endpoint.clone();
-    let db_ro_endpoint = config.database_ro_endpoint.clone();
-
-    let db_rw_url = format!(
-        "postgresql://{db_username}:{db_password}@{db_rw_endpoint}:5432/wire"
-    );
-    let db_ro_url = format!(
-        "postgresql://{db_username}:{db_password}@{db_ro_endpoint}:5432/wire"
+    let tls_settings = config.tls.clone();
+    let http_settings = config.http.clone();
+    let public_addrs = config.listen_addrs.clone();
+    let internal_addrs = config.internal_listen_addrs.clone();
+    let grpc_addrs = config.grpc_listen_addrs.clone();
+    if let Some(filter) = &config.admin_ip_filter {
+        tracing::info!(
+            allow = filter.allow.len(),
+            deny = filter.deny.len(),
+            trusted_proxies = filter.trusted_proxies.len(),
+            "Admin IP filter enabled"
+        );
+    }
+    let build = wire_api::build_info::build_info();
+    tracing::info!(
+        version = build.version,
+        release = build.release,
+        git_sha = build.git_sha,
+        public = ?public_addrs.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        internal = ?internal_addrs.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        grpc = ?grpc_addrs.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        "Starting wire-api service"
     );
 
-    let db_pool = postgres_models::connection::establish_connection(db_rw_url)
-        .await
-        .context("Failed to connect to Postgres (read-write)")?;
+    if !config.csrf.groups.is_empty() {
+        tracing::info!(
+            groups = ?config.csrf.groups,
+            trusted_origins = ?config.csrf.trusted_origins,
+            "CSRF protection enabled"
+        );
+    }
 
-    let db_pool_conn = db_pool
-        .get_owned()
+    let metrics =
+        ServerMetrics::new(None).context("Failed to create server metrics")?;
+    let telemetry = Telemetry::new(Some(metrics))
         .await
-        .context("Failed to get connection from pool for migrations")?;
-
-    postgres_models::connection::run_migrations(db_pool_conn, MIGRATIONS)
+        .context("Failed to create telemetry")?;
+    telemetry
+        .start()
         .await
-        .map_err(|e| anyhow::anyhow!("{e}"))
-        .context("Failed to run database migrations")?;
+        .context("Failed to start telemetry")?;
+    tracing::info!("Initialized telemetry");
+    postgres_models::connection::set_acquire_listener(Box::new(
+        AcquireMetrics(telemetry.clone()),
+    ));
 
-    wire_api::data_loader::load_energy_readings(
+    let startup = Arc::new(StartupStatus::default());
+    // The listeners only start once the dependencies are up, so the status
+    // is served on its own meanwhile
+    let status_addr = internal_addrs
+        .iter()
+        .chain(&public_addrs)
+        .find_map(|addr| match addr {
+            listener::ListenAddr::Tcp(addr) => Some(*addr),
+            listener::ListenAddr::Unix(_) => None,
+        })
+        .filter(|_| tls_settings.is_none());
+    let status_server = match status_addr {
+        Some(addr) => StatusServer::start(addr, startup.clone())
+            .await
+            .inspect_err(|e| {
+                tracing::warn!("Not serving the startup status: {e:#}");
+            })
+            .ok(),
+        None => None,
+    };
+    let retry_settings = config.startup_retry.clone();
+
+    let db_pool = retry("postgres_rw", &retry_settings, &startup, || {
+        wire_api::cli::connect_database(
+            &config,
+            &config.database_rw_endpoint,
+            Some(statement_listener(telemetry.clone(), "read_write")),
+        )
+    })
+    .await?;
+
+    if config.auto_migrate {
+        let applied = wire_api::cli::migrate::apply(&db_pool)
+            .await
+            .context("Failed to run database migrations")?;
+        if !applied.is_empty() {
+            tracing::info!(?applied, "Applied database migrations");
+        }
+    } else {
+        match wire_api::cli::migrate::pending(&db_pool).await {
+            Ok(pending) if !pending.is_empty() => tracing::warn!(
+                ?pending,
+                "Database migrations are pending, apply them with `wire-api migrate up`"
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to check migrations: {e:#}"),
+        }
+    }
+
+    let imported = wire_api::data_loader::load_energy_readings(
         &config.energy_readings_xls_file_path,
+        config.import_source_time,
+        config.quantity_policy,
         &db_pool,
     )
     .await
-    .context("Failed to load energy readings")?;
+    .context("Failed to load energy readings")?
+    .summary()
+    .inserted
+        > 0;
 
     let read_only_pool =
-        postgres_models::connection::establish_connection(db_ro_url)
-            .await
-            .context("Failed to connect to Postgres (read-only)")?;
+        retry("postgres_ro", &retry_settings, &startup, || {
+            wire_api::cli::connect_database(
+                &config,
+                &config.database_ro_endpoint,
+                Some(statement_listener(telemetry.clone(), "read_only")),
+            )
+        })
+        .await?;
 
-    let redis_pool =
-        redis_cache::connection::establish_connection(config.redis_url.clone())
-            .await
-            .context("Failed to connect to Redis")?;
+    let redis_pool = retry("redis", &retry_settings, &startup, || async {
+        redis_cache::connection::establish_connection(
+            config.redis_url.to_string(),
+        )
+        .await
+        .context("Failed to connect to Redis")
+    })
+    .await?;
 
     let shutdown = Arc::new(ShutdownCoordinator::new(
         db_pool.clone(),
         redis_pool.clone(),
     ));
 
-    let metrics =
-        ServerMetrics::new(None).context("Failed to create server metrics")?;
-    let telemetry = Telemetry::new(Some(metrics))
-        .await
-        .context("Failed to create telemetry")?;
-    telemetry
-        .start()
-        .await
-        .context("Failed to start telemetry")?;
-    tracing::info!("Initialized telemetry");
+    if let Some(settings) = config.database_pool_sizing.clone() {
+        let mut pools = Vec::new();
+        for (name, endpoint) in [
+            ("read_write", &config.database_rw_endpoint),
+            ("read_only", &config.database_ro_endpoint),
+        ] {
+            pools.push(
+                wire_api::pool_sizing::MonitoredPool::connected(
+                    name,
+                    config.database_url(endpoint),
+                )
+                .await
+                .with_context(|| {
+                    format!("Failed to read the pool size of {endpoint}")
+                })?,
+            );
+        }
+        let monitor = wire_api::pool_sizing::PoolSizeMonitor::new(
+            pools,
+            telemetry.clone(),
+            settings,
+        );
+        tokio::spawn(monitor.run(shutdown.clone()));
+    }
+
+    let domain_events = wire_api::events::DomainEvents::default();
+    tokio::spawn(
+        wire_api::events::EventMetrics::new(&domain_events, telemetry.clone())
+            .run(shutdown.clone()),
+    );
+    tokio::spawn(
+        wire_api::events::AuditLog::new(&domain_events).run(shutdown.clone()),
+    );
+
+    let dispatcher = wire_api::webhooks::dispatcher::WebhookDispatcher::new(
+        db_pool.clone(),
+        telemetry.clone(),
+        domain_events.clone(),
+        config.webhook_dispatcher.clone(),
+    )
+    .context("Failed to create webhook dispatcher")?;
+    tokio::spawn(dispatcher.run(shutdown.clone()));
+
+    let relay = wire_api::outbox::OutboxRelay::new(
+        db_pool.clone(),
+        redis_pool.clone(),
+        telemetry.clone(),
+        domain_events.clone(),
+        config.outbox_relay.clone(),
+    );
+    tokio::spawn(relay.run(shutdown.clone()));
+
+    let plant_events = wire_api::events::PlantEvents::default();
+    let scheduler = wire_api::maintenance::MaintenanceScheduler::new(
+        db_pool.clone(),
+        plant_events.clone(),
+        config.maintenance_scheduler.clone(),
+    );
+    tokio::spawn(scheduler.run(shutdown.clone()));
+
+    let evaluator = wire_api::alerts::AlertEvaluator::new(
+        db_pool.clone(),
+        telemetry.clone(),
+        config.alert_evaluator.clone(),
+    );
+    tokio::spawn(evaluator.run(shutdown.clone()));
+
+    let notifier =
+        wire_api::notifications::dispatcher::NotificationDispatcher::new(
+            db_pool.clone(),
+            telemetry.clone(),
+            config.smtp.as_ref(),
+            config.notification_dispatcher.clone(),
+        )
+        .context("Failed to create notification dispatcher")?;
+    tokio::spawn(notifier.run(shutdown.clone()));
 
+    let monitor = wire_api::notifications::health::HealthMonitor::new(
+        db_pool.clone(),
+        read_only_pool.clone(),
+        redis_pool.clone(),
+        config.health_monitor.clone(),
+    );
+    tokio::spawn(monitor.run(shutdown.clone()));
+
+    if let Some(settings) = config.weather.clone() {
+        let importer = wire_api::weather::WeatherImporter::new(
+            db_pool.clone(),
+            telemetry.clone(),
+            settings,
+        )
+        .context("Failed to create weather importer")?;
+        tokio::spawn(importer.run(shutdown.clone()));
+    }
+
+    if let Some(settings) = config.market_prices.clone() {
+        let importer = wire_api::market_prices::PriceImporter::new(
+            db_pool.clone(),
+            telemetry.clone(),
+            settings,
+        )
+        .context("Failed to create market price importer")?;
+        tokio::spawn(importer.run(shutdown.clone()));
+    }
+
+    if let Some(settings) = config.shadow.clone() {
+        let shadow_pool = wire_api::cli::connect_database(
+            &config,
+            &settings.database_endpoint,
+            Some(statement_listener(telemetry.clone(), "shadow")),
+        )
+        .await?;
+        let runner = wire_api::shadow::ShadowRunner::new(
+            read_only_pool.clone(),
+            shadow_pool,
+            telemetry.clone(),
+            settings,
+        );
+        tokio::spawn(runner.run(shutdown.clone()));
+    }
+
+    let jwt = config
+        .jwt
+        .clone()
+        .map(|settings| {
+            tracing::info!(issuer = %settings.issuer, "JWT authentication enabled");
+            wire_api::auth::jwt::JwtVerifier::new(settings).map(Arc::new)
+        })
+        .transpose()
+        .context("Failed to create JWT verifier")?;
+
+    let flags = Arc::new(wire_api::flags::FeatureFlags::new(
+        config.feature_flags.clone(),
+    ));
+
+    let mut reads =
+        wire_api::read_fallback::ReadPools::new(read_only_pool.clone());
+    if let Some(settings) = &config.read_fallback {
+        reads = reads.with_fallback(
+            db_pool.clone(),
+            settings.concurrency,
+            telemetry.clone(),
+        );
+        tokio::spawn(
+            wire_api::read_fallback::ReplicaMonitor::new(
+                reads.clone(),
+                settings.check_interval,
+            )
+            .run(shutdown.clone()),
+        );
+    }
+    let mut readings =
+        wire_api::repository::DieselEnergyReadings::new(reads.clone());
+    if let Some(settings) = &config.hedged_reads {
+        tracing::info!(
+            delay = ?settings.delay,
+            concurrency = settings.concurrency,
+            "Hedging aggregations on the read-write pool"
+        );
+        readings =
+            readings.with_hedge(db_pool.clone(), settings, telemetry.clone());
+    }
+    let readings = Arc::new(readings);
+    let query_history =
+        Arc::new(wire_api::repository::cached::CachedQueryHistory::new(
+            Arc::new(wire_api::repository::DieselQueryHistory::new(
+                db_pool.clone(),
+                reads.clone(),
+            )),
+            redis_pool.clone(),
+            flags.clone(),
+        ));
+    let admission = Arc::new(wire_api::admission::AdmissionControl::new(
+        config.admission.clone(),
+    ));
+    let (history_writer, history_flusher) =
+        wire_api::history_writer::HistoryWriter::new(
+            query_history.clone(),
+            telemetry.clone(),
+            config.query_history_writer.clone(),
+        );
+    shutdown
+        .drain_before_close(tokio::spawn(history_flusher.run(shutdown.clone())))
+        .await;
+    let breakers = Arc::new(wire_api::circuit_breaker::CircuitBreakers::new(
+        config.circuit_breaker.clone(),
+        Some(wire_api::metrics::breaker_listener(telemetry.clone())),
+    ));
+    let uploads =
+        Arc::new(wire_api::uploads::Uploads::new(config.uploads.clone()));
+    let snapshots = Arc::new(wire_api::snapshots::Snapshots::new(
+        config.snapshots.clone(),
+    ));
     let app_state = wire_api::AppState {
         telemetry,
         pool: db_pool,
         read_only_pool,
+        reads,
         cache_pool: redis_pool,
         config: Arc::new(config),
         shutdown: shutdown.clone(),
+        jwt,
+        flags,
+        log_filter,
+        plant_events,
+        domain_events,
+        readings,
+        query_history,
+        admission,
+        history_writer,
+        aggregations: Arc::default(),
+        breakers,
+        uploads,
+        snapshots,
     };
-    let app = axum::Router::new()
-        .without_v07_checks()
-        .route("/health", {
-            let state = app_state.clone();
-            axum::routing::get(move || {
-                let state = state.clone();
-                async move { wire_api::health::handler(state).await }
-            })
-        })
-        .route(
-            "/version",
-            axum::routing::get(|| async { VERSION.unwrap_or("unknown") }),
-        )
-        .route("/metrics", {
-            let telemetry = app_state.telemetry.clone();
-            axum::routing::get(move || {
-                let telemetry = telemetry.clone();
-                async move {
-                    (
-                        axum::http::StatusCode::OK,
-                        [(
-                            axum::http::header::CONTENT_TYPE,
-                            "text/plain; charset=utf-8",
-                        )],
-                        telemetry.get_metrics().await,
-                    )
-                }
-            })
-        })
-        .nest(
-            "/api/wire/v1",
-            wire_api::get_wire_api_v1_routes(app_state.clone()),
-        )
-        .fallback(fallback_handler)
-        .layer(tower_http::cors::CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .layer(CompressionLayer::new())
-        .layer(CatchPanicLayer::new())
-        .merge(wire_api::get_openapi_routes());
+    wire_api::api_modules().drain_on_shutdown(&app_state).await;
+    if imported {
+        Arc::new(wire_api::cache_warmer::CacheWarmer::new(
+            app_state.readings.clone(),
+            app_state.query_history.clone(),
+            app_state.cache_pool.clone(),
+            app_state.flags.clone(),
+            app_state.config.cache_warmer.clone(),
+        ))
+        .spawn(wire_api::auth::TenantContext::default());
+    }
+    // Without internal listeners the public ones serve every route
+    let mut routes = if internal_addrs.is_empty() {
+        vec![(
+            public_addrs,
+            router(&app_state, &startup, Routes::All),
+            &http_settings,
+        )]
+    } else {
+        vec![
+            (
+                public_addrs,
+                router(&app_state, &startup, Routes::Public),
+                &http_settings,
+            ),
+            (
+                internal_addrs,
+                router(&app_state, &startup, Routes::Internal),
+                &http_settings,
+            ),
+        ]
+    };
+    // gRPC needs HTTP/2 even where the HTTP listeners don't offer it
+    let grpc_http_settings = listener::HttpSettings {
+        http2: true,
+        ..http_settings.clone()
+    };
+    if !grpc_addrs.is_empty() {
+        routes.push((grpc_addrs, grpc_router(&app_state), &grpc_http_settings));
+    }
 
     let shutdown_handle = shutdown.clone();
-    tokio::spawn(async move {
-        listen_for_shutdown_signals().await;
+    let shutdown_sequence = tokio::spawn(async move {
+        tokio::select! {
+            _ = listen_for_shutdown_signals() => {}
+            _ = shutdown_handle.requested() => {
+                tracing::info!("Shutdown requested, starting graceful shutdown");
+            }
+        }
         shutdown_handle.shutdown().await;
     });
 
-    let listener = tokio::net::TcpListener::bind(&addr)
-        .await
-        .with_context(|| format!("Failed to bind to {addr}"))?;
-    let shutdown_for_serve = shutdown.clone();
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            shutdown_for_serve.wait_for_shutdown().await
-        })
-        .await
-        .context("Server exited with error")?;
+    let tls_config = match tls_settings {
+        Some(settings) => {
+            let tls_config = wire_api::tls::server_config(&settings)
+                .context("Failed to configure TLS")?;
+            tracing::info!(
+                mutual_tls = settings.client_ca_path.is_some(),
+                "Serving HTTPS on TCP listeners"
+            );
+            Some(Arc::new(tls_config))
+        }
+        None => None,
+    };
+
+    startup.set_ready();
+    if let Some(status_server) = status_server {
+        status_server.stop().await;
+    }
+    let mut servers = tokio::task::JoinSet::new();
+    for (addrs, app, http_settings) in routes {
+        for addr in addrs {
+            let app = app.clone();
+            let http_settings = http_settings.clone();
+            let tls_config = tls_config.clone();
+            let shutdown = shutdown.clone();
+            servers.spawn(async move {
+                listener::serve(addr, app, &http_settings, tls_config, shutdown)
+                    .await
+            });
+        }
+    }
+    // Any listener failing, e.g. to bind, stops the service
+    while let Some(result) = servers.join_next().await {
+        result.context("Listener task panicked")??;
+    }
+    // The listeners stop as shutdown begins; let it drain the workers and
+    // close the pools before exiting
+    shutdown_sequence.await.context("Shutdown task panicked")?;
 
     Ok(())
 }
@@ -0,0 +1,202 @@
+//! Plant maintenance windows.
+//!
+//! The [`MaintenanceScheduler`] moves each window through `scheduled`,
+//! `active` and `completed` as the clock passes its start and end. A plant
+//! is in `maintenance` while any of its windows is active and gets back the
+//! status it had before the first of them started, unless it was changed in
+//! the meantime. Decommissioned plants are left alone.
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+use postgres_models::models::maintenance_windows::{
+    MaintenanceWindow, window_status,
+};
+use postgres_models::models::plants::{Plant, plant_status};
+
+use crate::events::{PlantEvent, PlantEvents};
+use crate::shutdown::ShutdownCoordinator;
+
+/// Windows moved per scheduler transaction.
+const BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Clone)]
+pub struct SchedulerSettings {
+    pub poll_interval: Duration,
+}
+
+/// Mark an active `window` as `new_status` and restore its plant when no
+/// other window keeps it in maintenance. Returns the plant if it changed.
+pub async fn leave(
+    window: &MaintenanceWindow,
+    new_status: &str,
+    conn: &mut AsyncPgConnection,
+) -> Result<Option<Plant>, diesel::result::Error> {
+    MaintenanceWindow::set_status(window.id, new_status, None, conn).await?;
+
+    let Some(previous) = &window.previous_plant_status else {
+        return Ok(None);
+    };
+    if previous == plant_status::MAINTENANCE
+        || !MaintenanceWindow::active_for_plant(window.plant_id, conn)
+            .await?
+            .is_empty()
+    {
+        return Ok(None);
+    }
+    match Plant::find(&window.tenant_id, window.plant_id, conn).await? {
+        // Changed by hand during the window
+        Some(plant) if plant.status != plant_status::MAINTENANCE => Ok(None),
+        Some(_) => Plant::set_status(window.plant_id, previous, conn).await,
+        None => Ok(None),
+    }
+}
+
+/// Mark `window` as active and put its plant in maintenance. Returns the
+/// plant if it changed.
+async fn enter(
+    window: &MaintenanceWindow,
+    conn: &mut AsyncPgConnection,
+) -> Result<Option<Plant>, diesel::result::Error> {
+    let Some(plant) =
+        Plant::find(&window.tenant_id, window.plant_id, conn).await?
+    else {
+        return Ok(None);
+    };
+
+    // Overlapping windows restore what the plant was before the first
+    let active = MaintenanceWindow::active_for_plant(window.plant_id, conn)
+        .await?
+        .into_iter()
+        .find_map(|window| window.previous_plant_status);
+    let previous = active.unwrap_or_else(|| plant.status.clone());
+    if previous == plant_status::DECOMMISSIONED {
+        MaintenanceWindow::set_status(
+            window.id,
+            window_status::ACTIVE,
+            None,
+            conn,
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    MaintenanceWindow::set_status(
+        window.id,
+        window_status::ACTIVE,
+        Some(previous),
+        conn,
+    )
+    .await?;
+    if plant.status == plant_status::MAINTENANCE {
+        return Ok(None);
+    }
+    Plant::set_status(plant.id, plant_status::MAINTENANCE, conn).await
+}
+
+/// Background worker that starts and ends maintenance windows.
+pub struct MaintenanceScheduler {
+    pool: postgres_models::connection::Pool,
+    plant_events: PlantEvents,
+    settings: SchedulerSettings,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        plant_events: PlantEvents,
+        settings: SchedulerSettings,
+    ) -> Self {
+        Self {
+            pool,
+            plant_events,
+            settings,
+        }
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            poll_interval = ?self.settings.poll_interval,
+            "Starting maintenance scheduler"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.poll_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            // Keep going while full batches come back, to catch up quickly
+            loop {
+                match self.advance_batch().await {
+                    Ok(moved) if moved == BATCH_SIZE as usize => {}
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Maintenance scheduler cycle failed: {e:#}"
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        tracing::info!("Maintenance scheduler stopped");
+    }
+
+    /// Bring the next batch of windows up to date with the clock. Returns
+    /// the number of windows moved.
+    async fn advance_batch(&self) -> anyhow::Result<usize> {
+        let now = Utc::now();
+        let mut conn = self.pool.get().await?;
+        let (moved, plants) = conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                async move {
+                    let windows =
+                        MaintenanceWindow::claim_due(now, BATCH_SIZE, conn)
+                            .await?;
+
+                    // Windows to end come first, so a plant between
+                    // back-to-back windows stays in maintenance
+                    let mut plants = Vec::new();
+                    for window in &windows {
+                        let status = window.status_at(now);
+                        tracing::info!(
+                            window_id = %window.id,
+                            plant_id = %window.plant_id,
+                            from = %window.status,
+                            to = status,
+                            "Moving maintenance window"
+                        );
+                        let changed = if window.status == window_status::ACTIVE
+                        {
+                            leave(window, status, conn).await?
+                        } else if status == window_status::ACTIVE {
+                            enter(window, conn).await?
+                        } else {
+                            MaintenanceWindow::set_status(
+                                window.id, status, None, conn,
+                            )
+                            .await?;
+                            None
+                        };
+                        plants.extend(changed);
+                    }
+                    Ok((windows.len(), plants))
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        for plant in plants {
+            self.plant_events.publish(PlantEvent::Updated(plant));
+        }
+        Ok(moved)
+    }
+}
@@ -0,0 +1,227 @@
+//! Day-ahead market prices.
+//!
+//! When a price feed is configured, the [`PriceImporter`] fetches the hourly
+//! day-ahead prices of the configured bidding zones from a [`PriceFeed`],
+//! from a few days back to tomorrow once it is published, and stores them
+//! as `market_prices`. `POST /energy/cost` values readings at these prices
+//! as well as at a static tariff.
+pub mod nord_pool;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use postgres_models::models::market_prices::{MarketPrice, NewMarketPrice};
+use telemetry::metrics::Telemetry;
+use url::Url;
+
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+
+/// Decimals of stored prices.
+const PRICE_SCALE: i64 = 4;
+
+#[derive(Debug, Clone)]
+pub struct ImporterSettings {
+    /// Endpoint of the Nord Pool compatible day-ahead price API
+    pub api_url: Url,
+    /// Bidding zones to import, e.g. `SE3`
+    pub zones: Vec<String>,
+    pub currency: String,
+    pub poll_interval: Duration,
+    pub request_timeout: Duration,
+    /// Delivery days before today to import
+    pub past_days: u32,
+}
+
+/// The price of a delivery hour.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourlyPrice {
+    /// Start of the hour
+    pub starts_at: DateTime<Utc>,
+    pub price_per_mwh: BigDecimal,
+    pub currency: String,
+}
+
+/// A source of day-ahead prices.
+#[async_trait]
+pub trait PriceFeed: Send + Sync {
+    /// Recorded as the source of the prices
+    fn name(&self) -> &'static str;
+
+    /// The hourly prices of `zone` for the delivery day `date`, empty
+    /// while they are not published.
+    async fn day_ahead(
+        &self,
+        zone: &str,
+        date: NaiveDate,
+    ) -> anyhow::Result<Vec<HourlyPrice>>;
+}
+
+/// Hourly prices of `(start, price)` entries of any resolution: entries of
+/// the same hour, such as quarter-hours, are averaged.
+pub fn hourly_prices(
+    entries: impl IntoIterator<Item = (DateTime<Utc>, BigDecimal)>,
+    currency: &str,
+) -> Vec<HourlyPrice> {
+    let mut hours = BTreeMap::<DateTime<Utc>, (BigDecimal, u32)>::new();
+    for (starts_at, price) in entries {
+        let hour = starts_at
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(starts_at);
+        let (sum, count) = hours.entry(hour).or_default();
+        *sum += price;
+        *count += 1;
+    }
+
+    hours
+        .into_iter()
+        .map(|(starts_at, (sum, count))| HourlyPrice {
+            starts_at,
+            price_per_mwh: (sum / BigDecimal::from(count))
+                .with_scale_round(PRICE_SCALE, RoundingMode::HalfEven),
+            currency: currency.to_string(),
+        })
+        .collect()
+}
+
+/// Background worker that imports the day-ahead prices of the configured
+/// zones.
+///
+/// Days are re-imported on every poll, so tomorrow's prices appear once
+/// published and corrections replace earlier prices. A zone whose import
+/// fails is retried on the next poll.
+pub struct PriceImporter {
+    pool: postgres_models::connection::Pool,
+    feed: Box<dyn PriceFeed>,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    settings: ImporterSettings,
+}
+
+impl PriceImporter {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        settings: ImporterSettings,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(settings.request_timeout)
+            .build()?;
+        let feed = nord_pool::NordPool::new(
+            client,
+            settings.api_url.clone(),
+            settings.currency.clone(),
+        );
+
+        Ok(Self {
+            pool,
+            feed: Box::new(feed),
+            telemetry,
+            settings,
+        })
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            poll_interval = ?self.settings.poll_interval,
+            feed = self.feed.name(),
+            zones = ?self.settings.zones,
+            "Starting market price importer"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.poll_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            self.import_all().await;
+        }
+
+        tracing::info!("Market price importer stopped");
+    }
+
+    async fn import_all(&self) {
+        let today = Utc::now().date_naive();
+        let first = today - chrono::Days::new(self.settings.past_days.into());
+        let tomorrow = today + chrono::Days::new(1);
+
+        for zone in &self.settings.zones {
+            let outcome = match self.import(zone, first, tomorrow).await {
+                Ok(imported) => {
+                    tracing::debug!(zone, imported, "Imported market prices");
+                    "imported"
+                }
+                Err(e) => {
+                    tracing::warn!(zone, "Market price import failed: {e:#}");
+                    "failed"
+                }
+            };
+            self.telemetry
+                .maybe_use_metrics(|m| m.record_market_price_import(outcome));
+        }
+    }
+
+    /// Import the prices of `zone` for the delivery days from `first` to
+    /// `last`. Returns the number of hours stored.
+    async fn import(
+        &self,
+        zone: &str,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> anyhow::Result<usize> {
+        let mut prices = Vec::new();
+        for date in first.iter_days().take_while(|date| *date <= last) {
+            let day = self.feed.day_ahead(zone, date).await?;
+            prices.extend(day.into_iter().map(|price| NewMarketPrice {
+                zone: zone.to_string(),
+                starts_at: price.starts_at,
+                price_per_mwh: price.price_per_mwh,
+                currency: price.currency,
+                source: self.feed.name().to_string(),
+            }));
+        }
+        if prices.is_empty() {
+            return Ok(0);
+        }
+
+        let mut conn = self.pool.get().await?;
+        Ok(MarketPrice::upsert(prices, &mut conn).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_quarter_hours_are_averaged() {
+        let at = |h, m| Utc.with_ymd_and_hms(2025, 10, 1, h, m, 0).unwrap();
+        let price = |p: &str| p.parse::<BigDecimal>().unwrap();
+        let entries = [
+            (at(0, 0), price("40.10")),
+            (at(0, 15), price("41.20")),
+            (at(0, 30), price("39.90")),
+            (at(0, 45), price("42.00")),
+            (at(1, 0), price("-5.5")),
+        ];
+
+        let hours = hourly_prices(entries, "EUR");
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[0].starts_at, at(0, 0));
+        assert_eq!(hours[0].price_per_mwh, price("40.8"));
+        assert_eq!(hours[1].price_per_mwh, price("-5.5"));
+        assert_eq!(hours[1].currency, "EUR");
+    }
+}
@@ -0,0 +1,138 @@
+//! The Nord Pool day-ahead price API.
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use url::Url;
+
+use super::{HourlyPrice, PriceFeed, hourly_prices};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DayAheadResponse {
+    multi_area_entries: Vec<AreaEntry>,
+}
+
+/// Prices of one market time unit, by delivery area
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AreaEntry {
+    delivery_start: DateTime<Utc>,
+    entry_per_area: HashMap<String, serde_json::Number>,
+}
+
+/// Hourly prices of `zone` in a day-ahead response.
+fn parse_day_ahead(
+    body: &str,
+    zone: &str,
+    currency: &str,
+) -> anyhow::Result<Vec<HourlyPrice>> {
+    let response = serde_json::from_str::<DayAheadResponse>(body)?;
+    let mut entries = Vec::new();
+    for entry in response.multi_area_entries {
+        let Some(price) = entry.entry_per_area.get(zone) else {
+            continue;
+        };
+        let price = price
+            .to_string()
+            .parse::<BigDecimal>()
+            .map_err(|e| anyhow::anyhow!("Invalid price {price}: {e}"))?;
+        entries.push((entry.delivery_start, price));
+    }
+
+    Ok(hourly_prices(entries, currency))
+}
+
+/// Fetches day-ahead prices from a Nord Pool compatible endpoint, e.g.
+/// `https://dataportal-api.nordpoolgroup.com/api/DayAheadPrices`. Prices
+/// published per quarter-hour are averaged to hours.
+pub struct NordPool {
+    client: reqwest::Client,
+    url: Url,
+    currency: String,
+}
+
+impl NordPool {
+    pub fn new(client: reqwest::Client, url: Url, currency: String) -> Self {
+        Self {
+            client,
+            url,
+            currency,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceFeed for NordPool {
+    fn name(&self) -> &'static str {
+        "nord_pool"
+    }
+
+    async fn day_ahead(
+        &self,
+        zone: &str,
+        date: NaiveDate,
+    ) -> anyhow::Result<Vec<HourlyPrice>> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("date", &date.to_string())
+            .append_pair("market", "DayAhead")
+            .append_pair("deliveryArea", zone)
+            .append_pair("currency", &self.currency);
+
+        let response = self.client.get(url).send().await?;
+        let status = response.status();
+        // Not published yet
+        if status == StatusCode::NO_CONTENT {
+            return Ok(Vec::new());
+        }
+        let body = response.text().await?;
+        if !status.is_success() {
+            anyhow::bail!("Price feed responded with {status}: {body}");
+        }
+        parse_day_ahead(&body, zone, &self.currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_prices_of_the_zone() {
+        let body = r#"{
+            "deliveryDateCET": "2025-01-02",
+            "market": "DayAhead",
+            "currency": "EUR",
+            "multiAreaEntries": [
+                {
+                    "deliveryStart": "2025-01-01T23:00:00Z",
+                    "deliveryEnd": "2025-01-02T00:00:00Z",
+                    "entryPerArea": {"SE3": 21.37, "SE4": 35.1}
+                },
+                {
+                    "deliveryStart": "2025-01-02T00:00:00Z",
+                    "deliveryEnd": "2025-01-02T01:00:00Z",
+                    "entryPerArea": {"SE4": 33.0}
+                }
+            ]
+        }"#;
+
+        let prices = parse_day_ahead(body, "SE3", "EUR").unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(
+            prices[0].starts_at,
+            Utc.with_ymd_and_hms(2025, 1, 1, 23, 0, 0).unwrap()
+        );
+        assert_eq!(
+            prices[0].price_per_mwh,
+            "21.37".parse::<BigDecimal>().unwrap()
+        );
+        assert_eq!(parse_day_ahead(body, "SE4", "EUR").unwrap().len(), 2);
+    }
+}
@@ -1,12 +1,64 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use prometheus::{IntCounterVec, Registry, register_int_counter_vec};
-use telemetry::metrics::TelemetryMetrics;
+use std::time::Duration;
+
+use postgres_models::connection::{
+    AcquireListener, AcquireOutcome, StatementCacheUse, StatementListener,
+};
+use prometheus::{
+    HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Registry,
+    register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec,
+};
+use telemetry::metrics::{Telemetry, TelemetryMetrics};
+
+use crate::circuit_breaker::{BreakerState, Dependency, StateListener};
 
 #[derive(Clone, Debug)]
 pub struct ServerMetrics {
     pub registry: Registry,
 
     pub request_errors: IntCounterVec,
+
+    pub webhook_deliveries: IntCounterVec,
+
+    pub outbox_events: IntCounterVec,
+
+    pub ip_filter_decisions: IntCounterVec,
+
+    pub alert_events: IntCounterVec,
+
+    pub notification_deliveries: IntCounterVec,
+
+    pub weather_imports: IntCounterVec,
+
+    pub market_price_imports: IntCounterVec,
+
+    pub shadow_comparisons: IntCounterVec,
+
+    pub shadow_query_seconds: HistogramVec,
+
+    pub query_history_entries: IntCounterVec,
+
+    pub database_statements: IntCounterVec,
+
+    pub aggregate_computations: IntCounterVec,
+
+    pub circuit_breaker_state: IntGaugeVec,
+
+    pub hedged_reads: IntCounterVec,
+
+    pub database_pool_size: IntGaugeVec,
+
+    pub database_acquire_seconds: HistogramVec,
+
+    pub database_connection_waiters: IntGauge,
+
+    pub read_replica_up: IntGauge,
+
+    pub read_fallbacks: IntCounterVec,
+    pub domain_events: IntCounterVec,
 }
 
 impl Default for ServerMetrics {
@@ -45,13 +97,204 @@ impl ServerMetrics {
         )
         .expect("metric must be created");
 
+        let webhook_deliveries = register_int_counter_vec!(
+            format!("{}webhook_deliveries", metric_prefix),
+            "A metric counting webhook delivery attempts by outcome",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let outbox_events = register_int_counter_vec!(
+            format!("{}outbox_events", metric_prefix),
+            "A metric counting outbox events relayed by outcome",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let ip_filter_decisions = register_int_counter_vec!(
+            format!("{}ip_filter_decisions", metric_prefix),
+            "A metric counting admin IP filter decisions by outcome and rule",
+            &["decision", "rule"],
+        )
+        .expect("metric must be created");
+
+        let alert_events = register_int_counter_vec!(
+            format!("{}alert_events", metric_prefix),
+            "A metric counting alert firings, resolutions and notifications by outcome",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let notification_deliveries = register_int_counter_vec!(
+            format!("{}notification_deliveries", metric_prefix),
+            "A metric counting notification delivery attempts by channel kind and outcome",
+            &["kind", "outcome"],
+        )
+        .expect("metric must be created");
+
+        let weather_imports = register_int_counter_vec!(
+            format!("{}weather_imports", metric_prefix),
+            "A metric counting plant weather imports by outcome",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let market_price_imports = register_int_counter_vec!(
+            format!("{}market_price_imports", metric_prefix),
+            "A metric counting bidding zone price imports by outcome",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let shadow_comparisons = register_int_counter_vec!(
+            format!("{}shadow_comparisons", metric_prefix),
+            "A metric counting shadow aggregate comparisons by outcome",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let shadow_query_seconds = register_histogram_vec!(
+            format!("{}shadow_query_seconds", metric_prefix),
+            "A metric timing shadowed aggregate queries by backend",
+            &["backend"],
+        )
+        .expect("metric must be created");
+
+        let query_history_entries = register_int_counter_vec!(
+            format!("{}query_history_entries", metric_prefix),
+            "A metric counting buffered query history entries by outcome",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let database_statements = register_int_counter_vec!(
+            format!("{}database_statements", metric_prefix),
+            "A metric counting database queries by pool and prepared \
+             statement cache use",
+            &["pool", "cache"],
+        )
+        .expect("metric must be created");
+
+        let aggregate_computations = register_int_counter_vec!(
+            format!("{}aggregate_computations", metric_prefix),
+            "A metric counting aggregations computed or shared with \
+             identical ones in flight",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let circuit_breaker_state = register_int_gauge_vec!(
+            format!("{}circuit_breaker_state", metric_prefix),
+            "A metric with the state of each circuit breaker: 0 closed, \
+             1 half-open, 2 open",
+            &["endpoint", "dependency"],
+        )
+        .expect("metric must be created");
+
+        let hedged_reads = register_int_counter_vec!(
+            format!("{}hedged_reads", metric_prefix),
+            "A metric counting reads answered by the read-only pool alone, \
+             or hedged on the read-write one",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let database_pool_size = register_int_gauge_vec!(
+            format!("{}database_pool_size", metric_prefix),
+            "A metric with the current size of each database pool and the \
+             optimal one for its max_connections",
+            &["pool", "size"],
+        )
+        .expect("metric must be created");
+
+        let database_acquire_seconds = register_histogram_vec!(
+            format!("{}database_acquire_seconds", metric_prefix),
+            "A metric timing waits for a database connection by outcome",
+            &["outcome"],
+            vec![
+                0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+                10.0, 30.0
+            ],
+        )
+        .expect("metric must be created");
+
+        let database_connection_waiters = register_int_gauge!(
+            format!("{}database_connection_waiters", metric_prefix),
+            "A metric with the number of callers waiting for a database \
+             connection",
+        )
+        .expect("metric must be created");
+
+        let read_replica_up = register_int_gauge!(
+            format!("{}read_replica_up", metric_prefix),
+            "A metric with whether reads are served by the read replica: \
+             1 up, 0 falling back to the read-write pool",
+        )
+        .expect("metric must be created");
+        read_replica_up.set(1);
+
+        let read_fallbacks = register_int_counter_vec!(
+            format!("{}read_fallbacks", metric_prefix),
+            "A metric counting reads served by the read-write pool while \
+             the replica is down, or rejected over the fallback limit",
+            &["outcome"],
+        )
+        .expect("metric must be created");
+
+        let domain_events = register_int_counter_vec!(
+            format!("{}domain_events", metric_prefix),
+            "A metric counting the domain events published, by event",
+            &["event"],
+        )
+        .expect("metric must be created");
+
         let registry =
             Registry::new_custom(prefix, None).expect("registry to be created");
         registry.register(Box::new(request_errors.clone()))?;
+        registry.register(Box::new(webhook_deliveries.clone()))?;
+        registry.register(Box::new(outbox_events.clone()))?;
+        registry.register(Box::new(ip_filter_decisions.clone()))?;
+        registry.register(Box::new(alert_events.clone()))?;
+        registry.register(Box::new(notification_deliveries.clone()))?;
+        registry.register(Box::new(weather_imports.clone()))?;
+        registry.register(Box::new(market_price_imports.clone()))?;
+        registry.register(Box::new(shadow_comparisons.clone()))?;
+        registry.register(Box::new(shadow_query_seconds.clone()))?;
+        registry.register(Box::new(query_history_entries.clone()))?;
+        registry.register(Box::new(database_statements.clone()))?;
+        registry.register(Box::new(aggregate_computations.clone()))?;
+        registry.register(Box::new(circuit_breaker_state.clone()))?;
+        registry.register(Box::new(hedged_reads.clone()))?;
+        registry.register(Box::new(database_pool_size.clone()))?;
+        registry.register(Box::new(database_acquire_seconds.clone()))?;
+        registry.register(Box::new(database_connection_waiters.clone()))?;
+        registry.register(Box::new(read_replica_up.clone()))?;
+        registry.register(Box::new(read_fallbacks.clone()))?;
+        registry.register(Box::new(domain_events.clone()))?;
 
         Ok(Self {
             registry,
             request_errors,
+            webhook_deliveries,
+            outbox_events,
+            ip_filter_decisions,
+            alert_events,
+            notification_deliveries,
+            weather_imports,
+            market_price_imports,
+            shadow_comparisons,
+            shadow_query_seconds,
+            query_history_entries,
+            database_statements,
+            aggregate_computations,
+            circuit_breaker_state,
+            hedged_reads,
+            database_pool_size,
+            database_acquire_seconds,
+            database_connection_waiters,
+            read_replica_up,
+            read_fallbacks,
+            domain_events,
         })
     }
 
@@ -60,4 +303,166 @@ impl ServerMetrics {
             .with_label_values(&[handler, error_code])
             .inc();
     }
+
+    pub fn record_webhook_delivery(&self, outcome: &str) {
+        self.webhook_deliveries.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_outbox_events(&self, outcome: &str, count: u64) {
+        self.outbox_events
+            .with_label_values(&[outcome])
+            .inc_by(count);
+    }
+
+    pub fn record_ip_filter_decision(&self, decision: &str, rule: &str) {
+        self.ip_filter_decisions
+            .with_label_values(&[decision, rule])
+            .inc();
+    }
+
+    pub fn record_alert_event(&self, outcome: &str) {
+        self.alert_events.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_notification_delivery(&self, kind: &str, outcome: &str) {
+        self.notification_deliveries
+            .with_label_values(&[kind, outcome])
+            .inc();
+    }
+
+    pub fn record_weather_import(&self, outcome: &str) {
+        self.weather_imports.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_market_price_import(&self, outcome: &str) {
+        self.market_price_imports
+            .with_label_values(&[outcome])
+            .inc();
+    }
+
+    pub fn record_shadow_comparison(&self, outcome: &str) {
+        self.shadow_comparisons.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_shadow_query(&self, backend: &str, seconds: f64) {
+        self.shadow_query_seconds
+            .with_label_values(&[backend])
+            .observe(seconds);
+    }
+
+    pub fn record_query_history_entries(&self, outcome: &str, count: u64) {
+        self.query_history_entries
+            .with_label_values(&[outcome])
+            .inc_by(count);
+    }
+
+    pub fn record_domain_event(&self, event: &str) {
+        self.domain_events.with_label_values(&[event]).inc();
+    }
+
+    pub fn record_aggregate_computation(&self, outcome: &str) {
+        self.aggregate_computations
+            .with_label_values(&[outcome])
+            .inc();
+    }
+
+    pub fn record_circuit_breaker_state(
+        &self,
+        endpoint: &str,
+        dependency: &str,
+        state: i64,
+    ) {
+        self.circuit_breaker_state
+            .with_label_values(&[endpoint, dependency])
+            .set(state);
+    }
+
+    pub fn record_hedged_read(&self, outcome: &str) {
+        self.hedged_reads.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_database_pool_size(
+        &self,
+        pool: &str,
+        size: &str,
+        connections: u32,
+    ) {
+        self.database_pool_size
+            .with_label_values(&[pool, size])
+            .set(connections.into());
+    }
+
+    pub fn record_database_acquire(&self, outcome: &str, waited: Duration) {
+        self.database_acquire_seconds
+            .with_label_values(&[outcome])
+            .observe(waited.as_secs_f64());
+    }
+
+    pub fn record_database_connection_waiters(&self, waiters: usize) {
+        self.database_connection_waiters
+            .set(waiters.try_into().unwrap_or(i64::MAX));
+    }
+
+    pub fn record_read_replica_up(&self, up: bool) {
+        self.read_replica_up.set(up.into());
+    }
+
+    pub fn record_read_fallback(&self, outcome: &str) {
+        self.read_fallbacks.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_database_statement(&self, pool: &str, cache: &str) {
+        self.database_statements
+            .with_label_values(&[pool, cache])
+            .inc();
+    }
+}
+
+/// A listener counting the prepared statement cache use of the queries on
+/// `pool`, to pass when connecting it.
+pub fn statement_listener(
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    pool: &'static str,
+) -> StatementListener {
+    Arc::new(move |cache_use: StatementCacheUse| {
+        telemetry.maybe_use_metrics(|m| {
+            m.record_database_statement(pool, cache_use.as_str());
+        });
+    })
+}
+
+/// A listener exporting the state of circuit breakers, to pass when creating
+/// them.
+pub fn breaker_listener(
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+) -> StateListener {
+    Arc::new(
+        move |endpoint, dependency: Dependency, state: BreakerState| {
+            telemetry.maybe_use_metrics(|m| {
+                m.record_circuit_breaker_state(
+                    endpoint,
+                    dependency.as_str(),
+                    state.as_gauge(),
+                );
+            });
+        },
+    )
+}
+
+/// A listener exporting the waits for database connections, to install with
+/// [`postgres_models::connection::set_acquire_listener`].
+pub struct AcquireMetrics(pub Arc<Telemetry<ServerMetrics>>);
+
+impl AcquireListener for AcquireMetrics {
+    fn waiters(&self, waiters: usize) {
+        self.0.maybe_use_metrics(|m| {
+            m.record_database_connection_waiters(waiters);
+        });
+    }
+
+    fn acquired(&self, outcome: AcquireOutcome, waited: Duration) {
+        self.0.maybe_use_metrics(|m| {
+            m.record_database_acquire(outcome.as_str(), waited);
+        });
+    }
 }
@@ -0,0 +1,191 @@
+//! Feature areas of the API.
+//!
+//! An [`ApiModule`] brings everything the service needs to know about an
+//! area: its routes, the OpenAPI paths and tags of its handlers, the
+//! components `/health` checks for it and the work to finish before the
+//! pools close on shutdown. Modules are registered once, in
+//! [`crate::api_modules`]; the routers, the OpenAPI spec, `/health` and
+//! the shutdown sequence are all built from that registry.
+use std::future::Future;
+use std::pin::Pin;
+
+use axum::Router;
+use utoipa::openapi::OpenApi;
+
+use crate::AppState;
+use crate::endpoints::Endpoints;
+use crate::health::ComponentHealth;
+
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Where the routes of a module are served, all under `/api/wire/v1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mount {
+    /// Public listeners, authenticated and counted against the quota
+    Metered,
+    /// Public listeners, authenticated but not counted
+    Unmetered,
+    /// Internal listeners, behind the module's own layers
+    Internal,
+}
+
+/// A component `/health` reports on.
+#[derive(Clone, Copy)]
+pub struct HealthCheck {
+    /// Key of the component in the response
+    pub component: &'static str,
+    /// Whether the service is unhealthy, rather than degraded, while the
+    /// component is
+    pub critical: bool,
+    pub check: fn(AppState) -> BoxFuture<ComponentHealth>,
+}
+
+pub trait ApiModule: Send + Sync {
+    /// Name of the module in logs
+    fn name(&self) -> &'static str;
+
+    fn mount(&self) -> Mount {
+        Mount::Metered
+    }
+
+    /// Whether the module is served with the endpoint groups of
+    /// `DISABLED_ENDPOINTS`
+    fn enabled(&self, endpoints: &Endpoints) -> bool;
+
+    /// Routes of the module, nested at their paths under `/api/wire/v1`
+    fn routes(&self, state: AppState) -> Router;
+
+    /// Paths and tags of the module's handlers
+    fn openapi(&self) -> OpenApi;
+
+    fn health_checks(&self) -> Vec<HealthCheck> {
+        Vec::new()
+    }
+
+    /// Work to finish once shutdown begins, before the pools close
+    fn on_shutdown(&self, _state: &AppState) -> Option<BoxFuture<()>> {
+        None
+    }
+}
+
+/// The registered modules, in registration order.
+#[derive(Default)]
+pub struct ApiModules {
+    modules: Vec<Box<dyn ApiModule>>,
+}
+
+impl ApiModules {
+    pub fn register(mut self, module: impl ApiModule + 'static) -> Self {
+        self.modules.push(Box::new(module));
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn ApiModule> {
+        self.modules.iter().map(Box::as_ref)
+    }
+
+    /// Modules served with `endpoints`.
+    pub fn enabled<'a>(
+        &'a self,
+        endpoints: &'a Endpoints,
+    ) -> impl Iterator<Item = &'a dyn ApiModule> {
+        self.iter().filter(|module| module.enabled(endpoints))
+    }
+
+    /// Routes of the enabled modules served at `mount`.
+    pub fn routes(&self, state: &AppState, mount: Mount) -> Router {
+        self.enabled(&state.config.endpoints)
+            .filter(|module| module.mount() == mount)
+            .fold(Router::new(), |router, module| {
+                router.merge(module.routes(state.clone()))
+            })
+    }
+
+    /// `base` with the paths and tags of every module, enabled or not.
+    pub fn openapi(&self, base: OpenApi) -> OpenApi {
+        self.iter()
+            .fold(base, |spec, module| spec.merge_from(module.openapi()))
+    }
+
+    /// Health checks of the modules served with `endpoints`.
+    pub fn health_checks(&self, endpoints: &Endpoints) -> Vec<HealthCheck> {
+        self.enabled(endpoints)
+            .flat_map(|module| module.health_checks())
+            .collect()
+    }
+
+    /// Run the shutdown hooks of the enabled modules once shutdown begins,
+    /// holding the pools open until they finish.
+    pub async fn drain_on_shutdown(&self, state: &AppState) {
+        for module in self.enabled(&state.config.endpoints) {
+            let Some(hook) = module.on_shutdown(state) else {
+                continue;
+            };
+            let name = module.name();
+            let shutdown = state.shutdown.clone();
+            let task = tokio::spawn(async move {
+                shutdown.wait_for_shutdown().await;
+                hook.await;
+                tracing::info!(module = name, "Module shut down");
+            });
+            state.shutdown.drain_before_close(task).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::endpoints::EndpointGroup;
+
+    #[test]
+    fn test_modules_own_distinct_paths() {
+        let modules = crate::api_modules();
+        let names = modules.iter().map(ApiModule::name).collect::<Vec<_>>();
+        assert_eq!(names.iter().collect::<HashSet<_>>().len(), names.len());
+
+        let mut seen = HashSet::new();
+        for module in modules.iter() {
+            let spec = module.openapi();
+            assert!(!spec.paths.paths.is_empty(), "{}", module.name());
+            for path in spec.paths.paths.keys() {
+                assert!(seen.insert(path.clone()), "{path} registered twice");
+            }
+        }
+    }
+
+    #[test]
+    fn test_leaves_disabled_modules_out() {
+        let modules = crate::api_modules();
+        let enabled = |endpoints: Endpoints| {
+            modules
+                .enabled(&endpoints)
+                .map(ApiModule::name)
+                .collect::<Vec<_>>()
+        };
+
+        let names = enabled(Endpoints::disabling([
+            EndpointGroup::Admin,
+            EndpointGroup::Ingestion,
+        ]));
+        assert!(names.contains(&"energy"));
+        assert!(!names.contains(&"admin"));
+
+        let names = enabled(Endpoints::disabling([
+            EndpointGroup::Energy,
+            EndpointGroup::Ingestion,
+        ]));
+        assert!(!names.contains(&"energy"));
+        assert_eq!(
+            modules.health_checks(&Endpoints::default())[0].component,
+            "uploads"
+        );
+        assert!(
+            modules
+                .health_checks(&Endpoints::disabling([EndpointGroup::Admin]))
+                .is_empty()
+        );
+    }
+}
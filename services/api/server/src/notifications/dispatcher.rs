@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use postgres_models::models::notifications::{
+    Notification, NotificationChannel, channel_kind,
+};
+use postgres_models::models::webhooks::DeliveryAttempt;
+use telemetry::metrics::Telemetry;
+
+use super::Notifier;
+use super::email::{Mailer, SmtpSettings};
+use super::slack::SlackNotifier;
+use super::webhook::WebhookNotifier;
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+use crate::webhooks::dispatcher::retry_delay;
+
+const CLAIM_BATCH_SIZE: i32 = 50;
+const STALE_IN_FLIGHT_AFTER: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone)]
+pub struct DispatcherSettings {
+    pub poll_interval: Duration,
+    /// Timeout of webhook and Slack requests
+    pub request_timeout: Duration,
+    pub max_attempts: i32,
+}
+
+/// When to retry a notification that failed its `attempts`th attempt,
+/// `None` once `max_attempts` are used up. Backs off like webhook
+/// deliveries.
+fn retry_at(
+    attempts: i32,
+    max_attempts: i32,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    (attempts < max_attempts).then(|| {
+        now + chrono::Duration::from_std(retry_delay(attempts))
+            .unwrap_or(chrono::Duration::MAX)
+    })
+}
+
+/// Background worker that sends queued notifications through the
+/// [`Notifier`] of their channel's kind.
+///
+/// Failures are retried with exponential backoff and every outcome is
+/// recorded on the notification row. Notifications of email channels fail
+/// without retry when SMTP is not configured.
+pub struct NotificationDispatcher {
+    pool: postgres_models::connection::Pool,
+    notifiers: HashMap<&'static str, Box<dyn Notifier>>,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    settings: DispatcherSettings,
+}
+
+impl NotificationDispatcher {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        smtp: Option<&SmtpSettings>,
+        settings: DispatcherSettings,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(settings.request_timeout)
+            .build()?;
+
+        let mut notifiers: HashMap<&'static str, Box<dyn Notifier>> =
+            HashMap::new();
+        notifiers.insert(
+            channel_kind::WEBHOOK,
+            Box::new(WebhookNotifier::new(client.clone())),
+        );
+        notifiers
+            .insert(channel_kind::SLACK, Box::new(SlackNotifier::new(client)));
+        if let Some(smtp) = smtp {
+            notifiers.insert(channel_kind::EMAIL, Box::new(Mailer::new(smtp)?));
+        }
+
+        Ok(Self {
+            pool,
+            notifiers,
+            telemetry,
+            settings,
+        })
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            poll_interval = ?self.settings.poll_interval,
+            email = self.notifiers.contains_key(channel_kind::EMAIL),
+            "Starting notification dispatcher"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.poll_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            if let Err(e) = self.dispatch_due().await {
+                tracing::warn!("Notification dispatch cycle failed: {e:#}");
+            }
+        }
+
+        tracing::info!("Notification dispatcher stopped");
+    }
+
+    async fn dispatch_due(&self) -> anyhow::Result<()> {
+        let mut conn = self.pool.get().await?;
+        let due = Notification::claim_due(
+            CLAIM_BATCH_SIZE,
+            STALE_IN_FLIGHT_AFTER,
+            &mut conn,
+        )
+        .await?;
+
+        for notification in due {
+            // Deleting a channel deletes its notifications
+            let Some(channel) = NotificationChannel::find(
+                &notification.tenant_id,
+                notification.channel_id,
+                &mut conn,
+            )
+            .await?
+            else {
+                continue;
+            };
+
+            let attempt = self.attempt(&channel, &notification).await;
+            let outcome = match (&attempt.error, attempt.retry_at) {
+                (None, _) => "delivered",
+                (Some(_), Some(_)) => "retry",
+                (Some(_), None) => "failed",
+            };
+            self.telemetry.maybe_use_metrics(|m| {
+                m.record_notification_delivery(&channel.kind, outcome);
+            });
+
+            Notification::record_attempt(notification.id, attempt, &mut conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn attempt(
+        &self,
+        channel: &NotificationChannel,
+        notification: &Notification,
+    ) -> DeliveryAttempt {
+        let (error, retryable) = if !channel.enabled {
+            (Some("The channel is disabled".to_string()), false)
+        } else if let Some(notifier) = self.notifiers.get(channel.kind.as_str())
+        {
+            match notifier.send(&channel.target, notification).await {
+                Ok(()) => (None, false),
+                Err(e) => (Some(format!("{e:#}")), true),
+            }
+        } else if channel.kind == channel_kind::EMAIL {
+            (Some("SMTP is not configured".to_string()), false)
+        } else {
+            (
+                Some(format!("Unknown channel kind {}", channel.kind)),
+                false,
+            )
+        };
+
+        if let Some(error) = &error {
+            tracing::warn!(
+                notification_id = %notification.id,
+                channel_id = %channel.id,
+                kind = %channel.kind,
+                attempts = notification.attempts,
+                "Notification failed: {error}"
+            );
+        }
+
+        let retry_at = match error {
+            Some(_) if retryable => retry_at(
+                notification.attempts,
+                self.settings.max_attempts,
+                Utc::now(),
+            ),
+            _ => None,
+        };
+
+        DeliveryAttempt {
+            status_code: None,
+            error,
+            retry_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retries_until_max_attempts() {
+        let now = Utc::now();
+        assert_eq!(
+            retry_at(1, 3, now),
+            Some(now + chrono::Duration::seconds(30))
+        );
+        assert_eq!(
+            retry_at(2, 3, now),
+            Some(now + chrono::Duration::seconds(60))
+        );
+        assert_eq!(retry_at(3, 3, now), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! The `health_degraded` system event.
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use postgres_models::connection::with_connection;
+
+use super::{Message, SystemEvent, notify_event};
+use crate::health::{self, HealthResponse, HealthStatus};
+use crate::shutdown::ShutdownCoordinator;
+
+#[derive(Debug, Clone)]
+pub struct MonitorSettings {
+    pub interval: Duration,
+}
+
+/// Notification of a replica's health check no longer reporting healthy.
+fn degraded_message(
+    response: &HealthResponse,
+    detected_at: DateTime<Utc>,
+) -> Message {
+    let mut components = response.components.iter().collect::<Vec<_>>();
+    components.sort_by_key(|(name, _)| name.as_str());
+
+    let mut body = format!(
+        "The health check reported {} at {}.\n\n",
+        response.status.as_str(),
+        detected_at.to_rfc3339()
+    );
+    for (name, component) in components {
+        body.push_str(&format!("{name}: {}", component.status.as_str()));
+        if let Some(error) = &component.error {
+            body.push_str(&format!(" ({error})"));
+        }
+        body.push('\n');
+    }
+
+    Message {
+        subject: format!("Service {}", response.status.as_str()),
+        body,
+        data: serde_json::json!({
+            "status": response.status,
+            "components": response.components,
+            "detectedAt": detected_at,
+        }),
+    }
+}
+
+/// Background worker that runs the health check and notifies channels
+/// subscribed to `health_degraded`, of every tenant, when it stops
+/// reporting healthy.
+///
+/// Each replica reports its own view. The database may be what failed, so
+/// a notification that cannot be queued is retried on the next check.
+pub struct HealthMonitor {
+    pool: postgres_models::connection::Pool,
+    read_only_pool: postgres_models::connection::Pool,
+    cache_pool: redis_cache::connection::Pool,
+    settings: MonitorSettings,
+}
+
+impl HealthMonitor {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        read_only_pool: postgres_models::connection::Pool,
+        cache_pool: redis_cache::connection::Pool,
+        settings: MonitorSettings,
+    ) -> Self {
+        Self {
+            pool,
+            read_only_pool,
+            cache_pool,
+            settings,
+        }
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            interval = ?self.settings.interval,
+            "Starting health monitor"
+        );
+
+        let mut healthy = true;
+        let mut pending: Option<Message> = None;
+        let mut interval = tokio::time::interval(self.settings.interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            let response = health::check(
+                &self.pool,
+                &self.read_only_pool,
+                &self.cache_pool,
+                false,
+            )
+            .await;
+            let now_healthy = response.status == HealthStatus::Healthy;
+            if healthy && !now_healthy {
+                tracing::warn!(
+                    status = response.status.as_str(),
+                    "Health check degraded"
+                );
+                pending = Some(degraded_message(&response, Utc::now()));
+            } else if !healthy && now_healthy {
+                tracing::info!("Health check recovered");
+            }
+            healthy = now_healthy;
+
+            if let Some(message) = &pending {
+                let result =
+                    with_connection(&self.pool, |mut conn| async move {
+                        notify_event(
+                            SystemEvent::HealthDegraded,
+                            None,
+                            message,
+                            &mut conn,
+                        )
+                        .await
+                    })
+                    .await;
+                match result {
+                    Ok(_) => pending = None,
+                    Err(e) => tracing::warn!(
+                        "Failed to queue health notification, retrying: {e}"
+                    ),
+                }
+            }
+        }
+
+        tracing::info!("Health monitor stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::health::ComponentHealth;
+
+    #[test]
+    fn lists_components_in_message() {
+        let component = |status, error: Option<&str>| ComponentHealth {
+            status,
+            latency_ms: Some(1),
+            error: error.map(str::to_string),
+        };
+        let response = HealthResponse {
+            status: HealthStatus::Unhealthy,
+            components: HashMap::from([
+                (
+                    "redis_main".to_string(),
+                    component(HealthStatus::Unhealthy, Some("timeout")),
+                ),
+                (
+                    "postgres_rw".to_string(),
+                    component(HealthStatus::Healthy, None),
+                ),
+            ]),
+        };
+
+        let message = degraded_message(&response, Utc::now());
+        assert_eq!(message.subject, "Service unhealthy");
+        assert!(message.body.ends_with(
+            "postgres_rw: healthy\nredis_main: unhealthy (timeout)\n"
+        ));
+        assert_eq!(message.data["status"], "unhealthy");
+    }
+}
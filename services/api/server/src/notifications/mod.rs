@@ -0,0 +1,178 @@
+//! Notifications to a tenant's channels.
+//!
+//! Alerts and system events are queued as `notifications` rows, one per
+//! channel, in the transaction that produced them, and sent by the
+//! [`dispatcher::NotificationDispatcher`] through the [`Notifier`] of the
+//! channel's kind: a JSON webhook, a Slack-compatible incoming webhook or an
+//! email. Alert rules name their channel; channels subscribe to the
+//! [`SystemEvent`]s they receive.
+pub mod dispatcher;
+pub mod email;
+pub mod health;
+pub mod slack;
+pub mod webhook;
+
+use async_trait::async_trait;
+use diesel_async::AsyncPgConnection;
+use postgres_models::connection::with_connection;
+use postgres_models::models::notifications::{
+    NewNotification, Notification, NotificationChannel,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Event of the notifications of alert rules.
+pub const ALERT_FIRED: &str = "alert_fired";
+
+/// Sends notifications to the channels of one kind.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Send `notification` to a channel's `target`. Errors are retried by
+    /// the dispatcher.
+    async fn send(
+        &self,
+        target: &str,
+        notification: &Notification,
+    ) -> anyhow::Result<()>;
+}
+
+/// System events a channel can subscribe to
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemEvent {
+    /// An import of readings, from a file or the ingest endpoint, failed
+    ImportFailed,
+    /// A replica's health check stopped reporting healthy
+    HealthDegraded,
+}
+
+impl SystemEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SystemEvent::ImportFailed => "import_failed",
+            SystemEvent::HealthDegraded => "health_degraded",
+        }
+    }
+}
+
+impl std::fmt::Display for SystemEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Content of a notification.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub subject: String,
+    /// Plain text
+    pub body: String,
+    /// Structured details, sent as is by webhook channels
+    pub data: serde_json::Value,
+}
+
+fn new_notification(
+    channel: &NotificationChannel,
+    event: &str,
+    message: &Message,
+) -> NewNotification {
+    NewNotification {
+        channel_id: channel.id,
+        tenant_id: channel.tenant_id.clone(),
+        event: event.to_string(),
+        subject: message.subject.clone(),
+        body: message.body.clone(),
+        data: message.data.clone(),
+    }
+}
+
+/// Queue a notification for a channel of the tenant. `None` when the
+/// channel does not exist or is disabled.
+pub async fn notify_channel(
+    channel_id: Uuid,
+    tenant: &str,
+    event: &str,
+    message: &Message,
+    conn: &mut AsyncPgConnection,
+) -> Result<Option<Notification>, diesel::result::Error> {
+    let Some(channel) = NotificationChannel::find(tenant, channel_id, conn)
+        .await?
+        .filter(|channel| channel.enabled)
+    else {
+        return Ok(None);
+    };
+
+    let notification = new_notification(&channel, event, message);
+    let queued = Notification::enqueue(vec![notification], conn).await?;
+    Ok(queued.into_iter().next())
+}
+
+/// Queue a notification of a system event for every enabled channel
+/// subscribed to it, of one tenant or of every tenant when `tenant` is
+/// `None`. Returns the number of queued notifications.
+pub async fn notify_event(
+    event: SystemEvent,
+    tenant: Option<&str>,
+    message: &Message,
+    conn: &mut AsyncPgConnection,
+) -> Result<usize, diesel::result::Error> {
+    let channels =
+        NotificationChannel::subscribed_to(event.as_str(), tenant, conn)
+            .await?;
+    if channels.is_empty() {
+        return Ok(0);
+    }
+
+    let notifications = channels
+        .iter()
+        .map(|channel| new_notification(channel, event.as_str(), message))
+        .collect();
+    let queued = Notification::enqueue(notifications, conn).await?.len();
+    tracing::info!(%event, queued, "Queued notifications");
+
+    Ok(queued)
+}
+
+/// Notify the tenant's channels that an import from `source` failed.
+///
+/// Best effort: the import already failed, so a failure to queue the
+/// notification is only logged.
+pub async fn notify_import_failed(
+    pool: &postgres_models::connection::Pool,
+    tenant: &str,
+    source: &str,
+    error: &str,
+) {
+    let message = Message {
+        subject: format!("Import failed: {source}"),
+        body: format!(
+            "An import of energy readings for tenant {tenant} failed.\n\n\
+             Source: {source}\n\
+             Error:  {error}\n"
+        ),
+        data: serde_json::json!({
+            "tenant": tenant,
+            "source": source,
+            "error": error,
+        }),
+    };
+
+    let result = with_connection(pool, |mut conn| async move {
+        notify_event(
+            SystemEvent::ImportFailed,
+            Some(tenant),
+            &message,
+            &mut conn,
+        )
+        .await
+    })
+    .await;
+    if let Err(e) = result {
+        tracing::warn!(
+            tenant,
+            source,
+            "Failed to queue import notification: {e}"
+        );
+    }
+}
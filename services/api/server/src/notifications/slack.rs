@@ -0,0 +1,80 @@
+//! Slack-compatible incoming webhook channels.
+use async_trait::async_trait;
+use postgres_models::models::notifications::Notification;
+
+use super::Notifier;
+
+/// POSTs notifications as a `text` message to an incoming webhook URL, the
+/// format of Slack and of compatible services such as Mattermost.
+pub struct SlackNotifier {
+    client: reqwest::Client,
+}
+
+impl SlackNotifier {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+/// Message posted for `notification`: its subject in bold, then its body.
+fn payload(notification: &Notification) -> serde_json::Value {
+    serde_json::json!({
+        "text": format!("*{}*\n{}", notification.subject, notification.body),
+    })
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn send(
+        &self,
+        target: &str,
+        notification: &Notification,
+    ) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(target)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload(notification).to_string())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Receiver responded with {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn posts_subject_and_body_as_text() {
+        let notification = Notification {
+            id: Uuid::new_v4(),
+            channel_id: Uuid::new_v4(),
+            tenant_id: "default".to_string(),
+            event: "import_failed".to_string(),
+            subject: "Import failed: readings.xlsx".to_string(),
+            body: "Error: no such file\n".to_string(),
+            data: serde_json::json!({}),
+            status: "pending".to_string(),
+            attempts: 1,
+            last_error: None,
+            next_attempt_at: Utc::now(),
+            delivered_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        assert_eq!(
+            payload(&notification),
+            serde_json::json!({
+                "text": "*Import failed: readings.xlsx*\nError: no such file\n"
+            })
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! JSON webhook channels.
+use async_trait::async_trait;
+use postgres_models::models::notifications::Notification;
+
+use super::Notifier;
+use crate::webhooks::signing::{DELIVERY_HEADER, EVENT_HEADER};
+
+/// POSTs notifications as JSON to the channel's URL.
+///
+/// Unlike webhook subscriptions, channels have no signing secret; receivers
+/// can use the delivery header to drop retried duplicates.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+/// Body sent for `notification`.
+fn payload(notification: &Notification) -> serde_json::Value {
+    serde_json::json!({
+        "id": notification.id,
+        "event": notification.event,
+        "subject": notification.subject,
+        "body": notification.body,
+        "data": notification.data,
+        "createdAt": notification.created_at,
+    })
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn send(
+        &self,
+        target: &str,
+        notification: &Notification,
+    ) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(target)
+            .header(EVENT_HEADER, &notification.event)
+            .header(DELIVERY_HEADER, notification.id.to_string())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload(notification).to_string())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Receiver responded with {}", response.status());
+        }
+        Ok(())
+    }
+}
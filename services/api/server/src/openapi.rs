@@ -4,13 +4,12 @@
 
 use utoipa::OpenApi;
 
-/// Main OpenAPI documentation for the Wire v1 API
+pub use crate::wire_api::core::v2::openapi::WireV2ApiDoc;
+
+/// Main OpenAPI documentation for the Wire v1 API, the paths and tags of
+/// each of [`crate::api_modules`] merged into it
 #[derive(OpenApi)]
 #[openapi(
-    paths(
-        crate::wire_api::core::v1::energy::aggregate::handler::handler,
-        crate::wire_api::core::v1::energy::history::handler::handler,
-    ),
     info(
         title = "Energy Readings API",
         version = "1.0.0",
@@ -19,24 +18,25 @@ use utoipa::OpenApi;
     ),
     servers(
         (url = "/api/wire/v1", description = "API v1")
-    ),
-    tags(
-        (name = "energy", description = "Energy readings aggregation and query history")
     )
 )]
 pub struct WireV1ApiDoc;
 
 impl WireV1ApiDoc {
     pub fn openapi() -> utoipa::openapi::OpenApi {
-        let openapi = <WireV1ApiDoc as utoipa::OpenApi>::openapi();
-        openapi
+        crate::api_modules()
+            .openapi(<WireV1ApiDoc as utoipa::OpenApi>::openapi())
     }
 
     /// Get OpenAPI spec as fixed JSON for OpenAPI 3.0 compatibility
     /// Converts type: ["array", "null"] to type: "array", nullable: true
     pub fn openapi_json() -> serde_json::Value {
-        let openapi = Self::openapi();
+        Self::to_3_0_json(Self::openapi())
+    }
 
+    /// `openapi` as fixed JSON for OpenAPI 3.0 compatibility, see
+    /// [`Self::openapi_json`]
+    pub fn to_3_0_json(openapi: utoipa::openapi::OpenApi) -> serde_json::Value {
         // Serialize to JSON
         let mut json_value = serde_json::to_value(&openapi)
             .expect("Failed to serialize OpenAPI spec");
@@ -0,0 +1,200 @@
+//! Transactional outbox for emitted events.
+//!
+//! Changes that emit an event, such as storing readings, [`record`] it in
+//! the `outbox` table in the same transaction, so an event exists exactly
+//! when its change was committed. The [`OutboxRelay`] publishes committed
+//! events in order: to Redis pub/sub when `OUTBOX_REDIS_CHANNEL` is set, and
+//! as deliveries to every subscribed webhook. An event is only marked
+//! published once every sink took it, so delivery is at least once and
+//! consumers should deduplicate on the event id.
+use std::sync::Arc;
+use std::time::Duration;
+
+use deadpool_redis::redis::AsyncCommands;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+use postgres_models::models::outbox::{NewOutboxEvent, OutboxEvent};
+use telemetry::metrics::Telemetry;
+
+use crate::events::{DomainEvent, DomainEvents};
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+use crate::webhooks::{self, WebhookEvent};
+
+/// Events published per relay transaction.
+const BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Clone)]
+pub struct RelaySettings {
+    pub poll_interval: Duration,
+    /// Redis channel events are published on, `None` to skip Redis
+    pub redis_channel: Option<String>,
+}
+
+/// Record `event` in the outbox. `conn` should be in the transaction that
+/// makes the change the event describes.
+pub async fn record(
+    event: WebhookEvent,
+    tenant: &str,
+    data: serde_json::Value,
+    conn: &mut AsyncPgConnection,
+) -> Result<OutboxEvent, diesel::result::Error> {
+    OutboxEvent::append(
+        NewOutboxEvent {
+            event_type: event.as_str().to_string(),
+            tenant_id: tenant.to_string(),
+            payload: data,
+        },
+        conn,
+    )
+    .await
+}
+
+/// Background worker that publishes committed outbox events.
+pub struct OutboxRelay {
+    pool: postgres_models::connection::Pool,
+    redis: redis_cache::connection::Pool,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    events: DomainEvents,
+    settings: RelaySettings,
+}
+
+impl OutboxRelay {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        redis: redis_cache::connection::Pool,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        events: DomainEvents,
+        settings: RelaySettings,
+    ) -> Self {
+        Self {
+            pool,
+            redis,
+            telemetry,
+            events,
+            settings,
+        }
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            poll_interval = ?self.settings.poll_interval,
+            redis_channel = ?self.settings.redis_channel,
+            "Starting outbox relay"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.poll_interval);
+        let mut events = self.events.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                // Relay an import's event as soon as it is committed
+                event = events.recv() => {
+                    if !matches!(
+                        event.as_deref(),
+                        Ok(DomainEvent::ImportCompleted { .. }) | Err(_)
+                    ) {
+                        continue;
+                    }
+                }
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            // Keep going while full batches come back, to catch up quickly
+            loop {
+                match self.relay_batch().await {
+                    Ok(published) if published == BATCH_SIZE as usize => {}
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::warn!("Outbox relay cycle failed: {e:#}");
+                        break;
+                    }
+                }
+            }
+        }
+
+        tracing::info!("Outbox relay stopped");
+    }
+
+    /// Publish the next batch of events, stopping at the first that fails so
+    /// events are never published out of order. Returns the number published.
+    async fn relay_batch(&self) -> anyhow::Result<usize> {
+        let mut conn = self.pool.get().await?;
+        let (published, queued) = conn
+            .transaction::<_, anyhow::Error, _>(|conn| {
+                async move {
+                    let events =
+                        OutboxEvent::claim_unpublished(BATCH_SIZE, conn)
+                            .await?;
+
+                    let mut published = Vec::with_capacity(events.len());
+                    let mut queued = 0;
+                    for event in &events {
+                        if let Err(e) = self.publish_redis(event).await {
+                            tracing::warn!(
+                                event_id = event.id,
+                                event = %event.event_type,
+                                attempts = event.attempts + 1,
+                                "Failed to publish outbox event: {e:#}"
+                            );
+                            self.telemetry.maybe_use_metrics(|m| {
+                                m.record_outbox_events("failed", 1);
+                            });
+                            OutboxEvent::record_failure(
+                                event.id,
+                                &format!("{e:#}"),
+                                conn,
+                            )
+                            .await?;
+                            break;
+                        }
+                        queued += webhooks::enqueue_deliveries(
+                            &event.tenant_id,
+                            &event.event_type,
+                            event.payload.clone(),
+                            conn,
+                        )
+                        .await?;
+                        published.push(event.id);
+                    }
+
+                    OutboxEvent::mark_published(&published, conn).await?;
+                    self.telemetry.maybe_use_metrics(|m| {
+                        m.record_outbox_events(
+                            "published",
+                            published.len() as u64,
+                        );
+                    });
+                    Ok((published.len(), queued))
+                }
+                .scope_boxed()
+            })
+            .await?;
+        if queued > 0 {
+            self.events
+                .publish(DomainEvent::DeliveriesQueued { count: queued });
+        }
+        Ok(published)
+    }
+
+    async fn publish_redis(&self, event: &OutboxEvent) -> anyhow::Result<()> {
+        let Some(channel) = &self.settings.redis_channel else {
+            return Ok(());
+        };
+
+        let message = serde_json::json!({
+            "id": event.id,
+            "event": event.event_type,
+            "tenant": event.tenant_id,
+            "createdAt": event.created_at,
+            "data": event.payload,
+        })
+        .to_string();
+        let mut conn = self.redis.get().await?;
+        let _: i64 = conn.publish(channel, message).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! Re-checks of the size of the database pools.
+//!
+//! Pools are sized once at startup from the `max_connections` of their
+//! database, see [`optimal_pool_size`]. A parameter change or a failover
+//! can change it, so the [`PoolSizeMonitor`] reads it again every
+//! `DATABASE_POOL_CHECK_INTERVAL_SECS` and compares the size it calls for
+//! with the one the pool has, exporting both in the `database_pool_size`
+//! metric. Drift is logged, and with `DATABASE_POOL_RESIZE=restart` the
+//! service also shuts down gracefully, for its orchestrator to start it
+//! again with pools of the new size: the pools are shared by every handler
+//! and worker, so they are not rebuilt in place.
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use postgres_models::connection::optimal_pool_size;
+use telemetry::metrics::Telemetry;
+
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+
+/// What happens when a pool's size drifts from the one it calls for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftAction {
+    /// Log the drift
+    Log,
+    /// Log it and shut down gracefully
+    Restart,
+}
+
+impl FromStr for DriftAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "log" => Ok(DriftAction::Log),
+            "restart" => Ok(DriftAction::Restart),
+            other => Err(format!(
+                "Unknown resize `{other}`, expected `log` or `restart`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolSizingSettings {
+    pub check_interval: Duration,
+    pub on_drift: DriftAction,
+}
+
+/// A pool whose size is checked.
+pub struct MonitoredPool {
+    /// Name of the pool in logs and metrics, like `read_only`
+    pub name: &'static str,
+    pub database_url: String,
+    /// Size the pool was built with
+    pub size: u32,
+}
+
+impl MonitoredPool {
+    /// The pool connected to `database_url`, whose size is read as the pool
+    /// itself was sized when it connected, right before.
+    pub async fn connected(
+        name: &'static str,
+        database_url: String,
+    ) -> anyhow::Result<Self> {
+        let (_, size) = optimal_pool_size(&database_url).await?;
+        Ok(Self {
+            name,
+            database_url,
+            size,
+        })
+    }
+}
+
+/// Background worker checking the size of the pools, see the module docs.
+pub struct PoolSizeMonitor {
+    pools: Vec<MonitoredPool>,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    settings: PoolSizingSettings,
+}
+
+impl PoolSizeMonitor {
+    pub fn new(
+        pools: Vec<MonitoredPool>,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        settings: PoolSizingSettings,
+    ) -> Self {
+        Self {
+            pools,
+            telemetry,
+            settings,
+        }
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            check_interval = ?self.settings.check_interval,
+            on_drift = ?self.settings.on_drift,
+            "Starting pool size monitor"
+        );
+        for pool in &self.pools {
+            self.record(pool.name, "current", pool.size);
+            self.record(pool.name, "optimal", pool.size);
+        }
+
+        let mut interval = tokio::time::interval(self.settings.check_interval);
+        // The first tick is now, right after the pools were sized
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            if self.check().await
+                && self.settings.on_drift == DriftAction::Restart
+            {
+                tracing::warn!(
+                    "Restarting to resize the database pools after drift"
+                );
+                shutdown.request();
+                break;
+            }
+        }
+
+        tracing::info!("Pool size monitor stopped");
+    }
+
+    /// Read the size every pool calls for, returning whether any drifted.
+    async fn check(&self) -> bool {
+        let mut drifted = false;
+        for pool in &self.pools {
+            let (max_connections, optimal) =
+                match optimal_pool_size(&pool.database_url).await {
+                    Ok(size) => size,
+                    Err(e) => {
+                        tracing::warn!(
+                            pool = pool.name,
+                            "Pool size check failed: {e:#}"
+                        );
+                        continue;
+                    }
+                };
+            self.record(pool.name, "optimal", optimal);
+            if optimal != pool.size {
+                tracing::warn!(
+                    pool = pool.name,
+                    max_connections,
+                    current = pool.size,
+                    optimal,
+                    "Database pool size drifted from max_connections"
+                );
+                drifted = true;
+            }
+        }
+        drifted
+    }
+
+    fn record(&self, pool: &str, size: &str, connections: u32) {
+        self.telemetry.maybe_use_metrics(|m| {
+            m.record_database_pool_size(pool, size, connections);
+        });
+    }
+}
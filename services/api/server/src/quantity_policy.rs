@@ -0,0 +1,182 @@
+//! What happens to implausible quantities when readings are stored.
+//!
+//! Negative quantities, and quantities above `QUANTITY_MAX_KWH` when set,
+//! are each handled by their own [`QuantityAction`]: stored as sent,
+//! dropped, clamped to the nearest plausible value, or stored with a
+//! `quality_code` naming the problem. The file loader and
+//! `POST /energy/readings` apply the same [`QuantityPolicy`] and report how
+//! many readings each action affected.
+use std::str::FromStr;
+
+/// `quality_code` of negative readings stored as flagged.
+pub const NEGATIVE: &str = "negative";
+/// `quality_code` of readings above the maximum stored as flagged.
+pub const OUTLIER: &str = "outlier";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuantityAction {
+    /// Store the quantity as sent
+    #[default]
+    Allow,
+    /// Drop the reading
+    Reject,
+    /// Store the nearest plausible quantity, 0 or the maximum
+    Clamp,
+    /// Store the quantity with a quality code
+    Flag,
+}
+
+impl QuantityAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuantityAction::Allow => "allow",
+            QuantityAction::Reject => "reject",
+            QuantityAction::Clamp => "clamp",
+            QuantityAction::Flag => "flag",
+        }
+    }
+}
+
+impl FromStr for QuantityAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(QuantityAction::Allow),
+            "reject" => Ok(QuantityAction::Reject),
+            "clamp" => Ok(QuantityAction::Clamp),
+            "flag" => Ok(QuantityAction::Flag),
+            other => Err(format!(
+                "expected allow, reject, clamp or flag, got `{other}`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QuantityPolicy {
+    /// For quantities below 0
+    pub negative: QuantityAction,
+    /// Largest plausible quantity of one reading, `None` for no limit
+    pub max_kwh: Option<f64>,
+    /// For quantities above `max_kwh`
+    pub outlier: QuantityAction,
+}
+
+/// A quantity as it is to be stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checked {
+    pub kwh: f64,
+    /// See [`NEGATIVE`] and [`OUTLIER`]
+    pub quality_code: Option<&'static str>,
+}
+
+/// Readings each action affected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuantityCounts {
+    pub rejected: usize,
+    pub clamped: usize,
+    pub flagged: usize,
+}
+
+impl std::ops::Add for QuantityCounts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            rejected: self.rejected + other.rejected,
+            clamped: self.clamped + other.clamped,
+            flagged: self.flagged + other.flagged,
+        }
+    }
+}
+
+impl QuantityPolicy {
+    /// `kwh` as it is to be stored, `None` when the reading is dropped;
+    /// `counts` records the action taken.
+    pub fn check(
+        &self,
+        kwh: f64,
+        counts: &mut QuantityCounts,
+    ) -> Option<Checked> {
+        let (action, limit, code) = if kwh < 0.0 {
+            (self.negative, 0.0, NEGATIVE)
+        } else {
+            match self.max_kwh {
+                Some(max) if kwh > max => (self.outlier, max, OUTLIER),
+                _ => (QuantityAction::Allow, kwh, ""),
+            }
+        };
+        let checked = |kwh, quality_code| Checked { kwh, quality_code };
+        match action {
+            QuantityAction::Allow => Some(checked(kwh, None)),
+            QuantityAction::Reject => {
+                counts.rejected += 1;
+                None
+            }
+            QuantityAction::Clamp => {
+                counts.clamped += 1;
+                Some(checked(limit, None))
+            }
+            QuantityAction::Flag => {
+                counts.flagged += 1;
+                Some(checked(kwh, Some(code)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applies_actions() {
+        let policy = |negative, outlier| QuantityPolicy {
+            negative,
+            max_kwh: Some(100.0),
+            outlier,
+        };
+        let check = |policy: QuantityPolicy, kwh| {
+            let mut counts = QuantityCounts::default();
+            let checked = policy.check(kwh, &mut counts);
+            (checked.map(|c| (c.kwh, c.quality_code)), counts)
+        };
+        let counts = |rejected, clamped, flagged| QuantityCounts {
+            rejected,
+            clamped,
+            flagged,
+        };
+        use QuantityAction::*;
+
+        assert_eq!(
+            check(policy(Reject, Reject), 50.0),
+            (Some((50.0, None)), counts(0, 0, 0))
+        );
+        assert_eq!(
+            check(policy(Allow, Allow), -1.0),
+            (Some((-1.0, None)), counts(0, 0, 0))
+        );
+        assert_eq!(check(policy(Reject, Allow), -1.0), (None, counts(1, 0, 0)));
+        assert_eq!(
+            check(policy(Clamp, Allow), -1.0),
+            (Some((0.0, None)), counts(0, 1, 0))
+        );
+        assert_eq!(
+            check(policy(Flag, Allow), -1.0),
+            (Some((-1.0, Some(NEGATIVE))), counts(0, 0, 1))
+        );
+        assert_eq!(
+            check(policy(Allow, Clamp), 150.0),
+            (Some((100.0, None)), counts(0, 1, 0))
+        );
+        assert_eq!(
+            check(policy(Allow, Flag), 150.0),
+            (Some((150.0, Some(OUTLIER))), counts(0, 0, 1))
+        );
+        assert_eq!(
+            check(QuantityPolicy::default(), 1e9),
+            (Some((1e9, None)), counts(0, 0, 0))
+        );
+    }
+}
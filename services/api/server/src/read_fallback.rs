@@ -0,0 +1,279 @@
+//! Reads that fall back to the read-write pool while the read replica is down.
+//!
+//! Handlers read through [`ReadPools`] rather than the read-only pool. A
+//! [`ReplicaMonitor`] checks the replica every
+//! `READ_REPLICA_CHECK_INTERVAL_SECS`, and a read that fails to open a
+//! connection to it marks it down at once. While it is down, reads go to
+//! the read-write pool instead, at most `READ_FALLBACK_CONCURRENCY` at a
+//! time: reads beyond that fail with `503 pool_exhausted` rather than pile
+//! onto the primary. Responses with a read served by the primary carry
+//! `X-Degraded: read-replica`. A concurrency of 0 never falls back.
+use std::cell::Cell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use postgres_models::connection::{
+    Pool, PooledConnection, WithConnectionError, is_pool_exhausted,
+    with_connection,
+};
+use telemetry::metrics::Telemetry;
+use tokio::sync::Semaphore;
+
+use crate::health::{HealthStatus, check_postgres};
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+
+/// Header flagging responses with a read served by the read-write pool.
+pub const DEGRADED_HEADER: &str = "x-degraded";
+
+tokio::task_local! {
+    /// Whether a read of the current request fell back
+    static DEGRADED: Cell<bool>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackSettings {
+    /// Reads let onto the read-write pool at a time; 0 never falls back
+    pub concurrency: u32,
+    /// How often the replica is checked
+    pub check_interval: Duration,
+}
+
+struct Fallback {
+    pool: Pool,
+    permits: Semaphore,
+    replica_up: AtomicBool,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+}
+
+/// The pools reads are served from, see the module docs.
+#[derive(Clone)]
+pub struct ReadPools {
+    read_only_pool: Pool,
+    fallback: Option<Arc<Fallback>>,
+}
+
+impl ReadPools {
+    /// Reads from `read_only_pool` alone.
+    pub fn new(read_only_pool: Pool) -> Self {
+        Self {
+            read_only_pool,
+            fallback: None,
+        }
+    }
+
+    /// Fall back to `pool`, the read-write one, while the replica is down,
+    /// with at most `concurrency` reads at a time.
+    pub fn with_fallback(
+        mut self,
+        pool: Pool,
+        concurrency: u32,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+    ) -> Self {
+        self.fallback = Some(Arc::new(Fallback {
+            pool,
+            permits: Semaphore::new(concurrency as usize),
+            replica_up: AtomicBool::new(true),
+            telemetry,
+        }));
+        self
+    }
+
+    pub fn replica_up(&self) -> bool {
+        self.fallback
+            .as_ref()
+            .is_none_or(|fallback| fallback.replica_up.load(Ordering::Relaxed))
+    }
+
+    /// The pool reads are served from: the replica while it is up, the
+    /// read-write pool otherwise.
+    pub fn serving(&self) -> &Pool {
+        match &self.fallback {
+            Some(fallback) if !fallback.replica_up.load(Ordering::Relaxed) => {
+                &fallback.pool
+            }
+            _ => &self.read_only_pool,
+        }
+    }
+
+    /// Like [`with_connection`], on the replica while it is up and on the
+    /// read-write pool otherwise.
+    pub async fn with_connection<F, Fut, T, E>(
+        &self,
+        operation: F,
+    ) -> Result<T, WithConnectionError<E>>
+    where
+        F: FnOnce(PooledConnection) -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+    {
+        let Some(fallback) = &self.fallback else {
+            return with_connection(&self.read_only_pool, operation).await;
+        };
+        if fallback.replica_up.load(Ordering::Relaxed) {
+            let result = with_connection(&self.read_only_pool, operation).await;
+            // A pool timing out may just be busy, the monitor tells
+            if let Err(WithConnectionError::Pool(e)) = &result
+                && !is_pool_exhausted(e)
+            {
+                self.mark(false);
+            }
+            return result;
+        }
+
+        let Ok(_permit) = fallback.permits.try_acquire() else {
+            fallback.record("rejected");
+            return Err(WithConnectionError::Pool(
+                diesel_async::pooled_connection::bb8::RunError::TimedOut,
+            ));
+        };
+        fallback.record("served");
+        let _ = DEGRADED.try_with(|degraded| degraded.set(true));
+        with_connection(&fallback.pool, operation).await
+    }
+
+    fn mark(&self, up: bool) {
+        let Some(fallback) = &self.fallback else {
+            return;
+        };
+        if fallback.replica_up.swap(up, Ordering::Relaxed) != up {
+            if up {
+                tracing::info!("Read replica is back, reading from it again");
+            } else {
+                tracing::warn!(
+                    "Read replica is down, falling back to the read-write pool"
+                );
+            }
+            fallback.telemetry.maybe_use_metrics(|m| {
+                m.record_read_replica_up(up);
+            });
+        }
+    }
+}
+
+impl Fallback {
+    fn record(&self, outcome: &str) {
+        self.telemetry.maybe_use_metrics(|m| {
+            m.record_read_fallback(outcome);
+        });
+    }
+}
+
+/// Set `X-Degraded` on responses with a read served by the read-write pool.
+pub async fn flag_degraded(request: Request, next: Next) -> Response {
+    let (degraded, mut response) = DEGRADED
+        .scope(Cell::new(false), async {
+            let response = next.run(request).await;
+            (DEGRADED.with(Cell::get), response)
+        })
+        .await;
+    if degraded {
+        response
+            .headers_mut()
+            .insert(DEGRADED_HEADER, HeaderValue::from_static("read-replica"));
+    }
+    response
+}
+
+/// Background worker checking whether the replica is up.
+pub struct ReplicaMonitor {
+    reads: ReadPools,
+    check_interval: Duration,
+}
+
+impl ReplicaMonitor {
+    pub fn new(reads: ReadPools, check_interval: Duration) -> Self {
+        Self {
+            reads,
+            check_interval,
+        }
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            check_interval = ?self.check_interval,
+            "Starting read replica monitor"
+        );
+        let mut interval = tokio::time::interval(self.check_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            let health = check_postgres(&self.reads.read_only_pool).await;
+            if let Some(error) = &health.error {
+                tracing::debug!("Read replica check failed: {error}");
+            }
+            self.reads.mark(health.status == HealthStatus::Healthy);
+        }
+
+        tracing::info!("Read replica monitor stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+
+    use super::*;
+
+    fn unreachable_pool() -> Pool {
+        Pool::builder()
+            .connection_timeout(Duration::from_millis(100))
+            .build_unchecked(AsyncDieselConnectionManager::new(
+                "postgres://127.0.0.1:1/unused",
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_within_the_limit() {
+        let metrics = ServerMetrics::new_with_random_prefix().unwrap();
+        let telemetry = Telemetry::new(Some(metrics.clone())).await.unwrap();
+        let reads = ReadPools::new(unreachable_pool()).with_fallback(
+            unreachable_pool(),
+            1,
+            telemetry,
+        );
+        let read = || {
+            reads.with_connection(|_| async {
+                Ok::<_, diesel::result::Error>(())
+            })
+        };
+
+        // Not while the replica is up
+        let degraded = DEGRADED
+            .scope(Cell::new(false), async {
+                assert!(read().await.unwrap_err().is_pool_exhausted());
+                DEGRADED.with(Cell::get)
+            })
+            .await;
+        assert!(!degraded);
+        assert!(reads.replica_up());
+        assert!(std::ptr::eq(reads.serving(), &reads.read_only_pool));
+
+        reads.mark(false);
+        assert!(!std::ptr::eq(reads.serving(), &reads.read_only_pool));
+        let degraded = DEGRADED
+            .scope(Cell::new(false), async {
+                let (first, second) = tokio::join!(read(), read());
+                assert!(first.is_err() && second.is_err());
+                DEGRADED.with(Cell::get)
+            })
+            .await;
+        assert!(degraded);
+        let fallbacks = |outcome| {
+            metrics.read_fallbacks.with_label_values(&[outcome]).get()
+        };
+        assert_eq!(fallbacks("served"), 1);
+        assert_eq!(fallbacks("rejected"), 1);
+        assert_eq!(metrics.read_replica_up.get(), 0);
+    }
+}
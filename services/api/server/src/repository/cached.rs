@@ -0,0 +1,251 @@
+//! Read-through Redis cache in front of another repository.
+//!
+//! [`CachedQueryHistory`] keeps each tenant's last [`CACHED_ENTRIES`]
+//! queries in Redis, so polling `/energy/history` or the gRPC `History`
+//! does not reach the read replica, and drops them on every write through
+//! it. Reads go to the inner repository while the `response_cache` flag is
+//! off or Redis is unavailable; history written past this repository shows
+//! up within [`CACHE_TTL`].
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use postgres_models::models::Keyset;
+use postgres_models::models::query_history::{
+    FrequentQuery, NewQueryHistory, QueryHistory,
+};
+use redis_cache::connection::Pool;
+
+use super::{QueryHistoryRepository, RepositoryResult};
+use crate::auth::TenantContext;
+use crate::flags::{FeatureFlags, Flag};
+use crate::shared::cache_key::CacheKey;
+use crate::shared::response_cache;
+
+/// Entries cached per tenant; larger limits read the inner repository.
+pub const CACHED_ENTRIES: i64 = 100;
+/// How long the cached entries are served without a write.
+pub const CACHE_TTL: Duration = Duration::from_secs(300);
+
+pub struct CachedQueryHistory {
+    inner: Arc<dyn QueryHistoryRepository>,
+    cache_pool: Pool,
+    flags: Arc<FeatureFlags>,
+}
+
+impl CachedQueryHistory {
+    pub fn new(
+        inner: Arc<dyn QueryHistoryRepository>,
+        cache_pool: Pool,
+        flags: Arc<FeatureFlags>,
+    ) -> Self {
+        Self {
+            inner,
+            cache_pool,
+            flags,
+        }
+    }
+
+    fn key(tenant: &str) -> String {
+        CacheKey::new(&TenantContext::new(tenant), "query_history", "latest")
+            .build()
+    }
+}
+
+#[async_trait]
+impl QueryHistoryRepository for CachedQueryHistory {
+    async fn create(
+        &self,
+        entry: NewQueryHistory,
+    ) -> RepositoryResult<QueryHistory> {
+        let key = Self::key(&entry.tenant_id);
+        let created = self.inner.create(entry).await?;
+        // Also while the flag is off, so turning it on serves no stale list
+        response_cache::delete(&self.cache_pool, &key).await;
+        Ok(created)
+    }
+
+    async fn create_many(
+        &self,
+        entries: Vec<NewQueryHistory>,
+    ) -> RepositoryResult<usize> {
+        let keys = entries
+            .iter()
+            .map(|entry| Self::key(&entry.tenant_id))
+            .collect::<BTreeSet<_>>();
+        let created = self.inner.create_many(entries).await?;
+        for key in keys {
+            response_cache::delete(&self.cache_pool, &key).await;
+        }
+        Ok(created)
+    }
+
+    async fn latest(
+        &self,
+        tenant: &str,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>> {
+        if limit > CACHED_ENTRIES
+            || !self
+                .flags
+                .is_enabled(Flag::ResponseCache, &self.cache_pool)
+                .await
+        {
+            return self.inner.latest(tenant, limit).await;
+        }
+
+        let key = Self::key(tenant);
+        let cached =
+            response_cache::get(&self.cache_pool, &key).await.and_then(
+                |json| serde_json::from_str::<Vec<QueryHistory>>(&json).ok(),
+            );
+        let mut entries = match cached {
+            Some(entries) => entries,
+            None => {
+                let entries = self.inner.latest(tenant, CACHED_ENTRIES).await?;
+                if let Ok(json) = serde_json::to_string(&entries) {
+                    response_cache::set(
+                        &self.cache_pool,
+                        &key,
+                        &json,
+                        CACHE_TTL,
+                    )
+                    .await;
+                }
+                entries
+            }
+        };
+        entries.truncate(usize::try_from(limit).unwrap_or_default());
+        Ok(entries)
+    }
+
+    async fn page(
+        &self,
+        tenant: &str,
+        keyset: Keyset,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>> {
+        self.inner.page(tenant, keyset, limit).await
+    }
+
+    async fn most_frequent(
+        &self,
+        tenant: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> RepositoryResult<Vec<FrequentQuery>> {
+        self.inner.most_frequent(tenant, since, limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use uuid::Uuid;
+
+    use super::super::memory::InMemoryQueryHistory;
+    use super::*;
+    use crate::wire_api::testing::TestApp;
+
+    /// Counts the reads that reach the inner repository.
+    #[derive(Default)]
+    struct CountingHistory {
+        inner: InMemoryQueryHistory,
+        reads: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl QueryHistoryRepository for CountingHistory {
+        async fn create(
+            &self,
+            entry: NewQueryHistory,
+        ) -> RepositoryResult<QueryHistory> {
+            self.inner.create(entry).await
+        }
+
+        async fn create_many(
+            &self,
+            entries: Vec<NewQueryHistory>,
+        ) -> RepositoryResult<usize> {
+            self.inner.create_many(entries).await
+        }
+
+        async fn latest(
+            &self,
+            tenant: &str,
+            limit: i64,
+        ) -> RepositoryResult<Vec<QueryHistory>> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.inner.latest(tenant, limit).await
+        }
+
+        async fn page(
+            &self,
+            tenant: &str,
+            keyset: Keyset,
+            limit: i64,
+        ) -> RepositoryResult<Vec<QueryHistory>> {
+            self.inner.page(tenant, keyset, limit).await
+        }
+
+        async fn most_frequent(
+            &self,
+            tenant: &str,
+            since: DateTime<Utc>,
+            limit: i64,
+        ) -> RepositoryResult<Vec<FrequentQuery>> {
+            self.inner.most_frequent(tenant, since, limit).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_through_until_a_write() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let inner = Arc::new(CountingHistory::default());
+        let history = CachedQueryHistory::new(
+            inner.clone(),
+            app.state.cache_pool.clone(),
+            Arc::new(FeatureFlags::new(Default::default())),
+        );
+        // Keeps the shared Redis of earlier runs out of the way
+        let tenant = format!("cached-{}", Uuid::new_v4().simple());
+        let create = |aggregation_type: &str| NewQueryHistory {
+            aggregation_type: aggregation_type.to_string(),
+            date_from: None,
+            date_to: None,
+            api_key_id: None,
+            tenant_id: tenant.clone(),
+            created_at: None,
+        };
+        let types = |entries: Vec<QueryHistory>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.aggregation_type)
+                .collect::<Vec<_>>()
+        };
+
+        history.create(create("hourly")).await.unwrap();
+        history.create(create("monthly")).await.unwrap();
+        assert_eq!(
+            types(history.latest(&tenant, 10).await.unwrap()),
+            ["monthly", "hourly"]
+        );
+        assert_eq!(
+            types(history.latest(&tenant, 1).await.unwrap()),
+            ["monthly"]
+        );
+        assert_eq!(inner.reads.load(Ordering::Relaxed), 1);
+
+        history.create(create("day_of_month")).await.unwrap();
+        assert_eq!(
+            types(history.latest(&tenant, 10).await.unwrap()),
+            ["day_of_month", "monthly", "hourly"]
+        );
+        assert_eq!(inner.reads.load(Ordering::Relaxed), 2);
+    }
+}
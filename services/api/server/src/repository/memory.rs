@@ -0,0 +1,506 @@
+//! Repositories kept in memory, for tests and tools without a database.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Datelike, Months, TimeDelta, Timelike, Utc, Weekday};
+use postgres_models::connection::WithConnectionError;
+use postgres_models::models::Keyset;
+use postgres_models::models::energy_readings::{
+    AggregateEstimate, AggregateOptions, AggregatedReading, NewEnergyReading,
+};
+use postgres_models::models::query_history::{
+    FrequentQuery, NewQueryHistory, QueryHistory,
+};
+use uuid::Uuid;
+
+use super::{
+    EnergyReadingRepository, QueryHistoryRepository, RepositoryResult,
+};
+
+/// Start of the `trunc_level` period of `time` in UTC, as `date_trunc`
+/// computes it, see [`postgres_models::periods`].
+fn truncate(time: DateTime<Utc>, trunc_level: &str) -> Option<DateTime<Utc>> {
+    let date = time.date_naive();
+    let start = match trunc_level {
+        "hour" => date.and_hms_opt(time.hour(), 0, 0)?,
+        "day" => date.and_hms_opt(0, 0, 0)?,
+        "week" => date.week(Weekday::Mon).first_day().and_hms_opt(0, 0, 0)?,
+        "month" => date.with_day(1)?.and_hms_opt(0, 0, 0)?,
+        "quarter" => date
+            .with_day(1)?
+            .with_month0(date.month0() / 3 * 3)?
+            .and_hms_opt(0, 0, 0)?,
+        "year" => date.with_ordinal(1)?.and_hms_opt(0, 0, 0)?,
+        level => {
+            let start = level.strip_prefix("week_")?.parse::<Weekday>().ok()?;
+            date.week(start).first_day().and_hms_opt(0, 0, 0)?
+        }
+    };
+    Some(start.and_utc())
+}
+
+/// Start of the `trunc_level` period after the one starting at `start`.
+fn next_period(
+    start: DateTime<Utc>,
+    trunc_level: &str,
+) -> Option<DateTime<Utc>> {
+    match postgres_models::periods::field(trunc_level) {
+        "hour" => start.checked_add_signed(TimeDelta::hours(1)),
+        "day" => start.checked_add_signed(TimeDelta::days(1)),
+        "week" => start.checked_add_signed(TimeDelta::weeks(1)),
+        "month" => start.checked_add_months(Months::new(1)),
+        "quarter" => start.checked_add_months(Months::new(3)),
+        "year" => start.checked_add_months(Months::new(12)),
+        _ => None,
+    }
+}
+
+/// Readings in a vector.
+#[derive(Default)]
+pub struct InMemoryEnergyReadings {
+    readings: Mutex<Vec<NewEnergyReading>>,
+}
+
+impl InMemoryEnergyReadings {
+    pub fn new(readings: Vec<NewEnergyReading>) -> Self {
+        Self {
+            readings: Mutex::new(readings),
+        }
+    }
+
+    pub fn insert(&self, readings: impl IntoIterator<Item = NewEnergyReading>) {
+        self.readings
+            .lock()
+            .expect("readings lock poisoned")
+            .extend(readings);
+    }
+}
+
+#[async_trait]
+impl EnergyReadingRepository for InMemoryEnergyReadings {
+    async fn aggregate(
+        &self,
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        options: AggregateOptions,
+    ) -> RepositoryResult<Vec<AggregatedReading>> {
+        let unknown_level = || {
+            WithConnectionError::Operation(
+                diesel::result::Error::QueryBuilderError(
+                    format!("Unknown truncation level {trunc_level}").into(),
+                ),
+            )
+        };
+        // The readings by period
+        let mut periods = BTreeMap::<DateTime<Utc>, AggregatedReading>::new();
+        let mut feeds = HashMap::<DateTime<Utc>, HashSet<Option<Uuid>>>::new();
+        for reading in
+            self.readings.lock().expect("readings lock poisoned").iter()
+        {
+            if reading.tenant_id != tenant
+                || plant.is_some_and(|plant| reading.plant_id != Some(plant))
+                || date_from.is_some_and(|from| reading.reading_time < from)
+                || date_to.is_some_and(|to| reading.reading_time >= to)
+            {
+                continue;
+            }
+            let period = truncate(reading.reading_time, trunc_level)
+                .ok_or_else(unknown_level)?;
+            feeds.entry(period).or_default().insert(reading.plant_id);
+            let row = periods.entry(period).or_default();
+            let quantity = &reading.quantity_kwh;
+            row.total_kwh += quantity;
+            row.reading_count += 1;
+            if row.min_kwh.as_ref().is_none_or(|min| quantity < min) {
+                row.min_kwh = Some(quantity.clone());
+            }
+            if row.max_kwh.as_ref().is_none_or(|max| quantity > max) {
+                row.max_kwh = Some(quantity.clone());
+            }
+        }
+        for (period, row) in periods.iter_mut() {
+            row.feed_count = feeds[period].len() as i64;
+            // Rounded half away from zero, as `ROUND` does
+            row.avg_kwh = Some(
+                (&row.total_kwh / BigDecimal::from(row.reading_count))
+                    .with_scale_round(4, RoundingMode::HalfUp),
+            );
+        }
+
+        if options.fill_gaps {
+            let first = match date_from {
+                Some(from) => truncate(from, trunc_level),
+                None => periods.keys().next().copied(),
+            };
+            let last = match date_to {
+                Some(to) => {
+                    truncate(to - TimeDelta::microseconds(1), trunc_level)
+                }
+                None => periods.keys().next_back().copied(),
+            };
+            if let (Some(mut period), Some(last)) = (first, last) {
+                while period <= last {
+                    // At the scale of the column, as Postgres fills them
+                    periods.entry(period).or_insert_with(|| {
+                        AggregatedReading {
+                            total_kwh: BigDecimal::new(0.into(), 4),
+                            ..Default::default()
+                        }
+                    });
+                    period = next_period(period, trunc_level)
+                        .ok_or_else(unknown_level)?;
+                }
+            }
+        }
+        if options.cumulative {
+            let mut running = BigDecimal::default();
+            for row in periods.values_mut() {
+                running += &row.total_kwh;
+                row.total_kwh = running.clone();
+            }
+        }
+
+        Ok(periods
+            .into_iter()
+            .map(|(period, row)| AggregatedReading { period, ..row })
+            .collect())
+    }
+
+    /// Exact, from the aggregation with its gaps filled.
+    async fn estimate(
+        &self,
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+    ) -> RepositoryResult<AggregateEstimate> {
+        let options = AggregateOptions {
+            fill_gaps: true,
+            ..Default::default()
+        };
+        let periods = self
+            .aggregate(tenant, plant, trunc_level, date_from, date_to, options)
+            .await?;
+        Ok(AggregateEstimate {
+            periods: periods.len() as i64,
+            scanned_rows: periods.iter().map(|p| p.reading_count).sum(),
+        })
+    }
+}
+
+/// Query history in a vector.
+#[derive(Default)]
+pub struct InMemoryQueryHistory {
+    entries: Mutex<Vec<QueryHistory>>,
+}
+
+impl InMemoryQueryHistory {
+    /// Every recorded entry, oldest first.
+    pub fn entries(&self) -> Vec<QueryHistory> {
+        self.entries.lock().expect("history lock poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl QueryHistoryRepository for InMemoryQueryHistory {
+    async fn create(
+        &self,
+        entry: NewQueryHistory,
+    ) -> RepositoryResult<QueryHistory> {
+        let entry = QueryHistory {
+            id: Uuid::new_v4(),
+            aggregation_type: entry.aggregation_type,
+            date_from: entry.date_from,
+            date_to: entry.date_to,
+            created_at: entry.created_at.unwrap_or_else(Utc::now),
+            api_key_id: entry.api_key_id,
+            tenant_id: entry.tenant_id,
+        };
+        self.entries
+            .lock()
+            .expect("history lock poisoned")
+            .push(entry.clone());
+        Ok(entry)
+    }
+
+    async fn create_many(
+        &self,
+        entries: Vec<NewQueryHistory>,
+    ) -> RepositoryResult<usize> {
+        let count = entries.len();
+        for entry in entries {
+            self.create(entry).await?;
+        }
+        Ok(count)
+    }
+
+    async fn latest(
+        &self,
+        tenant: &str,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>> {
+        Ok(self
+            .entries
+            .lock()
+            .expect("history lock poisoned")
+            .iter()
+            .rev()
+            .filter(|entry| entry.tenant_id == tenant)
+            .take(usize::try_from(limit).unwrap_or_default())
+            .cloned()
+            .collect())
+    }
+
+    async fn page(
+        &self,
+        tenant: &str,
+        keyset: Keyset,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>> {
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("history lock poisoned")
+            .iter()
+            .filter(|entry| entry.tenant_id == tenant)
+            .filter(|entry| {
+                let key = (entry.created_at, entry.id);
+                match keyset {
+                    Keyset::First => true,
+                    Keyset::After(time, id) => key < (time, id),
+                    Keyset::Before(time, id) => key > (time, id),
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| {
+            std::cmp::Reverse((entry.created_at, entry.id))
+        });
+
+        let limit = usize::try_from(limit).unwrap_or_default();
+        // Nearest to the cursor first, like the query
+        if let Keyset::Before(..) = keyset {
+            let skip = entries.len().saturating_sub(limit);
+            entries.drain(..skip);
+        } else {
+            entries.truncate(limit);
+        }
+        Ok(entries)
+    }
+
+    async fn most_frequent(
+        &self,
+        tenant: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> RepositoryResult<Vec<FrequentQuery>> {
+        type Query = (String, Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+        // Count and latest run of each query
+        let mut runs = HashMap::<Query, (i64, DateTime<Utc>)>::new();
+        for entry in self.entries.lock().expect("history lock poisoned").iter()
+        {
+            if entry.tenant_id != tenant || entry.created_at < since {
+                continue;
+            }
+            let query = (
+                entry.aggregation_type.clone(),
+                entry.date_from,
+                entry.date_to,
+            );
+            let (count, latest) =
+                runs.entry(query).or_insert((0, entry.created_at));
+            *count += 1;
+            *latest = (*latest).max(entry.created_at);
+        }
+
+        let mut runs = runs.into_iter().collect::<Vec<_>>();
+        runs.sort_by(|(_, a), (_, b)| b.cmp(a));
+        Ok(runs
+            .into_iter()
+            .take(usize::try_from(limit).unwrap_or_default())
+            .map(|((aggregation_type, date_from, date_to), (count, _))| {
+                FrequentQuery {
+                    aggregation_type,
+                    date_from,
+                    date_to,
+                    count,
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_aggregate_like_date_trunc() {
+        let at = |month, day, hour| {
+            Utc.with_ymd_and_hms(2025, month, day, hour, 0, 0).unwrap()
+        };
+        let plant = Uuid::new_v4();
+        let reading = |tenant: &str, plant, time, kwh: &str| NewEnergyReading {
+            reading_time: time,
+            quantity_kwh: kwh.parse().unwrap(),
+            tenant_id: tenant.to_string(),
+            plant_id: plant,
+            quality_code: None,
+            source: "file".to_string(),
+        };
+        let readings = InMemoryEnergyReadings::new(vec![
+            reading("default", None, at(1, 1, 0), "1.5"),
+            reading("default", Some(plant), at(1, 31, 23), "2"),
+            reading("default", None, at(2, 1, 0), "4"),
+            reading("other", None, at(1, 2, 0), "100"),
+        ]);
+
+        let months = readings
+            .aggregate(
+                "default",
+                None,
+                "month",
+                None,
+                None,
+                AggregateOptions::default(),
+            )
+            .await
+            .unwrap();
+        let totals = months
+            .iter()
+            .map(|row| (row.period, row.total_kwh.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            totals,
+            [
+                (at(1, 1, 0), "3.5".to_string()),
+                (at(2, 1, 0), "4".to_string())
+            ]
+        );
+
+        let days = readings
+            .aggregate(
+                "default",
+                Some(plant),
+                "day",
+                Some(at(1, 2, 0)),
+                None,
+                AggregateOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].period, at(1, 31, 0));
+
+        let filled = readings
+            .aggregate(
+                "default",
+                None,
+                "month",
+                None,
+                Some(at(4, 1, 0)),
+                AggregateOptions {
+                    fill_gaps: true,
+                    cumulative: true,
+                },
+            )
+            .await
+            .unwrap();
+        let totals = filled
+            .iter()
+            .map(|row| (row.period, row.total_kwh.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            totals,
+            [
+                (at(1, 1, 0), "3.5".to_string()),
+                (at(2, 1, 0), "7.5".to_string()),
+                // The gap is at the scale of the column
+                (at(3, 1, 0), "7.5000".to_string())
+            ]
+        );
+        let counts = filled.iter().map(|row| row.reading_count);
+        assert_eq!(counts.collect::<Vec<_>>(), [2, 1, 0]);
+        assert!(
+            readings
+                .aggregate(
+                    "default",
+                    None,
+                    "decade",
+                    None,
+                    None,
+                    AggregateOptions::default(),
+                )
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_truncates_to_weeks_quarters_and_years() {
+        // A Wednesday
+        let time = Utc.with_ymd_and_hms(2025, 8, 13, 17, 30, 0).unwrap();
+        let at = |year, month, day| {
+            Some(Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap())
+        };
+        assert_eq!(truncate(time, "week"), at(2025, 8, 11));
+        assert_eq!(truncate(time, "week_sunday"), at(2025, 8, 10));
+        assert_eq!(truncate(time, "week_wednesday"), at(2025, 8, 13));
+        assert_eq!(truncate(time, "week_thursday"), at(2025, 8, 7));
+        assert_eq!(truncate(time, "quarter"), at(2025, 7, 1));
+        assert_eq!(truncate(time, "year"), at(2025, 1, 1));
+        assert_eq!(truncate(time, "week_someday"), None);
+    }
+
+    #[tokio::test]
+    async fn test_most_frequent_queries_first() {
+        let history = InMemoryQueryHistory::default();
+        for (tenant, aggregation_type) in [
+            ("acme", "hourly"),
+            ("acme", "monthly"),
+            ("acme", "monthly"),
+            ("other", "hourly"),
+            ("other", "hourly"),
+            ("acme", "day_of_month"),
+        ] {
+            let entry = NewQueryHistory {
+                aggregation_type: aggregation_type.to_string(),
+                date_from: None,
+                date_to: None,
+                api_key_id: None,
+                tenant_id: tenant.to_string(),
+                created_at: None,
+            };
+            history.create(entry).await.unwrap();
+        }
+
+        let since = Utc::now() - chrono::Duration::hours(1);
+        let counts = history
+            .most_frequent("acme", since, 2)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|query| (query.aggregation_type, query.count))
+            .collect::<Vec<_>>();
+        // Of the queries run once, the latest
+        assert_eq!(
+            counts,
+            [("monthly".to_string(), 2), ("day_of_month".to_string(), 1)]
+        );
+        assert!(
+            history
+                .most_frequent("acme", Utc::now(), 2)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
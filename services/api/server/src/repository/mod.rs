@@ -0,0 +1,764 @@
+//! Storage of readings and query history behind traits.
+//!
+//! Handlers reach aggregated readings and the query history through the
+//! [`EnergyReadingRepository`] and [`QueryHistoryRepository`] of the
+//! [`AppState`](crate::AppState) rather than the Diesel models, so they can
+//! run on the [`memory`] repositories in unit tests and on other backends.
+//! The Diesel repositories run the models' queries on the server's pools:
+//! reads on the read-only pool, writes on the read-write one, and the
+//! [`cached`] ones keep hot reads in Redis in front of them. Aggregations
+//! can be hedged on the read-write pool, see [`hedged`].
+pub mod cached;
+pub mod memory;
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use postgres_models::connection::{
+    CancelToken, Pool, PooledConnection, WithConnectionError, cancel_query,
+    with_connection,
+};
+use postgres_models::models::Keyset;
+use postgres_models::models::energy_readings::{
+    AggregateEstimate, AggregateOptions, AggregatedReading, EnergyReading,
+};
+use postgres_models::models::query_history::{
+    FrequentQuery, NewQueryHistory, QueryHistory,
+};
+use telemetry::metrics::Telemetry;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use crate::metrics::ServerMetrics;
+use crate::read_fallback::ReadPools;
+
+/// Result of a repository call, failing like [`with_connection`].
+pub type RepositoryResult<T> =
+    Result<T, WithConnectionError<diesel::result::Error>>;
+
+#[async_trait]
+pub trait EnergyReadingRepository: Send + Sync {
+    /// The tenant's readings, optionally only those of `plant`, summed by
+    /// `trunc_level` (`hour`, `day`, `week`, `week_<day>`, `month`,
+    /// `quarter` or `year`, see [`postgres_models::periods`]) in
+    /// `[date_from, date_to)`, ordered by period, returned as `options`
+    /// say.
+    async fn aggregate(
+        &self,
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        options: AggregateOptions,
+    ) -> RepositoryResult<Vec<AggregatedReading>>;
+
+    /// The periods and scanned readings of the aggregation of
+    /// [`EnergyReadingRepository::aggregate`], without running it, see
+    /// [`EnergyReading::estimate_aggregate`].
+    async fn estimate(
+        &self,
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+    ) -> RepositoryResult<AggregateEstimate>;
+}
+
+#[async_trait]
+pub trait QueryHistoryRepository: Send + Sync {
+    /// Record an aggregation query.
+    async fn create(
+        &self,
+        entry: NewQueryHistory,
+    ) -> RepositoryResult<QueryHistory>;
+
+    /// Record aggregation queries at once, returning how many were recorded.
+    async fn create_many(
+        &self,
+        entries: Vec<NewQueryHistory>,
+    ) -> RepositoryResult<usize>;
+
+    /// The tenant's last `limit` queries, most recent first.
+    async fn latest(
+        &self,
+        tenant: &str,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>>;
+
+    /// A page of the tenant's queries from `keyset`, like
+    /// [`QueryHistory::page`].
+    async fn page(
+        &self,
+        tenant: &str,
+        keyset: Keyset,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>>;
+
+    /// The tenant's `limit` most frequent queries since `since`, like
+    /// [`QueryHistory::most_frequent`].
+    async fn most_frequent(
+        &self,
+        tenant: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> RepositoryResult<Vec<FrequentQuery>>;
+}
+
+/// How a [`hedged`] read was answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HedgeOutcome {
+    /// The first attempt answered within the delay
+    NotHedged,
+    /// The first attempt answered before the hedge
+    PrimaryWon,
+    /// The hedge answered first
+    HedgeWon,
+    /// Too many reads were hedged already, the first attempt was awaited
+    Capped,
+}
+
+impl HedgeOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HedgeOutcome::NotHedged => "not_hedged",
+            HedgeOutcome::PrimaryWon => "primary_won",
+            HedgeOutcome::HedgeWon => "hedge_won",
+            HedgeOutcome::Capped => "capped",
+        }
+    }
+}
+
+/// The result of `primary`, or of `hedge` started once `primary` has not
+/// answered within `delay`, whichever succeeds first. When the first to
+/// answer fails, the other is awaited. `hedge` returns `None` when the read
+/// may not be hedged, and `primary` is awaited alone.
+///
+/// Once one succeeds, `cancel` is called with the outcome to cancel the
+/// query of the other, and returns whether it did: the other is then
+/// awaited, so its connection is not returned to the pool while the cancel
+/// is on its way, and dropped otherwise.
+pub async fn hedged<T, E, P, H, C>(
+    primary: P,
+    hedge: impl FnOnce() -> Option<H>,
+    delay: Duration,
+    cancel: impl FnOnce(HedgeOutcome) -> C,
+) -> (Result<T, E>, HedgeOutcome)
+where
+    P: Future<Output = Result<T, E>>,
+    H: Future<Output = Result<T, E>>,
+    C: Future<Output = bool>,
+{
+    let mut primary = std::pin::pin!(primary);
+    tokio::select! {
+        result = &mut primary => return (result, HedgeOutcome::NotHedged),
+        _ = tokio::time::sleep(delay) => {}
+    }
+
+    let Some(hedge) = hedge() else {
+        return (primary.await, HedgeOutcome::Capped);
+    };
+    let mut hedge = std::pin::pin!(hedge);
+    let (result, outcome) = tokio::select! {
+        result = &mut primary => match result {
+            Ok(_) => (result, HedgeOutcome::PrimaryWon),
+            Err(_) => return (hedge.await, HedgeOutcome::HedgeWon),
+        },
+        result = &mut hedge => match result {
+            Ok(_) => (result, HedgeOutcome::HedgeWon),
+            Err(_) => return (primary.await, HedgeOutcome::PrimaryWon),
+        },
+    };
+    if cancel(outcome).await {
+        if outcome == HedgeOutcome::PrimaryWon {
+            let _ = hedge.await;
+        } else {
+            let _ = primary.await;
+        }
+    }
+    (result, outcome)
+}
+
+/// `operation`, keeping the cancel token of its connection in `cancel`.
+fn cancellable<'a, F, Fut>(
+    cancel: &'a Mutex<Option<CancelToken>>,
+    operation: F,
+) -> impl FnOnce(PooledConnection) -> Fut + 'a
+where
+    F: FnOnce(PooledConnection) -> Fut + 'a,
+{
+    move |conn| {
+        *cancel.lock().unwrap() = Some(conn.cancel_token());
+        operation(conn)
+    }
+}
+
+/// Cancel the query whose token is in `cancel`, if it got a connection.
+async fn cancel_taken(cancel: &Mutex<Option<CancelToken>>) -> bool {
+    let token = cancel.lock().unwrap().take();
+    let Some(token) = token else {
+        return false;
+    };
+    cancel_query(&token).await;
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HedgeSettings {
+    /// How long aggregations wait for the read-only pool before they are
+    /// hedged
+    pub delay: Duration,
+    /// Aggregations hedged on the read-write pool at a time
+    pub concurrency: u32,
+}
+
+/// Where aggregations are hedged, see [`DieselEnergyReadings::with_hedge`].
+struct Hedge {
+    pool: Pool,
+    delay: Duration,
+    permits: Semaphore,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+}
+
+/// Readings in Postgres, see [`EnergyReading`].
+pub struct DieselEnergyReadings {
+    reads: ReadPools,
+    hedge: Option<Hedge>,
+}
+
+impl DieselEnergyReadings {
+    pub fn new(reads: ReadPools) -> Self {
+        Self { reads, hedge: None }
+    }
+
+    /// Hedge aggregations on `pool`, the read-write one, when the read-only
+    /// pool has not answered them within the delay of `settings`, with at
+    /// most its concurrency hedged at a time, counting the outcomes in the
+    /// `hedged_reads` metric.
+    pub fn with_hedge(
+        mut self,
+        pool: Pool,
+        settings: &HedgeSettings,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+    ) -> Self {
+        self.hedge = Some(Hedge {
+            pool,
+            delay: settings.delay,
+            permits: Semaphore::new(settings.concurrency as usize),
+            telemetry,
+        });
+        self
+    }
+}
+
+#[async_trait]
+impl EnergyReadingRepository for DieselEnergyReadings {
+    async fn aggregate(
+        &self,
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        options: AggregateOptions,
+    ) -> RepositoryResult<Vec<AggregatedReading>> {
+        let aggregate = move |mut conn: PooledConnection| async move {
+            EnergyReading::aggregate(
+                tenant,
+                plant,
+                trunc_level,
+                date_from,
+                date_to,
+                options,
+                &mut conn,
+            )
+            .await
+        };
+        let Some(hedge) = &self.hedge else {
+            return self.reads.with_connection(aggregate).await;
+        };
+
+        let primary_cancel = Mutex::new(None);
+        let hedge_cancel = Mutex::new(None);
+        let (result, outcome) = hedged(
+            self.reads
+                .with_connection(cancellable(&primary_cancel, aggregate)),
+            || {
+                let permit = hedge.permits.try_acquire().ok()?;
+                let hedge_cancel = &hedge_cancel;
+                Some(async move {
+                    let result = with_connection(
+                        &hedge.pool,
+                        cancellable(hedge_cancel, aggregate),
+                    )
+                    .await;
+                    drop(permit);
+                    result
+                })
+            },
+            hedge.delay,
+            async |outcome| match outcome {
+                HedgeOutcome::PrimaryWon => cancel_taken(&hedge_cancel).await,
+                _ => cancel_taken(&primary_cancel).await,
+            },
+        )
+        .await;
+        if outcome != HedgeOutcome::NotHedged {
+            tracing::debug!(outcome = outcome.as_str(), "Hedged aggregation");
+        }
+        hedge.telemetry.maybe_use_metrics(|m| {
+            m.record_hedged_read(outcome.as_str());
+        });
+        result
+    }
+
+    async fn estimate(
+        &self,
+        tenant: &str,
+        plant: Option<Uuid>,
+        trunc_level: &str,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+    ) -> RepositoryResult<AggregateEstimate> {
+        self.reads
+            .with_connection(|mut conn| async move {
+                EnergyReading::estimate_aggregate(
+                    tenant,
+                    plant,
+                    trunc_level,
+                    date_from,
+                    date_to,
+                    &mut conn,
+                )
+                .await
+            })
+            .await
+    }
+}
+
+/// Query history in Postgres, see [`QueryHistory`].
+pub struct DieselQueryHistory {
+    pool: Pool,
+    reads: ReadPools,
+}
+
+impl DieselQueryHistory {
+    pub fn new(pool: Pool, reads: ReadPools) -> Self {
+        Self { pool, reads }
+    }
+}
+
+#[async_trait]
+impl QueryHistoryRepository for DieselQueryHistory {
+    async fn create(
+        &self,
+        entry: NewQueryHistory,
+    ) -> RepositoryResult<QueryHistory> {
+        with_connection(&self.pool, |mut conn| async move {
+            QueryHistory::create(entry, &mut conn).await
+        })
+        .await
+    }
+
+    async fn create_many(
+        &self,
+        entries: Vec<NewQueryHistory>,
+    ) -> RepositoryResult<usize> {
+        with_connection(&self.pool, |mut conn| async move {
+            QueryHistory::create_many(&entries, &mut conn).await
+        })
+        .await
+    }
+
+    async fn latest(
+        &self,
+        tenant: &str,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>> {
+        self.reads
+            .with_connection(|mut conn| async move {
+                QueryHistory::get_latest(tenant, limit, &mut conn).await
+            })
+            .await
+    }
+
+    async fn page(
+        &self,
+        tenant: &str,
+        keyset: Keyset,
+        limit: i64,
+    ) -> RepositoryResult<Vec<QueryHistory>> {
+        self.reads
+            .with_connection(|mut conn| async move {
+                QueryHistory::page(tenant, keyset, limit, &mut conn).await
+            })
+            .await
+    }
+
+    async fn most_frequent(
+        &self,
+        tenant: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> RepositoryResult<Vec<FrequentQuery>> {
+        self.reads
+            .with_connection(|mut conn| async move {
+                QueryHistory::most_frequent(tenant, since, limit, &mut conn)
+                    .await
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use chrono::{Duration, FixedOffset, TimeZone};
+    use postgres_models::models::energy_readings::{
+        NewEnergyReading, reading_source,
+    };
+    use proptest::prelude::*;
+    use proptest::test_runner::{TestCaseError, TestRunner};
+
+    use super::memory::InMemoryEnergyReadings;
+    use super::*;
+    use crate::shared::json::Decimal;
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp, reading};
+
+    /// Readings fall in 2024 and 2025, bounds a little around them.
+    const START: i64 = 1_704_067_200;
+    const MINUTES: i64 = 2 * 366 * 24 * 60;
+
+    /// Readings with the bounds of the aggregation.
+    type Case = (
+        Vec<(i64, i64, Option<usize>)>,
+        Option<DateTime<Utc>>,
+        Option<DateTime<Utc>>,
+    );
+
+    /// `minutes` after [`START`], as written in a random UTC offset.
+    fn instant(minutes: i64) -> impl Strategy<Value = DateTime<Utc>> {
+        (-48..=56_i32).prop_map(move |quarters| {
+            let offset = FixedOffset::east_opt(quarters * 900).unwrap();
+            offset
+                .timestamp_opt(START + minutes * 60, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        })
+    }
+
+    /// A bound around the readings, often exactly at one of them.
+    fn bound(minutes: Vec<i64>) -> BoxedStrategy<Option<DateTime<Utc>>> {
+        let around = (-MINUTES / 8..MINUTES * 9 / 8).prop_flat_map(instant);
+        let bound = if minutes.is_empty() {
+            around.boxed()
+        } else {
+            prop_oneof![
+                around,
+                prop::sample::select(minutes).prop_flat_map(instant),
+            ]
+            .boxed()
+        };
+        prop::option::of(bound).boxed()
+    }
+
+    /// Readings as (minute, kWh in tenths of a Wh, plant), unique per plant
+    /// and minute like the table's index, with bounds.
+    fn readings() -> impl Strategy<Value = Case> {
+        prop::collection::btree_map(
+            (0..MINUTES, prop::option::of(0..2_usize)),
+            0..100_000_000_i64,
+            0..40,
+        )
+        .prop_flat_map(|readings| {
+            let readings = readings
+                .into_iter()
+                .map(|((minute, plant), kwh)| (minute, kwh, plant))
+                .collect::<Vec<_>>();
+            let minutes = readings
+                .iter()
+                .map(|(minute, ..)| *minute)
+                .collect::<Vec<_>>();
+            (Just(readings), bound(minutes.clone()), bound(minutes))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_hedged_reads() {
+        let delay = std::time::Duration::from_millis(20);
+        let answer = |after: u64, result: Result<&'static str, ()>| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(after)).await;
+            result
+        };
+        let cancel = async |_| false;
+
+        assert_eq!(
+            hedged(
+                answer(0, Ok("primary")),
+                || Some(answer(0, Ok("hedge"))),
+                delay,
+                cancel
+            )
+            .await,
+            (Ok("primary"), HedgeOutcome::NotHedged)
+        );
+        assert_eq!(
+            hedged(
+                answer(100, Ok("primary")),
+                || Some(answer(0, Ok("hedge"))),
+                delay,
+                cancel
+            )
+            .await,
+            (Ok("hedge"), HedgeOutcome::HedgeWon)
+        );
+        assert_eq!(
+            hedged(
+                answer(30, Ok("primary")),
+                || Some(answer(100, Ok("hedge"))),
+                delay,
+                cancel
+            )
+            .await,
+            (Ok("primary"), HedgeOutcome::PrimaryWon)
+        );
+        // A failed hedge waits for the first attempt
+        assert_eq!(
+            hedged(
+                answer(100, Ok("primary")),
+                || Some(answer(0, Err(()))),
+                delay,
+                cancel
+            )
+            .await,
+            (Ok("primary"), HedgeOutcome::PrimaryWon)
+        );
+        assert_eq!(
+            hedged(
+                answer(30, Ok("primary")),
+                || None::<std::future::Ready<_>>,
+                delay,
+                cancel
+            )
+            .await,
+            (Ok("primary"), HedgeOutcome::Capped)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hedged_reads_cancel_the_loser() {
+        let delay = std::time::Duration::from_millis(20);
+        let finished = std::sync::atomic::AtomicBool::new(false);
+        let loser = async {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            finished.store(true, std::sync::atomic::Ordering::Relaxed);
+            Ok::<_, ()>("primary")
+        };
+        let cancelled = Mutex::new(None);
+
+        // A loser holding a connection is awaited once cancelled
+        let (result, outcome) = hedged(
+            loser,
+            || Some(async { Ok("hedge") }),
+            delay,
+            async |outcome| {
+                *cancelled.lock().unwrap() = Some(outcome);
+                true
+            },
+        )
+        .await;
+        assert_eq!((result, outcome), (Ok("hedge"), HedgeOutcome::HedgeWon));
+        assert_eq!(*cancelled.lock().unwrap(), Some(HedgeOutcome::HedgeWon));
+        assert!(finished.load(std::sync::atomic::Ordering::Relaxed));
+
+        // One without a connection yet is dropped
+        let finished = std::sync::atomic::AtomicBool::new(false);
+        let loser = async {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            finished.store(true, std::sync::atomic::Ordering::Relaxed);
+            Ok::<_, ()>("primary")
+        };
+        let (result, _) = hedged(
+            loser,
+            || Some(async { Ok("hedge") }),
+            delay,
+            async |_| false,
+        )
+        .await;
+        assert_eq!(result, Ok("hedge"));
+        assert!(!finished.load(std::sync::atomic::Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_diesel_aggregate_matches_in_memory() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let Some(app) = runtime.block_on(TestApp::start()) else {
+            return;
+        };
+        let plants = runtime.block_on(async {
+            [
+                app.seed_plant(DEFAULT_TENANT, "North", "1").await.id,
+                app.seed_plant(DEFAULT_TENANT, "South", "2").await.id,
+            ]
+        });
+        let diesel =
+            DieselEnergyReadings::new(ReadPools::new(app.state.pool.clone()));
+
+        let cases = (
+            readings(),
+            prop::sample::select(vec![
+                "hour",
+                "day",
+                "week",
+                "week_sunday",
+                "month",
+                "quarter",
+                "year",
+            ]),
+            prop::option::of(0..2_usize),
+            any::<bool>(),
+            any::<bool>(),
+        );
+        // Failures are reported with their input rather than persisted
+        let config = ProptestConfig {
+            failure_persistence: None,
+            ..ProptestConfig::default()
+        };
+        let result = TestRunner::new(config).run(
+            &cases,
+            |(
+                (readings, date_from, date_to),
+                trunc_level,
+                plant,
+                fill_gaps,
+                cumulative,
+            )| {
+                // A tenant per case keeps cases apart in the one database
+                let tenant = Uuid::new_v4().to_string();
+                let readings = readings
+                    .into_iter()
+                    .map(|(minute, kwh, plant)| NewEnergyReading {
+                        reading_time: Utc.timestamp_opt(START, 0).unwrap()
+                            + Duration::minutes(minute),
+                        quantity_kwh: BigDecimal::new(kwh.into(), 4),
+                        tenant_id: tenant.clone(),
+                        plant_id: plant.map(|i| plants[i]),
+                        quality_code: None,
+                        source: reading_source::FILE.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                let plant = plant.map(|i| plants[i]);
+                let memory = InMemoryEnergyReadings::new(readings.clone());
+                let options = AggregateOptions {
+                    fill_gaps,
+                    cumulative,
+                };
+
+                runtime.block_on(async {
+                    if !readings.is_empty() {
+                        let mut conn = app.state.pool.get().await.unwrap();
+                        EnergyReading::bulk_insert(readings, &mut conn)
+                            .await
+                            .map_err(|e| TestCaseError::fail(e.to_string()))?;
+                    }
+                    let totals = |rows: RepositoryResult<_>| {
+                        rows.map(|rows: Vec<AggregatedReading>| {
+                            rows.into_iter()
+                                .map(|row| {
+                                    (
+                                        row.period,
+                                        row.total_kwh,
+                                        row.reading_count,
+                                        row.feed_count,
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .map_err(|e| TestCaseError::fail(e.to_string()))
+                    };
+                    let expected = totals(
+                        memory
+                            .aggregate(
+                                &tenant,
+                                plant,
+                                trunc_level,
+                                date_from,
+                                date_to,
+                                options,
+                            )
+                            .await,
+                    )?;
+                    let actual = totals(
+                        diesel
+                            .aggregate(
+                                &tenant,
+                                plant,
+                                trunc_level,
+                                date_from,
+                                date_to,
+                                options,
+                            )
+                            .await,
+                    )?;
+                    prop_assert_eq!(actual, expected);
+                    Ok(())
+                })
+            },
+        );
+        if let Err(e) = result {
+            panic!("{e}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_diesel_fills_gaps_like_in_memory() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let at = |day| Utc.with_ymd_and_hms(2025, 3, day, 0, 0, 0).unwrap();
+        let readings = vec![reading(at(2), "1.5000"), reading(at(4), "2.0000")];
+        let memory = InMemoryEnergyReadings::new(readings.clone());
+        let mut conn = app.state.pool.get().await.unwrap();
+        EnergyReading::bulk_insert(readings, &mut conn)
+            .await
+            .unwrap();
+        drop(conn);
+        let diesel =
+            DieselEnergyReadings::new(ReadPools::new(app.state.pool.clone()));
+
+        // Gaps before, between and after the readings, rendered as served;
+        // `Display` writes every zero as `0`
+        for (cumulative, expected) in [
+            (false, ["0.0000", "1.5000", "0.0000", "2.0000", "0.0000"]),
+            (true, ["0.0000", "1.5000", "1.5000", "3.5000", "3.5000"]),
+        ] {
+            let options = AggregateOptions {
+                fill_gaps: true,
+                cumulative,
+            };
+            let totals = |rows: Vec<AggregatedReading>| {
+                rows.into_iter()
+                    .map(|row| {
+                        serde_json::to_value(Decimal::from(row.total_kwh))
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let (from, to) = (Some(at(1)), Some(at(6)));
+            let actual = diesel
+                .aggregate(DEFAULT_TENANT, None, "day", from, to, options)
+                .await
+                .unwrap();
+            let in_memory = memory
+                .aggregate(DEFAULT_TENANT, None, "day", from, to, options)
+                .await
+                .unwrap();
+            assert_eq!(totals(actual), expected, "cumulative: {cumulative}");
+            assert_eq!(totals(in_memory), expected, "cumulative: {cumulative}");
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Shadow comparisons of aggregate queries.
+//!
+//! When a shadow database is configured, the [`ShadowRunner`] replays a
+//! random sample of the aggregations recorded in `query_history` against a
+//! baseline and a candidate [`AggregateBackend`], the read-only pool and the
+//! shadow database out of the box, and compares their rows and latency.
+//! Outcomes are counted in the `shadow_comparisons` metric and latencies in
+//! `shadow_query_seconds`, so a query rewrite or a new replica can be
+//! checked against production traffic before it serves it. Other code paths
+//! plug in through the [`AggregateBackend`] trait.
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use postgres_models::connection::Pool;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, AggregatedReading, EnergyReading,
+};
+use postgres_models::models::query_history::QueryHistory;
+use telemetry::metrics::Telemetry;
+
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+
+#[derive(Debug, Clone)]
+pub struct ShadowSettings {
+    /// Database endpoint the candidate queries run against
+    pub database_endpoint: String,
+    pub interval: Duration,
+    /// Aggregations replayed per run
+    pub sample_size: i32,
+    /// How far back aggregations are sampled from
+    pub lookback: Duration,
+}
+
+/// An aggregation of the query history, replayed as it was requested.
+#[derive(Debug, Clone)]
+pub struct ShadowQuery {
+    pub tenant_id: String,
+    pub trunc_level: &'static str,
+    pub date_from: Option<DateTime<Utc>>,
+    pub date_to: Option<DateTime<Utc>>,
+}
+
+impl ShadowQuery {
+    /// The query of a history entry, `None` for an unknown aggregation
+    /// type.
+    pub fn from_history(entry: QueryHistory) -> Option<Self> {
+        let trunc_level = match entry.aggregation_type.as_str() {
+            "hourly" => "hour",
+            "day_of_month" => "day",
+            "weekly" => "week",
+            "monthly" => "month",
+            "quarterly" => "quarter",
+            "yearly" => "year",
+            _ => return None,
+        };
+        Some(Self {
+            tenant_id: entry.tenant_id,
+            trunc_level,
+            date_from: entry.date_from,
+            date_to: entry.date_to,
+        })
+    }
+}
+
+/// A way of running aggregations.
+#[async_trait]
+pub trait AggregateBackend: Send + Sync {
+    /// Label of the backend in metrics and logs
+    fn name(&self) -> &'static str;
+
+    async fn aggregate(
+        &self,
+        query: &ShadowQuery,
+    ) -> anyhow::Result<Vec<AggregatedReading>>;
+}
+
+/// [`EnergyReading::aggregate`] on a database pool.
+pub struct PoolBackend {
+    name: &'static str,
+    pool: Pool,
+}
+
+impl PoolBackend {
+    pub fn new(name: &'static str, pool: Pool) -> Self {
+        Self { name, pool }
+    }
+}
+
+#[async_trait]
+impl AggregateBackend for PoolBackend {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn aggregate(
+        &self,
+        query: &ShadowQuery,
+    ) -> anyhow::Result<Vec<AggregatedReading>> {
+        let mut conn = self.pool.get().await?;
+        Ok(EnergyReading::aggregate(
+            &query.tenant_id,
+            None,
+            query.trunc_level,
+            query.date_from,
+            query.date_to,
+            AggregateOptions::default(),
+            &mut conn,
+        )
+        .await?)
+    }
+}
+
+/// How the candidate's rows compare with the baseline's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comparison {
+    Match,
+    /// Different numbers of periods
+    RowCount {
+        baseline: usize,
+        candidate: usize,
+    },
+    /// The first period whose start or total differs
+    Row {
+        period: DateTime<Utc>,
+    },
+}
+
+impl Comparison {
+    pub fn outcome(&self) -> &'static str {
+        match self {
+            Comparison::Match => "match",
+            Comparison::RowCount { .. } | Comparison::Row { .. } => "mismatch",
+        }
+    }
+}
+
+/// Compare two aggregations period by period. Totals are compared as
+/// decimals, so `1.50` and `1.5` match.
+pub fn compare(
+    baseline: &[AggregatedReading],
+    candidate: &[AggregatedReading],
+) -> Comparison {
+    if baseline.len() != candidate.len() {
+        return Comparison::RowCount {
+            baseline: baseline.len(),
+            candidate: candidate.len(),
+        };
+    }
+    baseline
+        .iter()
+        .zip(candidate)
+        .find(|(b, c)| b.period != c.period || b.total_kwh != c.total_kwh)
+        .map_or(Comparison::Match, |(b, _)| Comparison::Row {
+            period: b.period,
+        })
+}
+
+/// Background worker that replays sampled aggregations against a baseline
+/// and a candidate backend.
+///
+/// Queries run one at a time, baseline first, so the comparisons add at
+/// most one aggregation at a time to each database.
+pub struct ShadowRunner {
+    history_pool: Pool,
+    baseline: Box<dyn AggregateBackend>,
+    candidate: Box<dyn AggregateBackend>,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    settings: ShadowSettings,
+}
+
+impl ShadowRunner {
+    /// Compare `read_only_pool`, which also supplies the query history,
+    /// with `shadow_pool`.
+    pub fn new(
+        read_only_pool: Pool,
+        shadow_pool: Pool,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        settings: ShadowSettings,
+    ) -> Self {
+        Self::with_backends(
+            read_only_pool.clone(),
+            Box::new(PoolBackend::new("baseline", read_only_pool)),
+            Box::new(PoolBackend::new("candidate", shadow_pool)),
+            telemetry,
+            settings,
+        )
+    }
+
+    pub fn with_backends(
+        history_pool: Pool,
+        baseline: Box<dyn AggregateBackend>,
+        candidate: Box<dyn AggregateBackend>,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        settings: ShadowSettings,
+    ) -> Self {
+        Self {
+            history_pool,
+            baseline,
+            candidate,
+            telemetry,
+            settings,
+        }
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            interval = ?self.settings.interval,
+            sample_size = self.settings.sample_size,
+            baseline = self.baseline.name(),
+            candidate = self.candidate.name(),
+            "Starting shadow runner"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            if let Err(e) = self.run_sample().await {
+                tracing::warn!("Shadow sampling failed: {e:#}");
+            }
+        }
+
+        tracing::info!("Shadow runner stopped");
+    }
+
+    async fn run_sample(&self) -> anyhow::Result<()> {
+        let since = Utc::now() - self.settings.lookback;
+        let entries = {
+            let mut conn = self.history_pool.get().await?;
+            QueryHistory::sample(
+                since,
+                self.settings.sample_size.into(),
+                &mut conn,
+            )
+            .await?
+        };
+
+        for entry in entries {
+            let id = entry.id;
+            let Some(query) = ShadowQuery::from_history(entry) else {
+                continue;
+            };
+            let outcome = match self.replay(&query).await {
+                Ok(comparison) => {
+                    if comparison != Comparison::Match {
+                        tracing::warn!(
+                            query_history_id = %id,
+                            tenant_id = %query.tenant_id,
+                            ?comparison,
+                            "Shadow aggregate mismatch"
+                        );
+                    }
+                    comparison.outcome()
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        query_history_id = %id,
+                        "Shadow aggregate failed: {e:#}"
+                    );
+                    "failed"
+                }
+            };
+            self.telemetry
+                .maybe_use_metrics(|m| m.record_shadow_comparison(outcome));
+        }
+        Ok(())
+    }
+
+    async fn replay(&self, query: &ShadowQuery) -> anyhow::Result<Comparison> {
+        let baseline = self.timed(self.baseline.as_ref(), query).await?;
+        let candidate = self.timed(self.candidate.as_ref(), query).await?;
+        Ok(compare(&baseline, &candidate))
+    }
+
+    async fn timed(
+        &self,
+        backend: &dyn AggregateBackend,
+        query: &ShadowQuery,
+    ) -> anyhow::Result<Vec<AggregatedReading>> {
+        let started = Instant::now();
+        let rows = backend.aggregate(query).await?;
+        let seconds = started.elapsed().as_secs_f64();
+        self.telemetry.maybe_use_metrics(|m| {
+            m.record_shadow_query(backend.name(), seconds)
+        });
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn rows(totals: &[(u32, &str)]) -> Vec<AggregatedReading> {
+        totals
+            .iter()
+            .map(|(month, total)| AggregatedReading {
+                period: Utc.with_ymd_and_hms(2025, *month, 1, 0, 0, 0).unwrap(),
+                total_kwh: total.parse().unwrap(),
+                reading_count: 1,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = rows(&[(1, "100.50"), (2, "200")]);
+        assert_eq!(
+            compare(&baseline, &rows(&[(1, "100.5"), (2, "200.0000")])),
+            Comparison::Match
+        );
+        assert_eq!(
+            compare(&baseline, &rows(&[(1, "100.5"), (2, "199.9")])),
+            Comparison::Row {
+                period: Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap()
+            }
+        );
+        assert_eq!(
+            compare(&baseline, &rows(&[(1, "100.5")])).outcome(),
+            "mismatch"
+        );
+    }
+}
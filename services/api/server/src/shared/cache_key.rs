@@ -0,0 +1,154 @@
+//! Keys of the data cached in Redis.
+//!
+//! A [`CacheKey`] is scoped to a tenant, and names the namespace and the
+//! endpoint of the data, the version of its schema and the parameters it
+//! depends on, sorted by name:
+//!
+//! ```text
+//! tenant:acme:energy:aggregate:v1:from=2025-01-01T00%3A00%3A00+00%3A00:type=monthly
+//! ```
+//!
+//! Values are escaped, so two sets of parameters never share a key, and
+//! bumping the version of an endpoint leaves the entries of the old schema
+//! unread. [`CacheKey::prefix`] selects all the keys of an endpoint, to
+//! delete them with [`response_cache::delete_prefix`].
+//!
+//! [`response_cache::delete_prefix`]: super::response_cache::delete_prefix
+use std::fmt;
+
+use crate::auth::TenantContext;
+
+/// Key of cached data, built a part at a time.
+#[derive(Debug, Clone)]
+pub struct CacheKey {
+    tenant: TenantContext,
+    namespace: &'static str,
+    endpoint: &'static str,
+    version: u32,
+    params: Vec<(&'static str, String)>,
+}
+
+impl CacheKey {
+    /// Key of the tenant's data of `endpoint`, e.g. `aggregate`, in
+    /// `namespace`, e.g. `energy`, at version 1 and without parameters.
+    pub fn new(
+        tenant: &TenantContext,
+        namespace: &'static str,
+        endpoint: &'static str,
+    ) -> Self {
+        debug_assert!(!namespace.contains(':') && !endpoint.contains(':'));
+        Self {
+            tenant: tenant.clone(),
+            namespace,
+            endpoint,
+            version: 1,
+            params: Vec::new(),
+        }
+    }
+
+    /// Version of the schema of the cached data.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Add the parameter `name` with `value`.
+    pub fn param(
+        mut self,
+        name: &'static str,
+        value: impl fmt::Display,
+    ) -> Self {
+        debug_assert!(!name.contains([':', '=']));
+        self.params.push((name, escape(&value.to_string())));
+        self
+    }
+
+    /// Add the parameter `name` with `value`, if any.
+    pub fn opt_param(
+        self,
+        name: &'static str,
+        value: Option<impl fmt::Display>,
+    ) -> Self {
+        match value {
+            Some(value) => self.param(name, value),
+            None => self,
+        }
+    }
+
+    /// Start of the keys of the tenant's data of the endpoint, at any
+    /// version and with any parameters.
+    pub fn prefix(&self) -> String {
+        self.tenant
+            .cache_key(&format!("{}:{}:", self.namespace, self.endpoint))
+    }
+
+    /// The key, with the parameters sorted by name.
+    pub fn build(mut self) -> String {
+        self.params.sort();
+        let mut key = format!("{}v{}", self.prefix(), self.version);
+        for (name, value) in &self.params {
+            key.push_str(&format!(":{name}={value}"));
+        }
+        key
+    }
+}
+
+/// `value` with the separators of keys, `%` and the wildcards of Redis'
+/// patterns percent-encoded.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '%' | ':' | '=' | '*' | '?' | '[' | ']' | '\\' => {
+                escaped.push_str(&format!("%{:02X}", c as u32));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_keys() {
+        let acme = TenantContext::new("acme");
+        let key = CacheKey::new(&acme, "energy", "aggregate")
+            .param("type", "monthly")
+            .opt_param("to", None::<&str>)
+            .param("from", "2025-01-01T00:00:00+00:00");
+        assert_eq!(key.prefix(), "tenant:acme:energy:aggregate:");
+        assert_eq!(
+            key.version(2).build(),
+            "tenant:acme:energy:aggregate:v2\
+             :from=2025-01-01T00%3A00%3A00+00%3A00:type=monthly"
+        );
+    }
+
+    #[test]
+    fn test_keys_do_not_collide() {
+        let acme = TenantContext::new("acme");
+        let key = |params: &[(&'static str, &str)]| {
+            params
+                .iter()
+                .fold(CacheKey::new(&acme, "energy", "aggregate"), |key, p| {
+                    key.param(p.0, p.1)
+                })
+                .build()
+        };
+        assert_eq!(
+            key(&[("a", "1"), ("b", "2")]),
+            key(&[("b", "2"), ("a", "1")])
+        );
+        assert_ne!(key(&[("a", "1:b=2")]), key(&[("a", "1"), ("b", "2")]));
+        assert_ne!(key(&[("a", "%3A")]), key(&[("a", ":")]));
+        assert!(!key(&[("a", "*")]).contains('*'));
+        assert_ne!(
+            CacheKey::new(&acme, "energy", "aggregate").build(),
+            CacheKey::new(&TenantContext::default(), "energy", "aggregate")
+                .build()
+        );
+    }
+}
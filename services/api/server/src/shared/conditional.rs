@@ -0,0 +1,90 @@
+//! Conditional requests (RFC 9110, section 13) on versioned resources.
+//!
+//! The strong ETag of a resource is its quoted version, e.g. `"3"`. Every
+//! write increments the version, so the ETag changes with the
+//! representation, and the ETag of a response can be sent back as
+//! `If-Match` to update or delete the resource.
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+
+/// The ETag of a resource at `version`.
+pub fn etag(version: i32) -> HeaderValue {
+    HeaderValue::try_from(format!("\"{version}\""))
+        .expect("a quoted number is a valid header value")
+}
+
+/// The `ETag` header of a response.
+pub fn etag_header(version: i32) -> [(HeaderName, HeaderValue); 1] {
+    [(header::ETAG, etag(version))]
+}
+
+/// Whether `If-None-Match` matches the resource at `version`, meaning the
+/// client's copy is current. Tags are compared weakly, so `W/"3"` matches.
+pub fn none_match(headers: &HeaderMap, version: i32) -> bool {
+    none_match_tag(headers, &etag(version))
+}
+
+/// Whether `If-None-Match` matches `etag`, compared weakly.
+pub fn none_match_tag(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == *etag)
+}
+
+/// `304 Not Modified` for the resource at `version`.
+pub fn not_modified(version: i32) -> Response {
+    (StatusCode::NOT_MODIFIED, etag_header(version)).into_response()
+}
+
+/// The version named by `If-Match`, `None` when the header is absent. The
+/// value must be a single strong tag; an unquoted version is accepted too.
+/// Otherwise the value is returned as the error.
+pub fn if_match(headers: &HeaderMap) -> Result<Option<i32>, String> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value.to_str().unwrap_or_default().trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .parse()
+        .map(Some)
+        .map_err(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: HeaderName, value: &'static str) -> HeaderMap {
+        HeaderMap::from_iter([(name, HeaderValue::from_static(value))])
+    }
+
+    #[test]
+    fn test_none_match() {
+        assert!(none_match(&headers(header::IF_NONE_MATCH, "\"3\""), 3));
+        assert!(none_match(
+            &headers(header::IF_NONE_MATCH, "\"1\", W/\"3\""),
+            3
+        ));
+        assert!(none_match(&headers(header::IF_NONE_MATCH, "*"), 3));
+        assert!(!none_match(&headers(header::IF_NONE_MATCH, "\"2\""), 3));
+        assert!(!none_match(&HeaderMap::new(), 3));
+    }
+
+    #[test]
+    fn test_if_match() {
+        assert_eq!(if_match(&HeaderMap::new()), Ok(None));
+        assert_eq!(if_match(&headers(header::IF_MATCH, "\"3\"")), Ok(Some(3)));
+        assert_eq!(if_match(&headers(header::IF_MATCH, "3")), Ok(Some(3)));
+        assert_eq!(
+            if_match(&headers(header::IF_MATCH, "W/\"3\"")),
+            Err("W/\"3\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Typed custom request headers.
+//!
+//! A header is described once by implementing [`CustomHeader`], then
+//! extracted with [`Header`], or `Option<Header<_>>` when it may be absent.
+//! A missing or invalid header is rejected with `400 missing_header` or
+//! `400 invalid_header`, naming the header and the values it accepts;
+//! handlers recording their errors take `Result<Header<_>, HeaderRejection>`
+//! and pass the rejection to their recorder with [`HeaderRejection::code`].
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum::http::HeaderMap;
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::IntoWireV1Error;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+/// A request header with a typed value.
+pub trait CustomHeader: Sized {
+    /// Header name, lowercase
+    const NAME: &'static str;
+    /// Values accepted, for errors
+    const EXPECTED: &'static str;
+
+    /// The value of the header, `None` when it is invalid.
+    fn parse(value: &str) -> Option<Self>;
+}
+
+/// A [`CustomHeader`] of the request.
+#[derive(Debug, Clone, Copy)]
+pub struct Header<H>(pub H);
+
+impl<H: CustomHeader> Header<H> {
+    /// The header in `headers`, `None` when absent; the error is the
+    /// invalid value.
+    pub fn parse(headers: &HeaderMap) -> Result<Option<H>, String> {
+        let Some(value) = headers.get(H::NAME) else {
+            return Ok(None);
+        };
+        value
+            .to_str()
+            .ok()
+            .and_then(|value| H::parse(value.trim()))
+            .map(Some)
+            .ok_or_else(|| String::from_utf8_lossy(value.as_bytes()).into())
+    }
+}
+
+/// A missing or invalid [`CustomHeader`].
+#[derive(Debug, thiserror::Error)]
+#[error("{}", self.message())]
+pub struct HeaderRejection {
+    pub header: &'static str,
+    pub expected: &'static str,
+    /// The invalid value, `None` when the header is missing
+    pub value: Option<String>,
+    request_id: Uuid,
+}
+
+impl HeaderRejection {
+    fn new<H: CustomHeader>(value: Option<String>, request_id: Uuid) -> Self {
+        Self {
+            header: H::NAME,
+            expected: H::EXPECTED,
+            value,
+            request_id,
+        }
+    }
+
+    /// Error code of the rejection, for metrics and responses.
+    pub fn code(&self) -> &'static str {
+        match self.value {
+            Some(_) => "invalid_header",
+            None => "missing_header",
+        }
+    }
+
+    fn message(&self) -> String {
+        match &self.value {
+            Some(value) => format!("Invalid {} header: {value}", self.header),
+            None => format!("Missing {} header", self.header),
+        }
+    }
+}
+
+impl IntoWireV1Error for HeaderRejection {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        WireV1Error::bad_request(
+            self.message(),
+            vec![WireV1Detail {
+                field: Some(self.header.to_string()),
+                code: self.code().to_string(),
+                message: self.message(),
+                suggestion: format!(
+                    "Send {} as {}",
+                    self.header, self.expected
+                ),
+                documentation: String::new(),
+            }],
+            request_id.to_string(),
+        )
+    }
+}
+
+impl IntoResponse for HeaderRejection {
+    fn into_response(self) -> Response {
+        let request_id = self.request_id;
+        self.into_wire_v1_error(&request_id).into_response()
+    }
+}
+
+impl<H, S> FromRequestParts<S> for Header<H>
+where
+    H: CustomHeader,
+    S: Send + Sync,
+{
+    type Rejection = HeaderRejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Ok(RequestId(request_id)) =
+            RequestId::from_request_parts(parts, state).await;
+        match Self::parse(&parts.headers) {
+            Ok(Some(value)) => Ok(Self(value)),
+            Ok(None) => Err(HeaderRejection::new::<H>(None, request_id)),
+            Err(value) => {
+                Err(HeaderRejection::new::<H>(Some(value), request_id))
+            }
+        }
+    }
+}
+
+impl<H, S> OptionalFromRequestParts<S> for Header<H>
+where
+    H: CustomHeader,
+    S: Send + Sync,
+{
+    type Rejection = HeaderRejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let Ok(RequestId(request_id)) =
+            RequestId::from_request_parts(parts, state).await;
+        Self::parse(&parts.headers)
+            .map(|value| value.map(Self))
+            .map_err(|value| HeaderRejection::new::<H>(Some(value), request_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    struct Shard(u8);
+
+    impl CustomHeader for Shard {
+        const NAME: &'static str = "x-shard";
+        const EXPECTED: &'static str = "a number from 0 to 255";
+
+        fn parse(value: &str) -> Option<Self> {
+            value.parse().ok().map(Self)
+        }
+    }
+
+    fn parts(shard: Option<&str>) -> Parts {
+        let mut request = Request::builder();
+        if let Some(shard) = shard {
+            request = request.header("x-shard", shard);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    async fn required(shard: Option<&str>) -> Result<u8, HeaderRejection> {
+        let Header(Shard(shard)) =
+            <Header<Shard> as FromRequestParts<()>>::from_request_parts(
+                &mut parts(shard),
+                &(),
+            )
+            .await?;
+        Ok(shard)
+    }
+
+    async fn optional(
+        shard: Option<&str>,
+    ) -> Result<Option<u8>, HeaderRejection> {
+        let shard = <Header<Shard> as OptionalFromRequestParts<()>>::from_request_parts(
+            &mut parts(shard),
+            &(),
+        )
+        .await?;
+        Ok(shard.map(|Header(Shard(shard))| shard))
+    }
+
+    #[tokio::test]
+    async fn test_extracts_typed_headers() {
+        assert_eq!(required(Some(" 7 ")).await.unwrap(), 7);
+        assert_eq!(optional(Some("7")).await.unwrap(), Some(7));
+        assert_eq!(optional(None).await.unwrap(), None);
+
+        let missing = required(None).await.unwrap_err();
+        assert_eq!(missing.code(), "missing_header");
+        assert_eq!(missing.to_string(), "Missing x-shard header");
+
+        let invalid = optional(Some("256")).await.unwrap_err();
+        assert_eq!(invalid.code(), "invalid_header");
+        assert_eq!(invalid.value.as_deref(), Some("256"));
+        let error = invalid.into_wire_v1_error(&Uuid::nil());
+        assert_eq!(error.status_code, axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error.details[0].suggestion,
+            "Send x-shard as a number from 0 to 255"
+        );
+    }
+}
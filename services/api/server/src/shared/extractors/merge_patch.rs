@@ -0,0 +1,107 @@
+//! JSON Merge Patch (RFC 7396) request bodies.
+//!
+//! In a merge patch an absent member leaves a field unchanged and `null`
+//! removes it. Patch structs tell the two apart with
+//! `#[serde(default, deserialize_with = "merge_patch::nullable")]` on
+//! optional fields, and reject `null` for fields that cannot be removed with
+//! `#[serde(default, deserialize_with = "merge_patch::required")]`.
+use axum::extract::{FromRequest, Request};
+use axum::http::{HeaderMap, header};
+use serde::{Deserialize, Deserializer};
+
+use super::payload;
+use super::validations::{Error, ValidatedPayload};
+
+pub const CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// A validated merge patch body, sent as `application/merge-patch+json`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MergePatch<T>(pub T);
+
+impl<T, S> FromRequest<S> for MergePatch<T>
+where
+    T: serde::de::DeserializeOwned + validator::Validate + std::any::Any,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(
+        req: Request,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        if !merge_patch_content_type(req.headers()) {
+            return Err(Error::Payload(
+                payload::Error::UnsupportedContentType(CONTENT_TYPE),
+            ));
+        }
+
+        let ValidatedPayload(value) =
+            ValidatedPayload::<T>::from_request(req, state).await?;
+        Ok(MergePatch(value))
+    }
+}
+
+fn merge_patch_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+        .is_some_and(|mime| mime.essence_str() == CONTENT_TYPE)
+}
+
+/// `None` when the member is absent, `Some(None)` when it is `null`.
+pub fn nullable<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// `None` when the member is absent; `null` is an error.
+pub fn required<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    match Option::<T>::deserialize(deserializer)? {
+        Some(value) => Ok(Some(value)),
+        None => Err(serde::de::Error::custom("cannot be removed")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Patch {
+        #[serde(default, deserialize_with = "nullable")]
+        note: Option<Option<String>>,
+        #[serde(default, deserialize_with = "required")]
+        name: Option<String>,
+    }
+
+    fn patch(json: &str) -> Result<Patch, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn test_null_and_absent() {
+        let empty = patch("{}").unwrap();
+        assert_eq!(empty.note, None);
+        assert_eq!(empty.name, None);
+
+        let cleared = patch(r#"{"note": null, "name": "x"}"#).unwrap();
+        assert_eq!(cleared.note, Some(None));
+        assert_eq!(cleared.name, Some("x".to_string()));
+
+        let set = patch(r#"{"note": "y"}"#).unwrap();
+        assert_eq!(set.note, Some(Some("y".to_string())));
+
+        let error = patch(r#"{"name": null}"#).unwrap_err();
+        assert!(error.to_string().contains("cannot be removed"));
+    }
+}
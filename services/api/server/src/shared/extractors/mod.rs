@@ -1,6 +1,9 @@
 pub mod cache;
 pub mod database;
 pub mod error;
+pub mod header;
+pub mod merge_patch;
+pub mod query;
 pub mod request_id;
 pub mod validations;
 
@@ -45,6 +45,9 @@ pub enum Error {
 
     #[error("missing content-type header")]
     MissingJsonContentType,
+
+    #[error("content-type must be {0}")]
+    UnsupportedContentType(&'static str),
 }
 
 impl axum::response::IntoResponse for Error {
@@ -83,6 +86,12 @@ impl From<Error> for crate::shared::extractors::error::Error {
                     ..Default::default()
                 },
             },
+            Error::UnsupportedContentType(_) => Self {
+                status_code: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                code: "UNSUPPORTED_MEDIA_TYPE",
+                message: format!("{}", value),
+                ..Default::default()
+            },
             _ => Self {
                 status_code: StatusCode::BAD_REQUEST,
                 code: "INVALID_REQUEST",
@@ -0,0 +1,105 @@
+//! Query parameter values beyond what `Query` parses on its own.
+//!
+//! Parameter structs read `?kinds=solar,wind` into a `Vec` of enums, or of
+//! anything else deserialized from a string, with
+//! `#[serde(default, deserialize_with = "query::comma_separated")]`, and
+//! booleans given as `true`/`false`, `1`/`0`, `yes`/`no` or `on`/`off`, a
+//! bare `?open` being true, with
+//! `#[serde(default, deserialize_with = "query::flag")]`. Invalid values
+//! fail the `Query` extractor like any other, naming the parameter, the item
+//! and, for enums, the values accepted.
+use serde::de::{DeserializeOwned, Error, IntoDeserializer, Unexpected};
+use serde::{Deserialize, Deserializer};
+
+/// Comma-separated items, blanks around them and empty ones ignored.
+pub fn comma_separated<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = String::deserialize(deserializer)?;
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            T::deserialize(item.into_deserializer()).map_err(
+                |e: serde::de::value::Error| {
+                    D::Error::custom(format_args!("`{item}`: {e}"))
+                },
+            )
+        })
+        .collect()
+}
+
+/// A boolean, see the module docs.
+pub fn flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    match value.trim().to_ascii_lowercase().as_str() {
+        "" | "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(D::Error::invalid_value(
+            Unexpected::Str(&value),
+            &"true or false",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Query;
+    use axum::http::Uri;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Kind {
+        Solar,
+        Wind,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Params {
+        #[serde(default, deserialize_with = "comma_separated")]
+        kinds: Vec<Kind>,
+        #[serde(default, deserialize_with = "flag")]
+        open: bool,
+    }
+
+    fn parse(query: &str) -> Result<Params, String> {
+        let uri: Uri = format!("/alerts?{query}").parse().unwrap();
+        Query::<Params>::try_from_uri(&uri)
+            .map(|Query(params)| params)
+            .map_err(|e| e.body_text())
+    }
+
+    #[test]
+    fn test_reads_enum_lists() {
+        assert!(parse("").unwrap().kinds.is_empty());
+        assert_eq!(parse("kinds=solar").unwrap().kinds, [Kind::Solar]);
+        assert_eq!(
+            parse("kinds=wind,%20solar,").unwrap().kinds,
+            [Kind::Wind, Kind::Solar]
+        );
+
+        let error = parse("kinds=solar,tidal").unwrap_err();
+        assert!(error.contains("`tidal`"), "{error}");
+        assert!(error.contains("expected `solar` or `wind`"), "{error}");
+    }
+
+    #[test]
+    fn test_reads_flags() {
+        assert!(!parse("").unwrap().open);
+        for value in ["open", "open=true", "open=1", "open=Yes", "open=on"] {
+            assert!(parse(value).unwrap().open, "{value}");
+        }
+        for value in ["open=false", "open=0", "open=no", "open=OFF"] {
+            assert!(!parse(value).unwrap().open, "{value}");
+        }
+        assert!(parse("open=maybe").unwrap_err().contains("true or false"));
+    }
+}
@@ -351,6 +351,24 @@ fn payload_error_to_wire_v1_error(
             }],
             request_id.to_string(),
         ),
+        payload::Error::UnsupportedContentType(content_type) => {
+            WireV1Error::unsupported_media_type(
+                "Unsupported content type".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Content-Type".to_string()),
+                    code: "unsupported_content_type".to_string(),
+                    message: format!(
+                        "Content-Type header must be {content_type}"
+                    ),
+                    suggestion: format!(
+                        "Set Content-Type header to {content_type}"
+                    ),
+                    documentation: "https://doc.com/v1/api-reference"
+                        .to_string(),
+                }],
+                request_id.to_string(),
+            )
+        }
         payload::Error::Bytes(_) => WireV1Error::bad_request(
             "Request body error".to_string(),
             vec![WireV1Detail {
@@ -0,0 +1,346 @@
+//! Serialization of large JSON responses.
+//!
+//! [`decimal_str`] writes decimals as JSON strings without formatting them
+//! to a `String` first, and [`stream_array_field`] sends a response whose
+//! bulk is one array in chunks, so neither the serialized body nor a string
+//! per number is held in memory. [`DecimalFormat`] rounds decimals and
+//! writes them as JSON numbers on request.
+use std::fmt;
+
+use axum::body::{Body, Bytes};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use bigdecimal::num_traits::ToPrimitive;
+use bigdecimal::{BigDecimal, RoundingMode};
+use serde::{Deserialize, Serialize, Serializer};
+use utoipa::ToSchema;
+
+use crate::shared::cache_key::CacheKey;
+
+/// Items serialized per chunk of a streamed array.
+const CHUNK_ITEMS: usize = 512;
+
+/// Largest scale written without `BigDecimal`'s formatting; sums of
+/// `NUMERIC(12, 4)` columns have 4.
+const MAX_FAST_SCALE: i64 = 4;
+
+/// Most decimal places of [`DecimalFormat::decimals`], those of the stored
+/// readings.
+pub const MAX_DECIMALS: u32 = MAX_FAST_SCALE as u32;
+
+/// How decimals are rounded to fewer places.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// To the nearest, halves away from zero
+    #[default]
+    HalfUp,
+    /// To the nearest, halves to the even neighbour
+    HalfEven,
+    /// Toward zero
+    Down,
+    /// Away from zero
+    Up,
+}
+
+impl Rounding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rounding::HalfUp => "half_up",
+            Rounding::HalfEven => "half_even",
+            Rounding::Down => "down",
+            Rounding::Up => "up",
+        }
+    }
+
+    fn mode(self) -> RoundingMode {
+        match self {
+            Rounding::HalfUp => RoundingMode::HalfUp,
+            Rounding::HalfEven => RoundingMode::HalfEven,
+            Rounding::Down => RoundingMode::Down,
+            Rounding::Up => RoundingMode::Up,
+        }
+    }
+}
+
+/// How the decimals of a response are written; the default writes them as
+/// stored, as strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecimalFormat {
+    /// Decimal places, at most [`MAX_DECIMALS`]; `None` for those stored
+    pub decimals: Option<u32>,
+    pub rounding: Rounding,
+    /// Write JSON numbers rather than strings, losing precision past the
+    /// 15 significant digits of an `f64`
+    pub numbers: bool,
+}
+
+impl DecimalFormat {
+    /// `value` rounded to the format's decimal places.
+    pub fn apply(&self, value: BigDecimal) -> Decimal {
+        let value = match self.decimals {
+            Some(decimals) => {
+                value.with_scale_round(decimals.into(), self.rounding.mode())
+            }
+            None => value,
+        };
+        Decimal {
+            value,
+            number: self.numbers,
+        }
+    }
+
+    /// Add the parameters distinguishing cached responses of this format
+    /// to `key`, none for the default one.
+    pub fn key_params(&self, key: CacheKey) -> CacheKey {
+        if *self == Self::default() {
+            return key;
+        }
+        key.param(
+            "decimals",
+            self.decimals
+                .map_or("stored".to_string(), |d| d.to_string()),
+        )
+        .param("rounding", self.rounding.as_str())
+        .param("numbers", self.numbers)
+    }
+}
+
+/// A decimal written as a JSON string like [`decimal_str`], or as a JSON
+/// number with [`DecimalFormat::numbers`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "BigDecimal")]
+pub struct Decimal {
+    pub value: BigDecimal,
+    pub number: bool,
+}
+
+impl From<BigDecimal> for Decimal {
+    fn from(value: BigDecimal) -> Self {
+        Self {
+            value,
+            number: false,
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match self.value.to_f64().filter(|_| self.number) {
+            Some(number) => serializer.serialize_f64(number),
+            None => decimal_str(&self.value, serializer),
+        }
+    }
+}
+
+/// Serialize `value` as a string, e.g. `"12.5000"`, the same as its
+/// `Display`, for `#[serde(serialize_with)]`.
+pub fn decimal_str<S: Serializer>(
+    value: &BigDecimal,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let (digits, scale) = value.as_bigint_and_scale();
+    match digits.to_i128() {
+        Some(mantissa) if (0..=MAX_FAST_SCALE).contains(&scale) => serializer
+            .collect_str(&Fixed {
+                mantissa,
+                scale: scale as u32,
+            }),
+        _ => serializer.collect_str(value),
+    }
+}
+
+/// `mantissa / 10^scale` with `scale` decimal places.
+struct Fixed {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let magnitude = self.mantissa.unsigned_abs();
+        if self.scale == 0 {
+            return write!(f, "{sign}{magnitude}");
+        }
+        let unit = 10_u128.pow(self.scale);
+        write!(
+            f,
+            "{sign}{}.{:0width$}",
+            magnitude / unit,
+            magnitude % unit,
+            width = self.scale as usize
+        )
+    }
+}
+
+/// Respond with `object`, whose `field` is an empty array, with `items` in
+/// that array. The JSON before and after the array is serialized up front,
+/// the items [`CHUNK_ITEMS`] at a time as the body is sent.
+///
+/// `field` must be a top-level field of `object` and no field serialized
+/// before it may contain a `field` key with an empty array.
+pub fn stream_array_field<O, T>(
+    object: &O,
+    field: &str,
+    items: Vec<T>,
+) -> Result<Response, serde_json::Error>
+where
+    O: Serialize,
+    T: Serialize + Send + 'static,
+{
+    let json = serde_json::to_vec(object)?;
+    let marker = format!("\"{field}\":[]");
+    let at = json
+        .windows(marker.len())
+        .position(|window| window == marker.as_bytes())
+        .ok_or_else(|| {
+            serde::ser::Error::custom(format!("no empty `{field}` array"))
+        })?;
+    // Split between the brackets
+    let (head, tail) = json.split_at(at + marker.len() - 1);
+
+    let chunks = ArrayChunks {
+        items: items.into_iter(),
+        first: true,
+    };
+    let body = std::iter::once(Ok(Bytes::copy_from_slice(head)))
+        .chain(chunks)
+        .chain(std::iter::once(Ok(Bytes::copy_from_slice(tail))));
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(tokio_stream::iter(body)),
+    )
+        .into_response())
+}
+
+/// Comma-separated items of an array, serialized a chunk at a time.
+struct ArrayChunks<T> {
+    items: std::vec::IntoIter<T>,
+    first: bool,
+}
+
+impl<T: Serialize> Iterator for ArrayChunks<T> {
+    type Item = Result<Bytes, serde_json::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::new();
+        for item in self.items.by_ref().take(CHUNK_ITEMS) {
+            if !std::mem::take(&mut self.first) || !chunk.is_empty() {
+                chunk.push(b',');
+            }
+            if let Err(e) = serde_json::to_writer(&mut chunk, &item) {
+                return Some(Err(e));
+            }
+        }
+        (!chunk.is_empty()).then(|| Ok(Bytes::from(chunk)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::auth::TenantContext;
+
+    #[derive(Serialize)]
+    struct Kwh(#[serde(serialize_with = "decimal_str")] BigDecimal);
+
+    proptest! {
+        #[test]
+        fn test_decimal_str_like_display(
+            mantissa in any::<i64>(),
+            scale in -2..=6_i64,
+        ) {
+            let value = BigDecimal::new(mantissa.into(), scale);
+            prop_assert_eq!(
+                serde_json::to_value(Kwh(value.clone())).unwrap(),
+                json!(value.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_decimal_format() {
+        let value = |s: &str| s.parse::<BigDecimal>().unwrap();
+        let json = |format: DecimalFormat, s| {
+            serde_json::to_string(&format.apply(value(s))).unwrap()
+        };
+        let rounded = |decimals, rounding| DecimalFormat {
+            decimals: Some(decimals),
+            rounding,
+            numbers: false,
+        };
+
+        assert_eq!(
+            json(DecimalFormat::default(), "216000.0000"),
+            r#""216000.0000""#
+        );
+        assert_eq!(json(rounded(2, Rounding::HalfUp), "2.1250"), r#""2.13""#);
+        assert_eq!(json(rounded(2, Rounding::HalfEven), "2.1250"), r#""2.12""#);
+        assert_eq!(json(rounded(0, Rounding::Down), "-2.9000"), r#""-2""#);
+        assert_eq!(json(rounded(1, Rounding::Up), "2.0100"), r#""2.1""#);
+        let numbers = DecimalFormat {
+            numbers: true,
+            ..rounded(2, Rounding::HalfUp)
+        };
+        assert_eq!(json(numbers, "216000.0000"), "216000.0");
+        assert_eq!(json(numbers, "12.3456"), "12.35");
+
+        let key = || CacheKey::new(&TenantContext::default(), "a", "b");
+        assert_eq!(
+            DecimalFormat::default().key_params(key()).build(),
+            key().build()
+        );
+        assert_eq!(
+            numbers.key_params(key()).build(),
+            key()
+                .param("decimals", 2)
+                .param("numbers", true)
+                .param("rounding", "half_up")
+                .build()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_array_field() {
+        #[derive(Serialize)]
+        struct Page {
+            name: &'static str,
+            items: Vec<u32>,
+            total: u32,
+        }
+
+        for count in [0, 1, CHUNK_ITEMS, CHUNK_ITEMS * 2 + 1] {
+            let items = (0..count as u32).collect::<Vec<_>>();
+            let page = Page {
+                name: "items",
+                items: Vec::new(),
+                total: count as u32,
+            };
+            let response =
+                stream_array_field(&page, "items", items.clone()).unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                json!({"name": "items", "items": items, "total": count})
+            );
+        }
+    }
+}
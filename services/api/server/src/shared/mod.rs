@@ -1,2 +1,7 @@
+pub mod cache_key;
+pub mod conditional;
 pub mod errors;
 pub mod extractors;
+pub mod json;
+pub mod pagination;
+pub mod response_cache;
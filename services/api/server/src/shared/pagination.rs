@@ -0,0 +1,190 @@
+//! Cursor pagination of lists and its `Link` headers.
+//!
+//! Lists ordered by `(time, id)` are walked with opaque cursors: `after`
+//! reads the page following the item of the cursor, `before` the page
+//! preceding it. Responses carry the cursors of the neighbouring pages in
+//! their body, and as RFC 8288 `Link` headers with `rel="next"` and
+//! `rel="prev"`, the request's URI with the cursor swapped, so generic
+//! clients can walk the pages without reading the body.
+use axum::http::{HeaderValue, Uri};
+use chrono::{DateTime, Utc};
+use postgres_models::models::Keyset;
+use uuid::Uuid;
+
+/// Query parameter of the cursor of the next page.
+pub const AFTER: &str = "after";
+/// Query parameter of the cursor of the previous page.
+pub const BEFORE: &str = "before";
+
+/// Cursor of the item at `time` with `id`.
+pub fn encode_cursor(time: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}_{id}", time.timestamp_micros())
+}
+
+fn decode_cursor(cursor: &str) -> Option<(DateTime<Utc>, Uuid)> {
+    let (micros, id) = cursor.split_once('_')?;
+    let time = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+    Some((time, id.parse().ok()?))
+}
+
+/// The page asked for with `after` or `before`; the error describes an
+/// invalid cursor.
+pub fn keyset(
+    after: Option<&str>,
+    before: Option<&str>,
+) -> Result<Keyset, String> {
+    let invalid = |cursor: &str| format!("Invalid cursor `{cursor}`");
+    match (after, before) {
+        (None, None) => Ok(Keyset::First),
+        (Some(after), None) => decode_cursor(after)
+            .map(|(time, id)| Keyset::After(time, id))
+            .ok_or_else(|| invalid(after)),
+        (None, Some(before)) => decode_cursor(before)
+            .map(|(time, id)| Keyset::Before(time, id))
+            .ok_or_else(|| invalid(before)),
+        (Some(_), Some(_)) => {
+            Err(format!("Send `{AFTER}` or `{BEFORE}`, not both"))
+        }
+    }
+}
+
+/// A page of a list and the cursors of its neighbours.
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// The page of `limit` items from `rows`, read from `keyset` in list
+    /// order with a limit of `limit + 1`, the extra row telling whether
+    /// more follow in the direction read.
+    pub fn new(
+        mut rows: Vec<T>,
+        limit: usize,
+        keyset: Keyset,
+        cursor: impl Fn(&T) -> String,
+    ) -> Self {
+        let more = rows.len() > limit;
+        if more {
+            match keyset {
+                // Read backwards, the extra row comes first
+                Keyset::Before(..) => {
+                    rows.drain(..rows.len() - limit);
+                }
+                Keyset::First | Keyset::After(..) => rows.truncate(limit),
+            }
+        }
+        let first = rows.first().map(&cursor);
+        let last = rows.last().map(&cursor);
+        let (next, prev) = match keyset {
+            Keyset::First => (last.filter(|_| more), None),
+            Keyset::After(..) => (last.filter(|_| more), first),
+            Keyset::Before(..) => (last, first.filter(|_| more)),
+        };
+        Self {
+            items: rows,
+            next,
+            prev,
+        }
+    }
+
+    /// `Link` header to the neighbouring pages of a request to `uri`,
+    /// `None` for a single page.
+    pub fn link_header(&self, uri: &Uri) -> Option<HeaderValue> {
+        let links = [("next", AFTER, &self.next), ("prev", BEFORE, &self.prev)]
+            .into_iter()
+            .filter_map(|(rel, param, cursor)| {
+                let cursor = cursor.as_deref()?;
+                Some(format!(
+                    "<{}>; rel=\"{rel}\"",
+                    with_cursor(uri, param, cursor)
+                ))
+            })
+            .collect::<Vec<_>>();
+        if links.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&links.join(", ")).ok()
+    }
+}
+
+/// `uri`'s path and query, with `cursor` as `param` instead of any cursor.
+fn with_cursor(uri: &Uri, param: &str, cursor: &str) -> String {
+    let query =
+        url::form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes())
+            .filter(|(key, _)| key != AFTER && key != BEFORE);
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(query)
+        .append_pair(param, cursor)
+        .finish();
+    format!("{}?{query}", uri.path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(n: &u8) -> String {
+        format!("c{n}")
+    }
+
+    #[test]
+    fn test_round_trips_cursors() {
+        let time =
+            DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        let id = Uuid::new_v4();
+        let cursor = encode_cursor(time, id);
+
+        assert_eq!(keyset(Some(&cursor), None), Ok(Keyset::After(time, id)));
+        assert_eq!(keyset(None, Some(&cursor)), Ok(Keyset::Before(time, id)));
+        assert_eq!(keyset(None, None), Ok(Keyset::First));
+        assert!(keyset(Some(&cursor), Some(&cursor)).is_err());
+        assert!(keyset(Some("nope"), None).is_err());
+        assert!(keyset(None, Some("1_nope")).is_err());
+    }
+
+    #[test]
+    fn test_pages_in_both_directions() {
+        let after = Keyset::After(DateTime::UNIX_EPOCH, Uuid::nil());
+        let before = Keyset::Before(DateTime::UNIX_EPOCH, Uuid::nil());
+
+        let first = Page::new(vec![1, 2, 3], 2, Keyset::First, cursor);
+        assert_eq!(first.items, [1, 2]);
+        assert_eq!((first.next.as_deref(), first.prev), (Some("c2"), None));
+
+        let last = Page::new(vec![3], 2, after, cursor);
+        assert_eq!((last.next, last.prev.as_deref()), (None, Some("c3")));
+
+        let middle = Page::new(vec![1, 2, 3], 2, before, cursor);
+        assert_eq!(middle.items, [2, 3]);
+        assert_eq!(middle.next.as_deref(), Some("c3"));
+        assert_eq!(middle.prev.as_deref(), Some("c2"));
+
+        let newest = Page::new(vec![1, 2], 2, before, cursor);
+        assert_eq!((newest.next.as_deref(), newest.prev), (Some("c2"), None));
+    }
+
+    #[test]
+    fn test_links_neighbouring_pages() {
+        let uri: Uri = "/v1/alerts?open=true&after=c0&limit=2".parse().unwrap();
+        let page = Page {
+            items: vec![1, 2],
+            next: Some("c2".to_string()),
+            prev: Some("c1".to_string()),
+        };
+
+        assert_eq!(
+            page.link_header(&uri).unwrap(),
+            "</v1/alerts?open=true&limit=2&after=c2>; rel=\"next\", \
+             </v1/alerts?open=true&limit=2&before=c1>; rel=\"prev\""
+        );
+        let single = Page::<u8> {
+            items: vec![],
+            next: None,
+            prev: None,
+        };
+        assert!(single.link_header(&uri).is_none());
+    }
+}
@@ -0,0 +1,409 @@
+//! Redis cache of GET responses.
+//!
+//! [`cache_response`] stores the JSON responses of the GET routes it is
+//! layered on, keyed by the tenant, the caller's role and the URL with its
+//! query parameters sorted, for [`CACHE_TTL`]. Each response carries an
+//! `ETag` of its body and `X-Cache: hit` or `miss`; a matching
+//! `If-None-Match` gets `304 Not Modified`.
+//!
+//! Successful writes to a [`CacheGroup`] make the tenant's cached responses
+//! of the group stale at once, through [`invalidate`] on the group's routes:
+//! cache keys include a generation that every write increments. Changes
+//! made outside the group's routes show up within [`CACHE_TTL`].
+//!
+//! Caching and invalidation are skipped, and requests served as usual, when
+//! the `response_cache` flag is off or Redis is unavailable.
+use std::time::Duration;
+
+use axum::body::{Body, HttpBody};
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use deadpool_redis::redis::AsyncCommands;
+use redis_cache::RedisError;
+use redis_cache::connection::Pool;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::AppState;
+use crate::auth::{Role, TenantContext};
+use crate::flags::Flag;
+use crate::shared::cache_key::CacheKey;
+use crate::shared::conditional;
+
+/// How long a cached response is served.
+pub const CACHE_TTL: Duration = Duration::from_secs(30);
+/// Largest body cached; larger responses are sent as they are.
+const MAX_CACHED_BYTES: u64 = 1024 * 1024;
+const CACHE_HEADER: &str = "x-cache";
+/// Keys removed per `DEL` by [`delete_prefix`].
+const DELETE_BATCH: usize = 500;
+
+/// Routes whose cached responses are invalidated together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheGroup {
+    /// `/energy`; aggregations also record the query history
+    Energy,
+    /// `/plants`
+    Plants,
+}
+
+impl CacheGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheGroup::Energy => "energy",
+            CacheGroup::Plants => "plants",
+        }
+    }
+
+    fn generation_key(&self, tenant: &TenantContext) -> String {
+        CacheKey::new(tenant, self.as_str(), "generation").build()
+    }
+}
+
+/// A cached response.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    content_type: String,
+    etag: String,
+    body: String,
+}
+
+impl CachedResponse {
+    fn into_response(self, cache: &'static str) -> Response {
+        (
+            [
+                (header::CONTENT_TYPE, self.content_type),
+                (header::ETAG, self.etag),
+                (HeaderName::from_static(CACHE_HEADER), cache.into()),
+            ],
+            self.body,
+        )
+            .into_response()
+    }
+}
+
+/// Why Redis could not be read or written.
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error(transparent)]
+    Pool(#[from] deadpool_redis::PoolError),
+    #[error(transparent)]
+    Redis(#[from] RedisError),
+}
+
+/// The string stored at `key`, `None` when absent or Redis is unavailable.
+pub async fn get(cache: &Pool, key: &str) -> Option<String> {
+    try_get(cache, key).await.ok().flatten()
+}
+
+/// The string stored at `key`, `None` when absent.
+pub async fn try_get(
+    cache: &Pool,
+    key: &str,
+) -> Result<Option<String>, CacheError> {
+    let mut conn = cache.get().await?;
+    Ok(conn.get(key).await?)
+}
+
+/// Whether a value is stored at `key`.
+pub async fn try_exists(cache: &Pool, key: &str) -> Result<bool, CacheError> {
+    let mut conn = cache.get().await?;
+    Ok(conn.exists(key).await?)
+}
+
+/// Store `value` at `key` for `ttl`, ignoring Redis errors.
+pub async fn set(cache: &Pool, key: &str, value: &str, ttl: Duration) {
+    let _ = try_set(cache, key, value, ttl).await;
+}
+
+/// Store `value` at `key` for `ttl`.
+pub async fn try_set(
+    cache: &Pool,
+    key: &str,
+    value: &str,
+    ttl: Duration,
+) -> Result<(), CacheError> {
+    let mut conn = cache.get().await?;
+    Ok(conn.set_ex(key, value, ttl.as_secs()).await?)
+}
+
+/// Remove `key`, ignoring Redis errors.
+pub async fn delete(cache: &Pool, key: &str) {
+    if let Ok(mut conn) = cache.get().await {
+        let _: Result<(), _> = conn.del(key).await;
+    }
+}
+
+/// Remove the keys starting with `prefix`, like [`CacheKey::prefix`],
+/// ignoring Redis errors.
+pub async fn delete_prefix(cache: &Pool, prefix: &str) {
+    let Ok(mut conn) = cache.get().await else {
+        return;
+    };
+    let pattern = format!("{}*", escape_pattern(prefix));
+    let mut keys = Vec::new();
+    if let Ok(mut iter) = conn.scan_match::<_, String>(pattern).await {
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+    }
+    for chunk in keys.chunks(DELETE_BATCH) {
+        let _: Result<(), _> = conn.del(chunk).await;
+    }
+}
+
+/// `prefix` with the wildcards of Redis' patterns escaped.
+fn escape_pattern(prefix: &str) -> String {
+    let mut escaped = String::with_capacity(prefix.len());
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Path and query of `req` with the query parameters sorted, so their order
+/// does not matter.
+fn normalized_url(req: &Request) -> String {
+    let mut params = url::form_urlencoded::parse(
+        req.uri().query().unwrap_or_default().as_bytes(),
+    )
+    .collect::<Vec<_>>();
+    params.sort();
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    format!("{}?{query}", req.uri().path())
+}
+
+/// Strong ETag of a body, from its SHA-256.
+fn body_etag(body: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(&Sha256::digest(body)[..16]))
+}
+
+/// Serve GET requests from the cache, and cache successful JSON responses.
+pub async fn cache_response(
+    State((state, group)): State<(AppState, CacheGroup)>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let tenant = req.extensions().get::<TenantContext>().cloned();
+    let role = req.extensions().get::<Role>().copied();
+    let (Some(tenant), Some(role)) = (tenant, role) else {
+        return next.run(req).await;
+    };
+    if req.method() != Method::GET
+        || !state.flag_enabled(Flag::ResponseCache).await
+    {
+        return next.run(req).await;
+    }
+
+    let generation = get(&state.cache_pool, &group.generation_key(&tenant))
+        .await
+        .unwrap_or_default();
+    let key = CacheKey::new(&tenant, group.as_str(), "responses")
+        .param("generation", generation)
+        .param("role", role.as_str())
+        .param("url", normalized_url(&req))
+        .build();
+    let headers = req.headers().clone();
+
+    if let Some(cached) = get(&state.cache_pool, &key).await
+        && let Ok(cached) = serde_json::from_str::<CachedResponse>(&cached)
+    {
+        tracing::debug!("Response cache hit for {key}");
+        if let Ok(etag) = HeaderValue::from_str(&cached.etag)
+            && conditional::none_match_tag(&headers, &etag)
+        {
+            return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)])
+                .into_response();
+        }
+        return cached.into_response("hit");
+    }
+
+    let response = next.run(req).await;
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if response.status() != StatusCode::OK
+        || !content_type.starts_with("application/json")
+        || response
+            .body()
+            .size_hint()
+            .exact()
+            .is_none_or(|size| size > MAX_CACHED_BYTES)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to read response to cache: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = body_etag(&bytes);
+    if let Ok(body) = std::str::from_utf8(&bytes) {
+        let cached = CachedResponse {
+            content_type,
+            etag: etag.clone(),
+            body: body.to_string(),
+        };
+        if let Ok(json) = serde_json::to_string(&cached) {
+            set(&state.cache_pool, &key, &json, CACHE_TTL).await;
+        }
+    }
+
+    let etag = HeaderValue::from_str(&etag).expect("hex is a valid header");
+    if conditional::none_match_tag(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)])
+            .into_response();
+    }
+    parts.headers.insert(header::ETAG, etag);
+    parts
+        .headers
+        .insert(CACHE_HEADER, HeaderValue::from_static("miss"));
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Invalidate the tenant's cached responses of `group` after a successful
+/// write.
+pub async fn invalidate(
+    State((state, group)): State<(AppState, CacheGroup)>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let write =
+        !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let tenant = req.extensions().get::<TenantContext>().cloned();
+    let response = next.run(req).await;
+
+    if write
+        && response.status().is_success()
+        && let Some(tenant) = tenant
+        && state.flag_enabled(Flag::ResponseCache).await
+        && let Ok(mut conn) = state.cache_pool.get().await
+    {
+        let _: Result<i64, _> =
+            conn.incr(group.generation_key(&tenant), 1).await;
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::wire_api::testing::TestApp;
+
+    #[test]
+    fn test_normalized_url() {
+        let request = |uri: &str| {
+            Request::builder().uri(uri).body(Body::empty()).unwrap()
+        };
+        assert_eq!(
+            normalized_url(&request("/plants?b=2&a=1&a=0")),
+            "/plants?a=0&a=1&b=2"
+        );
+        assert_eq!(normalized_url(&request("/plants")), "/plants?");
+        assert_eq!(
+            normalized_url(&request("/plants?name=a%20b")),
+            normalized_url(&request("/plants?name=a+b"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deletes_by_prefix() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let cache = &app.state.cache_pool;
+        let tenant = TenantContext::new(format!("t{}", uuid::Uuid::new_v4()));
+        let key = |endpoint, value| {
+            CacheKey::new(&tenant, "energy", endpoint)
+                .param("type", value)
+                .build()
+        };
+        let (hourly, monthly) = (key("aggregate", "h*"), key("aggregate", "m"));
+        let other = key("aggregate_2", "m");
+        for key in [&hourly, &monthly, &other] {
+            set(cache, key, "{}", CACHE_TTL).await;
+        }
+
+        let prefix = CacheKey::new(&tenant, "energy", "aggregate").prefix();
+        delete_prefix(cache, &prefix).await;
+        assert_eq!(get(cache, &hourly).await, None);
+        assert_eq!(get(cache, &monthly).await, None);
+        assert_eq!(get(cache, &other).await.as_deref(), Some("{}"));
+        delete(cache, &other).await;
+    }
+
+    #[test]
+    fn test_escapes_patterns() {
+        assert_eq!(escape_pattern("tenant:a:b:"), "tenant:a:b:");
+        assert_eq!(escape_pattern("a*b?[c]\\"), "a\\*b\\?\\[c\\]\\\\");
+    }
+
+    #[tokio::test]
+    async fn test_caches_until_a_write() {
+        let Some(app) = TestApp::with_vars(&[(
+            "FEATURE_FLAGS",
+            "aggregate_cache=false,response_cache",
+        )])
+        .await
+        else {
+            return;
+        };
+        let create = |name: &'static str| {
+            app.server.post("/api/wire/v1/plants").json(&json!({
+                "name": name,
+                "energyType": "solar",
+                "capacityMw": 1.0,
+            }))
+        };
+        let plants = |response: &axum_test::TestResponse| {
+            response.json::<serde_json::Value>()["plants"]
+                .as_array()
+                .unwrap()
+                .len()
+        };
+        // Also invalidates responses cached by earlier runs
+        create("North field")
+            .await
+            .assert_status(StatusCode::CREATED);
+
+        let first = app.server.get("/api/wire/v1/plants").await;
+        first.assert_status_ok();
+        assert_eq!(first.header(CACHE_HEADER), "miss");
+        let etag = first.header(header::ETAG);
+
+        let second = app.server.get("/api/wire/v1/plants").await;
+        assert_eq!(second.header(CACHE_HEADER), "hit");
+        assert_eq!(second.header(header::ETAG), etag);
+        assert_eq!(second.text(), first.text());
+
+        app.server
+            .get("/api/wire/v1/plants")
+            .add_header(header::IF_NONE_MATCH, etag.clone())
+            .await
+            .assert_status(StatusCode::NOT_MODIFIED);
+
+        create("South field")
+            .await
+            .assert_status(StatusCode::CREATED);
+        let third = app.server.get("/api/wire/v1/plants").await;
+        assert_eq!(third.header(CACHE_HEADER), "miss");
+        assert_ne!(third.header(header::ETAG), etag);
+        assert_eq!(plants(&third), plants(&first) + 1);
+    }
+}
@@ -2,13 +2,18 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::signal;
 use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
 use tokio::time::Duration;
 use tracing::{info, warn};
 
 pub struct ShutdownCoordinator {
     notify: Arc<Notify>,
+    /// Shutdowns asked for by the service itself, see [`Self::request`]
+    requested: Notify,
     shutting_down: AtomicBool,
     inner: Mutex<Option<ShutdownInner>>,
+    /// Tasks that still write to the pools after shutdown begins
+    drains: Mutex<Vec<JoinHandle<()>>>,
 }
 
 struct ShutdownInner {
@@ -23,16 +28,40 @@ impl ShutdownCoordinator {
     ) -> Self {
         Self {
             notify: Arc::new(Notify::new()),
+            requested: Notify::new(),
             shutting_down: AtomicBool::new(false),
             inner: Mutex::new(Some(ShutdownInner {
                 db_pool,
                 redis_pool,
             })),
+            drains: Mutex::new(Vec::new()),
         }
     }
 
+    /// Resolves once shutdown begins, also when it already has.
     pub async fn wait_for_shutdown(&self) {
-        self.notify.notified().await;
+        let notified = self.notify.notified();
+        if self.is_shutting_down() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Wait for `task`, a worker stopping on [`Self::wait_for_shutdown`],
+    /// before closing the pools on shutdown, so it can write what it holds.
+    pub async fn drain_before_close(&self, task: JoinHandle<()>) {
+        self.drains.lock().await.push(task);
+    }
+
+    /// Ask for a graceful shutdown, as a signal would, e.g. for the
+    /// orchestrator to start the service again.
+    pub fn request(&self) {
+        self.requested.notify_one();
+    }
+
+    /// Resolves once a shutdown is requested, see [`Self::request`].
+    pub async fn requested(&self) {
+        self.requested.notified().await;
     }
 
     pub fn is_shutting_down(&self) -> bool {
@@ -54,6 +83,17 @@ impl ShutdownCoordinator {
         self.notify.notify_waiters();
         let shutdown_timeout = Duration::from_secs(10);
 
+        let drains = std::mem::take(&mut *self.drains.lock().await);
+        let drained = tokio::time::timeout(shutdown_timeout, async {
+            for drain in drains {
+                let _ = drain.await;
+            }
+        })
+        .await;
+        if drained.is_err() {
+            warn!("Workers did not finish before the pools close");
+        }
+
         let db_handle = tokio::spawn({
             let pool = inner.db_pool.clone();
             async move {
@@ -0,0 +1,159 @@
+//! Coalescing of identical computations in flight.
+//!
+//! The first caller of [`SingleFlight::run`] for a key computes the value;
+//! callers arriving with the same key while it runs wait for it and share
+//! its result instead of computing their own. Only successes are shared:
+//! when the first caller fails or is cancelled, each waiting caller computes
+//! the value itself, so an error is never handed to a request that did not
+//! cause it. Keys are forgotten once their computation ends, nothing is
+//! cached.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use tokio::sync::watch;
+
+/// How a caller of [`SingleFlight::run`] got its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The caller computed the value
+    Computed,
+    /// The caller shared the value computed for another caller
+    Shared,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Computed => "computed",
+            Outcome::Shared => "shared",
+        }
+    }
+}
+
+/// Computations in flight by key, see the module docs.
+pub struct SingleFlight<T> {
+    in_flight: Mutex<HashMap<String, watch::Receiver<Option<T>>>>,
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The value of `compute`, or that of the computation already in flight
+    /// for `key`.
+    pub async fn run<E, F, Fut>(
+        &self,
+        key: &str,
+        compute: F,
+    ) -> (Result<T, E>, Outcome)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let sender = {
+            let mut in_flight =
+                self.in_flight.lock().expect("single flight lock poisoned");
+            match in_flight.get(key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    in_flight.insert(key.to_string(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+
+        match sender {
+            Ok(sender) => {
+                let _leader = Leader { flight: self, key };
+                let result = compute().await;
+                if let Ok(value) = &result {
+                    sender.send_replace(Some(value.clone()));
+                }
+                (result, Outcome::Computed)
+            }
+            Err(mut receiver) => {
+                if let Ok(value) = receiver.wait_for(Option::is_some).await {
+                    let value = value.clone().expect("waited for a value");
+                    return (Ok(value), Outcome::Shared);
+                }
+                (compute().await, Outcome::Computed)
+            }
+        }
+    }
+}
+
+/// Forgets the key of a computation when it ends, however it ends.
+struct Leader<'a, T> {
+    flight: &'a SingleFlight<T>,
+    key: &'a str,
+}
+
+impl<T> Drop for Leader<'_, T> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.flight.in_flight.lock() {
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shares_the_computation_in_flight() {
+        let flight = SingleFlight::new();
+        let computations = AtomicUsize::new(0);
+        let compute = || async {
+            computations.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, ()>(42)
+        };
+
+        let (first, second, other) = tokio::join!(
+            flight.run("key", compute),
+            flight.run("key", compute),
+            flight.run("other", compute),
+        );
+        assert_eq!(first, (Ok(42), Outcome::Computed));
+        assert_eq!(second, (Ok(42), Outcome::Shared));
+        assert_eq!(other, (Ok(42), Outcome::Computed));
+        assert_eq!(computations.load(Ordering::SeqCst), 2);
+
+        // Nothing is kept once the computations end
+        let (again, outcome) = flight.run("key", compute).await;
+        assert_eq!((again, outcome), (Ok(42), Outcome::Computed));
+        assert!(flight.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_computes_again_when_the_first_caller_fails() {
+        let flight = SingleFlight::new();
+        let failing = || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err::<i32, _>("database down")
+        };
+        let succeeding = || async { Ok::<_, &str>(7) };
+
+        let (first, second) = tokio::join!(
+            flight.run("key", failing),
+            flight.run("key", succeeding),
+        );
+        assert_eq!(first, (Err("database down"), Outcome::Computed));
+        assert_eq!(second, (Ok(7), Outcome::Computed));
+    }
+}
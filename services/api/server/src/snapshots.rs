@@ -0,0 +1,570 @@
+//! Snapshots of a tenant's readings, taken and restored through
+//! `/admin/snapshots`.
+//!
+//! Taking a snapshot reads the tenant's `energy_readings` in one
+//! repeatable-read transaction, so it is consistent while readings keep
+//! arriving, and writes them as Parquet parts of [`PART_READINGS`] readings
+//! under `SNAPSHOT_DIR`, listed with their SHA-256 digests in a
+//! `manifest.json`. Parts and manifest are then kept in the [`Storage`] of
+//! `SNAPSHOT_STORAGE`. Restoring loads a snapshot into the
+//! `energy_readings` table of a scratch schema named after it, never into
+//! the live table, to compare what-if analyses against or to copy readings
+//! back from after a bad import. Both run in the background, their outcome
+//! recorded on the snapshot.
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Decimal128Type, TimestampMicrosecondType};
+use arrow_array::{Array, ArrayRef, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use bigdecimal::BigDecimal;
+use bigdecimal::num_bigint::BigInt;
+use chrono::{DateTime, Utc};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use postgres_models::connection::Pool;
+use postgres_models::models::Keyset;
+use postgres_models::models::dataset_snapshots::{
+    DatasetSnapshot, scratch_schema,
+};
+use postgres_models::models::energy_readings::{EnergyReading, ReadingFilter};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+
+use crate::uploads::Storage;
+use crate::uploads::storage::{self, S3Client};
+use crate::wire_api::core::v1::energy::export::encode::{
+    self, KWH_SCALE, READING_PRECISION, Writer,
+};
+use crate::wire_api::core::v1::energy::export::models::ExportFormat;
+
+/// Readings per Parquet part of a snapshot.
+pub const PART_READINGS: i64 = 100_000;
+/// Readings inserted into a scratch schema per statement.
+const INSERT_READINGS: usize = 10_000;
+/// Version of the manifest layout.
+pub const MANIFEST_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotSettings {
+    /// Where snapshots are written, and kept on disk storage
+    pub dir: PathBuf,
+    pub storage: Storage,
+}
+
+/// Contents of a snapshot, stored next to its parts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u32,
+    pub snapshot_id: Uuid,
+    pub tenant_id: String,
+    pub name: String,
+    pub taken_at: DateTime<Utc>,
+    pub readings: i64,
+    pub parts: Vec<ManifestPart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestPart {
+    /// File name, relative to the manifest
+    pub file: String,
+    pub readings: i64,
+    /// Hex SHA-256 digest of the file
+    pub sha256: String,
+}
+
+/// Whether `name` can name a snapshot: lowercase letters, digits, `-` and
+/// `_`, starting with a letter or digit.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = name.len() <= 100
+        && name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(
+            "expected at most 100 lowercase letters, digits, `-` and `_`"
+                .to_string(),
+        )
+    }
+}
+
+/// Schema of the parts of snapshots: every column of `energy_readings`.
+pub fn part_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("reading_time", encode::timestamp_type(), false),
+        Field::new(
+            "quantity_kwh",
+            DataType::Decimal128(READING_PRECISION, KWH_SCALE),
+            false,
+        ),
+        Field::new("created_at", encode::timestamp_type(), false),
+        Field::new("updated_at", encode::timestamp_type(), false),
+        Field::new("tenant_id", DataType::Utf8, false),
+        Field::new("plant_id", DataType::Utf8, true),
+        Field::new("quality_code", DataType::Utf8, true),
+        Field::new("source", DataType::Utf8, false),
+    ]))
+}
+
+/// `readings` as a Parquet file.
+pub fn encode_part(readings: &[EnergyReading]) -> anyhow::Result<Vec<u8>> {
+    let strings = |values: Vec<Option<String>>| -> ArrayRef {
+        Arc::new(StringArray::from(values))
+    };
+    let batch = RecordBatch::try_new(
+        part_schema(),
+        vec![
+            strings(readings.iter().map(|r| Some(r.id.to_string())).collect()),
+            encode::timestamps(readings.iter().map(|r| r.reading_time)),
+            encode::decimals(
+                readings.iter().map(|r| &r.quantity_kwh),
+                READING_PRECISION,
+            )
+            .map_err(anyhow::Error::msg)?,
+            encode::timestamps(readings.iter().map(|r| r.created_at)),
+            encode::timestamps(readings.iter().map(|r| r.updated_at)),
+            strings(
+                readings.iter().map(|r| Some(r.tenant_id.clone())).collect(),
+            ),
+            strings(
+                readings
+                    .iter()
+                    .map(|r| r.plant_id.map(|plant| plant.to_string()))
+                    .collect(),
+            ),
+            strings(readings.iter().map(|r| r.quality_code.clone()).collect()),
+            strings(readings.iter().map(|r| Some(r.source.clone())).collect()),
+        ],
+    )?;
+    let mut writer = Writer::new(ExportFormat::Parquet, part_schema())
+        .map_err(anyhow::Error::msg)?;
+    writer.write(&batch).map_err(anyhow::Error::msg)?;
+    writer.finish().map_err(anyhow::Error::msg)
+}
+
+/// The readings of a Parquet file of [`encode_part`].
+pub fn decode_part(bytes: Vec<u8>) -> anyhow::Result<Vec<EnergyReading>> {
+    let reader =
+        ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(bytes))?
+            .build()?;
+    let mut readings = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .with_context(|| format!("The part has no `{name}` column"))
+        };
+        let string = |name: &str| {
+            column(name)?
+                .as_string_opt::<i32>()
+                .with_context(|| format!("`{name}` is not a string column"))
+        };
+        let time = |name: &str| {
+            column(name)?
+                .as_primitive_opt::<TimestampMicrosecondType>()
+                .with_context(|| format!("`{name}` is not a timestamp column"))
+        };
+        let ids = string("id")?;
+        let reading_times = time("reading_time")?;
+        let quantities = column("quantity_kwh")?
+            .as_primitive_opt::<Decimal128Type>()
+            .context("`quantity_kwh` is not a decimal column")?;
+        let created = time("created_at")?;
+        let updated = time("updated_at")?;
+        let tenants = string("tenant_id")?;
+        let plants = string("plant_id")?;
+        let quality_codes = string("quality_code")?;
+        let sources = string("source")?;
+
+        let timestamp = |micros: i64| {
+            DateTime::from_timestamp_micros(micros)
+                .with_context(|| format!("{micros} is out of range"))
+        };
+        for row in 0..batch.num_rows() {
+            readings.push(EnergyReading {
+                id: ids.value(row).parse()?,
+                reading_time: timestamp(reading_times.value(row))?,
+                quantity_kwh: BigDecimal::new(
+                    BigInt::from(quantities.value(row)),
+                    KWH_SCALE.into(),
+                ),
+                created_at: timestamp(created.value(row))?,
+                updated_at: timestamp(updated.value(row))?,
+                tenant_id: tenants.value(row).to_string(),
+                plant_id: plants
+                    .is_valid(row)
+                    .then(|| plants.value(row).parse())
+                    .transpose()?,
+                quality_code: quality_codes
+                    .is_valid(row)
+                    .then(|| quality_codes.value(row).to_string()),
+                source: sources.value(row).to_string(),
+            });
+        }
+    }
+    Ok(readings)
+}
+
+/// Location of `file` next to the manifest at `manifest`.
+fn sibling(manifest: &str, file: &str) -> String {
+    match manifest.rsplit_once('/') {
+        Some((dir, _)) => format!("{dir}/{file}"),
+        None => file.to_string(),
+    }
+}
+
+/// Snapshots being taken or restored by this instance.
+pub struct Snapshots {
+    settings: SnapshotSettings,
+    tasks: TaskTracker,
+    s3: S3Client,
+}
+
+impl Snapshots {
+    pub fn new(settings: SnapshotSettings) -> Self {
+        Self {
+            settings,
+            tasks: TaskTracker::new(),
+            s3: S3Client::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &SnapshotSettings {
+        &self.settings
+    }
+
+    /// Where the files of `snapshot` are written.
+    pub fn local_dir(&self, snapshot: &DatasetSnapshot) -> PathBuf {
+        self.settings
+            .dir
+            .join(&snapshot.tenant_id)
+            .join(snapshot.id.to_string())
+    }
+
+    /// Take `snapshot` in the background, recording the outcome on it.
+    /// See [`Self::wait_for_tasks`].
+    pub fn spawn_take(self: Arc<Self>, pool: Pool, snapshot: DatasetSnapshot) {
+        let tasks = self.tasks.clone();
+        tasks.spawn(async move {
+            let taken = self.take(&pool, &snapshot).await;
+            let recorded = async {
+                let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+                match &taken {
+                    Ok((location, readings)) => {
+                        DatasetSnapshot::complete(
+                            snapshot.id,
+                            location,
+                            *readings,
+                            &mut conn,
+                        )
+                        .await
+                    }
+                    Err(e) => {
+                        let error = format!("{e:#}");
+                        tracing::error!(
+                            snapshot = %snapshot.id,
+                            "Snapshot failed: {error}"
+                        );
+                        DatasetSnapshot::fail(snapshot.id, &error, &mut conn)
+                            .await
+                    }
+                }
+                .map(drop)
+                .map_err(|e| e.to_string())
+            }
+            .await;
+            if let Err(e) = recorded {
+                tracing::error!(
+                    snapshot = %snapshot.id,
+                    "Failed to record a snapshot: {e}"
+                );
+            }
+        });
+    }
+
+    /// Restore `snapshot`, claimed with [`DatasetSnapshot::restoring`],
+    /// into its scratch schema in the background, recording the outcome
+    /// on it. See [`Self::wait_for_tasks`].
+    pub fn spawn_restore(
+        self: Arc<Self>,
+        pool: Pool,
+        snapshot: DatasetSnapshot,
+    ) {
+        let tasks = self.tasks.clone();
+        tasks.spawn(async move {
+            let schema = scratch_schema(snapshot.id);
+            let restored = self.restore(&pool, &snapshot, &schema).await;
+            let recorded = async {
+                let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+                let error = restored.as_ref().err().map(|e| format!("{e:#}"));
+                if let Some(error) = &error {
+                    tracing::error!(
+                        snapshot = %snapshot.id,
+                        "Restore failed: {error}"
+                    );
+                    // Leave no partial copy behind
+                    DatasetSnapshot::drop_scratch(&schema, &mut conn)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                DatasetSnapshot::restored(
+                    snapshot.id,
+                    &schema,
+                    error.as_deref(),
+                    &mut conn,
+                )
+                .await
+                .map(drop)
+                .map_err(|e| e.to_string())
+            }
+            .await;
+            if let Err(e) = recorded {
+                tracing::error!(
+                    snapshot = %snapshot.id,
+                    "Failed to record a restore: {e}"
+                );
+            }
+        });
+    }
+
+    /// Stop taking snapshots and restores and wait for those running, e.g.
+    /// on shutdown.
+    pub async fn wait_for_tasks(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+
+    /// Write and keep the parts and manifest of `snapshot`, returning the
+    /// location of the manifest and the readings in it.
+    async fn take(
+        &self,
+        pool: &Pool,
+        snapshot: &DatasetSnapshot,
+    ) -> anyhow::Result<(String, i64)> {
+        let dir = self.local_dir(snapshot);
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let mut conn = pool.get().await?;
+        let parts = conn
+            .build_transaction()
+            .repeatable_read()
+            .read_only()
+            .run(|conn| {
+                let dir = &dir;
+                async move {
+                    let mut parts = Vec::new();
+                    let mut keyset = Keyset::First;
+                    loop {
+                        let page = EnergyReading::page(
+                            &snapshot.tenant_id,
+                            keyset,
+                            ReadingFilter::default(),
+                            PART_READINGS,
+                            conn,
+                        )
+                        .await?;
+                        let Some(last) = page.last() else {
+                            break;
+                        };
+                        keyset = last.cursor();
+
+                        let file = format!("part-{:05}.parquet", parts.len());
+                        let bytes = encode_part(&page)?;
+                        tokio::fs::write(dir.join(&file), &bytes).await?;
+                        parts.push(ManifestPart {
+                            file,
+                            readings: page.len() as i64,
+                            sha256: hex::encode(Sha256::digest(&bytes)),
+                        });
+                    }
+                    Ok::<_, anyhow::Error>(parts)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        drop(conn);
+
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            snapshot_id: snapshot.id,
+            tenant_id: snapshot.tenant_id.clone(),
+            name: snapshot.name.clone(),
+            taken_at: snapshot.created_at,
+            readings: parts.iter().map(|part| part.readings).sum(),
+            parts,
+        };
+        tokio::fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(&manifest)?,
+        )
+        .await?;
+
+        let location = match &self.settings.storage {
+            Storage::Disk => {
+                dir.join(MANIFEST_FILE).to_string_lossy().into_owned()
+            }
+            Storage::S3 { bucket, prefix } => {
+                let key = |file: &str| {
+                    [
+                        prefix.as_str(),
+                        &snapshot.tenant_id,
+                        &snapshot.id.to_string(),
+                        file,
+                    ]
+                    .into_iter()
+                    .filter(|segment| !segment.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("/")
+                };
+                // The manifest goes last, so one found lists stored parts
+                for part in &manifest.parts {
+                    self.s3
+                        .put(bucket, &key(&part.file), &dir.join(&part.file))
+                        .await?;
+                }
+                let location = self
+                    .s3
+                    .put(bucket, &key(MANIFEST_FILE), &dir.join(MANIFEST_FILE))
+                    .await?;
+                if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+                    tracing::warn!(
+                        dir = %dir.display(),
+                        "Failed to remove a stored snapshot: {e}"
+                    );
+                }
+                location
+            }
+        };
+        Ok((location, manifest.readings))
+    }
+
+    /// Load `snapshot` into the `energy_readings` table of `schema`,
+    /// returning the readings restored.
+    async fn restore(
+        &self,
+        pool: &Pool,
+        snapshot: &DatasetSnapshot,
+        schema: &str,
+    ) -> anyhow::Result<usize> {
+        let location = snapshot
+            .location
+            .as_deref()
+            .context("The snapshot has no manifest")?;
+        let manifest: Manifest =
+            serde_json::from_slice(&self.load(location).await?)
+                .context("Invalid snapshot manifest")?;
+        if manifest.snapshot_id != snapshot.id {
+            anyhow::bail!(
+                "The manifest is of snapshot {}",
+                manifest.snapshot_id
+            );
+        }
+
+        let mut conn = pool.get().await?;
+        DatasetSnapshot::create_scratch(schema, &mut conn).await?;
+        let mut restored = 0;
+        for part in &manifest.parts {
+            let bytes = self.load(&sibling(location, &part.file)).await?;
+            if hex::encode(Sha256::digest(&bytes)) != part.sha256 {
+                anyhow::bail!("{} does not match its digest", part.file);
+            }
+            let readings = decode_part(bytes)
+                .with_context(|| format!("Invalid part {}", part.file))?;
+            for chunk in readings.chunks(INSERT_READINGS) {
+                restored +=
+                    DatasetSnapshot::insert_scratch(schema, chunk, &mut conn)
+                        .await?;
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Contents of the file at `location`, on disk or S3.
+    async fn load(&self, location: &str) -> anyhow::Result<Vec<u8>> {
+        match storage::parse_location(location) {
+            Some((bucket, key)) => self.s3.get(bucket, key).await,
+            None => tokio::fs::read(Path::new(location))
+                .await
+                .with_context(|| format!("Failed to read {location}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(time: &str, kwh: &str) -> EnergyReading {
+        let time = time.parse().unwrap();
+        EnergyReading {
+            id: Uuid::new_v4(),
+            reading_time: time,
+            quantity_kwh: kwh.parse().unwrap(),
+            created_at: time,
+            updated_at: time,
+            tenant_id: "acme".to_string(),
+            plant_id: None,
+            quality_code: None,
+            source: "file".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_round_trips_parts() {
+        let mut flagged = reading("2025-01-01T01:00:00Z", "-0.0001");
+        flagged.plant_id = Some(Uuid::new_v4());
+        flagged.quality_code = Some("negative".to_string());
+        flagged.source = "webhook".to_string();
+        let readings =
+            vec![reading("2025-01-01T00:00:00Z", "9000.25"), flagged];
+
+        let decoded = decode_part(encode_part(&readings).unwrap()).unwrap();
+        assert_eq!(decoded.len(), 2);
+        for (decoded, reading) in decoded.iter().zip(&readings) {
+            assert_eq!(decoded.id, reading.id);
+            assert_eq!(decoded.reading_time, reading.reading_time);
+            assert_eq!(decoded.quantity_kwh, reading.quantity_kwh);
+            assert_eq!(decoded.plant_id, reading.plant_id);
+            assert_eq!(decoded.quality_code, reading.quality_code);
+            assert_eq!(decoded.source, reading.source);
+        }
+        assert_eq!(decoded[1].quantity_kwh.to_string(), "-0.0001");
+    }
+
+    #[test]
+    fn test_validates_names() {
+        assert!(validate_name("before-import_2025-06").is_ok());
+        assert!(validate_name("Before").is_err());
+        assert!(validate_name("-before").is_err());
+        assert!(validate_name("../before").is_err());
+        assert!(validate_name("").is_err());
+        assert!(validate_name(&"a".repeat(101)).is_err());
+    }
+
+    #[test]
+    fn test_locates_parts_next_to_manifests() {
+        assert_eq!(
+            sibling("s3://readings/acme/1/manifest.json", "part-00000.parquet"),
+            "s3://readings/acme/1/part-00000.parquet"
+        );
+        assert_eq!(
+            sibling("snapshots/acme/1/manifest.json", "part-00000.parquet"),
+            "snapshots/acme/1/part-00000.parquet"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Waiting for Postgres and Redis at startup.
+//!
+//! Orchestrators often start the service alongside its dependencies, so
+//! connecting to them is retried rather than failing on the first error:
+//! after `STARTUP_RETRY_INITIAL_BACKOFF_MS`, doubling up to
+//! `STARTUP_RETRY_MAX_BACKOFF_SECS`, until `STARTUP_RETRY_MAX_WAIT_SECS`
+//! have passed; a maximum wait of 0 fails on the first error.
+//!
+//! Progress is reported by `GET /startup`, `503` until the service is ready
+//! and `200` once it is, with the attempts and last error of every
+//! dependency. While the service starts, a [`StatusServer`] answers it on
+//! the first TCP listener, over plain HTTP.
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::Json;
+use axum::http::StatusCode;
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetrySettings {
+    /// Wait before the second attempt
+    pub initial_backoff: Duration,
+    /// Longest wait between attempts
+    pub max_backoff: Duration,
+    /// How long a dependency is waited for; zero tries it once
+    pub max_wait: Duration,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyState {
+    Waiting,
+    Ready,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DependencyStatus {
+    pub status: DependencyState,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct StartupReport {
+    pub ready: bool,
+    pub dependencies: BTreeMap<&'static str, DependencyStatus>,
+}
+
+/// Startup progress, see the module docs.
+#[derive(Default)]
+pub struct StartupStatus {
+    ready: AtomicBool,
+    dependencies: Mutex<BTreeMap<&'static str, DependencyStatus>>,
+}
+
+impl StartupStatus {
+    /// Every dependency is connected and the listeners are about to start.
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
+
+    pub fn report(&self) -> StartupReport {
+        StartupReport {
+            ready: self.ready.load(Ordering::Relaxed),
+            dependencies: self.lock().clone(),
+        }
+    }
+
+    fn record(
+        &self,
+        dependency: &'static str,
+        attempts: u32,
+        error: Option<String>,
+    ) {
+        let status = if error.is_some() {
+            DependencyState::Waiting
+        } else {
+            DependencyState::Ready
+        };
+        self.lock().insert(
+            dependency,
+            DependencyStatus {
+                status,
+                attempts,
+                last_error: error,
+            },
+        );
+    }
+
+    fn lock(
+        &self,
+    ) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, DependencyStatus>>
+    {
+        self.dependencies
+            .lock()
+            .expect("startup status lock poisoned")
+    }
+}
+
+/// `GET /startup`
+pub async fn handler(
+    status: Arc<StartupStatus>,
+) -> (StatusCode, Json<StartupReport>) {
+    let report = status.report();
+    let status_code = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status_code, Json(report))
+}
+
+/// Connect to `dependency` with `connect`, retrying failures with backoff
+/// and recording every attempt in `status`.
+pub async fn retry<T, F, Fut>(
+    dependency: &'static str,
+    settings: &RetrySettings,
+    status: &StartupStatus,
+    mut connect: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let started = Instant::now();
+    let mut backoff = settings.initial_backoff;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let e = match connect().await {
+            Ok(connected) => {
+                status.record(dependency, attempts, None);
+                return Ok(connected);
+            }
+            Err(e) => e,
+        };
+        status.record(dependency, attempts, Some(format!("{e:#}")));
+        if started.elapsed() + backoff > settings.max_wait {
+            return Err(e.context(format!(
+                "Gave up on {dependency} after {attempts} attempts"
+            )));
+        }
+        tracing::warn!(
+            dependency,
+            attempts,
+            ?backoff,
+            "Dependency not ready, retrying: {e:#}"
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(settings.max_backoff);
+    }
+}
+
+/// Answers `GET /startup` until the service's own listeners take over.
+pub struct StatusServer {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl StatusServer {
+    pub async fn start(
+        addr: SocketAddr,
+        status: Arc<StartupStatus>,
+    ) -> anyhow::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind to {addr}"))?;
+        tracing::info!(%addr, "Serving startup status");
+        let app = axum::Router::new().route(
+            "/startup",
+            axum::routing::get(move || handler(status.clone())),
+        );
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let result = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = stopped.await;
+                })
+                .await;
+            if let Err(e) = result {
+                tracing::warn!("Startup status server failed: {e}");
+            }
+        });
+        Ok(Self { stop, task })
+    }
+
+    /// Stop serving and release the address for the listeners.
+    pub async fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(max_wait: Duration) -> RetrySettings {
+        RetrySettings {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(20),
+            max_wait,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_connected() {
+        let status = StartupStatus::default();
+        let mut failures = 2;
+        let connected = retry(
+            "postgres_rw",
+            &settings(Duration::from_secs(5)),
+            &status,
+            || {
+                let result = if failures > 0 {
+                    failures -= 1;
+                    Err(anyhow::anyhow!("connection refused"))
+                } else {
+                    Ok(42)
+                };
+                async move { result }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(connected, 42);
+        let report = status.report();
+        assert!(!report.ready);
+        let dependency = &report.dependencies["postgres_rw"];
+        assert_eq!(dependency.status, DependencyState::Ready);
+        assert_eq!(dependency.attempts, 3);
+        assert!(dependency.last_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_the_maximum_wait() {
+        let status = StartupStatus::default();
+        let result =
+            retry("redis", &settings(Duration::ZERO), &status, || async {
+                Err::<(), _>(anyhow::anyhow!("connection refused"))
+            })
+            .await;
+
+        assert!(result.is_err());
+        let dependency = &status.report().dependencies["redis"];
+        assert_eq!(dependency.status, DependencyState::Waiting);
+        assert_eq!(dependency.attempts, 1);
+        assert_eq!(
+            dependency.last_error.as_deref(),
+            Some("connection refused")
+        );
+    }
+}
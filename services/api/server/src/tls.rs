@@ -0,0 +1,83 @@
+//! TLS termination for deployments without Envoy in front of the service.
+//!
+//! The listener serves HTTPS when `TLS_CERT_PATH` and `TLS_KEY_PATH` are set.
+//! With `TLS_CLIENT_CA_PATH` it also requires every client to present a
+//! certificate signed by that CA (mutual TLS).
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use rustls::RootCertStore;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    /// PEM certificate chain, leaf first
+    pub cert_path: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: PathBuf,
+    /// PEM bundle of CAs trusted to sign client certificates
+    pub client_ca_path: Option<PathBuf>,
+}
+
+/// Build the rustls server config for the listener.
+pub fn server_config(
+    settings: &TlsSettings,
+) -> anyhow::Result<rustls::ServerConfig> {
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+
+    let cert_chain = load_certs(&settings.cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(&settings.key_path).with_context(
+        || format!("Failed to read key {}", settings.key_path.display()),
+    )?;
+
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("Failed to select TLS protocol versions")?;
+
+    let builder = match &settings.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots.add(cert).with_context(|| {
+                    format!("Invalid CA certificate in {}", ca_path.display())
+                })?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(
+                Arc::new(roots),
+                provider,
+            )
+            .build()
+            .context("Failed to build client certificate verifier")?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(cert_chain, key)
+        .context("Server certificate does not match its key")?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(config)
+}
+
+fn load_certs(
+    path: &std::path::Path,
+) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| {
+            format!("Failed to read certificates {}", path.display())
+        })?;
+
+    anyhow::ensure!(
+        !certs.is_empty(),
+        "No certificates found in {}",
+        path.display()
+    );
+
+    Ok(certs)
+}
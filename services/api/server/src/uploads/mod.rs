@@ -0,0 +1,336 @@
+//! Readings files uploaded through `/admin/files`.
+//!
+//! An upload is announced with the size and SHA-256 digest of the file,
+//! then sent in chunks, each starting at the bytes received so far; a
+//! client whose connection dropped asks for that offset and carries on.
+//! Chunks are staged under `UPLOAD_DIR`. Once the last one arrives the
+//! file is checked against its digest, kept in the [`Storage`] of
+//! `UPLOAD_STORAGE` and imported in the background like a file of
+//! `wire-api import`, its import run linked to the upload.
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use postgres_models::connection::Pool;
+use postgres_models::models::file_uploads::FileUpload;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+
+use crate::data_loader::{self, SourceTime};
+use crate::quantity_policy::QuantityPolicy;
+
+pub mod storage;
+
+pub use storage::Storage;
+
+/// Largest chunk accepted in one request.
+pub const MAX_CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+/// Staging directory of incomplete uploads, under the upload directory.
+const STAGING_DIR: &str = ".staging";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadSettings {
+    /// Where chunks are staged, and files kept on disk storage
+    pub dir: PathBuf,
+    pub storage: Storage,
+    /// Largest file accepted
+    pub max_bytes: u64,
+}
+
+/// Whether `name` can name an uploaded file: a plain `.xlsx` or `.csv`
+/// file name.
+pub fn validate_file_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.len() > 200
+        || name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || name.chars().any(char::is_control)
+    {
+        return Err("expected a file name without a directory".to_string());
+    }
+    if !data_loader::is_readings_file(Path::new(name)) {
+        return Err("expected an .xlsx or .csv file".to_string());
+    }
+    Ok(())
+}
+
+/// Uploads in progress on this instance.
+pub struct Uploads {
+    settings: UploadSettings,
+    /// Uploads a chunk is being written to
+    writing: Mutex<HashSet<Uuid>>,
+    /// Imports of complete uploads
+    imports: TaskTracker,
+    s3: storage::S3Client,
+}
+
+/// An upload claimed for writing a chunk, released on drop.
+pub struct Claim<'a> {
+    uploads: &'a Uploads,
+    id: Uuid,
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        self.uploads
+            .writing
+            .lock()
+            .expect("uploads lock poisoned")
+            .remove(&self.id);
+    }
+}
+
+impl Uploads {
+    pub fn new(settings: UploadSettings) -> Self {
+        Self {
+            settings,
+            writing: Mutex::default(),
+            imports: TaskTracker::new(),
+            s3: storage::S3Client::default(),
+        }
+    }
+
+    pub fn settings(&self) -> &UploadSettings {
+        &self.settings
+    }
+
+    /// Claim upload `id` for writing a chunk, `None` while another chunk
+    /// of it is written.
+    pub fn claim(&self, id: Uuid) -> Option<Claim<'_>> {
+        let claimed = self
+            .writing
+            .lock()
+            .expect("uploads lock poisoned")
+            .insert(id);
+        // Built only once claimed, its drop releasing the claim
+        if claimed {
+            Some(Claim { uploads: self, id })
+        } else {
+            None
+        }
+    }
+
+    /// Create the staging directory, failing when it cannot be.
+    pub async fn check_staging(&self) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(self.settings.dir.join(STAGING_DIR)).await
+    }
+
+    /// Where the chunks of `upload` are staged.
+    pub fn staged_path(&self, upload: &FileUpload) -> PathBuf {
+        self.settings
+            .dir
+            .join(STAGING_DIR)
+            .join(format!("{}-{}", upload.id, upload.file_name))
+    }
+
+    /// Write `chunk` at `offset` of the staged file of `upload`, dropping
+    /// anything past `offset` left by an interrupted chunk.
+    pub async fn write_chunk(
+        &self,
+        upload: &FileUpload,
+        offset: u64,
+        chunk: &[u8],
+    ) -> std::io::Result<()> {
+        let path = self.staged_path(upload);
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .await?;
+        file.set_len(offset).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.write_all(chunk).await?;
+        file.sync_data().await
+    }
+
+    /// Keep the complete staged file of `upload`, returning its location
+    /// and the local file to import.
+    pub async fn store(
+        &self,
+        upload: &FileUpload,
+    ) -> anyhow::Result<(String, PathBuf)> {
+        let staged = self.staged_path(upload);
+        let name = format!("{}-{}", upload.id, upload.file_name);
+        match &self.settings.storage {
+            Storage::Disk => {
+                let dir = self.settings.dir.join(&upload.tenant_id);
+                tokio::fs::create_dir_all(&dir).await?;
+                let path = dir.join(name);
+                tokio::fs::rename(&staged, &path).await?;
+                Ok((path.to_string_lossy().into_owned(), path))
+            }
+            Storage::S3 { bucket, prefix } => {
+                let key = [prefix.as_str(), &upload.tenant_id, &name]
+                    .into_iter()
+                    .filter(|segment| !segment.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let location = self.s3.put(bucket, &key, &staged).await?;
+                Ok((location, staged))
+            }
+        }
+    }
+
+    /// Import `file`, the stored file of `upload`, in the background,
+    /// recording the outcome on the upload. Staged copies of files stored
+    /// elsewhere are removed afterwards. See [`Self::wait_for_imports`].
+    pub fn spawn_import(
+        self: Arc<Self>,
+        pool: Pool,
+        upload: FileUpload,
+        file: PathBuf,
+        source_time: SourceTime,
+        quantities: QuantityPolicy,
+    ) {
+        let imports = self.imports.clone();
+        imports.spawn(async move {
+            let report = data_loader::import_files(
+                vec![file.clone()],
+                &upload.tenant_id,
+                source_time,
+                quantities,
+                &pool,
+            )
+            .await;
+            let recorded = async {
+                let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+                let import = report
+                    .as_ref()
+                    .ok()
+                    .and_then(|report| report.files.first());
+                match import {
+                    Some(import) => FileUpload::imported(
+                        upload.id,
+                        import.run_id,
+                        import.result.as_ref().err().map(String::as_str),
+                        &mut conn,
+                    )
+                    .await
+                    .map(drop),
+                    None => {
+                        let error = match &report {
+                            Err(e) => format!("{e:#}"),
+                            Ok(_) => "The file was not imported".to_string(),
+                        };
+                        tracing::error!(
+                            upload = %upload.id,
+                            "Import failed: {error}"
+                        );
+                        FileUpload::fail(upload.id, &error, &mut conn)
+                            .await
+                            .map(drop)
+                    }
+                }
+                .map_err(|e| e.to_string())
+            }
+            .await;
+            if let Err(e) = recorded {
+                tracing::error!(
+                    upload = %upload.id,
+                    "Failed to record the import of an upload: {e}"
+                );
+            }
+
+            if self.settings.storage != Storage::Disk
+                && let Err(e) = tokio::fs::remove_file(&file).await
+            {
+                tracing::warn!(
+                    file = %file.display(),
+                    "Failed to remove a staged upload: {e}"
+                );
+            }
+        });
+    }
+
+    /// Stop taking imports and wait for those running, e.g. on shutdown.
+    pub async fn wait_for_imports(&self) {
+        self.imports.close();
+        self.imports.wait().await;
+    }
+}
+
+/// Hex SHA-256 digest of the file at `path`.
+pub async fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(hex::encode(hasher.finalize()));
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validates_file_names() {
+        assert!(validate_file_name("readings-2025-01.xlsx").is_ok());
+        assert!(validate_file_name("Readings January.CSV").is_ok());
+        assert!(validate_file_name("readings.xls").is_err());
+        assert!(validate_file_name("../readings.xlsx").is_err());
+        assert!(validate_file_name(".readings.csv").is_err());
+        assert!(validate_file_name("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resumes_staged_chunks() {
+        let dir = std::env::temp_dir()
+            .join(format!("wire-uploads-{}", Uuid::new_v4().simple()));
+        let uploads = Uploads::new(UploadSettings {
+            dir: dir.clone(),
+            storage: Storage::Disk,
+            max_bytes: 1024,
+        });
+        let now = chrono::Utc::now();
+        let upload = FileUpload {
+            id: Uuid::new_v4(),
+            tenant_id: "acme".to_string(),
+            file_name: "readings.csv".to_string(),
+            size: 11,
+            sha256: hex::encode(Sha256::digest(b"hello world")),
+            received: 0,
+            status: "uploading".to_string(),
+            location: None,
+            import_run_id: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let claim = uploads.claim(upload.id).unwrap();
+        assert!(uploads.claim(upload.id).is_none());
+        drop(claim);
+        assert!(uploads.claim(upload.id).is_some());
+
+        uploads.write_chunk(&upload, 0, b"hello").await.unwrap();
+        // An interrupted chunk left bytes that were never acknowledged
+        uploads.write_chunk(&upload, 5, b" wor??").await.unwrap();
+        uploads.write_chunk(&upload, 5, b" world").await.unwrap();
+        let staged = uploads.staged_path(&upload);
+        assert_eq!(sha256_file(&staged).await.unwrap(), upload.sha256);
+
+        let (location, file) = uploads.store(&upload).await.unwrap();
+        assert_eq!(
+            file,
+            dir.join("acme").join(format!("{}-readings.csv", upload.id))
+        );
+        assert_eq!(location, file.to_string_lossy());
+        assert!(!staged.exists());
+        assert_eq!(std::fs::read(&file).unwrap(), b"hello world");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+//! Where complete uploads are kept.
+//!
+//! On disk they are moved out of the staging directory; on S3 they are
+//! sent as a multipart upload of [`PART_BYTES`] parts, signed with the
+//! credentials and region of the default AWS configuration chain.
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{
+    PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest,
+    SigningSettings, UriPathNormalizationMode, sign,
+};
+use aws_sigv4::sign::v4;
+use reqwest::Method;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use tokio::sync::OnceCell;
+use url::Url;
+
+/// Size of the parts of S3 uploads but the last, above S3's 5 MiB minimum.
+pub const PART_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Storage {
+    /// The upload directory
+    #[default]
+    Disk,
+    /// Objects under `prefix` of an S3 bucket
+    S3 { bucket: String, prefix: String },
+}
+
+impl FromStr for Storage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "disk" {
+            return Ok(Storage::Disk);
+        }
+        let Some(location) = s.strip_prefix("s3://") else {
+            return Err(format!(
+                "expected `disk` or `s3://bucket/prefix`, got `{s}`"
+            ));
+        };
+        let (bucket, prefix) =
+            location.split_once('/').unwrap_or((location, ""));
+        if bucket.is_empty() {
+            return Err(format!("`{s}` names no bucket"));
+        }
+        Ok(Storage::S3 {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+}
+
+/// Client of the S3 API, loading the AWS configuration on first use.
+#[derive(Default)]
+pub struct S3Client {
+    http: reqwest::Client,
+    aws: OnceCell<aws_config::SdkConfig>,
+}
+
+/// Signs requests with one set of credentials.
+struct Signer {
+    identity: aws_smithy_runtime_api::client::identity::Identity,
+    region: String,
+}
+
+impl S3Client {
+    /// Upload the file at `path` as `key` of `bucket`, returning its
+    /// `s3://` location.
+    pub async fn put(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+    ) -> anyhow::Result<String> {
+        let signer = self.signer().await?;
+        let url = object_url(bucket, key, &signer.region)?;
+
+        let created = self
+            .send(&signer, Method::POST, &url, "uploads", Vec::new())
+            .await?
+            .text()
+            .await?;
+        let upload_id = xml_element(&created, "UploadId")
+            .context("S3 returned no multipart upload id")?
+            .to_string();
+
+        let parts = self.put_parts(&signer, &url, &upload_id, path).await;
+        let completed = match parts {
+            Ok(etags) => self.complete(&signer, &url, &upload_id, &etags).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = completed {
+            let query = format!("uploadId={upload_id}");
+            if let Err(abort) = self
+                .send(&signer, Method::DELETE, &url, &query, Vec::new())
+                .await
+            {
+                tracing::warn!(
+                    key,
+                    "Failed to abort S3 multipart upload: {abort:#}"
+                );
+            }
+            return Err(e);
+        }
+        Ok(format!("s3://{bucket}/{key}"))
+    }
+
+    /// Download `key` of `bucket`.
+    pub async fn get(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> anyhow::Result<Vec<u8>> {
+        let signer = self.signer().await?;
+        let url = object_url(bucket, key, &signer.region)?;
+        let response = self
+            .send(&signer, Method::GET, &url, "", Vec::new())
+            .await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Send the parts of the file, returning their ETags.
+    async fn put_parts(
+        &self,
+        signer: &Signer,
+        url: &Url,
+        upload_id: &str,
+        path: &Path,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut etags = Vec::new();
+        loop {
+            let mut part = Vec::with_capacity(PART_BYTES);
+            (&mut file)
+                .take(PART_BYTES as u64)
+                .read_to_end(&mut part)
+                .await?;
+            if part.is_empty() && !etags.is_empty() {
+                return Ok(etags);
+            }
+            let query =
+                format!("partNumber={}&uploadId={upload_id}", etags.len() + 1);
+            let response =
+                self.send(signer, Method::PUT, url, &query, part).await?;
+            let etag = response
+                .headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .context("S3 returned no ETag for a part")?;
+            etags.push(etag.to_string());
+        }
+    }
+
+    async fn complete(
+        &self,
+        signer: &Signer,
+        url: &Url,
+        upload_id: &str,
+        etags: &[String],
+    ) -> anyhow::Result<()> {
+        let parts = etags
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{etag}</ETag></Part>",
+                    i + 1
+                )
+            })
+            .collect::<String>();
+        let body = format!(
+            "<CompleteMultipartUpload>{parts}</CompleteMultipartUpload>"
+        );
+        let query = format!("uploadId={upload_id}");
+        let response = self
+            .send(signer, Method::POST, url, &query, body.into_bytes())
+            .await?
+            .text()
+            .await?;
+        // Completion can fail after S3 answered 200
+        if let Some(error) = xml_element(&response, "Message") {
+            anyhow::bail!("S3 failed to complete the upload: {error}");
+        }
+        Ok(())
+    }
+
+    async fn signer(&self) -> anyhow::Result<Signer> {
+        let aws = self
+            .aws
+            .get_or_init(|| {
+                aws_config::load_defaults(aws_config::BehaviorVersion::latest())
+            })
+            .await;
+        let credentials = aws
+            .credentials_provider()
+            .context("No AWS credentials configured")?
+            .provide_credentials()
+            .await
+            .context("Failed to load AWS credentials")?;
+        let region = aws.region().context("No AWS region configured")?;
+        Ok(Signer {
+            identity: credentials.into(),
+            region: region.to_string(),
+        })
+    }
+
+    /// Send a signed request to `url` with `query`, failing unless S3
+    /// answers with a success.
+    async fn send(
+        &self,
+        signer: &Signer,
+        method: Method,
+        url: &Url,
+        query: &str,
+        body: Vec<u8>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let mut url = url.clone();
+        url.set_query(Some(query).filter(|query| !query.is_empty()));
+
+        let mut settings = SigningSettings::default();
+        settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+        settings.percent_encoding_mode = PercentEncodingMode::Single;
+        settings.uri_path_normalization_mode =
+            UriPathNormalizationMode::Disabled;
+        let params = v4::SigningParams::builder()
+            .identity(&signer.identity)
+            .region(&signer.region)
+            .name("s3")
+            .time(SystemTime::now())
+            .settings(settings)
+            .build()?
+            .into();
+        let request = SignableRequest::new(
+            method.as_str(),
+            url.as_str(),
+            std::iter::empty(),
+            SignableBody::Precomputed(hex::encode(Sha256::digest(&body))),
+        )?;
+        let (instructions, _) = sign(request, &params)?.into_parts();
+
+        let mut request = self.http.request(method, url.as_str()).body(body);
+        for (name, value) in instructions.headers() {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let message = xml_element(&body, "Message").unwrap_or(&body);
+            anyhow::bail!("S3 answered {status}: {message}");
+        }
+        Ok(response)
+    }
+}
+
+/// URL of `key` of `bucket` in `region`.
+fn object_url(bucket: &str, key: &str, region: &str) -> anyhow::Result<Url> {
+    let mut url =
+        Url::parse(&format!("https://{bucket}.s3.{region}.amazonaws.com"))
+            .context("Invalid S3 bucket name")?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("Invalid S3 bucket name"))?
+        .extend(key.split('/'));
+    Ok(url)
+}
+
+/// Bucket and key of an `s3://bucket/key` location, `None` for other
+/// locations.
+pub fn parse_location(location: &str) -> Option<(&str, &str)> {
+    location.strip_prefix("s3://")?.split_once('/')
+}
+
+/// Text of the first `<name>` element of an S3 response.
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{name}>"))? + name.len() + 2;
+    let end = xml[start..].find(&format!("</{name}>"))?;
+    Some(&xml[start..start + end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_storage() {
+        assert_eq!("disk".parse(), Ok(Storage::Disk));
+        assert_eq!(
+            "s3://readings/uploads/".parse(),
+            Ok(Storage::S3 {
+                bucket: "readings".to_string(),
+                prefix: "uploads".to_string(),
+            })
+        );
+        assert_eq!(
+            "s3://readings".parse(),
+            Ok(Storage::S3 {
+                bucket: "readings".to_string(),
+                prefix: String::new(),
+            })
+        );
+        assert!("s3://".parse::<Storage>().is_err());
+        assert!("/var/uploads".parse::<Storage>().is_err());
+    }
+
+    #[test]
+    fn test_parses_locations() {
+        assert_eq!(
+            parse_location("s3://readings/snapshots/manifest.json"),
+            Some(("readings", "snapshots/manifest.json"))
+        );
+        assert_eq!(parse_location("snapshots/manifest.json"), None);
+    }
+
+    #[test]
+    fn test_reads_xml_elements() {
+        let xml = "<InitiateMultipartUploadResult><Bucket>b</Bucket>\
+                   <UploadId>abc-123</UploadId></InitiateMultipartUploadResult>";
+        assert_eq!(xml_element(xml, "UploadId"), Some("abc-123"));
+        assert_eq!(xml_element(xml, "Message"), None);
+    }
+}
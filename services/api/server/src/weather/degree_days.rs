@@ -0,0 +1,163 @@
+//! Degree days and the regression baseline normalizing energy by them.
+
+/// Heating and cooling degree days of a day.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DegreeDays {
+    /// How far the mean temperature was below the heating base, in °C
+    pub heating: f64,
+    /// How far the mean temperature was above the cooling base, in °C
+    pub cooling: f64,
+}
+
+impl DegreeDays {
+    /// Degree days of a day with the given mean temperature.
+    pub fn of(
+        mean_temperature_c: f64,
+        heating_base_c: f64,
+        cooling_base_c: f64,
+    ) -> Self {
+        Self {
+            heating: (heating_base_c - mean_temperature_c).max(0.0),
+            cooling: (mean_temperature_c - cooling_base_c).max(0.0),
+        }
+    }
+}
+
+/// Daily energy as a linear function of the day's degree days,
+/// `energy = intercept + heating_slope × HDD + cooling_slope × CDD`, fitted
+/// by least squares over the days of a baseline period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baseline {
+    pub intercept: f64,
+    pub heating_slope: f64,
+    pub cooling_slope: f64,
+    /// Share of the variance of the daily energy the fit explains, `None`
+    /// when the energy is constant
+    pub r_squared: Option<f64>,
+    /// Days the baseline was fitted over
+    pub days: usize,
+    /// Average degree days of the baseline days, the "normal" weather
+    pub mean: DegreeDays,
+}
+
+impl Baseline {
+    /// Fit a baseline to days of degree days and energy, `None` without
+    /// any day. A kind of degree day that is the same on every day, e.g.
+    /// cooling in winter, gets a slope of 0.
+    pub fn fit(days: &[(DegreeDays, f64)]) -> Option<Self> {
+        if days.is_empty() {
+            return None;
+        }
+
+        let n = days.len() as f64;
+        let mean = DegreeDays {
+            heating: days.iter().map(|(dd, _)| dd.heating).sum::<f64>() / n,
+            cooling: days.iter().map(|(dd, _)| dd.cooling).sum::<f64>() / n,
+        };
+        let mean_energy = days.iter().map(|(_, kwh)| kwh).sum::<f64>() / n;
+
+        // Sums of squares and products of the centered values
+        let (mut shh, mut scc, mut shc, mut shy, mut scy, mut syy) =
+            (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        for (dd, kwh) in days {
+            let h = dd.heating - mean.heating;
+            let c = dd.cooling - mean.cooling;
+            let y = kwh - mean_energy;
+            shh += h * h;
+            scc += c * c;
+            shc += h * c;
+            shy += h * y;
+            scy += c * y;
+            syy += y * y;
+        }
+
+        let determinant = shh * scc - shc * shc;
+        let (heating_slope, cooling_slope) = if determinant > f64::EPSILON {
+            (
+                (shy * scc - scy * shc) / determinant,
+                (scy * shh - shy * shc) / determinant,
+            )
+        } else if shh > f64::EPSILON {
+            (shy / shh, 0.0)
+        } else if scc > f64::EPSILON {
+            (0.0, scy / scc)
+        } else {
+            (0.0, 0.0)
+        };
+        let intercept = mean_energy
+            - heating_slope * mean.heating
+            - cooling_slope * mean.cooling;
+
+        // The explained sum of squares of a least squares fit
+        let explained = heating_slope * shy + cooling_slope * scy;
+        let r_squared = (syy > f64::EPSILON).then(|| explained / syy);
+
+        Some(Self {
+            intercept,
+            heating_slope,
+            cooling_slope,
+            r_squared,
+            days: days.len(),
+            mean,
+        })
+    }
+
+    /// The energy of a day under the baseline's average weather: the
+    /// actual energy, less what its degree days add over the average.
+    pub fn normalize(&self, degree_days: DegreeDays, energy: f64) -> f64 {
+        energy
+            - self.heating_slope * (degree_days.heating - self.mean.heating)
+            - self.cooling_slope * (degree_days.cooling - self.mean.cooling)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degree_days() {
+        assert_eq!(
+            DegreeDays::of(5.5, 15.5, 22.0),
+            DegreeDays {
+                heating: 10.0,
+                cooling: 0.0
+            }
+        );
+        assert_eq!(DegreeDays::of(25.0, 15.5, 22.0).cooling, 3.0);
+        assert_eq!(DegreeDays::of(18.0, 15.5, 22.0), DegreeDays::default());
+    }
+
+    #[test]
+    fn test_baseline_fit_and_normalize() {
+        // 100 kWh a day plus 5 per heating and 8 per cooling degree day
+        let days =
+            [(10.0, 0.0), (4.0, 0.0), (0.0, 0.0), (0.0, 3.0), (1.0, 1.0)].map(
+                |(heating, cooling)| {
+                    let dd = DegreeDays { heating, cooling };
+                    (dd, 100.0 + 5.0 * heating + 8.0 * cooling)
+                },
+            );
+
+        let baseline = Baseline::fit(&days).unwrap();
+        assert!((baseline.intercept - 100.0).abs() < 1e-9);
+        assert!((baseline.heating_slope - 5.0).abs() < 1e-9);
+        assert!((baseline.cooling_slope - 8.0).abs() < 1e-9);
+        assert!((baseline.r_squared.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(baseline.days, 5);
+
+        // A cold day normalized to the average 3 HDD and 0.8 CDD
+        let cold = DegreeDays {
+            heating: 13.0,
+            cooling: 0.0,
+        };
+        assert!((baseline.normalize(cold, 165.0) - 121.4).abs() < 1e-9);
+
+        // Without cooling days the cooling slope is 0
+        let winter = &days[..3];
+        let baseline = Baseline::fit(winter).unwrap();
+        assert_eq!(baseline.cooling_slope, 0.0);
+        assert!((baseline.heating_slope - 5.0).abs() < 1e-9);
+        assert!(Baseline::fit(&[]).is_none());
+    }
+}
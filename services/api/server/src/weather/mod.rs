@@ -0,0 +1,242 @@
+//! Weather at the tenants' plants.
+//!
+//! When a weather API is configured, the [`WeatherImporter`] fetches the
+//! hourly temperature, irradiance and wind speed of every plant with
+//! coordinates from a [`WeatherProvider`], from a few days back to a few
+//! days ahead, and stores them as `weather_observations`. Each import
+//! overwrites the hours it covers, so forecasts are replaced by what was
+//! observed. `POST /energy/aggregate?include_weather=true` puts the weather
+//! next to the readings of each period, and `POST /energy/normalized`
+//! adjusts the readings for the [`degree_days`] of the weather.
+pub mod degree_days;
+pub mod open_meteo;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use postgres_models::models::plants::Plant;
+use postgres_models::models::weather::{
+    NewWeatherObservation, WeatherObservation,
+};
+use telemetry::metrics::Telemetry;
+use url::Url;
+
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+
+/// Smallest number of periods a correlation is computed over.
+const MIN_CORRELATION_PERIODS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct ImporterSettings {
+    /// Endpoint of the Open-Meteo compatible forecast API
+    pub api_url: Url,
+    pub poll_interval: Duration,
+    pub request_timeout: Duration,
+    /// Days before today to import
+    pub past_days: u32,
+    /// Days after today to import
+    pub forecast_days: u32,
+}
+
+/// The weather at a location in one hour.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeatherSample {
+    /// Start of the hour
+    pub observed_at: DateTime<Utc>,
+    pub temperature_c: Option<f64>,
+    pub irradiance_w_m2: Option<f64>,
+    pub wind_speed_m_s: Option<f64>,
+}
+
+/// A source of hourly weather.
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    /// Recorded as the source of the observations
+    fn name(&self) -> &'static str;
+
+    /// The hourly weather at a location on the UTC days from `start` to
+    /// `end`, both included, observed or forecast.
+    async fn hourly(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> anyhow::Result<Vec<WeatherSample>>;
+}
+
+/// Pearson correlation of the pairs of values, `None` with fewer than
+/// three pairs or when either side is constant.
+pub fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < MIN_CORRELATION_PERIODS {
+        return None;
+    }
+
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+/// Background worker that imports the weather of every plant with
+/// coordinates.
+///
+/// A plant whose import fails is retried on the next poll; the others are
+/// imported regardless.
+pub struct WeatherImporter {
+    pool: postgres_models::connection::Pool,
+    provider: Box<dyn WeatherProvider>,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    settings: ImporterSettings,
+}
+
+impl WeatherImporter {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        settings: ImporterSettings,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(settings.request_timeout)
+            .build()?;
+        let provider =
+            open_meteo::OpenMeteo::new(client, settings.api_url.clone());
+
+        Ok(Self {
+            pool,
+            provider: Box::new(provider),
+            telemetry,
+            settings,
+        })
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            poll_interval = ?self.settings.poll_interval,
+            provider = self.provider.name(),
+            "Starting weather importer"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.poll_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            if let Err(e) = self.import_all().await {
+                tracing::warn!("Weather import cycle failed: {e:#}");
+            }
+        }
+
+        tracing::info!("Weather importer stopped");
+    }
+
+    async fn import_all(&self) -> anyhow::Result<()> {
+        let plants = {
+            let mut conn = self.pool.get().await?;
+            Plant::located(&mut conn).await?
+        };
+
+        let today = Utc::now().date_naive();
+        let start = today - chrono::Days::new(self.settings.past_days.into());
+        let end = today + chrono::Days::new(self.settings.forecast_days.into());
+        for plant in &plants {
+            let outcome = match self.import(plant, start, end).await {
+                Ok(imported) => {
+                    tracing::debug!(
+                        plant_id = %plant.id,
+                        imported,
+                        "Imported weather"
+                    );
+                    "imported"
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        plant_id = %plant.id,
+                        "Weather import failed: {e:#}"
+                    );
+                    "failed"
+                }
+            };
+            self.telemetry
+                .maybe_use_metrics(|m| m.record_weather_import(outcome));
+        }
+
+        Ok(())
+    }
+
+    /// Import the weather of `plant` from `start` to `end`. Returns the
+    /// number of hours stored.
+    async fn import(
+        &self,
+        plant: &Plant,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> anyhow::Result<usize> {
+        let (Some(latitude), Some(longitude)) =
+            (plant.latitude, plant.longitude)
+        else {
+            return Ok(0);
+        };
+
+        let samples = self
+            .provider
+            .hourly(latitude, longitude, start, end)
+            .await?;
+        if samples.is_empty() {
+            return Ok(0);
+        }
+
+        let now = Utc::now();
+        let observations = samples
+            .into_iter()
+            .map(|sample| NewWeatherObservation {
+                plant_id: plant.id,
+                // The current hour is not over yet
+                forecast: sample.observed_at + chrono::Duration::hours(1) > now,
+                observed_at: sample.observed_at,
+                tenant_id: plant.tenant_id.clone(),
+                temperature_c: sample.temperature_c,
+                irradiance_w_m2: sample.irradiance_w_m2,
+                wind_speed_m_s: sample.wind_speed_m_s,
+                source: self.provider.name().to_string(),
+            })
+            .collect();
+
+        let mut conn = self.pool.get().await?;
+        Ok(WeatherObservation::upsert(observations, &mut conn).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correlation() {
+        let rising = [(1.0, 10.0), (2.0, 20.0), (3.0, 31.0)];
+        assert!(correlation(&rising).unwrap() > 0.99);
+
+        let falling = [(1.0, 3.0), (2.0, 2.0), (3.0, 1.0)];
+        assert_eq!(correlation(&falling), Some(-1.0));
+
+        assert_eq!(correlation(&rising[..2]), None);
+        assert_eq!(correlation(&[(1.0, 5.0), (2.0, 5.0), (3.0, 5.0)]), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! The Open-Meteo forecast API.
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use url::Url;
+
+use super::{WeatherProvider, WeatherSample};
+
+const HOURLY_VARIABLES: &str =
+    "temperature_2m,shortwave_radiation,wind_speed_10m";
+
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    hourly: Hourly,
+}
+
+/// Hourly series, one value per entry of `time`
+#[derive(Debug, Deserialize)]
+struct Hourly {
+    time: Vec<String>,
+    temperature_2m: Vec<Option<f64>>,
+    shortwave_radiation: Vec<Option<f64>>,
+    wind_speed_10m: Vec<Option<f64>>,
+}
+
+/// Samples of a forecast response requested in UTC.
+fn parse_hourly(body: &str) -> anyhow::Result<Vec<WeatherSample>> {
+    let hourly = serde_json::from_str::<ForecastResponse>(body)?.hourly;
+    let len = hourly.time.len();
+    if hourly.temperature_2m.len() != len
+        || hourly.shortwave_radiation.len() != len
+        || hourly.wind_speed_10m.len() != len
+    {
+        anyhow::bail!("Hourly series have different lengths");
+    }
+
+    hourly
+        .time
+        .iter()
+        .enumerate()
+        .map(|(i, time)| {
+            let observed_at =
+                NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M")?
+                    .and_utc();
+            Ok(WeatherSample {
+                observed_at,
+                temperature_c: hourly.temperature_2m[i],
+                irradiance_w_m2: hourly.shortwave_radiation[i],
+                wind_speed_m_s: hourly.wind_speed_10m[i],
+            })
+        })
+        .collect()
+}
+
+/// Fetches the weather from an Open-Meteo compatible forecast endpoint,
+/// e.g. `https://api.open-meteo.com/v1/forecast`. Irradiance is the
+/// shortwave radiation on a horizontal surface.
+pub struct OpenMeteo {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl OpenMeteo {
+    pub fn new(client: reqwest::Client, url: Url) -> Self {
+        Self { client, url }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "open_meteo"
+    }
+
+    async fn hourly(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> anyhow::Result<Vec<WeatherSample>> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("latitude", &latitude.to_string())
+            .append_pair("longitude", &longitude.to_string())
+            .append_pair("hourly", HOURLY_VARIABLES)
+            .append_pair("wind_speed_unit", "ms")
+            .append_pair("timezone", "GMT")
+            .append_pair("start_date", &start.to_string())
+            .append_pair("end_date", &end.to_string());
+
+        let response = self.client.get(url).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            anyhow::bail!("Weather API responded with {status}: {body}");
+        }
+        parse_hourly(&body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    #[test]
+    fn parses_hourly_series() {
+        let body = r#"{
+            "latitude": 42.7,
+            "longitude": 23.32,
+            "hourly": {
+                "time": ["2025-06-01T11:00", "2025-06-01T12:00"],
+                "temperature_2m": [24.1, 25.3],
+                "shortwave_radiation": [812.0, null],
+                "wind_speed_10m": [3.2, 2.9]
+            }
+        }"#;
+
+        let samples = parse_hourly(body).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(
+            samples[1].observed_at,
+            Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap()
+        );
+        assert_eq!(samples[0].irradiance_w_m2, Some(812.0));
+        assert_eq!(samples[1].irradiance_w_m2, None);
+        assert_eq!(samples[1].wind_speed_m_s, Some(2.9));
+
+        let uneven = body.replace("[3.2, 2.9]", "[3.2]");
+        assert!(parse_hourly(&uneven).is_err());
+    }
+}
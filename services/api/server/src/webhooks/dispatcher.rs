@@ -0,0 +1,230 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use postgres_models::models::webhooks::{
+    DeliveryAttempt, Webhook, WebhookDelivery,
+};
+use telemetry::metrics::Telemetry;
+
+use super::signing;
+use crate::events::{DomainEvent, DomainEvents};
+use crate::metrics::ServerMetrics;
+use crate::shutdown::ShutdownCoordinator;
+
+const CLAIM_BATCH_SIZE: i32 = 50;
+const STALE_IN_FLIGHT_AFTER: Duration = Duration::from_secs(300);
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone)]
+pub struct DispatcherSettings {
+    pub poll_interval: Duration,
+    pub request_timeout: Duration,
+    pub max_attempts: i32,
+}
+
+/// Background worker that sends queued webhook deliveries.
+///
+/// Each attempt is signed with the webhook's secret (see [`signing::sign`]),
+/// failures are retried with exponential backoff and every outcome is
+/// recorded on the delivery row.
+pub struct WebhookDispatcher {
+    pool: postgres_models::connection::Pool,
+    client: reqwest::Client,
+    telemetry: Arc<Telemetry<ServerMetrics>>,
+    events: DomainEvents,
+    settings: DispatcherSettings,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        pool: postgres_models::connection::Pool,
+        telemetry: Arc<Telemetry<ServerMetrics>>,
+        events: DomainEvents,
+        settings: DispatcherSettings,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(settings.request_timeout)
+            .build()?;
+
+        Ok(Self {
+            pool,
+            client,
+            telemetry,
+            events,
+            settings,
+        })
+    }
+
+    pub async fn run(self, shutdown: Arc<ShutdownCoordinator>) {
+        tracing::info!(
+            poll_interval = ?self.settings.poll_interval,
+            "Starting webhook dispatcher"
+        );
+
+        let mut interval = tokio::time::interval(self.settings.poll_interval);
+        let mut events = self.events.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                // Send deliveries as soon as they are queued
+                event = events.recv() => {
+                    if !matches!(
+                        event.as_deref(),
+                        Ok(DomainEvent::DeliveriesQueued { .. }) | Err(_)
+                    ) {
+                        continue;
+                    }
+                }
+                _ = shutdown.wait_for_shutdown() => break,
+            }
+            if shutdown.is_shutting_down() {
+                break;
+            }
+
+            if let Err(e) = self.dispatch_due().await {
+                tracing::warn!("Webhook dispatch cycle failed: {e:#}");
+            }
+        }
+
+        tracing::info!("Webhook dispatcher stopped");
+    }
+
+    async fn dispatch_due(&self) -> anyhow::Result<()> {
+        // No connection is held across a delivery, which can take the
+        // whole request timeout
+        let due = {
+            let mut conn = self.pool.get().await?;
+            WebhookDelivery::claim_due(
+                CLAIM_BATCH_SIZE,
+                STALE_IN_FLIGHT_AFTER,
+                &mut conn,
+            )
+            .await?
+        };
+
+        for delivery in due {
+            let found = {
+                let mut conn = self.pool.get().await?;
+                Webhook::of_delivery(&delivery, &mut conn).await
+            };
+            let webhook = match found {
+                Ok(webhook) => webhook,
+                Err(diesel::result::Error::NotFound) => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            let attempt = self.attempt(&webhook, &delivery).await;
+            let outcome = match (&attempt.error, attempt.retry_at) {
+                (None, _) => "delivered",
+                (Some(_), Some(_)) => "retry",
+                (Some(_), None) => "failed",
+            };
+            self.telemetry.maybe_use_metrics(|m| {
+                m.record_webhook_delivery(outcome);
+            });
+
+            let mut conn = self.pool.get().await?;
+            WebhookDelivery::record_attempt(delivery.id, attempt, &mut conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn attempt(
+        &self,
+        webhook: &Webhook,
+        delivery: &WebhookDelivery,
+    ) -> DeliveryAttempt {
+        let body = serde_json::json!({
+            "id": delivery.id,
+            "event": delivery.event_type,
+            "createdAt": delivery.created_at,
+            "data": delivery.payload,
+        })
+        .to_string();
+        let timestamp = Utc::now().timestamp();
+        let signature =
+            signing::sign(&webhook.secret, timestamp, body.as_bytes());
+
+        let result = self
+            .client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(signing::SIGNATURE_HEADER, signature)
+            .header(signing::TIMESTAMP_HEADER, timestamp)
+            .header(signing::EVENT_HEADER, &delivery.event_type)
+            .header(signing::DELIVERY_HEADER, delivery.id.to_string())
+            .body(body)
+            .send()
+            .await;
+
+        let (status_code, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16() as i32), None)
+            }
+            Ok(response) => (
+                Some(response.status().as_u16() as i32),
+                Some(format!("Receiver responded with {}", response.status())),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        if let Some(error) = &error {
+            tracing::warn!(
+                delivery_id = %delivery.id,
+                webhook_id = %webhook.id,
+                attempts = delivery.attempts,
+                "Webhook delivery failed: {error}"
+            );
+        }
+
+        let retry_at = match error {
+            Some(_) if delivery.attempts < self.settings.max_attempts => {
+                let delay = retry_delay(delivery.attempts);
+                Some(
+                    Utc::now()
+                        + chrono::Duration::from_std(delay)
+                            .unwrap_or(chrono::Duration::MAX),
+                )
+            }
+            _ => None,
+        };
+
+        DeliveryAttempt {
+            status_code,
+            error,
+            retry_at,
+        }
+    }
+}
+
+/// Exponential backoff: 30s, 1m, 2m, 4m, ... capped at one hour.
+pub(crate) fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(exponent))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_grows_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(3), Duration::from_secs(120));
+        assert_eq!(retry_delay(4), Duration::from_secs(240));
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(i32::MAX), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(0), BASE_RETRY_DELAY);
+    }
+}
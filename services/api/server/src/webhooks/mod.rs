@@ -0,0 +1,126 @@
+//! Outgoing webhook events.
+//!
+//! Events recorded in the [`crate::outbox`] are turned into
+//! `webhook_deliveries` rows (one per subscribed webhook of the event's
+//! tenant) once committed, and sent asynchronously by the [`dispatcher::WebhookDispatcher`].
+pub mod dispatcher;
+pub mod signing;
+
+use diesel_async::AsyncPgConnection;
+use postgres_models::models::webhooks::{
+    NewWebhookDelivery, Webhook, WebhookDelivery,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    ImportCompleted,
+    AnomalyDetected,
+    ThresholdBreached,
+    ReadingsDeleted,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ImportCompleted => "import_completed",
+            WebhookEvent::AnomalyDetected => "anomaly_detected",
+            WebhookEvent::ThresholdBreached => "threshold_breached",
+            WebhookEvent::ReadingsDeleted => "readings_deleted",
+        }
+    }
+}
+
+impl std::fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Queue a delivery of an event of `tenant` to every active webhook of the
+/// tenant subscribed to it.
+///
+/// Called by the [`crate::outbox::OutboxRelay`] in the transaction that
+/// marks the event published. Returns the number of queued deliveries.
+pub async fn enqueue_deliveries(
+    tenant: &str,
+    event_type: &str,
+    data: serde_json::Value,
+    conn: &mut AsyncPgConnection,
+) -> Result<usize, diesel::result::Error> {
+    let subscribers = Webhook::subscribed_to(tenant, event_type, conn).await?;
+    if subscribers.is_empty() {
+        return Ok(0);
+    }
+
+    let deliveries = subscribers
+        .into_iter()
+        .map(|webhook| NewWebhookDelivery {
+            webhook_id: webhook.id,
+            event_type: event_type.to_string(),
+            payload: data.clone(),
+        })
+        .collect();
+
+    let queued = WebhookDelivery::enqueue(deliveries, conn).await?;
+    tracing::info!(
+        %tenant,
+        event = %event_type,
+        queued,
+        "Queued webhook deliveries"
+    );
+
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use postgres_models::models::webhooks::NewWebhook;
+
+    use super::*;
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[tokio::test]
+    async fn test_delivers_to_the_webhooks_of_the_events_tenant() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let mut conn = app.state.pool.get().await.unwrap();
+        let mut subscribe = async |tenant: &str| {
+            let webhook = NewWebhook {
+                tenant_id: tenant.to_string(),
+                url: format!("https://{tenant}.example.com/hooks"),
+                secret: signing::generate_secret(),
+                event_types: vec![Some("import_completed".to_string())],
+                description: None,
+                active: true,
+            };
+            Webhook::create(webhook, &mut conn).await.unwrap()
+        };
+        let ours = subscribe(DEFAULT_TENANT).await;
+        subscribe("other").await;
+
+        let data = serde_json::json!({ "tenant": DEFAULT_TENANT });
+        let queued = enqueue_deliveries(
+            DEFAULT_TENANT,
+            "import_completed",
+            data,
+            &mut conn,
+        )
+        .await
+        .unwrap();
+        assert_eq!(queued, 1);
+        let deliveries = WebhookDelivery::latest_for_webhook(
+            DEFAULT_TENANT,
+            ours.id,
+            10,
+            &mut conn,
+        )
+        .await
+        .unwrap();
+        assert_eq!(deliveries.len(), 1);
+    }
+}
@@ -0,0 +1,75 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub const SIGNATURE_HEADER: &str = "x-wire-signature";
+pub const TIMESTAMP_HEADER: &str = "x-wire-timestamp";
+pub const EVENT_HEADER: &str = "x-wire-event";
+pub const DELIVERY_HEADER: &str = "x-wire-delivery";
+
+const SIGNATURE_SCHEME: &str = "sha256=";
+
+/// Sign a webhook body with the subscriber's secret.
+///
+/// The signed message is `"{timestamp}.{body}"` so receivers can reject
+/// replayed payloads by checking the timestamp header. The result has the
+/// form `sha256=<hex digest>`.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!(
+        "{SIGNATURE_SCHEME}{}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Generate a new random signing secret for a webhook subscription.
+pub fn generate_secret() -> String {
+    let bytes: [u8; 32] = rand::random();
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_is_deterministic() {
+        let a = sign("secret", 1_700_000_000, b"{\"a\":1}");
+        let b = sign("secret", 1_700_000_000, b"{\"a\":1}");
+
+        assert_eq!(a, b);
+        assert!(a.starts_with("sha256="));
+        assert_eq!(a.len(), "sha256=".len() + 64);
+    }
+
+    #[test]
+    fn test_sign_covers_secret_timestamp_and_body() {
+        let base = sign("secret", 1_700_000_000, b"body");
+
+        assert_ne!(base, sign("other", 1_700_000_000, b"body"));
+        assert_ne!(base, sign("secret", 1_700_000_001, b"body"));
+        assert_ne!(base, sign("secret", 1_700_000_000, b"body2"));
+    }
+
+    #[test]
+    fn test_sign_known_vector() {
+        // echo -n "0.hello" | openssl dgst -sha256 -hmac "key"
+        assert_eq!(
+            sign("key", 0, b"hello"),
+            "sha256=552b25b10c33de2db9fe7fa273d44ab105d596c023fa954d01444d4642487149"
+        );
+    }
+
+    #[test]
+    fn test_generate_secret_is_unique() {
+        let a = generate_secret();
+        let b = generate_secret();
+
+        assert!(a.starts_with("whsec_"));
+        assert_ne!(a, b);
+    }
+}
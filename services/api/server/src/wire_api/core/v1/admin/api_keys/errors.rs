@@ -0,0 +1,34 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("API key not found: {0}")]
+    NotFound(Uuid),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "API key not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "api_key_not_found".to_string(),
+                    message: format!("No active API key exists with id {id}"),
+                    suggestion: "Check the API key id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "API key operation failed";
+}
@@ -0,0 +1,139 @@
+use axum::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use postgres_models::connection::with_connection;
+use postgres_models::models::api_keys::{ApiKey, NewApiKey};
+use uuid::Uuid;
+
+use crate::auth::{Role, api_key, tenant};
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{ApiKeyListResponse, ApiKeyResponse, CreateApiKeyRequest};
+
+const HANDLER_NAME: &str = "admin_api_keys";
+
+/// Issue a new API key
+///
+/// The key and its request signing secret are only returned in this
+/// response; the key is stored hashed.
+#[utoipa::path(
+    post,
+    path = "/admin/api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "API key issued", body = ApiKeyResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_api_keys_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateApiKeyRequest>,
+) -> HandlerResult<(StatusCode, Json<ApiKeyResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let generated = api_key::generate();
+    let new_key = NewApiKey {
+        name: payload.name,
+        key_prefix: generated.prefix,
+        key_hash: generated.hash,
+        role: payload.role.unwrap_or(Role::Read).to_string(),
+        daily_quota: payload.daily_quota,
+        monthly_quota: payload.monthly_quota,
+        signing_secret: Some(generated.signing_secret.clone()),
+        tenant_id: payload
+            .tenant_id
+            .unwrap_or_else(|| tenant::DEFAULT_TENANT.to_string()),
+    };
+
+    let key = with_connection(&ctx.state.pool, |mut conn| async move {
+        ApiKey::create(new_key, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    tracing::info!(
+        api_key_id = %key.id,
+        name = %key.name,
+        role = %key.role,
+        tenant = %key.tenant_id,
+        "Issued API key"
+    );
+
+    let mut response = ApiKeyResponse::from(key);
+    response.key = Some(generated.secret);
+    response.signing_secret = Some(generated.signing_secret);
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// List issued API keys, including revoked ones
+#[utoipa::path(
+    get,
+    path = "/admin/api-keys",
+    responses(
+        (status = 200, description = "Issued API keys", body = ApiKeyListResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_api_keys_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<ApiKeyListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let keys = with_connection(&ctx.state.pool, |mut conn| async move {
+        ApiKey::list(&mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let api_keys = keys.into_iter().map(ApiKeyResponse::from).collect();
+
+    Ok((StatusCode::OK, Json(ApiKeyListResponse { api_keys })))
+}
+
+/// Revoke an API key
+#[utoipa::path(
+    delete,
+    path = "/admin/api-keys/{id}",
+    params(("id" = Uuid, Path, description = "API key id")),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "API key not found or already revoked"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_api_keys_revoke")]
+pub async fn revoke(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let revoked = with_connection(&ctx.state.pool, |mut conn| async move {
+        ApiKey::revoke(id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    if revoked == 0 {
+        return Err(recorder.record("not_found", errors::Error::NotFound(id)));
+    }
+
+    tracing::info!(api_key_id = %id, "Revoked API key");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,13 @@
+use axum::Router;
+use axum::routing::{delete, get};
+
+mod errors;
+pub mod handler;
+pub mod models;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", get(handler::list).post(handler::create))
+        .route("/{id}", delete(handler::revoke))
+        .with_state(state)
+}
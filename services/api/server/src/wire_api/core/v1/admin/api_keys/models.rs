@@ -0,0 +1,95 @@
+use postgres_models::models::api_keys::ApiKey;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::auth::{Role, tenant};
+
+pub fn validate_tenant_id(tenant_id: &str) -> Result<(), ValidationError> {
+    if tenant::is_valid_tenant_id(tenant_id) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_tenant").with_message(
+            "Tenant ids are 1-64 lowercase letters, digits, `-` or `_`".into(),
+        ))
+    }
+}
+
+/// Request payload for issuing an API key
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    /// Human readable owner of the key, recorded with each request
+    #[validate(length(min = 1, max = 100))]
+    #[schema(example = "grafana-dashboard")]
+    pub name: String,
+
+    /// Access level of the key (defaults to read)
+    pub role: Option<Role>,
+
+    /// Tenant whose data the key can access (defaults to `default`)
+    #[validate(custom(function = "validate_tenant_id"))]
+    #[schema(example = "acme")]
+    pub tenant_id: Option<String>,
+
+    /// Requests allowed per UTC day (unlimited when omitted)
+    #[validate(range(min = 1))]
+    pub daily_quota: Option<i64>,
+
+    /// Requests allowed per calendar month (unlimited when omitted)
+    #[validate(range(min = 1))]
+    pub monthly_quota: Option<i64>,
+}
+
+/// An issued API key (without its secret)
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyResponse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    /// Leading characters of the key, for identification
+    #[schema(example = "wk_1a2b3c4d")]
+    pub key_prefix: String,
+    #[schema(example = "read")]
+    pub role: String,
+    #[schema(example = "default")]
+    pub tenant_id: String,
+    pub daily_quota: Option<i64>,
+    pub monthly_quota: Option<i64>,
+    /// Full key, only returned when the key is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Secret for signing ingestion requests, only returned when the key is
+    /// created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_secret: Option<String>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            name: key.name,
+            key_prefix: key.key_prefix,
+            role: key.role,
+            tenant_id: key.tenant_id,
+            daily_quota: key.daily_quota,
+            monthly_quota: key.monthly_quota,
+            key: None,
+            signing_secret: None,
+            last_used_at: key.last_used_at,
+            revoked_at: key.revoked_at,
+            created_at: key.created_at,
+        }
+    }
+}
+
+/// Response containing all issued API keys
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyListResponse {
+    pub api_keys: Vec<ApiKeyResponse>,
+}
@@ -0,0 +1,160 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Upload not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("File of {size} bytes is larger than the {max} allowed")]
+    TooLarge { size: i64, max: u64 },
+
+    #[error("Invalid Upload-Offset: {0}")]
+    InvalidOffset(String),
+
+    #[error(
+        "Chunk at {offset} does not continue the {received} bytes received"
+    )]
+    OffsetMismatch { offset: i64, received: i64 },
+
+    #[error("Chunk ends at {end}, past the {size} bytes of the file")]
+    PastEnd { end: i64, size: i64 },
+
+    #[error("Upload is {0}")]
+    NotUploading(String),
+
+    #[error("Another chunk of the upload is being written")]
+    Busy,
+
+    #[error("File has SHA-256 {actual}, expected {expected}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("Failed to store the file: {0}")]
+    StorageError(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        let message = self.to_string();
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Upload not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "upload_not_found".to_string(),
+                    message: format!("No upload exists with id {id}"),
+                    suggestion: "Check the upload id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::TooLarge { .. } => WireV1Error::payload_too_large(
+                "File too large".to_string(),
+                vec![WireV1Detail {
+                    field: Some("size".to_string()),
+                    code: "file_too_large".to_string(),
+                    message,
+                    suggestion: "Split the readings into smaller files"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidOffset(_) => WireV1Error::bad_request(
+                "Invalid chunk".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Upload-Offset".to_string()),
+                    code: "invalid_offset".to_string(),
+                    message,
+                    suggestion: "Send the byte offset the chunk starts at"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::OffsetMismatch { received, .. } => WireV1Error::conflict(
+                "Chunk out of order".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Upload-Offset".to_string()),
+                    code: "offset_mismatch".to_string(),
+                    message,
+                    suggestion: format!(
+                        "Resume the upload from byte {received}"
+                    ),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::PastEnd { .. } => WireV1Error::bad_request(
+                "Invalid chunk".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "chunk_past_end".to_string(),
+                    message,
+                    suggestion: "Send the remaining bytes of the file only"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::NotUploading(_) => WireV1Error::conflict(
+                "Upload already complete".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "upload_complete".to_string(),
+                    message,
+                    suggestion: "Start a new upload to send the file again"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::Busy => WireV1Error::conflict(
+                "Chunk in progress".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "upload_busy".to_string(),
+                    message,
+                    suggestion: "Send chunks of an upload one at a time"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::ChecksumMismatch { .. } => {
+                WireV1Error::unprocessable_entity(
+                    "Checksum mismatch".to_string(),
+                    vec![WireV1Detail {
+                        field: Some("sha256".to_string()),
+                        code: "checksum_mismatch".to_string(),
+                        message,
+                        suggestion: "Start a new upload of the file"
+                            .to_string(),
+                        documentation: String::new(),
+                    }],
+                    request_id.to_string(),
+                )
+            }
+            Error::StorageError(_) => WireV1Error::internal_server_error(
+                "Failed to store the file".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "storage_error".to_string(),
+                    message,
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Upload operation failed";
+}
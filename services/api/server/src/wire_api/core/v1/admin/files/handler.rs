@@ -0,0 +1,424 @@
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::Path;
+use axum::http::{HeaderMap, StatusCode};
+use postgres_models::connection::with_connection;
+use postgres_models::models::file_uploads::{
+    FileUpload, NewFileUpload, upload_status,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::tenant;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::uploads;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{CreateFileUploadRequest, FileUploadResponse};
+
+const HANDLER_NAME: &str = "admin_files";
+
+/// Header giving the byte offset a chunk starts at.
+const UPLOAD_OFFSET: &str = "upload-offset";
+
+/// Start uploading a readings file
+///
+/// Announces an Excel or CSV file by its size and SHA-256 digest. Its
+/// bytes are then sent in chunks with `PATCH /admin/files/{id}`.
+#[utoipa::path(
+    post,
+    path = "/admin/files",
+    request_body = CreateFileUploadRequest,
+    responses(
+        (status = 201, description = "Upload started", body = FileUploadResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 413, description = "File larger than `UPLOAD_MAX_BYTES`"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_files_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateFileUploadRequest>,
+) -> HandlerResult<(StatusCode, Json<FileUploadResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let max = ctx.state.uploads.settings().max_bytes;
+    if u64::try_from(payload.size).is_ok_and(|size| size > max) {
+        return Err(recorder.record(
+            "file_too_large",
+            errors::Error::TooLarge {
+                size: payload.size,
+                max,
+            },
+        ));
+    }
+
+    let new_upload = NewFileUpload {
+        tenant_id: payload
+            .tenant_id
+            .unwrap_or_else(|| tenant::DEFAULT_TENANT.to_string()),
+        file_name: payload.file_name,
+        size: payload.size,
+        sha256: payload.sha256.to_ascii_lowercase(),
+    };
+    let upload = with_connection(&ctx.state.pool, |mut conn| async move {
+        FileUpload::create(new_upload, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    tracing::info!(
+        upload = %upload.id,
+        file = %upload.file_name,
+        size = upload.size,
+        tenant = %upload.tenant_id,
+        "Started file upload"
+    );
+
+    Ok((StatusCode::CREATED, Json(FileUploadResponse::from(upload))))
+}
+
+/// Get an upload
+///
+/// `received` is where an interrupted upload resumes; once complete, the
+/// status follows the import of the file.
+#[utoipa::path(
+    get,
+    path = "/admin/files/{id}",
+    params(("id" = Uuid, Path, description = "Upload id")),
+    responses(
+        (status = 200, description = "The upload", body = FileUploadResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Upload not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_files_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<FileUploadResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let upload = find(&ctx.state, &recorder, id).await?;
+    Ok((StatusCode::OK, Json(FileUploadResponse::from(upload))))
+}
+
+/// Send a chunk of an upload
+///
+/// The body holds the bytes of the file starting at `Upload-Offset`, which
+/// must be the bytes received so far, and at most 16 MiB of them. After
+/// the last chunk the file is checked against its SHA-256 digest, stored
+/// and imported in the background.
+#[utoipa::path(
+    patch,
+    path = "/admin/files/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Upload id"),
+        ("Upload-Offset" = i64, Header, description = "Byte offset of the chunk in the file"),
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Chunk stored", body = FileUploadResponse),
+        (status = 400, description = "Missing offset, or a chunk past the end of the file"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Upload not found"),
+        (status = 409, description = "Offset other than the bytes received, or the upload is complete"),
+        (status = 413, description = "Chunk larger than 16 MiB"),
+        (status = 422, description = "The file does not match its SHA-256 digest"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_files_append")]
+pub async fn append(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    chunk: Bytes,
+) -> HandlerResult<(StatusCode, Json<FileUploadResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let offset = headers
+        .get(UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|&offset| offset >= 0)
+        .ok_or_else(|| {
+            let value = headers
+                .get(UPLOAD_OFFSET)
+                .map(|value| format!("{value:?}"))
+                .unwrap_or_else(|| "missing".to_string());
+            recorder
+                .record("invalid_offset", errors::Error::InvalidOffset(value))
+        })?;
+
+    let upload = find(&ctx.state, &recorder, id).await?;
+    if upload.status != upload_status::UPLOADING {
+        return Err(recorder.record(
+            "upload_complete",
+            errors::Error::NotUploading(upload.status),
+        ));
+    }
+    if offset != upload.received {
+        return Err(recorder.record(
+            "offset_mismatch",
+            errors::Error::OffsetMismatch {
+                offset,
+                received: upload.received,
+            },
+        ));
+    }
+    let end = offset + chunk.len() as i64;
+    if chunk.is_empty() || end > upload.size {
+        return Err(recorder.record(
+            "chunk_past_end",
+            errors::Error::PastEnd {
+                end,
+                size: upload.size,
+            },
+        ));
+    }
+
+    let Some(_claim) = ctx.state.uploads.claim(id) else {
+        return Err(recorder.record("upload_busy", errors::Error::Busy));
+    };
+    ctx.state
+        .uploads
+        .write_chunk(&upload, offset as u64, &chunk)
+        .await
+        .map_err(|e| {
+            recorder.record(
+                "storage_error",
+                errors::Error::StorageError(e.to_string()),
+            )
+        })?;
+    let upload = with_connection(&ctx.state.pool, |mut conn| async move {
+        FileUpload::advance(id, offset, end, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("upload_busy", errors::Error::Busy))?;
+
+    let upload = if upload.received == upload.size {
+        complete(&ctx.state, &recorder, upload).await?
+    } else {
+        upload
+    };
+    Ok((StatusCode::OK, Json(FileUploadResponse::from(upload))))
+}
+
+async fn find(
+    state: &AppState,
+    recorder: &ErrorRecorder<'_>,
+    id: Uuid,
+) -> HandlerResult<FileUpload> {
+    with_connection(&state.pool, |mut conn| async move {
+        FileUpload::find(id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))
+}
+
+/// Check the received file against its digest, store it and start its
+/// import.
+async fn complete(
+    state: &AppState,
+    recorder: &ErrorRecorder<'_>,
+    upload: FileUpload,
+) -> HandlerResult<FileUpload> {
+    let staged = state.uploads.staged_path(&upload);
+    let stored = match uploads::sha256_file(&staged).await {
+        Ok(actual) if actual == upload.sha256 => state
+            .uploads
+            .store(&upload)
+            .await
+            .map_err(|e| errors::Error::StorageError(format!("{e:#}"))),
+        Ok(actual) => Err(errors::Error::ChecksumMismatch {
+            expected: upload.sha256.clone(),
+            actual,
+        }),
+        Err(e) => Err(errors::Error::StorageError(e.to_string())),
+    };
+
+    let (location, file) = match stored {
+        Ok(stored) => stored,
+        Err(e) => {
+            let reason = e.to_string();
+            tracing::warn!(upload = %upload.id, "Upload failed: {reason}");
+            if let Err(remove) = tokio::fs::remove_file(&staged).await {
+                tracing::warn!(
+                    upload = %upload.id,
+                    "Failed to remove a staged upload: {remove}"
+                );
+            }
+            let id = upload.id;
+            with_connection(&state.pool, |mut conn| async move {
+                FileUpload::fail(id, &reason, &mut conn).await
+            })
+            .await
+            .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+            let code = match e {
+                errors::Error::ChecksumMismatch { .. } => "checksum_mismatch",
+                _ => "storage_error",
+            };
+            return Err(recorder.record(code, e));
+        }
+    };
+
+    let id = upload.id;
+    let stored_at = location.clone();
+    let upload = with_connection(&state.pool, |mut conn| async move {
+        FileUpload::importing(id, &stored_at, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    tracing::info!(
+        upload = %upload.id,
+        location = %location,
+        tenant = %upload.tenant_id,
+        "Stored uploaded file, importing it"
+    );
+    state.uploads.clone().spawn_import(
+        state.pool.clone(),
+        upload.clone(),
+        file,
+        state.config.import_source_time,
+        state.config.quantity_policy,
+    );
+    Ok(upload)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::Method;
+    use serde_json::{Value, json};
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::wire_api::testing::TestApp;
+
+    #[tokio::test]
+    async fn test_resumes_then_imports_an_upload() {
+        let dir = std::env::temp_dir()
+            .join(format!("wire-uploads-{}", Uuid::new_v4().simple()));
+        let Some(app) =
+            TestApp::with_vars(&[("UPLOAD_DIR", dir.to_str().unwrap())]).await
+        else {
+            return;
+        };
+        let file = b"Time (UTC),Quantity kWh\n\
+                     2025-01-01 00:00:00,12.5\n\
+                     2025-01-01 01:00:00,7.25\n";
+
+        let created = app
+            .admin(Method::POST, "/api/wire/v1/admin/files")
+            .json(&json!({
+                "fileName": "readings.csv",
+                "size": file.len(),
+                "sha256": hex::encode(Sha256::digest(file)),
+            }))
+            .await;
+        created.assert_status(StatusCode::CREATED);
+        let url = format!(
+            "/api/wire/v1/admin/files/{}",
+            created.json::<Value>()["id"].as_str().unwrap()
+        );
+
+        let chunk = |offset: usize, bytes: &[u8]| {
+            app.admin(Method::PATCH, &url)
+                .add_header(UPLOAD_OFFSET, offset.to_string())
+                .bytes(Bytes::copy_from_slice(bytes))
+        };
+        let sent = chunk(0, &file[..20]).await;
+        sent.assert_status_ok();
+        assert_eq!(sent.json::<Value>()["received"], 20);
+
+        // A retried chunk is refused, pointing at where to resume
+        chunk(0, &file[..20])
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        let resumed = app.admin(Method::GET, &url).await.json::<Value>();
+        assert_eq!(resumed["received"], 20);
+
+        let sent = chunk(20, &file[20..]).await;
+        sent.assert_status_ok();
+        assert_eq!(sent.json::<Value>()["status"], "importing");
+
+        let mut upload = Value::Null;
+        for _ in 0..50 {
+            upload = app.admin(Method::GET, &url).await.json::<Value>();
+            if upload["status"] != "importing" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(upload["status"], "imported", "{upload}");
+        assert!(upload["importRunId"].is_string());
+        assert!(
+            upload["location"]
+                .as_str()
+                .unwrap()
+                .ends_with("readings.csv")
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_a_file_not_matching_its_digest() {
+        let dir = std::env::temp_dir()
+            .join(format!("wire-uploads-{}", Uuid::new_v4().simple()));
+        let Some(app) =
+            TestApp::with_vars(&[("UPLOAD_DIR", dir.to_str().unwrap())]).await
+        else {
+            return;
+        };
+
+        let created = app
+            .admin(Method::POST, "/api/wire/v1/admin/files")
+            .json(&json!({
+                "fileName": "readings.csv",
+                "size": 5,
+                "sha256": hex::encode(Sha256::digest(b"hello")),
+            }))
+            .await
+            .json::<Value>();
+        let url = format!(
+            "/api/wire/v1/admin/files/{}",
+            created["id"].as_str().unwrap()
+        );
+
+        app.admin(Method::PATCH, &url)
+            .add_header(UPLOAD_OFFSET, "0")
+            .bytes(Bytes::from_static(b"hallo"))
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let upload = app.admin(Method::GET, &url).await.json::<Value>();
+        assert_eq!(upload["status"], "failed");
+
+        app.admin(Method::POST, "/api/wire/v1/admin/files")
+            .json(&json!({
+                "fileName": "readings.csv",
+                "size": 2_000_000_000_i64,
+                "sha256": hex::encode(Sha256::digest(b"hello")),
+            }))
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
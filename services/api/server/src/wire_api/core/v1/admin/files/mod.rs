@@ -0,0 +1,21 @@
+use axum::Router;
+use axum::extract::DefaultBodyLimit;
+use axum::routing::{get, post};
+
+use crate::uploads::MAX_CHUNK_BYTES;
+
+mod errors;
+pub mod handler;
+pub mod models;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", post(handler::create))
+        .route(
+            "/{id}",
+            get(handler::get)
+                .patch(handler::append)
+                .layer(DefaultBodyLimit::max(MAX_CHUNK_BYTES)),
+        )
+        .with_state(state)
+}
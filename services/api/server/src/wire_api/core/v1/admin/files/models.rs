@@ -0,0 +1,90 @@
+use postgres_models::models::file_uploads::FileUpload;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::uploads;
+use crate::wire_api::core::v1::admin::api_keys::models::validate_tenant_id;
+
+fn validate_file_name(name: &str) -> Result<(), ValidationError> {
+    uploads::validate_file_name(name).map_err(|message| {
+        ValidationError::new("invalid_file_name").with_message(message.into())
+    })
+}
+
+fn validate_sha256(digest: &str) -> Result<(), ValidationError> {
+    if digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_sha256").with_message(
+            "Expected the 64 hex digits of a SHA-256 digest".into(),
+        ))
+    }
+}
+
+/// Request payload for starting an upload
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFileUploadRequest {
+    /// Name of the file, ending in `.xlsx` or `.csv`
+    #[validate(custom(function = "validate_file_name"))]
+    #[schema(example = "readings-2025-01.xlsx")]
+    pub file_name: String,
+
+    /// Bytes of the whole file
+    #[validate(range(min = 1))]
+    pub size: i64,
+
+    /// Hex SHA-256 digest of the whole file, checked once it is received
+    #[validate(custom(function = "validate_sha256"))]
+    pub sha256: String,
+
+    /// Tenant the readings are imported for (defaults to `default`)
+    #[validate(custom(function = "validate_tenant_id"))]
+    #[schema(example = "acme")]
+    pub tenant_id: Option<String>,
+}
+
+/// An upload and, once complete, the import of its file
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileUploadResponse {
+    pub id: uuid::Uuid,
+    #[schema(example = "default")]
+    pub tenant_id: String,
+    pub file_name: String,
+    pub size: i64,
+    pub sha256: String,
+    /// Bytes received, the offset of the next chunk
+    pub received: i64,
+    /// `uploading`, `importing`, `imported` or `failed`
+    #[schema(example = "uploading")]
+    pub status: String,
+    /// Where the file is stored, once complete
+    pub location: Option<String>,
+    /// Import run of the file, once imported
+    pub import_run_id: Option<uuid::Uuid>,
+    /// Why the upload or its import failed
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<FileUpload> for FileUploadResponse {
+    fn from(upload: FileUpload) -> Self {
+        Self {
+            id: upload.id,
+            tenant_id: upload.tenant_id,
+            file_name: upload.file_name,
+            size: upload.size,
+            sha256: upload.sha256,
+            received: upload.received,
+            status: upload.status,
+            location: upload.location,
+            import_run_id: upload.import_run_id,
+            error: upload.error,
+            created_at: upload.created_at,
+            updated_at: upload.updated_at,
+        }
+    }
+}
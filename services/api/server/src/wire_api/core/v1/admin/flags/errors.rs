@@ -0,0 +1,62 @@
+use uuid::Uuid;
+
+use crate::flags::Flag;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Redis error: {0}")]
+    CacheError(String),
+
+    #[error("Failed to get Redis connection: {0}")]
+    PoolError(String),
+
+    #[error("Unknown feature flag: {0}")]
+    UnknownFlag(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::CacheError(e) => WireV1Error::internal_server_error(
+                "Feature flag operation failed".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "cache_error".to_string(),
+                    message: format!("Redis error: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::PoolError(e) => WireV1Error::service_unavailable(
+                "Service temporarily unavailable".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "pool_error".to_string(),
+                    message: format!("Failed to get Redis connection: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::UnknownFlag(name) => WireV1Error::not_found(
+                "Feature flag not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("flag".to_string()),
+                    code: "unknown_flag".to_string(),
+                    message: format!("No feature flag is named `{name}`"),
+                    suggestion: format!(
+                        "Use one of: {}",
+                        Flag::ALL.map(|flag| flag.as_str()).join(", ")
+                    ),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use axum::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use redis_cache::connection::PooledConnection;
+
+use crate::AppState;
+use crate::flags::Flag;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+use super::errors::{self, HandlerResult};
+use super::models::{FlagListResponse, FlagResponse, SetFlagRequest};
+
+const HANDLER_NAME: &str = "admin_flags";
+
+fn parse_flag(
+    recorder: &ErrorRecorder<'_>,
+    name: String,
+) -> HandlerResult<Flag> {
+    name.parse().map_err(|_| {
+        recorder.record("unknown_flag", errors::Error::UnknownFlag(name))
+    })
+}
+
+async fn connection(
+    state: &AppState,
+    recorder: &ErrorRecorder<'_>,
+) -> HandlerResult<PooledConnection> {
+    state.cache_pool.get().await.map_err(|e| {
+        recorder.record("pool_error", errors::Error::PoolError(e.to_string()))
+    })
+}
+
+fn record_cache_error(
+    recorder: &ErrorRecorder<'_>,
+    e: deadpool_redis::redis::RedisError,
+) -> WireV1Error {
+    recorder.record("cache_error", errors::Error::CacheError(e.to_string()))
+}
+
+/// List feature flags
+///
+/// Returns every flag with its current value, its default and whether a
+/// runtime override is set.
+#[utoipa::path(
+    get,
+    path = "/admin/flags",
+    responses(
+        (status = 200, description = "Feature flags", body = FlagListResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_flags_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<FlagListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let mut conn = connection(&ctx.state, &recorder).await?;
+    let flags = ctx
+        .state
+        .flags
+        .states(&mut conn)
+        .await
+        .map_err(|e| record_cache_error(&recorder, e))?
+        .into_iter()
+        .map(FlagResponse::from)
+        .collect();
+
+    Ok((StatusCode::OK, Json(FlagListResponse { flags })))
+}
+
+/// Override a feature flag
+///
+/// The override applies to every instance within a few seconds and lasts
+/// until it is removed.
+#[utoipa::path(
+    put,
+    path = "/admin/flags/{flag}",
+    params(("flag" = String, Path, description = "Flag name, e.g. `aggregate_cache`")),
+    request_body = SetFlagRequest,
+    responses(
+        (status = 200, description = "Flag overridden", body = FlagResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Unknown feature flag"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_flags_set")]
+pub async fn set(
+    ctx: HandlerCtx,
+    Path(name): Path<String>,
+    ValidatedPayload(payload): ValidatedPayload<SetFlagRequest>,
+) -> HandlerResult<(StatusCode, Json<FlagResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let flag = parse_flag(&recorder, name)?;
+
+    let mut conn = connection(&ctx.state, &recorder).await?;
+    let flag_state = ctx
+        .state
+        .flags
+        .set_override(flag, payload.enabled, &mut conn)
+        .await
+        .map_err(|e| record_cache_error(&recorder, e))?;
+
+    tracing::info!(
+        flag = flag.as_str(),
+        enabled = payload.enabled,
+        "Overrode feature flag"
+    );
+
+    Ok((StatusCode::OK, Json(FlagResponse::from(flag_state))))
+}
+
+/// Remove a feature flag override
+///
+/// The flag returns to its default on every instance within a few seconds.
+#[utoipa::path(
+    delete,
+    path = "/admin/flags/{flag}",
+    params(("flag" = String, Path, description = "Flag name, e.g. `aggregate_cache`")),
+    responses(
+        (status = 200, description = "Override removed", body = FlagResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Unknown feature flag"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_flags_clear")]
+pub async fn clear(
+    ctx: HandlerCtx,
+    Path(name): Path<String>,
+) -> HandlerResult<(StatusCode, Json<FlagResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let flag = parse_flag(&recorder, name)?;
+
+    let mut conn = connection(&ctx.state, &recorder).await?;
+    let flag_state = ctx
+        .state
+        .flags
+        .clear_override(flag, &mut conn)
+        .await
+        .map_err(|e| record_cache_error(&recorder, e))?;
+
+    tracing::info!(flag = flag.as_str(), "Removed feature flag override");
+
+    Ok((StatusCode::OK, Json(FlagResponse::from(flag_state))))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+
+    use crate::wire_api::testing::TestApp;
+
+    #[tokio::test]
+    async fn test_admin_routes_take_the_admin_token() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+
+        app.server
+            .get("/api/wire/v1/admin/flags")
+            .await
+            .assert_status_unauthorized();
+        app.admin(Method::GET, "/api/wire/v1/admin/flags")
+            .await
+            .assert_status_ok();
+    }
+}
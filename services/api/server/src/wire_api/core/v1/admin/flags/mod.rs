@@ -0,0 +1,13 @@
+use axum::Router;
+use axum::routing::{get, put};
+
+mod errors;
+pub mod handler;
+pub mod models;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", get(handler::list))
+        .route("/{flag}", put(handler::set).delete(handler::clear))
+        .with_state(state)
+}
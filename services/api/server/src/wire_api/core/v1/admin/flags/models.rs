@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::flags::{Flag, FlagState};
+
+/// Request payload for overriding a feature flag
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetFlagRequest {
+    /// Whether the flag is on for every instance until the override is
+    /// removed
+    pub enabled: bool,
+}
+
+/// A feature flag and its current value
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagResponse {
+    #[schema(example = "aggregate_cache")]
+    pub name: Flag,
+    pub enabled: bool,
+    /// Value without an override, from the config or built in
+    pub default_enabled: bool,
+    /// Whether `enabled` comes from a runtime override
+    pub overridden: bool,
+}
+
+impl From<FlagState> for FlagResponse {
+    fn from(state: FlagState) -> Self {
+        Self {
+            name: state.flag,
+            enabled: state.enabled,
+            default_enabled: state.default,
+            overridden: state.overridden,
+        }
+    }
+}
+
+/// Response containing every feature flag
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagListResponse {
+    pub flags: Vec<FlagResponse>,
+}
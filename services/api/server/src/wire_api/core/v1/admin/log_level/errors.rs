@@ -0,0 +1,58 @@
+use uuid::Uuid;
+
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid log filter: {0}")]
+    InvalidFilter(String),
+
+    #[error("Failed to reload log filter: {0}")]
+    ReloadFailed(String),
+}
+
+impl From<crate::logging::LogFilterError> for Error {
+    fn from(e: crate::logging::LogFilterError) -> Self {
+        match e {
+            crate::logging::LogFilterError::Invalid(e) => {
+                Error::InvalidFilter(e)
+            }
+            crate::logging::LogFilterError::Reload(e) => {
+                Error::ReloadFailed(e.to_string())
+            }
+        }
+    }
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::InvalidFilter(e) => WireV1Error::bad_request(
+                "Invalid request parameters".to_string(),
+                vec![WireV1Detail {
+                    field: Some("filter".to_string()),
+                    code: "invalid_filter".to_string(),
+                    message: e,
+                    suggestion: "Use RUST_LOG syntax, e.g. \
+                                 `info,wire_api::auth=debug`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::ReloadFailed(e) => WireV1Error::internal_server_error(
+                "Failed to change the log filter".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "reload_failed".to_string(),
+                    message: format!("Failed to reload log filter: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
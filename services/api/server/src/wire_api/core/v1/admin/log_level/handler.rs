@@ -0,0 +1,82 @@
+use axum::Json;
+use axum::http::StatusCode;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{LogLevelResponse, SetLogLevelRequest};
+
+const HANDLER_NAME: &str = "admin_log_level";
+
+fn error_code(e: &errors::Error) -> &'static str {
+    match e {
+        errors::Error::InvalidFilter(_) => "invalid_filter",
+        errors::Error::ReloadFailed(_) => "reload_failed",
+    }
+}
+
+/// Get the log filter
+///
+/// Returns the active filter of the instance that serves the request.
+#[utoipa::path(
+    get,
+    path = "/admin/log-level",
+    responses(
+        (status = 200, description = "Active log filter", body = LogLevelResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_log_level_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<LogLevelResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let filter = ctx.state.log_filter.current().map_err(|e| {
+        let e = errors::Error::from(e);
+        recorder.record(error_code(&e), e)
+    })?;
+
+    Ok((StatusCode::OK, Json(LogLevelResponse { filter })))
+}
+
+/// Change the log filter
+///
+/// Replaces the `RUST_LOG` filter of the instance that serves the request
+/// until it restarts. Per-target directives are supported, e.g.
+/// `info,wire_api::auth=debug`.
+#[utoipa::path(
+    put,
+    path = "/admin/log-level",
+    request_body = SetLogLevelRequest,
+    responses(
+        (status = 200, description = "Log filter changed", body = LogLevelResponse),
+        (status = 400, description = "Invalid filter directives"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_log_level_set")]
+pub async fn set(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<SetLogLevelRequest>,
+) -> HandlerResult<(StatusCode, Json<LogLevelResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let previous = ctx.state.log_filter.current().unwrap_or_default();
+    let filter = ctx.state.log_filter.set(&payload.filter).map_err(|e| {
+        let e = errors::Error::from(e);
+        recorder.record(error_code(&e), e)
+    })?;
+
+    // Logged at warn so the change shows up whatever the new filter is
+    tracing::warn!(%previous, %filter, "Changed log filter");
+
+    Ok((StatusCode::OK, Json(LogLevelResponse { filter })))
+}
@@ -0,0 +1,12 @@
+use axum::Router;
+use axum::routing::get;
+
+mod errors;
+pub mod handler;
+pub mod models;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", get(handler::get).put(handler::set))
+        .with_state(state)
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Request payload for changing the log filter
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLogLevelRequest {
+    /// Filter directives in `RUST_LOG` syntax, a default level optionally
+    /// followed by per-target levels
+    #[validate(length(min = 1, max = 1000))]
+    #[schema(example = "info,wire_api::auth=debug")]
+    pub filter: String,
+}
+
+/// The active log filter of the instance that served the request
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLevelResponse {
+    #[schema(example = "info,wire_api::auth=debug")]
+    pub filter: String,
+}
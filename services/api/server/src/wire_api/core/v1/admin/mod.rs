@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use axum::Router;
+use axum::middleware::{from_extractor, from_fn_with_state};
+use utoipa::OpenApi;
+
+use crate::auth::csrf::RouteGroup;
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::health::{self, ComponentHealth};
+use crate::modules::{ApiModule, BoxFuture, HealthCheck, Mount};
+
+pub mod api_keys;
+pub mod files;
+pub mod flags;
+pub mod log_level;
+mod openapi;
+pub mod snapshots;
+
+/// Admin routes, restricted to callers with the admin role and, when
+/// configured, to allowed client networks.
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .nest("/api-keys", api_keys::get_routes(state.clone()))
+        .nest("/files", files::get_routes(state.clone()))
+        .nest("/flags", flags::get_routes(state.clone()))
+        .nest("/log-level", log_level::get_routes(state.clone()))
+        .nest("/snapshots", snapshots::get_routes(state.clone()))
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>())
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::middleware::authenticate_admin,
+        ))
+        .layer(from_fn_with_state(
+            (state.clone(), RouteGroup::Admin),
+            crate::auth::middleware::protect_csrf,
+        ))
+        .layer(from_fn_with_state(
+            state,
+            crate::auth::middleware::filter_admin_ip,
+        ))
+}
+
+/// API keys, feature flags, log levels, file uploads and snapshots, served
+/// on the internal listeners.
+pub struct Module;
+
+/// Uploads are staged on disk whatever their storage; without the staging
+/// directory no upload completes, but the rest of the API is unaffected.
+fn check_uploads(state: crate::AppState) -> BoxFuture<ComponentHealth> {
+    Box::pin(async move {
+        health::timed(Duration::from_secs(3), async {
+            state
+                .uploads
+                .check_staging()
+                .await
+                .map_err(|e| e.to_string())
+        })
+        .await
+    })
+}
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "admin"
+    }
+
+    fn mount(&self) -> Mount {
+        Mount::Internal
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Admin)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/admin", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+
+    fn health_checks(&self) -> Vec<HealthCheck> {
+        vec![HealthCheck {
+            component: "uploads",
+            critical: false,
+            check: check_uploads,
+        }]
+    }
+
+    /// Imports of complete uploads, and snapshots and their restores,
+    /// write to the database
+    fn on_shutdown(&self, state: &crate::AppState) -> Option<BoxFuture<()>> {
+        let uploads = state.uploads.clone();
+        let snapshots = state.snapshots.clone();
+        Some(Box::pin(async move {
+            tokio::join!(
+                uploads.wait_for_imports(),
+                snapshots.wait_for_tasks()
+            );
+        }))
+    }
+}
@@ -0,0 +1,31 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the admin routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::api_keys::handler::create,
+        super::api_keys::handler::list,
+        super::api_keys::handler::revoke,
+        super::files::handler::create,
+        super::files::handler::get,
+        super::files::handler::append,
+        super::flags::handler::list,
+        super::flags::handler::set,
+        super::flags::handler::clear,
+        super::log_level::handler::get,
+        super::log_level::handler::set,
+        super::snapshots::handler::create,
+        super::snapshots::handler::list,
+        super::snapshots::handler::get,
+        super::snapshots::handler::restore,
+    ),
+    tags(
+        (name = "admin", description = "API keys, feature flags, log levels, uploads and snapshots, restricted to the admin role")
+    )
+)]
+pub(super) struct ApiDoc;
@@ -0,0 +1,69 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Snapshot not found: {0}")]
+    NotFound(String),
+
+    #[error("A snapshot named `{0}` already exists")]
+    NameTaken(String),
+
+    #[error("Snapshot is {0}")]
+    NotRestorable(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        let message = self.to_string();
+        match self {
+            Error::NotFound(name) => WireV1Error::not_found(
+                "Snapshot not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("name".to_string()),
+                    code: "snapshot_not_found".to_string(),
+                    message: format!(
+                        "No snapshot of the tenant named `{name}`"
+                    ),
+                    suggestion: "Check the snapshot name and tenantId"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::NameTaken(_) => WireV1Error::conflict(
+                "Snapshot name taken".to_string(),
+                vec![WireV1Detail {
+                    field: Some("name".to_string()),
+                    code: "name_taken".to_string(),
+                    message,
+                    suggestion: "Choose another name for the snapshot"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::NotRestorable(_) => WireV1Error::conflict(
+                "Snapshot not restorable".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "not_restorable".to_string(),
+                    message,
+                    suggestion: "Restore a completed snapshot once its last \
+                                 restore finished"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Snapshot operation failed";
+}
@@ -0,0 +1,324 @@
+use axum::Json;
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use postgres_models::connection::{WithConnectionError, with_connection};
+use postgres_models::models::dataset_snapshots::{
+    DatasetSnapshot, NewDatasetSnapshot, restore_state, snapshot_status,
+};
+
+use crate::auth::tenant;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    CreateSnapshotRequest, SnapshotListResponse, SnapshotParams,
+    SnapshotResponse,
+};
+
+const HANDLER_NAME: &str = "admin_snapshots";
+
+/// Take a snapshot of a tenant's readings
+///
+/// Writes the tenant's readings, as of the start of the snapshot, as
+/// Parquet parts with a manifest to `SNAPSHOT_STORAGE`, in the background;
+/// poll `GET /admin/snapshots/{name}` until it is `completed`.
+#[utoipa::path(
+    post,
+    path = "/admin/snapshots",
+    request_body = CreateSnapshotRequest,
+    responses(
+        (status = 202, description = "Snapshot started", body = SnapshotResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 409, description = "The tenant has a snapshot of the name"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_snapshots_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateSnapshotRequest>,
+) -> HandlerResult<(StatusCode, Json<SnapshotResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let name = payload.name.clone();
+    let new_snapshot = NewDatasetSnapshot {
+        tenant_id: payload
+            .tenant_id
+            .unwrap_or_else(|| tenant::DEFAULT_TENANT.to_string()),
+        name: payload.name,
+    };
+    let snapshot = with_connection(&ctx.state.pool, |mut conn| async move {
+        DatasetSnapshot::create(new_snapshot, &mut conn).await
+    })
+    .await
+    .map_err(|e| match e {
+        WithConnectionError::Operation(DieselError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            _,
+        )) => recorder.record("name_taken", errors::Error::NameTaken(name)),
+        e => recorder.record_database::<errors::Error>(e),
+    })?;
+
+    tracing::info!(
+        snapshot = %snapshot.id,
+        name = %snapshot.name,
+        tenant = %snapshot.tenant_id,
+        "Taking snapshot"
+    );
+    ctx.state
+        .snapshots
+        .clone()
+        .spawn_take(ctx.state.pool.clone(), snapshot.clone());
+
+    Ok((StatusCode::ACCEPTED, Json(SnapshotResponse::from(snapshot))))
+}
+
+/// List a tenant's snapshots
+#[utoipa::path(
+    get,
+    path = "/admin/snapshots",
+    params(SnapshotParams),
+    responses(
+        (status = 200, description = "Snapshots, newest first", body = SnapshotListResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_snapshots_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+    Query(params): Query<SnapshotParams>,
+) -> HandlerResult<(StatusCode, Json<SnapshotListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = params.tenant_id();
+    let snapshots = with_connection(&ctx.state.pool, |mut conn| async move {
+        DatasetSnapshot::list(tenant_id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let snapshots = snapshots.into_iter().map(SnapshotResponse::from).collect();
+    Ok((StatusCode::OK, Json(SnapshotListResponse { snapshots })))
+}
+
+/// Get a snapshot
+#[utoipa::path(
+    get,
+    path = "/admin/snapshots/{name}",
+    params(
+        ("name" = String, Path, description = "Snapshot name"),
+        SnapshotParams,
+    ),
+    responses(
+        (status = 200, description = "The snapshot", body = SnapshotResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Snapshot not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_snapshots_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(name): Path<String>,
+    Query(params): Query<SnapshotParams>,
+) -> HandlerResult<(StatusCode, Json<SnapshotResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let snapshot = find(&ctx, &recorder, params.tenant_id(), name).await?;
+    Ok((StatusCode::OK, Json(SnapshotResponse::from(snapshot))))
+}
+
+/// Restore a snapshot into a scratch schema
+///
+/// Loads the readings of a completed snapshot, in the background, into the
+/// `energy_readings` table of the schema named in `restoredSchema` once
+/// `restoreStatus` is `restored`, replacing an earlier restore of it. The
+/// live readings are left as they are.
+#[utoipa::path(
+    post,
+    path = "/admin/snapshots/{name}/restore",
+    params(
+        ("name" = String, Path, description = "Snapshot name"),
+        SnapshotParams,
+    ),
+    responses(
+        (status = 202, description = "Restore started", body = SnapshotResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Snapshot not found"),
+        (status = 409, description = "The snapshot is not completed, or is being restored"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin",
+)]
+#[tracing::instrument(skip_all, name = "admin_snapshots_restore")]
+pub async fn restore(
+    ctx: HandlerCtx,
+    Path(name): Path<String>,
+    Query(params): Query<SnapshotParams>,
+) -> HandlerResult<(StatusCode, Json<SnapshotResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let snapshot = find(&ctx, &recorder, params.tenant_id(), name).await?;
+    let id = snapshot.id;
+    let claimed = with_connection(&ctx.state.pool, |mut conn| async move {
+        DatasetSnapshot::restoring(id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+    let Some(snapshot) = claimed else {
+        return Err(recorder.record(
+            "not_restorable",
+            errors::Error::NotRestorable(
+                match snapshot.restore_status.as_deref() {
+                    Some(restore_state::RESTORING)
+                        if snapshot.status == snapshot_status::COMPLETED =>
+                    {
+                        "being restored".to_string()
+                    }
+                    _ => snapshot.status,
+                },
+            ),
+        ));
+    };
+
+    tracing::info!(
+        snapshot = %snapshot.id,
+        name = %snapshot.name,
+        tenant = %snapshot.tenant_id,
+        "Restoring snapshot"
+    );
+    ctx.state
+        .snapshots
+        .clone()
+        .spawn_restore(ctx.state.pool.clone(), snapshot.clone());
+
+    Ok((StatusCode::ACCEPTED, Json(SnapshotResponse::from(snapshot))))
+}
+
+async fn find(
+    ctx: &HandlerCtx,
+    recorder: &ErrorRecorder<'_>,
+    tenant_id: &str,
+    name: String,
+) -> HandlerResult<DatasetSnapshot> {
+    let snapshot_name = name.as_str();
+    with_connection(&ctx.state.pool, |mut conn| async move {
+        DatasetSnapshot::find_by_name(tenant_id, snapshot_name, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::Method;
+    use chrono::{TimeZone, Utc};
+    use diesel::sql_types::BigInt;
+    use diesel_async::RunQueryDsl;
+    use serde_json::{Value, json};
+
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[derive(diesel::QueryableByName)]
+    struct Count {
+        #[diesel(sql_type = BigInt)]
+        count: i64,
+    }
+
+    async fn wait_for(
+        app: &TestApp,
+        url: &str,
+        field: &str,
+        running: &str,
+    ) -> Value {
+        let mut snapshot = Value::Null;
+        for _ in 0..50 {
+            snapshot = app.admin(Method::GET, url).await.json::<Value>();
+            if snapshot[field] != running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        snapshot
+    }
+
+    #[tokio::test]
+    async fn test_takes_and_restores_a_snapshot() {
+        let dir = std::env::temp_dir()
+            .join(format!("wire-snapshots-{}", uuid::Uuid::new_v4().simple()));
+        let Some(app) =
+            TestApp::with_vars(&[("SNAPSHOT_DIR", dir.to_str().unwrap())])
+                .await
+        else {
+            return;
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2025, 6, 1, hour, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(0), "1"), (at(1), "2.5")],
+        )
+        .await;
+        app.seed_readings("other", None, &[(at(0), "100")]).await;
+
+        app.admin(
+            Method::POST,
+            "/api/wire/v1/admin/snapshots/before-import/restore",
+        )
+        .await
+        .assert_status_not_found();
+        let created = app
+            .admin(Method::POST, "/api/wire/v1/admin/snapshots")
+            .json(&json!({ "name": "before-import" }))
+            .await;
+        created.assert_status(axum::http::StatusCode::ACCEPTED);
+        app.admin(Method::POST, "/api/wire/v1/admin/snapshots")
+            .json(&json!({ "name": "before-import" }))
+            .await
+            .assert_status(axum::http::StatusCode::CONFLICT);
+
+        let url = "/api/wire/v1/admin/snapshots/before-import";
+        let snapshot = wait_for(&app, url, "status", "taking").await;
+        assert_eq!(snapshot["status"], "completed", "{snapshot}");
+        assert_eq!(snapshot["readings"], 2);
+        assert!(
+            snapshot["location"]
+                .as_str()
+                .unwrap()
+                .ends_with("manifest.json")
+        );
+
+        app.admin(Method::POST, &format!("{url}/restore"))
+            .await
+            .assert_status(axum::http::StatusCode::ACCEPTED);
+        let snapshot = wait_for(&app, url, "restoreStatus", "restoring").await;
+        assert_eq!(snapshot["restoreStatus"], "restored", "{snapshot}");
+
+        let schema = snapshot["restoredSchema"].as_str().unwrap().to_string();
+        let mut conn = app.state.pool.get().await.unwrap();
+        let restored = diesel::sql_query(format!(
+            "SELECT COUNT(*) AS count FROM {schema}.energy_readings"
+        ))
+        .get_result::<Count>(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(restored.count, 2);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,14 @@
+use axum::Router;
+use axum::routing::{get, post};
+
+mod errors;
+pub mod handler;
+pub mod models;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", post(handler::create).get(handler::list))
+        .route("/{name}", get(handler::get))
+        .route("/{name}/restore", post(handler::restore))
+        .with_state(state)
+}
@@ -0,0 +1,98 @@
+use postgres_models::models::dataset_snapshots::DatasetSnapshot;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::auth::tenant;
+use crate::snapshots;
+use crate::wire_api::core::v1::admin::api_keys::models::validate_tenant_id;
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    snapshots::validate_name(name).map_err(|message| {
+        ValidationError::new("invalid_name").with_message(message.into())
+    })
+}
+
+/// Request payload for taking a snapshot
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSnapshotRequest {
+    /// Name of the snapshot, unique among the tenant's
+    #[validate(custom(function = "validate_name"))]
+    #[schema(example = "before-2025-06-import")]
+    pub name: String,
+
+    /// Tenant whose readings are snapshotted (defaults to `default`)
+    #[validate(custom(function = "validate_tenant_id"))]
+    #[schema(example = "acme")]
+    pub tenant_id: Option<String>,
+}
+
+/// Query parameters naming the tenant of snapshots
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct SnapshotParams {
+    /// Tenant of the snapshots (defaults to `default`)
+    pub tenant_id: Option<String>,
+}
+
+impl SnapshotParams {
+    pub fn tenant_id(&self) -> &str {
+        self.tenant_id.as_deref().unwrap_or(tenant::DEFAULT_TENANT)
+    }
+}
+
+/// A snapshot and its last restore
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotResponse {
+    pub id: uuid::Uuid,
+    #[schema(example = "default")]
+    pub tenant_id: String,
+    pub name: String,
+    /// `taking`, `completed` or `failed`
+    #[schema(example = "completed")]
+    pub status: String,
+    /// Where the manifest of the snapshot is stored, once completed
+    pub location: Option<String>,
+    /// Readings in the snapshot, once completed
+    pub readings: Option<i64>,
+    /// Why taking the snapshot failed
+    pub error: Option<String>,
+    /// `restoring`, `restored` or `failed`, absent until first restored
+    pub restore_status: Option<String>,
+    /// Schema whose `energy_readings` table holds the restored readings
+    #[schema(example = "snapshot_0d6f1c1e8a9b4d7c9e2f3a4b5c6d7e8f")]
+    pub restored_schema: Option<String>,
+    /// Why the last restore failed
+    pub restore_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<DatasetSnapshot> for SnapshotResponse {
+    fn from(snapshot: DatasetSnapshot) -> Self {
+        Self {
+            id: snapshot.id,
+            tenant_id: snapshot.tenant_id,
+            name: snapshot.name,
+            status: snapshot.status,
+            location: snapshot.location,
+            readings: snapshot.readings,
+            error: snapshot.error,
+            restore_status: snapshot.restore_status,
+            restored_schema: snapshot.restored_schema,
+            restore_error: snapshot.restore_error,
+            created_at: snapshot.created_at,
+            updated_at: snapshot.updated_at,
+        }
+    }
+}
+
+/// Response listing snapshots, newest first
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotListResponse {
+    pub snapshots: Vec<SnapshotResponse>,
+}
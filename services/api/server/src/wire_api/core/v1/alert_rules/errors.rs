@@ -0,0 +1,65 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Alert rule not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Plant not found: {0}")]
+    PlantNotFound(Uuid),
+
+    #[error("Notification channel not found: {0}")]
+    ChannelNotFound(Uuid),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Alert rule not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "alert_rule_not_found".to_string(),
+                    message: format!("No alert rule exists with id {id}"),
+                    suggestion: "Check the alert rule id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::PlantNotFound(id) => WireV1Error::not_found(
+                "Plant not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("plantId".to_string()),
+                    code: "plant_not_found".to_string(),
+                    message: format!("No plant exists with id {id}"),
+                    suggestion: "Check the plant id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::ChannelNotFound(id) => WireV1Error::not_found(
+                "Notification channel not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("channelId".to_string()),
+                    code: "notification_channel_not_found".to_string(),
+                    message: format!(
+                        "No notification channel exists with id {id}"
+                    ),
+                    suggestion: "Check the notification channel id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Alert rule operation failed";
+}
@@ -0,0 +1,326 @@
+use std::str::FromStr;
+
+use axum::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use diesel_async::AsyncConnection;
+use diesel_async::AsyncPgConnection;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use postgres_models::connection::with_connection;
+use postgres_models::models::alerts::{
+    Alert, AlertRule, NewAlertRule, UpdateAlertRule,
+};
+use postgres_models::models::notifications::NotificationChannel;
+use postgres_models::models::plants::Plant;
+use uuid::Uuid;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    AlertRuleListResponse, AlertRuleResponse, CreateAlertRuleRequest,
+    UpdateAlertRuleRequest,
+};
+
+const HANDLER_NAME: &str = "alert_rules";
+
+fn threshold(value: f64) -> BigDecimal {
+    // Validated to a finite range, so always a decimal
+    BigDecimal::from_str(&format!("{value:.4}")).unwrap_or_default()
+}
+
+/// Check that the plant and channel a rule refers to exist in the tenant.
+async fn check_references(
+    tenant: &str,
+    plant_id: Option<Uuid>,
+    channel_id: Option<Uuid>,
+    conn: &mut AsyncPgConnection,
+) -> Result<Option<errors::Error>, diesel::result::Error> {
+    if let Some(plant_id) = plant_id
+        && Plant::find(tenant, plant_id, conn).await?.is_none()
+    {
+        return Ok(Some(errors::Error::PlantNotFound(plant_id)));
+    }
+    if let Some(channel_id) = channel_id
+        && NotificationChannel::find(tenant, channel_id, conn)
+            .await?
+            .is_none()
+    {
+        return Ok(Some(errors::Error::ChannelNotFound(channel_id)));
+    }
+    Ok(None)
+}
+
+fn record_error(recorder: &ErrorRecorder<'_>, e: errors::Error) -> WireV1Error {
+    match e {
+        errors::Error::PlantNotFound(_) => {
+            recorder.record("plant_not_found", e)
+        }
+        errors::Error::ChannelNotFound(_) => {
+            recorder.record("channel_not_found", e)
+        }
+        e => recorder.record("not_found", e),
+    }
+}
+
+/// Create an alert rule
+///
+/// The evaluator checks enabled rules every
+/// `ALERT_EVALUATION_INTERVAL_SECS` and fires an alert when the metric
+/// starts breaching the threshold, notifying the rule's channel if it has
+/// one.
+#[utoipa::path(
+    post,
+    path = "/alert-rules",
+    request_body = CreateAlertRuleRequest,
+    responses(
+        (status = 201, description = "Rule created", body = AlertRuleResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Plant or notification channel not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alert_rules_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateAlertRuleRequest>,
+) -> HandlerResult<(StatusCode, Json<AlertRuleResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let new_rule = NewAlertRule {
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        name: payload.name,
+        plant_id: payload.plant_id,
+        metric: payload.metric.as_str().to_string(),
+        comparison: payload.comparison.as_str().to_string(),
+        threshold: threshold(payload.threshold),
+        window_minutes: payload.window_minutes,
+        enabled: payload.enabled.unwrap_or(true),
+        channel_id: payload.channel_id,
+    };
+
+    let rule = with_connection(&ctx.state.pool, |mut conn| async move {
+        if let Some(e) = check_references(
+            &new_rule.tenant_id,
+            new_rule.plant_id,
+            new_rule.channel_id,
+            &mut conn,
+        )
+        .await?
+        {
+            return Ok(Err(e));
+        }
+        AlertRule::create(new_rule, &mut conn).await.map(Ok)
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .map_err(|e| record_error(&recorder, e))?;
+
+    Ok((StatusCode::CREATED, Json(AlertRuleResponse::from(rule))))
+}
+
+/// List alert rules
+///
+/// Returns the caller's tenant's rules ordered by name.
+#[utoipa::path(
+    get,
+    path = "/alert-rules",
+    responses(
+        (status = 200, description = "Alert rules", body = AlertRuleListResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alert_rules_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<AlertRuleListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let rules = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            AlertRule::list(tenant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let rules = rules.into_iter().map(AlertRuleResponse::from).collect();
+    Ok((StatusCode::OK, Json(AlertRuleListResponse { rules })))
+}
+
+/// Get an alert rule by id
+#[utoipa::path(
+    get,
+    path = "/alert-rules/{id}",
+    params(("id" = Uuid, Path, description = "Alert rule id")),
+    responses(
+        (status = 200, description = "Alert rule", body = AlertRuleResponse),
+        (status = 404, description = "Alert rule not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alert_rules_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<AlertRuleResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let rule = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            AlertRule::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    Ok((StatusCode::OK, Json(AlertRuleResponse::from(rule))))
+}
+
+/// Update an alert rule
+///
+/// Changes apply from the rule's next evaluation. Disabling a firing rule
+/// resolves its open alert.
+#[utoipa::path(
+    put,
+    path = "/alert-rules/{id}",
+    params(("id" = Uuid, Path, description = "Alert rule id")),
+    request_body = UpdateAlertRuleRequest,
+    responses(
+        (status = 200, description = "Updated rule", body = AlertRuleResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Alert rule, plant or notification channel not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alert_rules_update")]
+pub async fn update(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    ValidatedPayload(payload): ValidatedPayload<UpdateAlertRuleRequest>,
+) -> HandlerResult<(StatusCode, Json<AlertRuleResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let rule = with_connection(&ctx.state.pool, |mut conn| async move {
+        AlertRule::find(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    // Disabled rules are not evaluated, so nothing would resolve the alert
+    let disabling = rule.firing && payload.enabled == Some(false);
+    let changes = UpdateAlertRule {
+        name: payload.name,
+        plant_id: payload.plant_id,
+        metric: payload.metric.map(|metric| metric.as_str().to_string()),
+        comparison: payload
+            .comparison
+            .map(|comparison| comparison.as_str().to_string()),
+        threshold: payload.threshold.map(threshold),
+        window_minutes: payload.window_minutes,
+        enabled: payload.enabled,
+        firing: disabling.then_some(false),
+        channel_id: payload.channel_id,
+    };
+    // An empty UPDATE is an error in Diesel
+    if changes.name.is_none()
+        && changes.plant_id.is_none()
+        && changes.metric.is_none()
+        && changes.comparison.is_none()
+        && changes.threshold.is_none()
+        && changes.window_minutes.is_none()
+        && changes.enabled.is_none()
+        && changes.channel_id.is_none()
+    {
+        return Ok((StatusCode::OK, Json(AlertRuleResponse::from(rule))));
+    }
+
+    let updated = with_connection(&ctx.state.pool, |mut conn| async move {
+        conn.transaction::<_, diesel::result::Error, _>(move |conn| {
+            async move {
+                if let Some(e) = check_references(
+                    tenant_id,
+                    changes.plant_id.flatten(),
+                    changes.channel_id.flatten(),
+                    conn,
+                )
+                .await?
+                {
+                    return Ok(Err(e));
+                }
+                let Some(rule) =
+                    AlertRule::update(tenant_id, id, changes, conn).await?
+                else {
+                    return Ok(Err(errors::Error::NotFound(id)));
+                };
+                if disabling {
+                    Alert::resolve_open(id, Utc::now(), conn).await?;
+                }
+                Ok(Ok(rule))
+            }
+            .scope_boxed()
+        })
+        .await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let rule = updated.map_err(|e| record_error(&recorder, e))?;
+
+    Ok((StatusCode::OK, Json(AlertRuleResponse::from(rule))))
+}
+
+/// Delete an alert rule
+///
+/// Its alerts are deleted with it.
+#[utoipa::path(
+    delete,
+    path = "/alert-rules/{id}",
+    params(("id" = Uuid, Path, description = "Alert rule id")),
+    responses(
+        (status = 204, description = "Rule deleted"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Alert rule not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alert_rules_delete")]
+pub async fn delete(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let deleted = with_connection(&ctx.state.pool, |mut conn| async move {
+        AlertRule::delete(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    if deleted == 0 {
+        return Err(recorder.record("not_found", errors::Error::NotFound(id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
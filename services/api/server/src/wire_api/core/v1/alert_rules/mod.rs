@@ -0,0 +1,26 @@
+use axum::Router;
+use axum::middleware::from_extractor;
+use axum::routing::get;
+
+use crate::auth::{RequirePermission, permission};
+
+mod errors;
+pub mod handler;
+pub mod models;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    let manage = Router::new()
+        .route("/", axum::routing::post(handler::create))
+        .route(
+            "/{id}",
+            axum::routing::put(handler::update).delete(handler::delete),
+        )
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>());
+
+    Router::new()
+        .route("/", get(handler::list))
+        .route("/{id}", get(handler::get))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .merge(manage)
+        .with_state(state)
+}
@@ -0,0 +1,231 @@
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Utc};
+use postgres_models::models::alerts::{AlertRule, comparison, metric};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::shared::extractors::merge_patch;
+
+/// Largest accepted threshold magnitude.
+pub const MAX_THRESHOLD: f64 = 1_000_000_000_000.0;
+/// Longest window of readings, 31 days.
+pub const MAX_WINDOW_MINUTES: i32 = 44_640;
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("blank")
+            .with_message("Rule names cannot be blank".into()));
+    }
+    Ok(())
+}
+
+/// Metric of a rule, computed over the readings of its window
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Sum of the readings, 0 without readings
+    TotalKwh,
+    AverageKwh,
+    MaxKwh,
+    MinKwh,
+    /// Number of readings, e.g. `lt 1` to alert when readings stop
+    ReadingCount,
+}
+
+impl AlertMetric {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertMetric::TotalKwh => metric::TOTAL_KWH,
+            AlertMetric::AverageKwh => metric::AVERAGE_KWH,
+            AlertMetric::MaxKwh => metric::MAX_KWH,
+            AlertMetric::MinKwh => metric::MIN_KWH,
+            AlertMetric::ReadingCount => metric::READING_COUNT,
+        }
+    }
+}
+
+/// How a metric is compared with the threshold
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertComparison {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl AlertComparison {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertComparison::Gt => comparison::GT,
+            AlertComparison::Gte => comparison::GTE,
+            AlertComparison::Lt => comparison::LT,
+            AlertComparison::Lte => comparison::LTE,
+        }
+    }
+}
+
+/// Request payload for creating an alert rule
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAlertRuleRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 200,
+            message = "Rule names are 1-200 characters"
+        ),
+        custom(function = "validate_name")
+    )]
+    #[schema(example = "Sofia Solar Park output low")]
+    pub name: String,
+
+    /// Only count readings of this plant, all readings when omitted
+    pub plant_id: Option<uuid::Uuid>,
+
+    pub metric: AlertMetric,
+
+    pub comparison: AlertComparison,
+
+    /// Compared with the metric, stored with 4 decimal places
+    #[validate(range(
+        min = -MAX_THRESHOLD,
+        max = MAX_THRESHOLD,
+        message = "Thresholds are between -1e12 and 1e12"
+    ))]
+    #[schema(example = 50.0)]
+    pub threshold: f64,
+
+    /// Length of the window of readings, ending at evaluation time
+    #[validate(range(
+        min = 1,
+        max = MAX_WINDOW_MINUTES,
+        message = "Windows are 1 minute to 31 days"
+    ))]
+    #[schema(example = 60)]
+    pub window_minutes: i32,
+
+    /// Notification channel of the rule's alerts; without one, alerts only
+    /// emit the `threshold_breached` webhook event
+    pub channel_id: Option<uuid::Uuid>,
+
+    /// Whether the rule is evaluated (defaults to true)
+    pub enabled: Option<bool>,
+}
+
+/// Request payload for updating an alert rule; omitted fields are unchanged
+/// and `null` removes `plantId` or `channelId`
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateAlertRuleRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 200,
+            message = "Rule names are 1-200 characters"
+        ),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+
+    #[serde(default, deserialize_with = "merge_patch::nullable")]
+    #[schema(value_type = Option<uuid::Uuid>)]
+    pub plant_id: Option<Option<uuid::Uuid>>,
+
+    pub metric: Option<AlertMetric>,
+
+    pub comparison: Option<AlertComparison>,
+
+    #[validate(range(
+        min = -MAX_THRESHOLD,
+        max = MAX_THRESHOLD,
+        message = "Thresholds are between -1e12 and 1e12"
+    ))]
+    pub threshold: Option<f64>,
+
+    #[validate(range(
+        min = 1,
+        max = MAX_WINDOW_MINUTES,
+        message = "Windows are 1 minute to 31 days"
+    ))]
+    pub window_minutes: Option<i32>,
+
+    #[serde(default, deserialize_with = "merge_patch::nullable")]
+    #[schema(value_type = Option<uuid::Uuid>)]
+    pub channel_id: Option<Option<uuid::Uuid>>,
+
+    /// Disabling a firing rule resolves its open alert
+    pub enabled: Option<bool>,
+}
+
+/// A threshold rule on the tenant's readings
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRuleResponse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub plant_id: Option<uuid::Uuid>,
+    #[schema(example = "total_kwh")]
+    pub metric: String,
+    #[schema(example = "lt")]
+    pub comparison: String,
+    #[schema(example = 50.0)]
+    pub threshold: f64,
+    #[schema(example = 60)]
+    pub window_minutes: i32,
+    pub channel_id: Option<uuid::Uuid>,
+    pub enabled: bool,
+    /// Whether the rule is breached, as of its last evaluation
+    pub firing: bool,
+    pub evaluated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<AlertRule> for AlertRuleResponse {
+    fn from(rule: AlertRule) -> Self {
+        Self {
+            id: rule.id,
+            name: rule.name,
+            plant_id: rule.plant_id,
+            metric: rule.metric,
+            comparison: rule.comparison,
+            threshold: rule.threshold.to_f64().unwrap_or_default(),
+            window_minutes: rule.window_minutes,
+            channel_id: rule.channel_id,
+            enabled: rule.enabled,
+            firing: rule.firing,
+            evaluated_at: rule.evaluated_at,
+            created_at: rule.created_at,
+            updated_at: rule.updated_at,
+        }
+    }
+}
+
+/// Response containing alert rules
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRuleListResponse {
+    pub rules: Vec<AlertRuleResponse>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_distinguishes_null_from_omitted() {
+        let omitted: UpdateAlertRuleRequest =
+            serde_json::from_str(r#"{"name": "Output low"}"#).unwrap();
+        assert_eq!(omitted.channel_id, None);
+
+        let removed: UpdateAlertRuleRequest =
+            serde_json::from_str(r#"{"channelId": null}"#).unwrap();
+        assert_eq!(removed.channel_id, Some(None));
+    }
+}
@@ -0,0 +1,67 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Alert not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("{0}")]
+    InvalidCursor(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Alert not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "alert_not_found".to_string(),
+                    message: format!("No alert exists with id {id}"),
+                    suggestion: "Check the alert id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidQuery(message) => WireV1Error::bad_request(
+                "Invalid query parameters".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_query".to_string(),
+                    message,
+                    suggestion:
+                        "Send `ruleId` as a rule id, `open` as a boolean and \
+                                 `limit` as a number"
+                            .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidCursor(message) => WireV1Error::bad_request(
+                "Invalid cursor".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_cursor".to_string(),
+                    message,
+                    suggestion: "Send the `nextCursor` or `prevCursor` of a \
+                                 page as `after` or `before`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Alert operation failed";
+}
@@ -0,0 +1,160 @@
+use axum::Json;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{OriginalUri, Path, Query};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use postgres_models::connection::with_connection;
+use postgres_models::models::alerts::Alert;
+use uuid::Uuid;
+
+use crate::shared::pagination::{self, Page};
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{AlertListResponse, AlertParams, AlertResponse};
+
+const HANDLER_NAME: &str = "alerts";
+
+/// List alerts
+///
+/// Returns the caller's tenant's latest alerts, newest first. Pages are
+/// linked with `Link: <...>; rel="next"` and `rel="prev"` headers.
+#[utoipa::path(
+    get,
+    path = "/alerts",
+    params(AlertParams),
+    responses(
+        (status = 200, description = "Alerts", body = AlertListResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alerts_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+    OriginalUri(uri): OriginalUri,
+    params: Result<Query<AlertParams>, QueryRejection>,
+) -> HandlerResult<Response> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+    let keyset =
+        pagination::keyset(params.after.as_deref(), params.before.as_deref())
+            .map_err(|e| {
+            recorder.record("invalid_cursor", errors::Error::InvalidCursor(e))
+        })?;
+
+    let limit = params.limit();
+    let alerts = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Alert::list(
+                tenant_id,
+                params.rule_id,
+                params.open,
+                keyset,
+                limit + 1,
+                &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let page = Page::new(alerts, limit as usize, keyset, |alert| {
+        pagination::encode_cursor(alert.fired_at, alert.id)
+    });
+    let link = page.link_header(&uri);
+    let body = AlertListResponse {
+        alerts: page.items.into_iter().map(AlertResponse::from).collect(),
+        next_cursor: page.next,
+        prev_cursor: page.prev,
+    };
+    let mut response = (StatusCode::OK, Json(body)).into_response();
+    if let Some(link) = link {
+        response.headers_mut().insert(header::LINK, link);
+    }
+    Ok(response)
+}
+
+/// Get an alert by id
+#[utoipa::path(
+    get,
+    path = "/alerts/{id}",
+    params(("id" = Uuid, Path, description = "Alert id")),
+    responses(
+        (status = 200, description = "Alert", body = AlertResponse),
+        (status = 404, description = "Alert not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alerts_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<AlertResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let alert = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Alert::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    Ok((StatusCode::OK, Json(AlertResponse::from(alert))))
+}
+
+/// Acknowledge an alert
+///
+/// Records who acknowledged the alert and when. Acknowledging an alert
+/// again leaves it unchanged.
+#[utoipa::path(
+    post,
+    path = "/alerts/{id}/acknowledge",
+    params(("id" = Uuid, Path, description = "Alert id")),
+    responses(
+        (status = 200, description = "Acknowledged alert", body = AlertResponse),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Alert not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "alerts",
+)]
+#[tracing::instrument(skip_all, name = "alerts_acknowledge")]
+pub async fn acknowledge(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<AlertResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let by = ctx.caller.as_ref().map(|caller| caller.name.as_str());
+    let alert = with_connection(&ctx.state.pool, |mut conn| async move {
+        match Alert::acknowledge(tenant_id, id, by, Utc::now(), &mut conn)
+            .await?
+        {
+            Some(alert) => Ok(Some(alert)),
+            None => Alert::find(tenant_id, id, &mut conn).await,
+        }
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    Ok((StatusCode::OK, Json(AlertResponse::from(alert))))
+}
@@ -0,0 +1,50 @@
+use axum::Router;
+use axum::middleware::from_extractor;
+use axum::routing::{get, post};
+use utoipa::OpenApi;
+
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+use crate::wire_api::core::v1::alert_rules;
+
+mod errors;
+pub mod handler;
+pub mod models;
+mod openapi;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    let manage = Router::new()
+        .route("/{id}/acknowledge", post(handler::acknowledge))
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>());
+
+    Router::new()
+        .route("/", get(handler::list))
+        .route("/{id}", get(handler::get))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .merge(manage)
+        .with_state(state)
+}
+
+/// Threshold rules on readings and the alerts they fire.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "alerts"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Alerts)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new()
+            .nest("/alert-rules", alert_rules::get_routes(state.clone()))
+            .nest("/alerts", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
@@ -0,0 +1,94 @@
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Utc};
+use postgres_models::models::alerts::Alert;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::shared::extractors::query;
+
+/// Alerts returned when `limit` is omitted.
+pub const DEFAULT_LIMIT: i64 = 100;
+/// Most alerts returned by one request.
+pub const MAX_LIMIT: i64 = 1000;
+
+/// Query parameters of the alert list
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct AlertParams {
+    /// Only the alerts of this rule
+    pub rule_id: Option<uuid::Uuid>,
+    /// Only unresolved alerts
+    #[serde(default, deserialize_with = "query::flag")]
+    pub open: bool,
+    /// Most alerts to return, 100 by default and at most 1000
+    pub limit: Option<i64>,
+    /// Cursor of the alert after which the page starts, `nextCursor` of the
+    /// previous page
+    pub after: Option<String>,
+    /// Cursor of the alert before which the page ends, `prevCursor` of the
+    /// next page
+    pub before: Option<String>,
+}
+
+impl AlertParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// A breach of an alert rule
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertResponse {
+    pub id: uuid::Uuid,
+    pub rule_id: uuid::Uuid,
+    /// The metric's value when the rule fired
+    #[schema(example = 12.5)]
+    pub value: f64,
+    /// The rule's threshold when it fired
+    #[schema(example = 50.0)]
+    pub threshold: f64,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub fired_at: DateTime<Utc>,
+    /// Set once the metric no longer breaches the threshold
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// Name of the API key that acknowledged the alert
+    pub acknowledged_by: Option<String>,
+    /// Notification queued for the rule's channel, see
+    /// `GET /notification-channels/{id}/notifications` for its status
+    pub notification_id: Option<uuid::Uuid>,
+}
+
+impl From<Alert> for AlertResponse {
+    fn from(alert: Alert) -> Self {
+        Self {
+            id: alert.id,
+            rule_id: alert.rule_id,
+            value: alert.value.to_f64().unwrap_or_default(),
+            threshold: alert.threshold.to_f64().unwrap_or_default(),
+            window_start: alert.window_start,
+            window_end: alert.window_end,
+            fired_at: alert.fired_at,
+            resolved_at: alert.resolved_at,
+            acknowledged_at: alert.acknowledged_at,
+            acknowledged_by: alert.acknowledged_by,
+            notification_id: alert.notification_id,
+        }
+    }
+}
+
+/// Response containing alerts, latest first
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertListResponse {
+    pub alerts: Vec<AlertResponse>,
+    /// `after` of the next page, absent on the last one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// `before` of the previous page, absent on the first one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
@@ -0,0 +1,24 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the alert rule and alert routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::alert_rules::handler::create,
+        super::alert_rules::handler::list,
+        super::alert_rules::handler::get,
+        super::alert_rules::handler::update,
+        super::alert_rules::handler::delete,
+        super::handler::list,
+        super::handler::get,
+        super::handler::acknowledge,
+    ),
+    tags(
+        (name = "alerts", description = "Threshold rules on readings and the alerts they fire")
+    )
+)]
+pub(super) struct ApiDoc;
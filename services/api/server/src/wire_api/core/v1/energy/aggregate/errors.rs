@@ -1,39 +1,45 @@
 use uuid::Uuid;
 
+use crate::wire_api::handler_error::DomainError;
 use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
 
 pub type HandlerResult<T> = Result<T, WireV1Error>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Database error: {0}")]
-    DatabaseError(#[from] diesel::result::Error),
+    #[error("Invalid query parameters: {0}")]
+    InvalidQuery(String),
 
-    #[error("Failed to get database connection: {0}")]
-    PoolError(String),
+    #[error("Failed to serialize the response: {0}")]
+    Serialization(#[from] serde_json::Error),
 }
 
-impl Error {
-    pub fn to_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
         match self {
-            Error::DatabaseError(e) => WireV1Error::internal_server_error(
-                "Aggregation query failed".to_string(),
+            Error::Serialization(e) => WireV1Error::internal_server_error(
+                "Aggregation response failed".to_string(),
                 vec![WireV1Detail {
                     field: None,
-                    code: "database_error".to_string(),
-                    message: format!("Database error: {e}"),
+                    code: "serialization_error".to_string(),
+                    message: format!("Failed to serialize the response: {e}"),
                     suggestion: "Please try again later".to_string(),
                     documentation: String::new(),
                 }],
                 request_id.to_string(),
             ),
-            Error::PoolError(e) => WireV1Error::service_unavailable(
-                "Service temporarily unavailable".to_string(),
+            Error::InvalidQuery(message) => WireV1Error::bad_request(
+                "Invalid query parameters".to_string(),
                 vec![WireV1Detail {
                     field: None,
-                    code: "pool_error".to_string(),
-                    message: format!("Failed to get database connection: {e}"),
-                    suggestion: "Please try again later".to_string(),
+                    code: "invalid_query".to_string(),
+                    message,
+                    suggestion: "Send `include_weather`, `explain`, \
+                                 `fill_gaps`, `cumulative` and `numbers` as \
+                                 true or false, `cumulative` without \
+                                 `include_weather`, and `decimals` from 0 \
+                                 to 4"
+                        .to_string(),
                     documentation: String::new(),
                 }],
                 request_id.to_string(),
@@ -42,8 +48,6 @@ impl Error {
     }
 }
 
-impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
-    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
-        self.to_wire_v1_error(request_id)
-    }
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Aggregation query failed";
 }
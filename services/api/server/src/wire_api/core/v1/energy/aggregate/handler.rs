@@ -1,135 +1,780 @@
-use axum::Json;
-use axum::extract::State;
-use axum::http::StatusCode;
-use deadpool_redis::redis::AsyncCommands;
-use postgres_models::connection::{WithConnectionError, with_connection};
-use postgres_models::models::energy_readings::EnergyReading;
-use postgres_models::models::query_history::{NewQueryHistory, QueryHistory};
-
-use crate::AppState;
-use crate::shared::extractors::request_id::RequestId;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::Query;
+use axum::extract::rejection::QueryRejection;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use postgres_models::connection::WithConnectionError;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, EnergyReading,
+};
+use postgres_models::models::query_history::NewQueryHistory;
+use postgres_models::models::weather::WeatherObservation;
+use redis_cache::connection::Pool;
+
+use crate::admission::{Rejection, aggregation_cost};
+use crate::auth::{RequirePermission, TenantContext, permission};
+use crate::circuit_breaker::{CircuitBreaker, Dependency, Open};
+use crate::events::{AggregateSource, DomainEvent};
+use crate::flags::Flag;
+use crate::repository::{EnergyReadingRepository, RepositoryResult};
+use crate::shared::cache_key::CacheKey;
 use crate::shared::extractors::validations::ValidatedPayload;
-use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::shared::json::{DecimalFormat, stream_array_field};
+use crate::shared::response_cache;
+use crate::single_flight::Outcome;
+use crate::wire_api::handler_ctx::HandlerCtx;
+use crate::wire_api::wire_error_v1::WireV1Error;
 
 use super::errors::{self, HandlerResult};
-use super::models::{AggregateDataPoint, AggregateRequest, AggregateResponse};
+use super::models::{
+    AggregateParams, AggregateRequest, AggregateResponse,
+    ColumnarAggregateResponse, Layout, Metric, data_points,
+};
 
 const HANDLER_NAME: &str = "energy_aggregate";
-const CACHE_TTL_SECONDS: u64 = 300; // 5 minutes
+const CACHE_TTL: Duration = Duration::from_secs(300);
+/// Version of the cached [`AggregateResponse`]s, to bump when it changes.
+const CACHE_VERSION: u32 = 2;
+/// Most data points of a cached response; larger ones, like multi-year
+/// hourly aggregations, are streamed instead of serialized whole.
+const CACHE_MAX_POINTS: usize = 10_000;
 
-fn cache_key(payload: &AggregateRequest) -> String {
-    format!(
-        "energy:aggregate:{}:{}:{}",
-        payload.aggregation_type,
-        payload
-            .date_from
-            .map_or("none".to_string(), |d| d.to_rfc3339()),
-        payload
-            .date_to
-            .map_or("none".to_string(), |d| d.to_rfc3339()),
-    )
+/// Why the readings of an aggregation could not be fetched.
+enum Failure {
+    Rejected(Rejection),
+    Unavailable(Open),
+    Database(WithConnectionError<diesel::result::Error>),
+}
+
+impl From<WithConnectionError<diesel::result::Error>> for Failure {
+    fn from(e: WithConnectionError<diesel::result::Error>) -> Self {
+        Failure::Database(e)
+    }
+}
+
+/// Cache key of an aggregation with its totals in `format`, per tenant.
+pub fn cache_key(
+    tenant: &TenantContext,
+    payload: &AggregateRequest,
+    params: &AggregateParams,
+    format: &DecimalFormat,
+) -> String {
+    let key = CacheKey::new(tenant, "energy", "aggregate")
+        .version(CACHE_VERSION)
+        .param("type", payload.aggregation_type)
+        .opt_param("from", payload.date_from.map(|d| d.to_rfc3339()))
+        .opt_param("to", payload.date_to.map(|d| d.to_rfc3339()))
+        .opt_param("week_start", payload.week_start)
+        .param("interval", payload.interval_minutes)
+        .opt_param("metrics", metrics_param(&payload.metrics))
+        .param("weather", params.include_weather)
+        .param("fill_gaps", params.fill_gaps)
+        .param("cumulative", params.cumulative)
+        .param("layout", params.layout);
+    format.key_params(key).build()
+}
+
+/// `metrics` in the order of [`Metric`], so the same ones asked for in
+/// another order share a key; `None` for none.
+fn metrics_param(metrics: &[Metric]) -> Option<String> {
+    let mut metrics = metrics.to_vec();
+    metrics.sort();
+    metrics.dedup();
+    (!metrics.is_empty()).then(|| {
+        metrics
+            .iter()
+            .map(Metric::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    })
+}
+
+/// Start of the cache keys of the tenant's aggregations.
+pub fn cache_prefix(tenant: &TenantContext) -> String {
+    CacheKey::new(tenant, "energy", "aggregate").prefix()
 }
 
 /// Aggregate energy readings by hour, day, or month
 ///
 /// Returns energy consumption summed by the requested granularity,
-/// optionally filtered by date range.
+/// optionally filtered by date range. With `include_weather=true`, each
+/// period also carries the weather at the tenant's plants, when the weather
+/// importer is enabled, and the response how the energy correlates with it.
+/// With `fill_gaps=true`, periods without readings are returned too, with a
+/// total of 0, from the start of the range, or the first reading, to its
+/// end, or the last reading. With `cumulative=true`, each period's total
+/// is a running total from the start of the range, e.g. month to date by
+/// day. The `metrics` of the request add the smallest, largest and mean
+/// reading of each period, which stay per period with running totals.
+/// With `explain=true`, admins also get the query plan of the
+/// aggregation, from `EXPLAIN (ANALYZE, BUFFERS)` on the read-only pool, to
+/// debug slow queries; such requests bypass the cache.
+///
+/// Data points are serialized as the body is sent, so large responses are
+/// never held in memory as JSON. Their `totalKwh` is a string with the 4
+/// decimal places it is stored with, unless `decimals` rounds it, as
+/// `rounding` says, and `numbers` turns it into a JSON number. With
+/// `layout=columnar`, the response is a `ColumnarAggregateResponse`, with
+/// arrays of the periods and totals instead of `data`, serialized whole.
+///
+/// While the database is busy, aggregations over the cost budget wait for
+/// it and are rejected with `Retry-After` when it stays busy (`503`) or too
+/// many already wait (`429`). Identical aggregations arriving while one is
+/// computed wait for it and share its result. While most recent aggregations
+/// failed in the database, they fail at once with `503` and `Retry-After`,
+/// and while Redis fails the cache is skipped.
 #[utoipa::path(
     post,
     path = "/energy/aggregate",
+    params(AggregateParams),
     request_body = AggregateRequest,
     responses(
-        (status = 200, description = "Aggregated energy data", body = AggregateResponse),
+        (status = 200, description = "Aggregated energy data, a `ColumnarAggregateResponse` with `layout=columnar`", body = AggregateResponse),
         (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "`explain` needs the admin role"),
+        (status = 429, description = "Too many expensive aggregations waiting for the database"),
         (status = 500, description = "Internal server error"),
+        (status = 503, description = "Database busy for an aggregation over budget, or failing"),
     ),
     tag = "energy",
 )]
 #[tracing::instrument(skip_all, name = "energy_aggregate")]
 pub async fn handler(
-    State(state): State<AppState>,
-    RequestId(request_id): RequestId,
+    ctx: HandlerCtx,
+    admin: Result<RequirePermission<permission::Admin>, WireV1Error>,
+    params: Result<Query<AggregateParams>, QueryRejection>,
     ValidatedPayload(payload): ValidatedPayload<AggregateRequest>,
-) -> HandlerResult<(StatusCode, Json<AggregateResponse>)> {
+) -> HandlerResult<Response> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+    if params.explain {
+        admin?;
+    }
+    aggregate(&ctx, params, payload).await
+}
+
+/// The response of `POST /energy/aggregate` for `payload` and `params`, to
+/// the caller of `ctx`, who is allowed to `explain` when asked to.
+pub async fn aggregate(
+    ctx: &HandlerCtx,
+    params: AggregateParams,
+    payload: AggregateRequest,
+) -> HandlerResult<Response> {
     tracing::info!(
         aggregation_type = %payload.aggregation_type,
         date_from = ?payload.date_from,
         date_to = ?payload.date_to,
-        request_id = %request_id,
+        request_id = %ctx.request_id,
         "Energy aggregate request",
     );
 
-    let recorder =
-        ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let explain = params.explain;
+    let format = params.decimal_format().map_err(|e| {
+        recorder.record("invalid_query", errors::Error::InvalidQuery(e))
+    })?;
+    if format.numbers && !ctx.state.flag_enabled(Flag::DecimalNumbers).await {
+        return Err(recorder.record(
+            "invalid_query",
+            errors::Error::InvalidQuery(
+                "numbers: JSON numbers are not enabled".to_string(),
+            ),
+        ));
+    }
+    if params.cumulative && params.include_weather {
+        return Err(recorder.record(
+            "invalid_query",
+            errors::Error::InvalidQuery(
+                "cumulative: running totals are not correlated with the \
+                 weather"
+                    .to_string(),
+            ),
+        ));
+    }
+
+    let database_error = |e| recorder.record_database::<errors::Error>(e);
 
     let new_entry = NewQueryHistory {
         aggregation_type: payload.aggregation_type.to_string(),
         date_from: payload.date_from,
         date_to: payload.date_to,
+        api_key_id: ctx.caller.as_ref().map(|c| c.api_key_id),
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        created_at: None,
     };
-    with_connection(&state.pool, |mut conn| async move {
-        QueryHistory::create(new_entry, &mut conn).await
-    })
-    .await
-    .map_err(|e| match e {
-        WithConnectionError::Pool(e) => recorder
-            .record("pool_error", errors::Error::PoolError(e.to_string())),
-        WithConnectionError::Operation(e) => {
-            recorder.record("database_error", errors::Error::DatabaseError(e))
-        }
-    })?;
+    ctx.state
+        .record_query(new_entry)
+        .await
+        .map_err(database_error)?;
 
-    let key = cache_key(&payload);
-    if let Ok(mut conn) = state.cache_pool.get().await {
-        let cached: Result<Option<String>, _> = conn.get(&key).await;
-        if let Ok(Some(json_str)) = cached {
-            if let Ok(response) =
-                serde_json::from_str::<AggregateResponse>(&json_str)
-            {
-                tracing::debug!("Cache hit for {key}");
-                return Ok((StatusCode::OK, Json(response)));
-            }
+    let use_cache =
+        !explain && ctx.state.flag_enabled(Flag::AggregateCache).await;
+    let key = cache_key(&ctx.tenant, &payload, &params, &format);
+    let redis = ctx.state.breakers.get(HANDLER_NAME, Dependency::Redis);
+    if use_cache {
+        if let Some(json_str) =
+            cache_get(&redis, &ctx.state.cache_pool, &key).await
+        {
+            tracing::debug!("Cache hit for {key}");
+            ctx.state
+                .domain_events
+                .publish(DomainEvent::AggregateServed {
+                    tenant_id: ctx.tenant.tenant_id.clone(),
+                    source: AggregateSource::Cache,
+                });
+            return Ok(json_response(json_str));
         }
+        ctx.state
+            .domain_events
+            .publish(DomainEvent::CacheMiss { cache: "aggregate" });
     }
 
-    let trunc_level = payload.aggregation_type.to_trunc_level().to_owned();
+    let cost = aggregation_cost(
+        payload.aggregation_type,
+        payload.date_from,
+        payload.date_to,
+        Utc::now(),
+    );
+    let trunc_level = payload.trunc_level();
     let date_from = payload.date_from;
     let date_to = payload.date_to;
+    let options = params.options();
+    let tenant_id = &ctx.tenant.tenant_id;
 
-    let rows = with_connection(&state.read_only_pool, |mut conn| async move {
-        EnergyReading::aggregate(&trunc_level, date_from, date_to, &mut conn)
+    let postgres = ctx.state.breakers.get(HANDLER_NAME, Dependency::Postgres);
+    let fetch = || async {
+        let permit = postgres.acquire().map_err(Failure::Unavailable)?;
+        ctx.state
+            .admission
+            .admit(cost, ctx.state.reads.serving())
             .await
-    })
-    .await
-    .map_err(|e| match e {
-        WithConnectionError::Pool(e) => recorder
-            .record("pool_error", errors::Error::PoolError(e.to_string())),
-        WithConnectionError::Operation(e) => {
-            recorder.record("database_error", errors::Error::DatabaseError(e))
+            .map_err(Failure::Rejected)?;
+        let aggregation = async {
+            let rows = ctx
+                .state
+                .readings
+                .aggregate(
+                    tenant_id,
+                    None,
+                    trunc_level,
+                    date_from,
+                    date_to,
+                    options,
+                )
+                .await?;
+            let weather = if params.include_weather {
+                let weather = ctx
+                    .state
+                    .reads
+                    .with_connection(|mut conn| async move {
+                        WeatherObservation::aggregate(
+                            tenant_id,
+                            None,
+                            trunc_level,
+                            date_from,
+                            date_to,
+                            &mut conn,
+                        )
+                        .await
+                    })
+                    .await?;
+                Some(weather)
+            } else {
+                None
+            };
+            RepositoryResult::Ok((rows, weather))
         }
-    })?;
+        .await;
+        permit.record(&aggregation);
+        Ok(Arc::new(aggregation?))
+    };
+    // Identical aggregations in flight share one computation, until the
+    // first of them fills the cache
+    let aggregation = if explain {
+        fetch().await
+    } else {
+        let (aggregation, outcome) =
+            ctx.state.aggregations.run(&key, fetch).await;
+        if aggregation.is_ok() {
+            let source = match outcome {
+                Outcome::Computed => AggregateSource::Computed,
+                Outcome::Shared => AggregateSource::Shared,
+            };
+            ctx.state
+                .domain_events
+                .publish(DomainEvent::AggregateServed {
+                    tenant_id: ctx.tenant.tenant_id.clone(),
+                    source,
+                });
+        }
+        aggregation
+    };
+    let (rows, weather) = match aggregation {
+        Ok(aggregation) => Arc::unwrap_or_clone(aggregation),
+        Err(Failure::Rejected(rejection)) => {
+            return Ok(rejection.into_response(&recorder));
+        }
+        Err(Failure::Unavailable(open)) => {
+            return Ok(open.into_response(&recorder));
+        }
+        Err(Failure::Database(e)) => return Err(database_error(e)),
+    };
 
-    let data = rows
-        .into_iter()
-        .map(|r| AggregateDataPoint {
-            period: r.period,
-            total_kwh: r.total_kwh.to_string(),
-        })
-        .collect();
+    let plan = if explain {
+        let plan = ctx
+            .state
+            .reads
+            .with_connection(|mut conn| async move {
+                EnergyReading::explain_aggregate(
+                    tenant_id,
+                    None,
+                    trunc_level,
+                    date_from,
+                    date_to,
+                    options,
+                    &mut conn,
+                )
+                .await
+            })
+            .await
+            .map_err(database_error)?;
+        Some(plan)
+    } else {
+        None
+    };
 
+    let (data, weather_correlation) =
+        data_points(&payload, rows, weather, format);
+
+    let mut response = AggregateResponse {
+        aggregation_type: payload.aggregation_type,
+        date_from: payload.date_from,
+        date_to: payload.date_to,
+        data,
+        weather_correlation,
+        plan,
+    };
+
+    if params.layout == Layout::Columnar {
+        let points = response.data.len();
+        let columns = ColumnarAggregateResponse::new(response, &payload);
+        let json_str = serde_json::to_string(&columns).map_err(|e| {
+            recorder
+                .record("serialization_error", errors::Error::Serialization(e))
+        })?;
+        if use_cache && points <= CACHE_MAX_POINTS {
+            cache_set(&redis, &ctx.state.cache_pool, &key, &json_str).await;
+        }
+        return Ok(json_response(json_str));
+    }
+
+    if use_cache
+        && response.data.len() <= CACHE_MAX_POINTS
+        && let Ok(json_str) = serde_json::to_string(&response)
+    {
+        cache_set(&redis, &ctx.state.cache_pool, &key, &json_str).await;
+        return Ok(json_response(json_str));
+    }
+
+    let data = std::mem::take(&mut response.data);
+    stream_array_field(&response, "data", data).map_err(|e| {
+        recorder.record("serialization_error", errors::Error::Serialization(e))
+    })
+}
+
+/// Store the aggregation of `payload`, with the default parameters, in the
+/// cache as a request for it would, so that request is a cache hit. Returns
+/// whether it was small enough to cache.
+pub async fn warm_cache(
+    readings: &dyn EnergyReadingRepository,
+    cache: &Pool,
+    tenant: &TenantContext,
+    payload: &AggregateRequest,
+) -> RepositoryResult<bool> {
+    let rows = readings
+        .aggregate(
+            &tenant.tenant_id,
+            None,
+            payload.trunc_level(),
+            payload.date_from,
+            payload.date_to,
+            AggregateOptions::default(),
+        )
+        .await?;
+    if rows.len() > CACHE_MAX_POINTS {
+        return Ok(false);
+    }
+
+    let (data, _) = data_points(payload, rows, None, DecimalFormat::default());
     let response = AggregateResponse {
         aggregation_type: payload.aggregation_type,
         date_from: payload.date_from,
         date_to: payload.date_to,
         data,
+        weather_correlation: None,
+        plan: None,
     };
+    let Ok(json_str) = serde_json::to_string(&response) else {
+        return Ok(false);
+    };
+    let key = cache_key(
+        tenant,
+        payload,
+        &AggregateParams::default(),
+        &DecimalFormat::default(),
+    );
+    response_cache::set(cache, &key, &json_str, CACHE_TTL).await;
+    Ok(true)
+}
+
+/// The cached response at `key`, skipping Redis while its breaker is open.
+async fn cache_get(
+    breaker: &Arc<CircuitBreaker>,
+    cache: &Pool,
+    key: &str,
+) -> Option<String> {
+    let permit = breaker.acquire().ok()?;
+    let cached = response_cache::try_get(cache, key).await;
+    permit.record(&cached);
+    cached.ok().flatten()
+}
+
+/// Cache the response at `key`, unless Redis' breaker is open.
+async fn cache_set(
+    breaker: &Arc<CircuitBreaker>,
+    cache: &Pool,
+    key: &str,
+    json: &str,
+) {
+    if let Ok(permit) = breaker.acquire() {
+        let stored = response_cache::try_set(cache, key, json, CACHE_TTL).await;
+        permit.record(&stored);
+    }
+}
+
+fn json_response(json: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], json).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::{TimeZone, Utc};
+    use postgres_models::models::energy_readings::NewEnergyReading;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::wire_api::testing::{
+        DEFAULT_TENANT, TestApp, reading, readings_server,
+    };
+
+    #[tokio::test]
+    async fn test_aggregates_the_callers_readings() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let at = |month, day| {
+            Utc.with_ymd_and_hms(2025, month, day, 0, 0, 0).unwrap()
+        };
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(1, 1), "10.5"), (at(1, 15), "4.5"), (at(2, 1), "7")],
+        )
+        .await;
+        app.seed_readings("other", None, &[(at(1, 2), "100")]).await;
+
+        let response = app
+            .server
+            .post("/api/wire/v1/energy/aggregate")
+            .json(&json!({"aggregationType": "monthly"}))
+            .await;
+        response.assert_status_ok();
+        let totals = response
+            .json::<AggregateResponse>()
+            .data
+            .into_iter()
+            .map(|point| (point.period, point.total_kwh.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            totals,
+            [
+                (at(1, 1), "15.0000".to_string()),
+                (at(2, 1), "7.0000".to_string())
+            ]
+        );
+
+        app.server
+            .post("/api/wire/v1/energy/aggregate")
+            .json(&json!({"aggregationType": "fortnightly"}))
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_formats_the_totals() {
+        let Some(app) = TestApp::with_vars(&[(
+            "FEATURE_FLAGS",
+            "aggregate_cache=false,response_cache=false,decimal_numbers",
+        )])
+        .await
+        else {
+            return;
+        };
+        let at = |day| Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(1), "10.55"), (at(15), "4.5")],
+        )
+        .await;
+        let total = |query: &str| {
+            let response = app
+                .server
+                .post(&format!("/api/wire/v1/energy/aggregate?{query}"))
+                .json(&json!({"aggregationType": "monthly"}));
+            async move {
+                let response = response.await;
+                response.assert_status_ok();
+                response.json::<serde_json::Value>()["data"][0]["totalKwh"]
+                    .clone()
+            }
+        };
+
+        assert_eq!(total("").await, json!("15.0500"));
+        assert_eq!(total("decimals=1").await, json!("15.1"));
+        assert_eq!(total("decimals=1&rounding=half_even").await, json!("15.0"));
+        assert_eq!(total("decimals=1&numbers=true").await, json!(15.1));
+        app.server
+            .post("/api/wire/v1/energy/aggregate?decimals=5")
+            .json(&json!({"aggregationType": "monthly"}))
+            .await
+            .assert_status_bad_request();
+    }
 
-    if let Ok(json_str) = serde_json::to_string(&response) {
-        if let Ok(mut conn) = state.cache_pool.get().await {
-            let _: Result<(), _> =
-                conn.set_ex(&key, &json_str, CACHE_TTL_SECONDS).await;
+    #[tokio::test]
+    async fn test_lays_out_columns() {
+        let at =
+            |day, hour| Utc.with_ymd_and_hms(2025, 3, day, hour, 0, 0).unwrap();
+        let server = readings_server(vec![
+            reading(at(1, 0), "10.5"),
+            reading(at(1, 13), "2.25"),
+            reading(at(2, 6), "4"),
+        ])
+        .await;
+
+        let response = server
+            .post("/api/wire/v1/energy/aggregate?layout=columnar&decimals=2")
+            .json(&json!({"aggregationType": "day_of_month"}))
+            .await;
+        response.assert_status_ok();
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["periods"], json!([at(1, 0), at(2, 0)]));
+        assert_eq!(body["totals"], json!(["12.75", "4.00"]));
+        assert!(body.get("data").is_none());
+
+        server
+            .post("/api/wire/v1/energy/aggregate?layout=tabular")
+            .json(&json!({"aggregationType": "day_of_month"}))
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_aggregates_by_weeks_quarters_and_years() {
+        let at = |month, day| {
+            Utc.with_ymd_and_hms(2025, month, day, 0, 0, 0).unwrap()
+        };
+        // A Saturday, a Sunday and a Tuesday
+        let server = readings_server(vec![
+            reading(at(3, 1), "10.5"),
+            reading(at(3, 2), "4"),
+            reading(at(4, 1), "1"),
+        ])
+        .await;
+        let aggregate = |request: serde_json::Value| {
+            let response = server
+                .post(
+                    "/api/wire/v1/energy/aggregate?layout=columnar&decimals=1",
+                )
+                .json(&request);
+            async move {
+                let response = response.await;
+                response.assert_status_ok();
+                let body = response.json::<serde_json::Value>();
+                (body["periods"].clone(), body["totals"].clone())
+            }
+        };
+
+        assert_eq!(
+            aggregate(json!({"aggregationType": "weekly"})).await,
+            (json!([at(2, 24), at(3, 31)]), json!(["14.5", "1.0"]))
+        );
+        assert_eq!(
+            aggregate(
+                json!({"aggregationType": "weekly", "weekStart": "sunday"})
+            )
+            .await,
+            (
+                json!([at(2, 23), at(3, 2), at(3, 30)]),
+                json!(["10.5", "4.0", "1.0"])
+            )
+        );
+        assert_eq!(
+            aggregate(json!({"aggregationType": "quarterly"})).await,
+            (json!([at(1, 1), at(4, 1)]), json!(["14.5", "1.0"]))
+        );
+        assert_eq!(
+            aggregate(json!({"aggregationType": "yearly"})).await,
+            (json!([at(1, 1)]), json!(["15.5"]))
+        );
+
+        server
+            .post("/api/wire/v1/energy/aggregate")
+            .json(&json!({"aggregationType": "monthly", "weekStart": "sunday"}))
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_fills_the_gaps() {
+        let at = |day| Utc.with_ymd_and_hms(2025, 3, day, 0, 0, 0).unwrap();
+        let server =
+            readings_server(vec![reading(at(2), "1.5"), reading(at(4), "2")])
+                .await;
+        let aggregate = |request: serde_json::Value| {
+            let response = server
+                .post("/api/wire/v1/energy/aggregate?layout=columnar&fill_gaps=true")
+                .json(&request);
+            async move {
+                let response = response.await;
+                response.assert_status_ok();
+                let body = response.json::<serde_json::Value>();
+                (body["periods"].clone(), body["totals"].clone())
+            }
+        };
+
+        assert_eq!(
+            aggregate(json!({"aggregationType": "day_of_month"})).await,
+            (json!([at(2), at(3), at(4)]), json!(["1.5", "0.0000", "2"]))
+        );
+        assert_eq!(
+            aggregate(json!({
+                "aggregationType": "day_of_month",
+                "dateFrom": at(1),
+                "dateTo": at(6),
+            }))
+            .await,
+            (
+                json!([at(1), at(2), at(3), at(4), at(5)]),
+                json!(["0.0000", "1.5", "0.0000", "2", "0.0000"])
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_returns_running_totals() {
+        let at = |day| Utc.with_ymd_and_hms(2025, 3, day, 0, 0, 0).unwrap();
+        // Two plants report on the 3rd
+        let of_plant = |plant, reading| NewEnergyReading {
+            plant_id: Some(Uuid::from_u128(plant)),
+            ..reading
+        };
+        let server = readings_server(vec![
+            of_plant(1, reading(at(1), "1.5")),
+            of_plant(1, reading(at(3), "2")),
+            of_plant(2, reading(at(3), "0.5")),
+        ])
+        .await;
+
+        let response = server
+            .post("/api/wire/v1/energy/aggregate?layout=columnar&decimals=1&cumulative=true&fill_gaps=true")
+            .json(&json!({
+                "aggregationType": "day_of_month",
+                "dateTo": at(5),
+                "intervalMinutes": 1440,
+            }))
+            .await;
+        response.assert_status_ok();
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["periods"], json!([at(1), at(2), at(3), at(4)]));
+        assert_eq!(body["totals"], json!(["1.5", "1.5", "4.0", "4.0"]));
+        // Counts and coverage stay by period
+        assert_eq!(body["readingCounts"], json!([1, 0, 2, 0]));
+        assert_eq!(body["coverage"], json!([1.0, 0.0, 1.0, 0.0]));
+
+        server
+            .post("/api/wire/v1/energy/aggregate?cumulative=true&include_weather=true")
+            .json(&json!({"aggregationType": "day_of_month"}))
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_records_the_query_in_the_background() {
+        let Some(app) =
+            TestApp::with_vars(&[("QUERY_HISTORY_STRICT", "false")]).await
+        else {
+            return;
+        };
+        app.server
+            .post("/api/wire/v1/energy/aggregate")
+            .json(&json!({"aggregationType": "monthly"}))
+            .await
+            .assert_status_ok();
+
+        // Flushed within a second
+        for _ in 0..150 {
+            let history = app.server.get("/api/wire/v1/energy/history").await;
+            if history.json::<serde_json::Value>()["queries"][0]["aggregationType"]
+                == "monthly"
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
         }
+        panic!("the query was not recorded");
     }
 
-    Ok((StatusCode::OK, Json(response)))
+    #[tokio::test]
+    async fn test_rejects_aggregations_over_budget_while_busy() {
+        // The pool counts as busy with no connections in use
+        let Some(app) = TestApp::with_vars(&[
+            ("ADMISSION_BUDGET", "100"),
+            ("ADMISSION_BUSY_CONNECTIONS", "0"),
+            ("ADMISSION_QUEUE_TIMEOUT_SECS", "1"),
+        ])
+        .await
+        else {
+            return;
+        };
+        let aggregate = |date_to: &str| {
+            app.server
+                .post("/api/wire/v1/energy/aggregate")
+                .json(&json!({
+                    "aggregationType": "hourly",
+                    "dateFrom": "2025-01-01T00:00:00Z",
+                    "dateTo": date_to,
+                }))
+        };
+
+        aggregate("2025-01-01T12:00:00Z").await.assert_status_ok();
+
+        let response = aggregate("2025-02-01T00:00:00Z").await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.header(header::RETRY_AFTER), "1");
+        assert_eq!(
+            response.json::<serde_json::Value>()["details"][0]["code"],
+            "database_busy"
+        );
+    }
 }
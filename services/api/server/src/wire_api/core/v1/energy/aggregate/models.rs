@@ -1,21 +1,66 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Months, TimeDelta, Utc};
+use postgres_models::models::energy_readings::{
+    AggregateOptions, AggregatedReading,
+};
+use postgres_models::models::weather::AggregatedWeather;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::shared::json::{Decimal, DecimalFormat, MAX_DECIMALS, Rounding};
+use crate::weather::correlation;
 
-#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    PartialEq,
+    Eq,
+    async_graphql::Enum,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum AggregationType {
     Hourly,
     DayOfMonth,
+    /// Weeks starting on Monday, or on the request's `weekStart`
+    Weekly,
     Monthly,
+    Quarterly,
+    Yearly,
 }
 
 impl AggregationType {
-    pub fn to_trunc_level(&self) -> &str {
+    pub fn to_trunc_level(self) -> &'static str {
         match self {
             AggregationType::Hourly => "hour",
             AggregationType::DayOfMonth => "day",
+            AggregationType::Weekly => "week",
             AggregationType::Monthly => "month",
+            AggregationType::Quarterly => "quarter",
+            AggregationType::Yearly => "year",
+        }
+    }
+
+    /// End of the period starting at `start`.
+    pub fn period_end(self, start: DateTime<Utc>) -> DateTime<Utc> {
+        let add_months = |months| {
+            start
+                .checked_add_months(Months::new(months))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC)
+        };
+        match self {
+            AggregationType::Hourly => start + TimeDelta::hours(1),
+            AggregationType::DayOfMonth => start + TimeDelta::days(1),
+            AggregationType::Weekly => start + TimeDelta::weeks(1),
+            AggregationType::Monthly => add_months(1),
+            AggregationType::Quarterly => add_months(3),
+            AggregationType::Yearly => add_months(12),
         }
     }
 }
@@ -25,14 +70,135 @@ impl std::fmt::Display for AggregationType {
         match self {
             AggregationType::Hourly => write!(f, "hourly"),
             AggregationType::DayOfMonth => write!(f, "day_of_month"),
+            AggregationType::Weekly => write!(f, "weekly"),
             AggregationType::Monthly => write!(f, "monthly"),
+            AggregationType::Quarterly => write!(f, "quarterly"),
+            AggregationType::Yearly => write!(f, "yearly"),
+        }
+    }
+}
+
+impl std::str::FromStr for AggregationType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hourly" => Ok(AggregationType::Hourly),
+            "day_of_month" => Ok(AggregationType::DayOfMonth),
+            "weekly" => Ok(AggregationType::Weekly),
+            "monthly" => Ok(AggregationType::Monthly),
+            "quarterly" => Ok(AggregationType::Quarterly),
+            "yearly" => Ok(AggregationType::Yearly),
+            other => Err(format!("Unknown aggregation type `{other}`")),
+        }
+    }
+}
+
+/// First day of the weeks of a weekly aggregation.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl WeekStart {
+    /// Truncation level of the weeks starting on this day, see
+    /// [`postgres_models::periods`].
+    pub fn to_trunc_level(self) -> &'static str {
+        match self {
+            WeekStart::Monday => "week",
+            WeekStart::Tuesday => "week_tuesday",
+            WeekStart::Wednesday => "week_wednesday",
+            WeekStart::Thursday => "week_thursday",
+            WeekStart::Friday => "week_friday",
+            WeekStart::Saturday => "week_saturday",
+            WeekStart::Sunday => "week_sunday",
         }
     }
 }
 
+impl std::fmt::Display for WeekStart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeekStart::Monday => write!(f, "monday"),
+            WeekStart::Tuesday => write!(f, "tuesday"),
+            WeekStart::Wednesday => write!(f, "wednesday"),
+            WeekStart::Thursday => write!(f, "thursday"),
+            WeekStart::Friday => write!(f, "friday"),
+            WeekStart::Saturday => write!(f, "saturday"),
+            WeekStart::Sunday => write!(f, "sunday"),
+        }
+    }
+}
+
+/// A statistic of the readings of each period, besides their total.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Deserialize,
+    Serialize,
+    ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// The smallest reading, `minKwh`
+    Min,
+    /// The largest reading, `maxKwh`
+    Max,
+    /// The mean reading, `avgKwh`
+    Avg,
+    /// The number of readings, `readingCount`, which every data point has
+    Count,
+}
+
+impl std::fmt::Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Metric::Min => write!(f, "min"),
+            Metric::Max => write!(f, "max"),
+            Metric::Avg => write!(f, "avg"),
+            Metric::Count => write!(f, "count"),
+        }
+    }
+}
+
+/// Minutes between readings of the imported spreadsheets.
+pub const DEFAULT_INTERVAL_MINUTES: i32 = 60;
+
+fn default_interval_minutes() -> i32 {
+    DEFAULT_INTERVAL_MINUTES
+}
+
+fn validate_week_start(
+    request: &AggregateRequest,
+) -> Result<(), ValidationError> {
+    if request.week_start.is_some()
+        && request.aggregation_type != AggregationType::Weekly
+    {
+        return Err(ValidationError::new("weekStart")
+            .with_message("only applies to weekly aggregations".into()));
+    }
+    Ok(())
+}
+
 /// Request payload for aggregating energy readings
-#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_week_start"))]
 pub struct AggregateRequest {
     /// Aggregation granularity
     #[schema(example = "monthly")]
@@ -45,6 +211,202 @@ pub struct AggregateRequest {
     /// End of date range (exclusive, optional)
     #[schema(example = "2025-04-01T00:00:00Z")]
     pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// First day of the weeks of a weekly aggregation, Monday by default
+    #[serde(default)]
+    pub week_start: Option<WeekStart>,
+
+    /// Minutes between two readings of a feed, a plant or the readings
+    /// without one, to count the readings expected in a period for its
+    /// `coverage`; by default 60, the interval of the imported spreadsheets
+    #[serde(default = "default_interval_minutes")]
+    #[validate(range(
+        min = 1,
+        max = 1440,
+        message = "Intervals are 1-1440 minutes"
+    ))]
+    #[schema(example = 60)]
+    pub interval_minutes: i32,
+
+    /// Statistics of each period's readings to return besides their total,
+    /// none by default
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["min", "max", "avg"]))]
+    pub metrics: Vec<Metric>,
+}
+
+impl AggregateRequest {
+    /// Truncation level of the periods to aggregate by.
+    pub fn trunc_level(&self) -> &'static str {
+        match (self.aggregation_type, self.week_start) {
+            (AggregationType::Weekly, Some(start)) => start.to_trunc_level(),
+            (aggregation_type, _) => aggregation_type.to_trunc_level(),
+        }
+    }
+
+    /// Whether the request asks for `metric`.
+    pub fn wants(&self, metric: Metric) -> bool {
+        self.metrics.contains(&metric)
+    }
+
+    /// Share of the readings expected in the period starting at `period`,
+    /// one per `interval_minutes` over the part of it in the date range
+    /// from each of its `feed_count` feeds, that `reading_count` makes;
+    /// `None` when none is expected.
+    pub fn coverage(
+        &self,
+        period: DateTime<Utc>,
+        reading_count: i64,
+        feed_count: i64,
+    ) -> Option<f64> {
+        let end = self.aggregation_type.period_end(period);
+        let start = self.date_from.map_or(period, |from| period.max(from));
+        let end = self.date_to.map_or(end, |to| end.min(to));
+        let interval_secs = i64::from(self.interval_minutes) * 60;
+        let expected = (end - start).num_seconds() / interval_secs;
+        (expected > 0).then(|| {
+            // A period without readings has no feeds, and no coverage
+            let expected = expected * feed_count.max(1);
+            let coverage = reading_count as f64 / expected as f64;
+            (coverage * 10_000.0).round() / 10_000.0
+        })
+    }
+}
+
+/// Query parameters of an aggregation
+#[derive(Debug, Clone, Default, Deserialize, Serialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AggregateParams {
+    /// Add the weather at the tenant's plants to each period, see
+    /// `weatherCorrelation`
+    #[serde(default)]
+    pub include_weather: bool,
+
+    /// Also return the periods without readings, with a total of 0, from
+    /// the start of the range, or the first reading, to its end, or the
+    /// last reading
+    #[serde(default)]
+    pub fill_gaps: bool,
+
+    /// Return running totals: each period's `totalKwh` plus those of the
+    /// periods before it in the range; not with `include_weather`
+    #[serde(default)]
+    pub cumulative: bool,
+
+    /// Also run the aggregation under `EXPLAIN (ANALYZE, BUFFERS)` and
+    /// return its plan in `plan`; admin role only
+    #[serde(default)]
+    pub explain: bool,
+
+    /// Round `totalKwh` to this many decimal places, at most 4; by default
+    /// it has the 4 it is stored with
+    pub decimals: Option<u32>,
+
+    /// How `totalKwh` is rounded with `decimals`
+    #[serde(default)]
+    #[param(inline)]
+    pub rounding: Rounding,
+
+    /// Write `totalKwh` as a JSON number rather than a string, when the
+    /// `decimal_numbers` feature flag is on
+    #[serde(default)]
+    pub numbers: bool,
+
+    /// Return the data points as rows, or as the columns of a
+    /// `ColumnarAggregateResponse`
+    #[serde(default)]
+    #[param(inline)]
+    pub layout: Layout,
+}
+
+impl AggregateParams {
+    /// How the periods are returned.
+    pub fn options(&self) -> AggregateOptions {
+        AggregateOptions {
+            fill_gaps: self.fill_gaps,
+            cumulative: self.cumulative,
+        }
+    }
+
+    /// Format of the totals the parameters ask for.
+    pub fn decimal_format(&self) -> Result<DecimalFormat, String> {
+        if let Some(decimals) = self.decimals
+            && decimals > MAX_DECIMALS
+        {
+            return Err(format!(
+                "decimals: {decimals} is over the maximum of {MAX_DECIMALS}"
+            ));
+        }
+        Ok(DecimalFormat {
+            decimals: self.decimals,
+            rounding: self.rounding,
+            numbers: self.numbers,
+        })
+    }
+}
+
+/// How the data points of a response are laid out.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// An object per data point, in `data`
+    #[default]
+    Rows,
+    /// An array per field of the data points, `periods`, `totals` and
+    /// `weather`, about half the size of the rows for long series
+    Columnar,
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layout::Rows => write!(f, "rows"),
+            Layout::Columnar => write!(f, "columnar"),
+        }
+    }
+}
+
+/// The weather of a period, averaged over its hours and the tenant's
+/// plants with coordinates
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodWeather {
+    /// Air temperature in °C
+    #[schema(example = 18.4)]
+    pub temperature: Option<f64>,
+
+    /// Global horizontal irradiance in W/m²
+    #[schema(example = 412.7)]
+    pub irradiance: Option<f64>,
+
+    /// Wind speed 10 m above ground in m/s
+    #[schema(example = 4.1)]
+    pub wind_speed: Option<f64>,
+}
+
+impl From<AggregatedWeather> for PeriodWeather {
+    fn from(weather: AggregatedWeather) -> Self {
+        Self {
+            temperature: weather.temperature_c,
+            irradiance: weather.irradiance_w_m2,
+            wind_speed: weather.wind_speed_m_s,
+        }
+    }
+}
+
+/// Pearson correlation of each period's energy with its weather, `null`
+/// with fewer than three periods having both or when either is constant
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherCorrelation {
+    #[schema(example = 0.42)]
+    pub temperature: Option<f64>,
+    #[schema(example = 0.91)]
+    pub irradiance: Option<f64>,
+    #[schema(example = -0.08)]
+    pub wind_speed: Option<f64>,
 }
 
 /// A single aggregated data point
@@ -55,9 +417,42 @@ pub struct AggregateDataPoint {
     #[schema(example = "2025-01-01T00:00:00Z")]
     pub period: chrono::DateTime<chrono::Utc>,
 
-    /// Total energy in kWh for this period
-    #[schema(example = "216000.0000")]
-    pub total_kwh: String,
+    /// Total energy in kWh for this period, a JSON number with `numbers`
+    #[schema(value_type = String, example = "216000.0000")]
+    pub total_kwh: Decimal,
+
+    /// Readings summed in this period
+    #[schema(example = 744)]
+    pub reading_count: i64,
+
+    /// `readingCount` over the readings expected in the period, one per
+    /// `intervalMinutes` of it in the date range from each feed (plant, or
+    /// readings without a plant) with readings in it, rounded to 4
+    /// decimals: below 1 when readings are missing; `null` when none is
+    /// expected
+    #[schema(example = 1.0)]
+    pub coverage: Option<f64>,
+
+    /// Smallest reading of the period in kWh with the `min` metric, absent
+    /// when it has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "0.0000")]
+    pub min_kwh: Option<Decimal>,
+
+    /// Largest reading of the period in kWh with the `max` metric
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "612.5000")]
+    pub max_kwh: Option<Decimal>,
+
+    /// Mean reading of the period in kWh with the `avg` metric
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "290.3226")]
+    pub avg_kwh: Option<Decimal>,
+
+    /// Weather of the period with `include_weather`, absent when there are
+    /// no observations for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weather: Option<PeriodWeather>,
 }
 
 /// Response for an aggregation query
@@ -68,4 +463,388 @@ pub struct AggregateResponse {
     pub date_from: Option<chrono::DateTime<chrono::Utc>>,
     pub date_to: Option<chrono::DateTime<chrono::Utc>>,
     pub data: Vec<AggregateDataPoint>,
+
+    /// With `include_weather`, how the energy follows the weather
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weather_correlation: Option<WeatherCorrelation>,
+
+    /// With `explain`, the PostgreSQL plan of the aggregation, as
+    /// `EXPLAIN (ANALYZE, BUFFERS, FORMAT JSON)` reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub plan: Option<serde_json::Value>,
+}
+
+/// Response for an aggregation query with `layout=columnar`: the data
+/// points as one array per field, the `n`th period having the `n`th total
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnarAggregateResponse {
+    pub aggregation_type: AggregationType,
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Start of each aggregation period
+    pub periods: Vec<chrono::DateTime<chrono::Utc>>,
+
+    /// Total energy in kWh of each period, JSON numbers with `numbers`
+    #[schema(value_type = Vec<String>, example = json!(["216000.0000"]))]
+    pub totals: Vec<Decimal>,
+
+    /// Readings summed in each period
+    #[schema(example = json!([744]))]
+    pub reading_counts: Vec<i64>,
+
+    /// Coverage of each period, see `AggregateDataPoint`
+    #[schema(example = json!([1.0]))]
+    pub coverage: Vec<Option<f64>>,
+
+    /// With the `min` metric, the smallest reading of each period, `null`
+    /// when it has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<String>>)]
+    pub mins: Option<Vec<Option<Decimal>>>,
+
+    /// With the `max` metric, the largest reading of each period
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<String>>)]
+    pub maxes: Option<Vec<Option<Decimal>>>,
+
+    /// With the `avg` metric, the mean reading of each period
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<String>>)]
+    pub averages: Option<Vec<Option<Decimal>>>,
+
+    /// With `include_weather`, the weather of each period, `null` when
+    /// there are no observations for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weather: Option<Vec<Option<PeriodWeather>>>,
+
+    /// With `include_weather`, how the energy follows the weather
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weather_correlation: Option<WeatherCorrelation>,
+
+    /// With `explain`, the PostgreSQL plan of the aggregation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub plan: Option<serde_json::Value>,
+}
+
+impl ColumnarAggregateResponse {
+    /// The columns of `response` to `request`, with those of the metrics it
+    /// asks for.
+    pub fn new(
+        response: AggregateResponse,
+        request: &AggregateRequest,
+    ) -> Self {
+        let len = response.data.len();
+        let mut periods = Vec::with_capacity(len);
+        let mut totals = Vec::with_capacity(len);
+        let mut reading_counts = Vec::with_capacity(len);
+        let mut coverage = Vec::with_capacity(len);
+        let mut mins = Vec::with_capacity(len);
+        let mut maxes = Vec::with_capacity(len);
+        let mut averages = Vec::with_capacity(len);
+        let mut weather = Vec::with_capacity(len);
+        for point in response.data {
+            periods.push(point.period);
+            totals.push(point.total_kwh);
+            reading_counts.push(point.reading_count);
+            coverage.push(point.coverage);
+            mins.push(point.min_kwh);
+            maxes.push(point.max_kwh);
+            averages.push(point.avg_kwh);
+            weather.push(point.weather);
+        }
+        // The metrics asked for, even when no period has readings
+        let column = |metric, values| request.wants(metric).then_some(values);
+        Self {
+            aggregation_type: response.aggregation_type,
+            date_from: response.date_from,
+            date_to: response.date_to,
+            periods,
+            totals,
+            reading_counts,
+            coverage,
+            mins: column(Metric::Min, mins),
+            maxes: column(Metric::Max, maxes),
+            averages: column(Metric::Avg, averages),
+            // Only aggregations with weather have a correlation
+            weather: response.weather_correlation.is_some().then_some(weather),
+            weather_correlation: response.weather_correlation,
+            plan: response.plan,
+        }
+    }
+}
+
+/// Data points of `rows` of `request` with their totals and the metrics
+/// it asks for in `format` and coverage, with the weather of their period
+/// when `weather` is given, and the correlation of both.
+pub fn data_points(
+    request: &AggregateRequest,
+    rows: Vec<AggregatedReading>,
+    weather: Option<Vec<AggregatedWeather>>,
+    format: DecimalFormat,
+) -> (Vec<AggregateDataPoint>, Option<WeatherCorrelation>) {
+    let mut by_period = weather
+        .iter()
+        .flatten()
+        .map(|w| (w.period, PeriodWeather::from(w.clone())))
+        .collect::<HashMap<_, _>>();
+
+    let mut energy = Vec::new();
+    let data = rows
+        .into_iter()
+        .map(|r| {
+            let weather = by_period.remove(&r.period);
+            if let Some(weather) = &weather
+                && let Some(kwh) = r.total_kwh.to_f64()
+            {
+                energy.push((kwh, weather.clone()));
+            }
+            let metric = |metric, value: Option<BigDecimal>| {
+                value
+                    .filter(|_| request.wants(metric))
+                    .map(|value| format.apply(value))
+            };
+            AggregateDataPoint {
+                period: r.period,
+                total_kwh: format.apply(r.total_kwh),
+                reading_count: r.reading_count,
+                coverage: request.coverage(
+                    r.period,
+                    r.reading_count,
+                    r.feed_count,
+                ),
+                min_kwh: metric(Metric::Min, r.min_kwh),
+                max_kwh: metric(Metric::Max, r.max_kwh),
+                avg_kwh: metric(Metric::Avg, r.avg_kwh),
+                weather,
+            }
+        })
+        .collect::<Vec<_>>();
+    if weather.is_none() {
+        return (data, None);
+    }
+
+    let pairs = |value: fn(&PeriodWeather) -> Option<f64>| {
+        energy
+            .iter()
+            .filter_map(|(kwh, weather)| Some((*kwh, value(weather)?)))
+            .collect::<Vec<_>>()
+    };
+    let correlation = WeatherCorrelation {
+        temperature: correlation(&pairs(|w| w.temperature)),
+        irradiance: correlation(&pairs(|w| w.irradiance)),
+        wind_speed: correlation(&pairs(|w| w.wind_speed)),
+    };
+    (data, Some(correlation))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::*;
+
+    fn day(d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, d, 0, 0, 0).unwrap()
+    }
+
+    fn daily() -> AggregateRequest {
+        AggregateRequest {
+            aggregation_type: AggregationType::DayOfMonth,
+            date_from: None,
+            date_to: None,
+            week_start: None,
+            interval_minutes: 60,
+            metrics: Vec::new(),
+        }
+    }
+
+    fn weather(d: u32, irradiance: f64) -> AggregatedWeather {
+        AggregatedWeather {
+            period: day(d),
+            temperature_c: Some(20.0),
+            irradiance_w_m2: Some(irradiance),
+            wind_speed_m_s: None,
+        }
+    }
+
+    #[test]
+    fn test_data_points_with_weather() {
+        let rows = (1..=4)
+            .map(|d| AggregatedReading {
+                period: day(d),
+                total_kwh: BigDecimal::from(100 * d),
+                reading_count: 24,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let (data, correlation) =
+            data_points(&daily(), rows.clone(), None, DecimalFormat::default());
+        assert!(data.iter().all(|point| point.weather.is_none()));
+        assert!(correlation.is_none());
+
+        // No weather for the 4th
+        let observed = vec![
+            weather(1, 200.0),
+            weather(2, 410.0),
+            weather(3, 590.0),
+            weather(9, 800.0),
+        ];
+        let (data, correlation) = data_points(
+            &daily(),
+            rows,
+            Some(observed),
+            DecimalFormat::default(),
+        );
+        assert_eq!(data[1].weather.as_ref().unwrap().irradiance, Some(410.0));
+        assert!(data[3].weather.is_none());
+
+        let correlation = correlation.unwrap();
+        assert!(correlation.irradiance.unwrap() > 0.99);
+        // Constant temperature, no wind
+        assert_eq!(correlation.temperature, None);
+        assert_eq!(correlation.wind_speed, None);
+    }
+
+    #[test]
+    fn test_lays_out_columns() {
+        let rows = (1..=2)
+            .map(|d| AggregatedReading {
+                period: day(d),
+                total_kwh: BigDecimal::from(100 * d),
+                reading_count: 24,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let response = |weather| {
+            let (data, weather_correlation) = data_points(
+                &daily(),
+                rows.clone(),
+                weather,
+                DecimalFormat::default(),
+            );
+            AggregateResponse {
+                aggregation_type: AggregationType::DayOfMonth,
+                date_from: None,
+                date_to: None,
+                data,
+                weather_correlation,
+                plan: None,
+            }
+        };
+
+        let columns = ColumnarAggregateResponse::new(response(None), &daily());
+        assert_eq!(
+            serde_json::to_value(&columns).unwrap(),
+            serde_json::json!({
+                "aggregationType": "day_of_month",
+                "dateFrom": null,
+                "dateTo": null,
+                "periods": ["2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z"],
+                "totals": ["100", "200"],
+                "readingCounts": [24, 24],
+                "coverage": [1.0, 1.0],
+            })
+        );
+
+        let weather = Some(vec![weather(2, 410.0)]);
+        let columns =
+            ColumnarAggregateResponse::new(response(weather), &daily());
+        let weather = columns.weather.unwrap();
+        assert_eq!(weather[0], None);
+        assert_eq!(weather[1].as_ref().unwrap().irradiance, Some(410.0));
+    }
+
+    #[test]
+    fn test_data_points_with_metrics() {
+        let kwh = |kwh: &str| Some(kwh.parse::<BigDecimal>().unwrap());
+        let rows = vec![
+            AggregatedReading {
+                period: day(1),
+                total_kwh: BigDecimal::from(30),
+                reading_count: 3,
+                feed_count: 1,
+                min_kwh: kwh("5.0000"),
+                max_kwh: kwh("15.0000"),
+                avg_kwh: kwh("10.0000"),
+            },
+            // A gap
+            AggregatedReading {
+                period: day(2),
+                ..Default::default()
+            },
+        ];
+
+        let (data, _) =
+            data_points(&daily(), rows.clone(), None, DecimalFormat::default());
+        assert!(data[0].min_kwh.is_none() && data[0].avg_kwh.is_none());
+
+        let request = AggregateRequest {
+            metrics: vec![Metric::Max, Metric::Min],
+            ..daily()
+        };
+        let format = DecimalFormat {
+            decimals: Some(1),
+            ..Default::default()
+        };
+        let (data, _) = data_points(&request, rows.clone(), None, format);
+        let json = serde_json::to_value(&data).unwrap();
+        assert_eq!(json[0]["minKwh"], "5.0");
+        assert_eq!(json[0]["maxKwh"], "15.0");
+        assert!(json[0].get("avgKwh").is_none());
+        assert!(json[1].get("minKwh").is_none());
+
+        let columns = |data| {
+            let response = AggregateResponse {
+                aggregation_type: AggregationType::DayOfMonth,
+                date_from: None,
+                date_to: None,
+                data,
+                weather_correlation: None,
+                plan: None,
+            };
+            let columns = ColumnarAggregateResponse::new(response, &request);
+            serde_json::to_value(&columns).unwrap()
+        };
+        let json = columns(data);
+        assert_eq!(json["mins"], serde_json::json!(["5.0", null]));
+        assert!(json.get("averages").is_none());
+
+        // A metric asked for has its column without readings
+        let (gaps, _) = data_points(&request, rows[1..].to_vec(), None, format);
+        let json = columns(gaps);
+        assert_eq!(json["mins"], serde_json::json!([null]));
+        assert_eq!(json["maxes"], serde_json::json!([null]));
+        assert!(json.get("averages").is_none());
+    }
+
+    #[test]
+    fn test_covers_the_periods_in_the_range() {
+        let mut request = daily();
+        assert_eq!(request.coverage(day(1), 24, 1), Some(1.0));
+        assert_eq!(request.coverage(day(1), 18, 1), Some(0.75));
+        assert_eq!(request.coverage(day(1), 0, 0), Some(0.0));
+        // Each plant is expected to report
+        assert_eq!(request.coverage(day(1), 48, 2), Some(1.0));
+        assert_eq!(request.coverage(day(1), 36, 2), Some(0.75));
+
+        // Half of the day is in the range
+        request.date_from = Some(day(1) + TimeDelta::hours(12));
+        assert_eq!(request.coverage(day(1), 6, 1), Some(0.5));
+        request.date_to = Some(day(1) + TimeDelta::hours(12));
+        assert_eq!(request.coverage(day(1), 0, 0), None);
+
+        let request = AggregateRequest {
+            aggregation_type: AggregationType::Monthly,
+            interval_minutes: 15,
+            ..daily()
+        };
+        // 30 days of June, 96 readings a day
+        assert_eq!(request.coverage(day(1), 2880, 1), Some(1.0));
+        assert_eq!(request.coverage(day(1), 1, 1), Some(0.0003));
+    }
 }
@@ -0,0 +1,144 @@
+use axum::Json;
+use axum::http::StatusCode;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, EnergyReading,
+};
+use postgres_models::models::market_prices::{MarketPrice, PricedPeriod};
+use postgres_models::models::plants::Plant;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{CostRequest, CostResponse, data_points, tariff};
+
+const HANDLER_NAME: &str = "energy_cost";
+
+/// What the tenant's energy is worth
+///
+/// Sums energy readings by hour, day or month like `POST /energy/aggregate`
+/// and values them at the hourly day-ahead prices of a bidding `zone`,
+/// imported by the market price importer, at a static `tariffPerKwh`, or
+/// both. Readings of hours without a price are left out of the market cost
+/// and counted in `unpricedKwh`.
+#[utoipa::path(
+    post,
+    path = "/energy/cost",
+    request_body = CostRequest,
+    responses(
+        (status = 200, description = "Energy and its cost by period", body = CostResponse),
+        (status = 400, description = "Invalid request parameters or unknown zone"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_cost")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CostRequest>,
+) -> HandlerResult<(StatusCode, Json<CostResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let plant = payload.plant_id;
+    let zone = payload.zone.as_deref();
+    let trunc_level = payload.aggregation_type.to_trunc_level();
+    let date_from = payload.date_from;
+    let date_to = payload.date_to;
+
+    if let Some(plant) = plant {
+        ctx.state
+            .reads
+            .with_connection(|mut conn| async move {
+                Plant::find(tenant_id, plant, &mut conn).await
+            })
+            .await
+            .map_err(|e| recorder.record_database::<errors::Error>(e))?
+            .ok_or_else(|| {
+                recorder.record(
+                    "plant_not_found",
+                    errors::Error::PlantNotFound(plant),
+                )
+            })?;
+    }
+    if let Some(zone) = zone {
+        let exists = ctx
+            .state
+            .reads
+            .with_connection(|mut conn| async move {
+                MarketPrice::zone_exists(zone, &mut conn).await
+            })
+            .await
+            .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+        if !exists {
+            return Err(recorder.record(
+                "unknown_zone",
+                errors::Error::UnknownZone(zone.to_string()),
+            ));
+        }
+    }
+
+    let periods = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            let Some(zone) = zone else {
+                let rows = EnergyReading::aggregate(
+                    tenant_id,
+                    plant,
+                    trunc_level,
+                    date_from,
+                    date_to,
+                    AggregateOptions::default(),
+                    &mut conn,
+                )
+                .await?;
+                // Readings are not valued at market prices
+                return Ok(rows
+                    .into_iter()
+                    .map(|row| PricedPeriod {
+                        period: row.period,
+                        total_kwh: row.total_kwh,
+                        market_cost: None,
+                        unpriced_kwh: Default::default(),
+                        currency: None,
+                    })
+                    .collect());
+            };
+            MarketPrice::price_readings(
+                tenant_id,
+                plant,
+                zone,
+                trunc_level,
+                date_from,
+                date_to,
+                &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let tariff_per_kwh = payload.tariff_per_kwh.and_then(tariff);
+    let (data, totals) =
+        data_points(&periods, zone.is_some(), tariff_per_kwh.as_ref());
+
+    Ok((
+        StatusCode::OK,
+        Json(CostResponse {
+            aggregation_type: payload.aggregation_type,
+            date_from: payload.date_from,
+            date_to: payload.date_to,
+            plant_id: payload.plant_id,
+            zone: payload.zone.clone(),
+            currency: totals.currency,
+            tariff_per_kwh: payload.tariff_per_kwh,
+            total_kwh: totals.total_kwh.to_string(),
+            market_cost: totals.market_cost.map(|cost| cost.to_string()),
+            unpriced_kwh: totals.unpriced_kwh.map(|kwh| kwh.to_string()),
+            tariff_cost: totals.tariff_cost.map(|cost| cost.to_string()),
+            data,
+        }),
+    ))
+}
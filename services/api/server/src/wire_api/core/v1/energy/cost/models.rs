@@ -0,0 +1,235 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use postgres_models::models::market_prices::PricedPeriod;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+/// Decimals of costs.
+const COST_SCALE: i64 = 4;
+
+fn validate_pricing(request: &CostRequest) -> Result<(), ValidationError> {
+    if request.zone.is_none() && request.tariff_per_kwh.is_none() {
+        return Err(ValidationError::new("pricing")
+            .with_message("Send a `zone`, a `tariffPerKwh` or both".into()));
+    }
+    Ok(())
+}
+
+/// Request payload for valuing energy readings
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_pricing"))]
+pub struct CostRequest {
+    /// Aggregation granularity
+    #[schema(example = "day_of_month")]
+    pub aggregation_type: AggregationType,
+
+    /// Start of date range (inclusive, optional)
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// End of date range (exclusive, optional)
+    #[schema(example = "2025-02-01T00:00:00Z")]
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only value the readings attributed to this plant
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Bidding zone whose imported day-ahead prices value each reading
+    #[validate(length(
+        min = 1,
+        max = 20,
+        message = "Zones are 1-20 characters"
+    ))]
+    #[schema(example = "SE3")]
+    pub zone: Option<String>,
+
+    /// Static price per kWh
+    #[validate(range(min = 0.0, message = "Tariffs cannot be negative"))]
+    #[schema(example = 0.12)]
+    pub tariff_per_kwh: Option<f64>,
+}
+
+/// Energy and cost of one aggregation period
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CostDataPoint {
+    /// Start of the aggregation period
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub period: chrono::DateTime<chrono::Utc>,
+
+    /// Total energy in kWh for this period
+    #[schema(example = "5184.0000")]
+    pub total_kwh: String,
+
+    /// Value of the readings at the zone's hourly prices, without those
+    /// of hours without a price; absent without a `zone`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "402.6620")]
+    pub market_cost: Option<String>,
+
+    /// Energy of the readings of hours without a price
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "0.0000")]
+    pub unpriced_kwh: Option<String>,
+
+    /// `totalKwh × tariffPerKwh`; absent without a tariff
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "622.0800")]
+    pub tariff_cost: Option<String>,
+}
+
+/// Response for a cost query
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CostResponse {
+    pub aggregation_type: AggregationType,
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+    pub plant_id: Option<uuid::Uuid>,
+    pub zone: Option<String>,
+
+    /// Currency of the market prices
+    #[schema(example = "EUR")]
+    pub currency: Option<String>,
+
+    pub tariff_per_kwh: Option<f64>,
+
+    /// Totals over all periods
+    #[schema(example = "5184.0000")]
+    pub total_kwh: String,
+    pub market_cost: Option<String>,
+    pub unpriced_kwh: Option<String>,
+    pub tariff_cost: Option<String>,
+
+    pub data: Vec<CostDataPoint>,
+}
+
+/// Totals of a cost query.
+#[derive(Debug, PartialEq)]
+pub struct CostTotals {
+    pub total_kwh: BigDecimal,
+    pub market_cost: Option<BigDecimal>,
+    pub unpriced_kwh: Option<BigDecimal>,
+    pub tariff_cost: Option<BigDecimal>,
+    pub currency: Option<String>,
+}
+
+/// A tariff as a decimal, from its shortest representation rather than its
+/// binary value.
+pub fn tariff(tariff_per_kwh: f64) -> Option<BigDecimal> {
+    tariff_per_kwh.to_string().parse().ok()
+}
+
+fn cost(kwh: &BigDecimal, price: &BigDecimal) -> BigDecimal {
+    (kwh * price).with_scale_round(COST_SCALE, RoundingMode::HalfEven)
+}
+
+/// Sum of the values, `None` when there are none.
+fn total<'a>(
+    values: impl Iterator<Item = Option<&'a BigDecimal>>,
+) -> Option<BigDecimal> {
+    values
+        .flatten()
+        .fold(None, |sum, value| Some(sum.unwrap_or_default() + value))
+}
+
+/// The data points and totals of a cost response from `periods`, priced at
+/// market prices when `priced` and at `tariff` when given.
+pub fn data_points(
+    periods: &[PricedPeriod],
+    priced: bool,
+    tariff: Option<&BigDecimal>,
+) -> (Vec<CostDataPoint>, CostTotals) {
+    let tariff_costs = periods
+        .iter()
+        .map(|p| tariff.map(|tariff| cost(&p.total_kwh, tariff)))
+        .collect::<Vec<_>>();
+
+    let totals = CostTotals {
+        total_kwh: total(periods.iter().map(|p| Some(&p.total_kwh)))
+            .unwrap_or_default(),
+        market_cost: priced
+            .then(|| total(periods.iter().map(|p| p.market_cost.as_ref())))
+            .flatten(),
+        unpriced_kwh: priced.then(|| {
+            total(periods.iter().map(|p| Some(&p.unpriced_kwh)))
+                .unwrap_or_default()
+        }),
+        tariff_cost: tariff.map(|tariff| {
+            let total_kwh = total(periods.iter().map(|p| Some(&p.total_kwh)));
+            cost(&total_kwh.unwrap_or_default(), tariff)
+        }),
+        currency: periods.iter().find_map(|p| p.currency.clone()),
+    };
+
+    let data = periods
+        .iter()
+        .zip(tariff_costs)
+        .map(|(p, tariff_cost)| CostDataPoint {
+            period: p.period,
+            total_kwh: p.total_kwh.to_string(),
+            market_cost: priced
+                .then(|| p.market_cost.as_ref().map(ToString::to_string))
+                .flatten(),
+            unpriced_kwh: priced.then(|| p.unpriced_kwh.to_string()),
+            tariff_cost: tariff_cost.map(|cost| cost.to_string()),
+        })
+        .collect();
+
+    (data, totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn decimal(value: &str) -> BigDecimal {
+        value.parse().unwrap()
+    }
+
+    fn period(day: u32, kwh: &str, cost: Option<&str>) -> PricedPeriod {
+        PricedPeriod {
+            period: Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap(),
+            total_kwh: decimal(kwh),
+            market_cost: cost.map(decimal),
+            unpriced_kwh: if cost.is_some() {
+                decimal("0")
+            } else {
+                decimal(kwh)
+            },
+            currency: cost.map(|_| "EUR".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_market_and_tariff_costs() {
+        let periods = [
+            period(1, "100.5000", Some("4.0200")),
+            period(2, "50.0000", None),
+        ];
+        let tariff = tariff(0.12).unwrap();
+        assert_eq!(tariff, decimal("0.12"));
+
+        let (data, totals) = data_points(&periods, true, Some(&tariff));
+        assert_eq!(data[0].market_cost.as_deref(), Some("4.0200"));
+        assert_eq!(data[0].tariff_cost.as_deref(), Some("12.0600"));
+        assert_eq!(data[1].market_cost, None);
+        assert_eq!(data[1].unpriced_kwh.as_deref(), Some("50.0000"));
+        assert_eq!(totals.total_kwh, decimal("150.5"));
+        assert_eq!(totals.market_cost, Some(decimal("4.02")));
+        assert_eq!(totals.unpriced_kwh, Some(decimal("50")));
+        assert_eq!(totals.tariff_cost, Some(decimal("18.06")));
+        assert_eq!(totals.currency.as_deref(), Some("EUR"));
+
+        let (data, totals) = data_points(&periods, false, None);
+        assert_eq!(data[0].market_cost, None);
+        assert_eq!(data[0].unpriced_kwh, None);
+        assert_eq!(totals.unpriced_kwh, None);
+        assert_eq!(totals.tariff_cost, None);
+    }
+}
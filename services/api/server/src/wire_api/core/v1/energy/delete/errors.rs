@@ -0,0 +1,20 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+/// Deleting readings only fails in the database.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, _request_id: &Uuid) -> WireV1Error {
+        match self {}
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Failed to delete readings";
+}
@@ -0,0 +1,210 @@
+use axum::Json;
+use axum::http::StatusCode;
+use diesel_async::AsyncConnection;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use postgres_models::connection::with_connection;
+use postgres_models::models::energy_readings::EnergyReading;
+use tracing::Instrument;
+
+use crate::flags::Flag;
+use crate::outbox;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::shared::response_cache;
+use crate::webhooks::WebhookEvent;
+use crate::wire_api::core::v1::energy::aggregate;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{ReadingDeleteRequest, ReadingDeleteResponse};
+
+const HANDLER_NAME: &str = "energy_readings_delete";
+
+/// Delete readings
+///
+/// Deletes the caller's tenant's readings in `[dateFrom, dateTo)`, or at
+/// each of `timestamps`, optionally only those of a plant, e.g. to undo a
+/// bad import. Each deleted reading leaves a tombstone, a `delete` revision
+/// holding it as it was, in the revisions table. The response is a
+/// `207 Multi-Status` with a result for the range, or per timestamp:
+/// `200` when readings were deleted and `404` when there were none.
+/// Cached aggregations of the tenant are invalidated, and a
+/// `readings_deleted` event is sent to subscribed webhooks.
+#[utoipa::path(
+    delete,
+    path = "/energy/readings",
+    request_body = ReadingDeleteRequest,
+    responses(
+        (status = 207, description = "Result per timestamp, or for the range", body = ReadingDeleteResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_readings_delete")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<ReadingDeleteRequest>,
+) -> HandlerResult<(StatusCode, Json<ReadingDeleteResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let request_id = ctx.request_id;
+    let (plant, selection) = (payload.plant_id, payload.selection());
+    let deleted = with_connection(&ctx.state.pool, |mut conn| async move {
+        conn.transaction::<_, diesel::result::Error, _>(move |conn| {
+            async move {
+                let deleted = EnergyReading::delete(
+                    tenant_id,
+                    plant,
+                    selection,
+                    Some(request_id),
+                    conn,
+                )
+                .await?;
+                if !deleted.is_empty() {
+                    let event_data = serde_json::json!({
+                        "tenant": tenant_id,
+                        "plantId": plant,
+                        "requestId": request_id,
+                        "deleted": deleted.iter().map(|d| d.deleted).sum::<i64>(),
+                        "readingTimes": deleted
+                            .iter()
+                            .map(|d| d.reading_time)
+                            .collect::<Vec<_>>(),
+                    });
+                    outbox::record(
+                        WebhookEvent::ReadingsDeleted,
+                        tenant_id,
+                        event_data,
+                        conn,
+                    )
+                    .await?;
+                }
+                Ok(deleted)
+            }
+            .scope_boxed()
+        })
+        .await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let response = ReadingDeleteResponse::new(&payload, &deleted);
+    tracing::info!(
+        tenant = %ctx.tenant.tenant_id,
+        deleted = response.deleted,
+        "Deleted energy readings"
+    );
+    if response.deleted > 0 && ctx.flag_enabled(Flag::AggregateCache).await {
+        let cache = ctx.state.cache_pool.clone();
+        let prefix = aggregate::handler::cache_prefix(&ctx.tenant);
+        tokio::spawn(
+            async move {
+                response_cache::delete_prefix(&cache, &prefix).await;
+            }
+            .instrument(ctx.span.clone()),
+        );
+    }
+
+    Ok((StatusCode::MULTI_STATUS, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use postgres_models::connection::with_connection;
+    use postgres_models::models::energy_reading_revisions::{
+        EnergyReadingRevision, revision_operation,
+    };
+    use postgres_models::models::outbox::OutboxEvent;
+    use postgres_models::schema::outbox;
+    use serde_json::json;
+
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[tokio::test]
+    async fn test_deletes_readings_with_tombstones() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2025, 6, 1, hour, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(0), "1"), (at(1), "2"), (at(2), "3"), (at(3), "4")],
+        )
+        .await;
+        app.seed_readings("other", None, &[(at(0), "100")]).await;
+
+        let response = app
+            .server
+            .delete("/api/wire/v1/energy/readings")
+            .json(&json!({ "timestamps": [at(0), at(5)] }))
+            .await;
+        response.assert_status(axum::http::StatusCode::MULTI_STATUS);
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["deleted"], 1);
+        assert_eq!(body["results"][0]["status"], 200);
+        assert_eq!(body["results"][1]["status"], 404);
+
+        let response = app
+            .server
+            .delete("/api/wire/v1/energy/readings")
+            .json(&json!({ "dateFrom": at(1), "dateTo": at(3) }))
+            .await;
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["deleted"], 2);
+        assert_eq!(body["results"][0]["status"], 200);
+
+        let tombstones =
+            with_connection(&app.state.pool, |mut conn| async move {
+                EnergyReadingRevision::list(
+                    DEFAULT_TENANT,
+                    at(0),
+                    at(6),
+                    &mut conn,
+                )
+                .await
+            })
+            .await
+            .unwrap();
+        assert_eq!(tombstones.len(), 3);
+
+        // An event per request that deleted readings
+        let events = with_connection(&app.state.pool, |mut conn| async move {
+            outbox::table
+                .filter(outbox::event_type.eq("readings_deleted"))
+                .order(outbox::id)
+                .select(OutboxEvent::as_select())
+                .load(&mut conn)
+                .await
+        })
+        .await
+        .unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.tenant_id == DEFAULT_TENANT));
+        assert_eq!(events[0].payload["deleted"], 1);
+        assert_eq!(events[0].payload["readingTimes"], json!([at(0)]));
+        assert_eq!(events[1].payload["deleted"], 2);
+        assert!(
+            tombstones
+                .iter()
+                .all(|t| t.operation == revision_operation::DELETE)
+        );
+        let remaining = app
+            .server
+            .get("/api/wire/v1/energy/readings")
+            .await
+            .json::<serde_json::Value>();
+        assert_eq!(remaining["readings"].as_array().unwrap().len(), 1);
+
+        app.server
+            .delete("/api/wire/v1/energy/readings")
+            .json(&json!({ "dateFrom": at(0), "timestamps": [at(0)] }))
+            .await
+            .assert_status_bad_request();
+    }
+}
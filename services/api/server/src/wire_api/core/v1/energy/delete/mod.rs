@@ -0,0 +1,3 @@
+mod errors;
+pub mod handler;
+pub mod models;
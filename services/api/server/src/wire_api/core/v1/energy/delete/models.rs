@@ -0,0 +1,210 @@
+use chrono::{DateTime, Utc};
+use postgres_models::models::energy_readings::{
+    DeletedReadings, ReadingSelection,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// Most timestamps of one request.
+pub const MAX_TIMESTAMPS: u64 = 1000;
+
+fn validate_selection(
+    request: &ReadingDeleteRequest,
+) -> Result<(), ValidationError> {
+    match (
+        request.date_from,
+        request.date_to,
+        request.timestamps.is_empty(),
+    ) {
+        (Some(from), Some(to), true) if to <= from => {
+            Err(ValidationError::new("dateTo")
+                .with_message("must be after dateFrom".into()))
+        }
+        (Some(_), Some(_), true) | (None, None, false) => Ok(()),
+        _ => Err(ValidationError::new("selection").with_message(
+            "Send either dateFrom and dateTo, or timestamps".into(),
+        )),
+    }
+}
+
+/// Request payload for deleting readings: a range of reading times, or a
+/// list of them
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_selection"))]
+pub struct ReadingDeleteRequest {
+    /// Start of the range of reading times (inclusive)
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub date_from: Option<DateTime<Utc>>,
+
+    /// End of the range of reading times (exclusive)
+    #[schema(example = "2025-01-02T00:00:00Z")]
+    pub date_to: Option<DateTime<Utc>>,
+
+    /// Reading times to delete the readings of, at most 1000, rather than
+    /// a range
+    #[serde(default)]
+    #[validate(length(max = MAX_TIMESTAMPS, message = "At most 1000 timestamps"))]
+    pub timestamps: Vec<DateTime<Utc>>,
+
+    /// Only delete the readings attributed to this plant
+    pub plant_id: Option<uuid::Uuid>,
+}
+
+impl ReadingDeleteRequest {
+    /// The readings the request deletes.
+    pub fn selection(&self) -> ReadingSelection<'_> {
+        match (self.date_from, self.date_to) {
+            (Some(from), Some(to)) => ReadingSelection::Range { from, to },
+            _ => ReadingSelection::Times(&self.timestamps),
+        }
+    }
+}
+
+/// Outcome of deleting the readings of a range or of a timestamp
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingDeleteResult {
+    /// `200` when readings were deleted, `404` when there were none
+    #[schema(example = 200)]
+    pub status: u16,
+
+    /// The timestamp, for a list of them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reading_time: Option<DateTime<Utc>>,
+
+    /// The range, for a range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_to: Option<DateTime<Utc>>,
+
+    /// Readings deleted
+    #[schema(example = 1)]
+    pub deleted: i64,
+}
+
+impl ReadingDeleteResult {
+    fn new(deleted: i64) -> Self {
+        Self {
+            status: if deleted > 0 { 200 } else { 404 },
+            reading_time: None,
+            date_from: None,
+            date_to: None,
+            deleted,
+        }
+    }
+}
+
+/// Response for deleting readings, a result per timestamp of the request
+/// in its order, or one for its range
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingDeleteResponse {
+    /// Readings deleted in all
+    #[schema(example = 24)]
+    pub deleted: i64,
+    pub results: Vec<ReadingDeleteResult>,
+}
+
+impl ReadingDeleteResponse {
+    /// The results of `request`, which deleted `deleted`.
+    pub fn new(
+        request: &ReadingDeleteRequest,
+        deleted: &[DeletedReadings],
+    ) -> Self {
+        let total = deleted.iter().map(|d| d.deleted).sum();
+        let results = match request.selection() {
+            ReadingSelection::Range { from, to } => {
+                vec![ReadingDeleteResult {
+                    date_from: Some(from),
+                    date_to: Some(to),
+                    ..ReadingDeleteResult::new(total)
+                }]
+            }
+            ReadingSelection::Times(times) => times
+                .iter()
+                .map(|&time| {
+                    let count = deleted
+                        .iter()
+                        .find(|d| d.reading_time == time)
+                        .map_or(0, |d| d.deleted);
+                    ReadingDeleteResult {
+                        reading_time: Some(time),
+                        ..ReadingDeleteResult::new(count)
+                    }
+                })
+                .collect(),
+        };
+        Self {
+            deleted: total,
+            results,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_validates_selection() {
+        let request = |date_from, date_to, timestamps| ReadingDeleteRequest {
+            date_from,
+            date_to,
+            timestamps,
+            plant_id: None,
+        };
+        assert!(request(Some(at(0)), Some(at(1)), vec![]).validate().is_ok());
+        assert!(request(None, None, vec![at(0)]).validate().is_ok());
+        assert!(request(None, None, vec![]).validate().is_err());
+        assert!(
+            request(Some(at(1)), Some(at(0)), vec![])
+                .validate()
+                .is_err()
+        );
+        assert!(request(Some(at(0)), None, vec![]).validate().is_err());
+        assert!(
+            request(Some(at(0)), Some(at(1)), vec![at(0)])
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_results_per_timestamp() {
+        let request = ReadingDeleteRequest {
+            date_from: None,
+            date_to: None,
+            timestamps: vec![at(2), at(0)],
+            plant_id: None,
+        };
+        let deleted = [DeletedReadings {
+            reading_time: at(0),
+            deleted: 2,
+        }];
+        let response = ReadingDeleteResponse::new(&request, &deleted);
+        assert_eq!(response.deleted, 2);
+        assert_eq!(
+            response.results,
+            [
+                ReadingDeleteResult {
+                    reading_time: Some(at(2)),
+                    ..ReadingDeleteResult::new(0)
+                },
+                ReadingDeleteResult {
+                    reading_time: Some(at(0)),
+                    ..ReadingDeleteResult::new(2)
+                },
+            ]
+        );
+        assert_eq!(response.results[0].status, 404);
+    }
+}
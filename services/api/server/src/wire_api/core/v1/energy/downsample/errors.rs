@@ -0,0 +1,34 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Plant {0} not found")]
+    PlantNotFound(Uuid),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::PlantNotFound(id) => WireV1Error::not_found(
+                "Plant not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("plantId".to_string()),
+                    code: "plant_not_found".to_string(),
+                    message: format!("Plant {id} not found"),
+                    suggestion: "Use the id of one of your plants".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Downsample query failed";
+}
@@ -0,0 +1,92 @@
+use axum::Json;
+use axum::http::StatusCode;
+use postgres_models::models::energy_readings::EnergyReading;
+use postgres_models::models::plants::Plant;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{DownsampleMethod, DownsampleRequest, DownsampleResponse};
+use super::sample;
+
+const HANDLER_NAME: &str = "energy_downsample";
+
+/// Downsample the tenant's readings for charting
+///
+/// Sums the readings of each reading time, optionally of one plant, and
+/// reduces the series to at most `maxPoints` points with
+/// largest-triangle-three-buckets (`lttb`) or the lowest and highest point
+/// of each bucket (`min_max`), so years of readings chart from a few
+/// thousand points.
+#[utoipa::path(
+    post,
+    path = "/energy/downsample",
+    request_body = DownsampleRequest,
+    responses(
+        (status = 200, description = "Downsampled series", body = DownsampleResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_downsample")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<DownsampleRequest>,
+) -> HandlerResult<(StatusCode, Json<DownsampleResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let plant = payload.plant_id;
+    let date_from = payload.date_from;
+    let date_to = payload.date_to;
+
+    if let Some(plant) = plant {
+        ctx.state
+            .reads
+            .with_connection(|mut conn| async move {
+                Plant::find(tenant_id, plant, &mut conn).await
+            })
+            .await
+            .map_err(|e| recorder.record_database::<errors::Error>(e))?
+            .ok_or_else(|| {
+                recorder.record(
+                    "plant_not_found",
+                    errors::Error::PlantNotFound(plant),
+                )
+            })?;
+    }
+
+    let series = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            EnergyReading::series(
+                tenant_id, plant, date_from, date_to, &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let sampled = match payload.method {
+        DownsampleMethod::Lttb => sample::lttb(&series, payload.max_points),
+        DownsampleMethod::MinMax => {
+            sample::min_max(&series, payload.max_points)
+        }
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(DownsampleResponse {
+            method: payload.method,
+            date_from,
+            date_to,
+            plant_id: plant,
+            source_points: series.len(),
+            points: sampled.into_iter().map(Into::into).collect(),
+        }),
+    ))
+}
@@ -0,0 +1,4 @@
+mod errors;
+pub mod handler;
+pub mod models;
+mod sample;
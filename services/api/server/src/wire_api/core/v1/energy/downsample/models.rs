@@ -0,0 +1,93 @@
+use postgres_models::models::energy_readings::SeriesPoint;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Most points a downsampled series can have.
+pub const MAX_POINTS: usize = 10_000;
+
+fn default_max_points() -> usize {
+    1_000
+}
+
+/// How a series is reduced
+#[derive(
+    Debug, Clone, Copy, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DownsampleMethod {
+    /// Largest-triangle-three-buckets, keeps the visual shape
+    #[default]
+    Lttb,
+    /// Lowest and highest point of each bucket, keeps every peak
+    MinMax,
+}
+
+/// Request payload for downsampling energy readings
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DownsampleRequest {
+    /// Start of date range (inclusive, optional)
+    #[schema(example = "2023-01-01T00:00:00Z")]
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// End of date range (exclusive, optional)
+    #[schema(example = "2026-01-01T00:00:00Z")]
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only the readings attributed to this plant
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Most points to return
+    #[serde(default = "default_max_points")]
+    #[validate(range(
+        min = 3,
+        max = MAX_POINTS,
+        message = "maxPoints is 3-10000"
+    ))]
+    #[schema(example = 1000)]
+    pub max_points: usize,
+
+    /// Reduction method
+    #[serde(default)]
+    #[schema(example = "lttb")]
+    pub method: DownsampleMethod,
+}
+
+/// A point of a downsampled series
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DownsamplePoint {
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub reading_time: chrono::DateTime<chrono::Utc>,
+
+    /// Energy at the time, summed over the plants
+    #[schema(example = 9000.0)]
+    pub kwh: f64,
+}
+
+impl From<SeriesPoint> for DownsamplePoint {
+    fn from(point: SeriesPoint) -> Self {
+        Self {
+            reading_time: point.reading_time,
+            kwh: point.kwh,
+        }
+    }
+}
+
+/// Response for a downsample query
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DownsampleResponse {
+    pub method: DownsampleMethod,
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Reading times in the range
+    #[schema(example = 26280)]
+    pub source_points: usize,
+
+    /// The series, in time order
+    pub points: Vec<DownsamplePoint>,
+}
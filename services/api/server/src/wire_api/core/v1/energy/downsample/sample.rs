@@ -0,0 +1,143 @@
+//! Reducing a series to a number of points that still charts like it.
+use postgres_models::models::energy_readings::SeriesPoint;
+
+/// Largest-triangle-three-buckets: keeps the first and last points and, of
+/// each of `threshold - 2` equal-count buckets in between, the point
+/// forming the largest triangle with the point kept from the previous
+/// bucket and the average of the next one. Series of at most `threshold`
+/// points are returned as they are.
+pub fn lttb(points: &[SeriesPoint], threshold: usize) -> Vec<SeriesPoint> {
+    if threshold < 3 || points.len() <= threshold {
+        return points.to_vec();
+    }
+
+    let x = |point: &SeriesPoint| point.reading_time.timestamp() as f64;
+    let bucket_size = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let bucket = |i: usize| {
+        let start = (i as f64 * bucket_size) as usize + 1;
+        let end =
+            (((i + 1) as f64 * bucket_size) as usize + 1).min(points.len() - 1);
+        start..end
+    };
+
+    let mut sampled = Vec::with_capacity(threshold);
+    let mut kept = points[0];
+    sampled.push(kept);
+    for i in 0..threshold - 2 {
+        // Average of the next bucket, or the last point after the last one
+        let next = if i + 1 < threshold - 2 {
+            &points[bucket(i + 1)]
+        } else {
+            &points[points.len() - 1..]
+        };
+        let n = next.len() as f64;
+        let average_x = next.iter().map(x).sum::<f64>() / n;
+        let average_y = next.iter().map(|p| p.kwh).sum::<f64>() / n;
+
+        let (kept_x, kept_y) = (x(&kept), kept.kwh);
+        let largest = points[bucket(i)]
+            .iter()
+            .max_by(|a, b| {
+                let area = |p: &SeriesPoint| {
+                    ((kept_x - average_x) * (p.kwh - kept_y)
+                        - (kept_x - x(p)) * (average_y - kept_y))
+                        .abs()
+                };
+                area(a).total_cmp(&area(b))
+            })
+            .copied();
+        if let Some(point) = largest {
+            kept = point;
+            sampled.push(point);
+        }
+    }
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+/// The lowest and highest point of each of `max_points / 2` equal-count
+/// buckets, in time order, so peaks survive. Series of at most
+/// `max_points` points are returned as they are.
+pub fn min_max(points: &[SeriesPoint], max_points: usize) -> Vec<SeriesPoint> {
+    let buckets = max_points / 2;
+    if buckets == 0 || points.len() <= max_points {
+        return points.to_vec();
+    }
+
+    let bucket_size = points.len().div_ceil(buckets);
+    let mut sampled = Vec::with_capacity(max_points);
+    for bucket in points.chunks(bucket_size) {
+        let lowest = bucket
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.kwh.total_cmp(&b.kwh));
+        let highest = bucket
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.kwh.total_cmp(&b.kwh));
+        let (Some((low, _)), Some((high, _))) = (lowest, highest) else {
+            continue;
+        };
+        let (first, second) = (low.min(high), low.max(high));
+        sampled.push(bucket[first]);
+        if second != first {
+            sampled.push(bucket[second]);
+        }
+    }
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+
+    fn series(values: &[f64]) -> Vec<SeriesPoint> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, kwh)| SeriesPoint {
+                reading_time: DateTime::<Utc>::from_timestamp(
+                    i as i64 * 3600,
+                    0,
+                )
+                .unwrap(),
+                kwh: *kwh,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lttb_keeps_ends_and_spikes() {
+        let mut values = vec![1.0; 100];
+        values[37] = 50.0;
+        values[80] = -20.0;
+        let points = series(&values);
+
+        let sampled = lttb(&points, 10);
+        assert_eq!(sampled.len(), 10);
+        assert_eq!(sampled[0], points[0]);
+        assert_eq!(sampled[9], points[99]);
+        assert!(sampled.contains(&points[37]));
+        assert!(sampled.contains(&points[80]));
+        assert!(
+            sampled
+                .windows(2)
+                .all(|w| w[0].reading_time < w[1].reading_time)
+        );
+
+        assert_eq!(lttb(&points[..5], 10), points[..5].to_vec());
+    }
+
+    #[test]
+    fn test_min_max_per_bucket() {
+        let points = series(&[3.0, 9.0, 1.0, 4.0, 4.0, 4.0, 7.0, 2.0]);
+
+        let sampled = min_max(&points, 4);
+        // Buckets [3, 9, 1, 4] and [4, 4, 7, 2], in time order
+        let values = sampled.iter().map(|p| p.kwh).collect::<Vec<_>>();
+        assert_eq!(values, vec![9.0, 1.0, 7.0, 2.0]);
+        assert_eq!(min_max(&points, 8).len(), 8);
+    }
+}
@@ -0,0 +1,37 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid query parameters: {0}")]
+    InvalidQuery(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::InvalidQuery(message) => WireV1Error::bad_request(
+                "Invalid query parameters".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_query".to_string(),
+                    message,
+                    suggestion: "Send the query parameters of the \
+                                 aggregation to estimate, as for \
+                                 `POST /energy/aggregate`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Aggregation estimate failed";
+}
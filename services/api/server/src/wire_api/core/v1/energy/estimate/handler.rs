@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::Query;
+use axum::extract::rejection::QueryRejection;
+use axum::http::StatusCode;
+use chrono::Utc;
+use redis_cache::connection::Pool;
+
+use crate::admission::aggregation_cost;
+use crate::circuit_breaker::{CircuitBreaker, Dependency};
+use crate::flags::Flag;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::shared::response_cache;
+use crate::wire_api::core::v1::energy::aggregate::handler::cache_key;
+use crate::wire_api::core::v1::energy::aggregate::models::{
+    AggregateParams, AggregateRequest,
+};
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::AggregateEstimateResponse;
+
+const HANDLER_NAME: &str = "energy_aggregate_estimate";
+
+/// Estimate an aggregation before running it
+///
+/// Takes the query parameters and body of `POST /energy/aggregate` and,
+/// without aggregating, returns how many periods the aggregation has, how
+/// many readings the database expects to scan for it, its admission cost
+/// and whether its response is cached, so clients can warn before
+/// launching a long aggregation. Uncached aggregations always run over the
+/// readings themselves, there are no precomputed rollups of them.
+#[utoipa::path(
+    post,
+    path = "/energy/aggregate/estimate",
+    params(AggregateParams),
+    request_body = AggregateRequest,
+    responses(
+        (status = 200, description = "Estimate of the aggregation", body = AggregateEstimateResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_aggregate_estimate")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    params: Result<Query<AggregateParams>, QueryRejection>,
+    ValidatedPayload(payload): ValidatedPayload<AggregateRequest>,
+) -> HandlerResult<(StatusCode, Json<AggregateEstimateResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+    let format = params.decimal_format().map_err(|e| {
+        recorder.record("invalid_query", errors::Error::InvalidQuery(e))
+    })?;
+
+    let estimate = ctx
+        .state
+        .readings
+        .estimate(
+            &ctx.tenant.tenant_id,
+            None,
+            payload.trunc_level(),
+            payload.date_from,
+            payload.date_to,
+        )
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let cost = aggregation_cost(
+        payload.aggregation_type,
+        payload.date_from,
+        payload.date_to,
+        Utc::now(),
+    );
+    // Explained aggregations bypass the cache
+    let cached =
+        if !params.explain && ctx.flag_enabled(Flag::AggregateCache).await {
+            let key = cache_key(&ctx.tenant, &payload, &params, &format);
+            let redis = ctx.state.breakers.get(HANDLER_NAME, Dependency::Redis);
+            cache_contains(&redis, &ctx.state.cache_pool, &key).await
+        } else {
+            false
+        };
+
+    Ok((
+        StatusCode::OK,
+        Json(AggregateEstimateResponse {
+            aggregation_type: payload.aggregation_type,
+            date_from: payload.date_from,
+            date_to: payload.date_to,
+            bucket_count: estimate.periods,
+            scanned_rows: estimate.scanned_rows,
+            cost,
+            over_budget: ctx.state.admission.over_budget(cost),
+            cached,
+        }),
+    ))
+}
+
+/// Whether a response is cached at `key`, `false` while Redis' breaker is
+/// open.
+async fn cache_contains(
+    breaker: &Arc<CircuitBreaker>,
+    cache: &Pool,
+    key: &str,
+) -> bool {
+    let Ok(permit) = breaker.acquire() else {
+        return false;
+    };
+    let exists = response_cache::try_exists(cache, key).await;
+    permit.record(&exists);
+    exists.unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::wire_api::testing::{reading, readings_server};
+
+    #[tokio::test]
+    async fn test_estimates_without_aggregating() {
+        let at =
+            |day, hour| Utc.with_ymd_and_hms(2025, 1, day, hour, 0, 0).unwrap();
+        let server = readings_server(vec![
+            reading(at(1, 0), "1"),
+            reading(at(1, 12), "1"),
+            reading(at(3, 6), "1"),
+        ])
+        .await;
+
+        let response = server
+            .post("/api/wire/v1/energy/aggregate/estimate")
+            .json(&json!({"aggregationType": "day_of_month"}))
+            .await;
+        response.assert_status_ok();
+        let estimate = response.json::<AggregateEstimateResponse>();
+        // From the first reading's day to the last's
+        assert_eq!(estimate.bucket_count, 3);
+        assert_eq!(estimate.scanned_rows, 3);
+        assert!(!estimate.cached);
+
+        let response = server
+            .post("/api/wire/v1/energy/aggregate/estimate")
+            .json(&json!({
+                "aggregationType": "hourly",
+                "dateFrom": at(1, 0),
+                "dateTo": at(2, 0),
+            }))
+            .await;
+        response.assert_status_ok();
+        let estimate = response.json::<AggregateEstimateResponse>();
+        assert_eq!(estimate.bucket_count, 24);
+        assert_eq!(estimate.scanned_rows, 2);
+        assert_eq!(estimate.cost, 24 * 4);
+
+        server
+            .post("/api/wire/v1/energy/aggregate/estimate?decimals=9")
+            .json(&json!({"aggregationType": "hourly"}))
+            .await
+            .assert_status_bad_request();
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+/// What running an aggregation would take
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateEstimateResponse {
+    pub aggregation_type: AggregationType,
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Periods of the range, or from the first to the last reading, as
+    /// returned with `fill_gaps=true`; at most as many without
+    #[schema(example = 744)]
+    pub bucket_count: i64,
+
+    /// Readings the database expects to scan, from its planner's
+    /// statistics
+    #[schema(example = 2976)]
+    pub scanned_rows: i64,
+
+    /// Cost of the aggregation for admission control
+    #[schema(example = 2976)]
+    pub cost: u64,
+
+    /// Whether the cost is over `ADMISSION_BUDGET`, so the aggregation
+    /// waits, or is rejected, while the database is busy
+    pub over_budget: bool,
+
+    /// Whether the response is cached, so the aggregation would not reach
+    /// the database
+    pub cached: bool,
+}
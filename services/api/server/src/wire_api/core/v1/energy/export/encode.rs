@@ -0,0 +1,226 @@
+//! Arrow record batches of query results, written as Parquet or Arrow IPC.
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, Decimal128Array, RecordBatch, TimestampMicrosecondArray,
+};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use postgres_models::models::energy_readings::{
+    AggregatedReading, EnergyReading,
+};
+
+use super::models::ExportFormat;
+
+/// Scale of `energy_readings.quantity_kwh`, `NUMERIC(12, 4)`.
+pub const KWH_SCALE: i8 = 4;
+pub const READING_PRECISION: u8 = 12;
+/// Sums can exceed the column precision.
+const TOTAL_PRECISION: u8 = 38;
+
+pub type EncodeResult<T> = Result<T, String>;
+
+pub fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+}
+
+pub fn readings_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("reading_time", timestamp_type(), false),
+        Field::new(
+            "quantity_kwh",
+            DataType::Decimal128(READING_PRECISION, KWH_SCALE),
+            false,
+        ),
+    ]))
+}
+
+pub fn aggregate_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("period", timestamp_type(), false),
+        Field::new(
+            "total_kwh",
+            DataType::Decimal128(TOTAL_PRECISION, KWH_SCALE),
+            false,
+        ),
+    ]))
+}
+
+pub fn timestamps(times: impl Iterator<Item = DateTime<Utc>>) -> ArrayRef {
+    Arc::new(
+        TimestampMicrosecondArray::from_iter_values(
+            times.map(|time| time.timestamp_micros()),
+        )
+        .with_timezone("UTC"),
+    )
+}
+
+pub fn decimals<'a>(
+    values: impl Iterator<Item = &'a BigDecimal>,
+    precision: u8,
+) -> EncodeResult<ArrayRef> {
+    let values = values
+        .map(|value| {
+            let (unscaled, _) =
+                value.with_scale(KWH_SCALE.into()).as_bigint_and_exponent();
+            unscaled
+                .to_i128()
+                .ok_or_else(|| format!("{value} does not fit a decimal"))
+        })
+        .collect::<EncodeResult<Vec<_>>>()?;
+
+    Decimal128Array::from(values)
+        .with_precision_and_scale(precision, KWH_SCALE)
+        .map(|array| Arc::new(array) as ArrayRef)
+        .map_err(|e| e.to_string())
+}
+
+pub fn readings_batch(readings: &[EnergyReading]) -> EncodeResult<RecordBatch> {
+    RecordBatch::try_new(
+        readings_schema(),
+        vec![
+            timestamps(readings.iter().map(|r| r.reading_time)),
+            decimals(
+                readings.iter().map(|r| &r.quantity_kwh),
+                READING_PRECISION,
+            )?,
+        ],
+    )
+    .map_err(|e| e.to_string())
+}
+
+pub fn aggregate_batch(
+    rows: &[AggregatedReading],
+) -> EncodeResult<RecordBatch> {
+    RecordBatch::try_new(
+        aggregate_schema(),
+        vec![
+            timestamps(rows.iter().map(|r| r.period)),
+            decimals(rows.iter().map(|r| &r.total_kwh), TOTAL_PRECISION)?,
+        ],
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Writes record batches into an in-memory file.
+pub enum Writer {
+    Parquet(ArrowWriter<Vec<u8>>),
+    Arrow(FileWriter<Vec<u8>>),
+}
+
+impl Writer {
+    pub fn new(format: ExportFormat, schema: SchemaRef) -> EncodeResult<Self> {
+        match format {
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                ArrowWriter::try_new(Vec::new(), schema, Some(properties))
+                    .map(Writer::Parquet)
+                    .map_err(|e| e.to_string())
+            }
+            ExportFormat::Arrow => FileWriter::try_new(Vec::new(), &schema)
+                .map(Writer::Arrow)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> EncodeResult<()> {
+        match self {
+            Writer::Parquet(writer) => {
+                writer.write(batch).map_err(|e| e.to_string())
+            }
+            Writer::Arrow(writer) => {
+                writer.write(batch).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Write the footer and return the file contents.
+    pub fn finish(self) -> EncodeResult<Vec<u8>> {
+        match self {
+            Writer::Parquet(writer) => {
+                writer.into_inner().map_err(|e| e.to_string())
+            }
+            Writer::Arrow(writer) => {
+                writer.into_inner().map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+
+    fn reading(time: &str, kwh: &str) -> EnergyReading {
+        let time = time.parse().unwrap();
+        EnergyReading {
+            id: uuid::Uuid::new_v4(),
+            reading_time: time,
+            quantity_kwh: kwh.parse().unwrap(),
+            created_at: time,
+            updated_at: time,
+            tenant_id: "default".to_string(),
+            plant_id: None,
+            quality_code: None,
+            source: "file".to_string(),
+        }
+    }
+
+    fn check(batch: &RecordBatch) {
+        assert_eq!(batch.schema(), readings_schema());
+        assert_eq!(batch.num_rows(), 2);
+        let kwh = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Decimal128Array>()
+            .unwrap();
+        assert_eq!(kwh.value_as_string(0), "9000.2500");
+        assert_eq!(kwh.value_as_string(1), "0.0001");
+        let times = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(times.value(1), 1_735_693_200_000_000);
+        assert!(!times.is_null(0));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let batch = readings_batch(&[
+            reading("2025-01-01T00:00:00Z", "9000.25"),
+            reading("2025-01-01T01:00:00Z", "0.0001"),
+        ])
+        .unwrap();
+
+        let mut parquet =
+            Writer::new(ExportFormat::Parquet, readings_schema()).unwrap();
+        parquet.write(&batch).unwrap();
+        let file = axum::body::Bytes::from(parquet.finish().unwrap());
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        check(&reader.next().unwrap().unwrap());
+
+        let mut arrow =
+            Writer::new(ExportFormat::Arrow, readings_schema()).unwrap();
+        arrow.write(&batch).unwrap();
+        let file = Cursor::new(arrow.finish().unwrap());
+        let mut reader = FileReader::try_new(file, None).unwrap();
+        check(&reader.next().unwrap().unwrap());
+    }
+}
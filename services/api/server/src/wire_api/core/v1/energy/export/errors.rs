@@ -0,0 +1,34 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Failed to encode export: {0}")]
+    EncodeError(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::EncodeError(e) => WireV1Error::internal_server_error(
+                "Export failed".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "encode_error".to_string(),
+                    message: format!("Failed to encode export: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Export failed";
+}
@@ -0,0 +1,180 @@
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use postgres_models::models::Keyset;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, EnergyReading, ReadingFilter,
+};
+use postgres_models::models::query_history::NewQueryHistory;
+
+use crate::admission::aggregation_cost;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::encode::{self, Writer};
+use super::errors::{self, HandlerResult};
+use super::models::{ExportDataset, ExportRequest};
+
+const HANDLER_NAME: &str = "energy_export";
+/// Readings fetched per query, and rows per record batch.
+const PAGE_SIZE: i64 = 10_000;
+
+/// Export energy data as Parquet or Arrow
+///
+/// Returns readings or aggregates as a columnar file that pandas, Polars or
+/// DuckDB read directly. Times are UTC microsecond timestamps and energy is
+/// a decimal with 4 fractional digits.
+#[utoipa::path(
+    post,
+    path = "/energy/export",
+    request_body = ExportRequest,
+    responses(
+        (status = 200, description = "Parquet or Arrow IPC file", content(
+            (Vec<u8> = "application/vnd.apache.parquet"),
+            (Vec<u8> = "application/vnd.apache.arrow.file"),
+        )),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 429, description = "Too many expensive aggregations waiting for the database"),
+        (status = 500, description = "Internal server error"),
+        (status = 503, description = "Database busy for an aggregation over budget"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_export")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<ExportRequest>,
+) -> HandlerResult<Response> {
+    tracing::info!(
+        dataset = payload.dataset.as_str(),
+        format = payload.format.extension(),
+        date_from = ?payload.date_from,
+        date_to = ?payload.date_to,
+        request_id = %ctx.request_id,
+        "Energy export request",
+    );
+
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let encode_error = |e: String| {
+        recorder.record("encode_error", errors::Error::EncodeError(e))
+    };
+    let date_from = payload.date_from;
+    let date_to = payload.date_to;
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let file = match (payload.dataset, payload.aggregation_type) {
+        (ExportDataset::Aggregate, Some(aggregation_type)) => {
+            let new_entry = NewQueryHistory {
+                aggregation_type: aggregation_type.to_string(),
+                date_from,
+                date_to,
+                api_key_id: ctx.caller.as_ref().map(|c| c.api_key_id),
+                tenant_id: tenant_id.clone(),
+                created_at: None,
+            };
+            ctx.state
+                .record_query(new_entry)
+                .await
+                .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+            let cost = aggregation_cost(
+                aggregation_type,
+                date_from,
+                date_to,
+                Utc::now(),
+            );
+            if let Err(rejection) = ctx
+                .state
+                .admission
+                .admit(cost, ctx.state.reads.serving())
+                .await
+            {
+                return Ok(rejection.into_response(&recorder));
+            }
+
+            let trunc_level = aggregation_type.to_trunc_level();
+            let rows = ctx
+                .state
+                .readings
+                .aggregate(
+                    tenant_id,
+                    None,
+                    trunc_level,
+                    date_from,
+                    date_to,
+                    AggregateOptions::default(),
+                )
+                .await
+                .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+            let mut writer =
+                Writer::new(payload.format, encode::aggregate_schema())
+                    .map_err(encode_error)?;
+            writer
+                .write(&encode::aggregate_batch(&rows).map_err(encode_error)?)
+                .map_err(encode_error)?;
+            writer.finish().map_err(encode_error)?
+        }
+        _ => {
+            let mut writer =
+                Writer::new(payload.format, encode::readings_schema())
+                    .map_err(encode_error)?;
+            let mut keyset = Keyset::First;
+            loop {
+                let page = ctx
+                    .state
+                    .reads
+                    .with_connection(|mut conn| async move {
+                        let filter = ReadingFilter {
+                            date_from,
+                            date_to,
+                            ..Default::default()
+                        };
+                        EnergyReading::page(
+                            tenant_id, keyset, filter, PAGE_SIZE, &mut conn,
+                        )
+                        .await
+                    })
+                    .await
+                    .map_err(|e| {
+                        recorder.record_database::<errors::Error>(e)
+                    })?;
+
+                if !page.is_empty() {
+                    writer
+                        .write(
+                            &encode::readings_batch(&page)
+                                .map_err(encode_error)?,
+                        )
+                        .map_err(encode_error)?;
+                }
+                if (page.len() as i64) < PAGE_SIZE {
+                    break;
+                }
+                keyset = page.last().map_or(keyset, EnergyReading::cursor);
+            }
+            writer.finish().map_err(encode_error)?
+        }
+    };
+
+    let filename = format!(
+        "{}.{}",
+        payload.dataset.as_str(),
+        payload.format.extension()
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                payload.format.content_type().to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        file,
+    )
+        .into_response())
+}
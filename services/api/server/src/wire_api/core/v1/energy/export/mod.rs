@@ -0,0 +1,4 @@
+pub(crate) mod encode;
+mod errors;
+pub mod handler;
+pub mod models;
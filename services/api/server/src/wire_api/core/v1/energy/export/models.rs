@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+/// Data to export
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportDataset {
+    /// Raw readings, in time order
+    Readings,
+    /// Readings summed by `aggregationType`
+    Aggregate,
+}
+
+impl ExportDataset {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportDataset::Readings => "readings",
+            ExportDataset::Aggregate => "aggregate",
+        }
+    }
+}
+
+/// File format of the export
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Apache Parquet, Snappy compressed
+    Parquet,
+    /// Arrow IPC file (Feather v2)
+    Arrow,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+            ExportFormat::Arrow => "application/vnd.apache.arrow.file",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Arrow => "arrow",
+        }
+    }
+}
+
+fn validate_export(request: &ExportRequest) -> Result<(), ValidationError> {
+    if request.dataset == ExportDataset::Aggregate
+        && request.aggregation_type.is_none()
+    {
+        return Err(ValidationError::new("aggregationType")
+            .with_message("required for the aggregate dataset".into()));
+    }
+    Ok(())
+}
+
+/// Request payload for exporting energy data as a columnar file
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_export"))]
+pub struct ExportRequest {
+    /// Data to export
+    #[schema(example = "readings")]
+    pub dataset: ExportDataset,
+
+    /// File format
+    #[schema(example = "parquet")]
+    pub format: ExportFormat,
+
+    /// Aggregation granularity, required for the aggregate dataset
+    #[schema(example = "monthly")]
+    pub aggregation_type: Option<AggregationType>,
+
+    /// Start of date range (inclusive, optional)
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// End of date range (exclusive, optional)
+    #[schema(example = "2025-04-01T00:00:00Z")]
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+}
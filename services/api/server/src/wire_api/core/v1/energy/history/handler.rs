@@ -1,12 +1,7 @@
 use axum::Json;
-use axum::extract::State;
 use axum::http::StatusCode;
-use postgres_models::connection::{WithConnectionError, with_connection};
-use postgres_models::models::query_history::QueryHistory;
 
-use crate::AppState;
-use crate::shared::extractors::request_id::RequestId;
-use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
 
 use super::errors::{self, HandlerResult};
 use super::models::{HistoryResponse, QueryHistoryEntry};
@@ -16,7 +11,8 @@ const HISTORY_LIMIT: i64 = 10;
 
 /// Get the last 10 aggregation queries
 ///
-/// Returns the most recent query history entries with their filter parameters.
+/// Returns the caller's tenant's most recent query history entries with their
+/// filter parameters.
 #[utoipa::path(
     get,
     path = "/energy/history",
@@ -28,23 +24,16 @@ const HISTORY_LIMIT: i64 = 10;
 )]
 #[tracing::instrument(skip_all, name = "energy_history")]
 pub async fn handler(
-    State(state): State<AppState>,
-    RequestId(request_id): RequestId,
+    ctx: HandlerCtx,
 ) -> HandlerResult<(StatusCode, Json<HistoryResponse>)> {
-    let recorder =
-        ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id);
+    let recorder = ctx.recorder(HANDLER_NAME);
 
-    let entries =
-        with_connection(&state.read_only_pool, |mut conn| async move {
-            QueryHistory::get_latest(HISTORY_LIMIT, &mut conn).await
-        })
+    let entries = ctx
+        .state
+        .query_history
+        .latest(&ctx.tenant.tenant_id, HISTORY_LIMIT)
         .await
-        .map_err(|e| match e {
-            WithConnectionError::Pool(e) => recorder
-                .record("pool_error", errors::Error::PoolError(e.to_string())),
-            WithConnectionError::Operation(e) => recorder
-                .record("database_error", errors::Error::DatabaseError(e)),
-        })?;
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
 
     let queries = entries
         .into_iter()
@@ -53,9 +42,52 @@ pub async fn handler(
             aggregation_type: e.aggregation_type,
             date_from: e.date_from,
             date_to: e.date_to,
+            api_key_id: e.api_key_id,
             created_at: e.created_at,
         })
         .collect();
 
     Ok((StatusCode::OK, Json(HistoryResponse { queries })))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use postgres_models::models::query_history::NewQueryHistory;
+
+    use crate::repository::QueryHistoryRepository;
+    use crate::repository::memory::InMemoryQueryHistory;
+    use crate::wire_api::testing::{DEFAULT_TENANT, in_memory_server};
+
+    #[tokio::test]
+    async fn test_lists_the_callers_latest_queries() {
+        let history = Arc::new(InMemoryQueryHistory::default());
+        for (tenant, aggregation_type) in [
+            (DEFAULT_TENANT, "hourly"),
+            ("other", "monthly"),
+            (DEFAULT_TENANT, "day_of_month"),
+        ] {
+            let entry = NewQueryHistory {
+                aggregation_type: aggregation_type.to_string(),
+                date_from: None,
+                date_to: None,
+                api_key_id: None,
+                tenant_id: tenant.to_string(),
+                created_at: None,
+            };
+            history.create(entry).await.unwrap();
+        }
+        let server = in_memory_server(Arc::default(), history).await;
+
+        let response = server.get("/api/wire/v1/energy/history").await;
+        response.assert_status_ok();
+        let types = response.json::<serde_json::Value>()["queries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|q| q["aggregationType"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["day_of_month", "hourly"]);
+    }
+}
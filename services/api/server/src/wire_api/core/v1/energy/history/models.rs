@@ -11,6 +11,8 @@ pub struct QueryHistoryEntry {
     pub date_from: Option<chrono::DateTime<chrono::Utc>>,
     #[schema(example = "2025-04-01T00:00:00Z")]
     pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+    /// API key that issued the query, when authentication is enabled
+    pub api_key_id: Option<uuid::Uuid>,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -0,0 +1,66 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid quantity at index {index}: {quantity}")]
+    InvalidQuantity { index: usize, quantity: f64 },
+
+    #[error("Reading ingestion is disabled")]
+    IngestionDisabled,
+
+    #[error("Plant not found: {0}")]
+    PlantNotFound(Uuid),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::InvalidQuantity { index, quantity } => {
+                WireV1Error::bad_request(
+                    "Invalid request parameters".to_string(),
+                    vec![WireV1Detail {
+                        field: Some(format!("readings[{index}].quantityKwh")),
+                        code: "invalid_quantity".to_string(),
+                        message: format!("Quantity {quantity} is not a number"),
+                        suggestion: "Send a finite quantity in kWh".to_string(),
+                        documentation: String::new(),
+                    }],
+                    request_id.to_string(),
+                )
+            }
+            Error::IngestionDisabled => WireV1Error::service_unavailable(
+                "Reading ingestion is disabled".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "ingestion_disabled".to_string(),
+                    message: "The reading_ingestion feature flag is off"
+                        .to_string(),
+                    suggestion: "Retry once ingestion is enabled again"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::PlantNotFound(id) => WireV1Error::not_found(
+                "Plant not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("plantId".to_string()),
+                    code: "plant_not_found".to_string(),
+                    message: format!("No plant exists with id {id}"),
+                    suggestion: "Check the plant id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Failed to store energy readings";
+}
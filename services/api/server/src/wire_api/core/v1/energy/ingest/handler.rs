@@ -0,0 +1,206 @@
+use axum::Json;
+use axum::http::StatusCode;
+use diesel_async::AsyncConnection;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use postgres_models::connection::with_connection;
+use postgres_models::models::energy_readings::{
+    EnergyReading, NewEnergyReading,
+};
+use postgres_models::models::plants::Plant;
+use tracing::Instrument;
+
+use crate::auth::Caller;
+use crate::data_loader::kwh_decimal;
+use crate::events::DomainEvent;
+use crate::flags::Flag;
+use crate::notifications;
+use crate::outbox;
+use crate::quantity_policy::QuantityCounts;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::shared::response_cache;
+use crate::webhooks::WebhookEvent;
+use crate::wire_api::core::v1::energy::aggregate;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{IngestRequest, IngestResponse};
+
+const HANDLER_NAME: &str = "energy_ingest";
+const BATCH_SIZE: usize = 1000;
+
+/// Load energy readings
+///
+/// Requires the `ingest` role and a request signed with the API key's
+/// signing secret. Readings for an already stored time, of the same plant,
+/// are skipped. Negative and outlier quantities are stored, dropped,
+/// clamped or flagged as the deployment's quantity policy says. The
+/// tenant's cached aggregations are dropped once new readings are stored.
+#[utoipa::path(
+    post,
+    path = "/energy/readings",
+    request_body = IngestRequest,
+    params(
+        ("X-Wire-Timestamp" = i64, Header, description = "Unix time the request was signed at"),
+        ("X-Wire-Signature" = String, Header, description = "`sha256=` HMAC of `{timestamp}.{body}`"),
+    ),
+    responses(
+        (status = 201, description = "Readings stored", body = IngestResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 401, description = "Missing credentials, or invalid, expired or replayed signature"),
+        (status = 403, description = "Ingest role and a signing secret required"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+        (status = 503, description = "Ingestion is disabled by the `reading_ingestion` feature flag"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_ingest")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    caller: Caller,
+    ValidatedPayload(payload): ValidatedPayload<IngestRequest>,
+) -> HandlerResult<(StatusCode, Json<IngestResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    if !ctx.flag_enabled(Flag::ReadingIngestion).await {
+        return Err(recorder
+            .record("ingestion_disabled", errors::Error::IngestionDisabled));
+    }
+
+    let plant_id = payload.plant_id;
+    if let Some(plant_id) = plant_id {
+        let tenant_id = &ctx.tenant.tenant_id;
+        let plant = with_connection(&ctx.state.pool, |mut conn| async move {
+            Plant::find(tenant_id, plant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+        if plant.is_none() {
+            return Err(recorder.record(
+                "plant_not_found",
+                errors::Error::PlantNotFound(plant_id),
+            ));
+        }
+    }
+
+    let received = payload.readings.len();
+    let source = payload.source.as_str();
+    let policy = ctx.state.config.quantity_policy;
+    let mut quantities = QuantityCounts::default();
+    let mut readings = Vec::with_capacity(received);
+    for (index, reading) in payload.readings.into_iter().enumerate() {
+        let invalid = || {
+            recorder.record(
+                "invalid_quantity",
+                errors::Error::InvalidQuantity {
+                    index,
+                    quantity: reading.quantity_kwh,
+                },
+            )
+        };
+        if !reading.quantity_kwh.is_finite() {
+            return Err(invalid());
+        }
+        let Some(checked) = policy.check(reading.quantity_kwh, &mut quantities)
+        else {
+            continue;
+        };
+        let quantity_kwh = kwh_decimal(checked.kwh).map_err(|_| invalid())?;
+        readings.push(NewEnergyReading {
+            reading_time: reading.reading_time,
+            quantity_kwh,
+            tenant_id: ctx.tenant.tenant_id.clone(),
+            plant_id,
+            quality_code: checked.quality_code.map(str::to_string),
+            source: source.to_string(),
+        });
+    }
+
+    let tenant_id = ctx.tenant.tenant_id.clone();
+    let api_key_id = caller.api_key_id;
+    let result = with_connection(&ctx.state.pool, |mut conn| async move {
+        conn.transaction::<_, diesel::result::Error, _>(move |conn| {
+            async move {
+                let mut inserted = 0;
+                for chunk in readings.chunks(BATCH_SIZE) {
+                    inserted +=
+                        EnergyReading::bulk_insert(chunk.to_vec(), conn)
+                            .await?;
+                }
+                let event_data = serde_json::json!({
+                    "apiKeyId": api_key_id,
+                    "tenant": tenant_id,
+                    "plantId": plant_id,
+                    "source": source,
+                    "inserted": inserted,
+                    "total": received,
+                    "rejected": quantities.rejected,
+                    "clamped": quantities.clamped,
+                    "flagged": quantities.flagged,
+                });
+                outbox::record(
+                    WebhookEvent::ImportCompleted,
+                    &tenant_id,
+                    event_data,
+                    conn,
+                )
+                .await?;
+                Ok(inserted)
+            }
+            .scope_boxed()
+        })
+        .await
+    })
+    .await;
+    if let Err(e) = &result {
+        notifications::notify_import_failed(
+            &ctx.state.pool,
+            &ctx.tenant.tenant_id,
+            &format!("ingest by API key {}", caller.api_key_id),
+            &e.to_string(),
+        )
+        .await;
+    }
+    let inserted =
+        result.map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    tracing::info!(
+        api_key_id = %caller.api_key_id,
+        tenant = %ctx.tenant.tenant_id,
+        received,
+        inserted,
+        rejected = quantities.rejected,
+        clamped = quantities.clamped,
+        flagged = quantities.flagged,
+        "Stored signed energy readings"
+    );
+    ctx.state
+        .domain_events
+        .publish(DomainEvent::ImportCompleted {
+            tenant_id: ctx.tenant.tenant_id.clone(),
+            api_key_id: caller.api_key_id,
+            inserted,
+            total: received,
+        });
+    if inserted > 0 && ctx.flag_enabled(Flag::AggregateCache).await {
+        let cache = ctx.state.cache_pool.clone();
+        let prefix = aggregate::handler::cache_prefix(&ctx.tenant);
+        tokio::spawn(
+            async move {
+                response_cache::delete_prefix(&cache, &prefix).await;
+            }
+            .instrument(ctx.span.clone()),
+        );
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IngestResponse {
+            received,
+            inserted,
+            rejected: quantities.rejected,
+            clamped: quantities.clamped,
+            flagged: quantities.flagged,
+        }),
+    ))
+}
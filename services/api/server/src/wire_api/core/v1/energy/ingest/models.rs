@@ -0,0 +1,100 @@
+use postgres_models::models::energy_readings::reading_source;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Where readings came from
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    PartialEq,
+    Eq,
+    async_graphql::Enum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingSource {
+    /// An imported readings file
+    File,
+    /// A push to `POST /energy/readings`
+    #[default]
+    Webhook,
+    /// A push relayed from Kafka
+    Kafka,
+    /// A manual correction
+    Manual,
+}
+
+impl ReadingSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReadingSource::File => reading_source::FILE,
+            ReadingSource::Webhook => reading_source::WEBHOOK,
+            ReadingSource::Kafka => reading_source::KAFKA,
+            ReadingSource::Manual => reading_source::MANUAL,
+        }
+    }
+}
+
+impl std::str::FromStr for ReadingSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            reading_source::FILE => Ok(ReadingSource::File),
+            reading_source::WEBHOOK => Ok(ReadingSource::Webhook),
+            reading_source::KAFKA => Ok(ReadingSource::Kafka),
+            reading_source::MANUAL => Ok(ReadingSource::Manual),
+            other => Err(format!("Unknown reading source `{other}`")),
+        }
+    }
+}
+
+/// A single energy reading
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestReading {
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub reading_time: chrono::DateTime<chrono::Utc>,
+
+    /// Energy in kWh, stored with 4 decimal places
+    #[schema(example = 12.5)]
+    pub quantity_kwh: f64,
+}
+
+/// Request payload for loading energy readings
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestRequest {
+    /// Readings to store; readings for an already stored time are skipped
+    #[validate(length(min = 1, max = 10000))]
+    pub readings: Vec<IngestReading>,
+
+    /// Plant the readings are attributed to
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Where the readings came from, recorded with each of them
+    #[serde(default)]
+    #[schema(example = "webhook")]
+    pub source: ReadingSource,
+}
+
+/// Response after loading energy readings
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestResponse {
+    /// Number of readings in the request
+    pub received: usize,
+    /// Number of new readings stored
+    pub inserted: usize,
+    /// Readings dropped by the quantity policy
+    pub rejected: usize,
+    /// Readings whose quantity the policy clamped
+    pub clamped: usize,
+    /// Readings stored with a quality code, `negative` or `outlier`
+    pub flagged: usize,
+}
@@ -1,14 +1,115 @@
 use axum::Router;
+use axum::middleware::{from_extractor, from_fn_with_state};
+use utoipa::OpenApi;
+
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+use crate::shared::response_cache::{self, CacheGroup};
+use crate::wire_api::core::v1::preferences::defaults as preferences;
 
 pub mod aggregate;
+pub mod cost;
+pub mod delete;
+pub mod downsample;
+pub mod estimate;
+pub mod export;
 pub mod history;
+pub mod ingest;
+pub mod normalized;
+mod openapi;
+pub mod poll;
+pub mod quality;
+pub mod readings;
+pub mod saved_queries;
+pub mod targets;
 
+/// Energy routes, without those of a disabled [`EndpointGroup`].
 pub fn get_routes(state: crate::AppState) -> Router {
-    Router::new()
+    let endpoints = &state.config.endpoints;
+    let ingest = Router::new()
+        .route("/readings", axum::routing::post(ingest::handler::handler))
+        .route_layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::middleware::verify_signature,
+        ))
+        .route_layer(from_extractor::<RequirePermission<permission::Ingest>>());
+
+    // Preferences of the calling API key fill in what aggregations omit
+    let defaults = from_fn_with_state(state.clone(), preferences::apply);
+    let query = Router::new()
         .route(
             "/aggregate",
-            axum::routing::post(aggregate::handler::handler),
+            axum::routing::post(aggregate::handler::handler)
+                .layer(defaults.clone()),
+        )
+        .route(
+            "/aggregate/estimate",
+            axum::routing::post(estimate::handler::handler).layer(defaults),
+        )
+        .route("/cost", axum::routing::post(cost::handler::handler))
+        .route(
+            "/downsample",
+            axum::routing::post(downsample::handler::handler),
+        )
+        .route("/export", axum::routing::post(export::handler::handler))
+        .route(
+            "/history",
+            axum::routing::get(history::handler::handler).layer(
+                from_fn_with_state(
+                    (state.clone(), CacheGroup::Energy),
+                    response_cache::cache_response,
+                ),
+            ),
+        )
+        .route(
+            "/normalized",
+            axum::routing::post(normalized::handler::handler),
         )
-        .route("/history", axum::routing::get(history::handler::handler))
-        .with_state(state)
+        .route("/quality", axum::routing::post(quality::handler::handler))
+        .route("/readings", axum::routing::get(readings::handler::handler))
+        .route("/readings/poll", axum::routing::get(poll::handler::handler))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .with_state(state.clone())
+        .nest("/saved-queries", saved_queries::get_routes(state.clone()))
+        .nest("/targets", targets::get_routes(state.clone()));
+
+    let manage = Router::new()
+        .route("/readings", axum::routing::delete(delete::handler::handler))
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>())
+        .with_state(state.clone());
+
+    let mut router = Router::new();
+    if endpoints.is_enabled(EndpointGroup::Energy) {
+        router = router.merge(query).merge(manage);
+    }
+    if endpoints.is_enabled(EndpointGroup::Ingestion) {
+        router = router.merge(ingest.with_state(state.clone()));
+    }
+    router.layer(from_fn_with_state(
+        (state, CacheGroup::Energy),
+        response_cache::invalidate,
+    ))
+}
+
+/// Energy readings: their ingestion and the queries over them.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "energy"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Energy)
+            || endpoints.is_enabled(EndpointGroup::Ingestion)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/energy", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
 }
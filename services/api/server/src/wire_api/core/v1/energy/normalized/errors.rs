@@ -0,0 +1,58 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+use super::models::MIN_BASELINE_DAYS;
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Plant {0} not found")]
+    PlantNotFound(Uuid),
+
+    #[error("Only {0} baseline days have both readings and weather")]
+    InsufficientBaseline(usize),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::PlantNotFound(id) => WireV1Error::not_found(
+                "Plant not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("plantId".to_string()),
+                    code: "plant_not_found".to_string(),
+                    message: format!("Plant {id} not found"),
+                    suggestion: "Use the id of one of your plants".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InsufficientBaseline(days) => {
+                WireV1Error::unprocessable_entity(
+                    "Not enough baseline data".to_string(),
+                    vec![WireV1Detail {
+                        field: Some("baselineFrom".to_string()),
+                        code: "insufficient_baseline".to_string(),
+                        message: format!(
+                            "Only {days} baseline days have both readings \
+                             and weather, at least {MIN_BASELINE_DAYS} are \
+                             needed"
+                        ),
+                        suggestion: "Pick a longer baseline period with \
+                                     imported weather"
+                            .to_string(),
+                        documentation: String::new(),
+                    }],
+                    request_id.to_string(),
+                )
+            }
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Normalization query failed";
+}
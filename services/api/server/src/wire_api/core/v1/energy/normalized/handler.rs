@@ -0,0 +1,149 @@
+use axum::Json;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use diesel_async::AsyncPgConnection;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, AggregatedReading, EnergyReading,
+};
+use postgres_models::models::plants::Plant;
+use postgres_models::models::weather::{AggregatedWeather, WeatherObservation};
+use uuid::Uuid;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    BaselineSummary, NormalizedRequest, NormalizedResponse, daily_degree_days,
+    data_points, fit_baseline,
+};
+
+const HANDLER_NAME: &str = "energy_normalized";
+
+/// The daily energy and weather of a tenant, or of one plant, in
+/// `[from, to)`.
+async fn daily(
+    tenant: &str,
+    plant: Option<Uuid>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    conn: &mut AsyncPgConnection,
+) -> Result<
+    (Vec<AggregatedReading>, Vec<AggregatedWeather>),
+    diesel::result::Error,
+> {
+    let days = EnergyReading::aggregate(
+        tenant,
+        plant,
+        "day",
+        Some(from),
+        Some(to),
+        AggregateOptions::default(),
+        conn,
+    )
+    .await?;
+    let weather = WeatherObservation::aggregate(
+        tenant,
+        plant,
+        "day",
+        Some(from),
+        Some(to),
+        conn,
+    )
+    .await?;
+    Ok((days, weather))
+}
+
+/// Normalize the tenant's energy by degree days
+///
+/// Fits a regression of daily energy on heating and cooling degree days,
+/// from the imported weather at the tenant's plants, over a baseline
+/// period, and reports the energy of each period of the range as it would
+/// have been under the baseline's average weather. Periods of different
+/// years are compared without the weather in between.
+#[utoipa::path(
+    post,
+    path = "/energy/normalized",
+    request_body = NormalizedRequest,
+    responses(
+        (status = 200, description = "Actual and normalized energy by period", body = NormalizedResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 404, description = "Plant not found"),
+        (status = 422, description = "Too few baseline days with readings and weather"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_normalized")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<NormalizedRequest>,
+) -> HandlerResult<(StatusCode, Json<NormalizedResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let plant = payload.plant_id;
+
+    if let Some(plant) = plant {
+        ctx.state
+            .reads
+            .with_connection(|mut conn| async move {
+                Plant::find(tenant_id, plant, &mut conn).await
+            })
+            .await
+            .map_err(|e| recorder.record_database::<errors::Error>(e))?
+            .ok_or_else(|| {
+                recorder.record(
+                    "plant_not_found",
+                    errors::Error::PlantNotFound(plant),
+                )
+            })?;
+    }
+
+    let (baseline_from, baseline_to) =
+        (payload.baseline_from, payload.baseline_to);
+    let (date_from, date_to) = (payload.date_from, payload.date_to);
+    let ((baseline_days, baseline_weather), (days, weather)) = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            let baseline =
+                daily(tenant_id, plant, baseline_from, baseline_to, &mut conn)
+                    .await?;
+            let reported =
+                daily(tenant_id, plant, date_from, date_to, &mut conn).await?;
+            Ok((baseline, reported))
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let heating_base = payload.heating_base_c;
+    let cooling_base = payload.cooling_base_c;
+    let baseline = fit_baseline(
+        &baseline_days,
+        &daily_degree_days(&baseline_weather, heating_base, cooling_base),
+    )
+    .map_err(|days| {
+        recorder.record(
+            "insufficient_baseline",
+            errors::Error::InsufficientBaseline(days),
+        )
+    })?;
+
+    let degree_days = daily_degree_days(&weather, heating_base, cooling_base);
+    let (data, total) =
+        data_points(&days, &degree_days, &baseline, payload.aggregation_type);
+
+    Ok((
+        StatusCode::OK,
+        Json(NormalizedResponse {
+            aggregation_type: payload.aggregation_type,
+            date_from: payload.date_from,
+            date_to: payload.date_to,
+            plant_id: plant,
+            baseline: BaselineSummary::new(&payload, &baseline),
+            total,
+            data,
+        }),
+    ))
+}
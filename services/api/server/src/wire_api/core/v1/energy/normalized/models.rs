@@ -0,0 +1,436 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Datelike, TimeDelta, Utc};
+use postgres_models::models::energy_readings::AggregatedReading;
+use postgres_models::models::weather::AggregatedWeather;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::weather::degree_days::{Baseline, DegreeDays};
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+/// Longest accepted baseline or reporting range, a year.
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+/// Fewest baseline days with both readings and weather a baseline is
+/// fitted over.
+pub const MIN_BASELINE_DAYS: usize = 14;
+
+fn default_heating_base_c() -> f64 {
+    15.5
+}
+
+fn default_cooling_base_c() -> f64 {
+    22.0
+}
+
+fn validate_range(
+    field: &'static str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(), ValidationError> {
+    if to <= from {
+        return Err(ValidationError::new(field)
+            .with_message("Ranges must end after they start".into()));
+    }
+    if (to - from).num_days() > MAX_RANGE_DAYS {
+        return Err(ValidationError::new(field)
+            .with_message("Ranges span at most 366 days".into()));
+    }
+    Ok(())
+}
+
+fn validate_normalization(
+    request: &NormalizedRequest,
+) -> Result<(), ValidationError> {
+    if request.aggregation_type == AggregationType::Hourly {
+        return Err(ValidationError::new("aggregationType").with_message(
+            "Degree days are daily; use day_of_month or longer periods".into(),
+        ));
+    }
+    if request.heating_base_c > request.cooling_base_c {
+        return Err(ValidationError::new("heatingBaseC")
+            .with_message("must not be above coolingBaseC".into()));
+    }
+    validate_range("baselineTo", request.baseline_from, request.baseline_to)?;
+    validate_range("dateTo", request.date_from, request.date_to)
+}
+
+/// Request payload for normalizing energy readings by degree days
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_normalization"))]
+pub struct NormalizedRequest {
+    /// Aggregation granularity, `day_of_month` or `monthly`
+    #[schema(example = "monthly")]
+    pub aggregation_type: AggregationType,
+
+    /// Start of the period the baseline is fitted over (inclusive)
+    #[schema(example = "2024-01-01T00:00:00Z")]
+    pub baseline_from: DateTime<Utc>,
+
+    /// End of the baseline period (exclusive), at most 366 days after
+    /// `baselineFrom`
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub baseline_to: DateTime<Utc>,
+
+    /// Start of the reported range (inclusive)
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub date_from: DateTime<Utc>,
+
+    /// End of the reported range (exclusive), at most 366 days after
+    /// `dateFrom`
+    #[schema(example = "2026-01-01T00:00:00Z")]
+    pub date_to: DateTime<Utc>,
+
+    /// Only normalize the readings, and use the weather, of this plant
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Daily mean temperature below which a day has heating degree days
+    #[serde(default = "default_heating_base_c")]
+    #[validate(range(
+        min = -30.0,
+        max = 40.0,
+        message = "Base temperatures are -30 to 40 °C"
+    ))]
+    #[schema(example = 15.5)]
+    pub heating_base_c: f64,
+
+    /// Daily mean temperature above which a day has cooling degree days
+    #[serde(default = "default_cooling_base_c")]
+    #[validate(range(
+        min = -30.0,
+        max = 40.0,
+        message = "Base temperatures are -30 to 40 °C"
+    ))]
+    #[schema(example = 22.0)]
+    pub cooling_base_c: f64,
+}
+
+/// The regression baseline the readings are normalized with:
+/// `dailyKwh = interceptKwh + heatingSlopeKwh × HDD + coolingSlopeKwh × CDD`
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineSummary {
+    pub baseline_from: DateTime<Utc>,
+    pub baseline_to: DateTime<Utc>,
+    pub heating_base_c: f64,
+    pub cooling_base_c: f64,
+
+    /// Daily energy without degree days
+    #[schema(example = 1520.4)]
+    pub intercept_kwh: f64,
+
+    /// Energy per heating degree day
+    #[schema(example = 38.2)]
+    pub heating_slope_kwh: f64,
+
+    /// Energy per cooling degree day
+    #[schema(example = 0.0)]
+    pub cooling_slope_kwh: f64,
+
+    /// Share of the variance of the daily energy the baseline explains,
+    /// absent when the energy is constant
+    #[schema(example = 0.81)]
+    pub r_squared: Option<f64>,
+
+    /// Baseline days with both readings and weather
+    #[schema(example = 365)]
+    pub days: usize,
+
+    /// Average daily heating degree days of the baseline, the weather
+    /// readings are normalized to
+    #[schema(example = 7.3)]
+    pub normal_heating_degree_days: f64,
+
+    /// Average daily cooling degree days of the baseline
+    #[schema(example = 0.4)]
+    pub normal_cooling_degree_days: f64,
+}
+
+/// Actual and weather-normalized energy of a period
+#[derive(Debug, Default, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizedEnergy {
+    /// Energy read in kWh
+    #[schema(example = "48210.0000")]
+    pub actual_kwh: String,
+
+    /// Energy under the baseline's average weather in kWh
+    #[schema(example = 45102.5)]
+    pub normalized_kwh: f64,
+
+    /// Heating degree days of the days with readings
+    #[schema(example = 312.4)]
+    pub heating_degree_days: f64,
+
+    /// Cooling degree days of the days with readings
+    #[schema(example = 0.0)]
+    pub cooling_degree_days: f64,
+
+    /// Days with readings but no weather, left unadjusted
+    #[schema(example = 0)]
+    pub days_without_weather: u32,
+}
+
+/// Normalized energy of one aggregation period
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizedDataPoint {
+    /// Start of the aggregation period
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub period: DateTime<Utc>,
+
+    #[serde(flatten)]
+    pub energy: NormalizedEnergy,
+}
+
+/// Response for a normalization query
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizedResponse {
+    pub aggregation_type: AggregationType,
+    pub date_from: DateTime<Utc>,
+    pub date_to: DateTime<Utc>,
+    pub plant_id: Option<uuid::Uuid>,
+    pub baseline: BaselineSummary,
+
+    /// Totals over the reported range
+    pub total: NormalizedEnergy,
+
+    pub data: Vec<NormalizedDataPoint>,
+}
+
+/// Round to 4 decimals.
+fn round(value: f64) -> f64 {
+    (value * 10_000.0).round() / 10_000.0
+}
+
+/// Degree days of each day with a mean temperature.
+pub fn daily_degree_days(
+    weather: &[AggregatedWeather],
+    heating_base_c: f64,
+    cooling_base_c: f64,
+) -> HashMap<DateTime<Utc>, DegreeDays> {
+    weather
+        .iter()
+        .filter_map(|day| {
+            let temperature = day.temperature_c?;
+            let degree_days =
+                DegreeDays::of(temperature, heating_base_c, cooling_base_c);
+            Some((day.period, degree_days))
+        })
+        .collect()
+}
+
+/// Fit a baseline over the days having both readings and degree days.
+/// Returns the number of those days when there are too few.
+pub fn fit_baseline(
+    days: &[AggregatedReading],
+    degree_days: &HashMap<DateTime<Utc>, DegreeDays>,
+) -> Result<Baseline, usize> {
+    let pairs = days
+        .iter()
+        .filter_map(|day| {
+            Some((*degree_days.get(&day.period)?, day.total_kwh.to_f64()?))
+        })
+        .collect::<Vec<_>>();
+    if pairs.len() < MIN_BASELINE_DAYS {
+        return Err(pairs.len());
+    }
+    Baseline::fit(&pairs).ok_or(pairs.len())
+}
+
+impl BaselineSummary {
+    pub fn new(request: &NormalizedRequest, baseline: &Baseline) -> Self {
+        Self {
+            baseline_from: request.baseline_from,
+            baseline_to: request.baseline_to,
+            heating_base_c: request.heating_base_c,
+            cooling_base_c: request.cooling_base_c,
+            intercept_kwh: round(baseline.intercept),
+            heating_slope_kwh: round(baseline.heating_slope),
+            cooling_slope_kwh: round(baseline.cooling_slope),
+            r_squared: baseline.r_squared.map(round),
+            days: baseline.days,
+            normal_heating_degree_days: round(baseline.mean.heating),
+            normal_cooling_degree_days: round(baseline.mean.cooling),
+        }
+    }
+}
+
+/// Sums of the days of a period.
+#[derive(Default)]
+struct PeriodSums {
+    actual: BigDecimal,
+    normalized: f64,
+    heating: f64,
+    cooling: f64,
+    days_without_weather: u32,
+}
+
+impl PeriodSums {
+    fn add(&mut self, other: &PeriodSums) {
+        self.actual += &other.actual;
+        self.normalized += other.normalized;
+        self.heating += other.heating;
+        self.cooling += other.cooling;
+        self.days_without_weather += other.days_without_weather;
+    }
+}
+
+impl From<&PeriodSums> for NormalizedEnergy {
+    fn from(sums: &PeriodSums) -> Self {
+        Self {
+            actual_kwh: sums.actual.to_string(),
+            normalized_kwh: round(sums.normalized),
+            heating_degree_days: round(sums.heating),
+            cooling_degree_days: round(sums.cooling),
+            days_without_weather: sums.days_without_weather,
+        }
+    }
+}
+
+/// Start of the aggregation period of a day.
+fn period_of(
+    day: DateTime<Utc>,
+    aggregation_type: AggregationType,
+) -> DateTime<Utc> {
+    match aggregation_type {
+        AggregationType::Weekly => {
+            day - TimeDelta::days(day.weekday().num_days_from_monday().into())
+        }
+        AggregationType::Monthly => day.with_day(1).unwrap_or(day),
+        AggregationType::Quarterly => day
+            .with_day(1)
+            .and_then(|first| first.with_month0(first.month0() / 3 * 3))
+            .unwrap_or(day),
+        AggregationType::Yearly => day.with_ordinal(1).unwrap_or(day),
+        AggregationType::Hourly | AggregationType::DayOfMonth => day,
+    }
+}
+
+/// The daily energy of `days` normalized with `baseline` and summed by
+/// aggregation period, and the totals.
+pub fn data_points(
+    days: &[AggregatedReading],
+    degree_days: &HashMap<DateTime<Utc>, DegreeDays>,
+    baseline: &Baseline,
+    aggregation_type: AggregationType,
+) -> (Vec<NormalizedDataPoint>, NormalizedEnergy) {
+    let mut periods = BTreeMap::<DateTime<Utc>, PeriodSums>::new();
+    for day in days {
+        let sums = periods
+            .entry(period_of(day.period, aggregation_type))
+            .or_default();
+        sums.actual += &day.total_kwh;
+        let kwh = day.total_kwh.to_f64().unwrap_or_default();
+        match degree_days.get(&day.period) {
+            Some(dd) => {
+                sums.normalized += baseline.normalize(*dd, kwh);
+                sums.heating += dd.heating;
+                sums.cooling += dd.cooling;
+            }
+            None => {
+                sums.normalized += kwh;
+                sums.days_without_weather += 1;
+            }
+        }
+    }
+
+    let mut total = PeriodSums::default();
+    let data = periods
+        .iter()
+        .map(|(period, sums)| {
+            total.add(sums);
+            NormalizedDataPoint {
+                period: *period,
+                energy: sums.into(),
+            }
+        })
+        .collect();
+    (data, (&total).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn day(month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn reading(period: DateTime<Utc>, kwh: &str) -> AggregatedReading {
+        AggregatedReading {
+            period,
+            total_kwh: kwh.parse().unwrap(),
+            reading_count: 24,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_monthly_normalization() {
+        let baseline = Baseline {
+            intercept: 100.0,
+            heating_slope: 5.0,
+            cooling_slope: 0.0,
+            r_squared: Some(0.9),
+            days: 365,
+            mean: DegreeDays {
+                heating: 4.0,
+                cooling: 0.0,
+            },
+        };
+        let weather =
+            [(day(1, 30), 5.5), (day(1, 31), 11.5), (day(2, 1), 19.5)].map(
+                |(period, temperature)| AggregatedWeather {
+                    period,
+                    temperature_c: Some(temperature),
+                    irradiance_w_m2: None,
+                    wind_speed_m_s: None,
+                },
+            );
+        let degree_days = daily_degree_days(&weather, 15.5, 22.0);
+        let days = [
+            reading(day(1, 30), "150"),
+            reading(day(1, 31), "120"),
+            reading(day(2, 1), "100"),
+            reading(day(2, 2), "90.5"),
+        ];
+
+        let (data, total) = data_points(
+            &days,
+            &degree_days,
+            &baseline,
+            AggregationType::Monthly,
+        );
+
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].period, day(1, 1));
+        assert_eq!(data[0].energy.actual_kwh, "270");
+        // 10 and 4 HDD against a normal 4: 150 - 30 + 120 - 0
+        assert_eq!(data[0].energy.normalized_kwh, 240.0);
+        assert_eq!(data[0].energy.heating_degree_days, 14.0);
+        // No HDD adds 20; the day without weather is left as is
+        assert_eq!(data[1].energy.normalized_kwh, 210.5);
+        assert_eq!(data[1].energy.days_without_weather, 1);
+        assert_eq!(total.actual_kwh, "460.5");
+        assert_eq!(total.normalized_kwh, 450.5);
+    }
+
+    #[test]
+    fn test_periods_of_days() {
+        // A Thursday
+        let thursday = day(8, 14);
+        assert_eq!(period_of(thursday, AggregationType::Weekly), day(8, 11));
+        assert_eq!(period_of(thursday, AggregationType::Monthly), day(8, 1));
+        assert_eq!(period_of(thursday, AggregationType::Quarterly), day(7, 1));
+        assert_eq!(period_of(thursday, AggregationType::Yearly), day(1, 1));
+        assert_eq!(period_of(thursday, AggregationType::DayOfMonth), thursday);
+    }
+}
@@ -0,0 +1,41 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the energy routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::aggregate::handler::handler,
+        super::cost::handler::handler,
+        super::delete::handler::handler,
+        super::downsample::handler::handler,
+        super::estimate::handler::handler,
+        super::export::handler::handler,
+        super::history::handler::handler,
+        super::ingest::handler::handler,
+        super::normalized::handler::handler,
+        super::poll::handler::handler,
+        super::quality::handler::handler,
+        super::readings::handler::handler,
+        super::saved_queries::handler::create,
+        super::saved_queries::handler::list,
+        super::saved_queries::handler::get,
+        super::saved_queries::handler::update,
+        super::saved_queries::handler::delete,
+        super::saved_queries::handler::run,
+        super::targets::handler::create,
+        super::targets::handler::list,
+        super::targets::handler::progress,
+        super::targets::handler::get,
+        super::targets::handler::update,
+        super::targets::handler::delete,
+    ),
+    components(schemas(super::aggregate::models::ColumnarAggregateResponse)),
+    tags(
+        (name = "energy", description = "Energy readings ingestion, listing, deletion, aggregation, its estimates and saved queries, downsampling, cost, weather normalization, data quality, targets and query history")
+    )
+)]
+pub(super) struct ApiDoc;
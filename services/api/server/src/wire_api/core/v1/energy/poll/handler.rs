@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Json;
+use axum::extract::Query;
+use axum::extract::rejection::QueryRejection;
+use axum::http::StatusCode;
+use postgres_models::models::Keyset;
+use postgres_models::models::energy_readings::{EnergyReading, ReadingFilter};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::events::DomainEvent;
+use crate::shutdown::ShutdownCoordinator;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{PollParams, PollResponse};
+
+const HANDLER_NAME: &str = "energy_readings_poll";
+/// Most readings of a response.
+const PAGE_SIZE: i64 = 1000;
+/// How often a waiting poll looks for readings stored without an import
+/// event reaching this instance, by other instances or file imports.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Time kept before the request's deadline to answer in.
+const DEADLINE_MARGIN: Duration = Duration::from_millis(500);
+
+/// Poll for new readings
+///
+/// Returns the tenant's readings of times after `since`, at most 1000, as
+/// soon as there are any: at once when they are already stored, or when
+/// they are stored while the request waits, up to `timeoutSecs` or the
+/// configured `POLL_TIMEOUT_SECS`, whichever is shorter, and never past the
+/// request's deadline. A poll that times out returns no readings. Poll
+/// again with the returned `since` to follow the readings, for clients
+/// that cannot keep a stream open through their proxies.
+///
+/// Readings ingested through the same API instance end the wait at once;
+/// others, stored by other instances or file imports, are found within 5
+/// seconds.
+#[utoipa::path(
+    get,
+    path = "/energy/readings/poll",
+    params(PollParams),
+    responses(
+        (status = 200, description = "New readings, or none once the poll timed out", body = PollResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_readings_poll")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    params: Result<Query<PollParams>, QueryRejection>,
+) -> HandlerResult<(StatusCode, Json<PollResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+    let timeout = params
+        .timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(ctx.state.config.poll_timeout)
+        .min(ctx.state.config.poll_timeout);
+    let mut until = Instant::now() + timeout;
+    if let Some(deadline) = ctx.deadline() {
+        until = until.min(deadline - DEADLINE_MARGIN);
+    }
+
+    // Subscribed before the first query, so no import is missed between
+    // them
+    let mut events = ctx.state.domain_events.subscribe();
+    let tenant_id = &ctx.tenant.tenant_id;
+    // After every reading of `since`
+    let keyset = Keyset::After(params.since, Uuid::max());
+    loop {
+        let readings = ctx
+            .state
+            .reads
+            .with_connection(|mut conn| async move {
+                EnergyReading::page(
+                    tenant_id,
+                    keyset,
+                    ReadingFilter::default(),
+                    PAGE_SIZE,
+                    &mut conn,
+                )
+                .await
+            })
+            .await
+            .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+        if !readings.is_empty()
+            || Instant::now() >= until
+            || ctx.state.shutdown.is_shutting_down()
+        {
+            let full = readings.len() as i64 == PAGE_SIZE;
+            let response = PollResponse::new(params.since, readings, full);
+            return Ok((StatusCode::OK, Json(response)));
+        }
+
+        let recheck = until.min(Instant::now() + RECHECK_INTERVAL);
+        readings_stored(&mut events, tenant_id, recheck, &ctx.state.shutdown)
+            .await;
+    }
+}
+
+/// Wait until readings of `tenant_id` are stored through this instance,
+/// `until` passes or shutdown begins.
+async fn readings_stored(
+    events: &mut broadcast::Receiver<Arc<DomainEvent>>,
+    tenant_id: &str,
+    until: Instant,
+    shutdown: &ShutdownCoordinator,
+) {
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = tokio::time::sleep_until(until) => return,
+            _ = shutdown.wait_for_shutdown() => return,
+        };
+        match event {
+            Ok(event) => {
+                if let DomainEvent::ImportCompleted {
+                    tenant_id: imported,
+                    inserted,
+                    ..
+                } = &*event
+                    && imported == tenant_id
+                    && *inserted > 0
+                {
+                    return;
+                }
+            }
+            // The missed events may have been imports of the tenant
+            Err(RecvError::Lagged(_)) => return,
+            Err(RecvError::Closed) => {
+                tokio::time::sleep_until(until).await;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[tokio::test]
+    async fn test_returns_new_readings_or_times_out() {
+        let Some(app) = TestApp::with_vars(&[("POLL_TIMEOUT_SECS", "1")]).await
+        else {
+            return;
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2025, 5, 1, hour, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(0), "1.5"), (at(1), "2")],
+        )
+        .await;
+        app.seed_readings("other", None, &[(at(2), "100")]).await;
+        let poll = |since: chrono::DateTime<Utc>| {
+            app.server
+                .get("/api/wire/v1/energy/readings/poll")
+                .add_query_param("since", since.to_rfc3339())
+        };
+
+        let response = poll(at(0)).await;
+        response.assert_status_ok();
+        let response = response.json::<serde_json::Value>();
+        assert_eq!(response["readings"][0]["quantityKwh"], "2.0000");
+        assert_eq!(response["readings"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            response["since"]
+                .as_str()
+                .unwrap()
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap(),
+            at(1)
+        );
+
+        let started = Instant::now();
+        let response = poll(at(1)).await;
+        response.assert_status_ok();
+        let response = response.json::<serde_json::Value>();
+        assert_eq!(response["readings"], serde_json::json!([]));
+        assert!(started.elapsed() >= Duration::from_millis(900));
+
+        app.server
+            .get("/api/wire/v1/energy/readings/poll")
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    async fn test_wakes_up_on_imports() {
+        let (sender, mut events) = broadcast::channel(8);
+        let shutdown = ShutdownCoordinator::new(
+            postgres_models::connection::Pool::builder().build_unchecked(
+                diesel_async::pooled_connection::AsyncDieselConnectionManager::new(
+                    "postgres://127.0.0.1:1/unused",
+                ),
+            ),
+            Arc::new(
+                deadpool_redis::Config::from_url("redis://127.0.0.1:1")
+                    .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+                    .unwrap(),
+            ),
+        );
+        let until = Instant::now() + Duration::from_secs(10);
+        let import = |tenant_id: &str, inserted| {
+            Arc::new(DomainEvent::ImportCompleted {
+                tenant_id: tenant_id.to_string(),
+                api_key_id: Uuid::nil(),
+                inserted,
+                total: inserted,
+            })
+        };
+        sender.send(import("other", 1)).unwrap();
+        sender.send(import("acme", 0)).unwrap();
+        sender.send(import("acme", 3)).unwrap();
+
+        let started = Instant::now();
+        readings_stored(&mut events, "acme", until, &shutdown).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(events.is_empty());
+
+        let started = Instant::now();
+        let until = Instant::now() + Duration::from_millis(50);
+        readings_stored(&mut events, "acme", until, &shutdown).await;
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+}
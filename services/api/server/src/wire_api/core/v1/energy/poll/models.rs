@@ -0,0 +1,137 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use postgres_models::models::energy_readings::EnergyReading;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::shared::json::decimal_str;
+
+/// Query parameters of a poll
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct PollParams {
+    /// Return the readings of times after this one
+    pub since: DateTime<Utc>,
+
+    /// Wait at most this many seconds for new readings, by default and at
+    /// most the configured `POLL_TIMEOUT_SECS`
+    pub timeout_secs: Option<u64>,
+}
+
+/// A reading returned by a poll
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PolledReading {
+    pub id: uuid::Uuid,
+    #[schema(example = "2025-01-01T00:15:00Z")]
+    pub reading_time: DateTime<Utc>,
+    #[serde(serialize_with = "decimal_str")]
+    #[schema(value_type = String, example = "12.5000")]
+    pub quantity_kwh: BigDecimal,
+    /// The plant the reading is attributed to
+    pub plant_id: Option<uuid::Uuid>,
+    /// Problem found with the quantity when it was stored, e.g. `negative`
+    pub quality_code: Option<String>,
+    /// Where the reading came from, e.g. `webhook`
+    pub source: String,
+}
+
+impl From<EnergyReading> for PolledReading {
+    fn from(reading: EnergyReading) -> Self {
+        Self {
+            id: reading.id,
+            reading_time: reading.reading_time,
+            quantity_kwh: reading.quantity_kwh,
+            plant_id: reading.plant_id,
+            quality_code: reading.quality_code,
+            source: reading.source,
+        }
+    }
+}
+
+/// Response of a poll: the readings after `since` in time order, empty when
+/// none arrived before the poll timed out
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PollResponse {
+    pub readings: Vec<PolledReading>,
+
+    /// `since` of the next poll: the time of the last reading, or the
+    /// requested `since` when there are none
+    pub since: DateTime<Utc>,
+}
+
+impl PollResponse {
+    /// Response with `readings` after `since`. Of a `full` page, the
+    /// readings sharing the time of the last one are left to the next
+    /// poll, which asks for times after it, unless they are all there is.
+    pub fn new(
+        since: DateTime<Utc>,
+        mut readings: Vec<EnergyReading>,
+        full: bool,
+    ) -> Self {
+        if full
+            && let Some(last) = readings.last().map(|r| r.reading_time)
+            && readings.iter().any(|r| r.reading_time < last)
+        {
+            readings.retain(|r| r.reading_time < last);
+        }
+        Self {
+            since: readings.last().map_or(since, |r| r.reading_time),
+            readings: readings.into_iter().map(PolledReading::from).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use postgres_models::models::energy_readings::reading_source;
+
+    use super::*;
+
+    fn reading(minute: u32) -> EnergyReading {
+        let at = Utc.with_ymd_and_hms(2025, 3, 1, 0, minute, 0).unwrap();
+        EnergyReading {
+            id: uuid::Uuid::new_v4(),
+            reading_time: at,
+            quantity_kwh: BigDecimal::from(1),
+            created_at: at,
+            updated_at: at,
+            tenant_id: "acme".to_string(),
+            plant_id: None,
+            quality_code: None,
+            source: reading_source::WEBHOOK.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_leaves_incomplete_times_to_the_next_poll() {
+        let since = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let times = |response: &PollResponse| {
+            response
+                .readings
+                .iter()
+                .map(|r| r.reading_time.format("%M").to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let page = vec![reading(15), reading(30), reading(30)];
+        let response = PollResponse::new(since, page.clone(), false);
+        assert_eq!(times(&response), ["15", "30", "30"]);
+        assert_eq!(response.since, page[2].reading_time);
+
+        // Other readings of 00:30 may follow the page
+        let response = PollResponse::new(since, page.clone(), true);
+        assert_eq!(times(&response), ["15"]);
+        assert_eq!(response.since, page[0].reading_time);
+
+        let response = PollResponse::new(since, page[1..].to_vec(), true);
+        assert_eq!(times(&response), ["30", "30"]);
+
+        let response = PollResponse::new(since, Vec::new(), false);
+        assert!(response.readings.is_empty());
+        assert_eq!(response.since, since);
+    }
+}
@@ -0,0 +1,185 @@
+use axum::Json;
+use axum::http::StatusCode;
+use postgres_models::models::energy_readings::EnergyReading;
+use postgres_models::models::plants::Plant;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::core::v1::energy::ingest::models::ReadingSource;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{QualityRequest, QualityResponse, quality_days};
+
+const HANDLER_NAME: &str = "energy_quality";
+
+/// Score the quality of the tenant's readings
+///
+/// For each UTC day of the range: completeness (the share of expected
+/// intervals, one per `intervalMinutes` and feed, holding a reading),
+/// duplicate rate (readings beyond the first in an interval) and the count
+/// of out-of-range readings (negative, or above the plant's capacity over
+/// an interval). A feed is a plant, or the readings without a plant; only
+/// feeds with readings in the range are expected to report. With a
+/// `source`, only readings from that source are scored.
+#[utoipa::path(
+    post,
+    path = "/energy/quality",
+    request_body = QualityRequest,
+    responses(
+        (status = 200, description = "Quality of the readings by day", body = QualityResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_quality")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<QualityRequest>,
+) -> HandlerResult<(StatusCode, Json<QualityResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let plant = payload.plant_id;
+    let origin = payload.source.map(ReadingSource::as_str);
+    let date_from = payload.date_from;
+    let date_to = payload.date_to;
+    let interval_secs = payload.interval_minutes * 60;
+
+    let feeds = match plant {
+        Some(plant) => {
+            ctx.state
+                .reads
+                .with_connection(|mut conn| async move {
+                    Plant::find(tenant_id, plant, &mut conn).await
+                })
+                .await
+                .map_err(|e| recorder.record_database::<errors::Error>(e))?
+                .ok_or_else(|| {
+                    recorder.record(
+                        "plant_not_found",
+                        errors::Error::PlantNotFound(plant),
+                    )
+                })?;
+            1
+        }
+        None => ctx
+            .state
+            .reads
+            .with_connection(|mut conn| async move {
+                EnergyReading::feed_count(
+                    tenant_id, origin, date_from, date_to, &mut conn,
+                )
+                .await
+            })
+            .await
+            .map_err(|e| recorder.record_database::<errors::Error>(e))?,
+    };
+
+    let rows = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            EnergyReading::daily_quality(
+                tenant_id,
+                plant,
+                origin,
+                interval_secs,
+                date_from,
+                date_to,
+                &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let (days, total) =
+        quality_days(&rows, date_from, date_to, interval_secs.into(), feeds);
+
+    Ok((
+        StatusCode::OK,
+        Json(QualityResponse {
+            date_from,
+            date_to,
+            plant_id: plant,
+            source: payload.source,
+            interval_minutes: payload.interval_minutes,
+            feeds,
+            total,
+            days,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use postgres_models::models::energy_readings::{
+        NewEnergyReading, reading_source,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[tokio::test]
+    async fn test_scores_the_readings_of_a_source() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(0), "1"), (at(1), "2"), (at(2), "3")],
+        )
+        .await;
+        let corrected = NewEnergyReading {
+            reading_time: at(3),
+            quantity_kwh: "-4".parse().unwrap(),
+            tenant_id: DEFAULT_TENANT.to_string(),
+            plant_id: None,
+            quality_code: None,
+            source: reading_source::MANUAL.to_string(),
+        };
+        let mut conn = app.state.pool.get().await.unwrap();
+        EnergyReading::bulk_insert(vec![corrected], &mut conn)
+            .await
+            .unwrap();
+
+        let score = |source: Option<&str>| {
+            let response =
+                app.server.post("/api/wire/v1/energy/quality").json(&json!({
+                    "dateFrom": "2025-01-01T00:00:00Z",
+                    "dateTo": "2025-01-02T00:00:00Z",
+                    "intervalMinutes": 60,
+                    "source": source,
+                }));
+            async move {
+                let response = response.await;
+                response.assert_status_ok();
+                let body = response.json::<serde_json::Value>();
+                (
+                    body["total"]["readings"].as_i64().unwrap(),
+                    body["total"]["outOfRange"].as_i64().unwrap(),
+                )
+            }
+        };
+        assert_eq!(score(None).await, (4, 1));
+        assert_eq!(score(Some("file")).await, (3, 0));
+        assert_eq!(score(Some("manual")).await, (1, 1));
+        assert_eq!(score(Some("kafka")).await, (0, 0));
+
+        app.server
+            .post("/api/wire/v1/energy/quality")
+            .json(&json!({
+                "dateFrom": "2025-01-01T00:00:00Z",
+                "dateTo": "2025-01-02T00:00:00Z",
+                "source": "spreadsheet",
+            }))
+            .await
+            .assert_status_bad_request();
+    }
+}
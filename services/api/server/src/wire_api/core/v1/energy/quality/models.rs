@@ -0,0 +1,227 @@
+use chrono::{DateTime, Days, Utc};
+use postgres_models::models::energy_readings::DailyQuality;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::wire_api::core::v1::energy::ingest::models::ReadingSource;
+
+/// Longest accepted date range, a year.
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+fn default_interval_minutes() -> i32 {
+    15
+}
+
+fn validate_range(request: &QualityRequest) -> Result<(), ValidationError> {
+    if request.date_to <= request.date_from {
+        return Err(ValidationError::new("dateTo")
+            .with_message("must be after dateFrom".into()));
+    }
+    if (request.date_to - request.date_from).num_days() > MAX_RANGE_DAYS {
+        return Err(ValidationError::new("dateTo")
+            .with_message("Ranges span at most 366 days".into()));
+    }
+    Ok(())
+}
+
+/// Request payload for scoring the quality of energy readings
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_range"))]
+pub struct QualityRequest {
+    /// Start of date range (inclusive)
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub date_from: DateTime<Utc>,
+
+    /// End of date range (exclusive), at most 366 days after `dateFrom`
+    #[schema(example = "2025-02-01T00:00:00Z")]
+    pub date_to: DateTime<Utc>,
+
+    /// Only score the readings attributed to this plant
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Only score the readings from this source
+    pub source: Option<ReadingSource>,
+
+    /// Minutes between two readings of a feed
+    #[serde(default = "default_interval_minutes")]
+    #[validate(range(
+        min = 1,
+        max = 1440,
+        message = "Intervals are 1-1440 minutes"
+    ))]
+    #[schema(example = 15)]
+    pub interval_minutes: i32,
+}
+
+/// Quality of the readings of a day or of the whole range
+#[derive(Debug, Default, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityScore {
+    /// Readings expected from the feeds at one per interval
+    #[schema(example = 96)]
+    pub expected_readings: i64,
+
+    /// Readings received
+    #[schema(example = 95)]
+    pub readings: i64,
+
+    /// Share of the expected intervals holding a reading, 0-1; absent
+    /// when none is expected
+    #[schema(example = 0.9792)]
+    pub completeness: Option<f64>,
+
+    /// Readings beyond the first in an interval of a feed
+    #[schema(example = 1)]
+    pub duplicates: i64,
+
+    /// `duplicates / readings`; absent without readings
+    #[schema(example = 0.0105)]
+    pub duplicate_rate: Option<f64>,
+
+    /// Negative readings and readings above the plant's capacity over an
+    /// interval
+    #[schema(example = 0)]
+    pub out_of_range: i64,
+}
+
+/// Quality of the readings of one day
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityDay {
+    /// Start of the day, in UTC
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub day: DateTime<Utc>,
+
+    #[serde(flatten)]
+    pub score: QualityScore,
+}
+
+/// Response for a quality query
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityResponse {
+    pub date_from: DateTime<Utc>,
+    pub date_to: DateTime<Utc>,
+    pub plant_id: Option<uuid::Uuid>,
+    pub source: Option<ReadingSource>,
+    pub interval_minutes: i32,
+
+    /// Feeds expected to report: the plant, or the plants, and readings
+    /// without a plant, with readings in the range
+    #[schema(example = 1)]
+    pub feeds: i64,
+
+    /// Quality over the whole range
+    pub total: QualityScore,
+
+    /// Quality of each day of the range, including days without readings
+    pub days: Vec<QualityDay>,
+}
+
+/// `part / whole` rounded to 4 decimals, `None` when `whole` is 0.
+fn ratio(part: i64, whole: i64) -> Option<f64> {
+    (whole > 0)
+        .then(|| (part as f64 / whole as f64 * 10_000.0).round() / 10_000.0)
+}
+
+fn score(
+    expected: i64,
+    readings: i64,
+    slots: i64,
+    out_of_range: i64,
+) -> QualityScore {
+    QualityScore {
+        expected_readings: expected,
+        readings,
+        completeness: ratio(slots.min(expected), expected),
+        duplicates: readings - slots,
+        duplicate_rate: ratio(readings - slots, readings),
+        out_of_range,
+    }
+}
+
+/// The quality of each UTC day of `[date_from, date_to)` and of the whole
+/// range, from the counts of the days with readings. Partial days at the
+/// ends of the range expect readings only for the part in range.
+pub fn quality_days(
+    rows: &[DailyQuality],
+    date_from: DateTime<Utc>,
+    date_to: DateTime<Utc>,
+    interval_secs: i64,
+    feeds: i64,
+) -> (Vec<QualityDay>, QualityScore) {
+    let mut days = Vec::new();
+    let (mut expected, mut readings, mut slots, mut out_of_range) =
+        (0, 0, 0, 0);
+
+    let mut day = date_from
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    while day < date_to {
+        let next = day + Days::new(1);
+        let covered = next.min(date_to) - day.max(date_from);
+        let day_expected = covered.num_seconds() / interval_secs * feeds;
+        let row = rows.iter().find(|row| row.day == day);
+        let (day_readings, day_slots, day_out_of_range) = row
+            .map(|row| {
+                (row.reading_count, row.slot_count, row.out_of_range_count)
+            })
+            .unwrap_or_default();
+
+        expected += day_expected;
+        readings += day_readings;
+        slots += day_slots;
+        out_of_range += day_out_of_range;
+        days.push(QualityDay {
+            day,
+            score: score(
+                day_expected,
+                day_readings,
+                day_slots,
+                day_out_of_range,
+            ),
+        });
+        day = next;
+    }
+
+    (days, score(expected, readings, slots, out_of_range))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_quality_days() {
+        let from = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2025, 1, 3, 0, 0, 0).unwrap();
+        let rows = [DailyQuality {
+            day: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            reading_count: 50,
+            slot_count: 45,
+            out_of_range_count: 2,
+        }];
+
+        let (days, total) = quality_days(&rows, from, to, 900, 2);
+
+        assert_eq!(days.len(), 2);
+        // Half a day of two feeds reporting every 15 minutes
+        assert_eq!(days[0].score.expected_readings, 96);
+        assert_eq!(days[0].score.completeness, Some(0.4688));
+        assert_eq!(days[0].score.duplicates, 5);
+        assert_eq!(days[0].score.duplicate_rate, Some(0.1));
+        assert_eq!(days[0].score.out_of_range, 2);
+        assert_eq!(days[1].score.readings, 0);
+        assert_eq!(days[1].score.completeness, Some(0.0));
+        assert_eq!(days[1].score.duplicate_rate, None);
+        assert_eq!(total.expected_readings, 288);
+        assert_eq!(total.completeness, Some(0.1563));
+        assert_eq!(total.out_of_range, 2);
+    }
+}
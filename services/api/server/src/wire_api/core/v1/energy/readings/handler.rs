@@ -0,0 +1,152 @@
+use axum::Json;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{OriginalUri, Query};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use postgres_models::models::energy_readings::{EnergyReading, ReadingFilter};
+
+use crate::shared::pagination::{self, Page};
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{ReadingListParams, ReadingListResponse, ReadingResponse};
+
+const HANDLER_NAME: &str = "energy_readings_list";
+
+/// List readings
+///
+/// Returns the caller's tenant's readings as they are stored, in time
+/// order, optionally only those in `[dateFrom, dateTo)` or of a plant, to
+/// audit the data behind the aggregations. Pages are read by cursor rather
+/// than offset, so each one costs the same however deep into the readings
+/// it is, and are linked with `Link: <...>; rel="next"` and `rel="prev"`
+/// headers.
+#[utoipa::path(
+    get,
+    path = "/energy/readings",
+    params(ReadingListParams),
+    responses(
+        (status = 200, description = "Readings", body = ReadingListResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_readings_list")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    OriginalUri(uri): OriginalUri,
+    params: Result<Query<ReadingListParams>, QueryRejection>,
+) -> HandlerResult<Response> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+    let keyset =
+        pagination::keyset(params.after.as_deref(), params.before.as_deref())
+            .map_err(|e| {
+            recorder.record("invalid_cursor", errors::Error::InvalidCursor(e))
+        })?;
+
+    let limit = params.limit();
+    let tenant_id = &ctx.tenant.tenant_id;
+    let readings = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            let filter = ReadingFilter {
+                plant: params.plant_id,
+                date_from: params.date_from,
+                date_to: params.date_to,
+                source: None,
+            };
+            EnergyReading::page(tenant_id, keyset, filter, limit + 1, &mut conn)
+                .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let page = Page::new(readings, limit as usize, keyset, |reading| {
+        pagination::encode_cursor(reading.reading_time, reading.id)
+    });
+    let link = page.link_header(&uri);
+    let body = ReadingListResponse {
+        readings: page.items.into_iter().map(ReadingResponse::from).collect(),
+        next_cursor: page.next,
+        prev_cursor: page.prev,
+    };
+    let mut response = (StatusCode::OK, Json(body)).into_response();
+    if let Some(link) = link {
+        response.headers_mut().insert(header::LINK, link);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[tokio::test]
+    async fn test_pages_through_readings() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2025, 6, 1, hour, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(0), "1"), (at(1), "2"), (at(2), "3"), (at(3), "4")],
+        )
+        .await;
+        app.seed_readings("other", None, &[(at(1), "100")]).await;
+        let quantities = |response: &serde_json::Value| {
+            response["readings"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["quantityKwh"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let response = app
+            .server
+            .get("/api/wire/v1/energy/readings")
+            .add_query_param("dateFrom", at(1).to_rfc3339())
+            .add_query_param("limit", 2)
+            .await;
+        response.assert_status_ok();
+        assert!(response.header("link").to_str().unwrap().contains("next"));
+        let first = response.json::<serde_json::Value>();
+        assert_eq!(quantities(&first), ["2.0000", "3.0000"]);
+
+        let response = app
+            .server
+            .get("/api/wire/v1/energy/readings")
+            .add_query_param("dateFrom", at(1).to_rfc3339())
+            .add_query_param("limit", 2)
+            .add_query_param("after", first["nextCursor"].as_str().unwrap())
+            .await;
+        let second = response.json::<serde_json::Value>();
+        assert_eq!(quantities(&second), ["4.0000"]);
+        assert!(second.get("nextCursor").is_none());
+
+        let response = app
+            .server
+            .get("/api/wire/v1/energy/readings")
+            .add_query_param("dateFrom", at(1).to_rfc3339())
+            .add_query_param("limit", 2)
+            .add_query_param("before", second["prevCursor"].as_str().unwrap())
+            .await;
+        assert_eq!(quantities(&response.json()), ["2.0000", "3.0000"]);
+
+        app.server
+            .get("/api/wire/v1/energy/readings")
+            .add_query_param("after", "nope")
+            .await
+            .assert_status_bad_request();
+    }
+}
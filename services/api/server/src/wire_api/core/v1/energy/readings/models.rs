@@ -0,0 +1,87 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use postgres_models::models::energy_readings::EnergyReading;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::shared::json::decimal_str;
+
+/// Readings returned when `limit` is omitted.
+pub const DEFAULT_LIMIT: i64 = 100;
+/// Most readings returned by one request.
+pub const MAX_LIMIT: i64 = 1000;
+
+/// Query parameters of the reading list
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct ReadingListParams {
+    /// Start of the range of reading times (inclusive)
+    pub date_from: Option<DateTime<Utc>>,
+    /// End of the range of reading times (exclusive)
+    pub date_to: Option<DateTime<Utc>>,
+    /// Only the readings attributed to this plant
+    pub plant_id: Option<uuid::Uuid>,
+    /// Most readings to return, 100 by default and at most 1000
+    pub limit: Option<i64>,
+    /// Cursor of the reading after which the page starts, `nextCursor` of
+    /// the previous page
+    pub after: Option<String>,
+    /// Cursor of the reading before which the page ends, `prevCursor` of
+    /// the next page
+    pub before: Option<String>,
+}
+
+impl ReadingListParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// A reading as it is stored
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingResponse {
+    pub id: uuid::Uuid,
+    #[schema(example = "2025-01-01T00:15:00Z")]
+    pub reading_time: DateTime<Utc>,
+    #[serde(serialize_with = "decimal_str")]
+    #[schema(value_type = String, example = "12.5000")]
+    pub quantity_kwh: BigDecimal,
+    /// The plant the reading is attributed to
+    pub plant_id: Option<uuid::Uuid>,
+    /// Problem found with the quantity when it was stored, e.g. `negative`
+    pub quality_code: Option<String>,
+    /// Where the reading came from, e.g. `webhook`
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<EnergyReading> for ReadingResponse {
+    fn from(reading: EnergyReading) -> Self {
+        Self {
+            id: reading.id,
+            reading_time: reading.reading_time,
+            quantity_kwh: reading.quantity_kwh,
+            plant_id: reading.plant_id,
+            quality_code: reading.quality_code,
+            source: reading.source,
+            created_at: reading.created_at,
+            updated_at: reading.updated_at,
+        }
+    }
+}
+
+/// Response containing readings in time order
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingListResponse {
+    pub readings: Vec<ReadingResponse>,
+    /// `after` of the next page, absent on the last one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// `before` of the previous page, absent on the first one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
@@ -0,0 +1,69 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Saved query not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("A saved query is already named {0}")]
+    NameTaken(String),
+
+    #[error("Saved query {0} is no longer a valid aggregation: {1}")]
+    Outdated(Uuid, String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Saved query not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "saved_query_not_found".to_string(),
+                    message: format!("No saved query exists with id {id}"),
+                    suggestion: "Check the saved query id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::NameTaken(name) => WireV1Error::conflict(
+                "Saved query name taken".to_string(),
+                vec![WireV1Detail {
+                    field: Some("name".to_string()),
+                    code: "saved_query_name_taken".to_string(),
+                    message: format!("A saved query is already named {name}"),
+                    suggestion: "Choose another name, or update the saved \
+                                 query of that name"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::Outdated(id, message) => WireV1Error::unprocessable_entity(
+                "Saved query outdated".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "saved_query_outdated".to_string(),
+                    message: format!(
+                        "Saved query {id} is no longer a valid aggregation: \
+                         {message}"
+                    ),
+                    suggestion: "Update the saved query's `request` and \
+                                 `params`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Saved query operation failed";
+}
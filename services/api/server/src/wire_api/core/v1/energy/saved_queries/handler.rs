@@ -0,0 +1,373 @@
+use axum::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::Response;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use postgres_models::connection::{WithConnectionError, with_connection};
+use postgres_models::models::saved_queries::{
+    NewSavedQuery, SavedQuery, UpdateSavedQuery,
+};
+use uuid::Uuid;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::core::v1::energy::aggregate;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    CreateSavedQueryRequest, SavedQueryListResponse, SavedQueryResponse,
+    UpdateSavedQueryRequest, aggregation,
+};
+
+const HANDLER_NAME: &str = "energy_saved_queries";
+
+/// `NameTaken` when the tenant already has a saved query named `name`.
+fn saving_error(
+    recorder: &ErrorRecorder<'_>,
+    name: String,
+    e: WithConnectionError<DieselError>,
+) -> WireV1Error {
+    match e {
+        WithConnectionError::Operation(DieselError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            _,
+        )) => recorder.record("name_taken", errors::Error::NameTaken(name)),
+        e => recorder.record_database::<errors::Error>(e),
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).expect("aggregations to serialize to JSON")
+}
+
+/// Save an aggregation
+///
+/// Stores the body and query parameters of a `POST /energy/aggregate`
+/// request under a name, to run again with
+/// `POST /energy/saved-queries/{id}/run` rather than have every client
+/// repeat them.
+#[utoipa::path(
+    post,
+    path = "/energy/saved-queries",
+    request_body = CreateSavedQueryRequest,
+    responses(
+        (status = 201, description = "Query saved", body = SavedQueryResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 409, description = "A saved query already has the name"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_saved_queries_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateSavedQueryRequest>,
+) -> HandlerResult<(StatusCode, Json<SavedQueryResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let name = payload.name.trim().to_string();
+    let new_query = NewSavedQuery {
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        name: name.clone(),
+        request: to_json(&payload.request),
+        params: to_json(&payload.params),
+    };
+    let query = with_connection(&ctx.state.pool, |mut conn| async move {
+        SavedQuery::create(new_query, &mut conn).await
+    })
+    .await
+    .map_err(|e| saving_error(&recorder, name, e))?;
+
+    Ok((StatusCode::CREATED, Json(SavedQueryResponse::from(query))))
+}
+
+/// List saved queries
+///
+/// Returns the caller's tenant's saved queries ordered by name.
+#[utoipa::path(
+    get,
+    path = "/energy/saved-queries",
+    responses(
+        (status = 200, description = "Saved queries", body = SavedQueryListResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_saved_queries_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<SavedQueryListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let queries = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            SavedQuery::list(tenant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let saved_queries =
+        queries.into_iter().map(SavedQueryResponse::from).collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(SavedQueryListResponse { saved_queries }),
+    ))
+}
+
+/// Get a saved query by id
+#[utoipa::path(
+    get,
+    path = "/energy/saved-queries/{id}",
+    params(("id" = Uuid, Path, description = "Saved query id")),
+    responses(
+        (status = 200, description = "Saved query", body = SavedQueryResponse),
+        (status = 404, description = "Saved query not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_saved_queries_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<SavedQueryResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let query = find(&ctx, &recorder, id).await?;
+
+    Ok((StatusCode::OK, Json(SavedQueryResponse::from(query))))
+}
+
+/// Change a saved query
+#[utoipa::path(
+    put,
+    path = "/energy/saved-queries/{id}",
+    params(("id" = Uuid, Path, description = "Saved query id")),
+    request_body = UpdateSavedQueryRequest,
+    responses(
+        (status = 200, description = "Updated saved query", body = SavedQueryResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 404, description = "Saved query not found"),
+        (status = 409, description = "Another saved query has the name"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_saved_queries_update")]
+pub async fn update(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    ValidatedPayload(payload): ValidatedPayload<UpdateSavedQueryRequest>,
+) -> HandlerResult<(StatusCode, Json<SavedQueryResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let changes = UpdateSavedQuery {
+        name: payload.name.map(|name| name.trim().to_string()),
+        request: payload.request.as_ref().map(to_json),
+        params: payload.params.as_ref().map(to_json),
+    };
+    // An empty UPDATE is an error in Diesel
+    if changes.name.is_none()
+        && changes.request.is_none()
+        && changes.params.is_none()
+    {
+        let query = find(&ctx, &recorder, id).await?;
+        return Ok((StatusCode::OK, Json(SavedQueryResponse::from(query))));
+    }
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let name = changes.name.clone().unwrap_or_default();
+    let query = with_connection(&ctx.state.pool, |mut conn| async move {
+        SavedQuery::update(tenant_id, id, changes, &mut conn).await
+    })
+    .await
+    .map_err(|e| saving_error(&recorder, name, e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    Ok((StatusCode::OK, Json(SavedQueryResponse::from(query))))
+}
+
+/// Delete a saved query
+#[utoipa::path(
+    delete,
+    path = "/energy/saved-queries/{id}",
+    params(("id" = Uuid, Path, description = "Saved query id")),
+    responses(
+        (status = 204, description = "Saved query deleted"),
+        (status = 404, description = "Saved query not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_saved_queries_delete")]
+pub async fn delete(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let deleted = with_connection(&ctx.state.pool, |mut conn| async move {
+        SavedQuery::delete(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+    if deleted == 0 {
+        return Err(recorder.record("not_found", errors::Error::NotFound(id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run a saved query
+///
+/// Responds as `POST /energy/aggregate` does to the saved body and query
+/// parameters: from the same cache, under the same admission control and
+/// recorded in the query history.
+#[utoipa::path(
+    post,
+    path = "/energy/saved-queries/{id}/run",
+    params(("id" = Uuid, Path, description = "Saved query id")),
+    responses(
+        (status = 200, description = "Aggregated energy data, a `ColumnarAggregateResponse` with `layout=columnar`", body = aggregate::models::AggregateResponse),
+        (status = 404, description = "Saved query not found"),
+        (status = 422, description = "The saved query is no longer a valid aggregation"),
+        (status = 429, description = "Too many expensive aggregations waiting for the database"),
+        (status = 500, description = "Internal server error"),
+        (status = 503, description = "Database busy for an aggregation over budget, or failing"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_saved_queries_run")]
+pub async fn run(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<Response> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let query = find(&ctx, &recorder, id).await?;
+    let (request, params) = aggregation(&query).map_err(|e| {
+        recorder.record("saved_query_outdated", errors::Error::Outdated(id, e))
+    })?;
+
+    aggregate::handler::aggregate(&ctx, params, request).await
+}
+
+async fn find(
+    ctx: &HandlerCtx,
+    recorder: &ErrorRecorder<'_>,
+    id: Uuid,
+) -> HandlerResult<SavedQuery> {
+    let tenant_id = &ctx.tenant.tenant_id;
+    ctx.state
+        .reads
+        .with_connection(|mut conn| async move {
+            SavedQuery::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use super::*;
+    use crate::wire_api::testing::{DEFAULT_TENANT, TestApp};
+
+    #[tokio::test]
+    async fn test_saves_and_runs_aggregations() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let at = |month| Utc.with_ymd_and_hms(2025, month, 1, 0, 0, 0).unwrap();
+        app.seed_readings(
+            DEFAULT_TENANT,
+            None,
+            &[(at(1), "10.5"), (at(3), "4")],
+        )
+        .await;
+
+        let created = app
+            .server
+            .post("/api/wire/v1/energy/saved-queries")
+            .json(&json!({
+                "name": "Monthly",
+                "request": {"aggregationType": "monthly"},
+                "params": {"fill_gaps": true, "layout": "columnar"},
+            }))
+            .await;
+        created.assert_status(StatusCode::CREATED);
+        let saved = created.json::<SavedQueryResponse>();
+        assert_eq!(saved.request["aggregationType"], "monthly");
+
+        let run = app
+            .server
+            .post(&format!(
+                "/api/wire/v1/energy/saved-queries/{}/run",
+                saved.id
+            ))
+            .await;
+        run.assert_status_ok();
+        let body = run.json::<serde_json::Value>();
+        assert_eq!(body["periods"], json!([at(1), at(2), at(3)]));
+        assert_eq!(body["totals"], json!(["10.5000", "0.0000", "4.0000"]));
+
+        // Names are unique per tenant
+        app.server
+            .post("/api/wire/v1/energy/saved-queries")
+            .json(&json!({
+                "name": "Monthly",
+                "request": {"aggregationType": "hourly"},
+            }))
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        app.server
+            .post("/api/wire/v1/energy/saved-queries")
+            .json(&json!({
+                "name": "Explained",
+                "request": {"aggregationType": "hourly"},
+                "params": {"explain": true},
+            }))
+            .await
+            .assert_status_bad_request();
+
+        let updated = app
+            .server
+            .put(&format!("/api/wire/v1/energy/saved-queries/{}", saved.id))
+            .json(&json!({"name": "Quarterly", "request": {"aggregationType": "quarterly"}}))
+            .await;
+        updated.assert_status_ok();
+        let listed = app
+            .server
+            .get("/api/wire/v1/energy/saved-queries")
+            .await
+            .json::<SavedQueryListResponse>();
+        assert_eq!(
+            listed.saved_queries,
+            [updated.json::<SavedQueryResponse>()]
+        );
+
+        app.server
+            .delete(&format!("/api/wire/v1/energy/saved-queries/{}", saved.id))
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        app.server
+            .post(&format!(
+                "/api/wire/v1/energy/saved-queries/{}/run",
+                saved.id
+            ))
+            .await
+            .assert_status_not_found();
+    }
+}
@@ -0,0 +1,23 @@
+use axum::Router;
+use axum::middleware::from_extractor;
+use axum::routing::{get, post};
+
+use crate::auth::{RequirePermission, permission};
+
+mod errors;
+pub mod handler;
+pub mod models;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", get(handler::list).post(handler::create))
+        .route(
+            "/{id}",
+            get(handler::get)
+                .put(handler::update)
+                .delete(handler::delete),
+        )
+        .route("/{id}/run", post(handler::run))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .with_state(state)
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Utc};
+use postgres_models::models::saved_queries::SavedQuery;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::wire_api::core::v1::energy::aggregate::models::{
+    AggregateParams, AggregateRequest,
+};
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("blank")
+            .with_message("Saved query names cannot be blank".into()));
+    }
+    Ok(())
+}
+
+/// Check that saved parameters are accepted by `POST /energy/aggregate`
+/// and leave out `explain`, which is for debugging one request.
+fn validate_params(params: &AggregateParams) -> Result<(), ValidationError> {
+    if params.explain {
+        return Err(ValidationError::new("explain")
+            .with_message("Saved queries cannot explain".into()));
+    }
+    if params.cumulative && params.include_weather {
+        return Err(ValidationError::new("cumulative").with_message(
+            "Running totals are not correlated with the weather".into(),
+        ));
+    }
+    params
+        .decimal_format()
+        .map(|_| ())
+        .map_err(|e| ValidationError::new("decimals").with_message(e.into()))
+}
+
+/// Request payload for saving an aggregation
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSavedQueryRequest {
+    /// Name of the query, unique per tenant
+    #[validate(
+        length(min = 1, max = 200, message = "Names are 1-200 characters"),
+        custom(function = "validate_name")
+    )]
+    #[schema(example = "Monthly energy, 2025")]
+    pub name: String,
+
+    /// Body of `POST /energy/aggregate`
+    #[validate(nested)]
+    pub request: AggregateRequest,
+
+    /// Query parameters of `POST /energy/aggregate`, named as in the query
+    /// string, e.g. `{"fill_gaps": true, "decimals": 2}`; not `explain`
+    #[serde(default)]
+    #[validate(custom(function = "validate_params"))]
+    #[schema(value_type = Object)]
+    pub params: AggregateParams,
+}
+
+/// Request payload for changing a saved query; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSavedQueryRequest {
+    #[validate(
+        length(min = 1, max = 200, message = "Names are 1-200 characters"),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+
+    #[validate(nested)]
+    pub request: Option<AggregateRequest>,
+
+    #[validate(custom(function = "validate_params"))]
+    #[schema(value_type = Option<Object>)]
+    pub params: Option<AggregateParams>,
+}
+
+/// A saved aggregation
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedQueryResponse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    /// Body of `POST /energy/aggregate`
+    #[schema(value_type = AggregateRequest)]
+    pub request: serde_json::Value,
+    /// Query parameters of `POST /energy/aggregate`
+    #[schema(value_type = Object)]
+    pub params: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<SavedQuery> for SavedQueryResponse {
+    fn from(query: SavedQuery) -> Self {
+        Self {
+            id: query.id,
+            name: query.name,
+            request: query.request,
+            params: query.params,
+            created_at: query.created_at,
+            updated_at: query.updated_at,
+        }
+    }
+}
+
+/// Response containing saved queries
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedQueryListResponse {
+    pub saved_queries: Vec<SavedQueryResponse>,
+}
+
+/// The aggregation a saved query stands for, or why it no longer is one,
+/// e.g. after `POST /energy/aggregate` changed.
+pub fn aggregation(
+    query: &SavedQuery,
+) -> Result<(AggregateRequest, AggregateParams), String> {
+    let request =
+        serde_json::from_value::<AggregateRequest>(query.request.clone())
+            .map_err(|e| format!("request: {e}"))?;
+    request.validate().map_err(|e| format!("request: {e}"))?;
+    let params =
+        serde_json::from_value::<AggregateParams>(query.params.clone())
+            .map_err(|e| format!("params: {e}"))?;
+    validate_params(&params).map_err(|e| format!("params: {e}"))?;
+    Ok((request, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn saved(
+        request: serde_json::Value,
+        params: serde_json::Value,
+    ) -> SavedQuery {
+        SavedQuery {
+            id: uuid::Uuid::nil(),
+            tenant_id: "default".to_string(),
+            name: "Monthly".to_string(),
+            request,
+            params,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let (request, params) = aggregation(&saved(
+            json!({"aggregationType": "monthly"}),
+            json!({"fill_gaps": true, "decimals": 2}),
+        ))
+        .unwrap();
+        assert_eq!(request.trunc_level(), "month");
+        assert!(params.fill_gaps);
+        assert_eq!(params.decimals, Some(2));
+
+        // Saved as serialized
+        let stored = serde_json::to_value(&request).unwrap();
+        assert!(aggregation(&saved(stored, json!({}))).is_ok());
+
+        assert!(
+            aggregation(&saved(
+                json!({"aggregationType": "decade"}),
+                json!({})
+            ))
+            .unwrap_err()
+            .starts_with("request: ")
+        );
+        assert!(
+            aggregation(&saved(
+                json!({"aggregationType": "monthly"}),
+                json!({"explain": true})
+            ))
+            .unwrap_err()
+            .starts_with("params: ")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Energy target not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Plant not found: {0}")]
+    PlantNotFound(Uuid),
+
+    #[error("Invalid target period: {0}")]
+    InvalidPeriod(String),
+
+    #[error("Invalid target: {0}")]
+    InvalidTarget(f64),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Energy target not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "energy_target_not_found".to_string(),
+                    message: format!("No energy target exists with id {id}"),
+                    suggestion: "Check the energy target id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::PlantNotFound(id) => WireV1Error::not_found(
+                "Plant not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("plantId".to_string()),
+                    code: "plant_not_found".to_string(),
+                    message: format!("No plant exists with id {id}"),
+                    suggestion: "Check the plant id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidPeriod(message) => WireV1Error::bad_request(
+                "Invalid request payload".to_string(),
+                vec![WireV1Detail {
+                    field: Some("periodEnd".to_string()),
+                    code: "invalid_period".to_string(),
+                    message,
+                    suggestion: "Send a `periodEnd` after `periodStart`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidTarget(target) => WireV1Error::bad_request(
+                "Invalid request payload".to_string(),
+                vec![WireV1Detail {
+                    field: Some("targetKwh".to_string()),
+                    code: "invalid_target".to_string(),
+                    message: format!("{target} is not a valid target"),
+                    suggestion: "Send the target as a non-negative number \
+                                 of kWh"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidQuery(message) => WireV1Error::bad_request(
+                "Invalid query parameters".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_query".to_string(),
+                    message,
+                    suggestion: "Send `plantId` as a plant id and times as \
+                                 RFC 3339"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Energy target operation failed";
+}
@@ -0,0 +1,332 @@
+use std::str::FromStr;
+
+use axum::Json;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use postgres_models::connection::with_connection;
+use postgres_models::models::energy_targets::{
+    EnergyTarget, NewEnergyTarget, UpdateEnergyTarget,
+};
+use postgres_models::models::plants::Plant;
+use uuid::Uuid;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    CreateEnergyTargetRequest, EnergyTargetListResponse, EnergyTargetParams,
+    EnergyTargetResponse, ProgressParams, ProgressResponse,
+    UpdateEnergyTargetRequest, progress as target_progress, validate_period,
+};
+
+const HANDLER_NAME: &str = "energy_targets";
+
+fn target_kwh(
+    recorder: &ErrorRecorder<'_>,
+    target_kwh: f64,
+) -> HandlerResult<BigDecimal> {
+    BigDecimal::from_str(&format!("{target_kwh:.4}")).map_err(|_| {
+        recorder
+            .record("invalid_target", errors::Error::InvalidTarget(target_kwh))
+    })
+}
+
+/// Set an energy target
+///
+/// A target is the energy the tenant, or one of its plants, aims at over a
+/// period, e.g. a monthly consumption budget.
+#[utoipa::path(
+    post,
+    path = "/energy/targets",
+    request_body = CreateEnergyTargetRequest,
+    responses(
+        (status = 201, description = "Target set", body = EnergyTargetResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_targets_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateEnergyTargetRequest>,
+) -> HandlerResult<(StatusCode, Json<EnergyTargetResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let plant_id = payload.plant_id;
+    let new_target = NewEnergyTarget {
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        plant_id,
+        name: payload.name.trim().to_string(),
+        period_start: payload.period_start,
+        period_end: payload.period_end,
+        target_kwh: target_kwh(&recorder, payload.target_kwh)?,
+    };
+
+    let target = with_connection(&ctx.state.pool, |mut conn| async move {
+        if let Some(plant) = plant_id
+            && Plant::find(&new_target.tenant_id, plant, &mut conn)
+                .await?
+                .is_none()
+        {
+            return Ok(None);
+        }
+        EnergyTarget::create(new_target, &mut conn).await.map(Some)
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| {
+        recorder.record(
+            "plant_not_found",
+            errors::Error::PlantNotFound(plant_id.unwrap_or_default()),
+        )
+    })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(EnergyTargetResponse::from(target)),
+    ))
+}
+
+/// List energy targets
+///
+/// Returns the caller's tenant's targets ordered by period start,
+/// optionally of one plant.
+#[utoipa::path(
+    get,
+    path = "/energy/targets",
+    params(EnergyTargetParams),
+    responses(
+        (status = 200, description = "Energy targets", body = EnergyTargetListResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_targets_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+    params: Result<Query<EnergyTargetParams>, QueryRejection>,
+) -> HandlerResult<(StatusCode, Json<EnergyTargetListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let targets = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            EnergyTarget::list(tenant_id, params.plant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let targets = targets
+        .into_iter()
+        .map(EnergyTargetResponse::from)
+        .collect();
+
+    Ok((StatusCode::OK, Json(EnergyTargetListResponse { targets })))
+}
+
+/// Get an energy target by id
+#[utoipa::path(
+    get,
+    path = "/energy/targets/{id}",
+    params(("id" = Uuid, Path, description = "Energy target id")),
+    responses(
+        (status = 200, description = "Energy target", body = EnergyTargetResponse),
+        (status = 404, description = "Energy target not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_targets_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<EnergyTargetResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let target = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            EnergyTarget::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    Ok((StatusCode::OK, Json(EnergyTargetResponse::from(target))))
+}
+
+/// Change an energy target
+#[utoipa::path(
+    put,
+    path = "/energy/targets/{id}",
+    params(("id" = Uuid, Path, description = "Energy target id")),
+    request_body = UpdateEnergyTargetRequest,
+    responses(
+        (status = 200, description = "Updated target", body = EnergyTargetResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Energy target not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_targets_update")]
+pub async fn update(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    ValidatedPayload(payload): ValidatedPayload<UpdateEnergyTargetRequest>,
+) -> HandlerResult<(StatusCode, Json<EnergyTargetResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let target = with_connection(&ctx.state.pool, |mut conn| async move {
+        EnergyTarget::find(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    let period_start = payload.period_start.unwrap_or(target.period_start);
+    let period_end = payload.period_end.unwrap_or(target.period_end);
+    validate_period(period_start, period_end).map_err(|e| {
+        recorder.record(
+            "invalid_period",
+            errors::Error::InvalidPeriod(e.to_string()),
+        )
+    })?;
+
+    let changes = UpdateEnergyTarget {
+        name: payload.name.map(|name| name.trim().to_string()),
+        period_start: payload.period_start,
+        period_end: payload.period_end,
+        target_kwh: payload
+            .target_kwh
+            .map(|target| target_kwh(&recorder, target))
+            .transpose()?,
+    };
+    // An empty UPDATE is an error in Diesel
+    if changes.name.is_none()
+        && changes.period_start.is_none()
+        && changes.period_end.is_none()
+        && changes.target_kwh.is_none()
+    {
+        return Ok((StatusCode::OK, Json(EnergyTargetResponse::from(target))));
+    }
+    let target = with_connection(&ctx.state.pool, |mut conn| async move {
+        EnergyTarget::update(tenant_id, id, changes, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    Ok((StatusCode::OK, Json(EnergyTargetResponse::from(target))))
+}
+
+/// Delete an energy target
+#[utoipa::path(
+    delete,
+    path = "/energy/targets/{id}",
+    params(("id" = Uuid, Path, description = "Energy target id")),
+    responses(
+        (status = 204, description = "Target deleted"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Energy target not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_targets_delete")]
+pub async fn delete(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let deleted = with_connection(&ctx.state.pool, |mut conn| async move {
+        EnergyTarget::delete(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+    if deleted == 0 {
+        return Err(recorder.record("not_found", errors::Error::NotFound(id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Report progress towards energy targets
+///
+/// Compares the energy read in each target's period so far with the
+/// target, spread evenly over the period, and projects the energy at the
+/// end of the period from the rate so far. Targets are ordered by period
+/// start.
+#[utoipa::path(
+    get,
+    path = "/energy/targets/progress",
+    params(ProgressParams),
+    responses(
+        (status = 200, description = "Progress of each target", body = ProgressResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_targets_progress")]
+pub async fn progress(
+    ctx: HandlerCtx,
+    params: Result<Query<ProgressParams>, QueryRejection>,
+) -> HandlerResult<(StatusCode, Json<ProgressResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+    let at = params.at.unwrap_or_else(Utc::now);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let rows = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            EnergyTarget::actuals(
+                tenant_id,
+                params.plant_id,
+                params.date_from,
+                params.date_to,
+                at,
+                &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let targets = rows
+        .into_iter()
+        .map(|row| target_progress(row, at))
+        .collect();
+
+    Ok((StatusCode::OK, Json(ProgressResponse { at, targets })))
+}
@@ -0,0 +1,318 @@
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive, Zero};
+use chrono::{DateTime, Utc};
+use postgres_models::models::energy_targets::{EnergyTarget, TargetActual};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// Longest accepted target period, a year.
+pub const MAX_PERIOD_DAYS: i64 = 366;
+/// Largest accepted target, a TWh.
+pub const MAX_TARGET_KWH: f64 = 1_000_000_000.0;
+
+/// Decimals of the energy of progress reports.
+const KWH_SCALE: i64 = 4;
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("blank")
+            .with_message("Target names cannot be blank".into()));
+    }
+    Ok(())
+}
+
+/// Check that a target period ends after it starts and lasts at most
+/// [`MAX_PERIOD_DAYS`].
+pub fn validate_period(
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> Result<(), ValidationError> {
+    if period_end <= period_start {
+        return Err(ValidationError::new("period")
+            .with_message("Periods must end after they start".into()));
+    }
+    if (period_end - period_start).num_days() > MAX_PERIOD_DAYS {
+        return Err(ValidationError::new("period").with_message(
+            "Periods last at most 366 days; set one target per year".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_create(
+    request: &CreateEnergyTargetRequest,
+) -> Result<(), ValidationError> {
+    validate_period(request.period_start, request.period_end)
+}
+
+/// Request payload for setting an energy target
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_create"))]
+pub struct CreateEnergyTargetRequest {
+    #[validate(
+        length(min = 1, max = 200, message = "Names are 1-200 characters"),
+        custom(function = "validate_name")
+    )]
+    #[schema(example = "January budget")]
+    pub name: String,
+
+    /// Plant whose readings count; all of the tenant's when omitted
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Start of the period (inclusive)
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub period_start: DateTime<Utc>,
+
+    /// End of the period (exclusive), at most 366 days after `periodStart`
+    #[schema(example = "2025-02-01T00:00:00Z")]
+    pub period_end: DateTime<Utc>,
+
+    /// Energy aimed at over the period
+    #[validate(range(
+        min = 0.0,
+        max = MAX_TARGET_KWH,
+        message = "Targets are 0 to 1,000,000,000 kWh"
+    ))]
+    #[schema(example = 250000.0)]
+    pub target_kwh: f64,
+}
+
+/// Request payload for changing a target; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateEnergyTargetRequest {
+    #[validate(
+        length(min = 1, max = 200, message = "Names are 1-200 characters"),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+
+    pub period_start: Option<DateTime<Utc>>,
+
+    pub period_end: Option<DateTime<Utc>>,
+
+    #[validate(range(
+        min = 0.0,
+        max = MAX_TARGET_KWH,
+        message = "Targets are 0 to 1,000,000,000 kWh"
+    ))]
+    pub target_kwh: Option<f64>,
+}
+
+/// Query parameters of the target list
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct EnergyTargetParams {
+    /// Only the targets of this plant
+    pub plant_id: Option<uuid::Uuid>,
+}
+
+/// Query parameters of a progress report
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct ProgressParams {
+    /// Only the targets of this plant
+    pub plant_id: Option<uuid::Uuid>,
+
+    /// Only the targets whose period ends after this time
+    pub date_from: Option<DateTime<Utc>>,
+
+    /// Only the targets whose period starts before this time
+    pub date_to: Option<DateTime<Utc>>,
+
+    /// Time to report progress at, now when omitted
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// An energy target
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnergyTargetResponse {
+    pub id: uuid::Uuid,
+    pub plant_id: Option<uuid::Uuid>,
+    pub name: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    #[schema(example = "250000.0000")]
+    pub target_kwh: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<EnergyTarget> for EnergyTargetResponse {
+    fn from(target: EnergyTarget) -> Self {
+        Self {
+            id: target.id,
+            plant_id: target.plant_id,
+            name: target.name,
+            period_start: target.period_start,
+            period_end: target.period_end,
+            target_kwh: target.target_kwh.to_string(),
+            created_at: target.created_at,
+            updated_at: target.updated_at,
+        }
+    }
+}
+
+/// Response containing energy targets
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnergyTargetListResponse {
+    pub targets: Vec<EnergyTargetResponse>,
+}
+
+/// Where a target's period stands at the time of a report
+#[derive(Debug, Clone, Copy, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PeriodStatus {
+    Upcoming,
+    InProgress,
+    Ended,
+}
+
+/// A target with the energy read so far and where it is heading
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetProgress {
+    #[serde(flatten)]
+    pub target: EnergyTargetResponse,
+
+    pub status: PeriodStatus,
+
+    /// Share of the period elapsed, 0-1
+    #[schema(example = 0.5)]
+    pub elapsed: f64,
+
+    /// Energy read in the period so far
+    #[schema(example = "131000.0000")]
+    pub actual_kwh: String,
+
+    /// Share of the target spread evenly over the elapsed time
+    #[schema(example = "125000.0000")]
+    pub expected_to_date_kwh: String,
+
+    /// Energy at the end of the period if it goes on at the rate so far;
+    /// absent before the period starts
+    #[schema(example = "262000.0000")]
+    pub projected_kwh: Option<String>,
+
+    /// `projectedKwh - targetKwh`, positive when heading above the target
+    #[schema(example = "12000.0000")]
+    pub projected_variance_kwh: Option<String>,
+
+    /// `actualKwh` as a percentage of the target; absent for a target of 0
+    #[schema(example = 52.4)]
+    pub percent_of_target: Option<f64>,
+}
+
+/// Response for a progress report
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressResponse {
+    /// Time the progress is reported at
+    pub at: DateTime<Utc>,
+    pub targets: Vec<TargetProgress>,
+}
+
+fn rounded(value: BigDecimal) -> String {
+    value
+        .with_scale_round(KWH_SCALE, RoundingMode::HalfEven)
+        .to_string()
+}
+
+/// Progress of a target at `at`. Energy is assumed to accrue evenly over
+/// the period, both for the expected energy and the projection.
+pub fn progress(row: TargetActual, at: DateTime<Utc>) -> TargetProgress {
+    let target = row.target;
+    let total_secs = (target.period_end - target.period_start).num_seconds();
+    let elapsed_secs = (at - target.period_start)
+        .num_seconds()
+        .clamp(0, total_secs);
+    let status = if at < target.period_start {
+        PeriodStatus::Upcoming
+    } else if at < target.period_end {
+        PeriodStatus::InProgress
+    } else {
+        PeriodStatus::Ended
+    };
+
+    let total = BigDecimal::from(total_secs);
+    let elapsed = BigDecimal::from(elapsed_secs);
+    let expected = &target.target_kwh * &elapsed / &total;
+    let projected =
+        (elapsed_secs > 0).then(|| &row.actual_kwh * &total / &elapsed);
+    let percent = (!target.target_kwh.is_zero()).then(|| {
+        let percent =
+            &row.actual_kwh * BigDecimal::from(100) / &target.target_kwh;
+        percent
+            .with_scale_round(2, RoundingMode::HalfEven)
+            .to_f64()
+            .unwrap_or_default()
+    });
+
+    TargetProgress {
+        status,
+        elapsed: (elapsed_secs as f64 / total_secs as f64 * 10_000.0).round()
+            / 10_000.0,
+        actual_kwh: rounded(row.actual_kwh.clone()),
+        expected_to_date_kwh: rounded(expected),
+        projected_variance_kwh: projected
+            .as_ref()
+            .map(|projected| rounded(projected - &target.target_kwh)),
+        projected_kwh: projected.map(rounded),
+        percent_of_target: percent,
+        target: target.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn row(actual: &str) -> TargetActual {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        TargetActual {
+            target: EnergyTarget {
+                id: uuid::Uuid::nil(),
+                tenant_id: "default".to_string(),
+                plant_id: None,
+                name: "January".to_string(),
+                period_start: start,
+                period_end: Utc.with_ymd_and_hms(2025, 1, 11, 0, 0, 0).unwrap(),
+                target_kwh: "1000".parse().unwrap(),
+                created_at: start,
+                updated_at: start,
+            },
+            actual_kwh: actual.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 5, 0, 0, 0).unwrap();
+        let report = progress(row("450"), at);
+        assert_eq!(report.status, PeriodStatus::InProgress);
+        assert_eq!(report.elapsed, 0.4);
+        assert_eq!(report.expected_to_date_kwh, "400.0000");
+        assert_eq!(report.projected_kwh.as_deref(), Some("1125.0000"));
+        assert_eq!(report.projected_variance_kwh.as_deref(), Some("125.0000"));
+        assert_eq!(report.percent_of_target, Some(45.0));
+
+        let before = Utc.with_ymd_and_hms(2024, 12, 1, 0, 0, 0).unwrap();
+        let report = progress(row("0"), before);
+        assert_eq!(report.status, PeriodStatus::Upcoming);
+        assert_eq!(report.projected_kwh, None);
+        assert_eq!(report.expected_to_date_kwh, "0");
+
+        let after = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let report = progress(row("980.5"), after);
+        assert_eq!(report.status, PeriodStatus::Ended);
+        assert_eq!(report.projected_kwh.as_deref(), Some("980.5000"));
+        assert_eq!(report.projected_variance_kwh.as_deref(), Some("-19.5000"));
+    }
+}
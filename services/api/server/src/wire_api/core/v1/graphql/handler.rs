@@ -0,0 +1,62 @@
+use async_graphql::dataloader::DataLoader;
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::Extension;
+
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::loaders::ApiKeyLoader;
+use super::schema::WireSchema;
+
+/// Run a GraphQL query
+///
+/// Queries readings, aggregates and query history of the caller's tenant.
+/// The schema is served in SDL at `GET /graphql/schema`.
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    request_body(content = Object, description = "GraphQL request with `query`, and optionally `variables` and `operationName`"),
+    responses(
+        (status = 200, description = "GraphQL response with `data` and/or `errors`", body = Object),
+    ),
+    tag = "graphql",
+)]
+#[tracing::instrument(skip_all, name = "graphql")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    Extension(schema): Extension<WireSchema>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let request = request.into_inner();
+    tracing::info!(
+        operation_name = ?request.operation_name,
+        request_id = %ctx.request_id,
+        "GraphQL request",
+    );
+
+    let loader = DataLoader::new(
+        ApiKeyLoader {
+            state: ctx.state,
+            tenant_id: ctx.tenant.tenant_id.clone(),
+        },
+        tokio::spawn,
+    );
+    let mut request = request.data(ctx.tenant).data(loader);
+    if let Some(caller) = ctx.caller {
+        request = request.data(caller);
+    }
+
+    schema.execute(request).await.into()
+}
+
+/// Print the schema in SDL
+#[utoipa::path(
+    get,
+    path = "/graphql/schema",
+    responses(
+        (status = 200, description = "GraphQL schema", content_type = "text/plain", body = String),
+    ),
+    tag = "graphql",
+)]
+pub async fn schema(Extension(schema): Extension<WireSchema>) -> String {
+    schema.sdl()
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use async_graphql::dataloader::Loader;
+use postgres_models::models::api_keys::ApiKey;
+use uuid::Uuid;
+
+use crate::AppState;
+
+use super::schema::database_error;
+
+/// Batches the API key lookups of one request into a single query on the
+/// read-only pool. Built per request, so keys of other tenants are never
+/// loaded.
+pub struct ApiKeyLoader {
+    pub state: AppState,
+    pub tenant_id: String,
+}
+
+impl Loader<Uuid> for ApiKeyLoader {
+    type Value = ApiKey;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[Uuid],
+    ) -> Result<HashMap<Uuid, Self::Value>, Self::Error> {
+        let tenant_id = &self.tenant_id;
+        let api_keys = self
+            .state
+            .reads
+            .with_connection(|mut conn| async move {
+                ApiKey::find_many(tenant_id, keys, &mut conn).await
+            })
+            .await
+            .map_err(|e| database_error(&self.state, "api_keys", e))?;
+
+        Ok(api_keys.into_iter().map(|key| (key.id, key)).collect())
+    }
+}
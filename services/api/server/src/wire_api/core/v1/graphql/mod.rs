@@ -0,0 +1,42 @@
+use axum::middleware::from_extractor;
+use axum::{Extension, Router};
+use utoipa::OpenApi;
+
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+
+pub mod handler;
+pub mod loaders;
+mod openapi;
+pub mod schema;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", axum::routing::post(handler::handler))
+        .route("/schema", axum::routing::get(handler::schema))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .layer(Extension(schema::build(state.clone())))
+        .with_state(state)
+}
+
+/// GraphQL queries over readings, aggregates and query history.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "graphql"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Graphql)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/graphql", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
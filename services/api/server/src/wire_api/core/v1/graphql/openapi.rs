@@ -0,0 +1,18 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the GraphQL routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handler::handler,
+        super::handler::schema,
+    ),
+    tags(
+        (name = "graphql", description = "GraphQL queries over readings, aggregates and query history")
+    )
+)]
+pub(super) struct ApiDoc;
@@ -0,0 +1,282 @@
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Schema,
+    SimpleObject,
+};
+use chrono::{DateTime, Utc};
+use postgres_models::connection::WithConnectionError;
+use postgres_models::models::Keyset;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, EnergyReading, ReadingFilter,
+};
+use postgres_models::models::query_history::NewQueryHistory;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::{Caller, TenantContext};
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+use crate::wire_api::core::v1::energy::ingest::models::ReadingSource;
+
+use super::loaders::ApiKeyLoader;
+
+pub type WireSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Deepest nesting a query may use.
+const MAX_DEPTH: usize = 8;
+/// Most fields a query may select, lists counted once.
+const MAX_COMPLEXITY: usize = 256;
+
+pub fn build(state: AppState) -> WireSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// Log a failed query and count it like HTTP handler errors. Clients only
+/// see a generic message.
+pub(super) fn database_error(
+    state: &AppState,
+    field: &str,
+    e: WithConnectionError<diesel::result::Error>,
+) -> async_graphql::Error {
+    let error_code = match e {
+        _ if e.is_pool_exhausted() => "pool_exhausted",
+        WithConnectionError::Pool(_) => "pool_error",
+        WithConnectionError::Operation(_) => "database_error",
+    };
+    tracing::error!(field, error_code, "GraphQL query failed: {e}");
+    state.telemetry.maybe_use_metrics(|m| {
+        m.record_error("graphql", error_code);
+    });
+    async_graphql::Error::new("Internal server error")
+}
+
+/// An energy reading.
+#[derive(SimpleObject)]
+pub struct Reading {
+    pub reading_time: DateTime<Utc>,
+    /// Energy in kWh, as a decimal string
+    pub quantity_kwh: String,
+    /// Where the reading came from
+    pub source: ReadingSource,
+}
+
+/// A single aggregated data point.
+#[derive(SimpleObject)]
+pub struct AggregateDataPoint {
+    /// Start of the aggregation period
+    pub period: DateTime<Utc>,
+    /// Total energy in kWh for this period, as a decimal string
+    pub total_kwh: String,
+}
+
+/// Result of an aggregation query.
+#[derive(SimpleObject)]
+pub struct Aggregate {
+    pub aggregation_type: AggregationType,
+    pub date_from: Option<DateTime<Utc>>,
+    pub date_to: Option<DateTime<Utc>>,
+    pub data: Vec<AggregateDataPoint>,
+}
+
+/// An API key, without its secrets.
+#[derive(SimpleObject)]
+pub struct ApiKeySummary {
+    pub id: Uuid,
+    pub name: String,
+    pub role: String,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// A past aggregation query.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct HistoryEntry {
+    pub id: Uuid,
+    pub aggregation_type: String,
+    pub date_from: Option<DateTime<Utc>>,
+    pub date_to: Option<DateTime<Utc>>,
+    /// API key that issued the query, when authentication is enabled
+    pub api_key_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl HistoryEntry {
+    /// The key that issued the query. Lookups of all entries are batched.
+    async fn api_key(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<ApiKeySummary>> {
+        let Some(api_key_id) = self.api_key_id else {
+            return Ok(None);
+        };
+        let api_key = ctx
+            .data::<DataLoader<ApiKeyLoader>>()?
+            .load_one(api_key_id)
+            .await?;
+
+        Ok(api_key.map(|key| ApiKeySummary {
+            id: key.id,
+            name: key.name,
+            role: key.role,
+            revoked_at: key.revoked_at,
+        }))
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Readings in time order. Pass the `readingTime` of the last reading
+    /// as `after` to get the next page; every reading at that time, of any
+    /// plant, is skipped. With a `source`, only readings from that source
+    /// are listed.
+    async fn readings(
+        &self,
+        ctx: &Context<'_>,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+        source: Option<ReadingSource>,
+        after: Option<DateTime<Utc>>,
+        #[graphql(default = 100, validator(minimum = 1, maximum = 1000))]
+        first: i64,
+    ) -> async_graphql::Result<Vec<Reading>> {
+        let state = ctx.data::<AppState>()?;
+        let tenant_id = &ctx.data::<TenantContext>()?.tenant_id;
+
+        let readings = state
+            .reads
+            .with_connection(|mut conn| async move {
+                let keyset = after.map_or(Keyset::First, |time| {
+                    Keyset::After(time, Uuid::max())
+                });
+                let filter = ReadingFilter {
+                    date_from,
+                    date_to,
+                    source: source.map(ReadingSource::as_str),
+                    ..Default::default()
+                };
+                EnergyReading::page(tenant_id, keyset, filter, first, &mut conn)
+                    .await
+            })
+            .await
+            .map_err(|e| database_error(state, "readings", e))?;
+
+        Ok(readings
+            .into_iter()
+            .map(|reading| Reading {
+                reading_time: reading.reading_time,
+                quantity_kwh: reading.quantity_kwh.to_string(),
+                // Stored sources are checked against the known ones
+                source: reading.source.parse().unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Energy summed by hour, day or month, like `POST /energy/aggregate`.
+    /// The query is recorded in the history.
+    async fn aggregate(
+        &self,
+        ctx: &Context<'_>,
+        aggregation_type: AggregationType,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>,
+    ) -> async_graphql::Result<Aggregate> {
+        let state = ctx.data::<AppState>()?;
+        let tenant_id = &ctx.data::<TenantContext>()?.tenant_id;
+
+        let new_entry = NewQueryHistory {
+            aggregation_type: aggregation_type.to_string(),
+            date_from,
+            date_to,
+            api_key_id: ctx.data_opt::<Caller>().map(|c| c.api_key_id),
+            tenant_id: tenant_id.clone(),
+            created_at: None,
+        };
+        state
+            .record_query(new_entry)
+            .await
+            .map_err(|e| database_error(state, "aggregate", e))?;
+
+        let trunc_level = aggregation_type.to_trunc_level();
+        let rows = state
+            .readings
+            .aggregate(
+                tenant_id,
+                None,
+                trunc_level,
+                date_from,
+                date_to,
+                AggregateOptions::default(),
+            )
+            .await
+            .map_err(|e| database_error(state, "aggregate", e))?;
+
+        Ok(Aggregate {
+            aggregation_type,
+            date_from,
+            date_to,
+            data: rows
+                .into_iter()
+                .map(|row| AggregateDataPoint {
+                    period: row.period,
+                    total_kwh: row.total_kwh.to_string(),
+                })
+                .collect(),
+        })
+    }
+
+    /// The most recent aggregation queries, newest first.
+    async fn history(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 10, validator(minimum = 1, maximum = 50))]
+        limit: i64,
+    ) -> async_graphql::Result<Vec<HistoryEntry>> {
+        let state = ctx.data::<AppState>()?;
+        let tenant_id = &ctx.data::<TenantContext>()?.tenant_id;
+
+        let entries = state
+            .query_history
+            .latest(tenant_id, limit)
+            .await
+            .map_err(|e| database_error(state, "history", e))?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| HistoryEntry {
+                id: entry.id,
+                aggregation_type: entry.aggregation_type,
+                date_from: entry.date_from,
+                date_to: entry.date_to,
+                api_key_id: entry.api_key_id,
+                created_at: entry.created_at,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sdl() {
+        let sdl = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .finish()
+            .sdl();
+
+        assert!(sdl.contains("enum AggregationType"));
+        assert!(sdl.contains("DAY_OF_MONTH"));
+        assert!(sdl.contains(
+            "aggregate(aggregationType: AggregationType!, \
+             dateFrom: DateTime, dateTo: DateTime): Aggregate!"
+        ));
+        assert!(sdl.contains("apiKey: ApiKeySummary"));
+        assert!(sdl.contains("source: ReadingSource"));
+    }
+}
@@ -0,0 +1,78 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Maintenance window not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Plant not found: {0}")]
+    PlantNotFound(Uuid),
+
+    #[error("Invalid window times: {0}")]
+    InvalidTimes(String),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Maintenance window not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "maintenance_window_not_found".to_string(),
+                    message: format!(
+                        "No maintenance window exists with id {id}"
+                    ),
+                    suggestion: "Check the maintenance window id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::PlantNotFound(id) => WireV1Error::not_found(
+                "Plant not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("plantId".to_string()),
+                    code: "plant_not_found".to_string(),
+                    message: format!("No plant exists with id {id}"),
+                    suggestion: "Check the plant id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidTimes(message) => WireV1Error::bad_request(
+                "Invalid request payload".to_string(),
+                vec![WireV1Detail {
+                    field: Some("endsAt".to_string()),
+                    code: "invalid_times".to_string(),
+                    message,
+                    suggestion: "Send an `endsAt` after `startsAt`".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidQuery(message) => WireV1Error::bad_request(
+                "Invalid query parameters".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_query".to_string(),
+                    message,
+                    suggestion: "Send `plantId` as a plant id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Maintenance window operation failed";
+}
@@ -0,0 +1,305 @@
+use axum::Json;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use chrono::Utc;
+use diesel_async::AsyncConnection;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use postgres_models::connection::with_connection;
+use postgres_models::models::maintenance_windows::{
+    MaintenanceWindow, NewMaintenanceWindow, UpdateMaintenanceWindow,
+    window_status,
+};
+use postgres_models::models::plants::Plant;
+use uuid::Uuid;
+
+use crate::events::PlantEvent;
+use crate::maintenance;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    CreateMaintenanceWindowRequest, MaintenanceWindowListResponse,
+    MaintenanceWindowParams, MaintenanceWindowResponse,
+    UpdateMaintenanceWindowRequest, validate_times,
+};
+
+const HANDLER_NAME: &str = "maintenance_windows";
+
+/// Schedule maintenance of a plant
+///
+/// The scheduler puts the plant in `maintenance` once the window starts
+/// and restores its status when it ends, within
+/// `MAINTENANCE_POLL_INTERVAL_SECS` of either.
+#[utoipa::path(
+    post,
+    path = "/maintenance-windows",
+    request_body = CreateMaintenanceWindowRequest,
+    responses(
+        (status = 201, description = "Window scheduled", body = MaintenanceWindowResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "maintenance",
+)]
+#[tracing::instrument(skip_all, name = "maintenance_windows_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateMaintenanceWindowRequest>,
+) -> HandlerResult<(StatusCode, Json<MaintenanceWindowResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let plant_id = payload.plant_id;
+    let new_window = NewMaintenanceWindow {
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        plant_id,
+        starts_at: payload.starts_at,
+        ends_at: payload.ends_at,
+        notes: payload.notes,
+    };
+
+    let window = with_connection(&ctx.state.pool, |mut conn| async move {
+        if Plant::find(&new_window.tenant_id, plant_id, &mut conn)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        MaintenanceWindow::create(new_window, &mut conn)
+            .await
+            .map(Some)
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| {
+        recorder
+            .record("plant_not_found", errors::Error::PlantNotFound(plant_id))
+    })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MaintenanceWindowResponse::from(window)),
+    ))
+}
+
+/// List maintenance windows
+///
+/// Returns the caller's tenant's windows ordered by start, optionally of
+/// one plant.
+#[utoipa::path(
+    get,
+    path = "/maintenance-windows",
+    params(MaintenanceWindowParams),
+    responses(
+        (status = 200, description = "Maintenance windows", body = MaintenanceWindowListResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "maintenance",
+)]
+#[tracing::instrument(skip_all, name = "maintenance_windows_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+    params: Result<Query<MaintenanceWindowParams>, QueryRejection>,
+) -> HandlerResult<(StatusCode, Json<MaintenanceWindowListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+
+    let windows = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            MaintenanceWindow::list(tenant_id, params.plant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let windows = windows
+        .into_iter()
+        .map(MaintenanceWindowResponse::from)
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(MaintenanceWindowListResponse { windows }),
+    ))
+}
+
+/// Get a maintenance window by id
+#[utoipa::path(
+    get,
+    path = "/maintenance-windows/{id}",
+    params(("id" = Uuid, Path, description = "Maintenance window id")),
+    responses(
+        (status = 200, description = "Maintenance window", body = MaintenanceWindowResponse),
+        (status = 404, description = "Maintenance window not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "maintenance",
+)]
+#[tracing::instrument(skip_all, name = "maintenance_windows_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<MaintenanceWindowResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let window = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            MaintenanceWindow::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(MaintenanceWindowResponse::from(window)),
+    ))
+}
+
+/// Reschedule a maintenance window
+///
+/// Moving an active window out of the current time ends it on the
+/// scheduler's next run, and moving a completed one into the future
+/// schedules it again.
+#[utoipa::path(
+    put,
+    path = "/maintenance-windows/{id}",
+    params(("id" = Uuid, Path, description = "Maintenance window id")),
+    request_body = UpdateMaintenanceWindowRequest,
+    responses(
+        (status = 200, description = "Updated window", body = MaintenanceWindowResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Maintenance window not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "maintenance",
+)]
+#[tracing::instrument(skip_all, name = "maintenance_windows_update")]
+pub async fn update(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    ValidatedPayload(payload): ValidatedPayload<UpdateMaintenanceWindowRequest>,
+) -> HandlerResult<(StatusCode, Json<MaintenanceWindowResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let window = with_connection(&ctx.state.pool, |mut conn| async move {
+        MaintenanceWindow::find(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    let starts_at = payload.starts_at.unwrap_or(window.starts_at);
+    let ends_at = payload.ends_at.unwrap_or(window.ends_at);
+    validate_times(starts_at, ends_at).map_err(|e| {
+        recorder
+            .record("invalid_times", errors::Error::InvalidTimes(e.to_string()))
+    })?;
+
+    // The scheduler only looks at windows that are not completed
+    let reopened =
+        window.status == window_status::COMPLETED && ends_at > Utc::now();
+    let changes = UpdateMaintenanceWindow {
+        starts_at: payload.starts_at,
+        ends_at: payload.ends_at,
+        notes: payload.notes.map(Some),
+        status: reopened.then(|| window_status::SCHEDULED.to_string()),
+    };
+    // An empty UPDATE is an error in Diesel
+    if changes.starts_at.is_none()
+        && changes.ends_at.is_none()
+        && changes.notes.is_none()
+        && changes.status.is_none()
+    {
+        return Ok((
+            StatusCode::OK,
+            Json(MaintenanceWindowResponse::from(window)),
+        ));
+    }
+    let window = with_connection(&ctx.state.pool, |mut conn| async move {
+        MaintenanceWindow::update(tenant_id, id, changes, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(MaintenanceWindowResponse::from(window)),
+    ))
+}
+
+/// Delete a maintenance window
+///
+/// Deleting an active window ends the plant's maintenance right away.
+#[utoipa::path(
+    delete,
+    path = "/maintenance-windows/{id}",
+    params(("id" = Uuid, Path, description = "Maintenance window id")),
+    responses(
+        (status = 204, description = "Window deleted"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Maintenance window not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "maintenance",
+)]
+#[tracing::instrument(skip_all, name = "maintenance_windows_delete")]
+pub async fn delete(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let deleted = with_connection(&ctx.state.pool, |mut conn| async move {
+        conn.transaction::<_, diesel::result::Error, _>(move |conn| {
+            async move {
+                let Some(window) =
+                    MaintenanceWindow::find_for_update(tenant_id, id, conn)
+                        .await?
+                else {
+                    return Ok(None);
+                };
+                let plant = if window.status == window_status::ACTIVE {
+                    maintenance::leave(&window, window_status::COMPLETED, conn)
+                        .await?
+                } else {
+                    None
+                };
+                MaintenanceWindow::delete(id, conn).await?;
+                Ok(Some(plant))
+            }
+            .scope_boxed()
+        })
+        .await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let plant = deleted.ok_or_else(|| {
+        recorder.record("not_found", errors::Error::NotFound(id))
+    })?;
+    if let Some(plant) = plant {
+        ctx.state.plant_events.publish(PlantEvent::Updated(plant));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
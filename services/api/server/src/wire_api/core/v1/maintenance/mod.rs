@@ -0,0 +1,51 @@
+use axum::Router;
+use axum::middleware::from_extractor;
+use axum::routing::get;
+use utoipa::OpenApi;
+
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+
+mod errors;
+pub mod handler;
+pub mod models;
+mod openapi;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    let manage = Router::new()
+        .route("/", axum::routing::post(handler::create))
+        .route(
+            "/{id}",
+            axum::routing::put(handler::update).delete(handler::delete),
+        )
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>());
+
+    Router::new()
+        .route("/", get(handler::list))
+        .route("/{id}", get(handler::get))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .merge(manage)
+        .with_state(state)
+}
+
+/// Planned maintenance windows of plants.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "maintenance"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Maintenance)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/maintenance-windows", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
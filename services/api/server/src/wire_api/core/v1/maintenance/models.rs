@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use postgres_models::models::maintenance_windows::MaintenanceWindow;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// Longest accepted window, a year.
+pub const MAX_WINDOW_DAYS: i64 = 366;
+
+/// Check that a window ends after it starts and lasts at most
+/// [`MAX_WINDOW_DAYS`].
+pub fn validate_times(
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+) -> Result<(), ValidationError> {
+    if ends_at <= starts_at {
+        return Err(ValidationError::new("times")
+            .with_message("Windows must end after they start".into()));
+    }
+    if (ends_at - starts_at).num_days() >= MAX_WINDOW_DAYS {
+        return Err(ValidationError::new("times").with_message(
+            "Windows last at most 366 days; split longer work".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_create(
+    request: &CreateMaintenanceWindowRequest,
+) -> Result<(), ValidationError> {
+    validate_times(request.starts_at, request.ends_at)
+}
+
+/// Request payload for scheduling maintenance of a plant
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_create"))]
+pub struct CreateMaintenanceWindowRequest {
+    pub plant_id: uuid::Uuid,
+
+    /// Planned start
+    #[schema(example = "2025-06-01T06:00:00Z")]
+    pub starts_at: DateTime<Utc>,
+
+    /// Planned end, after `startsAt`
+    #[schema(example = "2025-06-01T18:00:00Z")]
+    pub ends_at: DateTime<Utc>,
+
+    #[validate(length(
+        max = 2000,
+        message = "Notes are at most 2000 characters"
+    ))]
+    #[schema(example = "Inverter replacement")]
+    pub notes: Option<String>,
+}
+
+/// Request payload for rescheduling a window; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMaintenanceWindowRequest {
+    pub starts_at: Option<DateTime<Utc>>,
+
+    pub ends_at: Option<DateTime<Utc>>,
+
+    #[validate(length(
+        max = 2000,
+        message = "Notes are at most 2000 characters"
+    ))]
+    pub notes: Option<String>,
+}
+
+/// Query parameters of the window list
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct MaintenanceWindowParams {
+    /// Only the windows of this plant
+    pub plant_id: Option<uuid::Uuid>,
+}
+
+/// A planned maintenance window
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceWindowResponse {
+    pub id: uuid::Uuid,
+    pub plant_id: uuid::Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub notes: Option<String>,
+    /// `scheduled`, `active` or `completed`, updated by the scheduler
+    #[schema(example = "scheduled")]
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<MaintenanceWindow> for MaintenanceWindowResponse {
+    fn from(window: MaintenanceWindow) -> Self {
+        Self {
+            id: window.id,
+            plant_id: window.plant_id,
+            starts_at: window.starts_at,
+            ends_at: window.ends_at,
+            notes: window.notes,
+            status: window.status,
+            created_at: window.created_at,
+            updated_at: window.updated_at,
+        }
+    }
+}
+
+/// Response containing maintenance windows
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceWindowListResponse {
+    pub windows: Vec<MaintenanceWindowResponse>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_validate_times() {
+        let start = time("2025-06-01T06:00:00Z");
+
+        assert!(validate_times(start, time("2025-06-01T18:00:00Z")).is_ok());
+        assert!(validate_times(start, start).is_err());
+        assert!(validate_times(start, time("2025-05-31T06:00:00Z")).is_err());
+        assert!(validate_times(start, time("2026-06-02T06:00:00Z")).is_err());
+    }
+}
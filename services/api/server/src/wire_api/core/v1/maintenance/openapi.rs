@@ -0,0 +1,21 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the maintenance window routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handler::create,
+        super::handler::list,
+        super::handler::get,
+        super::handler::update,
+        super::handler::delete,
+    ),
+    tags(
+        (name = "maintenance", description = "Planned maintenance windows of plants")
+    )
+)]
+pub(super) struct ApiDoc;
@@ -1,9 +1,48 @@
 use axum::Router;
+use axum::middleware::{from_fn, from_fn_with_state};
 
+use crate::auth::csrf::RouteGroup;
+use crate::modules::{ApiModules, Mount};
+
+pub(crate) mod admin;
+pub(crate) mod alert_rules;
+pub(crate) mod alerts;
 pub(crate) mod energy;
 pub(crate) mod errors;
+pub(crate) mod graphql;
+pub(crate) mod maintenance;
+pub(crate) mod notification_channels;
+pub(crate) mod plants;
+pub(crate) mod preferences;
 pub(crate) mod types;
+pub(crate) mod usage;
+pub(crate) mod webhooks;
+
+/// Routes of the modules served on the public listeners, the metered ones
+/// counted against the caller's quota, see [`Mount`].
+pub fn get_routes(state: crate::AppState, modules: &ApiModules) -> Router {
+    let metered =
+        modules
+            .routes(&state, Mount::Metered)
+            .layer(from_fn_with_state(
+                state.clone(),
+                crate::auth::middleware::enforce_quota,
+            ));
 
-pub fn get_routes(state: crate::AppState) -> Router {
-    Router::new().nest("/energy", energy::get_routes(state))
+    metered
+        .merge(modules.routes(&state, Mount::Unmetered))
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::middleware::authenticate,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::deadline::enforce_deadline,
+        ))
+        .layer(from_fn_with_state(
+            (state.clone(), RouteGroup::Wire),
+            crate::auth::middleware::protect_csrf,
+        ))
+        .layer(from_fn(crate::read_fallback::flag_degraded))
+        .layer(from_fn_with_state(state, crate::load::advise))
 }
@@ -0,0 +1,71 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Notification channel not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid target: {0}")]
+    InvalidTarget(String),
+
+    #[error("Email channels are not configured")]
+    EmailNotConfigured,
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Notification channel not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "notification_channel_not_found".to_string(),
+                    message: format!(
+                        "No notification channel exists with id {id}"
+                    ),
+                    suggestion: "Check the notification channel id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidTarget(message) => WireV1Error::bad_request(
+                "Invalid request payload".to_string(),
+                vec![WireV1Detail {
+                    field: Some("target".to_string()),
+                    code: "invalid_target".to_string(),
+                    message,
+                    suggestion: "Use an http(s) URL for webhook and slack \
+                                 channels, and comma separated addresses \
+                                 for email channels"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::EmailNotConfigured => WireV1Error::bad_request(
+                "Invalid request payload".to_string(),
+                vec![WireV1Detail {
+                    field: Some("kind".to_string()),
+                    code: "email_not_configured".to_string(),
+                    message: "This deployment has no SMTP relay for email \
+                              channels"
+                        .to_string(),
+                    suggestion: "Use a webhook or slack channel, or set \
+                                 `SMTP_URL` and `ALERT_EMAIL_FROM`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Notification channel operation failed";
+}
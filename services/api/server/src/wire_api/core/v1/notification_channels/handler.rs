@@ -0,0 +1,317 @@
+use axum::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use postgres_models::connection::with_connection;
+use postgres_models::models::notifications::{
+    NewNotificationChannel, Notification, NotificationChannel,
+    UpdateNotificationChannel,
+};
+use uuid::Uuid;
+
+use crate::notifications::SystemEvent;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    ChannelKind, CreateNotificationChannelRequest,
+    NotificationChannelListResponse, NotificationChannelResponse,
+    NotificationsResponse, UpdateNotificationChannelRequest, validate_target,
+};
+
+const HANDLER_NAME: &str = "notification_channels";
+const NOTIFICATIONS_LIMIT: i64 = 50;
+
+fn events(events: &[SystemEvent]) -> Vec<Option<String>> {
+    events
+        .iter()
+        .map(|e| Some(e.as_str().to_string()))
+        .collect()
+}
+
+/// Create a notification channel
+///
+/// Email channels need an SMTP relay (`SMTP_URL` and `ALERT_EMAIL_FROM`).
+#[utoipa::path(
+    post,
+    path = "/notification-channels",
+    request_body = CreateNotificationChannelRequest,
+    responses(
+        (status = 201, description = "Channel created", body = NotificationChannelResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "notifications",
+)]
+#[tracing::instrument(skip_all, name = "notification_channels_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<
+        CreateNotificationChannelRequest,
+    >,
+) -> HandlerResult<(StatusCode, Json<NotificationChannelResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    if payload.kind == ChannelKind::Email && ctx.state.config.smtp.is_none() {
+        return Err(recorder.record(
+            "email_not_configured",
+            errors::Error::EmailNotConfigured,
+        ));
+    }
+
+    let new_channel = NewNotificationChannel {
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        name: payload.name,
+        kind: payload.kind.as_str().to_string(),
+        target: payload.target,
+        events: events(&payload.events),
+        enabled: payload.enabled.unwrap_or(true),
+    };
+
+    let channel = with_connection(&ctx.state.pool, |mut conn| async move {
+        NotificationChannel::create(new_channel, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(NotificationChannelResponse::from(channel)),
+    ))
+}
+
+/// List notification channels
+///
+/// Returns the caller's tenant's channels ordered by name.
+#[utoipa::path(
+    get,
+    path = "/notification-channels",
+    responses(
+        (status = 200, description = "Notification channels", body = NotificationChannelListResponse),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "notifications",
+)]
+#[tracing::instrument(skip_all, name = "notification_channels_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<NotificationChannelListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let channels = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            NotificationChannel::list(tenant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let channels = channels
+        .into_iter()
+        .map(NotificationChannelResponse::from)
+        .collect();
+    Ok((
+        StatusCode::OK,
+        Json(NotificationChannelListResponse { channels }),
+    ))
+}
+
+/// Get a notification channel by id
+#[utoipa::path(
+    get,
+    path = "/notification-channels/{id}",
+    params(("id" = Uuid, Path, description = "Notification channel id")),
+    responses(
+        (status = 200, description = "Notification channel", body = NotificationChannelResponse),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Notification channel not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "notifications",
+)]
+#[tracing::instrument(skip_all, name = "notification_channels_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<NotificationChannelResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let channel = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            NotificationChannel::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationChannelResponse::from(channel)),
+    ))
+}
+
+/// Update a notification channel
+///
+/// A new target is checked against the channel's kind.
+#[utoipa::path(
+    put,
+    path = "/notification-channels/{id}",
+    params(("id" = Uuid, Path, description = "Notification channel id")),
+    request_body = UpdateNotificationChannelRequest,
+    responses(
+        (status = 200, description = "Updated channel", body = NotificationChannelResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Notification channel not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "notifications",
+)]
+#[tracing::instrument(skip_all, name = "notification_channels_update")]
+pub async fn update(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    ValidatedPayload(payload): ValidatedPayload<
+        UpdateNotificationChannelRequest,
+    >,
+) -> HandlerResult<(StatusCode, Json<NotificationChannelResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let channel = with_connection(&ctx.state.pool, |mut conn| async move {
+        NotificationChannel::find(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    if let Some(target) = &payload.target {
+        validate_target(&channel.kind, target).map_err(|e| {
+            recorder.record("invalid_target", errors::Error::InvalidTarget(e))
+        })?;
+    }
+
+    let changes = UpdateNotificationChannel {
+        name: payload.name,
+        target: payload.target,
+        events: payload.events.as_deref().map(events),
+        enabled: payload.enabled,
+    };
+    // An empty UPDATE is an error in Diesel
+    if changes.name.is_none()
+        && changes.target.is_none()
+        && changes.events.is_none()
+        && changes.enabled.is_none()
+    {
+        return Ok((
+            StatusCode::OK,
+            Json(NotificationChannelResponse::from(channel)),
+        ));
+    }
+
+    let channel = with_connection(&ctx.state.pool, |mut conn| async move {
+        NotificationChannel::update(tenant_id, id, changes, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    .ok_or_else(|| recorder.record("not_found", errors::Error::NotFound(id)))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationChannelResponse::from(channel)),
+    ))
+}
+
+/// Delete a notification channel
+///
+/// Its notifications are deleted with it; alert rules using it keep
+/// firing without a channel.
+#[utoipa::path(
+    delete,
+    path = "/notification-channels/{id}",
+    params(("id" = Uuid, Path, description = "Notification channel id")),
+    responses(
+        (status = 204, description = "Channel deleted"),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Notification channel not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "notifications",
+)]
+#[tracing::instrument(skip_all, name = "notification_channels_delete")]
+pub async fn delete(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let deleted = with_connection(&ctx.state.pool, |mut conn| async move {
+        NotificationChannel::delete(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    if deleted == 0 {
+        return Err(recorder.record("not_found", errors::Error::NotFound(id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the most recent notifications of a channel
+#[utoipa::path(
+    get,
+    path = "/notification-channels/{id}/notifications",
+    params(("id" = Uuid, Path, description = "Notification channel id")),
+    responses(
+        (status = 200, description = "Last 50 notifications", body = NotificationsResponse),
+        (status = 403, description = "Admin role required"),
+        (status = 404, description = "Notification channel not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "notifications",
+)]
+#[tracing::instrument(skip_all, name = "notification_channels_notifications")]
+pub async fn notifications(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<NotificationsResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let notifications = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            if NotificationChannel::find(tenant_id, id, &mut conn)
+                .await?
+                .is_none()
+            {
+                return Ok(None);
+            }
+            Notification::latest_for_channel(id, NOTIFICATIONS_LIMIT, &mut conn)
+                .await
+                .map(Some)
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    let notifications = notifications.into_iter().map(Into::into).collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationsResponse { notifications }),
+    ))
+}
@@ -0,0 +1,49 @@
+use axum::Router;
+use axum::middleware::from_extractor;
+use axum::routing::get;
+use utoipa::OpenApi;
+
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+
+mod errors;
+pub mod handler;
+pub mod models;
+mod openapi;
+
+// Admin only: channel targets are webhook URLs and recipients
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", get(handler::list).post(handler::create))
+        .route(
+            "/{id}",
+            get(handler::get)
+                .put(handler::update)
+                .delete(handler::delete),
+        )
+        .route("/{id}/notifications", get(handler::notifications))
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>())
+        .with_state(state)
+}
+
+/// Channels alerts and system events are sent to.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "notifications"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Notifications)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/notification-channels", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
@@ -0,0 +1,233 @@
+use chrono::{DateTime, Utc};
+use postgres_models::models::notifications::{
+    Notification, NotificationChannel, channel_kind,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::notifications::SystemEvent;
+use crate::notifications::email;
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("blank")
+            .with_message("Channel names cannot be blank".into()));
+    }
+    Ok(())
+}
+
+/// Check that `target` suits a channel of `kind`: an http(s) URL for
+/// `webhook` and `slack` channels, recipients for `email` channels.
+pub fn validate_target(kind: &str, target: &str) -> Result<(), String> {
+    if kind == channel_kind::EMAIL {
+        return email::parse_recipients(target).map(|_| ());
+    }
+    match Url::parse(target) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+        Ok(_) => Err("expected an http:// or https:// URL".to_string()),
+        Err(e) => Err(format!("not a URL: {e}")),
+    }
+}
+
+fn validate_create(
+    request: &CreateNotificationChannelRequest,
+) -> Result<(), ValidationError> {
+    validate_target(request.kind.as_str(), &request.target)
+        .map_err(|e| ValidationError::new("target").with_message(e.into()))
+}
+
+/// How notifications are sent
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    /// A JSON POST to `target`
+    Webhook,
+    /// A `text` message to the Slack-compatible incoming webhook `target`
+    Slack,
+    /// An email to the comma separated recipients in `target`
+    Email,
+}
+
+impl ChannelKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChannelKind::Webhook => channel_kind::WEBHOOK,
+            ChannelKind::Slack => channel_kind::SLACK,
+            ChannelKind::Email => channel_kind::EMAIL,
+        }
+    }
+}
+
+/// Request payload for creating a notification channel
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_create"))]
+pub struct CreateNotificationChannelRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 200,
+            message = "Channel names are 1-200 characters"
+        ),
+        custom(function = "validate_name")
+    )]
+    #[schema(example = "Grid ops Slack")]
+    pub name: String,
+
+    pub kind: ChannelKind,
+
+    /// URL of `webhook` and `slack` channels, comma separated recipients of
+    /// `email` channels
+    #[validate(length(
+        min = 1,
+        max = 2000,
+        message = "Targets are 1-2000 characters"
+    ))]
+    #[schema(example = "https://hooks.slack.com/services/T000/B000/XXXX")]
+    pub target: String,
+
+    /// System events sent to the channel; alert rules name their channel
+    /// themselves
+    #[serde(default)]
+    pub events: Vec<SystemEvent>,
+
+    /// Whether notifications are sent (defaults to true)
+    pub enabled: Option<bool>,
+}
+
+/// Request payload for updating a notification channel; omitted fields are
+/// unchanged. The kind of a channel cannot change.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateNotificationChannelRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 200,
+            message = "Channel names are 1-200 characters"
+        ),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+
+    #[validate(length(
+        min = 1,
+        max = 2000,
+        message = "Targets are 1-2000 characters"
+    ))]
+    pub target: Option<String>,
+
+    pub events: Option<Vec<SystemEvent>>,
+
+    /// Notifications queued for a disabled channel fail
+    pub enabled: Option<bool>,
+}
+
+/// A destination for the tenant's notifications
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationChannelResponse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    #[schema(example = "slack")]
+    pub kind: String,
+    pub target: String,
+    #[schema(example = json!(["import_failed"]))]
+    pub events: Vec<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<NotificationChannel> for NotificationChannelResponse {
+    fn from(channel: NotificationChannel) -> Self {
+        Self {
+            events: channel.subscribed_events(),
+            id: channel.id,
+            name: channel.name,
+            kind: channel.kind,
+            target: channel.target,
+            enabled: channel.enabled,
+            created_at: channel.created_at,
+            updated_at: channel.updated_at,
+        }
+    }
+}
+
+/// Response containing notification channels
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationChannelListResponse {
+    pub channels: Vec<NotificationChannelResponse>,
+}
+
+/// A notification and the outcome of its latest attempt
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationEntry {
+    pub id: uuid::Uuid,
+    #[schema(example = "alert_fired")]
+    pub event: String,
+    pub subject: String,
+    #[schema(example = "delivered")]
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Notification> for NotificationEntry {
+    fn from(notification: Notification) -> Self {
+        Self {
+            id: notification.id,
+            event: notification.event,
+            subject: notification.subject,
+            status: notification.status,
+            attempts: notification.attempts,
+            last_error: notification.last_error,
+            next_attempt_at: notification.next_attempt_at,
+            delivered_at: notification.delivered_at,
+            created_at: notification.created_at,
+        }
+    }
+}
+
+/// Response containing the most recent notifications of a channel
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationsResponse {
+    pub notifications: Vec<NotificationEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_target() {
+        assert!(
+            validate_target(channel_kind::SLACK, "https://hooks.example.com/x")
+                .is_ok()
+        );
+        assert!(
+            validate_target(channel_kind::WEBHOOK, "ftp://example.com")
+                .is_err()
+        );
+        assert!(
+            validate_target(channel_kind::WEBHOOK, "ops@example.com").is_err()
+        );
+        assert!(
+            validate_target(channel_kind::EMAIL, "ops@example.com").is_ok()
+        );
+        assert!(
+            validate_target(channel_kind::EMAIL, "https://example.com")
+                .is_err()
+        );
+    }
+}
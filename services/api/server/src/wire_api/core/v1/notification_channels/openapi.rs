@@ -0,0 +1,22 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the notification channel routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handler::create,
+        super::handler::list,
+        super::handler::get,
+        super::handler::update,
+        super::handler::delete,
+        super::handler::notifications,
+    ),
+    tags(
+        (name = "notifications", description = "Webhook, Slack and email channels for alerts and system events, restricted to the admin role")
+    )
+)]
+pub(super) struct ApiDoc;
@@ -0,0 +1,196 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+use super::handler::MAX_IMPORT_ROWS;
+use super::models::PlantFileFormat;
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Plant not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid capacity: {0}")]
+    InvalidCapacity(f64),
+
+    #[error("Unsupported import content type: {0}")]
+    UnsupportedContentType(String),
+
+    #[error("Invalid import file: {0}")]
+    InvalidFile(String),
+
+    #[error("Too many rows: {0}")]
+    TooManyRows(usize),
+
+    #[error("Invalid query: {message}")]
+    InvalidQuery {
+        message: String,
+        suggestion: &'static str,
+    },
+
+    #[error("Failed to write export: {0}")]
+    ExportFailed(String),
+
+    #[error("Missing If-Match header")]
+    MissingIfMatch,
+
+    #[error("Invalid If-Match header: {0}")]
+    InvalidIfMatch(String),
+
+    #[error("Plant version {current} does not match {expected}")]
+    VersionMismatch { expected: i32, current: i32 },
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotFound(id) => WireV1Error::not_found(
+                "Plant not found".to_string(),
+                vec![WireV1Detail {
+                    field: Some("id".to_string()),
+                    code: "plant_not_found".to_string(),
+                    message: format!("No plant exists with id {id}"),
+                    suggestion: "Check the plant id".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidCapacity(capacity) => WireV1Error::bad_request(
+                "Invalid request payload".to_string(),
+                vec![WireV1Detail {
+                    field: Some("capacityMw".to_string()),
+                    code: "invalid_capacity".to_string(),
+                    message: format!("{capacity} is not a valid capacity"),
+                    suggestion: "Send the capacity in MW as a number"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::UnsupportedContentType(content_type) => {
+                WireV1Error::bad_request(
+                    "Unsupported import file".to_string(),
+                    vec![WireV1Detail {
+                        field: Some("Content-Type".to_string()),
+                        code: "unsupported_content_type".to_string(),
+                        message: format!(
+                            "Cannot import `{content_type}` files"
+                        ),
+                        suggestion: format!(
+                            "Send `{}` or `{}`",
+                            PlantFileFormat::CSV_CONTENT_TYPE,
+                            PlantFileFormat::XLSX_CONTENT_TYPE
+                        ),
+                        documentation: String::new(),
+                    }],
+                    request_id.to_string(),
+                )
+            }
+            Error::InvalidFile(e) => WireV1Error::bad_request(
+                "Invalid import file".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_file".to_string(),
+                    message: e,
+                    suggestion: "Send a header row with `name`, \
+                                 `energy_type` and `capacity_mw` columns"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::TooManyRows(rows) => WireV1Error::bad_request(
+                "Invalid import file".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "too_many_rows".to_string(),
+                    message: format!(
+                        "The file has {rows} rows, at most {MAX_IMPORT_ROWS} \
+                         are accepted"
+                    ),
+                    suggestion: "Split the file".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidQuery {
+                message,
+                suggestion,
+            } => WireV1Error::bad_request(
+                "Invalid query parameters".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_query".to_string(),
+                    message,
+                    suggestion: suggestion.to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::ExportFailed(e) => WireV1Error::internal_server_error(
+                "Export failed".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "export_failed".to_string(),
+                    message: format!("Failed to write export: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::MissingIfMatch => WireV1Error::precondition_required(
+                "Precondition required".to_string(),
+                vec![WireV1Detail {
+                    field: Some("If-Match".to_string()),
+                    code: "missing_if_match".to_string(),
+                    message: "Changes to a plant must name the version \
+                              they are based on"
+                        .to_string(),
+                    suggestion: "Send the plant's `version` as \
+                                 `If-Match: \"<version>\"`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidIfMatch(value) => WireV1Error::bad_request(
+                "Invalid request headers".to_string(),
+                vec![WireV1Detail {
+                    field: Some("If-Match".to_string()),
+                    code: "invalid_if_match".to_string(),
+                    message: format!("`{value}` is not a plant version"),
+                    suggestion: "Send the plant's `version` as \
+                                 `If-Match: \"<version>\"`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::VersionMismatch { expected, current } => {
+                WireV1Error::conflict(
+                    "Plant was modified".to_string(),
+                    vec![WireV1Detail {
+                        field: Some("If-Match".to_string()),
+                        code: "version_mismatch".to_string(),
+                        message: format!(
+                            "The plant is at version {current}, the request \
+                             was based on version {expected}"
+                        ),
+                        suggestion: "Get the plant, reapply the changes and \
+                                     retry with its current version"
+                            .to_string(),
+                        documentation: String::new(),
+                    }],
+                    request_id.to_string(),
+                )
+            }
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Plant operation failed";
+}
@@ -0,0 +1,177 @@
+//! Capacity factors: the energy a plant generated over the energy its
+//! nameplate capacity yields running at full output for the same time.
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Utc};
+use postgres_models::models::energy_readings::AggregatedReading;
+use uuid::Uuid;
+
+use super::models::{
+    PlantAggregateDataPoint, PlantAggregateParams, PlantAggregateResponse,
+};
+
+/// Hours of `[start, end)` that fall in the requested date range.
+fn hours(
+    params: &PlantAggregateParams,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> f64 {
+    let start = params.date_from.map_or(start, |from| start.max(from));
+    let end = params.date_to.map_or(end, |to| end.min(to));
+    ((end - start).num_seconds() as f64 / 3600.0).max(0.0)
+}
+
+/// `None` when the plant could not have generated anything in `hours`.
+fn capacity_factor(
+    kwh: &BigDecimal,
+    capacity_mw: f64,
+    hours: f64,
+) -> Option<f64> {
+    let possible_kwh = capacity_mw * 1000.0 * hours;
+    if possible_kwh <= 0.0 {
+        return None;
+    }
+    let factor = kwh.to_f64()? / possible_kwh;
+    Some((factor * 10_000.0).round() / 10_000.0)
+}
+
+/// Aggregated readings of a plant with the capacity factor of each period
+/// and of the whole date range. Periods cut by `dateFrom` or `dateTo` count
+/// only their hours inside the range; an open range runs from the first
+/// period to the end of the last.
+pub fn response(
+    plant_id: Uuid,
+    capacity_mw: f64,
+    params: PlantAggregateParams,
+    rows: Vec<AggregatedReading>,
+) -> PlantAggregateResponse {
+    let aggregation_type = params.aggregation_type;
+    let total_kwh = rows.iter().map(|row| &row.total_kwh).sum::<BigDecimal>();
+    let overall = match (rows.first(), rows.last()) {
+        (Some(first), Some(last)) => capacity_factor(
+            &total_kwh,
+            capacity_mw,
+            hours(
+                &params,
+                first.period,
+                params.date_to.unwrap_or_else(|| {
+                    aggregation_type.period_end(last.period)
+                }),
+            ),
+        ),
+        _ => None,
+    };
+
+    let data = rows
+        .into_iter()
+        .map(|row| {
+            let end = aggregation_type.period_end(row.period);
+            let hours = hours(&params, row.period, end);
+            PlantAggregateDataPoint {
+                period: row.period,
+                capacity_factor: capacity_factor(
+                    &row.total_kwh,
+                    capacity_mw,
+                    hours,
+                ),
+                total_kwh: row.total_kwh.to_string(),
+            }
+        })
+        .collect();
+
+    PlantAggregateResponse {
+        plant_id,
+        capacity_mw,
+        aggregation_type,
+        date_from: params.date_from,
+        date_to: params.date_to,
+        total_kwh: format!("{total_kwh:.4}"),
+        capacity_factor: overall,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+    fn row(period: &str, kwh: &str) -> AggregatedReading {
+        AggregatedReading {
+            period: period.parse().unwrap(),
+            total_kwh: kwh.parse().unwrap(),
+            reading_count: 1,
+            ..Default::default()
+        }
+    }
+
+    fn params(
+        aggregation_type: AggregationType,
+        date_from: Option<&str>,
+        date_to: Option<&str>,
+    ) -> PlantAggregateParams {
+        PlantAggregateParams {
+            aggregation_type,
+            date_from: date_from.map(|d| d.parse().unwrap()),
+            date_to: date_to.map(|d| d.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_daily_capacity_factor() {
+        // 10 MW yields 240 MWh a day
+        let response = response(
+            Uuid::nil(),
+            10.0,
+            params(AggregationType::DayOfMonth, None, None),
+            vec![
+                row("2025-01-01T00:00:00Z", "60000"),
+                row("2025-01-02T00:00:00Z", "240000"),
+            ],
+        );
+
+        assert_eq!(response.total_kwh, "300000.0000");
+        assert_eq!(response.capacity_factor, Some(0.625));
+        let factors = response
+            .data
+            .iter()
+            .map(|point| point.capacity_factor)
+            .collect::<Vec<_>>();
+        assert_eq!(factors, [Some(0.25), Some(1.0)]);
+    }
+
+    #[test]
+    fn test_range_cuts_periods() {
+        // January has 744 hours; the range keeps 12 of them
+        let response = response(
+            Uuid::nil(),
+            1.0,
+            params(
+                AggregationType::Monthly,
+                Some("2025-01-31T12:00:00Z"),
+                Some("2025-03-01T00:00:00Z"),
+            ),
+            vec![
+                row("2025-01-01T00:00:00Z", "6000"),
+                row("2025-02-01T00:00:00Z", "336000"),
+            ],
+        );
+
+        assert_eq!(response.data[0].capacity_factor, Some(0.5));
+        assert_eq!(response.data[1].capacity_factor, Some(0.5));
+        assert_eq!(response.capacity_factor, Some(0.5));
+    }
+
+    #[test]
+    fn test_no_readings() {
+        let response = response(
+            Uuid::nil(),
+            1.0,
+            params(AggregationType::Hourly, None, None),
+            Vec::new(),
+        );
+
+        assert_eq!(response.total_kwh, "0.0000");
+        assert_eq!(response.capacity_factor, None);
+        assert!(response.data.is_empty());
+    }
+}
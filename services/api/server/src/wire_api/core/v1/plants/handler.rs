@@ -0,0 +1,979 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, KeepAliveStream, Sse};
+use axum::response::{IntoResponse, Response};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use postgres_models::connection::with_connection;
+use postgres_models::models::energy_readings::{
+    AggregateOptions, EnergyReading,
+};
+use postgres_models::models::plants::{NewPlant, Plant, UpdatePlant};
+use postgres_models::models::query_history::NewQueryHistory;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::AppState;
+use crate::events::PlantEvent;
+use crate::flags::Flag;
+use crate::shared::conditional;
+use crate::shared::extractors::merge_patch::MergePatch;
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::generation;
+use super::models::{
+    CreatePlantRequest, ExportPlantsParams, ImportPlantsResponse,
+    NearPlantsParams, NearbyPlantResponse, NearbyPlantsResponse,
+    PatchPlantRequest, PlantAggregateParams, PlantAggregateResponse,
+    PlantDeletedEvent, PlantFileFormat, PlantListResponse, PlantResponse,
+    PlantStatus, PlantSummaryResponse, UpdatePlantRequest,
+};
+use super::summary;
+use super::transfer::{self, Columns};
+
+const HANDLER_NAME: &str = "plants";
+/// Change events queued for a slow SSE client.
+const CHANGE_BUFFER: usize = 16;
+/// Largest number of data rows accepted by an import.
+pub const MAX_IMPORT_ROWS: usize = 10_000;
+
+/// A plant with its `ETag`.
+type PlantWithEtag = (
+    StatusCode,
+    [(HeaderName, HeaderValue); 1],
+    Json<PlantResponse>,
+);
+
+fn with_etag(status: StatusCode, plant: Plant) -> PlantWithEtag {
+    (
+        status,
+        conditional::etag_header(plant.version),
+        Json(PlantResponse::from(plant)),
+    )
+}
+
+fn capacity(
+    recorder: &ErrorRecorder<'_>,
+    capacity_mw: f64,
+) -> HandlerResult<BigDecimal> {
+    BigDecimal::from_str(&format!("{capacity_mw:.3}")).map_err(|_| {
+        recorder.record(
+            "invalid_capacity",
+            errors::Error::InvalidCapacity(capacity_mw),
+        )
+    })
+}
+
+fn new_plant(
+    recorder: &ErrorRecorder<'_>,
+    tenant_id: String,
+    payload: CreatePlantRequest,
+) -> HandlerResult<NewPlant> {
+    Ok(NewPlant {
+        tenant_id,
+        name: payload.name,
+        energy_type: payload.energy_type.as_str().to_string(),
+        capacity_mw: capacity(recorder, payload.capacity_mw)?,
+        address: payload.address,
+        latitude: payload.latitude,
+        longitude: payload.longitude,
+        status: payload
+            .status
+            .unwrap_or(PlantStatus::Active)
+            .as_str()
+            .to_string(),
+    })
+}
+
+/// Register a plant
+#[utoipa::path(
+    post,
+    path = "/plants",
+    request_body = CreatePlantRequest,
+    responses(
+        (status = 201, description = "Plant registered", body = PlantResponse, headers(
+            ("ETag" = String, description = "Quoted plant version"),
+        )),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreatePlantRequest>,
+) -> HandlerResult<PlantWithEtag> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let new_plant =
+        new_plant(&recorder, ctx.tenant.tenant_id.clone(), payload)?;
+
+    let plant = with_connection(&ctx.state.pool, |mut conn| async move {
+        Plant::create(new_plant, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+    ctx.state
+        .plant_events
+        .publish(PlantEvent::Created(plant.clone()));
+
+    Ok(with_etag(StatusCode::CREATED, plant))
+}
+
+/// List plants
+///
+/// Returns the caller's tenant's plants ordered by name.
+#[utoipa::path(
+    get,
+    path = "/plants",
+    responses(
+        (status = 200, description = "Plants", body = PlantListResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<PlantListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let plants = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Plant::list(tenant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let plants = plants.into_iter().map(PlantResponse::from).collect();
+
+    Ok((StatusCode::OK, Json(PlantListResponse { plants })))
+}
+
+/// Summarize plant capacity
+///
+/// Returns the number, total and average capacity of the caller's tenant's
+/// plants overall, per energy type, per status and per energy type and
+/// status.
+#[utoipa::path(
+    get,
+    path = "/plants/summary",
+    responses(
+        (status = 200, description = "Capacity summary", body = PlantSummaryResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_summary")]
+pub async fn summary(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<PlantSummaryResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let groups = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Plant::capacity_groups(tenant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    Ok((StatusCode::OK, Json(summary::response(groups))))
+}
+
+/// Find plants near a point
+///
+/// Returns the caller's tenant's plants within `radius_km` of `lat`/`lon`,
+/// nearest first. Plants without coordinates are never found.
+#[utoipa::path(
+    get,
+    path = "/plants/near",
+    params(NearPlantsParams),
+    responses(
+        (status = 200, description = "Plants within the radius", body = NearbyPlantsResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_near")]
+pub async fn near(
+    ctx: HandlerCtx,
+    params: Result<Query<NearPlantsParams>, QueryRejection>,
+) -> HandlerResult<(StatusCode, Json<NearbyPlantsResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let invalid_query = |message: String| {
+        recorder.record(
+            "invalid_query",
+            errors::Error::InvalidQuery {
+                message,
+                suggestion: "Send `lat` and `lon` in degrees and `radius_km` \
+                             in km",
+            },
+        )
+    };
+    let Query(params) = params.map_err(|e| invalid_query(e.body_text()))?;
+    params
+        .validate()
+        .map_err(|e| invalid_query(e.to_string()))?;
+
+    let plants = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Plant::near(
+                tenant_id,
+                params.lat,
+                params.lon,
+                params.radius_km,
+                &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let plants = plants
+        .into_iter()
+        .map(|nearby| NearbyPlantResponse {
+            plant: PlantResponse::from(nearby.plant),
+            distance_km: (nearby.distance_km * 1000.0).round() / 1000.0,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(NearbyPlantsResponse { plants })))
+}
+
+/// The SSE event of a plant change.
+fn change_event(event: &PlantEvent) -> Result<Event, axum::Error> {
+    let sse = Event::default().event(event.as_str());
+    match event {
+        PlantEvent::Created(plant) | PlantEvent::Updated(plant) => {
+            sse.json_data(PlantResponse::from(plant.clone()))
+        }
+        PlantEvent::Deleted { id, .. } => {
+            sse.json_data(PlantDeletedEvent { id: *id })
+        }
+    }
+}
+
+/// Follow plant changes
+///
+/// A server-sent event stream of the caller's tenant's plant changes made
+/// after it was opened: `plant_created` and `plant_updated` carry the
+/// plant, `plant_deleted` its id. A `resync` event with the number of
+/// `missed` changes is sent when the client falls behind; list the plants
+/// again then. Each API instance streams the changes made through it.
+#[utoipa::path(
+    get,
+    path = "/plants/changes",
+    responses(
+        (status = 200, description = "Server-sent events of plant changes", content(
+            (String = "text/event-stream"),
+        )),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_changes")]
+pub async fn changes(
+    ctx: HandlerCtx,
+) -> Sse<KeepAliveStream<ReceiverStream<Result<Event, Infallible>>>> {
+    let HandlerCtx { state, tenant, .. } = ctx;
+    let mut events = state.plant_events.subscribe();
+    let (tx, rx) = mpsc::channel(CHANGE_BUFFER);
+    tokio::spawn(async move {
+        while !state.shutdown.is_shutting_down() {
+            let event = tokio::select! {
+                _ = state.shutdown.wait_for_shutdown() => return,
+                // The client went away
+                _ = tx.closed() => return,
+                event = events.recv() => event,
+            };
+            let sse = match event {
+                Ok(event) if event.tenant_id() == tenant.tenant_id => {
+                    match change_event(&event) {
+                        Ok(sse) => sse,
+                        Err(e) => {
+                            tracing::warn!("Failed to encode plant event: {e}");
+                            continue;
+                        }
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => Event::default()
+                    .event("resync")
+                    .data(format!("{{\"missed\":{missed}}}")),
+                Err(RecvError::Closed) => return,
+            };
+            if tx.send(Ok(sse)).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default())
+}
+
+/// Get a plant by id
+///
+/// The `ETag` is the quoted plant version. With `If-None-Match` naming it,
+/// `304` is returned without a body.
+#[utoipa::path(
+    get,
+    path = "/plants/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Plant id"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the client's copy, e.g. `\"3\"`"),
+    ),
+    responses(
+        (status = 200, description = "Plant", body = PlantResponse, headers(
+            ("ETag" = String, description = "Quoted plant version"),
+        )),
+        (status = 304, description = "The client's copy is current"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> HandlerResult<Response> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let plant = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Plant::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    if conditional::none_match(&headers, plant.version) {
+        return Ok(conditional::not_modified(plant.version));
+    }
+    Ok(with_etag(StatusCode::OK, plant).into_response())
+}
+
+/// The plant version a change is based on, from `If-Match: "<version>"`.
+fn if_match(
+    recorder: &ErrorRecorder<'_>,
+    headers: &HeaderMap,
+) -> HandlerResult<Option<i32>> {
+    conditional::if_match(headers).map_err(|value| {
+        recorder
+            .record("invalid_if_match", errors::Error::InvalidIfMatch(value))
+    })
+}
+
+/// Like [`if_match`], failing when the header is missing.
+fn expected_version(
+    recorder: &ErrorRecorder<'_>,
+    headers: &HeaderMap,
+) -> HandlerResult<i32> {
+    if_match(recorder, headers)?.ok_or_else(|| {
+        recorder.record("missing_if_match", errors::Error::MissingIfMatch)
+    })
+}
+
+/// Apply `changes` to a plant if it is still at `expected_version`.
+async fn apply_update(
+    state: &AppState,
+    recorder: &ErrorRecorder<'_>,
+    tenant_id: &str,
+    id: Uuid,
+    expected_version: i32,
+    changes: UpdatePlant,
+) -> HandlerResult<Plant> {
+    let unchanged = changes.name.is_none()
+        && changes.energy_type.is_none()
+        && changes.capacity_mw.is_none()
+        && changes.address.is_none()
+        && changes.status.is_none()
+        && changes.latitude.is_none()
+        && changes.longitude.is_none();
+
+    let (plant, updated) =
+        with_connection(&state.pool, |mut conn| async move {
+            // An empty UPDATE is an error in Diesel
+            if !unchanged
+                && let Some(plant) = Plant::update(
+                    tenant_id,
+                    id,
+                    expected_version,
+                    changes,
+                    &mut conn,
+                )
+                .await?
+            {
+                return Ok((Some(plant), true));
+            }
+            // Missing, updated since, or nothing to change
+            Ok((Plant::find(tenant_id, id, &mut conn).await?, false))
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let plant = plant.ok_or_else(|| {
+        recorder.record("not_found", errors::Error::NotFound(id))
+    })?;
+    if updated {
+        state
+            .plant_events
+            .publish(PlantEvent::Updated(plant.clone()));
+    } else if plant.version != expected_version {
+        return Err(recorder.record(
+            "version_mismatch",
+            errors::Error::VersionMismatch {
+                expected: expected_version,
+                current: plant.version,
+            },
+        ));
+    }
+    Ok(plant)
+}
+
+/// Update a plant
+///
+/// `If-Match` must carry the `version` of the plant the changes are based
+/// on. When the plant was updated since, nothing is changed and `409` is
+/// returned, so concurrent edits do not overwrite each other.
+#[utoipa::path(
+    put,
+    path = "/plants/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Plant id"),
+        ("If-Match" = String, Header, description = "Quoted plant version the update is based on, e.g. `\"3\"`"),
+    ),
+    request_body = UpdatePlantRequest,
+    responses(
+        (status = 200, description = "Updated plant", body = PlantResponse, headers(
+            ("ETag" = String, description = "Quoted plant version"),
+        )),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 404, description = "Plant not found"),
+        (status = 403, description = "Admin role required"),
+        (status = 409, description = "The plant was updated since the given version"),
+        (status = 428, description = "Missing If-Match header"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_update")]
+pub async fn update(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedPayload(payload): ValidatedPayload<UpdatePlantRequest>,
+) -> HandlerResult<PlantWithEtag> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let expected_version = expected_version(&recorder, &headers)?;
+
+    let changes = UpdatePlant {
+        name: payload.name,
+        energy_type: payload.energy_type.map(|t| t.as_str().to_string()),
+        capacity_mw: payload
+            .capacity_mw
+            .map(|mw| capacity(&recorder, mw))
+            .transpose()?,
+        address: payload.address.map(Some),
+        status: payload.status.map(|s| s.as_str().to_string()),
+        latitude: payload.latitude.map(Some),
+        longitude: payload.longitude.map(Some),
+    };
+    let plant = apply_update(
+        &ctx.state,
+        &recorder,
+        tenant_id,
+        id,
+        expected_version,
+        changes,
+    )
+    .await?;
+
+    Ok(with_etag(StatusCode::OK, plant))
+}
+
+/// Patch a plant
+///
+/// Applies a JSON Merge Patch (`application/merge-patch+json`): members
+/// left out are unchanged and `null` removes the address or the
+/// coordinates. Like `PUT`, `If-Match` must carry the plant's `version`.
+#[utoipa::path(
+    patch,
+    path = "/plants/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Plant id"),
+        ("If-Match" = String, Header, description = "Quoted plant version the patch is based on, e.g. `\"3\"`"),
+    ),
+    request_body(content = PatchPlantRequest, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Patched plant", body = PlantResponse, headers(
+            ("ETag" = String, description = "Quoted plant version"),
+        )),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 404, description = "Plant not found"),
+        (status = 403, description = "Admin role required"),
+        (status = 409, description = "The plant was updated since the given version"),
+        (status = 415, description = "Content-Type is not application/merge-patch+json"),
+        (status = 428, description = "Missing If-Match header"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_patch")]
+pub async fn patch(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    MergePatch(payload): MergePatch<PatchPlantRequest>,
+) -> HandlerResult<PlantWithEtag> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let expected_version = expected_version(&recorder, &headers)?;
+
+    let changes = UpdatePlant {
+        name: payload.name,
+        energy_type: payload.energy_type.map(|t| t.as_str().to_string()),
+        capacity_mw: payload
+            .capacity_mw
+            .map(|mw| capacity(&recorder, mw))
+            .transpose()?,
+        address: payload.address,
+        status: payload.status.map(|s| s.as_str().to_string()),
+        latitude: payload.latitude,
+        longitude: payload.longitude,
+    };
+    let plant = apply_update(
+        &ctx.state,
+        &recorder,
+        tenant_id,
+        id,
+        expected_version,
+        changes,
+    )
+    .await?;
+
+    Ok(with_etag(StatusCode::OK, plant))
+}
+
+/// Delete a plant
+///
+/// With `If-Match`, the plant is only deleted while it is still at that
+/// version, and `409` is returned otherwise. The header is required when
+/// the `plant_delete_if_match` feature flag is on.
+#[utoipa::path(
+    delete,
+    path = "/plants/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Plant id"),
+        ("If-Match" = Option<String>, Header, description = "Quoted plant version the deletion is based on, e.g. `\"3\"`"),
+    ),
+    responses(
+        (status = 204, description = "Plant deleted"),
+        (status = 400, description = "Invalid If-Match header"),
+        (status = 404, description = "Plant not found"),
+        (status = 403, description = "Admin role required"),
+        (status = 409, description = "The plant was updated since the given version"),
+        (status = 428, description = "Missing If-Match header"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_delete")]
+pub async fn delete(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let expected_version = if_match(&recorder, &headers)?;
+    if expected_version.is_none()
+        && ctx.state.flag_enabled(Flag::PlantDeleteIfMatch).await
+    {
+        return Err(
+            recorder.record("missing_if_match", errors::Error::MissingIfMatch)
+        );
+    }
+
+    let tenant_id = &ctx.tenant.tenant_id;
+    let (deleted, current_version) =
+        with_connection(&ctx.state.pool, |mut conn| async move {
+            let deleted =
+                Plant::delete(tenant_id, id, expected_version, &mut conn)
+                    .await?;
+            if deleted > 0 || expected_version.is_none() {
+                return Ok((deleted, None));
+            }
+            // Missing or updated since
+            let plant = Plant::find(tenant_id, id, &mut conn).await?;
+            Ok((deleted, plant.map(|plant| plant.version)))
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    if deleted == 0 {
+        return Err(match (expected_version, current_version) {
+            (Some(expected), Some(current)) => recorder.record(
+                "version_mismatch",
+                errors::Error::VersionMismatch { expected, current },
+            ),
+            _ => recorder.record("not_found", errors::Error::NotFound(id)),
+        });
+    }
+    ctx.state.plant_events.publish(PlantEvent::Deleted {
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        id,
+    });
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Import plants from CSV or Excel
+///
+/// The body is a CSV file (`text/csv`) or an Excel workbook, whose first
+/// worksheet is read. The header row names the columns `name`,
+/// `energy_type`, `capacity_mw` and optionally `address`, `latitude`,
+/// `longitude` and `status`, in any order and case. Valid rows are stored
+/// and invalid ones are reported with their row number; blank rows are
+/// skipped.
+#[utoipa::path(
+    post,
+    path = "/plants/import",
+    request_body(content(
+        (String = "text/csv"),
+        (Vec<u8> = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    )),
+    responses(
+        (status = 200, description = "Import outcome", body = ImportPlantsResponse),
+        (status = 400, description = "Unreadable file or missing columns"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_import")]
+pub async fn import(
+    ctx: HandlerCtx,
+    headers: HeaderMap,
+    body: Bytes,
+) -> HandlerResult<(StatusCode, Json<ImportPlantsResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let format =
+        PlantFileFormat::from_content_type(content_type).ok_or_else(|| {
+            recorder.record(
+                "unsupported_content_type",
+                errors::Error::UnsupportedContentType(content_type.to_string()),
+            )
+        })?;
+    let invalid_file = |e: String| {
+        recorder.record("invalid_file", errors::Error::InvalidFile(e))
+    };
+
+    let table = transfer::read(format, body.to_vec()).map_err(invalid_file)?;
+    let columns = Columns::find(&table).map_err(|missing| {
+        invalid_file(format!("Missing column `{missing}`"))
+    })?;
+    if table.rows.len() > MAX_IMPORT_ROWS {
+        return Err(recorder.record(
+            "too_many_rows",
+            errors::Error::TooManyRows(table.rows.len()),
+        ));
+    }
+
+    let mut plants = Vec::new();
+    let mut errors = Vec::new();
+    let mut failed = 0;
+    for (index, cells) in table.rows.iter().enumerate() {
+        if cells.iter().all(|cell| cell.as_text().is_none()) {
+            continue;
+        }
+        // The header is row 1
+        match columns.parse(index + 2, cells) {
+            Ok(payload) => plants.push(new_plant(
+                &recorder,
+                ctx.tenant.tenant_id.clone(),
+                payload,
+            )?),
+            Err(row_errors) => {
+                failed += 1;
+                errors.extend(row_errors);
+            }
+        }
+    }
+
+    let created = if plants.is_empty() {
+        Vec::new()
+    } else {
+        with_connection(&ctx.state.pool, |mut conn| async move {
+            Plant::create_many(plants, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+    };
+    let imported = created.len();
+    for plant in created {
+        ctx.state.plant_events.publish(PlantEvent::Created(plant));
+    }
+    tracing::info!(imported, failed, "Imported plants");
+
+    Ok((
+        StatusCode::OK,
+        Json(ImportPlantsResponse {
+            imported,
+            failed,
+            errors,
+        }),
+    ))
+}
+
+/// Export plants as CSV or Excel
+///
+/// Returns the caller's tenant's plants in the import format, so an export
+/// can be edited and imported elsewhere.
+#[utoipa::path(
+    get,
+    path = "/plants/export",
+    params(ExportPlantsParams),
+    responses(
+        (status = 200, description = "Plants file", content(
+            (String = "text/csv"),
+            (Vec<u8> = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+        )),
+        (status = 400, description = "Invalid format"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_export")]
+pub async fn export(
+    ctx: HandlerCtx,
+    params: Result<Query<ExportPlantsParams>, QueryRejection>,
+) -> HandlerResult<Response> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let Query(params) = params.map_err(|e| {
+        recorder.record(
+            "invalid_query",
+            errors::Error::InvalidQuery {
+                message: e.body_text(),
+                suggestion: "Use `format=csv` or `format=xlsx`",
+            },
+        )
+    })?;
+
+    let plants = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Plant::list(tenant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let file = transfer::write(params.format, &transfer::to_table(plants))
+        .map_err(|e| {
+            recorder.record("export_failed", errors::Error::ExportFailed(e))
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                params.format.content_type().to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"plants.{}\"",
+                    params.format.extension()
+                ),
+            ),
+        ],
+        file,
+    )
+        .into_response())
+}
+
+/// Aggregate a plant's energy readings
+///
+/// Sums the readings attributed to the plant by hour, day or month, like
+/// `POST /energy/aggregate`, and relates each period's generation to the
+/// plant's nameplate capacity: the capacity factor is the energy generated
+/// over `capacityMw × hours` of the period. The query is recorded in the
+/// history.
+#[utoipa::path(
+    get,
+    path = "/plants/{id}/energy/aggregate",
+    params(
+        ("id" = Uuid, Path, description = "Plant id"),
+        PlantAggregateParams,
+    ),
+    responses(
+        (status = 200, description = "Aggregated energy of the plant", body = PlantAggregateResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "plants",
+)]
+#[tracing::instrument(skip_all, name = "plants_aggregate")]
+pub async fn aggregate(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    params: Result<Query<PlantAggregateParams>, QueryRejection>,
+) -> HandlerResult<(StatusCode, Json<PlantAggregateResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder.record(
+            "invalid_query",
+            errors::Error::InvalidQuery {
+                message: e.body_text(),
+                suggestion: "Send `aggregationType` as hourly, day_of_month \
+                             or monthly and dates in RFC 3339",
+            },
+        )
+    })?;
+    let tenant_id = &ctx.tenant.tenant_id;
+
+    let plant = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Plant::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?
+        .ok_or_else(|| {
+            recorder.record("not_found", errors::Error::NotFound(id))
+        })?;
+
+    let new_entry = NewQueryHistory {
+        aggregation_type: params.aggregation_type.to_string(),
+        date_from: params.date_from,
+        date_to: params.date_to,
+        api_key_id: ctx.caller.as_ref().map(|c| c.api_key_id),
+        tenant_id: tenant_id.clone(),
+        created_at: None,
+    };
+    ctx.state
+        .record_query(new_entry)
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let trunc_level = params.aggregation_type.to_trunc_level();
+    let date_from = params.date_from;
+    let date_to = params.date_to;
+    let rows = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            EnergyReading::aggregate(
+                tenant_id,
+                Some(id),
+                trunc_level,
+                date_from,
+                date_to,
+                AggregateOptions::default(),
+                &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let capacity_mw = plant.capacity_mw.to_f64().unwrap_or_default();
+    Ok((
+        StatusCode::OK,
+        Json(generation::response(plant.id, capacity_mw, params, rows)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::wire_api::testing::TestApp;
+
+    #[tokio::test]
+    async fn test_create_then_get_plant() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+
+        let created = app
+            .server
+            .post("/api/wire/v1/plants")
+            .json(&json!({
+                "name": "North field",
+                "energyType": "solar",
+                "capacityMw": 12.5,
+            }))
+            .await;
+        created.assert_status(StatusCode::CREATED);
+        let id = created.json::<serde_json::Value>()["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let fetched =
+            app.server.get(&format!("/api/wire/v1/plants/{id}")).await;
+        fetched.assert_status_ok();
+        let plant = fetched.json::<serde_json::Value>();
+        assert_eq!(plant["name"], "North field");
+        assert_eq!(plant["capacityMw"], 12.5);
+
+        let etag = fetched.header(header::ETAG);
+        app.server
+            .get(&format!("/api/wire/v1/plants/{id}"))
+            .add_header(header::IF_NONE_MATCH, etag)
+            .await
+            .assert_status(StatusCode::NOT_MODIFIED);
+
+        let other = app.seed_plant("other", "Elsewhere", "3").await;
+        app.server
+            .get(&format!("/api/wire/v1/plants/{}", other.id))
+            .await
+            .assert_status_not_found();
+    }
+}
@@ -0,0 +1,73 @@
+use axum::Router;
+use axum::middleware::{from_extractor, from_fn_with_state};
+use axum::routing::get;
+use utoipa::OpenApi;
+
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+use crate::shared::response_cache::{self, CacheGroup};
+
+mod errors;
+mod generation;
+pub mod handler;
+pub mod models;
+mod openapi;
+mod summary;
+mod transfer;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    let manage = Router::new()
+        .route("/", axum::routing::post(handler::create))
+        .route("/import", axum::routing::post(handler::import))
+        .route(
+            "/{id}",
+            axum::routing::put(handler::update)
+                .patch(handler::patch)
+                .delete(handler::delete),
+        )
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>());
+
+    Router::new()
+        .route(
+            "/",
+            get(handler::list).layer(from_fn_with_state(
+                (state.clone(), CacheGroup::Plants),
+                response_cache::cache_response,
+            )),
+        )
+        .route("/export", get(handler::export))
+        .route("/changes", get(handler::changes))
+        .route("/near", get(handler::near))
+        .route("/summary", get(handler::summary))
+        .route("/{id}", get(handler::get))
+        .route("/{id}/energy/aggregate", get(handler::aggregate))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .merge(manage)
+        .with_state(state.clone())
+        .layer(from_fn_with_state(
+            (state, CacheGroup::Plants),
+            response_cache::invalidate,
+        ))
+}
+
+/// Generation and storage sites of the tenant.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "plants"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Plants)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/plants", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
@@ -0,0 +1,667 @@
+use bigdecimal::ToPrimitive;
+use postgres_models::models::plants::{Plant, energy_type, plant_status};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::shared::extractors::merge_patch;
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+/// Largest accepted nameplate capacity, above any single plant in operation.
+pub const MAX_CAPACITY_MW: f64 = 50_000.0;
+/// Largest search radius, about half the Earth's circumference.
+pub const MAX_RADIUS_KM: f64 = 20_000.0;
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("blank")
+            .with_message("Plant names cannot be blank".into()));
+    }
+    Ok(())
+}
+
+fn validate_coordinates(
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> Result<(), ValidationError> {
+    if latitude.is_some() != longitude.is_some() {
+        return Err(ValidationError::new("coordinates")
+            .with_message("latitude and longitude are set together".into()));
+    }
+    Ok(())
+}
+
+fn validate_create(
+    request: &CreatePlantRequest,
+) -> Result<(), ValidationError> {
+    validate_coordinates(request.latitude, request.longitude)
+}
+
+fn validate_update(
+    request: &UpdatePlantRequest,
+) -> Result<(), ValidationError> {
+    validate_coordinates(request.latitude, request.longitude)
+}
+
+fn validate_patch(request: &PatchPlantRequest) -> Result<(), ValidationError> {
+    // Both patched, and both set or both removed
+    let set = |coordinate: Option<Option<f64>>| coordinate.map(|c| c.is_some());
+    if set(request.latitude) != set(request.longitude) {
+        return Err(ValidationError::new("coordinates").with_message(
+            "latitude and longitude are set or removed together".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Primary energy source of a plant
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EnergyType {
+    Solar,
+    Wind,
+    Hydro,
+    Battery,
+    Other,
+}
+
+impl EnergyType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EnergyType::Solar => energy_type::SOLAR,
+            EnergyType::Wind => energy_type::WIND,
+            EnergyType::Hydro => energy_type::HYDRO,
+            EnergyType::Battery => energy_type::BATTERY,
+            EnergyType::Other => energy_type::OTHER,
+        }
+    }
+}
+
+impl std::str::FromStr for EnergyType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            EnergyType::Solar,
+            EnergyType::Wind,
+            EnergyType::Hydro,
+            EnergyType::Battery,
+            EnergyType::Other,
+        ]
+        .into_iter()
+        .find(|t| t.as_str().eq_ignore_ascii_case(s.trim()))
+        .ok_or(())
+    }
+}
+
+/// Operating status of a plant
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PlantStatus {
+    Active,
+    Inactive,
+    Maintenance,
+    Decommissioned,
+}
+
+impl PlantStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PlantStatus::Active => plant_status::ACTIVE,
+            PlantStatus::Inactive => plant_status::INACTIVE,
+            PlantStatus::Maintenance => plant_status::MAINTENANCE,
+            PlantStatus::Decommissioned => plant_status::DECOMMISSIONED,
+        }
+    }
+}
+
+impl std::str::FromStr for PlantStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            PlantStatus::Active,
+            PlantStatus::Inactive,
+            PlantStatus::Maintenance,
+            PlantStatus::Decommissioned,
+        ]
+        .into_iter()
+        .find(|status| status.as_str().eq_ignore_ascii_case(s.trim()))
+        .ok_or(())
+    }
+}
+
+/// Request payload for registering a plant
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_create"))]
+pub struct CreatePlantRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 200,
+            message = "Plant names are 1-200 characters"
+        ),
+        custom(function = "validate_name")
+    )]
+    #[schema(example = "Sofia Solar Park")]
+    pub name: String,
+
+    pub energy_type: EnergyType,
+
+    /// Nameplate capacity in MW, stored with 3 decimal places
+    #[validate(range(
+        exclusive_min = 0.0,
+        max = MAX_CAPACITY_MW,
+        message = "Capacity must be above 0 and at most 50000 MW"
+    ))]
+    #[schema(example = 12.5)]
+    pub capacity_mw: f64,
+
+    /// Postal address
+    #[validate(length(
+        max = 500,
+        message = "Addresses are at most 500 characters"
+    ))]
+    #[schema(example = "1 Tsarigradsko Shose, Sofia, Bulgaria")]
+    pub address: Option<String>,
+
+    /// WGS 84 latitude in degrees, sent together with `longitude`
+    #[validate(range(
+        min = -90.0,
+        max = 90.0,
+        message = "Latitude must be between -90 and 90"
+    ))]
+    #[schema(example = 42.6977)]
+    pub latitude: Option<f64>,
+
+    /// WGS 84 longitude in degrees, sent together with `latitude`
+    #[validate(range(
+        min = -180.0,
+        max = 180.0,
+        message = "Longitude must be between -180 and 180"
+    ))]
+    #[schema(example = 23.3219)]
+    pub longitude: Option<f64>,
+
+    /// Operating status (defaults to active)
+    pub status: Option<PlantStatus>,
+}
+
+/// Request payload for updating a plant; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_update"))]
+pub struct UpdatePlantRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 200,
+            message = "Plant names are 1-200 characters"
+        ),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+
+    pub energy_type: Option<EnergyType>,
+
+    #[validate(range(
+        exclusive_min = 0.0,
+        max = MAX_CAPACITY_MW,
+        message = "Capacity must be above 0 and at most 50000 MW"
+    ))]
+    pub capacity_mw: Option<f64>,
+
+    #[validate(length(
+        max = 500,
+        message = "Addresses are at most 500 characters"
+    ))]
+    pub address: Option<String>,
+
+    #[validate(range(
+        min = -90.0,
+        max = 90.0,
+        message = "Latitude must be between -90 and 90"
+    ))]
+    pub latitude: Option<f64>,
+
+    #[validate(range(
+        min = -180.0,
+        max = 180.0,
+        message = "Longitude must be between -180 and 180"
+    ))]
+    pub longitude: Option<f64>,
+
+    pub status: Option<PlantStatus>,
+}
+
+/// JSON Merge Patch of a plant: absent members are unchanged and `null`
+/// removes the address or the coordinates
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_patch"))]
+pub struct PatchPlantRequest {
+    #[serde(default, deserialize_with = "merge_patch::required")]
+    #[validate(
+        length(
+            min = 1,
+            max = 200,
+            message = "Plant names are 1-200 characters"
+        ),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+
+    #[serde(default, deserialize_with = "merge_patch::required")]
+    pub energy_type: Option<EnergyType>,
+
+    #[serde(default, deserialize_with = "merge_patch::required")]
+    #[validate(range(
+        exclusive_min = 0.0,
+        max = MAX_CAPACITY_MW,
+        message = "Capacity must be above 0 and at most 50000 MW"
+    ))]
+    pub capacity_mw: Option<f64>,
+
+    #[serde(default, deserialize_with = "merge_patch::nullable")]
+    #[validate(length(
+        max = 500,
+        message = "Addresses are at most 500 characters"
+    ))]
+    #[schema(value_type = Option<String>)]
+    pub address: Option<Option<String>>,
+
+    #[serde(default, deserialize_with = "merge_patch::nullable")]
+    #[validate(range(
+        min = -90.0,
+        max = 90.0,
+        message = "Latitude must be between -90 and 90"
+    ))]
+    #[schema(value_type = Option<f64>)]
+    pub latitude: Option<Option<f64>>,
+
+    #[serde(default, deserialize_with = "merge_patch::nullable")]
+    #[validate(range(
+        min = -180.0,
+        max = 180.0,
+        message = "Longitude must be between -180 and 180"
+    ))]
+    #[schema(value_type = Option<f64>)]
+    pub longitude: Option<Option<f64>>,
+
+    #[serde(default, deserialize_with = "merge_patch::required")]
+    pub status: Option<PlantStatus>,
+}
+
+/// A registered plant
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantResponse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    #[schema(example = "solar")]
+    pub energy_type: String,
+    #[schema(example = 12.5)]
+    pub capacity_mw: f64,
+    pub address: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    #[schema(example = "active")]
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Send as `If-Match` to update the plant
+    #[schema(example = 1)]
+    pub version: i32,
+}
+
+impl From<Plant> for PlantResponse {
+    fn from(plant: Plant) -> Self {
+        Self {
+            id: plant.id,
+            name: plant.name,
+            energy_type: plant.energy_type,
+            capacity_mw: plant.capacity_mw.to_f64().unwrap_or_default(),
+            address: plant.address,
+            latitude: plant.latitude,
+            longitude: plant.longitude,
+            status: plant.status,
+            created_at: plant.created_at,
+            updated_at: plant.updated_at,
+            version: plant.version,
+        }
+    }
+}
+
+/// Response containing a tenant's plants
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantListResponse {
+    pub plants: Vec<PlantResponse>,
+}
+
+/// Data of a `plant_deleted` change event
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantDeletedEvent {
+    pub id: uuid::Uuid,
+}
+
+/// Query parameters of a plant radius search
+#[derive(Debug, Deserialize, Validate, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NearPlantsParams {
+    /// Latitude of the center in degrees
+    #[validate(range(
+        min = -90.0,
+        max = 90.0,
+        message = "Latitude must be between -90 and 90"
+    ))]
+    #[param(example = 42.6977)]
+    pub lat: f64,
+
+    /// Longitude of the center in degrees
+    #[validate(range(
+        min = -180.0,
+        max = 180.0,
+        message = "Longitude must be between -180 and 180"
+    ))]
+    #[param(example = 23.3219)]
+    pub lon: f64,
+
+    /// Search radius in km
+    #[validate(range(
+        exclusive_min = 0.0,
+        max = MAX_RADIUS_KM,
+        message = "The radius must be above 0 and at most 20000 km"
+    ))]
+    #[param(example = 50.0)]
+    pub radius_km: f64,
+}
+
+/// A plant found by a radius search
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NearbyPlantResponse {
+    #[serde(flatten)]
+    pub plant: PlantResponse,
+
+    /// Great-circle distance from the center in km
+    #[schema(example = 12.34)]
+    pub distance_km: f64,
+}
+
+/// Plants within a radius, nearest first
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NearbyPlantsResponse {
+    pub plants: Vec<NearbyPlantResponse>,
+}
+
+/// File format of plant imports and exports
+#[derive(
+    Debug, Clone, Copy, Default, Deserialize, Serialize, ToSchema, PartialEq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PlantFileFormat {
+    /// Comma separated values with a header row
+    #[default]
+    Csv,
+    /// Excel workbook; the first worksheet is read
+    Xlsx,
+}
+
+impl PlantFileFormat {
+    pub const CSV_CONTENT_TYPE: &str = "text/csv";
+    pub const XLSX_CONTENT_TYPE: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            PlantFileFormat::Csv => Self::CSV_CONTENT_TYPE,
+            PlantFileFormat::Xlsx => Self::XLSX_CONTENT_TYPE,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PlantFileFormat::Csv => "csv",
+            PlantFileFormat::Xlsx => "xlsx",
+        }
+    }
+
+    /// The format of a request body, from its `Content-Type`.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim();
+        [PlantFileFormat::Csv, PlantFileFormat::Xlsx]
+            .into_iter()
+            .find(|format| format.content_type().eq_ignore_ascii_case(mime))
+    }
+}
+
+/// Query parameters of a plant export
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportPlantsParams {
+    /// File format (defaults to csv)
+    #[serde(default)]
+    pub format: PlantFileFormat,
+}
+
+/// A problem with one row of an import
+#[derive(Debug, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRowError {
+    /// Row number in the file, counting the header row as 1
+    #[schema(example = 3)]
+    pub row: usize,
+    #[schema(example = "capacity_mw")]
+    pub field: String,
+    pub message: String,
+}
+
+/// Response after importing plants
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPlantsResponse {
+    /// Number of plants stored
+    pub imported: usize,
+    /// Number of rows skipped because of errors
+    pub failed: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// The number and capacity of a set of plants
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CapacityTotals {
+    #[schema(example = 4)]
+    pub plant_count: i64,
+    #[schema(example = 50.0)]
+    pub total_capacity_mw: f64,
+    /// `null` when there are no plants
+    #[schema(example = 12.5)]
+    pub average_capacity_mw: Option<f64>,
+}
+
+/// Capacity of the plants of an energy type
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnergyTypeCapacity {
+    #[schema(example = "solar")]
+    pub energy_type: String,
+    #[serde(flatten)]
+    pub totals: CapacityTotals,
+}
+
+/// Capacity of the plants in a status
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusCapacity {
+    #[schema(example = "active")]
+    pub status: String,
+    #[serde(flatten)]
+    pub totals: CapacityTotals,
+}
+
+/// Capacity of the plants of an energy type in a status
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CapacityGroup {
+    #[schema(example = "solar")]
+    pub energy_type: String,
+    #[schema(example = "active")]
+    pub status: String,
+    #[serde(flatten)]
+    pub totals: CapacityTotals,
+}
+
+/// Capacity summary of a tenant's plants
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantSummaryResponse {
+    #[serde(flatten)]
+    pub totals: CapacityTotals,
+    pub by_energy_type: Vec<EnergyTypeCapacity>,
+    pub by_status: Vec<StatusCapacity>,
+    /// Every energy type and status combination with plants
+    pub groups: Vec<CapacityGroup>,
+}
+
+/// Query parameters of a plant's energy aggregation
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query, rename_all = "camelCase")]
+pub struct PlantAggregateParams {
+    /// Aggregation granularity
+    pub aggregation_type: AggregationType,
+    /// Start of date range (inclusive, optional)
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    /// End of date range (exclusive, optional)
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Energy of a plant in one aggregation period
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantAggregateDataPoint {
+    /// Start of the aggregation period
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub period: chrono::DateTime<chrono::Utc>,
+
+    /// Total energy in kWh for this period
+    #[schema(example = "2150.5000")]
+    pub total_kwh: String,
+
+    /// Generation over what the nameplate capacity yields in the period
+    #[schema(example = 0.2312)]
+    pub capacity_factor: Option<f64>,
+}
+
+/// Response for a plant's energy aggregation
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantAggregateResponse {
+    pub plant_id: uuid::Uuid,
+    #[schema(example = 12.5)]
+    pub capacity_mw: f64,
+    pub aggregation_type: AggregationType,
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Total energy in kWh over all periods
+    #[schema(example = "25806.0000")]
+    pub total_kwh: String,
+
+    /// Capacity factor over the whole date range, `null` without readings
+    #[schema(example = 0.2312)]
+    pub capacity_factor: Option<f64>,
+
+    pub data: Vec<PlantAggregateDataPoint>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(name: &str, capacity_mw: f64) -> CreatePlantRequest {
+        CreatePlantRequest {
+            name: name.to_string(),
+            energy_type: EnergyType::Solar,
+            capacity_mw,
+            address: None,
+            latitude: None,
+            longitude: None,
+            status: None,
+        }
+    }
+
+    fn invalid_fields(request: impl Validate) -> Vec<String> {
+        let mut fields = request
+            .validate()
+            .unwrap_err()
+            .field_errors()
+            .keys()
+            .map(|field| field.to_string())
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_create_validation() {
+        assert!(create("Sofia Solar", 12.5).validate().is_ok());
+        assert!(create("Sofia Solar", MAX_CAPACITY_MW).validate().is_ok());
+
+        assert_eq!(invalid_fields(create("", 12.5)), ["name"]);
+        assert_eq!(invalid_fields(create("  ", 12.5)), ["name"]);
+        assert_eq!(invalid_fields(create("Sofia Solar", 0.0)), ["capacity_mw"]);
+        assert_eq!(
+            invalid_fields(create("x".repeat(201).as_str(), -1.0)),
+            ["capacity_mw", "name"]
+        );
+        assert_eq!(
+            invalid_fields(CreatePlantRequest {
+                address: Some("x".repeat(501)),
+                latitude: Some(91.0),
+                longitude: Some(23.3),
+                ..create("Sofia Solar", 12.5)
+            }),
+            ["address", "latitude"]
+        );
+        assert_eq!(
+            invalid_fields(CreatePlantRequest {
+                latitude: Some(42.7),
+                ..create("Sofia Solar", 12.5)
+            }),
+            ["__all__"]
+        );
+    }
+
+    #[test]
+    fn test_update_validation() {
+        let update = UpdatePlantRequest {
+            name: None,
+            energy_type: None,
+            capacity_mw: None,
+            address: None,
+            latitude: None,
+            longitude: None,
+            status: None,
+        };
+        assert!(update.validate().is_ok());
+
+        assert_eq!(
+            invalid_fields(UpdatePlantRequest {
+                name: Some(String::new()),
+                capacity_mw: Some(MAX_CAPACITY_MW + 1.0),
+                ..update
+            }),
+            ["capacity_mw", "name"]
+        );
+    }
+}
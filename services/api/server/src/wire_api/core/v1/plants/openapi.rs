@@ -0,0 +1,28 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the plants routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handler::create,
+        super::handler::list,
+        super::handler::near,
+        super::handler::summary,
+        super::handler::changes,
+        super::handler::get,
+        super::handler::update,
+        super::handler::patch,
+        super::handler::delete,
+        super::handler::import,
+        super::handler::export,
+        super::handler::aggregate,
+    ),
+    tags(
+        (name = "plants", description = "Generation and storage sites of the tenant")
+    )
+)]
+pub(super) struct ApiDoc;
@@ -0,0 +1,165 @@
+//! Capacity summary of a tenant's plants, rolled up from the energy type and
+//! status groups of [`Plant::capacity_groups`].
+//!
+//! [`Plant::capacity_groups`]: postgres_models::models::plants::Plant::capacity_groups
+use std::collections::BTreeMap;
+
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use postgres_models::models::plants::PlantCapacityGroup;
+
+use super::models::{
+    CapacityGroup, CapacityTotals, EnergyTypeCapacity, PlantSummaryResponse,
+    StatusCapacity,
+};
+
+/// Decimal places of average capacities.
+const AVERAGE_SCALE: i64 = 4;
+
+#[derive(Debug, Clone)]
+struct Totals {
+    plant_count: i64,
+    capacity_mw: BigDecimal,
+}
+
+impl Default for Totals {
+    fn default() -> Self {
+        Self {
+            plant_count: 0,
+            capacity_mw: BigDecimal::zero(),
+        }
+    }
+}
+
+impl Totals {
+    fn add(&mut self, group: &PlantCapacityGroup) {
+        self.plant_count += group.plant_count;
+        self.capacity_mw += &group.total_capacity_mw;
+    }
+
+    fn to_response(&self) -> CapacityTotals {
+        let average = (self.plant_count > 0).then(|| {
+            (&self.capacity_mw / BigDecimal::from(self.plant_count))
+                .round(AVERAGE_SCALE)
+        });
+        CapacityTotals {
+            plant_count: self.plant_count,
+            total_capacity_mw: self.capacity_mw.to_f64().unwrap_or_default(),
+            average_capacity_mw: average.and_then(|average| average.to_f64()),
+        }
+    }
+}
+
+/// Totals over all plants, per energy type and per status, each ordered by
+/// name.
+pub fn response(groups: Vec<PlantCapacityGroup>) -> PlantSummaryResponse {
+    let mut total = Totals::default();
+    let mut by_energy_type = BTreeMap::<&str, Totals>::new();
+    let mut by_status = BTreeMap::<&str, Totals>::new();
+    for group in &groups {
+        total.add(group);
+        by_energy_type
+            .entry(&group.energy_type)
+            .or_default()
+            .add(group);
+        by_status.entry(&group.status).or_default().add(group);
+    }
+
+    PlantSummaryResponse {
+        totals: total.to_response(),
+        by_energy_type: by_energy_type
+            .into_iter()
+            .map(|(energy_type, totals)| EnergyTypeCapacity {
+                energy_type: energy_type.to_string(),
+                totals: totals.to_response(),
+            })
+            .collect(),
+        by_status: by_status
+            .into_iter()
+            .map(|(status, totals)| StatusCapacity {
+                status: status.to_string(),
+                totals: totals.to_response(),
+            })
+            .collect(),
+        groups: groups
+            .iter()
+            .map(|group| CapacityGroup {
+                energy_type: group.energy_type.clone(),
+                status: group.status.clone(),
+                totals: Totals {
+                    plant_count: group.plant_count,
+                    capacity_mw: group.total_capacity_mw.clone(),
+                }
+                .to_response(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(
+        energy_type: &str,
+        status: &str,
+        plant_count: i64,
+        capacity_mw: &str,
+    ) -> PlantCapacityGroup {
+        PlantCapacityGroup {
+            energy_type: energy_type.to_string(),
+            status: status.to_string(),
+            plant_count,
+            total_capacity_mw: capacity_mw.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_rollup() {
+        let response = response(vec![
+            group("solar", "active", 2, "10"),
+            group("solar", "maintenance", 1, "2.5"),
+            group("wind", "active", 3, "10"),
+        ]);
+
+        assert_eq!(response.totals.plant_count, 6);
+        assert_eq!(response.totals.total_capacity_mw, 22.5);
+        assert_eq!(response.totals.average_capacity_mw, Some(3.75));
+
+        let energy_types = response
+            .by_energy_type
+            .iter()
+            .map(|summary| {
+                (
+                    summary.energy_type.as_str(),
+                    summary.totals.plant_count,
+                    summary.totals.total_capacity_mw,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(energy_types, [("solar", 3, 12.5), ("wind", 3, 10.0)]);
+
+        let statuses = response
+            .by_status
+            .iter()
+            .map(|summary| {
+                (summary.status.as_str(), summary.totals.plant_count)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [("active", 5), ("maintenance", 1)]);
+
+        assert_eq!(response.groups.len(), 3);
+        // 10 MW over 3 plants
+        assert_eq!(response.groups[2].totals.average_capacity_mw, Some(3.3333));
+    }
+
+    #[test]
+    fn test_no_plants() {
+        let response = response(Vec::new());
+
+        assert_eq!(response.totals.plant_count, 0);
+        assert_eq!(response.totals.total_capacity_mw, 0.0);
+        assert_eq!(response.totals.average_capacity_mw, None);
+        assert!(response.by_energy_type.is_empty());
+        assert!(response.by_status.is_empty());
+    }
+}
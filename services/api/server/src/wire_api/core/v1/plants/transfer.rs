@@ -0,0 +1,329 @@
+//! Plants as CSV or Excel tables, for bulk import and export.
+use bigdecimal::ToPrimitive;
+use excel_client::models::{Cell, Table};
+use postgres_models::models::plants::Plant;
+use validator::Validate;
+
+use super::models::{
+    CreatePlantRequest, EnergyType, ImportRowError, PlantFileFormat,
+    PlantStatus,
+};
+
+const NAME: &str = "name";
+const ENERGY_TYPE: &str = "energy_type";
+const CAPACITY_MW: &str = "capacity_mw";
+const ADDRESS: &str = "address";
+const LATITUDE: &str = "latitude";
+const LONGITUDE: &str = "longitude";
+const STATUS: &str = "status";
+
+/// Columns of exports, in order. Imports need the first three.
+pub const COLUMNS: [&str; 7] = [
+    NAME,
+    ENERGY_TYPE,
+    CAPACITY_MW,
+    ADDRESS,
+    LATITUDE,
+    LONGITUDE,
+    STATUS,
+];
+
+/// Read a file into a table.
+pub fn read(format: PlantFileFormat, bytes: Vec<u8>) -> Result<Table, String> {
+    match format {
+        PlantFileFormat::Csv => read_csv(&bytes),
+        PlantFileFormat::Xlsx => {
+            excel_client::table::read_table(bytes).map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn read_csv(bytes: &[u8]) -> Result<Table, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(bytes);
+    let headers = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let mut cells = record
+            .iter()
+            .map(|field| {
+                if field.is_empty() {
+                    Cell::Empty
+                } else {
+                    Cell::Text(field.to_string())
+                }
+            })
+            .collect::<Vec<_>>();
+        cells.resize(headers.len(), Cell::Empty);
+        rows.push(cells);
+    }
+
+    Ok(Table { headers, rows })
+}
+
+/// Write a table in the given format.
+pub fn write(
+    format: PlantFileFormat,
+    table: &Table,
+) -> Result<Vec<u8>, String> {
+    match format {
+        PlantFileFormat::Csv => write_csv(table),
+        PlantFileFormat::Xlsx => {
+            excel_client::table::write_table("Plants", table)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn write_csv(table: &Table) -> Result<Vec<u8>, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(&table.headers)
+        .map_err(|e| e.to_string())?;
+    for row in &table.rows {
+        writer
+            .write_record(
+                row.iter().map(|cell| cell.as_text().unwrap_or_default()),
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+/// Plants as a table with [`COLUMNS`].
+pub fn to_table(plants: Vec<Plant>) -> Table {
+    let text = |value: Option<String>| value.map_or(Cell::Empty, Cell::Text);
+    let number = |value: Option<f64>| value.map_or(Cell::Empty, Cell::Number);
+    Table {
+        headers: COLUMNS.iter().map(|c| c.to_string()).collect(),
+        rows: plants
+            .into_iter()
+            .map(|plant| {
+                vec![
+                    Cell::Text(plant.name),
+                    Cell::Text(plant.energy_type),
+                    plant
+                        .capacity_mw
+                        .to_f64()
+                        .map_or(Cell::Empty, Cell::Number),
+                    text(plant.address),
+                    number(plant.latitude),
+                    number(plant.longitude),
+                    Cell::Text(plant.status),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Positions of the import columns in a table.
+pub struct Columns {
+    name: usize,
+    energy_type: usize,
+    capacity_mw: usize,
+    address: Option<usize>,
+    latitude: Option<usize>,
+    longitude: Option<usize>,
+    status: Option<usize>,
+}
+
+impl Columns {
+    /// Find the import columns, or name the first missing required one.
+    pub fn find(table: &Table) -> Result<Self, &'static str> {
+        let required = |name| table.column(name).ok_or(name);
+        Ok(Self {
+            name: required(NAME)?,
+            energy_type: required(ENERGY_TYPE)?,
+            capacity_mw: required(CAPACITY_MW)?,
+            address: table.column(ADDRESS),
+            latitude: table.column(LATITUDE),
+            longitude: table.column(LONGITUDE),
+            status: table.column(STATUS),
+        })
+    }
+
+    /// Parse and validate one data row. `row` is its number in the file.
+    pub fn parse(
+        &self,
+        row: usize,
+        cells: &[Cell],
+    ) -> Result<CreatePlantRequest, Vec<ImportRowError>> {
+        let cell = |index: usize| cells.get(index).unwrap_or(&Cell::Empty);
+        let error = |field: &str, message: &str| ImportRowError {
+            row,
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+        let mut errors = Vec::new();
+
+        let energy_type = cell(self.energy_type)
+            .as_text()
+            .and_then(|text| text.parse::<EnergyType>().ok());
+        if energy_type.is_none() {
+            errors.push(error(
+                ENERGY_TYPE,
+                "Expected one of solar, wind, hydro, battery or other",
+            ));
+        }
+
+        let capacity_mw = cell(self.capacity_mw).as_number();
+        if capacity_mw.is_none() {
+            errors.push(error(CAPACITY_MW, "Expected a number"));
+        }
+
+        let mut coordinate = |field: &'static str, index: Option<usize>| {
+            // Blank cells leave the coordinates unset
+            let text = index.and_then(|i| cell(i).as_text())?;
+            let value = text.parse::<f64>().ok();
+            if value.is_none() {
+                errors.push(error(field, "Expected a number"));
+            }
+            value
+        };
+        let latitude = coordinate(LATITUDE, self.latitude);
+        let longitude = coordinate(LONGITUDE, self.longitude);
+
+        let status = match self.status.and_then(|i| cell(i).as_text()) {
+            None => None,
+            Some(text) => match text.parse::<PlantStatus>() {
+                Ok(status) => Some(status),
+                Err(()) => {
+                    errors.push(error(
+                        STATUS,
+                        "Expected one of active, inactive, maintenance or \
+                         decommissioned",
+                    ));
+                    None
+                }
+            },
+        };
+
+        let (Some(energy_type), Some(capacity_mw)) = (energy_type, capacity_mw)
+        else {
+            return Err(errors);
+        };
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let request = CreatePlantRequest {
+            name: cell(self.name).as_text().unwrap_or_default(),
+            energy_type,
+            capacity_mw,
+            address: self.address.and_then(|i| cell(i).as_text()),
+            latitude,
+            longitude,
+            status,
+        };
+        request.validate().map_err(|e| {
+            let mut errors = e
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, field_errors)| {
+                    field_errors.iter().map(move |e| {
+                        let message = e.message.as_ref().unwrap_or(&e.code);
+                        // Errors across fields are named by their code
+                        let field =
+                            if field == "__all__" { &e.code } else { &field };
+                        error(field, message)
+                    })
+                })
+                .collect::<Vec<_>>();
+            errors.sort_by(|a, b| a.field.cmp(&b.field));
+            errors
+        })?;
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_import() {
+        let csv = "Name,Energy_Type,Capacity_MW,Status\n\
+                   Sofia Solar,solar,12.5,\n\
+                   Varna Wind,coal,abc,\n\
+                   ,,,\n\
+                   ,wind,0,retired\n\
+                   Rila Hydro,hydro,0";
+        let table =
+            read(PlantFileFormat::Csv, csv.as_bytes().to_vec()).unwrap();
+        let columns = Columns::find(&table).unwrap();
+
+        let plant = columns.parse(2, &table.rows[0]).unwrap();
+        assert_eq!(plant.name, "Sofia Solar");
+        assert_eq!(plant.energy_type, EnergyType::Solar);
+        assert_eq!(plant.capacity_mw, 12.5);
+        assert_eq!(plant.status, None);
+
+        let fields = |row: usize| {
+            columns
+                .parse(row + 2, &table.rows[row])
+                .unwrap_err()
+                .into_iter()
+                .map(|e| (e.row, e.field))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fields(1),
+            [(3, ENERGY_TYPE.to_string()), (3, CAPACITY_MW.to_string())]
+        );
+        assert_eq!(fields(3), [(5, STATUS.to_string())]);
+        assert_eq!(
+            fields(4),
+            [(6, CAPACITY_MW.to_string())],
+            "short rows are padded and validated"
+        );
+    }
+
+    #[test]
+    fn test_missing_columns() {
+        let table =
+            read(PlantFileFormat::Csv, b"name,capacity_mw\nx,1".to_vec())
+                .unwrap();
+        assert_eq!(Columns::find(&table).err(), Some(ENERGY_TYPE));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let table = Table {
+            headers: COLUMNS.iter().map(|c| c.to_string()).collect(),
+            rows: vec![vec![
+                Cell::Text("Sofia Solar".to_string()),
+                Cell::Text("solar".to_string()),
+                Cell::Number(12.5),
+                Cell::Empty,
+                Cell::Number(42.6977),
+                Cell::Number(23.3219),
+                Cell::Text("active".to_string()),
+            ]],
+        };
+
+        for format in [PlantFileFormat::Csv, PlantFileFormat::Xlsx] {
+            let bytes = write(format, &table).unwrap();
+            let read = read(format, bytes).unwrap();
+            let plant = Columns::find(&read)
+                .unwrap()
+                .parse(2, &read.rows[0])
+                .unwrap();
+            assert_eq!(plant.name, "Sofia Solar");
+            assert_eq!(plant.capacity_mw, 12.5);
+            assert_eq!(plant.address, None);
+            assert_eq!(plant.latitude, Some(42.6977));
+            assert_eq!(plant.longitude, Some(23.3219));
+            assert_eq!(plant.status, Some(PlantStatus::Active));
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Preferences of API keys, and their use as request defaults.
+//!
+//! Preferences are read from Postgres through a Redis cache of
+//! [`PREFERENCES_TTL`], which [`forget`] clears when they change. The
+//! [`apply`] middleware fills in the `aggregationType` body field and the
+//! `decimals` query parameter of the requests it is layered on when they
+//! leave them out; fields a request sets are never replaced.
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::Uri;
+use axum::middleware::Next;
+use axum::response::Response;
+use diesel::result::Error as DieselError;
+use postgres_models::connection::{WithConnectionError, with_connection};
+use postgres_models::models::api_key_preferences::ApiKeyPreferences;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::{Caller, TenantContext};
+use crate::shared::cache_key::CacheKey;
+use crate::shared::extractors::request_id::RequestId;
+use crate::shared::response_cache;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+use super::errors;
+
+/// How long preferences are cached; they are also cleared when they change.
+pub const PREFERENCES_TTL: Duration = Duration::from_secs(300);
+/// Largest body [`apply`] reads; requests taking defaults have small ones.
+pub(super) const MAX_BODY_BYTES: usize = 64 * 1024;
+const HANDLER_NAME: &str = "preferences_defaults";
+
+fn cache_key(tenant: &TenantContext, api_key_id: Uuid) -> String {
+    CacheKey::new(tenant, "preferences", "api_key")
+        .param("id", api_key_id)
+        .build()
+}
+
+/// The preferences of an API key, `None` when none are set. Misses are
+/// cached too, so keys without preferences cost no query per request.
+pub async fn load(
+    state: &AppState,
+    tenant: &TenantContext,
+    api_key_id: Uuid,
+) -> Result<Option<ApiKeyPreferences>, WithConnectionError<DieselError>> {
+    let key = cache_key(tenant, api_key_id);
+    if let Some(cached) = response_cache::get(&state.cache_pool, &key)
+        .await
+        .and_then(|json| serde_json::from_str(&json).ok())
+    {
+        return Ok(cached);
+    }
+
+    // From the primary, so a replica lagging behind a change is not cached
+    let preferences = with_connection(&state.pool, |mut conn| async move {
+        ApiKeyPreferences::find(api_key_id, &mut conn).await
+    })
+    .await?;
+    if let Ok(json) = serde_json::to_string(&preferences) {
+        response_cache::set(&state.cache_pool, &key, &json, PREFERENCES_TTL)
+            .await;
+    }
+    Ok(preferences)
+}
+
+/// Clear the cached preferences of an API key once they change.
+pub async fn forget(
+    state: &AppState,
+    tenant: &TenantContext,
+    api_key_id: Uuid,
+) {
+    response_cache::delete(&state.cache_pool, &cache_key(tenant, api_key_id))
+        .await;
+}
+
+/// `body` with `aggregationType` set to `aggregation_type` when it is a JSON
+/// object without one, `None` when it needs no change.
+pub fn fill_body(body: &[u8], aggregation_type: &str) -> Option<Vec<u8>> {
+    let mut value = serde_json::from_slice::<Value>(body).ok()?;
+    let object = value.as_object_mut()?;
+    if object.contains_key("aggregationType") {
+        return None;
+    }
+    object.insert("aggregationType".to_string(), aggregation_type.into());
+    serde_json::to_vec(&value).ok()
+}
+
+/// `uri` with the `decimals` query parameter set to `decimals` when it has
+/// none, `None` when it needs no change.
+pub fn fill_query(uri: &Uri, decimals: i32) -> Option<Uri> {
+    let query = uri.query().unwrap_or_default();
+    if url::form_urlencoded::parse(query.as_bytes())
+        .any(|(name, _)| name == "decimals")
+    {
+        return None;
+    }
+    let query = if query.is_empty() {
+        format!("decimals={decimals}")
+    } else {
+        format!("{query}&decimals={decimals}")
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query =
+        Some(format!("{}?{query}", uri.path()).parse().ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+/// Fill in the defaults the calling API key prefers, see the
+/// [module](self). Requests are passed on as they are when the preferences
+/// cannot be loaded.
+///
+/// Must run after [`crate::auth::middleware::authenticate`].
+pub async fn apply(
+    State(state): State<AppState>,
+    RequestId(request_id): RequestId,
+    req: Request,
+    next: Next,
+) -> Result<Response, WireV1Error> {
+    let (Some(caller), Some(tenant)) = (
+        req.extensions().get::<Caller>(),
+        req.extensions().get::<TenantContext>(),
+    ) else {
+        return Ok(next.run(req).await);
+    };
+
+    let preferences = match load(&state, tenant, caller.api_key_id).await {
+        Ok(Some(preferences)) => preferences,
+        Ok(None) => return Ok(next.run(req).await),
+        Err(e) => {
+            tracing::warn!("Request defaults skipped, preferences failed: {e}");
+            return Ok(next.run(req).await);
+        }
+    };
+
+    let (mut parts, body) = req.into_parts();
+    if let Some(decimals) = preferences.decimals
+        && let Some(uri) = fill_query(&parts.uri, decimals)
+    {
+        parts.uri = uri;
+    }
+    let Some(aggregation_type) = preferences.aggregation_type else {
+        return Ok(next.run(Request::from_parts(parts, body)).await);
+    };
+
+    let body =
+        axum::body::to_bytes(body, MAX_BODY_BYTES)
+            .await
+            .map_err(|e| {
+                ErrorRecorder::new(&state.telemetry, HANDLER_NAME, &request_id)
+                    .record(
+                        "unreadable_body",
+                        errors::Error::UnreadableBody(e.to_string()),
+                    )
+            })?;
+    let body = match fill_body(&body, &aggregation_type) {
+        Some(filled) => {
+            // The body's length changed
+            parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+            Body::from(filled)
+        }
+        None => Body::from(body),
+    };
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fills_missing_aggregation_type() {
+        let filled = fill_body(br#"{"dateFrom":null}"#, "monthly").unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&filled).unwrap(),
+            serde_json::json!({"dateFrom": null, "aggregationType": "monthly"})
+        );
+        assert!(
+            fill_body(br#"{"aggregationType":"yearly"}"#, "monthly").is_none()
+        );
+        assert!(fill_body(b"not json", "monthly").is_none());
+    }
+
+    #[test]
+    fn test_fills_missing_decimals() {
+        let uri = Uri::from_static("/energy/aggregate");
+        assert_eq!(
+            fill_query(&uri, 2).unwrap(),
+            "/energy/aggregate?decimals=2"
+        );
+        let uri = Uri::from_static("/energy/aggregate?fill_gaps=true");
+        assert_eq!(
+            fill_query(&uri, 0).unwrap(),
+            "/energy/aggregate?fill_gaps=true&decimals=0"
+        );
+        let uri = Uri::from_static("/energy/aggregate?decimals=4");
+        assert!(fill_query(&uri, 2).is_none());
+    }
+}
@@ -0,0 +1,55 @@
+use uuid::Uuid;
+
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Preferences are only kept for API keys")]
+    NotAnApiKey,
+
+    #[error("Failed to read request body: {0}")]
+    UnreadableBody(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotAnApiKey => WireV1Error::bad_request(
+                "Preferences are only kept for API keys".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Authorization".to_string()),
+                    code: "not_an_api_key".to_string(),
+                    message:
+                        "The request was not authenticated with an API key"
+                            .to_string(),
+                    suggestion: "Call this endpoint with the API key whose \
+                                 preferences you want to manage"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::UnreadableBody(e) => WireV1Error::bad_request(
+                "Invalid request body".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "unreadable_body".to_string(),
+                    message: format!("Failed to read request body: {e}"),
+                    suggestion: format!(
+                        "Send at most {} bytes per request",
+                        super::defaults::MAX_BODY_BYTES
+                    ),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Preferences operation failed";
+}
@@ -0,0 +1,124 @@
+use axum::Json;
+use axum::http::StatusCode;
+use postgres_models::connection::with_connection;
+use postgres_models::models::api_key_preferences::ApiKeyPreferences;
+use uuid::Uuid;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+use super::defaults;
+use super::errors::{self, HandlerResult};
+use super::models::{PreferencesRequest, PreferencesResponse};
+
+const HANDLER_NAME: &str = "preferences";
+
+fn api_key_id(
+    ctx: &HandlerCtx,
+    recorder: &ErrorRecorder<'_>,
+) -> Result<Uuid, WireV1Error> {
+    ctx.caller
+        .as_ref()
+        .map(|caller| caller.api_key_id)
+        .ok_or_else(|| {
+            recorder.record("not_an_api_key", errors::Error::NotAnApiKey)
+        })
+}
+
+/// Get the caller's preferences
+///
+/// Returns the defaults of the calling API key, with the fields it has not
+/// set as `null`.
+#[utoipa::path(
+    get,
+    path = "/preferences",
+    responses(
+        (status = 200, description = "Preferences", body = PreferencesResponse),
+        (status = 400, description = "Not authenticated with an API key"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "preferences",
+)]
+#[tracing::instrument(skip_all, name = "preferences_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<PreferencesResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let key_id = api_key_id(&ctx, &recorder)?;
+
+    let preferences = defaults::load(&ctx.state, &ctx.tenant, key_id)
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PreferencesResponse::new(key_id, preferences)),
+    ))
+}
+
+/// Set the caller's preferences
+///
+/// Replaces the defaults of the calling API key. `aggregationType` and
+/// `decimals` are used by `POST /energy/aggregate` and
+/// `POST /energy/aggregate/estimate` requests leaving them out; `timezone`
+/// and `unit` are kept for clients to read back.
+#[utoipa::path(
+    put,
+    path = "/preferences",
+    request_body = PreferencesRequest,
+    responses(
+        (status = 200, description = "Preferences set", body = PreferencesResponse),
+        (status = 400, description = "Invalid preferences, or not authenticated with an API key"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "preferences",
+)]
+#[tracing::instrument(skip_all, name = "preferences_put")]
+pub async fn put(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<PreferencesRequest>,
+) -> HandlerResult<(StatusCode, Json<PreferencesResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let key_id = api_key_id(&ctx, &recorder)?;
+
+    let entry = payload.into_new(key_id);
+    let preferences = with_connection(&ctx.state.pool, |mut conn| async move {
+        ApiKeyPreferences::upsert(entry, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+    defaults::forget(&ctx.state, &ctx.tenant, key_id).await;
+
+    Ok((
+        StatusCode::OK,
+        Json(PreferencesResponse::new(key_id, Some(preferences))),
+    ))
+}
+
+/// Clear the caller's preferences
+#[utoipa::path(
+    delete,
+    path = "/preferences",
+    responses(
+        (status = 204, description = "Preferences cleared"),
+        (status = 400, description = "Not authenticated with an API key"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "preferences",
+)]
+#[tracing::instrument(skip_all, name = "preferences_delete")]
+pub async fn delete(ctx: HandlerCtx) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let key_id = api_key_id(&ctx, &recorder)?;
+
+    with_connection(&ctx.state.pool, |mut conn| async move {
+        ApiKeyPreferences::delete(key_id, &mut conn).await
+    })
+    .await
+    .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+    defaults::forget(&ctx.state, &ctx.tenant, key_id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
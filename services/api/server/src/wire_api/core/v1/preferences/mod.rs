@@ -0,0 +1,42 @@
+use axum::Router;
+use axum::routing::get;
+use utoipa::OpenApi;
+
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+
+pub mod defaults;
+mod errors;
+pub mod handler;
+pub mod models;
+mod openapi;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(handler::get).put(handler::put).delete(handler::delete),
+        )
+        .with_state(state)
+}
+
+/// Defaults of the requests made with the calling API key.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "preferences"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Preferences)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/preferences", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
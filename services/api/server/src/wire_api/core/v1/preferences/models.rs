@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use postgres_models::models::api_key_preferences::{
+    ApiKeyPreferences, NewApiKeyPreferences,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+
+/// Unit energy is shown in
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EnergyUnit {
+    Wh,
+    Kwh,
+    Mwh,
+}
+
+impl EnergyUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnergyUnit::Wh => "wh",
+            EnergyUnit::Kwh => "kwh",
+            EnergyUnit::Mwh => "mwh",
+        }
+    }
+}
+
+impl std::str::FromStr for EnergyUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wh" => Ok(EnergyUnit::Wh),
+            "kwh" => Ok(EnergyUnit::Kwh),
+            "mwh" => Ok(EnergyUnit::Mwh),
+            other => Err(format!("Unknown unit `{other}`")),
+        }
+    }
+}
+
+fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    timezone.parse::<Tz>().map(|_| ()).map_err(|_| {
+        ValidationError::new("timezone").with_message(
+            format!("`{timezone}` is not an IANA time zone").into(),
+        )
+    })
+}
+
+/// Request payload for setting the preferences of the calling API key.
+/// Replaces all of them: a field left out is no longer set.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferencesRequest {
+    /// IANA time zone of the client
+    #[validate(custom(function = "validate_timezone"))]
+    #[schema(example = "Europe/Berlin")]
+    pub timezone: Option<String>,
+
+    /// Unit of energy of the client
+    pub unit: Option<EnergyUnit>,
+
+    /// `decimals` of aggregations leaving it out
+    #[validate(range(max = 4, message = "At most 4 decimal places"))]
+    #[schema(example = 2)]
+    pub decimals: Option<u32>,
+
+    /// `aggregationType` of aggregations leaving it out
+    pub aggregation_type: Option<AggregationType>,
+}
+
+impl PreferencesRequest {
+    pub fn into_new(self, api_key_id: Uuid) -> NewApiKeyPreferences {
+        NewApiKeyPreferences {
+            api_key_id,
+            timezone: self.timezone,
+            unit: self.unit.map(|unit| unit.as_str().to_string()),
+            decimals: self.decimals.map(|decimals| decimals as i32),
+            aggregation_type: self
+                .aggregation_type
+                .map(|aggregation| aggregation.to_string()),
+        }
+    }
+}
+
+/// Preferences of an API key
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferencesResponse {
+    pub api_key_id: Uuid,
+    pub timezone: Option<String>,
+    pub unit: Option<EnergyUnit>,
+    pub decimals: Option<u32>,
+    pub aggregation_type: Option<AggregationType>,
+    /// When the preferences were last set, `None` when they never were
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl PreferencesResponse {
+    /// The preferences of `api_key_id`, none set when `preferences` is
+    /// `None`.
+    pub fn new(
+        api_key_id: Uuid,
+        preferences: Option<ApiKeyPreferences>,
+    ) -> Self {
+        let Some(preferences) = preferences else {
+            return Self {
+                api_key_id,
+                timezone: None,
+                unit: None,
+                decimals: None,
+                aggregation_type: None,
+                updated_at: None,
+            };
+        };
+        Self {
+            api_key_id,
+            timezone: preferences.timezone,
+            unit: preferences.unit.and_then(|unit| unit.parse().ok()),
+            decimals: preferences
+                .decimals
+                .and_then(|decimals| u32::try_from(decimals).ok()),
+            aggregation_type: preferences
+                .aggregation_type
+                .and_then(|aggregation| aggregation.parse().ok()),
+            updated_at: Some(preferences.updated_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validates_timezones() {
+        let request = PreferencesRequest {
+            timezone: Some("Europe/Berlin".to_string()),
+            decimals: Some(2),
+            ..Default::default()
+        };
+        assert!(request.validate().is_ok());
+
+        let request = PreferencesRequest {
+            timezone: Some("Mars/Olympus_Mons".to_string()),
+            ..Default::default()
+        };
+        assert!(request.validate().is_err());
+
+        let request = PreferencesRequest {
+            decimals: Some(5),
+            ..Default::default()
+        };
+        assert!(request.validate().is_err());
+    }
+}
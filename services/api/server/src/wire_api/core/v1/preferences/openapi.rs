@@ -0,0 +1,19 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the preferences routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handler::get,
+        super::handler::put,
+        super::handler::delete,
+    ),
+    tags(
+        (name = "preferences", description = "Defaults of the requests of the calling API key")
+    )
+)]
+pub(super) struct ApiDoc;
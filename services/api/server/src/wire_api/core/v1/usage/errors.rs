@@ -0,0 +1,62 @@
+use uuid::Uuid;
+
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV1Error>;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Usage is only tracked for API keys")]
+    NotAnApiKey,
+
+    #[error("Failed to get cache connection: {0}")]
+    CachePoolError(String),
+
+    #[error("Cache error: {0}")]
+    CacheError(#[from] redis_cache::RedisError),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::NotAnApiKey => WireV1Error::bad_request(
+                "Usage is only tracked for API keys".to_string(),
+                vec![WireV1Detail {
+                    field: Some("Authorization".to_string()),
+                    code: "not_an_api_key".to_string(),
+                    message:
+                        "The request was not authenticated with an API key"
+                            .to_string(),
+                    suggestion: "Call this endpoint with the API key whose \
+                                 usage you want to see"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::CachePoolError(e) => WireV1Error::service_unavailable(
+                "Service temporarily unavailable".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "cache_pool_error".to_string(),
+                    message: format!("Failed to get cache connection: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::CacheError(e) => WireV1Error::internal_server_error(
+                "Failed to fetch usage".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "cache_error".to_string(),
+                    message: format!("Cache error: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use axum::Json;
+use axum::http::StatusCode;
+
+use crate::auth::quota;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::UsageResponse;
+
+const HANDLER_NAME: &str = "usage";
+
+/// Get the caller's quota consumption
+///
+/// Returns the requests made with the calling API key in the current UTC day
+/// and calendar month, next to the key's quotas.
+#[utoipa::path(
+    get,
+    path = "/usage",
+    responses(
+        (status = 200, description = "Current consumption", body = UsageResponse),
+        (status = 400, description = "Not authenticated with an API key"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "usage",
+)]
+#[tracing::instrument(skip_all, name = "usage")]
+pub async fn handler(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<UsageResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let caller = ctx.caller.as_ref().ok_or_else(|| {
+        recorder.record("not_an_api_key", errors::Error::NotAnApiKey)
+    })?;
+
+    let mut conn = ctx.state.cache_pool.get().await.map_err(|e| {
+        recorder.record(
+            "cache_pool_error",
+            errors::Error::CachePoolError(e.to_string()),
+        )
+    })?;
+
+    let windows = quota::usage(
+        &mut conn,
+        caller.api_key_id,
+        &caller.quota,
+        chrono::Utc::now(),
+    )
+    .await
+    .map_err(|e| {
+        recorder.record("cache_error", errors::Error::CacheError(e))
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(UsageResponse {
+            api_key_id: caller.api_key_id,
+            name: caller.name.clone(),
+            windows,
+        }),
+    ))
+}
@@ -0,0 +1,43 @@
+use axum::Router;
+use axum::routing::get;
+use utoipa::OpenApi;
+
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::{ApiModule, Mount};
+
+mod errors;
+pub mod handler;
+pub mod models;
+mod openapi;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", get(handler::handler))
+        .with_state(state)
+}
+
+/// Quota consumption of the caller, not itself metered so it can be
+/// checked once the quota is exhausted.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "usage"
+    }
+
+    fn mount(&self) -> Mount {
+        Mount::Unmetered
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Usage)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/usage", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
@@ -0,0 +1,14 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::quota::WindowUsage;
+
+/// Request consumption of the calling API key
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageResponse {
+    pub api_key_id: uuid::Uuid,
+    pub name: String,
+    /// Daily (UTC) and monthly windows
+    pub windows: Vec<WindowUsage>,
+}
@@ -0,0 +1,17 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the usage routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handler::handler,
+    ),
+    tags(
+        (name = "usage", description = "Quota consumption of the calling API key")
+    )
+)]
+pub(super) struct ApiDoc;
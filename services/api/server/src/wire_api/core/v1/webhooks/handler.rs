@@ -0,0 +1,338 @@
+use axum::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use postgres_models::connection::{WithConnectionError, with_connection};
+use postgres_models::models::webhooks::{
+    NewWebhook, UpdateWebhook, Webhook, WebhookDelivery,
+};
+use uuid::Uuid;
+
+use crate::shared::extractors::validations::ValidatedPayload;
+use crate::webhooks::{WebhookEvent, signing};
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::handler_ctx::HandlerCtx;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+use super::errors::{self, HandlerResult};
+use super::models::{
+    CreateWebhookRequest, UpdateWebhookRequest, WebhookDeliveriesResponse,
+    WebhookListResponse, WebhookResponse,
+};
+
+const HANDLER_NAME: &str = "webhooks";
+const DELIVERIES_LIMIT: i64 = 50;
+
+fn event_types(events: &[WebhookEvent]) -> Vec<Option<String>> {
+    events
+        .iter()
+        .map(|e| Some(e.as_str().to_string()))
+        .collect()
+}
+
+fn record_db_error(
+    recorder: &ErrorRecorder<'_>,
+    webhook_id: Option<Uuid>,
+    e: WithConnectionError<diesel::result::Error>,
+) -> WireV1Error {
+    match (e, webhook_id) {
+        (
+            WithConnectionError::Operation(diesel::result::Error::NotFound),
+            Some(id),
+        ) => recorder.record("not_found", errors::Error::NotFound(id)),
+        (e, _) => recorder.record_database::<errors::Error>(e),
+    }
+}
+
+/// Register a webhook
+///
+/// The signing secret is only returned in this response; store it to verify
+/// the `X-Wire-Signature` header of deliveries.
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "Webhook registered", body = WebhookResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "webhooks",
+)]
+#[tracing::instrument(skip_all, name = "webhooks_create")]
+pub async fn create(
+    ctx: HandlerCtx,
+    ValidatedPayload(payload): ValidatedPayload<CreateWebhookRequest>,
+) -> HandlerResult<(StatusCode, Json<WebhookResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let new_webhook = NewWebhook {
+        tenant_id: ctx.tenant.tenant_id.clone(),
+        url: payload.url,
+        secret: signing::generate_secret(),
+        event_types: event_types(&payload.event_types),
+        description: payload.description,
+        active: payload.active.unwrap_or(true),
+    };
+
+    let webhook = with_connection(&ctx.state.pool, |mut conn| async move {
+        Webhook::create(new_webhook, &mut conn).await
+    })
+    .await
+    .map_err(|e| record_db_error(&recorder, None, e))?;
+
+    let secret = webhook.secret.clone();
+    let mut response = WebhookResponse::from(webhook);
+    response.secret = Some(secret);
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// List registered webhooks
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    responses(
+        (status = 200, description = "Registered webhooks", body = WebhookListResponse),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "webhooks",
+)]
+#[tracing::instrument(skip_all, name = "webhooks_list")]
+pub async fn list(
+    ctx: HandlerCtx,
+) -> HandlerResult<(StatusCode, Json<WebhookListResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = ctx.tenant.tenant_id.as_str();
+
+    let webhooks = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Webhook::list(tenant_id, &mut conn).await
+        })
+        .await
+        .map_err(|e| record_db_error(&recorder, None, e))?;
+
+    let webhooks = webhooks.into_iter().map(WebhookResponse::from).collect();
+
+    Ok((StatusCode::OK, Json(WebhookListResponse { webhooks })))
+}
+
+/// Get a webhook by id
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    responses(
+        (status = 200, description = "Webhook", body = WebhookResponse),
+        (status = 404, description = "Webhook not found"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "webhooks",
+)]
+#[tracing::instrument(skip_all, name = "webhooks_get")]
+pub async fn get(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<WebhookResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = ctx.tenant.tenant_id.as_str();
+
+    let webhook = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Webhook::find(tenant_id, id, &mut conn).await
+        })
+        .await
+        .map_err(|e| record_db_error(&recorder, Some(id), e))?;
+
+    Ok((StatusCode::OK, Json(WebhookResponse::from(webhook))))
+}
+
+/// Update a webhook
+#[utoipa::path(
+    put,
+    path = "/webhooks/{id}",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    request_body = UpdateWebhookRequest,
+    responses(
+        (status = 200, description = "Updated webhook", body = WebhookResponse),
+        (status = 400, description = "Invalid request parameters"),
+        (status = 404, description = "Webhook not found"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "webhooks",
+)]
+#[tracing::instrument(skip_all, name = "webhooks_update")]
+pub async fn update(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+    ValidatedPayload(payload): ValidatedPayload<UpdateWebhookRequest>,
+) -> HandlerResult<(StatusCode, Json<WebhookResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = ctx.tenant.tenant_id.as_str();
+
+    let changes = UpdateWebhook {
+        url: payload.url,
+        event_types: payload.event_types.as_deref().map(event_types),
+        description: payload.description.map(Some),
+        active: payload.active,
+    };
+
+    let webhook = with_connection(&ctx.state.pool, |mut conn| async move {
+        Webhook::update(tenant_id, id, changes, &mut conn).await
+    })
+    .await
+    .map_err(|e| record_db_error(&recorder, Some(id), e))?;
+
+    Ok((StatusCode::OK, Json(WebhookResponse::from(webhook))))
+}
+
+/// Delete a webhook and its delivery history
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 404, description = "Webhook not found"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "webhooks",
+)]
+#[tracing::instrument(skip_all, name = "webhooks_delete")]
+pub async fn delete(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<StatusCode> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = ctx.tenant.tenant_id.as_str();
+
+    let deleted = with_connection(&ctx.state.pool, |mut conn| async move {
+        Webhook::delete(tenant_id, id, &mut conn).await
+    })
+    .await
+    .map_err(|e| record_db_error(&recorder, Some(id), e))?;
+
+    if deleted == 0 {
+        return Err(recorder.record("not_found", errors::Error::NotFound(id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the most recent deliveries of a webhook
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    responses(
+        (status = 200, description = "Last 50 deliveries", body = WebhookDeliveriesResponse),
+        (status = 404, description = "Webhook not found"),
+        (status = 403, description = "Admin role required"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "webhooks",
+)]
+#[tracing::instrument(skip_all, name = "webhooks_deliveries")]
+pub async fn deliveries(
+    ctx: HandlerCtx,
+    Path(id): Path<Uuid>,
+) -> HandlerResult<(StatusCode, Json<WebhookDeliveriesResponse>)> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+    let tenant_id = ctx.tenant.tenant_id.as_str();
+
+    let deliveries = ctx
+        .state
+        .reads
+        .with_connection(|mut conn| async move {
+            Webhook::find(tenant_id, id, &mut conn).await?;
+            WebhookDelivery::latest_for_webhook(
+                tenant_id,
+                id,
+                DELIVERIES_LIMIT,
+                &mut conn,
+            )
+            .await
+        })
+        .await
+        .map_err(|e| record_db_error(&recorder, Some(id), e))?;
+
+    let deliveries = deliveries.into_iter().map(Into::into).collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(WebhookDeliveriesResponse { deliveries }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::wire_api::testing::TestApp;
+
+    #[tokio::test]
+    async fn test_keeps_to_the_tenants_webhooks() {
+        let Some(app) = TestApp::start().await else {
+            return;
+        };
+        let mut conn = app.state.pool.get().await.unwrap();
+        let other = Webhook::create(
+            NewWebhook {
+                tenant_id: "other".to_string(),
+                url: "https://other.example.com/hooks".to_string(),
+                secret: signing::generate_secret(),
+                event_types: event_types(&[WebhookEvent::ImportCompleted]),
+                description: None,
+                active: true,
+            },
+            &mut conn,
+        )
+        .await
+        .unwrap();
+
+        let created = app
+            .server
+            .post("/api/wire/v1/webhooks")
+            .json(&json!({
+                "url": "https://example.com/hooks",
+                "eventTypes": ["import_completed"],
+            }))
+            .await;
+        created.assert_status(StatusCode::CREATED);
+        let id = created.json::<Value>()["id"].as_str().unwrap().to_string();
+
+        let listed = app.server.get("/api/wire/v1/webhooks").await;
+        let ids = listed.json::<Value>()["webhooks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|webhook| webhook["id"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [id]);
+
+        let url = format!("/api/wire/v1/webhooks/{}", other.id);
+        app.server.get(&url).await.assert_status_not_found();
+        app.server
+            .put(&url)
+            .json(&json!({ "url": "https://attacker.example.com" }))
+            .await
+            .assert_status_not_found();
+        app.server
+            .get(&format!("{url}/deliveries"))
+            .await
+            .assert_status_not_found();
+        app.server.delete(&url).await.assert_status_not_found();
+
+        let other = Webhook::find("other", other.id, &mut conn).await.unwrap();
+        assert_eq!(other.url, "https://other.example.com/hooks");
+    }
+}
@@ -0,0 +1,48 @@
+use axum::Router;
+use axum::middleware::from_extractor;
+use axum::routing::get;
+use utoipa::OpenApi;
+
+use crate::auth::{RequirePermission, permission};
+use crate::endpoints::{EndpointGroup, Endpoints};
+use crate::modules::ApiModule;
+
+mod errors;
+pub mod handler;
+pub mod models;
+mod openapi;
+
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route("/", get(handler::list).post(handler::create))
+        .route(
+            "/{id}",
+            get(handler::get)
+                .put(handler::update)
+                .delete(handler::delete),
+        )
+        .route("/{id}/deliveries", get(handler::deliveries))
+        .route_layer(from_extractor::<RequirePermission<permission::Admin>>())
+        .with_state(state)
+}
+
+/// Webhook subscriptions and their deliveries.
+pub struct Module;
+
+impl ApiModule for Module {
+    fn name(&self) -> &'static str {
+        "webhooks"
+    }
+
+    fn enabled(&self, endpoints: &Endpoints) -> bool {
+        endpoints.is_enabled(EndpointGroup::Webhooks)
+    }
+
+    fn routes(&self, state: crate::AppState) -> Router {
+        Router::new().nest("/webhooks", get_routes(state))
+    }
+
+    fn openapi(&self) -> utoipa::openapi::OpenApi {
+        openapi::ApiDoc::openapi()
+    }
+}
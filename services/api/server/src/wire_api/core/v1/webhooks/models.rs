@@ -0,0 +1,120 @@
+use postgres_models::models::webhooks::{Webhook, WebhookDelivery};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::webhooks::WebhookEvent;
+
+/// Request payload for registering a webhook
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookRequest {
+    /// Callback URL receiving signed POST requests
+    #[validate(url)]
+    #[schema(example = "https://example.com/hooks/energy")]
+    pub url: String,
+
+    /// Events to subscribe to
+    #[validate(length(min = 1))]
+    pub event_types: Vec<WebhookEvent>,
+
+    /// Free-form description
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+
+    /// Whether deliveries are sent (defaults to true)
+    pub active: Option<bool>,
+}
+
+/// Request payload for updating a webhook; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebhookRequest {
+    #[validate(url)]
+    pub url: Option<String>,
+
+    #[validate(length(min = 1))]
+    pub event_types: Option<Vec<WebhookEvent>>,
+
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+
+    pub active: Option<bool>,
+}
+
+/// A registered webhook
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookResponse {
+    pub id: uuid::Uuid,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+    pub active: bool,
+    /// Signing secret, only returned when the webhook is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            event_types: webhook.subscribed_events(),
+            id: webhook.id,
+            url: webhook.url,
+            description: webhook.description,
+            active: webhook.active,
+            secret: None,
+            created_at: webhook.created_at,
+            updated_at: webhook.updated_at,
+        }
+    }
+}
+
+/// Response containing all registered webhooks
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookListResponse {
+    pub webhooks: Vec<WebhookResponse>,
+}
+
+/// A single delivery and the outcome of its latest attempt
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryEntry {
+    pub id: uuid::Uuid,
+    pub event_type: String,
+    #[schema(example = "delivered")]
+    pub status: String,
+    pub attempts: i32,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<WebhookDelivery> for WebhookDeliveryEntry {
+    fn from(delivery: WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            event_type: delivery.event_type,
+            status: delivery.status,
+            attempts: delivery.attempts,
+            last_status_code: delivery.last_status_code,
+            last_error: delivery.last_error,
+            next_attempt_at: delivery.next_attempt_at,
+            delivered_at: delivery.delivered_at,
+            created_at: delivery.created_at,
+        }
+    }
+}
+
+/// Response containing the most recent deliveries of a webhook
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDeliveryEntry>,
+}
@@ -0,0 +1,22 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// Paths and tags of the webhook routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handler::create,
+        super::handler::list,
+        super::handler::get,
+        super::handler::update,
+        super::handler::delete,
+        super::handler::deliveries,
+    ),
+    tags(
+        (name = "webhooks", description = "Webhook subscriptions for import and alerting events")
+    )
+)]
+pub(super) struct ApiDoc;
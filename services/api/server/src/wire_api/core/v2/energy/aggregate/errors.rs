@@ -0,0 +1,37 @@
+use uuid::Uuid;
+
+use crate::wire_api::core::v2::errors::WireV2Error;
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV2Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Unsupported interval {0}")]
+    UnsupportedInterval(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::UnsupportedInterval(interval) => WireV1Error::bad_request(
+                "Invalid interval".to_string(),
+                vec![WireV1Detail {
+                    field: Some("interval".to_string()),
+                    code: "unsupported_interval".to_string(),
+                    message: format!("Unsupported interval {interval}"),
+                    suggestion: "Aggregate by `PT1H`, `P1D`, `P1W`, `P1M`, \
+                                 `P3M` or `P1Y`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Aggregation query failed";
+}
@@ -0,0 +1,159 @@
+use postgres_models::models::energy_readings::AggregatedReading;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::shared::json::{Decimal, DecimalFormat, Rounding};
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+use crate::wire_api::core::v2::types::IsoDuration;
+
+/// Totals as JSON numbers with the decimal places they are stored with.
+const TOTALS: DecimalFormat = DecimalFormat {
+    decimals: None,
+    rounding: Rounding::HalfUp,
+    numbers: true,
+};
+
+/// Periods of the aggregations an interval asks for, `None` for those not
+/// supported.
+pub fn aggregation_type(interval: IsoDuration) -> Option<AggregationType> {
+    match interval {
+        IsoDuration {
+            months: 0,
+            days: 0,
+            seconds: 3600,
+        } => Some(AggregationType::Hourly),
+        IsoDuration {
+            months: 0,
+            days: 1,
+            seconds: 0,
+        } => Some(AggregationType::DayOfMonth),
+        IsoDuration {
+            months: 0,
+            days: 7,
+            seconds: 0,
+        } => Some(AggregationType::Weekly),
+        IsoDuration {
+            months: 1,
+            days: 0,
+            seconds: 0,
+        } => Some(AggregationType::Monthly),
+        IsoDuration {
+            months: 3,
+            days: 0,
+            seconds: 0,
+        } => Some(AggregationType::Quarterly),
+        IsoDuration {
+            months: 12,
+            days: 0,
+            seconds: 0,
+        } => Some(AggregationType::Yearly),
+        _ => None,
+    }
+}
+
+/// Interval of the periods of `aggregation_type`.
+pub fn interval(aggregation_type: AggregationType) -> IsoDuration {
+    match aggregation_type {
+        AggregationType::Hourly => IsoDuration::seconds(3600),
+        AggregationType::DayOfMonth => IsoDuration::days(1),
+        AggregationType::Weekly => IsoDuration::days(7),
+        AggregationType::Monthly => IsoDuration::months(1),
+        AggregationType::Quarterly => IsoDuration::months(3),
+        AggregationType::Yearly => IsoDuration::months(12),
+    }
+}
+
+/// Request payload for aggregating energy readings
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateRequest {
+    /// Length of the periods, an ISO-8601 duration: `PT1H`, `P1D`, `P1W`
+    /// (weeks starting on Monday), `P1M`, `P3M` or `P1Y`
+    #[schema(value_type = String, example = "P1D")]
+    pub interval: IsoDuration,
+
+    /// Start of date range (inclusive, optional)
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// End of date range (exclusive, optional)
+    #[schema(example = "2025-04-01T00:00:00Z")]
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A single aggregated data point
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateDataPoint {
+    /// Start of the aggregation period
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub period: chrono::DateTime<chrono::Utc>,
+
+    /// Total energy in kWh for this period
+    #[schema(value_type = f64, example = 216000.0)]
+    pub total_kwh: Decimal,
+}
+
+impl From<AggregatedReading> for AggregateDataPoint {
+    fn from(reading: AggregatedReading) -> Self {
+        Self {
+            period: reading.period,
+            total_kwh: TOTALS.apply(reading.total_kwh),
+        }
+    }
+}
+
+/// Response for an aggregation query
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateResponse {
+    #[schema(value_type = String, example = "P1D")]
+    pub interval: IsoDuration,
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+    pub data: Vec<AggregateDataPoint>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn test_maps_intervals_to_aggregations() {
+        for aggregation in [
+            AggregationType::Hourly,
+            AggregationType::DayOfMonth,
+            AggregationType::Weekly,
+            AggregationType::Monthly,
+            AggregationType::Quarterly,
+            AggregationType::Yearly,
+        ] {
+            assert_eq!(
+                aggregation_type(interval(aggregation)),
+                Some(aggregation)
+            );
+        }
+        let parse = |s: &str| aggregation_type(s.parse().unwrap());
+        assert_eq!(parse("PT60M"), Some(AggregationType::Hourly));
+        assert_eq!(parse("PT15M"), None);
+        assert_eq!(parse("P1W"), Some(AggregationType::Weekly));
+        assert_eq!(parse("P12M"), Some(AggregationType::Yearly));
+        assert_eq!(parse("P2W"), None);
+    }
+
+    #[test]
+    fn test_writes_totals_as_numbers() {
+        let point = AggregateDataPoint::from(AggregatedReading {
+            period: Utc::now(),
+            total_kwh: "216000.1250".parse::<BigDecimal>().unwrap(),
+            reading_count: 1,
+            ..Default::default()
+        });
+        let json = serde_json::to_value(point).unwrap();
+        assert_eq!(json["totalKwh"], serde_json::json!(216000.125));
+    }
+}
@@ -0,0 +1,51 @@
+use uuid::Uuid;
+
+use crate::wire_api::core::v2::errors::WireV2Error;
+use crate::wire_api::handler_error::DomainError;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+pub type HandlerResult<T> = Result<T, WireV2Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid query parameters: {0}")]
+    InvalidQuery(String),
+
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+}
+
+impl crate::wire_api::error_recorder::IntoWireV1Error for Error {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            Error::InvalidQuery(message) => WireV1Error::bad_request(
+                "Invalid query parameters".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_query".to_string(),
+                    message,
+                    suggestion: "Send `limit` as a number".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            Error::InvalidCursor(message) => WireV1Error::bad_request(
+                "Invalid cursor".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "invalid_cursor".to_string(),
+                    message,
+                    suggestion: "Send the `nextCursor` or `prevCursor` of a \
+                                 page as `after` or `before`"
+                        .to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+        }
+    }
+}
+
+impl DomainError for Error {
+    const QUERY_FAILED: &'static str = "Failed to fetch query history";
+}
@@ -0,0 +1,151 @@
+use axum::Json;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{OriginalUri, Query};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+
+use crate::shared::pagination::{self, Page};
+use crate::wire_api::core::v2::errors::WireV2Error;
+use crate::wire_api::core::v2::types::Pagination;
+use crate::wire_api::handler_ctx::HandlerCtx;
+
+use super::errors::{self, HandlerResult};
+use super::models::{HistoryPage, HistoryParams, QueryHistoryEntry};
+
+const HANDLER_NAME: &str = "energy_history_v2";
+
+/// List the aggregation queries
+///
+/// Returns the caller's tenant's query history, most recent first, a page
+/// at a time. The response has the cursors of the neighbouring pages in
+/// `pagination`, and links to them in the `Link` header.
+#[utoipa::path(
+    get,
+    path = "/energy/history",
+    params(HistoryParams),
+    responses(
+        (status = 200, description = "A page of queries", body = HistoryPage),
+        (status = 400, description = "Invalid query parameters or cursor", body = WireV2Error, content_type = "application/problem+json"),
+        (status = 500, description = "Internal server error", body = WireV2Error, content_type = "application/problem+json"),
+    ),
+    tag = "energy",
+)]
+#[tracing::instrument(skip_all, name = "energy_history_v2")]
+pub async fn handler(
+    ctx: HandlerCtx,
+    OriginalUri(uri): OriginalUri,
+    params: Result<Query<HistoryParams>, QueryRejection>,
+) -> HandlerResult<Response> {
+    let recorder = ctx.recorder(HANDLER_NAME);
+
+    let Query(params) = params.map_err(|e| {
+        recorder
+            .record("invalid_query", errors::Error::InvalidQuery(e.body_text()))
+    })?;
+    let keyset =
+        pagination::keyset(params.after.as_deref(), params.before.as_deref())
+            .map_err(|e| {
+            recorder.record("invalid_cursor", errors::Error::InvalidCursor(e))
+        })?;
+
+    let limit = params.limit();
+    let entries = ctx
+        .state
+        .query_history
+        .page(&ctx.tenant.tenant_id, keyset, limit + 1)
+        .await
+        .map_err(|e| recorder.record_database::<errors::Error>(e))?;
+
+    let page = Page::new(entries, limit as usize, keyset, |entry| {
+        pagination::encode_cursor(entry.created_at, entry.id)
+    });
+    let link = page.link_header(&uri);
+    let (entries, pagination) = Pagination::of(page, limit);
+    let body = HistoryPage {
+        data: entries.into_iter().map(QueryHistoryEntry::from).collect(),
+        pagination,
+    };
+    let mut response = (StatusCode::OK, Json(body)).into_response();
+    if let Some(link) = link {
+        response.headers_mut().insert(header::LINK, link);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+    use postgres_models::models::query_history::NewQueryHistory;
+
+    use crate::repository::QueryHistoryRepository;
+    use crate::repository::memory::InMemoryQueryHistory;
+    use crate::wire_api::testing::{DEFAULT_TENANT, in_memory_server};
+
+    #[tokio::test]
+    async fn test_pages_through_the_history() {
+        let history = Arc::new(InMemoryQueryHistory::default());
+        let start = Utc::now() - Duration::hours(1);
+        for (i, aggregation_type) in ["hourly", "day_of_month", "monthly"]
+            .into_iter()
+            .enumerate()
+        {
+            let entry = NewQueryHistory {
+                aggregation_type: aggregation_type.to_string(),
+                date_from: None,
+                date_to: None,
+                api_key_id: None,
+                tenant_id: DEFAULT_TENANT.to_string(),
+                created_at: Some(start + Duration::minutes(i as i64)),
+            };
+            history.create(entry).await.unwrap();
+        }
+        let server = in_memory_server(Arc::default(), history).await;
+
+        let response = server
+            .get("/api/wire/v2/energy/history")
+            .add_query_param("limit", 2)
+            .await;
+        response.assert_status_ok();
+        assert!(
+            response
+                .header("link")
+                .to_str()
+                .unwrap()
+                .contains("rel=\"next\"")
+        );
+        let body = response.json::<serde_json::Value>();
+        let intervals = |body: &serde_json::Value| {
+            body["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["interval"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(intervals(&body), ["P1M", "P1D"]);
+        assert_eq!(body["pagination"]["limit"], 2);
+        assert!(body["pagination"]["prevCursor"].is_null());
+
+        let next = body["pagination"]["nextCursor"].as_str().unwrap();
+        let response = server
+            .get("/api/wire/v2/energy/history")
+            .add_query_param("limit", 2)
+            .add_query_param("after", next)
+            .await;
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(intervals(&body), ["PT1H"]);
+        assert!(body["pagination"]["nextCursor"].is_null());
+
+        let response = server
+            .get("/api/wire/v2/energy/history")
+            .add_query_param("after", "nope")
+            .await;
+        response.assert_status_bad_request();
+        assert_eq!(
+            response.json::<serde_json::Value>()["type"],
+            "urn:wire:problem:invalid_cursor"
+        );
+    }
+}
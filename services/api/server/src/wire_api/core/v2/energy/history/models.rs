@@ -0,0 +1,77 @@
+use postgres_models::models::query_history::QueryHistory;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::wire_api::core::v1::energy::aggregate::models::AggregationType;
+use crate::wire_api::core::v2::energy::aggregate::models::interval;
+use crate::wire_api::core::v2::types::Pagination;
+
+/// Queries returned when `limit` is omitted.
+pub const DEFAULT_LIMIT: i64 = 50;
+/// Most queries returned by one request.
+pub const MAX_LIMIT: i64 = 500;
+
+/// Query parameters of the query history
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryParams {
+    /// Most queries to return, 50 by default and at most 500
+    pub limit: Option<i64>,
+    /// Cursor of the query after which the page starts, `nextCursor` of
+    /// the previous page
+    pub after: Option<String>,
+    /// Cursor of the query before which the page ends, `prevCursor` of the
+    /// next page
+    pub before: Option<String>,
+}
+
+impl HistoryParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// A single query history entry
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryHistoryEntry {
+    pub id: uuid::Uuid,
+    /// Length of the periods aggregated, an ISO-8601 duration
+    #[schema(example = "P1D")]
+    pub interval: String,
+    #[schema(example = "2025-01-01T00:00:00Z")]
+    pub date_from: Option<chrono::DateTime<chrono::Utc>>,
+    #[schema(example = "2025-04-01T00:00:00Z")]
+    pub date_to: Option<chrono::DateTime<chrono::Utc>>,
+    /// API key that issued the query, when authentication is enabled
+    pub api_key_id: Option<uuid::Uuid>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<QueryHistory> for QueryHistoryEntry {
+    fn from(entry: QueryHistory) -> Self {
+        // Recorded by name, as v1 names aggregations
+        let interval = entry
+            .aggregation_type
+            .parse::<AggregationType>()
+            .map_or(entry.aggregation_type, |aggregation_type| {
+                interval(aggregation_type).to_string()
+            });
+        Self {
+            id: entry.id,
+            interval,
+            date_from: entry.date_from,
+            date_to: entry.date_to,
+            api_key_id: entry.api_key_id,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+/// A page of the query history, most recent first
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub data: Vec<QueryHistoryEntry>,
+    pub pagination: Pagination,
+}
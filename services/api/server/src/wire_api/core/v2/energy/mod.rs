@@ -0,0 +1,19 @@
+use axum::Router;
+use axum::middleware::from_extractor;
+
+use crate::auth::{RequirePermission, permission};
+
+pub mod aggregate;
+pub mod history;
+
+/// Energy routes of v2, queries over the readings.
+pub fn get_routes(state: crate::AppState) -> Router {
+    Router::new()
+        .route(
+            "/aggregate",
+            axum::routing::post(aggregate::handler::handler),
+        )
+        .route("/history", axum::routing::get(history::handler::handler))
+        .route_layer(from_extractor::<RequirePermission<permission::Read>>())
+        .with_state(state)
+}
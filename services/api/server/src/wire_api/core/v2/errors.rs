@@ -0,0 +1,328 @@
+//! Errors of the v2 API, as RFC 9457 problem details.
+//!
+//! Handlers record their errors with the [`ErrorRecorder`] of v1, whose
+//! [`WireV1Error`]s convert into [`WireV2Error`]s. The layers shared with
+//! v1, like authentication, quotas and payload extraction, still answer
+//! with v1 bodies; [`problem_responses`] rewrites those, so every error of
+//! `/api/wire/v2` is `application/problem+json`.
+//!
+//! [`ErrorRecorder`]: crate::wire_api::error_recorder::ErrorRecorder
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+/// Media type of error responses.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Prefix of the `type` of problems, followed by their code.
+const PROBLEM_TYPE_PREFIX: &str = "urn:wire:problem:";
+
+/// Largest error body [`problem_responses`] rewrites.
+const MAX_ERROR_BYTES: usize = 64 * 1024;
+
+/// An error response, an RFC 9457 problem
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = Problem)]
+pub struct WireV2Error {
+    #[serde(skip)]
+    pub(crate) status_code: StatusCode,
+    /// Sent as `Retry-After` when the request may be retried later
+    #[serde(skip)]
+    pub(crate) retry_after: Option<Duration>,
+    /// Kind of problem, `urn:wire:problem:` and the code of its first
+    /// error, or `about:blank`
+    #[serde(rename = "type")]
+    #[schema(example = "urn:wire:problem:invalid_interval")]
+    pub(crate) problem_type: String,
+    /// Summary of the kind of problem
+    #[schema(example = "Invalid interval")]
+    pub(crate) title: String,
+    /// HTTP status code of the response
+    #[schema(example = 400)]
+    pub(crate) status: u16,
+    /// What went wrong with this request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) detail: Option<String>,
+    pub(crate) request_id: String,
+    pub(crate) timestamp: String,
+    /// Each thing that went wrong, e.g. each invalid field
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) errors: Vec<WireV2Detail>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = ProblemError)]
+pub struct WireV2Detail {
+    /// Field of the request at fault
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) field: Option<String>,
+    #[schema(example = "invalid_interval")]
+    pub(crate) code: String,
+    pub(crate) message: String,
+    /// How to fix the request
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub(crate) suggestion: String,
+}
+
+impl WireV2Error {
+    /// Answer with `Retry-After: retry_after`.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    fn response(self) -> Response {
+        let retry_after = self.retry_after;
+        let mut response = (self.status_code, axum::Json(self)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON),
+        );
+        if let Some(retry_after) = retry_after {
+            headers.insert(header::RETRY_AFTER, retry_after.as_secs().into());
+        }
+        response
+    }
+}
+
+impl From<WireV1Error> for WireV2Error {
+    fn from(error: WireV1Error) -> Self {
+        let first = error.details.first();
+        let problem_type = first
+            .filter(|detail| !detail.code.is_empty())
+            .map_or("about:blank".to_string(), |detail| {
+                format!("{PROBLEM_TYPE_PREFIX}{}", detail.code)
+            });
+        let detail = first
+            .map(|detail| detail.message.clone())
+            .filter(|message| !message.is_empty());
+        Self {
+            status_code: error.status_code,
+            retry_after: None,
+            problem_type,
+            title: error.message,
+            status: error.status_code.as_u16(),
+            detail,
+            request_id: error.request_id,
+            timestamp: error.timestamp,
+            errors: error
+                .details
+                .into_iter()
+                .map(|detail| WireV2Detail {
+                    field: detail.field,
+                    code: detail.code,
+                    message: detail.message,
+                    suggestion: detail.suggestion,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl IntoResponse for WireV2Error {
+    fn into_response(self) -> Response {
+        if self.status_code.is_server_error() {
+            sentry::Hub::with_active(|hub| hub.capture_error(&self));
+        }
+        self.response()
+    }
+}
+
+impl std::fmt::Display for WireV2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}, {}",
+            self.status_code, self.title, self.request_id
+        )?;
+        if let Some(detail) = &self.detail {
+            write!(f, ", {detail}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for WireV2Error {}
+
+/// Body of a [`WireV1Error`], whose details leave out empty fields.
+#[derive(Deserialize)]
+struct V1Error {
+    message: String,
+    details: Vec<V1Detail>,
+    timestamp: String,
+    request_id: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct V1Detail {
+    field: Option<String>,
+    code: String,
+    message: String,
+    suggestion: String,
+}
+
+/// Body of the errors of the payload extractors.
+#[derive(Deserialize)]
+struct ExtractorError {
+    code: String,
+    message: String,
+}
+
+/// Rewrite the JSON error bodies of v1, of the layers and extractors v2
+/// shares with it, as problems; other responses pass through.
+pub async fn problem_responses(
+    RequestId(request_id): RequestId,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type == "application/json");
+    if !(status.is_client_error() || status.is_server_error()) || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_ERROR_BYTES).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let problem = if let Ok(error) = serde_json::from_slice::<V1Error>(&bytes) {
+        let details = error
+            .details
+            .into_iter()
+            .map(|detail| WireV1Detail {
+                field: detail.field,
+                code: detail.code,
+                message: detail.message,
+                suggestion: detail.suggestion,
+                documentation: String::new(),
+            })
+            .collect();
+        WireV2Error::from(WireV1Error {
+            status_code: status,
+            message: error.message,
+            details,
+            timestamp: error.timestamp,
+            request_id: error.request_id,
+        })
+    } else if let Ok(error) = serde_json::from_slice::<ExtractorError>(&bytes) {
+        WireV2Error {
+            status_code: status,
+            retry_after: None,
+            problem_type: format!(
+                "{PROBLEM_TYPE_PREFIX}{}",
+                error.code.to_lowercase()
+            ),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: Some(error.message),
+            request_id: request_id.to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            errors: Vec::new(),
+        }
+    } else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    // Keeps `Retry-After` and the like of the original response
+    parts.headers.remove(header::CONTENT_TYPE);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let mut response = problem.response();
+    response.headers_mut().extend(parts.headers);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::routing::get;
+    use axum_test::TestServer;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn rejected(request_id: &str) -> WireV1Error {
+        WireV1Error::too_many_requests(
+            "Quota exceeded".to_string(),
+            vec![WireV1Detail {
+                field: None,
+                code: "quota_exceeded".to_string(),
+                message: "Monthly quota of 10 requests used".to_string(),
+                suggestion: "Wait for the next month".to_string(),
+                documentation: String::new(),
+            }],
+            request_id.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_converts_v1_errors() {
+        let problem = WireV2Error::from(rejected("abc"));
+        assert_eq!(problem.status, 429);
+        assert_eq!(problem.problem_type, "urn:wire:problem:quota_exceeded");
+        assert_eq!(problem.title, "Quota exceeded");
+        assert_eq!(
+            problem.detail.as_deref(),
+            Some("Monthly quota of 10 requests used")
+        );
+        assert_eq!(problem.errors[0].suggestion, "Wait for the next month");
+
+        let bare = WireV2Error::from(WireV1Error::bad_request(
+            "Bad".to_string(),
+            Vec::new(),
+            "abc".to_string(),
+        ));
+        assert_eq!(bare.problem_type, "about:blank");
+        assert_eq!(bare.detail, None);
+    }
+
+    #[tokio::test]
+    async fn test_rewrites_v1_error_responses() {
+        let request_id = Uuid::new_v4().to_string();
+        let v1 = move || {
+            let request_id = request_id.clone();
+            async move {
+                let mut response = rejected(&request_id).into_response();
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, 30.into());
+                response
+            }
+        };
+        let router = Router::new()
+            .route("/v1", get(v1))
+            .route("/ok", get(|| async { axum::Json("fine") }))
+            .layer(axum::middleware::from_fn(problem_responses));
+        let server = TestServer::new(router).unwrap();
+
+        let response = server.get("/v1").await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.header(header::CONTENT_TYPE), PROBLEM_JSON);
+        assert_eq!(response.header(header::RETRY_AFTER), "30");
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["status"], 429);
+        assert_eq!(body["errors"][0]["code"], "quota_exceeded");
+        assert!(body.get("details").is_none());
+
+        let response = server.get("/ok").await;
+        response.assert_status_ok();
+        assert_eq!(response.json::<String>(), "fine");
+    }
+}
@@ -0,0 +1,51 @@
+//! Version 2 of the wire API, served under `/api/wire/v2` alongside v1.
+//!
+//! v2 gathers the changes v1 could not take without breaking its clients:
+//!
+//! - decimals are JSON numbers rather than strings;
+//! - lists are paginated, as `{data, pagination}` with the cursors of the
+//!   neighbouring pages, see [`types::Pagination`];
+//! - intervals are ISO-8601 durations like `P1D`, see [`types::IsoDuration`];
+//! - errors are RFC 9457 problem details, see [`errors`].
+//!
+//! Handlers share the models, repositories and middleware of v1; only
+//! their requests and responses differ.
+use axum::Router;
+use axum::middleware::{from_fn, from_fn_with_state};
+
+use crate::auth::csrf::RouteGroup;
+use crate::endpoints::EndpointGroup;
+
+pub(crate) mod energy;
+pub(crate) mod errors;
+pub(crate) mod openapi;
+pub(crate) mod types;
+
+/// Routes served under `/api/wire/v2` on the public listeners, all counted
+/// against the caller's quota.
+pub fn get_routes(state: crate::AppState) -> Router {
+    let mut router = Router::new();
+    if state.config.endpoints.is_enabled(EndpointGroup::Energy) {
+        router = router.nest("/energy", energy::get_routes(state.clone()));
+    }
+
+    router
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::middleware::enforce_quota,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::middleware::authenticate,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::deadline::enforce_deadline,
+        ))
+        .layer(from_fn_with_state(
+            (state, RouteGroup::Wire),
+            crate::auth::middleware::protect_csrf,
+        ))
+        .layer(from_fn(crate::read_fallback::flag_degraded))
+        .layer(from_fn(errors::problem_responses))
+}
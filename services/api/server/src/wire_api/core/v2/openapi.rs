@@ -0,0 +1,36 @@
+// The OpenApi derive macro generates code using Iterator::for_each,
+// which is disallowed by our clippy config, see `crate::openapi`.
+#![allow(clippy::disallowed_methods)]
+
+use utoipa::OpenApi;
+
+/// OpenAPI documentation of the Wire v2 API
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Energy Readings API",
+        version = "2.0.0",
+        description = "REST API for querying timeseries energy data, with numeric JSON values, ISO-8601 duration intervals, paginated lists and RFC 9457 problem details",
+        license(name = "MIT")
+    ),
+    servers(
+        (url = "/api/wire/v2", description = "API v2")
+    ),
+    paths(
+        super::energy::aggregate::handler::handler,
+        super::energy::history::handler::handler,
+    ),
+    components(schemas(super::errors::WireV2Error, super::errors::WireV2Detail)),
+    tags(
+        (name = "energy", description = "Energy readings aggregation and query history")
+    )
+)]
+pub struct WireV2ApiDoc;
+
+impl WireV2ApiDoc {
+    /// The spec as JSON for OpenAPI 3.0, see
+    /// [`crate::openapi::WireV1ApiDoc::to_3_0_json`]
+    pub fn openapi_json() -> serde_json::Value {
+        crate::openapi::WireV1ApiDoc::to_3_0_json(Self::openapi())
+    }
+}
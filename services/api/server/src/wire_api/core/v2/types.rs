@@ -0,0 +1,234 @@
+//! Types shared by the v2 handlers.
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::ToSchema;
+
+use crate::shared::pagination::Page;
+
+/// An ISO-8601 duration like `P1D` or `PT15M`, in calendar months, days
+/// and seconds; years are 12 months and weeks 7 days.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct IsoDuration {
+    pub months: u32,
+    pub days: u32,
+    pub seconds: u64,
+}
+
+impl IsoDuration {
+    pub const fn months(months: u32) -> Self {
+        Self {
+            months,
+            days: 0,
+            seconds: 0,
+        }
+    }
+
+    pub const fn days(days: u32) -> Self {
+        Self {
+            months: 0,
+            days,
+            seconds: 0,
+        }
+    }
+
+    pub const fn seconds(seconds: u64) -> Self {
+        Self {
+            months: 0,
+            days: 0,
+            seconds,
+        }
+    }
+}
+
+/// The numbers of `part`, each followed by one of `designators`, in that
+/// order and at most once each.
+fn components(part: &str, designators: &str) -> Option<Vec<(char, u64)>> {
+    let mut components = Vec::new();
+    let mut allowed = designators.chars();
+    let mut rest = part;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        if digits == 0 {
+            return None;
+        }
+        let designator = rest[digits..].chars().next()?;
+        // Designators come in order, each once
+        allowed.find(|&allowed| allowed == designator)?;
+        components.push((designator, rest[..digits].parse().ok()?));
+        rest = &rest[digits + designator.len_utf8()..];
+    }
+    Some(components)
+}
+
+impl FromStr for IsoDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("`{s}` is not an ISO-8601 duration like `P1D` or `PT15M`")
+        };
+        let rest = s.strip_prefix('P').ok_or_else(invalid)?;
+        let (date, time) = match rest.split_once('T') {
+            Some((_, "")) => return Err(invalid()),
+            Some((date, time)) => (date, time),
+            None => (rest, ""),
+        };
+        let date = components(date, "YMWD").ok_or_else(invalid)?;
+        let time = components(time, "HMS").ok_or_else(invalid)?;
+        if date.is_empty() && time.is_empty() {
+            return Err(invalid());
+        }
+
+        let too_long = || format!("`{s}` is too long a duration");
+        let mut duration = Self::default();
+        for (designator, n) in date {
+            let n = u32::try_from(n).map_err(|_| too_long())?;
+            let (field, n) = match designator {
+                'Y' => (&mut duration.months, n.checked_mul(12)),
+                'M' => (&mut duration.months, Some(n)),
+                'W' => (&mut duration.days, n.checked_mul(7)),
+                _ => (&mut duration.days, Some(n)),
+            };
+            *field =
+                n.and_then(|n| field.checked_add(n)).ok_or_else(too_long)?;
+        }
+        for (designator, n) in time {
+            let seconds = match designator {
+                'H' => n.checked_mul(3600),
+                'M' => n.checked_mul(60),
+                _ => Some(n),
+            };
+            duration.seconds = seconds
+                .and_then(|seconds| duration.seconds.checked_add(seconds))
+                .ok_or_else(too_long)?;
+        }
+        Ok(duration)
+    }
+}
+
+impl fmt::Display for IsoDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::default() {
+            return write!(f, "PT0S");
+        }
+        write!(f, "P")?;
+        let date = [
+            (self.months / 12, 'Y'),
+            (self.months % 12, 'M'),
+            (self.days, 'D'),
+        ];
+        for (n, designator) in date {
+            if n > 0 {
+                write!(f, "{n}{designator}")?;
+            }
+        }
+        if self.seconds > 0 {
+            write!(f, "T")?;
+            let time = [
+                (self.seconds / 3600, 'H'),
+                (self.seconds % 3600 / 60, 'M'),
+                (self.seconds % 60, 'S'),
+            ];
+            for (n, designator) in time {
+                if n > 0 {
+                    write!(f, "{n}{designator}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for IsoDuration {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IsoDuration {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Where a page of a list is, see [`crate::shared::pagination`]
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    /// Most items of a page
+    #[schema(example = 50)]
+    pub limit: i64,
+    /// Cursor of the next page, to send as `after`; `null` on the last page
+    pub next_cursor: Option<String>,
+    /// Cursor of the previous page, to send as `before`; `null` on the
+    /// first page
+    pub prev_cursor: Option<String>,
+}
+
+impl Pagination {
+    /// The items of `page` and where it is.
+    pub fn of<T>(page: Page<T>, limit: i64) -> (Vec<T>, Self) {
+        let pagination = Self {
+            limit,
+            next_cursor: page.next,
+            prev_cursor: page.prev,
+        };
+        (page.items, pagination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_iso_durations() {
+        let parse = |s: &str| s.parse::<IsoDuration>();
+        assert_eq!(parse("PT1H"), Ok(IsoDuration::seconds(3600)));
+        assert_eq!(parse("PT15M"), Ok(IsoDuration::seconds(900)));
+        assert_eq!(parse("P1D"), Ok(IsoDuration::days(1)));
+        assert_eq!(parse("P2W"), Ok(IsoDuration::days(14)));
+        assert_eq!(parse("P1M"), Ok(IsoDuration::months(1)));
+        assert_eq!(parse("P1Y"), Ok(IsoDuration::months(12)));
+        assert_eq!(
+            parse("P1Y2M3DT4H5M6S"),
+            Ok(IsoDuration {
+                months: 14,
+                days: 3,
+                seconds: 4 * 3600 + 5 * 60 + 6,
+            })
+        );
+
+        for invalid in [
+            "", "P", "PT", "1D", "P1H", "PT1D", "P1D1M", "P1DT", "PD", "P1.5D",
+        ] {
+            assert!(parse(invalid).is_err(), "{invalid}");
+        }
+        assert!(parse("P4294967296D").is_err());
+    }
+
+    #[test]
+    fn test_writes_iso_durations() {
+        for canonical in
+            ["PT1H", "PT15M", "P1D", "P1M", "P1Y", "P1Y2M3DT4H5M6S"]
+        {
+            let duration = canonical.parse::<IsoDuration>().unwrap();
+            assert_eq!(duration.to_string(), canonical);
+        }
+        assert_eq!(IsoDuration::seconds(90).to_string(), "PT1M30S");
+        assert_eq!(IsoDuration::default().to_string(), "PT0S");
+        assert_eq!(
+            serde_json::from_str::<IsoDuration>("\"PT60M\"").unwrap(),
+            IsoDuration::seconds(3600)
+        );
+    }
+}
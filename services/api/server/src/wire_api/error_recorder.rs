@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
+use postgres_models::connection::WithConnectionError;
 use telemetry::metrics::Telemetry;
 use uuid::Uuid;
 
 use crate::metrics::ServerMetrics;
+use crate::wire_api::handler_error::{DomainError, HandlerError};
 use crate::wire_api::wire_error_v1::WireV1Error;
 
 /// Trait for handler error types that can be converted to [`WireV1Error`].
@@ -39,4 +41,14 @@ impl<'a> ErrorRecorder<'a> {
         });
         e.into_wire_v1_error(self.request_id)
     }
+
+    /// Record a failure to get a connection or to query the database, as
+    /// an error of a handler with domain errors `E`.
+    pub fn record_database<E: DomainError>(
+        &self,
+        e: WithConnectionError<diesel::result::Error>,
+    ) -> WireV1Error {
+        let e = HandlerError::<E>::from(e);
+        self.record(e.code(), e)
+    }
 }
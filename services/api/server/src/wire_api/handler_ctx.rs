@@ -0,0 +1,81 @@
+//! What handlers know of the request they serve.
+//!
+//! [`HandlerCtx`] bundles the state, the request id, the caller and tenant
+//! attached by authentication and the request's span, so a handler takes
+//! one extractor for them, and data every handler may need is added here
+//! rather than to each signature. Every handler takes it, and helpers
+//! shared by handlers take `&HandlerCtx`:
+//!
+//! ```ignore
+//! pub async fn handler(
+//!     ctx: HandlerCtx,
+//!     ValidatedPayload(payload): ValidatedPayload<CostRequest>,
+//! ) -> HandlerResult<(StatusCode, Json<CostResponse>)> {
+//!     let recorder = ctx.recorder(HANDLER_NAME);
+//!     ...
+//! }
+//! ```
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::{Caller, TenantContext};
+use crate::flags::Flag;
+use crate::shared::extractors::request_id::RequestId;
+use crate::wire_api::error_recorder::ErrorRecorder;
+use crate::wire_api::wire_error_v1::WireV1Error;
+
+/// Context of the request a handler serves.
+#[derive(Clone)]
+pub struct HandlerCtx {
+    pub state: AppState,
+    pub request_id: Uuid,
+    /// The caller, absent when authentication is disabled
+    pub caller: Option<Caller>,
+    pub tenant: TenantContext,
+    /// Span of the request, to run work the handler spawns in
+    pub span: tracing::Span,
+}
+
+impl HandlerCtx {
+    /// Recorder of the errors of the handler `handler_name`.
+    pub fn recorder<'a>(&'a self, handler_name: &'a str) -> ErrorRecorder<'a> {
+        ErrorRecorder::new(
+            &self.state.telemetry,
+            handler_name,
+            &self.request_id,
+        )
+    }
+
+    /// When the request's database work is cancelled, see
+    /// [`crate::deadline`]; `None` without a deadline.
+    pub fn deadline(&self) -> Option<Instant> {
+        postgres_models::connection::deadline()
+    }
+
+    pub async fn flag_enabled(&self, flag: Flag) -> bool {
+        self.state.flag_enabled(flag).await
+    }
+}
+
+impl FromRequestParts<AppState> for HandlerCtx {
+    type Rejection = WireV1Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Ok(RequestId(request_id)) =
+            RequestId::from_request_parts(parts, state).await;
+        let tenant = TenantContext::from_request_parts(parts, state).await?;
+        Ok(Self {
+            state: state.clone(),
+            request_id,
+            caller: parts.extensions.get::<Caller>().cloned(),
+            tenant,
+            span: tracing::Span::current(),
+        })
+    }
+}
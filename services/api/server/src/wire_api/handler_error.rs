@@ -0,0 +1,156 @@
+//! Errors of handlers: the database failures every handler reports the same
+//! way, around the cases of its own domain.
+//!
+//! A handler declares only its domain cases, as an [`IntoWireV1Error`] enum
+//! that also implements [`DomainError`], and records database failures with
+//! [`ErrorRecorder::record_database`].
+//!
+//! [`ErrorRecorder::record_database`]: crate::wire_api::error_recorder::ErrorRecorder::record_database
+use postgres_models::connection::{WithConnectionError, is_pool_exhausted};
+use uuid::Uuid;
+
+use crate::wire_api::error_recorder::IntoWireV1Error;
+use crate::wire_api::wire_error_v1::{WireV1Detail, WireV1Error};
+
+/// The errors of a handler of its own.
+pub trait DomainError: IntoWireV1Error {
+    /// Message of the handler's failed queries, e.g. `"Export failed"`.
+    const QUERY_FAILED: &'static str;
+}
+
+/// An error of a handler with domain errors `E`.
+#[derive(Debug, thiserror::Error)]
+pub enum HandlerError<E> {
+    #[error("Database error: {0}")]
+    Database(#[from] diesel::result::Error),
+
+    #[error("Failed to get database connection: {0}")]
+    Pool(String),
+
+    #[error("No database connection available: {0}")]
+    PoolExhausted(String),
+
+    #[error(transparent)]
+    Domain(E),
+}
+
+impl<E: DomainError> From<E> for HandlerError<E> {
+    fn from(e: E) -> Self {
+        Self::Domain(e)
+    }
+}
+
+impl<E> From<WithConnectionError<diesel::result::Error>> for HandlerError<E> {
+    fn from(e: WithConnectionError<diesel::result::Error>) -> Self {
+        match e {
+            WithConnectionError::Pool(e) if is_pool_exhausted(&e) => {
+                Self::PoolExhausted(e.to_string())
+            }
+            WithConnectionError::Pool(e) => Self::Pool(e.to_string()),
+            WithConnectionError::Operation(e) => Self::Database(e),
+        }
+    }
+}
+
+impl<E> HandlerError<E> {
+    /// Code of the error's metrics; handlers record their domain errors
+    /// with codes of their own instead.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Database(_) => "database_error",
+            Self::Pool(_) => "pool_error",
+            Self::PoolExhausted(_) => "pool_exhausted",
+            Self::Domain(_) => "domain_error",
+        }
+    }
+}
+
+impl<E: DomainError> IntoWireV1Error for HandlerError<E> {
+    fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+        match self {
+            HandlerError::Database(e) => WireV1Error::internal_server_error(
+                E::QUERY_FAILED.to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "database_error".to_string(),
+                    message: format!("Database error: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            HandlerError::Pool(e) => WireV1Error::service_unavailable(
+                "Service temporarily unavailable".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "pool_error".to_string(),
+                    message: format!("Failed to get database connection: {e}"),
+                    suggestion: "Please try again later".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            HandlerError::PoolExhausted(e) => WireV1Error::service_unavailable(
+                "Service temporarily unavailable".to_string(),
+                vec![WireV1Detail {
+                    field: None,
+                    code: "pool_exhausted".to_string(),
+                    message: format!("No database connection available: {e}"),
+                    suggestion: "Retry with backoff".to_string(),
+                    documentation: String::new(),
+                }],
+                request_id.to_string(),
+            ),
+            HandlerError::Domain(e) => e.into_wire_v1_error(request_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    enum Error {
+        #[error("Plant not found")]
+        PlantNotFound,
+    }
+
+    impl IntoWireV1Error for Error {
+        fn into_wire_v1_error(self, request_id: &Uuid) -> WireV1Error {
+            WireV1Error::not_found(
+                self.to_string(),
+                Vec::new(),
+                request_id.to_string(),
+            )
+        }
+    }
+
+    impl DomainError for Error {
+        const QUERY_FAILED: &'static str = "Plant query failed";
+    }
+
+    #[test]
+    fn test_converts_standard_and_domain_errors() {
+        let request_id = Uuid::new_v4();
+        let error =
+            HandlerError::<Error>::from(diesel::result::Error::NotFound)
+                .into_wire_v1_error(&request_id);
+        assert_eq!(error.status_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.message, "Plant query failed");
+        assert_eq!(error.details[0].code, "database_error");
+
+        let error = HandlerError::<Error>::PoolExhausted("timed out".into())
+            .into_wire_v1_error(&request_id);
+        assert_eq!(error.status_code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.details[0].code, "pool_exhausted");
+        assert_eq!(error.details[0].suggestion, "Retry with backoff");
+
+        let error = HandlerError::from(Error::PlantNotFound)
+            .into_wire_v1_error(&request_id);
+        assert_eq!(error.status_code, StatusCode::NOT_FOUND);
+        assert_eq!(error.message, "Plant not found");
+    }
+}
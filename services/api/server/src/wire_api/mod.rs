@@ -1,5 +1,9 @@
 pub mod core;
 pub(crate) mod error_recorder;
 pub(crate) mod errors;
+pub(crate) mod handler_ctx;
+pub(crate) mod handler_error;
+#[cfg(test)]
+pub(crate) mod testing;
 pub(crate) mod wire_error;
 pub(crate) mod wire_error_v1;
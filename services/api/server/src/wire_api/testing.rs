@@ -0,0 +1,376 @@
+//! End-to-end tests of the HTTP API.
+//!
+//! [`TestApp::start`] creates a scratch database on the Postgres server of
+//! `TEST_DATABASE_URL`, migrates it, connects to the Redis of
+//! `TEST_REDIS_URL` (default `redis://127.0.0.1:6379`) and serves the wire
+//! v1, v2 and admin routes from an [`axum_test::TestServer`], with the
+//! settings of [`Config::from_vars`] and authentication disabled, so every
+//! wire request acts as an admin of the `default` tenant. Admin routes,
+//! unreachable without `ADMIN_API_TOKEN`, take [`ADMIN_TOKEN`]; send their
+//! requests with [`TestApp::admin`]. The database is dropped with the app.
+//!
+//! Without `TEST_DATABASE_URL`, `start` returns `None` and tests skip:
+//!
+//! ```ignore
+//! let Some(app) = TestApp::start().await else {
+//!     return;
+//! };
+//! app.seed_readings(DEFAULT_TENANT, None, &[(at, "12.5")]).await;
+//! let response = app.server.post("/api/wire/v1/energy/aggregate")
+//!     .json(&json!({"aggregationType": "monthly"}))
+//!     .await;
+//! ```
+//!
+//! `docker compose up postgresql-db redis` provides both services.
+//!
+//! Handlers that only read and record aggregations run without either on
+//! [`in_memory_server`], with the repositories of
+//! [`crate::repository::memory`], or on [`readings_server`] with the
+//! [`reading`]s given.
+use std::sync::Arc;
+
+use axum::http::Method;
+use axum_test::{TestRequest, TestServer};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use postgres_models::connection::{Pool, PoolSettings, establish_connection};
+use postgres_models::models::energy_readings::{
+    EnergyReading, NewEnergyReading, reading_source,
+};
+use postgres_models::models::plants::{NewPlant, Plant};
+use telemetry::metrics::Telemetry;
+use url::Url;
+use uuid::Uuid;
+
+use crate::admission::AdmissionControl;
+use crate::circuit_breaker::CircuitBreakers;
+use crate::config::Config;
+use crate::events::{DomainEvents, PlantEvents};
+use crate::flags::FeatureFlags;
+use crate::history_writer::HistoryWriter;
+use crate::logging::LogFilter;
+use crate::metrics::{self, ServerMetrics};
+use crate::read_fallback::ReadPools;
+use crate::repository::memory::{InMemoryEnergyReadings, InMemoryQueryHistory};
+use crate::repository::{
+    DieselEnergyReadings, DieselQueryHistory, EnergyReadingRepository,
+    QueryHistoryRepository,
+};
+use crate::shutdown::ShutdownCoordinator;
+use crate::snapshots::Snapshots;
+use crate::uploads::Uploads;
+use crate::{
+    AppState, get_internal_routes, get_wire_api_v1_routes,
+    get_wire_api_v2_routes,
+};
+
+/// Tenant of unauthenticated requests.
+pub const DEFAULT_TENANT: &str = "default";
+/// `ADMIN_API_TOKEN` of every test app, see [`TestApp::admin`].
+pub const ADMIN_TOKEN: &str = "test-admin-token";
+
+const DATABASE_URL_ENV: &str = "TEST_DATABASE_URL";
+const REDIS_URL_ENV: &str = "TEST_REDIS_URL";
+const DEFAULT_REDIS_URL: &str = "redis://127.0.0.1:6379";
+/// Redis of [`in_memory_server`]s, refusing connections
+const UNREACHABLE_REDIS_URL: &str = "redis://127.0.0.1:1";
+
+/// Settings of every test app; cached aggregates and responses would leak
+/// between tests sharing the Redis.
+const CONFIG_VARS: &[(&str, &str)] = &[
+    ("API_SERVICE_PORT", "0"),
+    (
+        "DATABASE_CREDENTIALS",
+        r#"{"username": "test", "password": "test"}"#,
+    ),
+    ("DATABASE_RW_ENDPOINT", "localhost"),
+    ("DATABASE_RO_ENDPOINT", "localhost"),
+    ("ENERGY_READINGS_XLS_FILE_PATH", "/dev/null"),
+    ("ADMIN_API_TOKEN", ADMIN_TOKEN),
+    // Queries are in the history as soon as the aggregation responds
+    ("QUERY_HISTORY_STRICT", "true"),
+    (
+        "FEATURE_FLAGS",
+        "aggregate_cache=false,response_cache=false",
+    ),
+];
+
+/// The API on a scratch database.
+pub struct TestApp {
+    pub server: TestServer,
+    pub state: AppState,
+    /// URL of the server's maintenance database, to drop the scratch one
+    admin_url: Url,
+    database: String,
+}
+
+impl TestApp {
+    /// Serve the API on a new database, `None` without
+    /// `TEST_DATABASE_URL`.
+    pub async fn start() -> Option<Self> {
+        Self::with_vars(&[]).await
+    }
+
+    /// Like [`TestApp::start`], with settings on top of the test ones,
+    /// e.g. `("FEATURE_FLAGS", "plant_delete_if_match")`.
+    pub async fn with_vars(vars: &[(&str, &str)]) -> Option<Self> {
+        let Ok(admin_url) = std::env::var(DATABASE_URL_ENV) else {
+            eprintln!("{DATABASE_URL_ENV} is not set, skipping");
+            return None;
+        };
+        let admin_url =
+            Url::parse(&admin_url).expect("TEST_DATABASE_URL to be a URL");
+        let redis_url = std::env::var(REDIS_URL_ENV)
+            .unwrap_or_else(|_| DEFAULT_REDIS_URL.to_string());
+
+        let database = format!("wire_test_{}", Uuid::new_v4().simple());
+        execute(&admin_url, &format!("CREATE DATABASE {database}")).await;
+        let mut url = admin_url.clone();
+        url.set_path(&database);
+        let pool = establish_connection(
+            url.to_string(),
+            &PoolSettings::default(),
+            None,
+        )
+        .await
+        .expect("test database to accept connections");
+        migrate(&pool).await;
+
+        let redis_url = Url::parse(&redis_url).expect("TEST_REDIS_URL");
+        let cache_pool = redis_cache::connection::establish_connection(
+            redis_url.to_string(),
+        )
+        .await
+        .expect("test Redis to accept connections");
+
+        let vars =
+            [CONFIG_VARS, vars, &[("REDIS_URL", redis_url.as_str())]].concat();
+        let config =
+            Config::from_vars(&vars).expect("test configuration to be valid");
+
+        let state = state(
+            config,
+            pool.clone(),
+            cache_pool,
+            Arc::new(DieselEnergyReadings::new(ReadPools::new(pool.clone()))),
+            Arc::new(DieselQueryHistory::new(
+                pool.clone(),
+                ReadPools::new(pool),
+            )),
+        )
+        .await;
+        let server = serve(&state);
+
+        Some(Self {
+            server,
+            state,
+            admin_url,
+            database,
+        })
+    }
+
+    /// A request to the admin route at `path`, with [`ADMIN_TOKEN`].
+    pub fn admin(&self, method: Method, path: &str) -> TestRequest {
+        self.server
+            .method(method, path)
+            .authorization_bearer(ADMIN_TOKEN)
+    }
+
+    /// Store readings of `(time, kWh)` for `tenant`, attributed to `plant`.
+    pub async fn seed_readings(
+        &self,
+        tenant: &str,
+        plant: Option<Uuid>,
+        readings: &[(DateTime<Utc>, &str)],
+    ) {
+        let readings = readings
+            .iter()
+            .map(|(reading_time, kwh)| NewEnergyReading {
+                tenant_id: tenant.to_string(),
+                plant_id: plant,
+                ..reading(*reading_time, kwh)
+            })
+            .collect();
+        let mut conn = self.state.pool.get().await.expect("a connection");
+        EnergyReading::bulk_insert(readings, &mut conn)
+            .await
+            .expect("readings to be stored");
+    }
+
+    /// Register an active solar plant of `capacity_mw` for `tenant`.
+    pub async fn seed_plant(
+        &self,
+        tenant: &str,
+        name: &str,
+        capacity_mw: &str,
+    ) -> Plant {
+        let plant = NewPlant {
+            tenant_id: tenant.to_string(),
+            name: name.to_string(),
+            energy_type: "solar".to_string(),
+            capacity_mw: capacity_mw
+                .parse::<BigDecimal>()
+                .expect("capacity to be a decimal"),
+            address: None,
+            status: "active".to_string(),
+            latitude: None,
+            longitude: None,
+        };
+        let mut conn = self.state.pool.get().await.expect("a connection");
+        Plant::create(plant, &mut conn)
+            .await
+            .expect("plant to be stored")
+    }
+}
+
+/// A file reading of `kwh` at `at` for [`DEFAULT_TENANT`], without a
+/// plant.
+pub fn reading(at: DateTime<Utc>, kwh: &str) -> NewEnergyReading {
+    NewEnergyReading {
+        reading_time: at,
+        quantity_kwh: kwh.parse().expect("reading to be a decimal"),
+        tenant_id: DEFAULT_TENANT.to_string(),
+        plant_id: None,
+        quality_code: None,
+        source: reading_source::FILE.to_string(),
+    }
+}
+
+/// [`in_memory_server`] on `readings`, with an empty query history.
+pub async fn readings_server(readings: Vec<NewEnergyReading>) -> TestServer {
+    in_memory_server(
+        Arc::new(InMemoryEnergyReadings::new(readings)),
+        Arc::new(InMemoryQueryHistory::default()),
+    )
+    .await
+}
+
+/// The API on in-memory repositories, without Postgres or Redis, for
+/// handlers that only use [`AppState::readings`] and
+/// [`AppState::query_history`]. Anything else reaching the database fails;
+/// the cache and feature flag overrides are unavailable.
+pub async fn in_memory_server(
+    readings: Arc<InMemoryEnergyReadings>,
+    query_history: Arc<InMemoryQueryHistory>,
+) -> TestServer {
+    let vars = [CONFIG_VARS, &[("REDIS_URL", UNREACHABLE_REDIS_URL)]].concat();
+    let config =
+        Config::from_vars(&vars).expect("test configuration to be valid");
+    // Neither pool connects until used
+    let pool = Pool::builder().build_unchecked(
+        AsyncDieselConnectionManager::new("postgres://127.0.0.1:1/unused"),
+    );
+    let cache_pool = deadpool_redis::Config::from_url(UNREACHABLE_REDIS_URL)
+        .create_pool(Some(deadpool_redis::Runtime::Tokio1))
+        .expect("Redis pool to be created");
+
+    let state =
+        state(config, pool, Arc::new(cache_pool), readings, query_history)
+            .await;
+    serve(&state)
+}
+
+async fn state(
+    config: Config,
+    pool: Pool,
+    cache_pool: redis_cache::connection::Pool,
+    readings: Arc<dyn EnergyReadingRepository>,
+    query_history: Arc<dyn QueryHistoryRepository>,
+) -> AppState {
+    let metrics = ServerMetrics::new_with_random_prefix()
+        .expect("test metrics to be created");
+    let telemetry = Telemetry::new(Some(metrics))
+        .await
+        .expect("test telemetry to be created");
+    let (_, log_filter) =
+        LogFilter::new(tracing_subscriber::EnvFilter::new("info"));
+
+    let shutdown =
+        Arc::new(ShutdownCoordinator::new(pool.clone(), cache_pool.clone()));
+    let (history_writer, history_flusher) = HistoryWriter::new(
+        query_history.clone(),
+        telemetry.clone(),
+        config.query_history_writer.clone(),
+    );
+    tokio::spawn(history_flusher.run(shutdown.clone()));
+
+    let breakers = Arc::new(CircuitBreakers::new(
+        config.circuit_breaker.clone(),
+        Some(metrics::breaker_listener(telemetry.clone())),
+    ));
+
+    let uploads = Arc::new(Uploads::new(config.uploads.clone()));
+    let snapshots = Arc::new(Snapshots::new(config.snapshots.clone()));
+
+    AppState {
+        telemetry,
+        pool: pool.clone(),
+        read_only_pool: pool.clone(),
+        reads: ReadPools::new(pool.clone()),
+        cache_pool: cache_pool.clone(),
+        flags: Arc::new(FeatureFlags::new(config.feature_flags.clone())),
+        admission: Arc::new(AdmissionControl::new(config.admission.clone())),
+        config: Arc::new(config),
+        shutdown,
+        jwt: None,
+        log_filter,
+        plant_events: PlantEvents::default(),
+        domain_events: DomainEvents::default(),
+        readings,
+        query_history,
+        history_writer,
+        aggregations: Arc::default(),
+        breakers,
+        uploads,
+        snapshots,
+    }
+}
+
+fn serve(state: &AppState) -> TestServer {
+    let router = axum::Router::new()
+        .nest(
+            "/api/wire/v1",
+            get_internal_routes(state.clone())
+                .merge(get_wire_api_v1_routes(state.clone())),
+        )
+        .nest("/api/wire/v2", get_wire_api_v2_routes(state.clone()));
+    TestServer::new(router).expect("test server to start")
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        // The test's runtime may be gone or busy; drop from a fresh one
+        let admin_url = self.admin_url.clone();
+        let statement =
+            format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", self.database);
+        let dropped = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("runtime to build")
+                .block_on(execute(&admin_url, &statement));
+        })
+        .join();
+        if dropped.is_err() {
+            eprintln!("Failed to drop test database {}", self.database);
+        }
+    }
+}
+
+async fn execute(url: &Url, statement: &str) {
+    let mut conn = AsyncPgConnection::establish(url.as_str())
+        .await
+        .expect("TEST_DATABASE_URL to accept connections");
+    diesel::sql_query(statement)
+        .execute(&mut conn)
+        .await
+        .unwrap_or_else(|e| panic!("`{statement}` failed: {e}"));
+}
+
+async fn migrate(pool: &Pool) {
+    crate::cli::migrate::apply(pool)
+        .await
+        .expect("migrations to run");
+}
@@ -54,6 +54,62 @@ impl WireV1Error {
         }
     }
 
+    pub fn conflict(
+        message: String,
+        details: Vec<WireV1Detail>,
+        request_id: String,
+    ) -> Self {
+        Self {
+            status_code: axum::http::StatusCode::CONFLICT,
+            message,
+            details,
+            timestamp: Utc::now().to_rfc3339(),
+            request_id,
+        }
+    }
+
+    pub fn payload_too_large(
+        message: String,
+        details: Vec<WireV1Detail>,
+        request_id: String,
+    ) -> Self {
+        Self {
+            status_code: axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+            message,
+            details,
+            timestamp: Utc::now().to_rfc3339(),
+            request_id,
+        }
+    }
+
+    pub fn precondition_required(
+        message: String,
+        details: Vec<WireV1Detail>,
+        request_id: String,
+    ) -> Self {
+        Self {
+            status_code: axum::http::StatusCode::PRECONDITION_REQUIRED,
+            message,
+            details,
+            timestamp: Utc::now().to_rfc3339(),
+            request_id,
+        }
+    }
+
+    pub fn unsupported_media_type(
+        message: String,
+        details: Vec<WireV1Detail>,
+        request_id: String,
+    ) -> Self {
+        Self {
+            status_code: axum::http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            message,
+            details,
+            timestamp: Utc::now().to_rfc3339(),
+            request_id,
+        }
+    }
+
     pub fn internal_server_error(
         message: String,
         details: Vec<WireV1Detail>,
@@ -137,6 +193,20 @@ impl WireV1Error {
             request_id,
         }
     }
+
+    pub fn gateway_timeout(
+        message: String,
+        details: Vec<WireV1Detail>,
+        request_id: String,
+    ) -> Self {
+        Self {
+            status_code: axum::http::StatusCode::GATEWAY_TIMEOUT,
+            message,
+            details,
+            timestamp: Utc::now().to_rfc3339(),
+            request_id,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]